@@ -0,0 +1 @@
+ALTER TABLE request_logs ADD COLUMN audio_duration_ms INTEGER;
@@ -0,0 +1,2 @@
+-- Remove context_trimming column
+ALTER TABLE credentials DROP COLUMN IF EXISTS context_trimming;
@@ -0,0 +1,9 @@
+-- Add optional conversation trimming strategy per credential.
+-- NULL keeps the default behavior: requests over the model's input limit are rejected.
+--
+-- Examples:
+--   {"strategy": "drop_oldest"}
+--   {"strategy": "keep_last", "messages": 20}
+--   {"strategy": "middle_out"}
+
+ALTER TABLE credentials ADD COLUMN context_trimming JSONB;
@@ -0,0 +1,2 @@
+-- Restore the api_key length limit (fails while encrypted values longer than 500 characters are stored)
+ALTER TABLE providers ALTER COLUMN api_key TYPE VARCHAR(500);
@@ -0,0 +1,8 @@
+-- Widen api_key so it can hold encrypted values.
+-- Encrypted keys have the form enc:v1:<key id>:<wrapped data key>:<sealed api key>
+-- and are written by the proxy itself when PROVIDER_KEY_ENCRYPTION_KEYS or
+-- PROVIDER_KEY_ENCRYPTION_KEY_FILE is set. Plaintext rows already stored are
+-- encrypted at startup only with PROVIDER_KEY_ENCRYPT_EXISTING=true, or by the
+-- re-encrypt admin endpoint.
+
+ALTER TABLE providers ALTER COLUMN api_key TYPE TEXT;
@@ -0,0 +1,33 @@
+-- Role-based admin tokens. ADMIN_KEY remains a bootstrap superuser; these
+-- tokens are stored hashed and carry one of three roles:
+--   viewer   - read-only admin endpoints
+--   operator - viewer + reloads, health checks and log cleanup
+--   admin    - full access, including providers, credentials and tokens
+CREATE TABLE admin_tokens (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    token_hash VARCHAR(255) NOT NULL UNIQUE,
+    token_preview VARCHAR(50) NOT NULL,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('viewer', 'operator', 'admin')),
+    expires_at TIMESTAMPTZ,
+    is_enabled BOOLEAN NOT NULL DEFAULT true,
+    created_by VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Audit trail of mutating admin API calls
+CREATE TABLE admin_audit_logs (
+    id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    actor VARCHAR(255) NOT NULL,
+    actor_role VARCHAR(20) NOT NULL,
+    action VARCHAR(50) NOT NULL,
+    resource_type VARCHAR(50) NOT NULL,
+    resource_id VARCHAR(255),
+    before JSONB,
+    after JSONB
+);
+
+CREATE INDEX idx_admin_audit_logs_created_at ON admin_audit_logs(created_at DESC);
+CREATE INDEX idx_admin_audit_logs_actor ON admin_audit_logs(actor);
+CREATE INDEX idx_admin_audit_logs_resource ON admin_audit_logs(resource_type, resource_id);
@@ -0,0 +1,10 @@
+CREATE OR REPLACE FUNCTION increment_config_version()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE config_version SET version = version + 1, updated_at = NOW() WHERE id = 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP FUNCTION IF EXISTS record_config_version(TEXT);
+DROP TABLE IF EXISTS config_history;
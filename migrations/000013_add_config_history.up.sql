@@ -0,0 +1,59 @@
+-- Configuration history: a snapshot of all providers and credentials for
+-- every config_version, so a bad change can be diffed and rolled back.
+--
+-- Provider API keys are only kept when encrypted at rest ("enc:v1:..."),
+-- plaintext keys are stored as NULL. Credentials only store key hashes.
+CREATE TABLE config_history (
+    version BIGINT PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    change_source VARCHAR(255) NOT NULL,
+    providers JSONB NOT NULL,
+    credentials JSONB NOT NULL
+);
+
+CREATE INDEX idx_config_history_created_at ON config_history(created_at DESC);
+
+-- Bump config_version and snapshot the resulting configuration
+CREATE OR REPLACE FUNCTION record_config_version(source TEXT)
+RETURNS BIGINT AS $$
+DECLARE
+    new_version BIGINT;
+BEGIN
+    UPDATE config_version SET version = version + 1, updated_at = NOW() WHERE id = 1
+    RETURNING version INTO new_version;
+
+    INSERT INTO config_history (version, change_source, providers, credentials)
+    SELECT
+        new_version,
+        source,
+        COALESCE((
+            SELECT jsonb_agg(
+                to_jsonb(p) || jsonb_build_object(
+                    'api_key', CASE WHEN p.api_key LIKE 'enc:v1:%' THEN p.api_key END
+                ) ORDER BY p.id)
+            FROM providers p
+        ), '[]'::jsonb),
+        COALESCE((
+            SELECT jsonb_agg(to_jsonb(c) ORDER BY c.id) FROM credentials c
+        ), '[]'::jsonb);
+
+    RETURN new_version;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Statement triggers record a version per change. A rollback sets
+-- llm_proxy.defer_config_version for its transaction and records a single
+-- version once all rows are restored.
+CREATE OR REPLACE FUNCTION increment_config_version()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF current_setting('llm_proxy.defer_config_version', true) = 'on' THEN
+        RETURN NEW;
+    END IF;
+    PERFORM record_config_version(TG_TABLE_NAME);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Baseline snapshot of the current configuration
+SELECT record_config_version('baseline');
@@ -0,0 +1,11 @@
+DROP TRIGGER IF EXISTS trg_credentials_version ON credentials;
+CREATE TRIGGER trg_credentials_version
+    AFTER INSERT OR UPDATE OR DELETE ON credentials
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
+
+DROP INDEX IF EXISTS idx_credentials_previous_key_hash;
+ALTER TABLE credentials
+    DROP COLUMN IF EXISTS previous_key_expires_at,
+    DROP COLUMN IF EXISTS previous_key_hash,
+    DROP COLUMN IF EXISTS last_used_at,
+    DROP COLUMN IF EXISTS expires_at;
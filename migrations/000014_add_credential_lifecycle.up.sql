@@ -0,0 +1,22 @@
+-- Credential lifecycle: expiry, key rotation with a grace period, and
+-- last-used tracking.
+--
+-- After a rotation the old key hash stays valid until previous_key_expires_at.
+ALTER TABLE credentials
+    ADD COLUMN expires_at TIMESTAMPTZ,
+    ADD COLUMN last_used_at TIMESTAMPTZ,
+    ADD COLUMN previous_key_hash VARCHAR(255),
+    ADD COLUMN previous_key_expires_at TIMESTAMPTZ;
+
+CREATE INDEX idx_credentials_previous_key_hash ON credentials(previous_key_hash);
+
+-- last_used_at is written in batches by every proxy instance; those updates
+-- must not bump config_version or record configuration history. New
+-- configuration columns have to be added to this list.
+DROP TRIGGER IF EXISTS trg_credentials_version ON credentials;
+CREATE TRIGGER trg_credentials_version
+    AFTER INSERT OR DELETE OR UPDATE OF
+        credential_key, name, allowed_models, rate_limit, is_enabled, context_trimming,
+        expires_at, previous_key_hash, previous_key_expires_at
+    ON credentials
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
@@ -0,0 +1,12 @@
+-- Per-credential network restrictions: requests authenticated with a
+-- credential must come from one of its CIDR ranges (empty = any address).
+ALTER TABLE credentials
+    ADD COLUMN allowed_cidrs JSONB NOT NULL DEFAULT '[]';
+
+DROP TRIGGER IF EXISTS trg_credentials_version ON credentials;
+CREATE TRIGGER trg_credentials_version
+    AFTER INSERT OR DELETE OR UPDATE OF
+        credential_key, name, allowed_models, rate_limit, is_enabled, context_trimming,
+        expires_at, previous_key_hash, previous_key_expires_at, allowed_cidrs
+    ON credentials
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
@@ -0,0 +1,10 @@
+DROP TRIGGER IF EXISTS trg_credentials_version ON credentials;
+CREATE TRIGGER trg_credentials_version
+    AFTER INSERT OR DELETE OR UPDATE OF
+        credential_key, name, allowed_models, rate_limit, is_enabled, context_trimming,
+        expires_at, previous_key_hash, previous_key_expires_at, allowed_cidrs
+    ON credentials
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
+
+ALTER TABLE request_logs DROP COLUMN IF EXISTS pii_redactions;
+ALTER TABLE credentials DROP COLUMN IF EXISTS pii_redaction;
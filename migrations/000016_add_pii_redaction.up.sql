@@ -0,0 +1,12 @@
+-- Per-credential PII redaction settings (null = use the provider's setting),
+-- and per-request counts of redacted values by placeholder label.
+ALTER TABLE credentials ADD COLUMN pii_redaction JSONB;
+ALTER TABLE request_logs ADD COLUMN pii_redactions JSONB;
+
+DROP TRIGGER IF EXISTS trg_credentials_version ON credentials;
+CREATE TRIGGER trg_credentials_version
+    AFTER INSERT OR DELETE OR UPDATE OF
+        credential_key, name, allowed_models, rate_limit, is_enabled, context_trimming,
+        expires_at, previous_key_hash, previous_key_expires_at, allowed_cidrs, pii_redaction
+    ON credentials
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
@@ -0,0 +1,38 @@
+DROP TRIGGER IF EXISTS trg_credentials_version ON credentials;
+CREATE TRIGGER trg_credentials_version
+    AFTER INSERT OR DELETE OR UPDATE OF
+        credential_key, name, allowed_models, rate_limit, is_enabled, context_trimming,
+        expires_at, previous_key_hash, previous_key_expires_at, allowed_cidrs, pii_redaction
+    ON credentials
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
+
+CREATE OR REPLACE FUNCTION record_config_version(source TEXT)
+RETURNS BIGINT AS $$
+DECLARE
+    new_version BIGINT;
+BEGIN
+    UPDATE config_version SET version = version + 1, updated_at = NOW() WHERE id = 1
+    RETURNING version INTO new_version;
+
+    INSERT INTO config_history (version, change_source, providers, credentials)
+    SELECT
+        new_version,
+        source,
+        COALESCE((
+            SELECT jsonb_agg(
+                to_jsonb(p) || jsonb_build_object(
+                    'api_key', CASE WHEN p.api_key LIKE 'enc:v1:%' THEN p.api_key END
+                ) ORDER BY p.id)
+            FROM providers p
+        ), '[]'::jsonb),
+        COALESCE((
+            SELECT jsonb_agg(to_jsonb(c) ORDER BY c.id) FROM credentials c
+        ), '[]'::jsonb);
+
+    RETURN new_version;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE config_history DROP COLUMN IF EXISTS guardrail_rules;
+ALTER TABLE credentials DROP COLUMN IF EXISTS guardrails;
+DROP TABLE IF EXISTS guardrail_rules;
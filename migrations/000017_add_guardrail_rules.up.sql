@@ -0,0 +1,66 @@
+-- Content guardrail rules, attached to credentials by name. Matches in
+-- prompts and completions are blocked, redacted or only logged, and every
+-- violation is recorded in error_logs as 'guardrail_violation'.
+CREATE TABLE guardrail_rules (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('secrets', 'deny_phrases', 'regex')),
+    patterns JSONB NOT NULL DEFAULT '[]',
+    action VARCHAR(20) NOT NULL DEFAULT 'block' CHECK (action IN ('block', 'redact', 'log')),
+    direction VARCHAR(20) NOT NULL DEFAULT 'both' CHECK (direction IN ('input', 'output', 'both')),
+    is_enabled BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER trg_guardrail_rules_version
+    AFTER INSERT OR UPDATE OR DELETE ON guardrail_rules
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
+
+-- Rules are part of the configuration history, so diffs show rule changes and
+-- a rollback restores the rules its credentials refer to. Versions recorded
+-- before this migration had no rules.
+ALTER TABLE config_history
+    ADD COLUMN guardrail_rules JSONB NOT NULL DEFAULT '[]';
+
+CREATE OR REPLACE FUNCTION record_config_version(source TEXT)
+RETURNS BIGINT AS $$
+DECLARE
+    new_version BIGINT;
+BEGIN
+    UPDATE config_version SET version = version + 1, updated_at = NOW() WHERE id = 1
+    RETURNING version INTO new_version;
+
+    INSERT INTO config_history (version, change_source, providers, credentials, guardrail_rules)
+    SELECT
+        new_version,
+        source,
+        COALESCE((
+            SELECT jsonb_agg(
+                to_jsonb(p) || jsonb_build_object(
+                    'api_key', CASE WHEN p.api_key LIKE 'enc:v1:%' THEN p.api_key END
+                ) ORDER BY p.id)
+            FROM providers p
+        ), '[]'::jsonb),
+        COALESCE((
+            SELECT jsonb_agg(to_jsonb(c) ORDER BY c.id) FROM credentials c
+        ), '[]'::jsonb),
+        COALESCE((
+            SELECT jsonb_agg(to_jsonb(g) ORDER BY g.id) FROM guardrail_rules g
+        ), '[]'::jsonb);
+
+    RETURN new_version;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE credentials
+    ADD COLUMN guardrails JSONB NOT NULL DEFAULT '[]';
+
+DROP TRIGGER IF EXISTS trg_credentials_version ON credentials;
+CREATE TRIGGER trg_credentials_version
+    AFTER INSERT OR DELETE OR UPDATE OF
+        credential_key, name, allowed_models, rate_limit, is_enabled, context_trimming,
+        expires_at, previous_key_hash, previous_key_expires_at, allowed_cidrs, pii_redaction,
+        guardrails
+    ON credentials
+    FOR EACH STATEMENT EXECUTE FUNCTION increment_config_version();
@@ -52,7 +52,7 @@ class ProviderModel(Base):
     )
     provider_type: Mapped[str] = mapped_column(String(50), nullable=False)
     api_base: Mapped[str] = mapped_column(String(500), nullable=False)
-    api_key: Mapped[str] = mapped_column(String(500), nullable=False)
+    api_key: Mapped[str] = mapped_column(Text, nullable=False)
     model_mapping: Mapped[dict] = mapped_column(JSONB, nullable=False, default={})
     weight: Mapped[int] = mapped_column(Integer, nullable=False, default=1)
     provider_params: Mapped[dict] = mapped_column(JSONB, nullable=False, default={})
@@ -88,6 +88,7 @@ class CredentialModel(Base):
     name: Mapped[str] = mapped_column(String(255), nullable=False)
     allowed_models: Mapped[list] = mapped_column(JSONB, nullable=False, default=[])
     rate_limit: Mapped[Optional[int]] = mapped_column(Integer, nullable=True)
+    context_trimming: Mapped[Optional[dict]] = mapped_column(JSONB, nullable=True)
     is_enabled: Mapped[bool] = mapped_column(Boolean, nullable=False, default=True)
     created_at: Mapped[datetime] = mapped_column(
         DateTime(timezone=True), nullable=False, server_default=func.now()
@@ -164,6 +165,8 @@ class RequestLogModel(Base):
     total_tokens: Mapped[int] = mapped_column(Integer, nullable=False, default=0)
     total_duration_ms: Mapped[Optional[int]] = mapped_column(Integer, nullable=True)
     ttft_ms: Mapped[Optional[int]] = mapped_column(Integer, nullable=True)
+    audio_duration_ms: Mapped[Optional[int]] = mapped_column(Integer, nullable=True)
+    pii_redactions: Mapped[Optional[dict]] = mapped_column(JSONB, nullable=True)
     error_category: Mapped[Optional[str]] = mapped_column(String(50), nullable=True)
     error_message: Mapped[Optional[str]] = mapped_column(Text, nullable=True)
     request_headers: Mapped[Optional[str]] = mapped_column(Text, nullable=True)
@@ -371,6 +374,28 @@ class Database:
             return row[0] if row else 0
 
 
+# Prefix of provider API keys encrypted at rest by the Rust server
+ENCRYPTED_API_KEY_PREFIX = "enc:v1:"
+
+
+def usable_providers(providers: list) -> list:
+    """Drop providers whose API key is encrypted at rest.
+
+    This server cannot decrypt keys encrypted by the Rust server and must not
+    send the ciphertext upstream as a vendor key.
+    """
+    usable = []
+    for provider in providers:
+        if (provider.api_key or "").startswith(ENCRYPTED_API_KEY_PREFIX):
+            logger.error(
+                f"Skipping provider '{provider.provider_key}': its API key is "
+                "encrypted at rest, which this server does not support"
+            )
+            continue
+        usable.append(provider)
+    return usable
+
+
 @dataclass
 class InitResult:
     """Initialization result"""
@@ -510,7 +535,7 @@ class DynamicConfig:
             provider_result = await session.execute(
                 select(ProviderModel).where(ProviderModel.is_enabled.is_(True))
             )
-            providers = list(provider_result.scalars().all())
+            providers = usable_providers(list(provider_result.scalars().all()))
 
             credential_result = await session.execute(
                 select(CredentialModel).where(CredentialModel.is_enabled.is_(True))
@@ -541,7 +566,7 @@ async def load_config_from_db(db: Database) -> InitResult:
         provider_result = await session.execute(
             select(ProviderModel).where(ProviderModel.is_enabled.is_(True))
         )
-        providers = list(provider_result.scalars().all())
+        providers = usable_providers(list(provider_result.scalars().all()))
 
         credential_result = await session.execute(
             select(CredentialModel).where(CredentialModel.is_enabled.is_(True))
@@ -9,6 +9,8 @@ from app.core.database import (
     hash_key,
     create_key_preview,
     InitResult,
+    ProviderModel,
+    usable_providers,
 )
 
 
@@ -87,6 +89,17 @@ class TestCreateKeyPreview:
         assert result == "***"
 
 
+class TestUsableProviders:
+    """Tests for usable_providers function"""
+
+    def test_skips_providers_with_encrypted_keys(self):
+        plain = ProviderModel(provider_key="plain", api_key="sk-plain")
+        encrypted = ProviderModel(
+            provider_key="encrypted", api_key="enc:v1:k1:d3JhcHBlZA==:c2VhbGVk"
+        )
+        assert usable_providers([plain, encrypted]) == [plain]
+
+
 class TestInitResult:
     """Tests for InitResult dataclass"""
 
@@ -1,26 +1,42 @@
 //! Admin API handlers for dynamic configuration management.
 //!
 //! Provides RESTful endpoints for managing providers and credentials.
-//! All endpoints require ADMIN_KEY authentication.
+//! Every endpoint requires `ADMIN_KEY` or an admin token with a sufficient
+//! role (see [`crate::api::admin_auth`]); mutating calls are audited.
 
 use axum::{
     extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    routing::{get, post},
-    Json, Router,
+    routing::{delete, get, post, put},
+    Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use utoipa::{OpenApi, ToSchema};
 
-use crate::core::config::ModelMappingValue;
+use crate::api::admin_auth::{
+    authenticate, bearer_token, require_role, AdminPrincipal, AdminRole, AuditEvent,
+};
+use crate::api::image_fetch::{ImageInliningConfig, IMAGE_INLINING_PARAM};
+use crate::core::config::{
+    parse_network, ContextTrimming, GuardrailAction, GuardrailDirection, GuardrailKind,
+    GuardrailRule, ModelMappingValue, PiiEntity, PiiPattern, PiiRedactionConfig,
+};
+use crate::core::config_history::{
+    diff_rows, redact_rows, ConfigRestoreError, FieldChange, ResourceChange, ResourceDiff,
+};
 use crate::core::database::{
-    create_key_preview, CreateCredential, CreateProvider, CredentialEntity, DynamicConfig,
-    ProviderEntity, UpdateCredential, UpdateProvider,
+    create_key_preview, AdminAuditLogEntity, AdminAuditLogFilter, AdminTokenEntity,
+    ConfigVersionSummary, CreateAdminToken, CreateCredential, CreateProvider, CredentialEntity,
+    DynamicConfig, GuardrailRuleEntity, ProviderEntity, UpdateCredential, UpdateProvider,
 };
 use crate::core::middleware::CLIENT_PATTERNS;
+use crate::core::provider_network::{NetworkDefaults, NetworkSettings, NETWORK_PARAM};
+use crate::core::tokenizer::{tokenizer_registry, TokenizerSource, TokenizerType, TOKENIZER_PARAM};
+use crate::transformer::guardrails::validate_rule;
+use crate::transformer::pii_redaction::{PiiRedactor, PII_REDACTION_PARAM};
 
 /// OpenAPI documentation for Admin API (admin endpoints only)
 #[derive(OpenApi)]
@@ -32,13 +48,30 @@ use crate::core::middleware::CLIENT_PATTERNS;
         get_provider,
         update_provider,
         delete_provider,
+        reencrypt_provider_keys,
         list_credentials,
         create_credential,
         get_credential,
         update_credential,
         delete_credential,
+        rotate_credential,
+        credential_lifecycle_report,
+        list_guardrail_rules,
+        create_guardrail_rule,
+        get_guardrail_rule,
+        update_guardrail_rule,
+        delete_guardrail_rule,
         get_config_version,
         reload_config,
+        list_config_history,
+        get_config_snapshot,
+        diff_config_versions,
+        rollback_config,
+        list_tokenizers,
+        reload_tokenizers,
+        list_admin_tokens,
+        create_admin_token,
+        delete_admin_token,
         crate::api::health::check_health,
         crate::api::health::get_provider_health,
         crate::api::health::check_provider_health_concurrent,
@@ -50,11 +83,44 @@ use crate::core::middleware::CLIENT_PATTERNS;
             ProviderResponse,
             CreateProviderRequest,
             UpdateProviderRequest,
+            ProviderKeyReencryptResponse,
             CredentialListResponse,
             CredentialResponse,
             CreateCredentialRequest,
             UpdateCredentialRequest,
+            RotateCredentialRequest,
+            RotateCredentialResponse,
+            CredentialLifecycleReport,
+            ContextTrimming,
+            PiiRedactionConfig,
+            PiiEntity,
+            PiiPattern,
+            GuardrailRuleListResponse,
+            GuardrailRuleResponse,
+            CreateGuardrailRuleRequest,
+            UpdateGuardrailRuleRequest,
+            GuardrailKind,
+            GuardrailAction,
+            GuardrailDirection,
             ConfigVersionResponse,
+            ConfigHistoryEntry,
+            ConfigHistoryListResponse,
+            ConfigSnapshotResponse,
+            ConfigDiffResponse,
+            ConfigRollbackResponse,
+            ResourceDiff,
+            ResourceChange,
+            FieldChange,
+            TokenizerListResponse,
+            ModelTokenizerInfo,
+            TokenizerReloadResponse,
+            TokenizerType,
+            TokenizerSource,
+            AdminRole,
+            AdminTokenListResponse,
+            AdminTokenResponse,
+            CreateAdminTokenRequest,
+            CreatedAdminTokenResponse,
             AdminErrorResponse,
             crate::api::health::HealthStatus,
             crate::api::health::ModelHealthStatus,
@@ -71,7 +137,10 @@ use crate::core::middleware::CLIENT_PATTERNS;
         (name = "auth", description = "Authentication endpoints"),
         (name = "providers", description = "Provider management endpoints"),
         (name = "credentials", description = "Credential management endpoints"),
+        (name = "guardrails", description = "Content guardrail rule endpoints"),
         (name = "config", description = "Configuration management endpoints"),
+        (name = "tokenizers", description = "Local tokenizer endpoints"),
+        (name = "admin-tokens", description = "Role-based admin token endpoints"),
         (name = "health", description = "Health check endpoints")
     ),
     info(
@@ -193,31 +262,15 @@ pub struct AdminState {
     pub dynamic_config: Arc<DynamicConfig>,
     pub admin_key: String,
     pub http_client: reqwest::Client,
-}
-
-/// Verify admin authentication
-pub fn verify_admin_auth(headers: &HeaderMap, admin_key: &str) -> Result<(), AdminError> {
-    let auth_header = headers
-        .get("authorization")
-        .and_then(|h| h.to_str().ok())
-        .ok_or(AdminError::Unauthorized)?;
-
-    if !auth_header.starts_with("Bearer ") {
-        return Err(AdminError::Unauthorized);
-    }
-
-    let provided_key = &auth_header[7..];
-    if provided_key != admin_key {
-        return Err(AdminError::Unauthorized);
-    }
-
-    Ok(())
+    /// Client settings of providers without `network` params
+    pub network_defaults: NetworkDefaults,
 }
 
 /// Admin API error types
 #[derive(Debug)]
 pub enum AdminError {
     Unauthorized,
+    Forbidden(String),
     NotFound(String),
     BadRequest(String),
     Internal(String),
@@ -228,6 +281,7 @@ impl IntoResponse for AdminError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
             AdminError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            AdminError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             AdminError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AdminError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             AdminError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
@@ -254,6 +308,18 @@ impl From<sqlx::Error> for AdminError {
     }
 }
 
+impl From<ConfigRestoreError> for AdminError {
+    fn from(e: ConfigRestoreError) -> Self {
+        match e {
+            ConfigRestoreError::NotFound(_) => AdminError::NotFound(e.to_string()),
+            ConfigRestoreError::MissingApiKeys(_) | ConfigRestoreError::UndecryptableApiKeys(_) => {
+                AdminError::BadRequest(e.to_string())
+            }
+            ConfigRestoreError::Database(e) => AdminError::Database(e),
+        }
+    }
+}
+
 // ============================================================================
 // Provider API Types
 // ============================================================================
@@ -441,10 +507,24 @@ pub struct CredentialResponse {
     pub key_preview: String,
     /// List of models this credential can access (empty = all models)
     pub allowed_models: Vec<String>,
+    /// CIDR ranges requests may come from (empty = any address)
+    pub allowed_cidrs: Vec<String>,
     /// Rate limit in requests per second (null = unlimited)
     pub rate_limit: Option<i32>,
     /// Whether this credential is enabled
     pub is_enabled: bool,
+    /// Conversation trimming strategy (null = reject over-long requests)
+    pub context_trimming: Option<ContextTrimming>,
+    /// PII redaction settings (null = use the provider's setting)
+    pub pii_redaction: Option<PiiRedactionConfig>,
+    /// Names of the guardrail rules applied to this credential's traffic
+    pub guardrails: Vec<String>,
+    /// Expiry timestamp (RFC 3339 format, null = never expires)
+    pub expires_at: Option<String>,
+    /// Last successful authentication (RFC 3339 format, null = never used)
+    pub last_used_at: Option<String>,
+    /// When the key replaced by the last rotation stops being accepted (RFC 3339 format)
+    pub previous_key_expires_at: Option<String>,
     /// Creation timestamp (RFC 3339 format)
     pub created_at: String,
     /// Last update timestamp (RFC 3339 format)
@@ -458,8 +538,15 @@ impl CredentialResponse {
             name: e.name,
             key_preview: preview,
             allowed_models: e.allowed_models,
+            allowed_cidrs: e.allowed_cidrs,
             rate_limit: e.rate_limit,
             is_enabled: e.is_enabled,
+            context_trimming: e.context_trimming,
+            pii_redaction: e.pii_redaction,
+            guardrails: e.guardrails,
+            expires_at: e.expires_at.map(|t| t.to_rfc3339()),
+            last_used_at: e.last_used_at.map(|t| t.to_rfc3339()),
+            previous_key_expires_at: e.previous_key_expires_at.map(|t| t.to_rfc3339()),
             created_at: e.created_at.to_rfc3339(),
             updated_at: e.updated_at.to_rfc3339(),
         }
@@ -472,6 +559,7 @@ impl CredentialResponse {
     "key": "sk-your-credential-key",
     "name": "Production Credential",
     "allowed_models": ["gpt-4", "gpt-3.5-turbo"],
+    "allowed_cidrs": ["10.0.0.0/8"],
     "rate_limit": 100,
     "is_enabled": true
 }))]
@@ -483,11 +571,26 @@ pub struct CreateCredentialRequest {
     /// List of models this credential can access (empty = all models)
     #[serde(default)]
     pub allowed_models: Vec<String>,
+    /// CIDR ranges or addresses requests may come from (empty = any address)
+    #[serde(default)]
+    pub allowed_cidrs: Vec<String>,
     /// Rate limit in requests per second (null = unlimited)
     pub rate_limit: Option<i32>,
     /// Whether this credential is enabled (default: true)
     #[serde(default = "default_true")]
     pub is_enabled: bool,
+    /// Conversation trimming strategy (null = reject over-long requests)
+    #[serde(default)]
+    pub context_trimming: Option<ContextTrimming>,
+    /// PII redaction settings (null = use the provider's setting)
+    #[serde(default)]
+    pub pii_redaction: Option<PiiRedactionConfig>,
+    /// Names of the guardrail rules applied to this credential's traffic
+    #[serde(default)]
+    pub guardrails: Vec<String>,
+    /// Expiry timestamp (RFC 3339 format, null = never expires)
+    #[serde(default)]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 /// Request to update an existing credential
@@ -504,16 +607,90 @@ pub struct UpdateCredentialRequest {
     pub name: Option<String>,
     /// List of models this credential can access (empty = all models)
     pub allowed_models: Option<Vec<String>>,
+    /// CIDR ranges or addresses requests may come from (empty = any address)
+    pub allowed_cidrs: Option<Vec<String>>,
     /// Rate limit in requests per second (null = unlimited)
     pub rate_limit: Option<i32>,
     /// Whether this credential is enabled
     pub is_enabled: Option<bool>,
+    /// Conversation trimming strategy
+    pub context_trimming: Option<ContextTrimming>,
+    /// PII redaction settings
+    pub pii_redaction: Option<PiiRedactionConfig>,
+    /// Names of the guardrail rules applied to this credential's traffic
+    pub guardrails: Option<Vec<String>>,
+    /// Expiry timestamp (RFC 3339 format)
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Default time a rotated-out credential key stays valid
+pub const DEFAULT_ROTATION_GRACE_PERIOD_SECS: i64 = 24 * 60 * 60;
+
+/// Longest allowed rotation grace period (30 days)
+pub const MAX_ROTATION_GRACE_PERIOD_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Request to rotate a credential key
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "grace_period_secs": 86400
+}))]
+pub struct RotateCredentialRequest {
+    /// New key value (generated when omitted)
+    pub new_key: Option<String>,
+    /// How long the old key keeps working, in seconds (default: 86400, max: 2592000)
+    pub grace_period_secs: Option<i64>,
+}
+
+/// Result of rotating a credential key
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RotateCredentialResponse {
+    /// The new key value; it is stored hashed and cannot be retrieved again
+    pub key: String,
+    /// When the old key stops being accepted (RFC 3339 format)
+    pub previous_key_expires_at: Option<String>,
+    pub credential: CredentialResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CredentialReportParams {
+    /// Report credentials not used for this many days (default: 30)
+    pub unused_days: Option<i64>,
+    /// Report credentials expiring within this many days (default: 7)
+    pub expiring_within_days: Option<i64>,
+}
+
+/// Credentials that are stale or about to stop working
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CredentialLifecycleReport {
+    /// Report time (RFC 3339 format)
+    pub generated_at: String,
+    pub unused_days: i64,
+    pub expiring_within_days: i64,
+    /// Enabled credentials not used within `unused_days` (never-used ones count from creation)
+    pub unused: Vec<CredentialResponse>,
+    /// Enabled credentials expiring within `expiring_within_days`
+    pub expiring: Vec<CredentialResponse>,
+    /// Credentials past their expiry
+    pub expired: Vec<CredentialResponse>,
 }
 
 // ============================================================================
 // Config API Types
 // ============================================================================
 
+/// Result of re-encrypting provider API keys
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "reencrypted": 3,
+    "primary_key_id": "2024-06"
+}))]
+pub struct ProviderKeyReencryptResponse {
+    /// Number of provider API keys re-encrypted
+    pub reencrypted: usize,
+    /// Master key the API keys are now encrypted with
+    pub primary_key_id: String,
+}
+
 /// Response containing configuration version
 #[derive(Debug, Serialize, ToSchema)]
 #[schema(example = json!({
@@ -527,6 +704,157 @@ pub struct ConfigVersionResponse {
     pub timestamp: String,
 }
 
+/// Recorded configuration version
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "version": 42,
+    "created_at": "2024-01-01T00:00:00Z",
+    "change_source": "providers",
+    "provider_count": 3,
+    "credential_count": 5
+}))]
+pub struct ConfigHistoryEntry {
+    /// Configuration version
+    pub version: i64,
+    /// When the version was recorded (RFC 3339 format)
+    pub created_at: String,
+    /// What recorded the version ("providers", "credentials", "baseline" or "rollback:<version>")
+    pub change_source: String,
+    /// Number of providers in the snapshot
+    pub provider_count: i32,
+    /// Number of credentials in the snapshot
+    pub credential_count: i32,
+}
+
+impl From<ConfigVersionSummary> for ConfigHistoryEntry {
+    fn from(s: ConfigVersionSummary) -> Self {
+        Self {
+            version: s.version,
+            created_at: s.created_at.to_rfc3339(),
+            change_source: s.change_source,
+            provider_count: s.provider_count,
+            credential_count: s.credential_count,
+        }
+    }
+}
+
+/// Page of recorded configuration versions, newest first
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigHistoryListResponse {
+    pub items: Vec<ConfigHistoryEntry>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub total_pages: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigHistoryQueryParams {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+/// Configuration snapshot of a version (secrets masked)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigSnapshotResponse {
+    /// Configuration version
+    pub version: i64,
+    /// When the version was recorded (RFC 3339 format)
+    pub created_at: String,
+    /// What recorded the version
+    pub change_source: String,
+    /// Provider rows; `api_key` is "***" when stored encrypted, null otherwise
+    pub providers: Vec<serde_json::Value>,
+    /// Credential rows; `credential_key` is masked
+    pub credentials: Vec<serde_json::Value>,
+    /// Guardrail rule rows
+    pub guardrail_rules: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigDiffQueryParams {
+    /// Older version
+    pub from: i64,
+    /// Newer version (defaults to the current version)
+    pub to: Option<i64>,
+}
+
+/// Structured diff between two configuration versions
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigDiffResponse {
+    pub from_version: i64,
+    pub to_version: i64,
+    pub providers: ResourceDiff,
+    pub credentials: ResourceDiff,
+    pub guardrail_rules: ResourceDiff,
+}
+
+/// Result of rolling back to a previous configuration version
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "restored_version": 40,
+    "version": 43,
+    "timestamp": "2024-01-01T00:00:00Z"
+}))]
+pub struct ConfigRollbackResponse {
+    /// Version whose snapshot was restored
+    pub restored_version: i64,
+    /// New configuration version recording the rollback
+    pub version: i64,
+    /// Timestamp when the restored configuration was loaded (RFC 3339 format)
+    pub timestamp: String,
+}
+
+/// Tokenizer a model mapping entry resolves to
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ModelTokenizerInfo {
+    /// Provider key
+    pub provider: String,
+    /// Model name (or pattern) as configured in the model mapping
+    pub model: String,
+    /// Model name sent to the provider
+    pub mapped_model: String,
+    /// Tokenizer implementation
+    pub tokenizer_type: TokenizerType,
+    /// Tokenizer name (`tiktoken`, `claude` or a local tokenizer name/path)
+    pub tokenizer: String,
+    /// Where the tokenizer choice came from
+    pub source: TokenizerSource,
+    /// Resolved tokenizer.json path for local tokenizers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Whether the tokenizer is currently loaded in memory
+    pub loaded: bool,
+}
+
+/// Tokenizer resolution for all configured models
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "tokenizer_dir": "/opt/tokenizers",
+    "models": [{
+        "provider": "vllm",
+        "model": "qwen-72b",
+        "mapped_model": "Qwen/Qwen2.5-72B-Instruct",
+        "tokenizer_type": "HuggingFace",
+        "tokenizer": "qwen",
+        "source": "model_family",
+        "path": "/opt/tokenizers/qwen/tokenizer.json",
+        "loaded": true
+    }]
+}))]
+pub struct TokenizerListResponse {
+    /// Directory searched for named tokenizers (`TOKENIZER_DIR`)
+    pub tokenizer_dir: Option<String>,
+    pub models: Vec<ModelTokenizerInfo>,
+}
+
+/// Result of reloading local tokenizers
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenizerReloadResponse {
+    /// Number of tokenizers dropped from memory; they are re-read on next use
+    pub unloaded: usize,
+}
+
 /// Admin API error response
 #[derive(Debug, Serialize, ToSchema)]
 #[schema(example = json!({
@@ -553,13 +881,17 @@ pub struct AdminErrorDetail {
 #[derive(Debug, Serialize, ToSchema)]
 #[schema(example = json!({
     "valid": true,
-    "message": "Admin key is valid"
+    "message": "Admin key is valid",
+    "role": "admin"
 }))]
 pub struct AuthValidateResponse {
     /// Whether the admin key is valid
     pub valid: bool,
     /// Validation message
     pub message: String,
+    /// Role granted by the key (absent when invalid)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<AdminRole>,
 }
 
 fn default_true() -> bool {
@@ -588,10 +920,7 @@ fn default_weight() -> i32 {
 )]
 pub async fn list_providers(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
 ) -> Result<Json<ProviderListResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let db = state.dynamic_config.database();
     let providers = db.load_all_providers().await?;
     let version = db.get_config_version().await?;
@@ -620,11 +949,8 @@ pub async fn list_providers(
 )]
 pub async fn get_provider(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Path(id): Path<i32>,
 ) -> Result<Json<ProviderResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let db = state.dynamic_config.database();
     let provider = db
         .get_provider(id)
@@ -634,6 +960,29 @@ pub async fn get_provider(
     Ok(Json(provider.into()))
 }
 
+/// Reject `network` provider params that cannot be turned into a client,
+/// e.g. a missing CA bundle, rather than falling back to the default client,
+/// and `image_inlining` or `pii_redaction` params that do not parse.
+fn validate_provider_params(
+    state: &AdminState,
+    params: &HashMap<String, serde_json::Value>,
+) -> Result<(), AdminError> {
+    if let Some(settings) = NetworkSettings::from_params(params).map_err(AdminError::BadRequest)? {
+        settings
+            .build_client(&state.network_defaults)
+            .map_err(|e| {
+                AdminError::BadRequest(format!("invalid {} param: {}", NETWORK_PARAM, e))
+            })?;
+    }
+    ImageInliningConfig::from_param(params.get(IMAGE_INLINING_PARAM))
+        .map_err(AdminError::BadRequest)?;
+    validate_pii_redaction(
+        PiiRedactor::provider_config(params.get(PII_REDACTION_PARAM))
+            .map_err(AdminError::BadRequest)?,
+    )?;
+    Ok(())
+}
+
 /// Create a new provider
 ///
 /// Creates a new provider with the specified configuration.
@@ -650,11 +999,9 @@ pub async fn get_provider(
 )]
 pub async fn create_provider(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Json(req): Json<CreateProviderRequest>,
 ) -> Result<(StatusCode, Json<ProviderResponse>), AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     if req.provider_key.is_empty() {
         return Err(AdminError::BadRequest(
             "Provider key is required".to_string(),
@@ -667,6 +1014,8 @@ pub async fn create_provider(
         return Err(AdminError::BadRequest("API key is required".to_string()));
     }
 
+    validate_provider_params(&state, &req.provider_params)?;
+
     let db = state.dynamic_config.database();
 
     if db.get_provider_by_key(&req.provider_key).await?.is_some() {
@@ -690,7 +1039,14 @@ pub async fn create_provider(
     let provider = db.create_provider(&create).await?;
     tracing::info!(provider_id = %provider.id, provider_key = %provider.provider_key, "Provider created");
 
-    Ok((StatusCode::CREATED, Json(provider.into())))
+    let response = ProviderResponse::from(provider);
+    AuditEvent::new("create", "provider")
+        .resource(response.id)
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 /// Update an existing provider
@@ -713,13 +1069,16 @@ pub async fn create_provider(
 )]
 pub async fn update_provider(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Path(id): Path<i32>,
     Json(req): Json<UpdateProviderRequest>,
 ) -> Result<Json<ProviderResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
+    if let Some(params) = &req.provider_params {
+        validate_provider_params(&state, params)?;
+    }
 
     let db = state.dynamic_config.database();
+    let before = db.get_provider(id).await?.map(ProviderResponse::from);
 
     let update = UpdateProvider {
         provider_type: req.provider_type,
@@ -738,7 +1097,15 @@ pub async fn update_provider(
 
     tracing::info!(provider_id = %id, "Provider updated");
 
-    Ok(Json(provider.into()))
+    let response = ProviderResponse::from(provider);
+    AuditEvent::new("update", "provider")
+        .resource(id)
+        .before(&before)
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(response))
 }
 
 /// Delete a provider
@@ -759,12 +1126,11 @@ pub async fn update_provider(
 )]
 pub async fn delete_provider(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Path(id): Path<i32>,
 ) -> Result<StatusCode, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let db = state.dynamic_config.database();
+    let before = db.get_provider(id).await?.map(ProviderResponse::from);
     let deleted = db.delete_provider(id).await?;
 
     if !deleted {
@@ -776,9 +1142,63 @@ pub async fn delete_provider(
 
     tracing::info!(provider_id = %id, "Provider deleted");
 
+    AuditEvent::new("delete", "provider")
+        .resource(id)
+        .before(&before)
+        .record(&state, &principal)
+        .await;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Re-encrypt provider API keys
+///
+/// Re-encrypts every stored provider API key that is not sealed under the
+/// primary master key, including keys still stored in plaintext. Run this
+/// after adding a new master key in front of the old one, before removing
+/// the old key.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/providers/reencrypt",
+    tag = "providers",
+    responses(
+        (status = 200, description = "Provider API keys re-encrypted", body = ProviderKeyReencryptResponse),
+        (status = 400, description = "Encryption not configured", body = AdminErrorResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 500, description = "Internal server error", body = AdminErrorResponse)
+    )
+)]
+pub async fn reencrypt_provider_keys(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+) -> Result<Json<ProviderKeyReencryptResponse>, AdminError> {
+    let db = state.dynamic_config.database();
+    let primary_key_id = db
+        .key_cipher()
+        .map(|cipher| cipher.primary_key_id().to_string())
+        .ok_or_else(|| {
+            AdminError::BadRequest("Provider API key encryption is not configured".to_string())
+        })?;
+    let reencrypted = db.reencrypt_provider_keys().await?;
+
+    tracing::info!(
+        reencrypted = reencrypted,
+        primary_key_id = %primary_key_id,
+        "Provider API keys re-encrypted"
+    );
+
+    let response = ProviderKeyReencryptResponse {
+        reencrypted,
+        primary_key_id,
+    };
+    AuditEvent::new("reencrypt", "provider")
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(response))
+}
+
 // ============================================================================
 // Credential Handlers
 // ============================================================================
@@ -798,10 +1218,7 @@ pub async fn delete_provider(
 )]
 pub async fn list_credentials(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
 ) -> Result<Json<CredentialListResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let db = state.dynamic_config.database();
     let credentials = db.load_all_credentials().await?;
     let version = db.get_config_version().await?;
@@ -839,11 +1256,8 @@ pub async fn list_credentials(
 )]
 pub async fn get_credential(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Path(id): Path<i32>,
 ) -> Result<Json<CredentialResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let db = state.dynamic_config.database();
     let credential = db
         .get_credential(id)
@@ -871,11 +1285,9 @@ pub async fn get_credential(
 )]
 pub async fn create_credential(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Json(req): Json<CreateCredentialRequest>,
 ) -> Result<(StatusCode, Json<CredentialResponse>), AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     if req.key.is_empty() {
         return Err(AdminError::BadRequest("Key value is required".to_string()));
     }
@@ -895,15 +1307,71 @@ pub async fn create_credential(
         allowed_models: req.allowed_models,
         rate_limit: req.rate_limit,
         is_enabled: req.is_enabled,
+        context_trimming: req.context_trimming,
+        pii_redaction: validate_pii_redaction(req.pii_redaction)?,
+        guardrails: validate_guardrail_names(&state, req.guardrails).await?,
+        expires_at: req.expires_at,
+        allowed_cidrs: normalize_cidrs(&req.allowed_cidrs)?,
     };
 
     let credential = db.create_credential(&create).await?;
     tracing::info!(credential_id = %credential.id, credential_name = %credential.name, "Credential created");
 
-    Ok((
-        StatusCode::CREATED,
-        Json(CredentialResponse::from_entity(credential, key_preview)),
-    ))
+    let response = CredentialResponse::from_entity(credential, key_preview);
+    AuditEvent::new("create", "credential")
+        .resource(response.id)
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Validate CIDR allowlist entries, storing single addresses as host ranges.
+fn normalize_cidrs(cidrs: &[String]) -> Result<Vec<String>, AdminError> {
+    cidrs
+        .iter()
+        .map(|cidr| {
+            parse_network(cidr)
+                .map(|net| net.to_string())
+                .map_err(AdminError::BadRequest)
+        })
+        .collect()
+}
+
+/// Reject PII redaction settings whose custom patterns do not compile.
+fn validate_pii_redaction(
+    config: Option<PiiRedactionConfig>,
+) -> Result<Option<PiiRedactionConfig>, AdminError> {
+    if let Some(ref config) = config {
+        PiiRedactor::new(config).map_err(AdminError::BadRequest)?;
+    }
+    Ok(config)
+}
+
+/// Reject guardrail rule names that do not exist.
+async fn validate_guardrail_names(
+    state: &AdminState,
+    names: Vec<String>,
+) -> Result<Vec<String>, AdminError> {
+    if names.is_empty() {
+        return Ok(names);
+    }
+    let rules = state
+        .dynamic_config
+        .database()
+        .list_guardrail_rules()
+        .await?;
+    if let Some(unknown) = names
+        .iter()
+        .find(|name| !rules.iter().any(|rule| &rule.name == *name))
+    {
+        return Err(AdminError::BadRequest(format!(
+            "Guardrail rule '{}' does not exist",
+            unknown
+        )));
+    }
+    Ok(names)
 }
 
 /// Update an existing credential
@@ -927,13 +1395,15 @@ pub async fn create_credential(
 )]
 pub async fn update_credential(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Path(id): Path<i32>,
     Json(req): Json<UpdateCredentialRequest>,
 ) -> Result<Json<CredentialResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let db = state.dynamic_config.database();
+    let before = db.get_credential(id).await?.map(|c| {
+        let preview = format!("***{}", &c.credential_key[..6]);
+        CredentialResponse::from_entity(c, preview)
+    });
 
     let update = UpdateCredential {
         key: req.key.clone(),
@@ -941,6 +1411,18 @@ pub async fn update_credential(
         allowed_models: req.allowed_models,
         rate_limit: req.rate_limit,
         is_enabled: req.is_enabled,
+        context_trimming: req.context_trimming,
+        pii_redaction: validate_pii_redaction(req.pii_redaction)?,
+        guardrails: match req.guardrails {
+            Some(names) => Some(validate_guardrail_names(&state, names).await?),
+            None => None,
+        },
+        expires_at: req.expires_at,
+        allowed_cidrs: req
+            .allowed_cidrs
+            .as_deref()
+            .map(normalize_cidrs)
+            .transpose()?,
     };
 
     let credential = db
@@ -956,7 +1438,15 @@ pub async fn update_credential(
 
     tracing::info!(credential_id = %id, "Credential updated");
 
-    Ok(Json(CredentialResponse::from_entity(credential, preview)))
+    let response = CredentialResponse::from_entity(credential, preview);
+    AuditEvent::new("update", "credential")
+        .resource(id)
+        .before(&before)
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(response))
 }
 
 /// Delete a credential
@@ -977,12 +1467,14 @@ pub async fn update_credential(
 )]
 pub async fn delete_credential(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Path(id): Path<i32>,
 ) -> Result<StatusCode, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let db = state.dynamic_config.database();
+    let before = db.get_credential(id).await?.map(|c| {
+        let preview = format!("***{}", &c.credential_key[..6]);
+        CredentialResponse::from_entity(c, preview)
+    });
     let deleted = db.delete_credential(id).await?;
 
     if !deleted {
@@ -994,9 +1486,157 @@ pub async fn delete_credential(
 
     tracing::info!(credential_id = %id, "Credential deleted");
 
+    AuditEvent::new("delete", "credential")
+        .resource(id)
+        .before(&before)
+        .record(&state, &principal)
+        .await;
+
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Rotate a credential key
+///
+/// Issues a new key for the credential. The old key keeps working until the
+/// grace period ends; a key replaced by an earlier rotation stops working
+/// immediately.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/credentials/{id}/rotate",
+    tag = "credentials",
+    params(
+        ("id" = i32, Path, description = "Credential ID")
+    ),
+    request_body = RotateCredentialRequest,
+    responses(
+        (status = 200, description = "Credential rotated", body = RotateCredentialResponse),
+        (status = 400, description = "Bad request", body = AdminErrorResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 404, description = "Credential not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn rotate_credential(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+    Path(id): Path<i32>,
+    body: Option<Json<RotateCredentialRequest>>,
+) -> Result<Json<RotateCredentialResponse>, AdminError> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let grace_period_secs = req
+        .grace_period_secs
+        .unwrap_or(DEFAULT_ROTATION_GRACE_PERIOD_SECS);
+    if !(0..=MAX_ROTATION_GRACE_PERIOD_SECS).contains(&grace_period_secs) {
+        return Err(AdminError::BadRequest(format!(
+            "grace_period_secs must be between 0 and {}",
+            MAX_ROTATION_GRACE_PERIOD_SECS
+        )));
+    }
+    let key = match req.new_key {
+        Some(key) if key.is_empty() => {
+            return Err(AdminError::BadRequest(
+                "new_key must not be empty".to_string(),
+            ))
+        }
+        Some(key) => key,
+        None => format!("sk-{}", hex::encode(rand::random::<[u8; 24]>())),
+    };
+
+    let db = state.dynamic_config.database();
+    let before = db.get_credential(id).await?.map(|c| {
+        let preview = format!("***{}", &c.credential_key[..6]);
+        CredentialResponse::from_entity(c, preview)
+    });
+    let credential = db
+        .rotate_credential(id, &key, grace_period_secs)
+        .await?
+        .ok_or_else(|| AdminError::NotFound(format!("Credential with ID {} not found", id)))?;
+
+    tracing::info!(
+        credential_id = %id,
+        grace_period_secs = grace_period_secs,
+        "Credential key rotated"
+    );
+
+    let response = CredentialResponse::from_entity(credential, create_key_preview(&key));
+    AuditEvent::new("rotate", "credential")
+        .resource(id)
+        .before(&before)
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(RotateCredentialResponse {
+        key,
+        previous_key_expires_at: response.previous_key_expires_at.clone(),
+        credential: response,
+    }))
+}
+
+/// Credential lifecycle report
+///
+/// Lists enabled credentials that have not been used recently or expire
+/// soon, and credentials that have already expired. Last-used times are
+/// recorded in batches and may lag by up to a minute.
+#[utoipa::path(
+    get,
+    path = "/admin/v1/credentials/report",
+    tag = "credentials",
+    params(
+        ("unused_days" = Option<i64>, Query, description = "Report credentials unused for this many days (default: 30)"),
+        ("expiring_within_days" = Option<i64>, Query, description = "Report credentials expiring within this many days (default: 7)")
+    ),
+    responses(
+        (status = 200, description = "Credential lifecycle report", body = CredentialLifecycleReport),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse)
+    )
+)]
+pub async fn credential_lifecycle_report(
+    State(state): State<Arc<AdminState>>,
+    Query(params): Query<CredentialReportParams>,
+) -> Result<Json<CredentialLifecycleReport>, AdminError> {
+    let unused_days = params.unused_days.unwrap_or(30).max(0);
+    let expiring_within_days = params.expiring_within_days.unwrap_or(7).max(0);
+    let now = chrono::Utc::now();
+    let unused_cutoff = now - chrono::Duration::days(unused_days);
+    let expiring_window = chrono::Duration::days(expiring_within_days);
+
+    let credentials = state
+        .dynamic_config
+        .database()
+        .load_all_credentials()
+        .await?;
+    let response = |c: &CredentialEntity| {
+        let preview = format!("***{}", &c.credential_key[..6]);
+        CredentialResponse::from_entity(c.clone(), preview)
+    };
+
+    let mut report = CredentialLifecycleReport {
+        generated_at: now.to_rfc3339(),
+        unused_days,
+        expiring_within_days,
+        unused: Vec::new(),
+        expiring: Vec::new(),
+        expired: Vec::new(),
+    };
+    for c in &credentials {
+        if c.is_expired(now) {
+            report.expired.push(response(c));
+            continue;
+        }
+        if !c.is_enabled {
+            continue;
+        }
+        if c.unused_since(unused_cutoff) {
+            report.unused.push(response(c));
+        }
+        if c.expires_within(now, expiring_window) {
+            report.expiring.push(response(c));
+        }
+    }
+
+    Ok(Json(report))
+}
+
 // ============================================================================
 // Auth Handlers
 // ============================================================================
@@ -1028,29 +1668,31 @@ pub async fn validate_admin_key(
         return Err((StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response());
     }
 
-    let auth_header = headers.get("authorization").and_then(|h| h.to_str().ok());
+    if bearer_token(&headers).is_none() {
+        return Ok(Json(AuthValidateResponse {
+            valid: false,
+            message: "Invalid admin key".to_string(),
+            role: None,
+        }));
+    }
 
-    let provided_key = match auth_header {
-        Some(h) if h.starts_with("Bearer ") => &h[7..],
-        _ => {
-            return Ok(Json(AuthValidateResponse {
+    let principal = match authenticate(&state, &headers).await {
+        Ok(principal) => principal,
+        Err(AdminError::Unauthorized) => {
+            let body = AuthValidateResponse {
                 valid: false,
                 message: "Invalid admin key".to_string(),
-            }));
+                role: None,
+            };
+            return Err((StatusCode::UNAUTHORIZED, Json(body)).into_response());
         }
+        Err(e) => return Err(e.into_response()),
     };
 
-    if provided_key != state.admin_key {
-        let body = AuthValidateResponse {
-            valid: false,
-            message: "Invalid admin key".to_string(),
-        };
-        return Err((StatusCode::UNAUTHORIZED, Json(body)).into_response());
-    }
-
     Ok(Json(AuthValidateResponse {
         valid: true,
         message: "Admin key is valid".to_string(),
+        role: Some(principal.role),
     }))
 }
 
@@ -1072,10 +1714,7 @@ pub async fn validate_admin_key(
 )]
 pub async fn get_config_version(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
 ) -> Result<Json<ConfigVersionResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let config = state.dynamic_config.get();
 
     Ok(Json(ConfigVersionResponse {
@@ -1100,30 +1739,316 @@ pub async fn get_config_version(
 )]
 pub async fn reload_config(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
 ) -> Result<Json<ConfigVersionResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let version = state.dynamic_config.reload().await?;
     let config = state.dynamic_config.get();
 
     tracing::info!(version = version, "Configuration reloaded via Admin API");
 
-    Ok(Json(ConfigVersionResponse {
+    let response = ConfigVersionResponse {
         version: config.version,
         timestamp: config.loaded_at.to_rfc3339(),
-    }))
+    };
+    AuditEvent::new("reload", "config")
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(response))
 }
 
-// ============================================================================
-// Request Logs API
-// ============================================================================
+/// List configuration history
+///
+/// Returns the recorded configuration versions, newest first. A snapshot of
+/// all providers and credentials is recorded for every version.
+#[utoipa::path(
+    get,
+    path = "/admin/v1/config/history",
+    tag = "config",
+    params(
+        ("page" = Option<i64>, Query, description = "Page number (default: 1)"),
+        ("page_size" = Option<i64>, Query, description = "Items per page (default: 50, max: 200)")
+    ),
+    responses(
+        (status = 200, description = "Configuration versions", body = ConfigHistoryListResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse)
+    )
+)]
+pub async fn list_config_history(
+    State(state): State<Arc<AdminState>>,
+    Query(params): Query<ConfigHistoryQueryParams>,
+) -> Result<Json<ConfigHistoryListResponse>, AdminError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(50).clamp(1, 200);
+    let offset = (page - 1) * page_size;
 
-fn extract_client_from_headers(headers_json: Option<&str>) -> Option<String> {
-    let json_str = headers_json?;
-    let parsed: serde_json::Value = serde_json::from_str(json_str).ok()?;
-    let ua = parsed
-        .get("user-agent")
+    let (items, total) = state
+        .dynamic_config
+        .database()
+        .list_config_history(page_size, offset)
+        .await?;
+
+    let total_pages = if total == 0 {
+        1
+    } else {
+        (total + page_size - 1) / page_size
+    };
+
+    Ok(Json(ConfigHistoryListResponse {
+        items: items.into_iter().map(Into::into).collect(),
+        total,
+        page,
+        page_size,
+        total_pages,
+    }))
+}
+
+/// Get a configuration snapshot
+///
+/// Returns the providers, credentials and guardrail rules recorded for a
+/// version, with secrets masked.
+#[utoipa::path(
+    get,
+    path = "/admin/v1/config/history/{version}",
+    tag = "config",
+    params(
+        ("version" = i64, Path, description = "Configuration version")
+    ),
+    responses(
+        (status = 200, description = "Configuration snapshot", body = ConfigSnapshotResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 404, description = "Version not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn get_config_snapshot(
+    State(state): State<Arc<AdminState>>,
+    Path(version): Path<i64>,
+) -> Result<Json<ConfigSnapshotResponse>, AdminError> {
+    let snapshot = state
+        .dynamic_config
+        .database()
+        .get_config_snapshot(version)
+        .await?
+        .ok_or_else(|| {
+            AdminError::NotFound(format!("Configuration version {} not found", version))
+        })?;
+
+    Ok(Json(ConfigSnapshotResponse {
+        version: snapshot.version,
+        created_at: snapshot.created_at.to_rfc3339(),
+        change_source: snapshot.change_source,
+        providers: redact_rows(&snapshot.providers.0),
+        credentials: redact_rows(&snapshot.credentials.0),
+        guardrail_rules: snapshot.guardrail_rules.0,
+    }))
+}
+
+/// Diff two configuration versions
+///
+/// Returns the providers, credentials and guardrail rules added, removed and
+/// changed between two versions, matched by ID. Secrets are masked.
+#[utoipa::path(
+    get,
+    path = "/admin/v1/config/history/diff",
+    tag = "config",
+    params(
+        ("from" = i64, Query, description = "Older version"),
+        ("to" = Option<i64>, Query, description = "Newer version (default: current version)")
+    ),
+    responses(
+        (status = 200, description = "Configuration diff", body = ConfigDiffResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 404, description = "Version not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn diff_config_versions(
+    State(state): State<Arc<AdminState>>,
+    Query(params): Query<ConfigDiffQueryParams>,
+) -> Result<Json<ConfigDiffResponse>, AdminError> {
+    let db = state.dynamic_config.database();
+    let to_version = match params.to {
+        Some(version) => version,
+        None => db.get_config_version().await?,
+    };
+    let not_found =
+        |version: i64| AdminError::NotFound(format!("Configuration version {} not found", version));
+    let from = db
+        .get_config_snapshot(params.from)
+        .await?
+        .ok_or_else(|| not_found(params.from))?;
+    let to = db
+        .get_config_snapshot(to_version)
+        .await?
+        .ok_or_else(|| not_found(to_version))?;
+
+    Ok(Json(ConfigDiffResponse {
+        from_version: from.version,
+        to_version: to.version,
+        providers: diff_rows(&from.providers.0, &to.providers.0, "provider_key"),
+        credentials: diff_rows(&from.credentials.0, &to.credentials.0, "name"),
+        guardrail_rules: diff_rows(&from.guardrail_rules.0, &to.guardrail_rules.0, "name"),
+    }))
+}
+
+/// Roll back to a configuration version
+///
+/// Atomically restores all providers, credentials and guardrail rules from the
+/// snapshot of a previous version, records the result as a new version and
+/// reloads the configuration. Provider API keys that were not stored (no encryption at
+/// rest) keep their current value.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/config/history/{version}/rollback",
+    tag = "config",
+    params(
+        ("version" = i64, Path, description = "Configuration version to restore")
+    ),
+    responses(
+        (status = 200, description = "Configuration restored", body = ConfigRollbackResponse),
+        (status = 400, description = "Snapshot cannot be restored", body = AdminErrorResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 404, description = "Version not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn rollback_config(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+    Path(version): Path<i64>,
+) -> Result<Json<ConfigRollbackResponse>, AdminError> {
+    let db = state.dynamic_config.database();
+    let previous_version = db.get_config_version().await?;
+    let new_version = db.restore_config_version(version).await?;
+    state.dynamic_config.reload().await?;
+    let config = state.dynamic_config.get();
+
+    tracing::info!(
+        restored_version = version,
+        version = new_version,
+        "Configuration rolled back via Admin API"
+    );
+
+    let response = ConfigRollbackResponse {
+        restored_version: version,
+        version: new_version,
+        timestamp: config.loaded_at.to_rfc3339(),
+    };
+    AuditEvent::new("rollback", "config")
+        .resource(version)
+        .before(&serde_json::json!({ "version": previous_version }))
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(response))
+}
+
+// ============================================================================
+// Tokenizer Handlers
+// ============================================================================
+
+/// List tokenizers resolved for each configured model
+///
+/// Reports which local tokenizer usage estimates use for every model mapping
+/// entry of the enabled providers.
+#[utoipa::path(
+    get,
+    path = "/admin/v1/tokenizers",
+    tag = "tokenizers",
+    responses(
+        (status = 200, description = "Tokenizer per model", body = TokenizerListResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse)
+    )
+)]
+pub async fn list_tokenizers(
+    State(state): State<Arc<AdminState>>,
+) -> Result<Json<TokenizerListResponse>, AdminError> {
+    let registry = tokenizer_registry();
+    let config = state.dynamic_config.get();
+    let mut models = Vec::new();
+    for provider in &config.providers {
+        let provider_tokenizer = provider
+            .provider_params
+            .get(TOKENIZER_PARAM)
+            .and_then(|v| v.as_str());
+        let mut entries: Vec<_> = provider.model_mapping.0.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        for (model, entry) in entries {
+            let selection = registry
+                .configured_selection(model, entry, provider_tokenizer)
+                .unwrap_or_else(|| registry.select_default(entry.mapped_model()));
+            let name = match selection.tokenizer_type {
+                TokenizerType::Tiktoken => "tiktoken".to_string(),
+                TokenizerType::HuggingFace => selection.hf_repo.clone().unwrap_or_default(),
+            };
+            let (tokenizer, path, loaded) = match name.as_str() {
+                "tiktoken" => (name, None, true),
+                "__embedded_claude__" => ("claude".to_string(), None, true),
+                _ => {
+                    let path = registry
+                        .resolve_path(&name)
+                        .map(|p| p.display().to_string());
+                    let loaded = registry.is_loaded(&name);
+                    (name, path, loaded)
+                }
+            };
+            models.push(ModelTokenizerInfo {
+                provider: provider.provider_key.clone(),
+                model: model.clone(),
+                mapped_model: entry.mapped_model().to_string(),
+                tokenizer_type: selection.tokenizer_type,
+                tokenizer,
+                source: selection.source,
+                path,
+                loaded,
+            });
+        }
+    }
+
+    Ok(Json(TokenizerListResponse {
+        tokenizer_dir: registry.dir().map(|d| d.display().to_string()),
+        models,
+    }))
+}
+
+/// Reload local tokenizers
+///
+/// Drops cached tokenizers and model resolutions so updated tokenizer files
+/// are picked up on next use.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/tokenizers/reload",
+    tag = "tokenizers",
+    responses(
+        (status = 200, description = "Tokenizers reloaded", body = TokenizerReloadResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse)
+    )
+)]
+pub async fn reload_tokenizers(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+) -> Result<Json<TokenizerReloadResponse>, AdminError> {
+    let unloaded = tokenizer_registry().reload();
+    tracing::info!(unloaded = unloaded, "Tokenizers reloaded via Admin API");
+
+    let response = TokenizerReloadResponse { unloaded };
+    AuditEvent::new("reload", "tokenizers")
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(response))
+}
+
+// ============================================================================
+// Request Logs API
+// ============================================================================
+
+fn extract_client_from_headers(headers_json: Option<&str>) -> Option<String> {
+    let json_str = headers_json?;
+    let parsed: serde_json::Value = serde_json::from_str(json_str).ok()?;
+    let ua = parsed
+        .get("user-agent")
         .or_else(|| parsed.get("User-Agent"))
         .and_then(|v| v.as_str())
         .unwrap_or("");
@@ -1169,6 +2094,9 @@ pub struct RequestLogItem {
     pub total_tokens: i32,
     pub total_duration_ms: Option<i32>,
     pub ttft_ms: Option<i32>,
+    pub audio_duration_ms: Option<i32>,
+    /// Number of distinct values redacted per placeholder label
+    pub pii_redactions: Option<serde_json::Value>,
     pub error_category: Option<String>,
     pub error_message: Option<String>,
     pub client: Option<String>,
@@ -1239,11 +2167,8 @@ fn parse_iso_time(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
 
 pub async fn list_logs(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Query(params): Query<LogQueryParams>,
 ) -> Result<Json<RequestLogListResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let page = params.page.unwrap_or(1).max(1);
     let page_size = params.page_size.unwrap_or(50).clamp(1, 200);
     let offset = (page - 1) * page_size;
@@ -1346,7 +2271,7 @@ pub async fn list_logs(
          model_requested, model_mapped, provider_name, provider_type, \
          client_protocol, provider_protocol, is_streaming, status_code, \
          input_tokens, output_tokens, total_tokens, \
-         total_duration_ms, ttft_ms, error_category, error_message, \
+         total_duration_ms, ttft_ms, audio_duration_ms, pii_redactions, error_category, error_message, \
          request_headers \
          FROM request_logs {} ORDER BY {} {} LIMIT {} OFFSET {}",
         where_clause, sort_col, sort_dir, page_size, offset
@@ -1449,6 +2374,8 @@ pub async fn list_logs(
         total_tokens: i32,
         total_duration_ms: Option<i32>,
         ttft_ms: Option<i32>,
+        audio_duration_ms: Option<i32>,
+        pii_redactions: Option<serde_json::Value>,
         error_category: Option<String>,
         error_message: Option<String>,
         request_headers: Option<String>,
@@ -1479,6 +2406,8 @@ pub async fn list_logs(
             total_tokens: r.total_tokens,
             total_duration_ms: r.total_duration_ms,
             ttft_ms: r.ttft_ms,
+            audio_duration_ms: r.audio_duration_ms,
+            pii_redactions: r.pii_redactions,
             error_category: r.error_category,
             error_message: r.error_message,
             client: extract_client_from_headers(r.request_headers.as_deref()),
@@ -1496,11 +2425,8 @@ pub async fn list_logs(
 
 pub async fn get_log_stats(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Query(params): Query<LogStatsParams>,
 ) -> Result<Json<RequestLogStatsResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let pool = state.dynamic_config.database().pool();
 
     let mut conditions: Vec<String> = Vec::new();
@@ -1696,11 +2622,8 @@ pub async fn get_log_stats(
 
 pub async fn get_log_detail(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Path(log_id): Path<i64>,
 ) -> Result<Json<RequestLogDetail>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let pool = state.dynamic_config.database().pool();
 
     #[derive(sqlx::FromRow)]
@@ -1723,6 +2646,8 @@ pub async fn get_log_detail(
         total_tokens: i32,
         total_duration_ms: Option<i32>,
         ttft_ms: Option<i32>,
+        audio_duration_ms: Option<i32>,
+        pii_redactions: Option<serde_json::Value>,
         error_category: Option<String>,
         error_message: Option<String>,
         request_headers: Option<String>,
@@ -1735,7 +2660,7 @@ pub async fn get_log_detail(
          model_requested, model_mapped, provider_name, provider_type, \
          client_protocol, provider_protocol, is_streaming, status_code, \
          input_tokens, output_tokens, total_tokens, \
-         total_duration_ms, ttft_ms, error_category, error_message, \
+         total_duration_ms, ttft_ms, audio_duration_ms, pii_redactions, error_category, error_message, \
          request_headers, request_body, response_body \
          FROM request_logs WHERE id = $1",
     )
@@ -1764,6 +2689,8 @@ pub async fn get_log_detail(
             total_tokens: row.total_tokens,
             total_duration_ms: row.total_duration_ms,
             ttft_ms: row.ttft_ms,
+            audio_duration_ms: row.audio_duration_ms,
+            pii_redactions: row.pii_redactions,
             error_category: row.error_category,
             error_message: row.error_message,
             client: extract_client_from_headers(row.request_headers.as_deref()),
@@ -1831,11 +2758,8 @@ pub struct ErrorLogQueryParams {
 
 pub async fn list_error_logs(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Query(params): Query<ErrorLogQueryParams>,
 ) -> Result<Json<ErrorLogListResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let page = params.page.unwrap_or(1).max(1);
     let page_size = params.page_size.unwrap_or(50).clamp(1, 200);
     let offset = (page - 1) * page_size;
@@ -2017,11 +2941,8 @@ pub async fn list_error_logs(
 
 pub async fn get_error_log_detail(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Path(log_id): Path<i64>,
 ) -> Result<Json<ErrorLogDetail>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     let pool = state.dynamic_config.database().pool();
 
     #[derive(sqlx::FromRow)]
@@ -2090,7 +3011,7 @@ pub async fn get_error_log_detail(
 // Log Deletion API
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BatchDeleteRequest {
     pub ids: Vec<i64>,
 }
@@ -2102,10 +3023,9 @@ pub struct BatchDeleteResponse {
 
 pub async fn delete_log(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Path(log_id): Path<i64>,
 ) -> Result<StatusCode, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
     let pool = state.dynamic_config.database().pool();
 
     let result = sqlx::query("DELETE FROM request_logs WHERE id = $1")
@@ -2118,16 +3038,18 @@ pub async fn delete_log(
     }
 
     tracing::info!(log_id = %log_id, "Request log deleted");
+    AuditEvent::new("delete", "request_log")
+        .resource(log_id)
+        .record(&state, &principal)
+        .await;
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn batch_delete_logs(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Json(body): Json<BatchDeleteRequest>,
 ) -> Result<Json<BatchDeleteResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     if body.ids.is_empty() {
         return Ok(Json(BatchDeleteResponse { deleted: 0 }));
     }
@@ -2158,15 +3080,19 @@ pub async fn batch_delete_logs(
     let deleted = result.rows_affected() as i64;
 
     tracing::info!(deleted = %deleted, "Request logs batch deleted");
+    AuditEvent::new("batch_delete", "request_log")
+        .before(&body)
+        .after(&BatchDeleteResponse { deleted })
+        .record(&state, &principal)
+        .await;
     Ok(Json(BatchDeleteResponse { deleted }))
 }
 
 pub async fn delete_error_log(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Path(log_id): Path<i64>,
 ) -> Result<StatusCode, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
     let pool = state.dynamic_config.database().pool();
 
     let result = sqlx::query("DELETE FROM error_logs WHERE id = $1")
@@ -2182,16 +3108,18 @@ pub async fn delete_error_log(
     }
 
     tracing::info!(log_id = %log_id, "Error log deleted");
+    AuditEvent::new("delete", "error_log")
+        .resource(log_id)
+        .record(&state, &principal)
+        .await;
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn batch_delete_error_logs(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
+    Extension(principal): Extension<AdminPrincipal>,
     Json(body): Json<BatchDeleteRequest>,
 ) -> Result<Json<BatchDeleteResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     if body.ids.is_empty() {
         return Ok(Json(BatchDeleteResponse { deleted: 0 }));
     }
@@ -2222,57 +3150,785 @@ pub async fn batch_delete_error_logs(
     let deleted = result.rows_affected() as i64;
 
     tracing::info!(deleted = %deleted, "Error logs batch deleted");
+    AuditEvent::new("batch_delete", "error_log")
+        .before(&body)
+        .after(&BatchDeleteResponse { deleted })
+        .record(&state, &principal)
+        .await;
     Ok(Json(BatchDeleteResponse { deleted }))
 }
 
+// ============================================================================
+// Guardrail Rule API
+// ============================================================================
+
+/// Guardrail rule response
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "id": 1,
+    "name": "no-secrets",
+    "kind": "secrets",
+    "patterns": ["\\bacme_[0-9a-f]{32}\\b"],
+    "action": "block",
+    "direction": "both",
+    "is_enabled": true,
+    "created_at": "2024-01-01T00:00:00Z",
+    "updated_at": "2024-01-01T00:00:00Z"
+}))]
+pub struct GuardrailRuleResponse {
+    /// Auto-increment rule ID
+    pub id: i32,
+    /// Unique name, referenced from credentials' `guardrails`
+    pub name: String,
+    /// What the rule detects
+    pub kind: GuardrailKind,
+    /// Deny phrases, regexes, or extra secret formats depending on `kind`
+    pub patterns: Vec<String>,
+    /// What happens when the rule matches
+    pub action: GuardrailAction,
+    /// Whether prompts, completions or both are scanned
+    pub direction: GuardrailDirection,
+    /// Whether this rule is enabled
+    pub is_enabled: bool,
+    /// Creation timestamp (RFC 3339 format)
+    pub created_at: String,
+    /// Last update timestamp (RFC 3339 format)
+    pub updated_at: String,
+}
+
+impl GuardrailRuleResponse {
+    fn from_entity(e: GuardrailRuleEntity) -> Result<Self, AdminError> {
+        let rule = e.to_rule().ok_or_else(|| {
+            AdminError::Internal(format!("Guardrail rule '{}' has invalid settings", e.name))
+        })?;
+        Ok(Self {
+            id: e.id,
+            name: rule.name,
+            kind: rule.kind,
+            patterns: rule.patterns,
+            action: rule.action,
+            direction: rule.direction,
+            is_enabled: rule.enabled,
+            created_at: e.created_at.to_rfc3339(),
+            updated_at: e.updated_at.to_rfc3339(),
+        })
+    }
+
+    fn to_rule(&self) -> GuardrailRule {
+        GuardrailRule {
+            name: self.name.clone(),
+            kind: self.kind,
+            patterns: self.patterns.clone(),
+            action: self.action,
+            direction: self.direction,
+            enabled: self.is_enabled,
+        }
+    }
+}
+
+/// List of guardrail rules
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GuardrailRuleListResponse {
+    pub rules: Vec<GuardrailRuleResponse>,
+}
+
+/// Request to create a guardrail rule
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "name": "no-codenames",
+    "kind": "deny_phrases",
+    "patterns": ["project falcon", "blue harbor"],
+    "action": "redact",
+    "direction": "both"
+}))]
+pub struct CreateGuardrailRuleRequest {
+    /// Unique name (letters, digits, '_', '.' and '-'; at most 64)
+    pub name: String,
+    /// What the rule detects
+    pub kind: GuardrailKind,
+    /// Deny phrases, regexes, or extra secret formats depending on `kind`
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// What happens when the rule matches (default: block)
+    #[serde(default)]
+    pub action: GuardrailAction,
+    /// Whether prompts, completions or both are scanned (default: both)
+    #[serde(default)]
+    pub direction: GuardrailDirection,
+    /// Whether this rule is enabled (default: true)
+    #[serde(default = "default_true")]
+    pub is_enabled: bool,
+}
+
+/// Request to update a guardrail rule
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "action": "log",
+    "is_enabled": false
+}))]
+pub struct UpdateGuardrailRuleRequest {
+    /// New unique name (rejected while credentials reference the rule)
+    pub name: Option<String>,
+    /// What the rule detects
+    pub kind: Option<GuardrailKind>,
+    /// Deny phrases, regexes, or extra secret formats depending on `kind`
+    pub patterns: Option<Vec<String>>,
+    /// What happens when the rule matches
+    pub action: Option<GuardrailAction>,
+    /// Whether prompts, completions or both are scanned
+    pub direction: Option<GuardrailDirection>,
+    /// Whether this rule is enabled
+    pub is_enabled: Option<bool>,
+}
+
+/// List guardrail rules
+///
+/// Returns all guardrail rules, including disabled ones.
+#[utoipa::path(
+    get,
+    path = "/admin/v1/guardrails",
+    tag = "guardrails",
+    responses(
+        (status = 200, description = "List of guardrail rules", body = GuardrailRuleListResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse)
+    )
+)]
+pub async fn list_guardrail_rules(
+    State(state): State<Arc<AdminState>>,
+) -> Result<Json<GuardrailRuleListResponse>, AdminError> {
+    let rules = state
+        .dynamic_config
+        .database()
+        .list_guardrail_rules()
+        .await?;
+
+    Ok(Json(GuardrailRuleListResponse {
+        rules: rules
+            .into_iter()
+            .map(GuardrailRuleResponse::from_entity)
+            .collect::<Result<_, _>>()?,
+    }))
+}
+
+/// Get a single guardrail rule
+#[utoipa::path(
+    get,
+    path = "/admin/v1/guardrails/{id}",
+    tag = "guardrails",
+    params(
+        ("id" = i32, Path, description = "Guardrail rule ID")
+    ),
+    responses(
+        (status = 200, description = "Guardrail rule details", body = GuardrailRuleResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 404, description = "Guardrail rule not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn get_guardrail_rule(
+    State(state): State<Arc<AdminState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<GuardrailRuleResponse>, AdminError> {
+    let rule = state
+        .dynamic_config
+        .database()
+        .get_guardrail_rule(id)
+        .await?
+        .ok_or_else(|| AdminError::NotFound(format!("Guardrail rule with ID {} not found", id)))?;
+
+    Ok(Json(GuardrailRuleResponse::from_entity(rule)?))
+}
+
+/// Create a guardrail rule
+///
+/// Creates a rule that credentials can reference by name in `guardrails`.
+/// Patterns are validated before the rule is stored.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/guardrails",
+    tag = "guardrails",
+    request_body = CreateGuardrailRuleRequest,
+    responses(
+        (status = 201, description = "Guardrail rule created", body = GuardrailRuleResponse),
+        (status = 400, description = "Bad request", body = AdminErrorResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse)
+    )
+)]
+pub async fn create_guardrail_rule(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+    Json(req): Json<CreateGuardrailRuleRequest>,
+) -> Result<(StatusCode, Json<GuardrailRuleResponse>), AdminError> {
+    let rule = GuardrailRule {
+        name: req.name,
+        kind: req.kind,
+        patterns: req.patterns,
+        action: req.action,
+        direction: req.direction,
+        enabled: req.is_enabled,
+    };
+    validate_rule(&rule).map_err(AdminError::BadRequest)?;
+
+    let db = state.dynamic_config.database();
+    if db
+        .list_guardrail_rules()
+        .await?
+        .iter()
+        .any(|existing| existing.name == rule.name)
+    {
+        return Err(AdminError::BadRequest(format!(
+            "Guardrail rule '{}' already exists",
+            rule.name
+        )));
+    }
+
+    let entity = db.create_guardrail_rule(&rule).await?;
+    tracing::info!(guardrail_rule_id = %entity.id, name = %entity.name, "Guardrail rule created");
+
+    let response = GuardrailRuleResponse::from_entity(entity)?;
+    AuditEvent::new("create", "guardrail_rule")
+        .resource(response.id)
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Update a guardrail rule
+///
+/// Only provided fields are updated. Changes apply to new requests once the
+/// configuration version is picked up.
+#[utoipa::path(
+    put,
+    path = "/admin/v1/guardrails/{id}",
+    tag = "guardrails",
+    params(
+        ("id" = i32, Path, description = "Guardrail rule ID")
+    ),
+    request_body = UpdateGuardrailRuleRequest,
+    responses(
+        (status = 200, description = "Guardrail rule updated", body = GuardrailRuleResponse),
+        (status = 400, description = "Bad request", body = AdminErrorResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 404, description = "Guardrail rule not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn update_guardrail_rule(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+    Path(id): Path<i32>,
+    Json(req): Json<UpdateGuardrailRuleRequest>,
+) -> Result<Json<GuardrailRuleResponse>, AdminError> {
+    let db = state.dynamic_config.database();
+    let before = db
+        .get_guardrail_rule(id)
+        .await?
+        .ok_or_else(|| AdminError::NotFound(format!("Guardrail rule with ID {} not found", id)))
+        .and_then(GuardrailRuleResponse::from_entity)?;
+
+    let mut rule = before.to_rule();
+    if let Some(name) = req.name.filter(|name| *name != before.name) {
+        let rules = db.list_guardrail_rules().await?;
+        if rules.iter().any(|existing| existing.name == name) {
+            return Err(AdminError::BadRequest(format!(
+                "Guardrail rule '{}' already exists",
+                name
+            )));
+        }
+        let credentials = db.credentials_using_guardrail(&before.name).await?;
+        if !credentials.is_empty() {
+            return Err(AdminError::BadRequest(format!(
+                "Guardrail rule '{}' cannot be renamed while used by credentials: {}",
+                before.name,
+                credentials.join(", ")
+            )));
+        }
+        rule.name = name;
+    }
+    rule.kind = req.kind.unwrap_or(rule.kind);
+    rule.patterns = req.patterns.unwrap_or(rule.patterns);
+    rule.action = req.action.unwrap_or(rule.action);
+    rule.direction = req.direction.unwrap_or(rule.direction);
+    rule.enabled = req.is_enabled.unwrap_or(rule.enabled);
+    validate_rule(&rule).map_err(AdminError::BadRequest)?;
+
+    let entity = db
+        .update_guardrail_rule(id, &rule)
+        .await?
+        .ok_or_else(|| AdminError::NotFound(format!("Guardrail rule with ID {} not found", id)))?;
+    tracing::info!(guardrail_rule_id = %id, "Guardrail rule updated");
+
+    let response = GuardrailRuleResponse::from_entity(entity)?;
+    AuditEvent::new("update", "guardrail_rule")
+        .resource(id)
+        .before(&before)
+        .after(&response)
+        .record(&state, &principal)
+        .await;
+
+    Ok(Json(response))
+}
+
+/// Delete a guardrail rule
+///
+/// Rules still referenced by credentials cannot be deleted.
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/guardrails/{id}",
+    tag = "guardrails",
+    params(
+        ("id" = i32, Path, description = "Guardrail rule ID")
+    ),
+    responses(
+        (status = 204, description = "Guardrail rule deleted"),
+        (status = 400, description = "Rule still used by credentials", body = AdminErrorResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 404, description = "Guardrail rule not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn delete_guardrail_rule(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AdminError> {
+    let db = state.dynamic_config.database();
+    let before = db
+        .get_guardrail_rule(id)
+        .await?
+        .ok_or_else(|| AdminError::NotFound(format!("Guardrail rule with ID {} not found", id)))
+        .and_then(GuardrailRuleResponse::from_entity)?;
+
+    let credentials = db.credentials_using_guardrail(&before.name).await?;
+    if !credentials.is_empty() {
+        return Err(AdminError::BadRequest(format!(
+            "Guardrail rule '{}' is used by credentials: {}",
+            before.name,
+            credentials.join(", ")
+        )));
+    }
+
+    if !db.delete_guardrail_rule(id).await? {
+        return Err(AdminError::NotFound(format!(
+            "Guardrail rule with ID {} not found",
+            id
+        )));
+    }
+
+    tracing::info!(guardrail_rule_id = %id, "Guardrail rule deleted");
+
+    AuditEvent::new("delete", "guardrail_rule")
+        .resource(id)
+        .before(&before)
+        .record(&state, &principal)
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// Admin Token API
+// ============================================================================
+
+/// Admin token response (the token value itself is only returned on creation)
+#[derive(Debug, Serialize, ToSchema)]
+#[schema(example = json!({
+    "id": 1,
+    "name": "ci-deployer",
+    "token_preview": "adm***f3a",
+    "role": "operator",
+    "expires_at": "2025-01-01T00:00:00Z",
+    "is_enabled": true,
+    "created_by": "bootstrap",
+    "created_at": "2024-01-01T00:00:00Z"
+}))]
+pub struct AdminTokenResponse {
+    /// Auto-increment admin token ID
+    pub id: i32,
+    /// Human-readable name, recorded as the actor in audit records
+    pub name: String,
+    /// Masked preview of the token
+    pub token_preview: String,
+    /// Role granted by the token
+    pub role: String,
+    /// Expiry timestamp (RFC 3339 format, null = never expires)
+    pub expires_at: Option<String>,
+    /// Whether this token is enabled
+    pub is_enabled: bool,
+    /// Admin who created the token
+    pub created_by: Option<String>,
+    /// Creation timestamp (RFC 3339 format)
+    pub created_at: String,
+}
+
+impl From<AdminTokenEntity> for AdminTokenResponse {
+    fn from(e: AdminTokenEntity) -> Self {
+        Self {
+            id: e.id,
+            name: e.name,
+            token_preview: e.token_preview,
+            role: e.role,
+            expires_at: e.expires_at.map(|t| t.to_rfc3339()),
+            is_enabled: e.is_enabled,
+            created_by: e.created_by,
+            created_at: e.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// List of admin tokens
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminTokenListResponse {
+    pub tokens: Vec<AdminTokenResponse>,
+}
+
+/// Request to create an admin token
+#[derive(Debug, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "name": "ci-deployer",
+    "role": "operator",
+    "expires_at": "2025-01-01T00:00:00Z"
+}))]
+pub struct CreateAdminTokenRequest {
+    /// Human-readable name, recorded as the actor in audit records
+    pub name: String,
+    /// Role granted by the token
+    pub role: AdminRole,
+    /// Expiry timestamp (RFC 3339 format, omit for a token that never expires)
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Newly created admin token
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedAdminTokenResponse {
+    /// The token value; it is stored hashed and cannot be retrieved again
+    pub token: String,
+    pub admin_token: AdminTokenResponse,
+}
+
+fn generate_admin_token() -> String {
+    format!("adm-{}", hex::encode(rand::random::<[u8; 32]>()))
+}
+
+/// List admin tokens
+///
+/// Returns all admin tokens. Token values are never returned.
+#[utoipa::path(
+    get,
+    path = "/admin/v1/admin-tokens",
+    tag = "admin-tokens",
+    responses(
+        (status = 200, description = "List of admin tokens", body = AdminTokenListResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 403, description = "Forbidden", body = AdminErrorResponse)
+    )
+)]
+pub async fn list_admin_tokens(
+    State(state): State<Arc<AdminState>>,
+) -> Result<Json<AdminTokenListResponse>, AdminError> {
+    let tokens = state.dynamic_config.database().list_admin_tokens().await?;
+
+    Ok(Json(AdminTokenListResponse {
+        tokens: tokens.into_iter().map(Into::into).collect(),
+    }))
+}
+
+/// Create an admin token
+///
+/// Creates a token with the given role. The token value is returned once in
+/// the response and stored hashed.
+#[utoipa::path(
+    post,
+    path = "/admin/v1/admin-tokens",
+    tag = "admin-tokens",
+    request_body = CreateAdminTokenRequest,
+    responses(
+        (status = 201, description = "Admin token created", body = CreatedAdminTokenResponse),
+        (status = 400, description = "Bad request", body = AdminErrorResponse),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 403, description = "Forbidden", body = AdminErrorResponse)
+    )
+)]
+pub async fn create_admin_token(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+    Json(req): Json<CreateAdminTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedAdminTokenResponse>), AdminError> {
+    if req.name.trim().is_empty() {
+        return Err(AdminError::BadRequest(
+            "Admin token name is required".to_string(),
+        ));
+    }
+    if req.expires_at.is_some_and(|t| t <= chrono::Utc::now()) {
+        return Err(AdminError::BadRequest(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    let token = generate_admin_token();
+    let create = CreateAdminToken {
+        name: req.name,
+        token: token.clone(),
+        role: req.role.to_string(),
+        expires_at: req.expires_at,
+        created_by: Some(principal.name.clone()),
+    };
+
+    let entity = state
+        .dynamic_config
+        .database()
+        .create_admin_token(&create)
+        .await?;
+    tracing::info!(admin_token_id = %entity.id, name = %entity.name, role = %entity.role, "Admin token created");
+
+    let admin_token = AdminTokenResponse::from(entity);
+    AuditEvent::new("create", "admin_token")
+        .resource(admin_token.id)
+        .after(&admin_token)
+        .record(&state, &principal)
+        .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedAdminTokenResponse { token, admin_token }),
+    ))
+}
+
+/// Delete an admin token
+///
+/// Revokes an admin token immediately.
+#[utoipa::path(
+    delete,
+    path = "/admin/v1/admin-tokens/{id}",
+    tag = "admin-tokens",
+    params(
+        ("id" = i32, Path, description = "Admin token ID")
+    ),
+    responses(
+        (status = 204, description = "Admin token deleted"),
+        (status = 401, description = "Unauthorized", body = AdminErrorResponse),
+        (status = 403, description = "Forbidden", body = AdminErrorResponse),
+        (status = 404, description = "Admin token not found", body = AdminErrorResponse)
+    )
+)]
+pub async fn delete_admin_token(
+    State(state): State<Arc<AdminState>>,
+    Extension(principal): Extension<AdminPrincipal>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AdminError> {
+    let db = state.dynamic_config.database();
+    let before = db
+        .get_admin_token(id)
+        .await?
+        .map(AdminTokenResponse::from)
+        .ok_or_else(|| AdminError::NotFound(format!("Admin token with ID {} not found", id)))?;
+
+    if !db.delete_admin_token(id).await? {
+        return Err(AdminError::NotFound(format!(
+            "Admin token with ID {} not found",
+            id
+        )));
+    }
+
+    tracing::info!(admin_token_id = %id, "Admin token deleted");
+
+    AuditEvent::new("delete", "admin_token")
+        .resource(id)
+        .before(&before)
+        .record(&state, &principal)
+        .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================================
+// Admin Audit Log API
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct AdminAuditLogItem {
+    pub id: i64,
+    pub created_at: String,
+    pub actor: String,
+    pub actor_role: String,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl From<AdminAuditLogEntity> for AdminAuditLogItem {
+    fn from(e: AdminAuditLogEntity) -> Self {
+        Self {
+            id: e.id,
+            created_at: e.created_at.to_rfc3339(),
+            actor: e.actor,
+            actor_role: e.actor_role,
+            action: e.action,
+            resource_type: e.resource_type,
+            resource_id: e.resource_id,
+            before: e.before.map(|v| v.0),
+            after: e.after.map(|v| v.0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminAuditLogListResponse {
+    pub items: Vec<AdminAuditLogItem>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+    pub total_pages: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminAuditLogQueryParams {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    pub actor: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    pub action: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+}
+
+pub async fn list_admin_audit_logs(
+    State(state): State<Arc<AdminState>>,
+    Query(params): Query<AdminAuditLogQueryParams>,
+) -> Result<Json<AdminAuditLogListResponse>, AdminError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(50).clamp(1, 200);
+    let offset = (page - 1) * page_size;
+
+    let filter = AdminAuditLogFilter {
+        actor: params.actor,
+        resource_type: params.resource_type,
+        resource_id: params.resource_id,
+        action: params.action,
+        start_time: params.start_time.as_deref().and_then(parse_iso_time),
+        end_time: params.end_time.as_deref().and_then(parse_iso_time),
+    };
+
+    let (items, total) = state
+        .dynamic_config
+        .database()
+        .list_admin_audit_logs(&filter, page_size, offset)
+        .await?;
+
+    let total_pages = if total == 0 {
+        1
+    } else {
+        (total + page_size - 1) / page_size
+    };
+
+    Ok(Json(AdminAuditLogListResponse {
+        items: items.into_iter().map(Into::into).collect(),
+        total,
+        page,
+        page_size,
+        total_pages,
+    }))
+}
+
 // ============================================================================
 // Router
 // ============================================================================
 
 /// Create Admin API router
+///
+/// Each route is guarded with the minimum [`AdminRole`] it requires:
+/// viewers can read, operators can additionally reload, run health checks
+/// and clean up logs, and admins can change providers, credentials,
+/// guardrail rules and admin tokens.
 pub fn admin_router(state: Arc<AdminState>) -> Router {
     use crate::api::health::{health_router, provider_health_router};
 
+    let viewer = |route| require_role(route, &state, AdminRole::Viewer);
+    let operator = |route| require_role(route, &state, AdminRole::Operator);
+    let admin = |route| require_role(route, &state, AdminRole::Admin);
+
     Router::new()
         // Auth routes
         .route("/auth/validate", post(validate_admin_key))
         // Provider routes
-        .route("/providers", get(list_providers).post(create_provider))
+        .route(
+            "/providers",
+            viewer(get(list_providers)).merge(admin(post(create_provider))),
+        )
         .route(
             "/providers/:id",
-            get(get_provider)
-                .put(update_provider)
-                .delete(delete_provider),
+            viewer(get(get_provider)).merge(admin(put(update_provider).delete(delete_provider))),
         )
+        .route("/providers/reencrypt", admin(post(reencrypt_provider_keys)))
         // Provider health check route (POST /admin/v1/providers/{id}/health)
-        .nest("/providers", provider_health_router())
+        .nest("/providers", provider_health_router(&state))
         // Credential routes
         .route(
             "/credentials",
-            get(list_credentials).post(create_credential),
+            viewer(get(list_credentials)).merge(admin(post(create_credential))),
         )
+        .route(
+            "/credentials/report",
+            viewer(get(credential_lifecycle_report)),
+        )
+        .route("/credentials/:id/rotate", admin(post(rotate_credential)))
         .route(
             "/credentials/:id",
-            get(get_credential)
-                .put(update_credential)
-                .delete(delete_credential),
+            viewer(get(get_credential))
+                .merge(admin(put(update_credential).delete(delete_credential))),
+        )
+        // Guardrail rule routes
+        .route(
+            "/guardrails",
+            viewer(get(list_guardrail_rules)).merge(admin(post(create_guardrail_rule))),
+        )
+        .route(
+            "/guardrails/:id",
+            viewer(get(get_guardrail_rule)).merge(admin(
+                put(update_guardrail_rule).delete(delete_guardrail_rule),
+            )),
         )
         // Config routes
-        .route("/config/version", get(get_config_version))
-        .route("/config/reload", post(reload_config))
+        .route("/config/version", viewer(get(get_config_version)))
+        .route("/config/reload", operator(post(reload_config)))
+        .route("/config/history", viewer(get(list_config_history)))
+        .route("/config/history/diff", viewer(get(diff_config_versions)))
+        .route("/config/history/:version", viewer(get(get_config_snapshot)))
+        .route(
+            "/config/history/:version/rollback",
+            admin(post(rollback_config)),
+        )
+        // Tokenizer routes
+        .route("/tokenizers", viewer(get(list_tokenizers)))
+        .route("/tokenizers/reload", operator(post(reload_tokenizers)))
         // Health check routes
-        .nest("/health", health_router())
+        .nest("/health", health_router(&state))
         // Request logs routes (stats and batch-delete before :id to avoid path conflict)
-        .route("/logs", get(list_logs))
-        .route("/logs/stats", get(get_log_stats))
-        .route("/logs/batch-delete", post(batch_delete_logs))
-        .route("/logs/:id", get(get_log_detail).delete(delete_log))
+        .route("/logs", viewer(get(list_logs)))
+        .route("/logs/stats", viewer(get(get_log_stats)))
+        .route("/logs/batch-delete", operator(post(batch_delete_logs)))
+        .route(
+            "/logs/:id",
+            viewer(get(get_log_detail)).merge(operator(delete(delete_log))),
+        )
         // Error logs routes
-        .route("/error-logs", get(list_error_logs))
-        .route("/error-logs/batch-delete", post(batch_delete_error_logs))
+        .route("/error-logs", viewer(get(list_error_logs)))
+        .route(
+            "/error-logs/batch-delete",
+            operator(post(batch_delete_error_logs)),
+        )
         .route(
             "/error-logs/:id",
-            get(get_error_log_detail).delete(delete_error_log),
+            viewer(get(get_error_log_detail)).merge(operator(delete(delete_error_log))),
+        )
+        // Admin token and audit routes
+        .route(
+            "/admin-tokens",
+            admin(get(list_admin_tokens).post(create_admin_token)),
         )
+        .route("/admin-tokens/:id", admin(delete(delete_admin_token)))
+        .route("/audit-logs", admin(get(list_admin_audit_logs)))
         .with_state(state)
 }
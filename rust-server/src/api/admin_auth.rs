@@ -0,0 +1,356 @@
+//! Role-based authentication and audit trail for the Admin API.
+//!
+//! Admin callers authenticate with either the `ADMIN_KEY` environment
+//! variable, which acts as a bootstrap superuser, or an admin token stored
+//! hashed in the `admin_tokens` table. Every token carries a role, and
+//! `admin_router` guards each route with the minimum role it needs.
+//!
+//! Mutating handlers record who changed what, with before/after snapshots,
+//! in the `admin_audit_logs` table.
+
+use axum::{
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::{from_fn_with_state, Next},
+    response::Response,
+    routing::MethodRouter,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use utoipa::ToSchema;
+
+use crate::api::admin::{AdminError, AdminState};
+use crate::core::database::{hash_key, AdminTokenEntity, NewAdminAuditLog};
+
+/// Actor name recorded for calls authenticated with `ADMIN_KEY`.
+pub const BOOTSTRAP_ACTOR: &str = "bootstrap";
+
+/// Admin role, ordered from least to most privileged.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AdminRole {
+    /// Read-only access to configuration, logs and tokenizers
+    Viewer,
+    /// Viewer plus reloads, health checks and log cleanup
+    Operator,
+    /// Full access, including providers, credentials and admin tokens
+    Admin,
+}
+
+impl AdminRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdminRole::Viewer => "viewer",
+            AdminRole::Operator => "operator",
+            AdminRole::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for AdminRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AdminRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(AdminRole::Viewer),
+            "operator" => Ok(AdminRole::Operator),
+            "admin" => Ok(AdminRole::Admin),
+            other => Err(format!(
+                "Unknown admin role '{}', expected viewer, operator or admin",
+                other
+            )),
+        }
+    }
+}
+
+/// Authenticated caller of the Admin API.
+///
+/// Inserted into request extensions by the role guard, so handlers can take
+/// `Extension<AdminPrincipal>` to attribute audit records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminPrincipal {
+    /// Token name, or [`BOOTSTRAP_ACTOR`] for `ADMIN_KEY`
+    pub name: String,
+    pub role: AdminRole,
+    /// Admin token ID (None for `ADMIN_KEY`)
+    pub token_id: Option<i32>,
+}
+
+impl AdminPrincipal {
+    fn bootstrap() -> Self {
+        Self {
+            name: BOOTSTRAP_ACTOR.to_string(),
+            role: AdminRole::Admin,
+            token_id: None,
+        }
+    }
+
+    fn from_token(token: &AdminTokenEntity) -> Option<Self> {
+        Some(Self {
+            name: token.name.clone(),
+            role: token.role.parse().ok()?,
+            token_id: Some(token.id),
+        })
+    }
+
+    /// Whether this principal may call a route requiring `required`.
+    pub fn allows(&self, required: AdminRole) -> bool {
+        self.role >= required
+    }
+}
+
+/// Token from the `Authorization: Bearer` header, if present and non-empty.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .filter(|token| !token.is_empty())
+}
+
+/// Compare a token with the bootstrap key in constant time.
+fn is_admin_key(token: &str, admin_key: &str) -> bool {
+    token.as_bytes().ct_eq(admin_key.as_bytes()).into()
+}
+
+/// Resolve the caller from the `Authorization: Bearer` header.
+pub async fn authenticate(
+    state: &AdminState,
+    headers: &HeaderMap,
+) -> Result<AdminPrincipal, AdminError> {
+    let token = bearer_token(headers).ok_or(AdminError::Unauthorized)?;
+
+    if !state.admin_key.is_empty() && is_admin_key(token, &state.admin_key) {
+        return Ok(AdminPrincipal::bootstrap());
+    }
+
+    let entity = state
+        .dynamic_config
+        .database()
+        .get_admin_token_by_hash(&hash_key(token))
+        .await?
+        .filter(|t| t.is_active(chrono::Utc::now()))
+        .ok_or(AdminError::Unauthorized)?;
+
+    AdminPrincipal::from_token(&entity).ok_or(AdminError::Unauthorized)
+}
+
+#[derive(Clone)]
+struct RoleGuard {
+    state: Arc<AdminState>,
+    role: AdminRole,
+}
+
+async fn role_guard(
+    State(guard): State<RoleGuard>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AdminError> {
+    let principal = authenticate(&guard.state, request.headers()).await?;
+    if !principal.allows(guard.role) {
+        return Err(AdminError::Forbidden(format!(
+            "This operation requires the '{}' role",
+            guard.role
+        )));
+    }
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+/// Guard every method of `route` so only callers with at least `role` reach it.
+pub fn require_role<S>(
+    route: MethodRouter<S>,
+    state: &Arc<AdminState>,
+    role: AdminRole,
+) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    route.route_layer(from_fn_with_state(
+        RoleGuard {
+            state: state.clone(),
+            role,
+        },
+        role_guard,
+    ))
+}
+
+/// A mutating admin call, recorded in the audit trail.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    action: &'static str,
+    resource_type: &'static str,
+    resource_id: Option<String>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+impl AuditEvent {
+    pub fn new(action: &'static str, resource_type: &'static str) -> Self {
+        Self {
+            action,
+            resource_type,
+            resource_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn resource(mut self, id: impl ToString) -> Self {
+        self.resource_id = Some(id.to_string());
+        self
+    }
+
+    /// Snapshot of the resource before the call.
+    pub fn before(mut self, value: &impl Serialize) -> Self {
+        self.before = serde_json::to_value(value).ok();
+        self
+    }
+
+    /// Snapshot of the resource after the call.
+    pub fn after(mut self, value: &impl Serialize) -> Self {
+        self.after = serde_json::to_value(value).ok();
+        self
+    }
+
+    fn into_record(self, principal: &AdminPrincipal) -> NewAdminAuditLog {
+        NewAdminAuditLog {
+            actor: principal.name.clone(),
+            actor_role: principal.role.to_string(),
+            action: self.action.to_string(),
+            resource_type: self.resource_type.to_string(),
+            resource_id: self.resource_id,
+            before: self.before,
+            after: self.after,
+        }
+    }
+
+    /// Write the audit record.
+    ///
+    /// The change itself has already been applied, so a failure to record it
+    /// is logged rather than turned into an error response.
+    pub async fn record(self, state: &AdminState, principal: &AdminPrincipal) {
+        let record = self.into_record(principal);
+        if let Err(e) = state
+            .dynamic_config
+            .database()
+            .insert_admin_audit_log(&record)
+            .await
+        {
+            tracing::error!(
+                error = %e,
+                actor = %record.actor,
+                action = %record.action,
+                resource_type = %record.resource_type,
+                resource_id = ?record.resource_id,
+                "Failed to write admin audit record"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use chrono::{Duration, Utc};
+
+    fn token(role: &str) -> AdminTokenEntity {
+        AdminTokenEntity {
+            id: 7,
+            name: "ci-deployer".to_string(),
+            token_hash: hash_key("secret"),
+            token_preview: "sec***ret".to_string(),
+            role: role.to_string(),
+            expires_at: None,
+            is_enabled: true,
+            created_by: Some(BOOTSTRAP_ACTOR.to_string()),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_roles_are_ordered_by_privilege() {
+        let operator = AdminPrincipal::from_token(&token("operator")).unwrap();
+        assert!(operator.allows(AdminRole::Viewer));
+        assert!(operator.allows(AdminRole::Operator));
+        assert!(!operator.allows(AdminRole::Admin));
+        assert!(AdminPrincipal::bootstrap().allows(AdminRole::Admin));
+    }
+
+    #[test]
+    fn test_role_round_trips_through_str_and_serde() {
+        for role in [AdminRole::Viewer, AdminRole::Operator, AdminRole::Admin] {
+            assert_eq!(role.as_str().parse::<AdminRole>().unwrap(), role);
+            assert_eq!(
+                serde_json::to_value(role).unwrap(),
+                serde_json::json!(role.as_str())
+            );
+        }
+        assert!("root".parse::<AdminRole>().is_err());
+    }
+
+    #[test]
+    fn test_unknown_stored_role_does_not_authenticate() {
+        assert!(AdminPrincipal::from_token(&token("superuser")).is_none());
+    }
+
+    #[test]
+    fn test_token_activity() {
+        let now = Utc::now();
+        let mut t = token("viewer");
+        assert!(t.is_active(now));
+        t.expires_at = Some(now - Duration::seconds(1));
+        assert!(!t.is_active(now));
+        t.expires_at = Some(now + Duration::hours(1));
+        assert!(t.is_active(now));
+        t.is_enabled = false;
+        assert!(!t.is_active(now));
+    }
+
+    #[test]
+    fn test_bearer_token_parsing() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert("authorization", HeaderValue::from_static("Basic abc"));
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert("authorization", HeaderValue::from_static("Bearer "));
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert("authorization", HeaderValue::from_static("Bearer abc"));
+        assert_eq!(bearer_token(&headers), Some("abc"));
+    }
+
+    #[test]
+    fn test_admin_key_comparison() {
+        assert!(is_admin_key("secret-key", "secret-key"));
+        assert!(!is_admin_key("secret-kez", "secret-key"));
+        assert!(!is_admin_key("secret", "secret-key"));
+        assert!(!is_admin_key("secret-key-2", "secret-key"));
+    }
+
+    #[test]
+    fn test_audit_record_attributes_principal() {
+        let principal = AdminPrincipal::from_token(&token("admin")).unwrap();
+        let record = AuditEvent::new("update", "provider")
+            .resource(3)
+            .before(&serde_json::json!({"weight": 1}))
+            .after(&serde_json::json!({"weight": 5}))
+            .into_record(&principal);
+        assert_eq!(record.actor, "ci-deployer");
+        assert_eq!(record.actor_role, "admin");
+        assert_eq!(record.resource_id.as_deref(), Some("3"));
+        assert_eq!(record.before, Some(serde_json::json!({"weight": 1})));
+        assert_eq!(record.after, Some(serde_json::json!({"weight": 5})));
+    }
+}
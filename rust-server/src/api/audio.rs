@@ -0,0 +1,757 @@
+//! OpenAI-compatible audio endpoints.
+//!
+//! `/v1/audio/transcriptions` and `/v1/audio/translations` accept multipart
+//! uploads; `/v1/audio/speech` accepts JSON and streams the binary audio body
+//! back to the client. Requests go through the usual auth, model mapping and
+//! provider selection, and only OpenAI-type providers are eligible.
+//!
+//! Audio duration is the usage unit: it is taken from the provider response
+//! when reported (`usage.seconds` / verbose `duration`), otherwise estimated
+//! from the audio container (WAV, MP3, FLAC, raw PCM). It is written to
+//! `request_logs.audio_duration_ms` and `llm_proxy_audio_duration_seconds_total`.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Multipart, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+    Extension, Json,
+};
+use futures::StreamExt;
+use serde_json::{json, Map, Value};
+
+use crate::api::auth::VerifiedJwt;
+use crate::api::media::{elapsed_ms, MediaRequest, MediaRoute};
+use crate::api::multipart::MultipartForm;
+use crate::api::proxy::ProxyState;
+use crate::api::upstream::{
+    attach_response_extensions, build_upstream_request, record_token_metrics,
+    StatusErrorResponseMode, UpstreamAuth,
+};
+use crate::core::config::GuardrailDirection;
+use crate::core::error_types::{
+    ERROR_CATEGORY_INTERNAL_ERROR, ERROR_TYPE_API, ERROR_TYPE_INVALID_REQUEST,
+};
+use crate::core::jsonl_logger::log_request;
+use crate::core::logging::PROVIDER_CONTEXT;
+use crate::core::metrics::get_metrics;
+use crate::core::request_logger::{log_request_record, RequestLogRecord};
+use crate::core::{AppError, Result};
+use crate::transformer::Protocol;
+use crate::with_request_context;
+
+/// Body limit for audio uploads (Whisper accepts files up to 25MB).
+pub const AUDIO_UPLOAD_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// Bytes of the speech stream kept for container header parsing.
+const AUDIO_HEADER_CAPTURE_BYTES: usize = 4096;
+
+/// Audio endpoint request body.
+#[derive(Debug, Clone)]
+pub enum AudioRequest {
+    /// Multipart upload (transcriptions / translations)
+    Upload(MultipartForm),
+    /// JSON text-to-speech request
+    Speech(Map<String, Value>),
+}
+
+impl AudioRequest {
+    fn fields(&self) -> &Map<String, Value> {
+        match self {
+            AudioRequest::Upload(form) => &form.fields,
+            AudioRequest::Speech(body) => body,
+        }
+    }
+
+    pub fn model(&self) -> Option<&str> {
+        self.fields().get("model").and_then(|v| v.as_str())
+    }
+
+    fn log_body(&self) -> Value {
+        match self {
+            AudioRequest::Upload(form) => form.log_body(),
+            AudioRequest::Speech(body) => Value::Object(body.clone()),
+        }
+    }
+}
+
+// ============================================================================
+// Endpoint Handlers
+// ============================================================================
+
+/// OpenAI-compatible transcription endpoint (multipart form data)
+pub async fn audio_transcriptions(
+    State(state): State<Arc<ProxyState>>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<Response> {
+    let form = MultipartForm::read(multipart).await?;
+    handle_audio_request(
+        state,
+        headers,
+        None,
+        "/v1/audio/transcriptions",
+        AudioRequest::Upload(form),
+    )
+    .await
+}
+
+/// OpenAI-compatible translation endpoint (multipart form data)
+pub async fn audio_translations(
+    State(state): State<Arc<ProxyState>>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<Response> {
+    let form = MultipartForm::read(multipart).await?;
+    handle_audio_request(
+        state,
+        headers,
+        None,
+        "/v1/audio/translations",
+        AudioRequest::Upload(form),
+    )
+    .await
+}
+
+/// OpenAI-compatible text-to-speech endpoint (binary audio response)
+pub async fn audio_speech(
+    State(state): State<Arc<ProxyState>>,
+    headers: HeaderMap,
+    verified_jwt: Option<Extension<VerifiedJwt>>,
+    Json(payload): Json<Value>,
+) -> Result<Response> {
+    let Value::Object(body) = payload else {
+        return Err(AppError::BadRequest(
+            "Request body must be a JSON object".to_string(),
+        ));
+    };
+    handle_audio_request(
+        state,
+        headers,
+        verified_jwt.as_ref().map(|Extension(jwt)| jwt),
+        "/v1/audio/speech",
+        AudioRequest::Speech(body),
+    )
+    .await
+}
+
+/// Shared handler for all audio endpoints.
+async fn handle_audio_request(
+    state: Arc<ProxyState>,
+    headers: HeaderMap,
+    verified_jwt: Option<&VerifiedJwt>,
+    path: &'static str,
+    mut request: AudioRequest,
+) -> Result<Response> {
+    let media = MediaRequest::authorize(
+        &state,
+        headers,
+        verified_jwt,
+        path,
+        "Audio",
+        request.model(),
+    )?;
+
+    with_request_context!(
+        media.request_id.clone(),
+        media.api_key_name.clone(),
+        async move {
+            let Some(original_model) = request.model().map(|m| m.to_string()) else {
+                return Err(AppError::BadRequest("model is required".to_string()));
+            };
+            if let AudioRequest::Upload(form) = &request {
+                if form.upload("file").is_none() {
+                    return Err(AppError::BadRequest("file is required".to_string()));
+                }
+            }
+            let route = match media.route(&state, &original_model, request.log_body()) {
+                Ok(route) => route,
+                Err(response) => return Ok(*response),
+            };
+            let provider = &route.provider;
+
+            if route.provider_protocol != Protocol::OpenAI {
+                let message = format!(
+                    "Provider '{}' ({}) does not support audio endpoints",
+                    provider.name, provider.provider_type
+                );
+                return Ok(route.fail(
+                    StatusCode::BAD_REQUEST,
+                    ERROR_TYPE_INVALID_REQUEST,
+                    "invalid_request",
+                    message,
+                ));
+            }
+
+            // Placeholders would be read out in the generated speech
+            let (fields, text_field, allow_placeholders) = match &mut request {
+                AudioRequest::Upload(form) => (&mut form.fields, "prompt", true),
+                AudioRequest::Speech(body) => (body, "input", false),
+            };
+            if let Err(response) = route
+                .check_guardrails(fields, &[text_field], GuardrailDirection::Input)
+                .and_then(|_| route.redact_fields(fields, &[text_field], allow_placeholders))
+            {
+                return Ok(*response);
+            }
+
+            log_request(route.request_id(), path, &provider.name, &route.log_body);
+
+            PROVIDER_CONTEXT
+                .scope(provider.name.clone(), async move {
+                    let provider = &route.provider;
+                    let url = format!("{}{}", provider.api_base, path.trim_start_matches("/v1"));
+                    let http_client = match state.app_state.provider_http_client(&provider.name) {
+                        Ok(client) => client,
+                        Err(err) => {
+                            return Ok(route.fail(
+                                StatusCode::INTERNAL_SERVER_ERROR,
+                                ERROR_TYPE_API,
+                                ERROR_CATEGORY_INTERNAL_ERROR,
+                                err.to_string(),
+                            ));
+                        }
+                    };
+                    let upstream_request = match &request {
+                        AudioRequest::Upload(form) => {
+                            match form.to_reqwest_form(&route.mapped_model) {
+                                Ok(multipart) => http_client
+                                    .post(&url)
+                                    .bearer_auth(&provider.api_key)
+                                    .multipart(multipart),
+                                Err(message) => {
+                                    return Ok(route.fail(
+                                        StatusCode::BAD_REQUEST,
+                                        ERROR_TYPE_INVALID_REQUEST,
+                                        "invalid_request",
+                                        message,
+                                    ));
+                                }
+                            }
+                        }
+                        AudioRequest::Speech(body) => {
+                            let mut payload = body.clone();
+                            payload.insert("model".to_string(), json!(route.mapped_model));
+                            build_upstream_request(
+                                &http_client,
+                                &url,
+                                &Value::Object(payload),
+                                UpstreamAuth::Bearer(&provider.api_key),
+                                None,
+                                None,
+                            )
+                        }
+                    };
+
+                    let response = match route
+                        .send(upstream_request, &url, StatusErrorResponseMode::Passthrough)
+                        .await
+                    {
+                        Ok(resp) => resp,
+                        Err(error_response) => return Ok(error_response),
+                    };
+                    let status = response.status();
+
+                    let content_type = response
+                        .headers()
+                        .get(reqwest::header::CONTENT_TYPE)
+                        .and_then(|v| HeaderValue::from_bytes(v.as_bytes()).ok());
+                    let labels = AudioMetricLabels {
+                        model: route.effective_model.clone(),
+                        provider: provider.name.clone(),
+                        endpoint: path.to_string(),
+                        api_key_name: route.api_key_name().to_string(),
+                        client: route.client.clone(),
+                    };
+
+                    match request {
+                        AudioRequest::Speech(body) => {
+                            let format = body
+                                .get("response_format")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("mp3")
+                                .to_string();
+                            let recorder = SpeechStreamRecorder {
+                                record: Some(RequestLogRecord {
+                                    status_code: Some(status.as_u16() as i32),
+                                    is_streaming: true,
+                                    ..route.record()
+                                }),
+                                labels,
+                                format,
+                                header: Vec::new(),
+                                total_bytes: 0,
+                                completed: false,
+                                error: None,
+                                start: route.start(),
+                            };
+                            Ok(build_speech_response(response, recorder, content_type))
+                        }
+                        AudioRequest::Upload(form) => {
+                            let body = match response.bytes().await {
+                                Ok(body) => body,
+                                Err(e) => {
+                                    return Ok(route.fail(
+                                        StatusCode::BAD_GATEWAY,
+                                        ERROR_TYPE_API,
+                                        "transport",
+                                        format!("Failed to read provider response: {}", e),
+                                    ));
+                                }
+                            };
+
+                            let body = match check_transcript(&route, body) {
+                                Ok(body) => body,
+                                Err(response) => return Ok(*response),
+                            };
+
+                            let parsed: Option<Value> = serde_json::from_slice(&body).ok();
+                            let duration_secs = parsed
+                                .as_ref()
+                                .and_then(reported_audio_duration_secs)
+                                .or_else(|| {
+                                    form.upload("file").and_then(|file| {
+                                        estimate_audio_duration_secs(
+                                            &file.data,
+                                            file.data.len(),
+                                            None,
+                                        )
+                                    })
+                                });
+                            let (input_tokens, output_tokens) = parsed
+                                .as_ref()
+                                .map(transcription_usage_tokens)
+                                .unwrap_or((0, 0));
+                            if input_tokens > 0 || output_tokens > 0 {
+                                record_token_metrics(
+                                    input_tokens as u64,
+                                    output_tokens as u64,
+                                    &labels.model,
+                                    &labels.provider,
+                                    &labels.api_key_name,
+                                    &labels.client,
+                                );
+                            }
+                            labels.record_duration(duration_secs);
+
+                            log_request_record(RequestLogRecord {
+                                status_code: Some(status.as_u16() as i32),
+                                input_tokens,
+                                output_tokens,
+                                total_tokens: input_tokens + output_tokens,
+                                audio_duration_ms: duration_secs.map(secs_to_ms),
+                                ..route.record()
+                            });
+
+                            let mut client_response = Response::new(Body::from(body));
+                            let content_type = content_type
+                                .unwrap_or_else(|| HeaderValue::from_static("application/json"));
+                            client_response
+                                .headers_mut()
+                                .insert(header::CONTENT_TYPE, content_type);
+                            attach_response_extensions(
+                                &mut client_response,
+                                Some(&route.effective_model),
+                                Some(&provider.name),
+                                Some(route.api_key_name()),
+                            );
+                            Ok(client_response)
+                        }
+                    }
+                })
+                .await
+        }
+    )
+}
+
+/// Apply output guardrails to a transcript: the text fields of JSON
+/// responses, or the whole body of the text formats (text, srt, vtt).
+fn check_transcript(route: &MediaRoute, body: Bytes) -> std::result::Result<Bytes, Box<Response>> {
+    if !route.has_guardrails() {
+        return Ok(body);
+    }
+    let names = ["text", "segments", "words"];
+    match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Object(mut fields)) => {
+            route.check_guardrails(&mut fields, &names, GuardrailDirection::Output)?;
+            Ok(serde_json::to_vec(&fields).map_or(body, Bytes::from))
+        }
+        _ => {
+            let mut fields = Map::new();
+            fields.insert(
+                "text".to_string(),
+                Value::String(String::from_utf8_lossy(&body).into_owned()),
+            );
+            route.check_guardrails(&mut fields, &names, GuardrailDirection::Output)?;
+            match fields.remove("text") {
+                Some(Value::String(text)) => Ok(Bytes::from(text)),
+                _ => Ok(body),
+            }
+        }
+    }
+}
+
+/// Stream the upstream speech body to the client while metering it.
+fn build_speech_response(
+    response: reqwest::Response,
+    mut recorder: SpeechStreamRecorder,
+    content_type: Option<HeaderValue>,
+) -> Response {
+    let mut upstream = response.bytes_stream();
+    let model = recorder.labels.model.clone();
+    let provider = recorder.labels.provider.clone();
+    let api_key_name = recorder.labels.api_key_name.clone();
+
+    let stream = async_stream::stream! {
+        while let Some(chunk) = upstream.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    recorder.observe(&bytes);
+                    yield Ok::<_, std::io::Error>(bytes);
+                }
+                Err(e) => {
+                    tracing::error!(
+                        provider = %recorder.labels.provider,
+                        error = %e,
+                        "Speech stream interrupted"
+                    );
+                    recorder.fail(e.to_string());
+                    yield Err(std::io::Error::other(e));
+                    return;
+                }
+            }
+        }
+        recorder.finish();
+    };
+
+    let mut client_response = Response::new(Body::from_stream(stream));
+    let content_type =
+        content_type.unwrap_or_else(|| HeaderValue::from_static("application/octet-stream"));
+    client_response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, content_type);
+    attach_response_extensions(
+        &mut client_response,
+        Some(&model),
+        Some(&provider),
+        Some(&api_key_name),
+    );
+    client_response
+}
+
+struct AudioMetricLabels {
+    model: String,
+    provider: String,
+    endpoint: String,
+    api_key_name: String,
+    client: String,
+}
+
+impl AudioMetricLabels {
+    fn record_duration(&self, duration_secs: Option<f64>) {
+        if let Some(secs) = duration_secs {
+            get_metrics()
+                .audio_duration_seconds
+                .with_label_values(&[
+                    &self.model,
+                    &self.provider,
+                    &self.endpoint,
+                    &self.api_key_name,
+                ])
+                .inc_by(secs);
+        }
+    }
+}
+
+/// Meters a speech response stream and writes the request log when dropped,
+/// so client disconnects (status 499) are recorded as well.
+struct SpeechStreamRecorder {
+    record: Option<RequestLogRecord>,
+    labels: AudioMetricLabels,
+    format: String,
+    header: Vec<u8>,
+    total_bytes: usize,
+    completed: bool,
+    error: Option<String>,
+    start: Instant,
+}
+
+impl SpeechStreamRecorder {
+    fn observe(&mut self, chunk: &[u8]) {
+        if self.header.len() < AUDIO_HEADER_CAPTURE_BYTES {
+            let take = (AUDIO_HEADER_CAPTURE_BYTES - self.header.len()).min(chunk.len());
+            self.header.extend_from_slice(&chunk[..take]);
+        }
+        self.total_bytes += chunk.len();
+    }
+
+    fn fail(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    fn finish(&mut self) {
+        self.completed = true;
+    }
+}
+
+impl Drop for SpeechStreamRecorder {
+    fn drop(&mut self) {
+        let Some(mut record) = self.record.take() else {
+            return;
+        };
+        let duration_secs =
+            estimate_audio_duration_secs(&self.header, self.total_bytes, Some(&self.format));
+        self.labels.record_duration(duration_secs);
+
+        record.total_duration_ms = Some(elapsed_ms(self.start));
+        record.audio_duration_ms = duration_secs.map(secs_to_ms);
+        if let Some(error) = self.error.take() {
+            record.error_category = Some("stream_error".to_string());
+            record.error_message = Some(error);
+        } else if !self.completed {
+            record.status_code = Some(499);
+            record.error_category = Some("client_disconnect".to_string());
+        }
+        log_request_record(record);
+    }
+}
+
+// ============================================================================
+// Audio duration helpers
+// ============================================================================
+
+/// Duration reported by the provider: `usage.seconds` (duration-billed
+/// models) or top-level `duration` (`verbose_json`).
+fn reported_audio_duration_secs(response: &Value) -> Option<f64> {
+    response
+        .get("usage")
+        .filter(|u| u.get("type").and_then(|t| t.as_str()) == Some("duration"))
+        .and_then(|u| u.get("seconds"))
+        .and_then(|s| s.as_f64())
+        .or_else(|| response.get("duration").and_then(|d| d.as_f64()))
+}
+
+/// Token usage reported by token-billed transcription models.
+fn transcription_usage_tokens(response: &Value) -> (i32, i32) {
+    let usage = response
+        .get("usage")
+        .filter(|u| u.get("type").and_then(|t| t.as_str()) != Some("duration"));
+    let read = |key: &str| {
+        usage
+            .and_then(|u| u.get(key))
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0) as i32
+    };
+    (read("input_tokens"), read("output_tokens"))
+}
+
+/// Estimate audio duration in seconds from the container header.
+///
+/// `header` holds the leading bytes of the audio and `total_len` the full
+/// length. `format_hint` is the requested speech `response_format`; raw
+/// `pcm` (24kHz, 16-bit mono) has no header and can only be sized by hint.
+/// Compressed formats without a cheap length field (opus, aac) return `None`.
+pub fn estimate_audio_duration_secs(
+    header: &[u8],
+    total_len: usize,
+    format_hint: Option<&str>,
+) -> Option<f64> {
+    if format_hint == Some("pcm") {
+        return Some(total_len as f64 / 48_000.0);
+    }
+    if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+        return wav_duration_secs(header, total_len);
+    }
+    if header.starts_with(b"fLaC") {
+        return flac_duration_secs(header);
+    }
+    mp3_duration_secs(header, total_len)
+}
+
+fn wav_duration_secs(header: &[u8], total_len: usize) -> Option<f64> {
+    let read_u32 = |at: usize| -> Option<u32> {
+        header
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mut offset = 12;
+    let mut byte_rate = None;
+    while offset + 8 <= header.len() {
+        let id = &header[offset..offset + 4];
+        let size = read_u32(offset + 4)? as usize;
+        let data_start = offset + 8;
+        if id == b"fmt " {
+            byte_rate = read_u32(data_start + 8);
+        } else if id == b"data" {
+            let byte_rate = byte_rate.filter(|r| *r > 0)? as f64;
+            // Streamed WAVs carry a placeholder size (0 or u32::MAX).
+            let available = total_len.saturating_sub(data_start);
+            let data_len = if size == 0 || size == u32::MAX as usize {
+                available
+            } else {
+                size.min(available)
+            };
+            return Some(data_len as f64 / byte_rate);
+        }
+        offset = data_start + size + (size & 1);
+    }
+    None
+}
+
+fn flac_duration_secs(header: &[u8]) -> Option<f64> {
+    // STREAMINFO is always the first metadata block, starting at offset 8.
+    let info = header.get(8..26)?;
+    let sample_rate =
+        ((info[10] as u32) << 12) | ((info[11] as u32) << 4) | ((info[12] as u32) >> 4);
+    let total_samples = (((info[13] & 0x0F) as u64) << 32)
+        | ((info[14] as u64) << 24)
+        | ((info[15] as u64) << 16)
+        | ((info[16] as u64) << 8)
+        | (info[17] as u64);
+    if sample_rate == 0 || total_samples == 0 {
+        return None;
+    }
+    Some(total_samples as f64 / sample_rate as f64)
+}
+
+/// MP3 duration from the first Layer III frame, assuming constant bitrate.
+fn mp3_duration_secs(header: &[u8], total_len: usize) -> Option<f64> {
+    const MPEG1_L3_KBPS: [u32; 15] = [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ];
+    const MPEG2_L3_KBPS: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+    let mut offset = 0;
+    if header.starts_with(b"ID3") {
+        let size = header.get(6..10)?;
+        let tag_len = ((size[0] as usize & 0x7F) << 21)
+            | ((size[1] as usize & 0x7F) << 14)
+            | ((size[2] as usize & 0x7F) << 7)
+            | (size[3] as usize & 0x7F);
+        offset = 10 + tag_len;
+    }
+
+    let frame = header.get(offset..offset + 4)?;
+    if frame[0] != 0xFF || frame[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+    let version = (frame[1] >> 3) & 0x03;
+    let layer = (frame[1] >> 1) & 0x03;
+    if layer != 0x01 || version == 0x01 {
+        return None;
+    }
+    let bitrate_index = (frame[2] >> 4) as usize;
+    if bitrate_index == 0 || bitrate_index == 15 {
+        return None;
+    }
+    let kbps = if version == 0x03 {
+        MPEG1_L3_KBPS[bitrate_index]
+    } else {
+        MPEG2_L3_KBPS[bitrate_index]
+    };
+    let audio_bytes = total_len.saturating_sub(offset);
+    Some(audio_bytes as f64 * 8.0 / (kbps as f64 * 1000.0))
+}
+
+fn secs_to_ms(secs: f64) -> i32 {
+    (secs * 1000.0).round().min(i32::MAX as f64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_header(byte_rate: u32, data_size: u32) -> Vec<u8> {
+        let mut h = Vec::new();
+        h.extend_from_slice(b"RIFF");
+        h.extend_from_slice(&0u32.to_le_bytes());
+        h.extend_from_slice(b"WAVE");
+        h.extend_from_slice(b"fmt ");
+        h.extend_from_slice(&16u32.to_le_bytes());
+        h.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        h.extend_from_slice(&1u16.to_le_bytes()); // mono
+        h.extend_from_slice(&16_000u32.to_le_bytes());
+        h.extend_from_slice(&byte_rate.to_le_bytes());
+        h.extend_from_slice(&2u16.to_le_bytes());
+        h.extend_from_slice(&16u16.to_le_bytes());
+        h.extend_from_slice(b"data");
+        h.extend_from_slice(&data_size.to_le_bytes());
+        h
+    }
+
+    #[test]
+    fn test_wav_duration() {
+        let header = wav_header(32_000, 64_000);
+        let total = header.len() + 64_000;
+        let secs = estimate_audio_duration_secs(&header, total, None).unwrap();
+        assert!((secs - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_streamed_wav_uses_total_length() {
+        let header = wav_header(32_000, u32::MAX);
+        let total = header.len() + 16_000;
+        let secs = estimate_audio_duration_secs(&header, total, Some("wav")).unwrap();
+        assert!((secs - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mp3_duration_with_id3_tag() {
+        let mut data = b"ID3\x04\x00\x00\x00\x00\x00\x0A".to_vec();
+        data.extend_from_slice(&[0u8; 10]);
+        // MPEG1 Layer III, 128kbps, 44.1kHz
+        data.extend_from_slice(&[0xFF, 0xFB, 0x90, 0x64]);
+        let total = 20 + 160_000;
+        let secs = estimate_audio_duration_secs(&data, total, Some("mp3")).unwrap();
+        assert!((secs - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_flac_duration() {
+        let mut data = b"fLaC".to_vec();
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x22]);
+        let mut info = [0u8; 18];
+        // 44100 Hz = 0x0AC44 in 20 bits, total samples = 88200
+        info[10] = 0x0A;
+        info[11] = 0xC4;
+        info[12] = 0x40;
+        info[14..18].copy_from_slice(&88_200u32.to_be_bytes());
+        data.extend_from_slice(&info);
+        let secs = estimate_audio_duration_secs(&data, data.len(), None).unwrap();
+        assert!((secs - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pcm_and_unknown_formats() {
+        assert_eq!(
+            estimate_audio_duration_secs(b"", 96_000, Some("pcm")),
+            Some(2.0)
+        );
+        assert_eq!(
+            estimate_audio_duration_secs(b"OggS", 1000, Some("opus")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reported_duration_and_usage() {
+        let verbose = json!({"text": "hi", "duration": 3.25});
+        assert_eq!(reported_audio_duration_secs(&verbose), Some(3.25));
+
+        let billed = json!({"text": "hi", "usage": {"type": "duration", "seconds": 7}});
+        assert_eq!(reported_audio_duration_secs(&billed), Some(7.0));
+        assert_eq!(transcription_usage_tokens(&billed), (0, 0));
+
+        let tokens = json!({"text": "hi", "usage": {"type": "tokens", "input_tokens": 14, "output_tokens": 45}});
+        assert_eq!(reported_audio_duration_secs(&tokens), None);
+        assert_eq!(transcription_usage_tokens(&tokens), (14, 45));
+    }
+
+    #[test]
+    fn test_secs_to_ms() {
+        assert_eq!(secs_to_ms(1.2345), 1235);
+    }
+}
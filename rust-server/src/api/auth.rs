@@ -8,7 +8,14 @@ use sha2::{Digest, Sha256};
 
 use crate::api::models::{compile_pattern, is_pattern};
 use crate::core::config::CredentialConfig;
+use crate::core::credential_usage::record_credential_use;
 use crate::core::error::Result;
+use crate::core::error_logger::{log_error, mask_headers, ErrorCategory, ErrorLogRecord};
+use crate::core::jwt_auth::{jwt_authenticator, JwtAuthenticator, JwtError, JwtIdentity};
+use crate::core::logging::{get_client_certificate, get_client_ip};
+use crate::core::metrics::try_get_metrics;
+use crate::core::middleware::extract_client;
+use crate::core::tls::{tls_server, ClientCertificate, TlsConfig, TlsServer};
 use crate::core::AppError;
 use crate::AppState;
 
@@ -18,8 +25,12 @@ use crate::AppState;
 
 /// Paths that should be exempt from rate limiting.
 /// These endpoints perform local computation and don't consume upstream LLM resources.
-pub const RATE_LIMIT_EXEMPT_PATHS: &[&str] =
-    &["/v1/messages/count_tokens", "/v2/messages/count_tokens"];
+pub const RATE_LIMIT_EXEMPT_PATHS: &[&str] = &[
+    "/v1/messages/count_tokens",
+    "/v2/messages/count_tokens",
+    "/v1/chat/completions/count_tokens",
+    "/v2/chat/completions/count_tokens",
+];
 
 // ============================================================================
 // Authentication Format
@@ -67,6 +78,181 @@ fn extract_bearer(headers: &HeaderMap) -> Option<&str> {
         .and_then(|s| s.strip_prefix("Bearer "))
 }
 
+// ============================================================================
+// Credential Resolution
+// ============================================================================
+
+/// JWT identity verified earlier in the same request.
+///
+/// `model_permission_middleware` stores it in the request extensions so the
+/// handler can pass it to [`verify_auth_with`] instead of verifying the
+/// token again.
+#[derive(Debug, Clone)]
+pub struct VerifiedJwt {
+    key_hash: String,
+    identity: JwtIdentity,
+}
+
+/// Find the credential that a presented key or JWT authenticates as.
+///
+/// Static keys are matched by hash (expired credentials and rotated-out keys
+/// past their grace period are rejected). Otherwise, when JWT authentication
+/// is enabled, the token is verified (unless `verified` already holds it) and
+/// mapped to its credential profile. The profile is returned unchanged, so
+/// metrics are labelled by profile; the JWT user only appears in logs.
+pub fn resolve_credential(
+    token: &str,
+    credentials: &[CredentialConfig],
+    jwt: Option<&JwtAuthenticator>,
+    verified: Option<&VerifiedJwt>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<(CredentialConfig, Option<VerifiedJwt>)> {
+    let key_hash = hash_key(token);
+    if let Some(credential) = credentials
+        .iter()
+        .find(|c| c.accepts_key_hash(&key_hash, now))
+    {
+        return Ok((credential.clone(), None));
+    }
+
+    let Some(jwt) = jwt else {
+        return Err(AppError::Unauthorized);
+    };
+    let verified = verified.filter(|v| v.key_hash == key_hash);
+    let identity = match verified {
+        Some(verified) => verified.identity.clone(),
+        None => match jwt.authenticate(token, now.timestamp()) {
+            Ok(identity) => identity,
+            Err(JwtError::NoProfile) => {
+                return Err(AppError::Forbidden(
+                    "No credential profile matches this token".to_string(),
+                ))
+            }
+            Err(e) => {
+                tracing::debug!(error = %e, "JWT authentication failed");
+                return Err(AppError::Unauthorized);
+            }
+        },
+    };
+
+    let profile = credentials
+        .iter()
+        .find(|c| c.name == identity.profile && c.is_active(now))
+        .ok_or_else(|| {
+            tracing::warn!(
+                user = %identity.user,
+                profile = %identity.profile,
+                "JWT credential profile does not exist or is disabled"
+            );
+            AppError::Forbidden(format!(
+                "Credential profile '{}' is not available",
+                identity.profile
+            ))
+        })?;
+
+    if verified.is_none() {
+        tracing::info!(
+            user = %identity.user,
+            profile = %identity.profile,
+            "JWT authenticated"
+        );
+    }
+    Ok((profile.clone(), Some(VerifiedJwt { key_hash, identity })))
+}
+
+/// Find the credential profile a verified client certificate maps to.
+///
+/// Used for requests without a key. Like JWT identities, the returned
+/// credential carries the profile's permissions and rate limit under the
+/// certificate's common name.
+pub fn resolve_certificate_credential(
+    certificate: &ClientCertificate,
+    credentials: &[CredentialConfig],
+    tls: Option<&TlsConfig>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<CredentialConfig> {
+    let Some(tls) = tls.filter(|tls| !tls.profile_rules.is_empty()) else {
+        return Err(AppError::Unauthorized);
+    };
+    let profile = tls.profile_for(certificate).ok_or_else(|| {
+        AppError::Forbidden("No credential profile matches this client certificate".to_string())
+    })?;
+
+    let credential = credentials
+        .iter()
+        .find(|c| c.name == profile && c.is_active(now))
+        .ok_or_else(|| {
+            tracing::warn!(
+                subject = %certificate.subject,
+                profile = %profile,
+                "Client certificate profile does not exist or is disabled"
+            );
+            AppError::Forbidden(format!("Credential profile '{}' is not available", profile))
+        })?;
+
+    tracing::debug!(
+        subject = %certificate.subject,
+        profile = %profile,
+        "Client certificate authenticated"
+    );
+    Ok(CredentialConfig {
+        name: certificate.identity().to_string(),
+        ..credential.clone()
+    })
+}
+
+/// Reject requests from addresses outside the credential's CIDR allowlist.
+///
+/// Rejections are recorded in `error_logs` and metrics, since they usually
+/// mean a leaked key is being used from elsewhere.
+fn check_network_restriction(
+    credential: &CredentialConfig,
+    client_ip: Option<std::net::IpAddr>,
+    headers: &HeaderMap,
+    request_path: Option<&str>,
+) -> Result<()> {
+    if credential.allows_ip(client_ip) {
+        return Ok(());
+    }
+
+    let client_ip = client_ip.map(|ip| ip.to_string());
+    tracing::warn!(
+        credential_name = %credential.name,
+        client_ip = client_ip.as_deref().unwrap_or("unknown"),
+        "Request from address outside credential allowlist"
+    );
+    if let Some(metrics) = try_get_metrics() {
+        metrics
+            .credential_network_rejections
+            .with_label_values(&[&credential.name])
+            .inc();
+    }
+    log_error(ErrorLogRecord {
+        request_id: headers
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+        error_category: ErrorCategory::NetworkRestriction,
+        error_message: format!(
+            "Client address {} is not allowed for credential '{}'",
+            client_ip.as_deref().unwrap_or("unknown"),
+            credential.name
+        ),
+        error_code: Some(403),
+        endpoint: request_path.unwrap_or_default().to_string(),
+        request_headers: Some(mask_headers(headers)),
+        response_status_code: Some(403),
+        credential_name: credential.name.clone(),
+        client: extract_client(headers),
+        ..Default::default()
+    });
+
+    Err(AppError::Forbidden(
+        "Requests from this address are not allowed for this credential".to_string(),
+    ))
+}
+
 // ============================================================================
 // Main Authentication Function
 // ============================================================================
@@ -85,6 +271,8 @@ fn extract_bearer(headers: &HeaderMap) -> Option<&str> {
 /// * `Ok(Some(credential))` - Authentication successful, returns matched credential
 /// * `Ok(None)` - No authentication required (empty credentials list)
 /// * `Err(AppError::Unauthorized)` - Authentication failed
+/// * `Err(AppError::Forbidden)` - Valid JWT or client certificate without a
+///   usable credential profile
 ///
 /// # Example
 ///
@@ -102,6 +290,17 @@ pub fn verify_auth(
     state: &AppState,
     format: AuthFormat,
     request_path: Option<&str>,
+) -> Result<Option<CredentialConfig>> {
+    verify_auth_with(headers, state, format, request_path, None)
+}
+
+/// [`verify_auth`] reusing a JWT already verified for this request.
+pub fn verify_auth_with(
+    headers: &HeaderMap,
+    state: &AppState,
+    format: AuthFormat,
+    request_path: Option<&str>,
+    verified: Option<&VerifiedJwt>,
 ) -> Result<Option<CredentialConfig>> {
     // Extract the provided key from headers
     let provided_key = extract_api_key(headers, format);
@@ -114,46 +313,55 @@ pub fn verify_auth(
         return Ok(None);
     }
 
-    // If credentials are configured, a key is required
-    let provided_key = provided_key.ok_or(AppError::Unauthorized)?;
-
-    // Hash the provided key for comparison with stored hashes
-    let provided_key_hash = hash_key(provided_key);
-
-    // Check against credentials configuration
-    for credential_config in credentials {
-        if credential_config.enabled && credential_config.credential_key == provided_key_hash {
-            // Check if request path is exempt from rate limiting
-            let is_exempt = request_path
-                .map(|path| RATE_LIMIT_EXEMPT_PATHS.contains(&path))
-                .unwrap_or(false);
-
-            // Only check rate limit if path is not exempt
-            if !is_exempt {
-                // Check rate limit for this credential using the hash
-                // Wrap error with key_name context at auth layer
-                if let Err(AppError::RateLimitExceeded { message, .. }) = state
-                    .rate_limiter
-                    .check_rate_limit(&credential_config.credential_key)
-                {
-                    return Err(AppError::RateLimitExceeded {
-                        message,
-                        key_name: Some(credential_config.name.clone()),
-                    });
-                }
-            }
-
-            tracing::debug!(
-                credential_name = %credential_config.name,
-                "Authentication successful"
-            );
+    // If credentials are configured, a key or a mapped client certificate is required
+    let credential_config = match provided_key {
+        Some(provided_key) => {
+            resolve_credential(
+                provided_key,
+                &credentials,
+                jwt_authenticator(),
+                verified,
+                chrono::Utc::now(),
+            )?
+            .0
+        }
+        None => resolve_certificate_credential(
+            &get_client_certificate().ok_or(AppError::Unauthorized)?,
+            &credentials,
+            tls_server().map(TlsServer::config),
+            chrono::Utc::now(),
+        )?,
+    };
 
-            return Ok(Some(credential_config));
+    check_network_restriction(&credential_config, get_client_ip(), headers, request_path)?;
+
+    // Check if request path is exempt from rate limiting
+    let is_exempt = request_path
+        .map(|path| RATE_LIMIT_EXEMPT_PATHS.contains(&path))
+        .unwrap_or(false);
+
+    // Only check rate limit if path is not exempt
+    if !is_exempt {
+        // Check rate limit for this credential using the hash
+        // Wrap error with key_name context at auth layer
+        if let Err(AppError::RateLimitExceeded { message, .. }) = state
+            .rate_limiter
+            .check_rate_limit(&credential_config.credential_key)
+        {
+            return Err(AppError::RateLimitExceeded {
+                message,
+                key_name: Some(credential_config.name.clone()),
+            });
         }
     }
 
-    // No matching credential found
-    Err(AppError::Unauthorized)
+    tracing::debug!(
+        credential_name = %credential_config.name,
+        "Authentication successful"
+    );
+    record_credential_use(&credential_config.credential_key);
+
+    Ok(Some(credential_config))
 }
 
 // ============================================================================
@@ -368,6 +576,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec!["gpt-4".to_string()],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert!(check_model_permission(None, &config).is_ok());
     }
@@ -386,6 +600,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec![],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert!(check_model_permission(Some("any-model"), &config).is_ok());
     }
@@ -399,6 +619,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec!["gpt-4".to_string(), "gpt-3.5-turbo".to_string()],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert!(check_model_permission(Some("gpt-4"), &config).is_ok());
         assert!(check_model_permission(Some("gpt-3.5-turbo"), &config).is_ok());
@@ -413,6 +639,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec!["gpt-4".to_string()],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         let result = check_model_permission(Some("gpt-3.5-turbo"), &config);
         assert!(result.is_err());
@@ -433,6 +665,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec!["claude-opus-4-5-.*".to_string()],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert!(check_model_permission(Some("claude-opus-4-5-20240620"), &config).is_ok());
         assert!(check_model_permission(Some("claude-opus-4-5-latest"), &config).is_ok());
@@ -447,6 +685,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec!["claude-opus-4-5-.*".to_string()],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert!(check_model_permission(Some("claude-3-opus"), &config).is_err());
     }
@@ -460,10 +704,166 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec!["gpt-*".to_string()],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert!(check_model_permission(Some("gpt-4"), &config).is_ok());
         assert!(check_model_permission(Some("gpt-4o"), &config).is_ok());
         assert!(check_model_permission(Some("gpt-3.5-turbo"), &config).is_ok());
         assert!(check_model_permission(Some("claude-3-opus"), &config).is_err());
     }
+
+    fn profile_credential(name: &str, key: &str) -> CredentialConfig {
+        CredentialConfig {
+            credential_key: hash_key(key),
+            name: name.to_string(),
+            description: None,
+            rate_limit: None,
+            enabled: true,
+            allowed_models: vec!["gpt-4o".to_string()],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
+        }
+    }
+
+    #[test]
+    fn test_resolve_credential_maps_jwt_to_profile() {
+        use crate::core::jwt_auth::test_support::{authenticator, config, TestIssuer};
+
+        let issuer = TestIssuer::new("k1");
+        let jwt = authenticator(&issuer, config());
+        let credentials = vec![
+            profile_credential("static", "sk-static"),
+            profile_credential("ml-research", "sk-ml"),
+        ];
+        let now = chrono::Utc::now();
+        let token = issuer.sign(&serde_json::json!({
+            "iss": "https://idp.example.com",
+            "aud": "llm-proxy",
+            "sub": "svc-batch",
+            "groups": ["ml-team"],
+            "exp": now.timestamp() + 300,
+        }));
+
+        let (resolved, verified) =
+            resolve_credential(&token, &credentials, Some(&jwt), None, now).unwrap();
+        // The profile name is kept so the user does not become a metric label
+        assert_eq!(resolved.name, "ml-research");
+        assert_eq!(resolved.credential_key, hash_key("sk-ml"));
+        assert_eq!(resolved.allowed_models, vec!["gpt-4o".to_string()]);
+        let verified = verified.unwrap();
+        assert_eq!(verified.identity.user, "svc-batch");
+
+        // A verified identity is reused without checking the token again
+        let other_issuer = authenticator(&TestIssuer::new("k2"), config());
+        let (resolved, _) = resolve_credential(
+            &token,
+            &credentials,
+            Some(&other_issuer),
+            Some(&verified),
+            now,
+        )
+        .unwrap();
+        assert_eq!(resolved.name, "ml-research");
+        // ...but only for the token it was verified from
+        assert!(resolve_credential(
+            "other-token",
+            &credentials,
+            Some(&jwt),
+            Some(&verified),
+            now
+        )
+        .is_err());
+
+        // Static keys still work alongside JWTs
+        let (resolved, verified) =
+            resolve_credential("sk-static", &credentials, Some(&jwt), None, now).unwrap();
+        assert_eq!(resolved.name, "static");
+        assert!(verified.is_none());
+
+        // Without JWT authentication the token is just an unknown key
+        assert!(matches!(
+            resolve_credential(&token, &credentials, None, None, now),
+            Err(AppError::Unauthorized)
+        ));
+
+        // A disabled profile rejects its JWT users
+        let mut disabled = credentials.clone();
+        disabled[1].enabled = false;
+        assert!(matches!(
+            resolve_credential(&token, &disabled, Some(&jwt), None, now),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_certificate_credential_maps_common_name_to_profile() {
+        use crate::core::tls::{parse_cert_profile_rules, ClientAuth};
+
+        let mut tls = TlsConfig {
+            cert_file: "server.pem".into(),
+            key_file: "server.key".into(),
+            client_ca_file: Some("ca.pem".into()),
+            client_auth: ClientAuth::Optional,
+            reload_interval: std::time::Duration::from_secs(30),
+            profile_rules: parse_cert_profile_rules("billing-svc=billing").unwrap(),
+        };
+        let credentials = vec![profile_credential("billing", "sk-billing")];
+        let certificate = ClientCertificate {
+            subject: "O=Acme,CN=billing-svc".to_string(),
+            common_name: Some("billing-svc".to_string()),
+        };
+        let now = chrono::Utc::now();
+
+        let resolved =
+            resolve_certificate_credential(&certificate, &credentials, Some(&tls), now).unwrap();
+        assert_eq!(resolved.name, "billing-svc");
+        assert_eq!(resolved.credential_key, hash_key("sk-billing"));
+
+        let other = ClientCertificate {
+            subject: "CN=search-svc".to_string(),
+            common_name: Some("search-svc".to_string()),
+        };
+        assert!(matches!(
+            resolve_certificate_credential(&other, &credentials, Some(&tls), now),
+            Err(AppError::Forbidden(_))
+        ));
+
+        // Without profile rules a certificate does not replace a key
+        assert!(matches!(
+            resolve_certificate_credential(&certificate, &credentials, None, now),
+            Err(AppError::Unauthorized)
+        ));
+        tls.profile_rules.clear();
+        assert!(matches!(
+            resolve_certificate_credential(&certificate, &credentials, Some(&tls), now),
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[test]
+    fn test_check_network_restriction() {
+        let mut credential = profile_credential("office", "sk-office");
+        let headers = HeaderMap::new();
+        let inside = Some("192.168.1.20".parse().unwrap());
+        let outside = Some("203.0.113.9".parse().unwrap());
+
+        assert!(check_network_restriction(&credential, outside, &headers, None).is_ok());
+
+        credential.allowed_cidrs = vec!["192.168.1.0/24".parse().unwrap()];
+        assert!(check_network_restriction(&credential, inside, &headers, None).is_ok());
+        assert!(matches!(
+            check_network_restriction(&credential, outside, &headers, None),
+            Err(AppError::Forbidden(_))
+        ));
+        assert!(check_network_restriction(&credential, None, &headers, None).is_err());
+    }
 }
@@ -4,7 +4,9 @@
 //! Claude format requests to OpenAI format, proxying to providers,
 //! and converting responses back to Claude format.
 
-use crate::api::auth::{verify_auth, AuthFormat};
+use crate::api::auth::{
+    check_model_permission, verify_auth, verify_auth_with, AuthFormat, VerifiedJwt,
+};
 use crate::api::claude_models::{
     ClaudeContentBlock, ClaudeMessage, ClaudeMessageContent, ClaudeMessagesRequest,
     ClaudeSystemPrompt, ClaudeTokenCountRequest, ClaudeTokenCountResponse, ClaudeTool,
@@ -13,6 +15,7 @@ use crate::api::gemini3::{normalize_request_payload, strip_gemini3_provider_fiel
 use crate::api::handlers::AppState;
 use crate::api::proxy::ensure_tool_use_result_pairing;
 use crate::api::streaming::calculate_message_tokens_with_tools;
+use crate::api::token_count::{count_tokens_upstream, default_pipeline};
 use crate::api::upstream::{
     attach_response_extensions, build_protocol_error_response, build_upstream_request,
     execute_upstream_request_or_transport_error, finalize_non_streaming_response,
@@ -44,7 +47,7 @@ use axum::{
     extract::State,
     http::{HeaderMap, StatusCode},
     response::Response,
-    Json,
+    Extension, Json,
 };
 use chrono::Utc;
 use futures::StreamExt;
@@ -263,7 +266,7 @@ pub async fn create_message(
                 // Build and send request based on provider_type
                 let response = if is_anthropic {
                     let request = build_upstream_request(
-                        &state.http_client,
+                        &state.provider_http_client(&provider.name)?,
                         &url,
                         &provider_request_value,
                         UpstreamAuth::XApiKey(&provider.api_key),
@@ -288,7 +291,7 @@ pub async fn create_message(
                     .await
                 } else {
                     let request = build_upstream_request(
-                        &state.http_client,
+                        &state.provider_http_client(&provider.name)?,
                         &url,
                         &openai_request,
                         UpstreamAuth::Bearer(&provider.api_key),
@@ -765,7 +768,8 @@ async fn handle_non_streaming_response(
 
 /// Claude token counting endpoint.
 ///
-/// Provides accurate token count for the given messages using tiktoken.
+/// Forwards to the provider's count_tokens API when it opted in via
+/// `upstream_count_tokens`, otherwise estimates locally with tiktoken.
 #[utoipa::path(
     post,
     path = "/v1/messages/count_tokens",
@@ -785,18 +789,35 @@ async fn handle_non_streaming_response(
 pub async fn count_tokens(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    verified_jwt: Option<Extension<VerifiedJwt>>,
     Json(claude_request): Json<ClaudeTokenCountRequest>,
 ) -> Result<Json<ClaudeTokenCountResponse>> {
     let request_id = generate_request_id();
-    let _key_config = verify_auth(
+    let key_config = verify_auth_with(
         &headers,
         &state,
         AuthFormat::MultiFormat,
         Some("/v1/messages/count_tokens"),
+        verified_jwt.as_ref().map(|Extension(jwt)| jwt),
     )?;
+    check_model_permission(Some(&claude_request.model), &key_config)?;
 
     REQUEST_ID
         .scope(request_id.clone(), async move {
+            let upstream_payload = serde_json::to_value(&claude_request)?;
+            if let Some(input_tokens) = count_tokens_upstream(
+                &state,
+                default_pipeline(),
+                Protocol::Anthropic,
+                &upstream_payload,
+                &headers,
+                key_config.as_ref(),
+            )
+            .await
+            {
+                return Ok(Json(ClaudeTokenCountResponse { input_tokens }));
+            }
+
             let model = &claude_request.model;
             let messages_value = build_claude_messages_for_token_count(
                 &claude_request.system,
@@ -0,0 +1,525 @@
+//! Pre-flight context-window guard.
+//!
+//! Estimates the prompt size of a provider payload and compares it with the
+//! model's `max_input_tokens`. Oversized requests are either trimmed with the
+//! configured [`ContextTrimming`] strategy or reported back so the proxy can
+//! reject them before paying for an upstream round trip.
+//!
+//! The conversation is trimmed in whole turns: a turn starts at a user message
+//! and runs until the next one, and turn boundaries are never placed between a
+//! tool call and its result. System prompts and the latest turn are always kept.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde_json::Value;
+
+use crate::api::proxy::{ensure_tool_use_result_pairing, tool_result_ids, tool_use_ids};
+use crate::api::streaming::count_tokens;
+use crate::core::config::ContextTrimming;
+use crate::transformer::Protocol;
+
+/// Per-message framing overhead (role markers, separators).
+const MESSAGE_OVERHEAD_TOKENS: usize = 3;
+
+/// Keys whose string values are opaque blobs rather than prompt text.
+const OPAQUE_KEYS: &[&str] = &[
+    "data",
+    "file_data",
+    "signature",
+    "thought_signature",
+    "thoughtSignature",
+    "encrypted_content",
+];
+
+/// Result of running the guard against a provider payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContextGuardOutcome {
+    /// The prompt fits the model's input limit unchanged.
+    Fits,
+    /// Older turns were dropped; `removed_tokens` is the estimated saving.
+    Trimmed { removed_tokens: usize },
+    /// The prompt is over the limit and could not be trimmed to fit.
+    Exceeded { tokens: usize, limit: usize },
+}
+
+/// Check `payload` against `limit` input tokens, trimming it in place when a
+/// strategy is configured.
+///
+/// The payload is left untouched unless the outcome is `Trimmed`.
+pub fn enforce_context_limit(
+    payload: &mut Value,
+    protocol: Protocol,
+    model: &str,
+    limit: usize,
+    trimming: Option<&ContextTrimming>,
+) -> ContextGuardOutcome {
+    let key = conversation_key(protocol);
+    let fixed = fixed_tokens(payload, key, model);
+    let messages = match payload.get(key).and_then(|m| m.as_array()) {
+        Some(messages) => messages,
+        None if fixed > limit => {
+            return ContextGuardOutcome::Exceeded {
+                tokens: fixed,
+                limit,
+            }
+        }
+        None => return ContextGuardOutcome::Fits,
+    };
+
+    let costs: Vec<usize> = messages.iter().map(|m| message_tokens(m, model)).collect();
+    let total = fixed + costs.iter().sum::<usize>();
+    if total <= limit {
+        return ContextGuardOutcome::Fits;
+    }
+    let exceeded = ContextGuardOutcome::Exceeded {
+        tokens: total,
+        limit,
+    };
+    let Some(strategy) = trimming else {
+        return exceeded;
+    };
+
+    let turns = split_turns(messages);
+    let Some(keep) = select_turns(&turns, &costs, messages, strategy, total - limit) else {
+        return exceeded;
+    };
+
+    let kept: Vec<Value> = turns
+        .iter()
+        .zip(&keep)
+        .filter(|(_, keep)| **keep)
+        .flat_map(|(turn, _)| messages[turn.range.clone()].iter().cloned())
+        .collect();
+    let removed_tokens = turns
+        .iter()
+        .zip(&keep)
+        .filter(|(_, keep)| !**keep)
+        .map(|(turn, _)| costs[turn.range.clone()].iter().sum::<usize>())
+        .sum();
+
+    payload[key] = Value::Array(kept);
+    // Turn boundaries already respect tool pairs; this only guards against
+    // conversations that arrived with orphans in the dropped region.
+    ensure_tool_use_result_pairing(payload);
+
+    ContextGuardOutcome::Trimmed { removed_tokens }
+}
+
+/// Estimate the prompt tokens of a provider payload.
+pub fn estimate_payload_tokens(payload: &Value, protocol: Protocol, model: &str) -> usize {
+    let key = conversation_key(protocol);
+    let messages: usize = payload
+        .get(key)
+        .and_then(|m| m.as_array())
+        .map(|messages| messages.iter().map(|m| message_tokens(m, model)).sum())
+        .unwrap_or(0);
+    fixed_tokens(payload, key, model) + messages
+}
+
+fn conversation_key(protocol: Protocol) -> &'static str {
+    match protocol {
+        Protocol::OpenAI | Protocol::Anthropic | Protocol::GcpVertex => "messages",
+        Protocol::Gemini => "contents",
+        Protocol::ResponseApi => "input",
+    }
+}
+
+/// Tokens outside the trimmable conversation: system prompt, instructions,
+/// tool definitions and a string `input`.
+fn fixed_tokens(payload: &Value, conversation_key: &str, model: &str) -> usize {
+    let Some(obj) = payload.as_object() else {
+        return 0;
+    };
+    obj.iter()
+        .filter(|(key, value)| *key != conversation_key || !value.is_array())
+        .map(|(key, value)| match key.as_str() {
+            "system" | "instructions" | "systemInstruction" | "system_instruction" => {
+                text_tokens(value, model)
+            }
+            "input" | "messages" | "contents" => text_tokens(value, model),
+            "tools" | "functions" => count_tokens(&value.to_string(), model),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn message_tokens(message: &Value, model: &str) -> usize {
+    text_tokens(message, model) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Count the tokens of every string leaf, skipping binary payloads.
+///
+/// Images and documents are therefore free in the estimate; their cost depends
+/// on provider-side processing that cannot be predicted here.
+fn text_tokens(value: &Value, model: &str) -> usize {
+    match value {
+        Value::String(s) if s.starts_with("data:") => 0,
+        Value::String(s) => count_tokens(s, model),
+        Value::Array(items) => items.iter().map(|v| text_tokens(v, model)).sum(),
+        Value::Object(map) => map
+            .iter()
+            .filter(|(key, _)| !OPAQUE_KEYS.contains(&key.as_str()))
+            .map(|(_, v)| text_tokens(v, model))
+            .sum(),
+        _ => 0,
+    }
+}
+
+#[derive(Debug)]
+struct Turn {
+    range: Range<usize>,
+    /// System/developer messages are never dropped.
+    pinned: bool,
+}
+
+fn role(message: &Value) -> Option<&str> {
+    message.get("role").and_then(|r| r.as_str())
+}
+
+fn is_pinned(message: &Value) -> bool {
+    matches!(role(message), Some("system" | "developer"))
+}
+
+/// A user-authored message that opens a new turn (tool results do not).
+fn starts_turn(message: &Value) -> bool {
+    // Responses API tool calls/outputs and reasoning items carry no role
+    role(message) == Some("user") && tool_result_ids(message).is_empty()
+}
+
+/// Split the conversation into turns that can be dropped independently.
+fn split_turns(messages: &[Value]) -> Vec<Turn> {
+    // A boundary before index i is unsafe when a tool call before i is
+    // answered at or after i.
+    let mut unsafe_boundary = vec![false; messages.len() + 1];
+    let mut call_index: HashMap<String, usize> = HashMap::new();
+    for (i, message) in messages.iter().enumerate() {
+        for id in tool_result_ids(message) {
+            if let Some(&start) = call_index.get(&id) {
+                for flag in &mut unsafe_boundary[start + 1..=i] {
+                    *flag = true;
+                }
+            }
+        }
+        for id in tool_use_ids(message) {
+            call_index.entry(id).or_insert(i);
+        }
+    }
+
+    let mut turns: Vec<Turn> = Vec::new();
+    for (i, message) in messages.iter().enumerate() {
+        let pinned = is_pinned(message);
+        let boundary = i == 0
+            || (!unsafe_boundary[i]
+                && (pinned || starts_turn(message) || turns.last().is_some_and(|t| t.pinned)));
+        match turns.last_mut() {
+            Some(turn) if !boundary => {
+                turn.range.end = i + 1;
+                turn.pinned |= pinned;
+            }
+            _ => turns.push(Turn {
+                range: i..i + 1,
+                pinned,
+            }),
+        }
+    }
+    turns
+}
+
+/// Decide which turns to keep so at least `excess` tokens are removed.
+///
+/// Returns `None` when the strategy cannot free enough tokens.
+fn select_turns(
+    turns: &[Turn],
+    costs: &[usize],
+    messages: &[Value],
+    strategy: &ContextTrimming,
+    excess: usize,
+) -> Option<Vec<bool>> {
+    let last = turns.len().checked_sub(1)?;
+    let droppable: Vec<usize> = (0..last).filter(|&i| !turns[i].pinned).collect();
+    let turn_cost = |i: usize| costs[turns[i].range.clone()].iter().sum::<usize>();
+
+    let mut keep = vec![true; turns.len()];
+    let mut removed = 0;
+
+    let order: Vec<usize> = match strategy {
+        ContextTrimming::DropOldest => droppable,
+        ContextTrimming::KeepLast { messages: n } => {
+            // First index of the last `n` non-system messages
+            let cutoff = (0..messages.len())
+                .rev()
+                .filter(|&i| !is_pinned(&messages[i]))
+                .nth(n.saturating_sub(1))
+                .filter(|_| *n > 0)
+                .unwrap_or(messages.len());
+            for &i in droppable.iter() {
+                if turns[i].range.start < cutoff {
+                    keep[i] = false;
+                    removed += turn_cost(i);
+                }
+            }
+            droppable
+        }
+        ContextTrimming::MiddleOut => {
+            // Keep the opening turn as long as possible, then remove from the
+            // middle of what remains so both ends of the session survive.
+            let (first, rest) = droppable.split_first()?;
+            let mut rest = rest.to_vec();
+            let mut order = Vec::with_capacity(droppable.len());
+            while !rest.is_empty() {
+                order.push(rest.remove((rest.len() - 1) / 2));
+            }
+            order.push(*first);
+            order
+        }
+    };
+
+    for i in order {
+        if removed >= excess {
+            break;
+        }
+        if keep[i] {
+            keep[i] = false;
+            removed += turn_cost(i);
+        }
+    }
+    (removed >= excess).then_some(keep)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const MODEL: &str = "gpt-4";
+
+    fn user(text: &str) -> Value {
+        json!({"role": "user", "content": text})
+    }
+
+    fn assistant(text: &str) -> Value {
+        json!({"role": "assistant", "content": text})
+    }
+
+    fn filler(words: usize) -> String {
+        vec!["hello"; words].join(" ")
+    }
+
+    fn openai_payload(messages: Vec<Value>) -> Value {
+        json!({"model": MODEL, "messages": messages})
+    }
+
+    fn contents(payload: &Value) -> Vec<String> {
+        payload["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["content"].as_str().unwrap_or("<blocks>").to_string())
+            .collect()
+    }
+
+    fn long_conversation() -> Value {
+        let big = filler(200);
+        openai_payload(vec![
+            json!({"role": "system", "content": "You are terse."}),
+            user(&format!("first {}", big)),
+            assistant(&format!("a1 {}", big)),
+            user(&format!("second {}", big)),
+            assistant(&format!("a2 {}", big)),
+            user(&format!("third {}", big)),
+            assistant(&format!("a3 {}", big)),
+            user("latest question"),
+        ])
+    }
+
+    #[test]
+    fn test_fits_leaves_payload_untouched() {
+        let mut payload = openai_payload(vec![user("hi")]);
+        let before = payload.clone();
+        let outcome = enforce_context_limit(&mut payload, Protocol::OpenAI, MODEL, 1000, None);
+        assert_eq!(outcome, ContextGuardOutcome::Fits);
+        assert_eq!(payload, before);
+    }
+
+    #[test]
+    fn test_exceeded_without_trimming() {
+        let mut payload = long_conversation();
+        let before = payload.clone();
+        let outcome = enforce_context_limit(&mut payload, Protocol::OpenAI, MODEL, 100, None);
+        match outcome {
+            ContextGuardOutcome::Exceeded { tokens, limit } => {
+                assert_eq!(limit, 100);
+                assert_eq!(
+                    tokens,
+                    estimate_payload_tokens(&before, Protocol::OpenAI, MODEL)
+                );
+            }
+            other => panic!("expected Exceeded, got {:?}", other),
+        }
+        assert_eq!(payload, before);
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_system_and_latest() {
+        let mut payload = long_conversation();
+        let before = estimate_payload_tokens(&payload, Protocol::OpenAI, MODEL);
+        let outcome = enforce_context_limit(
+            &mut payload,
+            Protocol::OpenAI,
+            MODEL,
+            500,
+            Some(&ContextTrimming::DropOldest),
+        );
+        let after = estimate_payload_tokens(&payload, Protocol::OpenAI, MODEL);
+        assert_eq!(
+            outcome,
+            ContextGuardOutcome::Trimmed {
+                removed_tokens: before - after
+            }
+        );
+        assert!(after <= 500);
+        let kept = contents(&payload);
+        assert_eq!(kept[0], "You are terse.");
+        assert!(kept[1].starts_with("third"));
+        assert_eq!(kept.last().unwrap(), "latest question");
+        assert_eq!(kept.len(), 4);
+    }
+
+    #[test]
+    fn test_keep_last_drops_everything_before_window() {
+        let mut payload = long_conversation();
+        let outcome = enforce_context_limit(
+            &mut payload,
+            Protocol::OpenAI,
+            MODEL,
+            1000,
+            Some(&ContextTrimming::KeepLast { messages: 3 }),
+        );
+        assert!(matches!(outcome, ContextGuardOutcome::Trimmed { .. }));
+        let kept = contents(&payload);
+        assert_eq!(kept.len(), 4);
+        assert_eq!(kept[0], "You are terse.");
+        assert!(kept[1].starts_with("third"));
+    }
+
+    #[test]
+    fn test_middle_out_keeps_first_turn() {
+        let mut payload = long_conversation();
+        let outcome = enforce_context_limit(
+            &mut payload,
+            Protocol::OpenAI,
+            MODEL,
+            900,
+            Some(&ContextTrimming::MiddleOut),
+        );
+        assert!(matches!(outcome, ContextGuardOutcome::Trimmed { .. }));
+        let kept = contents(&payload);
+        assert_eq!(kept[0], "You are terse.");
+        assert!(kept[1].starts_with("first"));
+        assert!(kept.iter().all(|m| !m.starts_with("second")));
+        assert_eq!(kept.last().unwrap(), "latest question");
+    }
+
+    #[test]
+    fn test_trimming_fails_when_latest_turn_too_large() {
+        let mut payload = openai_payload(vec![user("old"), user(&filler(500))]);
+        let outcome = enforce_context_limit(
+            &mut payload,
+            Protocol::OpenAI,
+            MODEL,
+            100,
+            Some(&ContextTrimming::DropOldest),
+        );
+        assert!(matches!(outcome, ContextGuardOutcome::Exceeded { .. }));
+        assert_eq!(payload["messages"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_tool_pairs_are_never_split() {
+        let big = filler(300);
+        let mut payload = json!({
+            "model": MODEL,
+            "messages": [
+                user("start"),
+                {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_1", "name": "read", "input": {}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": big}
+                ]},
+                assistant("done"),
+                user("next"),
+            ]
+        });
+        let turns = split_turns(payload["messages"].as_array().unwrap());
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].range, 0..4);
+
+        let outcome = enforce_context_limit(
+            &mut payload,
+            Protocol::Anthropic,
+            MODEL,
+            50,
+            Some(&ContextTrimming::DropOldest),
+        );
+        assert!(matches!(outcome, ContextGuardOutcome::Trimmed { .. }));
+        assert_eq!(payload["messages"], json!([user("next")]));
+    }
+
+    #[test]
+    fn test_openai_tool_messages_stay_with_call() {
+        let messages = vec![
+            user("q"),
+            json!({"role": "assistant", "tool_calls": [{"id": "call_1", "type": "function"}]}),
+            json!({"role": "tool", "tool_call_id": "call_1", "content": "r"}),
+            user("q2"),
+        ];
+        let turns = split_turns(&messages);
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[1].range, 3..4);
+    }
+
+    #[test]
+    fn test_gemini_contents_are_trimmed() {
+        let big = filler(300);
+        let mut payload = json!({
+            "systemInstruction": {"parts": [{"text": "sys"}]},
+            "contents": [
+                {"role": "user", "parts": [{"text": big}]},
+                {"role": "model", "parts": [{"text": "ok"}]},
+                {"role": "user", "parts": [{"text": "now"}]},
+            ]
+        });
+        let outcome = enforce_context_limit(
+            &mut payload,
+            Protocol::Gemini,
+            MODEL,
+            50,
+            Some(&ContextTrimming::DropOldest),
+        );
+        assert!(matches!(outcome, ContextGuardOutcome::Trimmed { .. }));
+        assert_eq!(payload["contents"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_response_api_function_call_items_grouped() {
+        let messages = vec![
+            json!({"type": "message", "role": "user", "content": "q"}),
+            json!({"type": "function_call", "call_id": "c1", "name": "f", "arguments": "{}"}),
+            json!({"type": "function_call_output", "call_id": "c1", "output": "x"}),
+            json!({"type": "message", "role": "user", "content": "q2"}),
+        ];
+        let turns = split_turns(&messages);
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[0].range, 0..3);
+    }
+
+    #[test]
+    fn test_binary_data_not_counted() {
+        let image = json!({"role": "user", "content": [
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "A".repeat(100_000)}}
+        ]});
+        assert!(message_tokens(&image, MODEL) < 20);
+    }
+}
@@ -256,7 +256,7 @@ pub async fn gcp_vertex_proxy(
                 );
 
                 let request = build_upstream_request(
-                    &state.app_state.http_client,
+                    &state.app_state.provider_http_client(&provider.name)?,
                     &upstream_url,
                     &payload,
                     UpstreamAuth::Bearer(&provider.api_key),
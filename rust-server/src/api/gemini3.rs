@@ -15,7 +15,7 @@ fn is_gemini3_flash(model: &str) -> bool {
     model.to_lowercase().contains("gemini-3-flash")
 }
 
-fn is_gemini3_image(model: &str) -> bool {
+pub fn is_gemini3_image(model: &str) -> bool {
     model.to_lowercase().contains("image")
 }
 
@@ -23,7 +23,7 @@ fn dummy_thought_signature() -> String {
     BASE64_STANDARD.encode("skip_thought_signature_validator")
 }
 
-fn map_reasoning_effort_to_thinking_level(
+pub(crate) fn map_reasoning_effort_to_thinking_level(
     reasoning_effort: &str,
     model: &str,
 ) -> Option<&'static str> {
@@ -3,13 +3,15 @@
 //! This module contains all endpoint handlers including chat completions,
 //! model listings, and metrics.
 
-use crate::api::auth::{verify_auth, AuthFormat};
+use crate::api::auth::{verify_auth, verify_auth_with, AuthFormat, VerifiedJwt};
 use crate::api::disconnect::DisconnectStream;
 use crate::api::gemini3::{
     log_gemini_request_signatures, log_gemini_response_signatures, normalize_request_payload,
     normalize_response_payload, strip_gemini3_provider_fields,
 };
 use crate::api::models::*;
+use crate::api::proxy::log_guardrail_violations;
+use crate::api::stream_watchdog::StreamWatchdog;
 use crate::api::streaming::{
     calculate_message_tokens_with_tools, create_sse_stream, rewrite_model_in_response,
 };
@@ -19,6 +21,8 @@ use crate::api::upstream::{
     execute_upstream_request_or_transport_error, parse_upstream_json_or_error_with_log,
     split_upstream_status_error_with_log, StatusErrorResponseMode, UpstreamAuth, UpstreamContext,
 };
+use crate::core::config::GuardrailDirection;
+use crate::core::error_logger::ErrorLogRecord;
 use crate::core::error_types::{ERROR_TYPE_API, ERROR_TYPE_TIMEOUT};
 use crate::core::jsonl_logger::{
     log_provider_request, log_provider_response, log_request, log_response,
@@ -30,17 +34,21 @@ use crate::core::langfuse::{
 use crate::core::logging::{generate_request_id, get_api_key_name, PROVIDER_CONTEXT, REQUEST_ID};
 use crate::core::metrics::get_metrics;
 use crate::core::middleware::{extract_client, HasCredentials};
+use crate::core::provider_network::ProviderClients;
+use crate::core::tokenizer::tokenizer_registry;
 use crate::core::utils::{get_key_name, strip_provider_suffix};
 use crate::core::{AppError, RateLimiter, Result, StreamCancelHandle};
 use crate::services::ProviderService;
-use crate::transformer::Protocol;
+use crate::transformer::guardrails::{GuardrailSet, Guardrails};
+use crate::transformer::pii_redaction::{PiiRedactor, PII_REDACTION_PARAM};
+use crate::transformer::{provider_type_to_protocol, Protocol};
 use crate::with_request_context;
 use axum::{
     body::Body,
     extract::State,
     http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use prometheus::{Encoder, TextEncoder};
 use serde_json::json;
@@ -53,12 +61,26 @@ struct CachedProviderService {
     version: i64,
     service: ProviderService,
     credentials: Vec<crate::core::config::CredentialConfig>,
+    guardrails: GuardrailSet,
+    clients: ProviderClients,
+}
+
+/// Enabled guardrail rules of a runtime config
+fn convert_guardrail_rules(
+    runtime_config: &crate::core::database::RuntimeConfig,
+) -> Vec<crate::core::config::GuardrailRule> {
+    runtime_config
+        .guardrail_rules
+        .iter()
+        .filter_map(crate::core::database::GuardrailRuleEntity::to_rule)
+        .collect()
 }
 
 /// Convert database credential to config credential
 fn convert_credential(
     c: &crate::core::database::CredentialEntity,
 ) -> crate::core::config::CredentialConfig {
+    let allowed_cidrs = c.allowed_networks();
     crate::core::config::CredentialConfig {
         credential_key: c.credential_key.clone(),
         name: c.name.clone(),
@@ -69,11 +91,26 @@ fn convert_credential(
                 requests_per_second: rps as u32,
                 burst_size: rps as u32,
             }),
-        enabled: c.is_enabled,
+        enabled: c.is_enabled && allowed_cidrs.is_some(),
         allowed_models: c.allowed_models.clone(),
+        context_trimming: c.context_trimming.clone(),
+        expires_at: c.expires_at,
+        previous_key: c.previous_key(),
+        allowed_cidrs: allowed_cidrs.unwrap_or_default(),
+        pii_redaction: c.pii_redaction.clone(),
+        guardrails: c.guardrails.clone(),
     }
 }
 
+/// The config without providers whose HTTP client is unavailable
+fn routable_config(
+    mut config: crate::core::config::AppConfig,
+    clients: &ProviderClients,
+) -> crate::core::config::AppConfig {
+    config.providers.retain(|p| clients.is_available(&p.name));
+    config
+}
+
 /// Convert database provider to config provider
 fn convert_provider(
     p: &crate::core::database::ProviderEntity,
@@ -185,21 +222,40 @@ impl AppState {
         http_client: reqwest::Client,
         dynamic_config: Option<Arc<crate::core::DynamicConfig>>,
     ) -> Self {
-        let (initial_version, initial_credentials) = match &dynamic_config {
+        let (initial_version, initial_credentials, guardrail_rules) = match &dynamic_config {
             Some(dc) => {
                 let rc = dc.get_full();
                 (
                     rc.version,
                     rc.credentials.iter().map(convert_credential).collect(),
+                    convert_guardrail_rules(&rc),
                 )
             }
-            None => (0, config.credentials.clone()),
+            None => (
+                0,
+                config.credentials.clone(),
+                config.guardrail_rules.clone(),
+            ),
         };
 
+        tokenizer_registry().sync_from_providers(&config.providers);
+
+        let clients = ProviderClients::new(http_client.clone(), &config, None);
+        let provider_service = if config
+            .providers
+            .iter()
+            .all(|p| clients.is_available(&p.name))
+        {
+            provider_service
+        } else {
+            ProviderService::new(routable_config(config.clone(), &clients))
+        };
         let cached = CachedProviderService {
             version: initial_version,
             service: provider_service,
             credentials: initial_credentials,
+            guardrails: GuardrailSet::new(&guardrail_rules),
+            clients,
         };
 
         Self {
@@ -215,6 +271,7 @@ impl AppState {
     fn rebuild_cache(
         &self,
         runtime_config: &crate::core::database::RuntimeConfig,
+        previous: &CachedProviderService,
     ) -> CachedProviderService {
         let providers: Vec<_> = runtime_config
             .providers
@@ -233,18 +290,29 @@ impl AppState {
             verify_ssl: self.config.verify_ssl,
             request_timeout_secs: self.config.request_timeout_secs,
             ttft_timeout_secs: self.config.ttft_timeout_secs,
+            stream_idle_timeout_secs: self.config.stream_idle_timeout_secs,
+            sse_keepalive_secs: self.config.sse_keepalive_secs,
             credentials: credentials.clone(),
             provider_suffix: self.config.provider_suffix.clone(),
             min_tokens_limit: self.config.min_tokens_limit,
             max_tokens_limit: self.config.max_tokens_limit,
+            guardrail_rules: convert_guardrail_rules(runtime_config),
         };
 
         // Sync rate limiter with updated credentials
         self.rate_limiter.sync_from_credentials(&credentials);
+        tokenizer_registry().sync_from_providers(&app_config.providers);
 
+        let clients = ProviderClients::new(
+            self.http_client.clone(),
+            &app_config,
+            Some(&previous.clients),
+        );
         CachedProviderService {
             version: runtime_config.version,
-            service: ProviderService::new(app_config),
+            guardrails: GuardrailSet::new(&app_config.guardrail_rules),
+            service: ProviderService::new(routable_config(app_config, &clients)),
+            clients,
             credentials,
         }
     }
@@ -258,7 +326,7 @@ impl AppState {
             let runtime_config = dc.get_full();
             if cached.version != runtime_config.version {
                 // Version changed, rebuild cache
-                let new_cached = Arc::new(self.rebuild_cache(&runtime_config));
+                let new_cached = Arc::new(self.rebuild_cache(&runtime_config, &cached));
                 self.cached_service.store(new_cached);
 
                 tracing::debug!(
@@ -283,6 +351,36 @@ impl AppState {
     pub fn get_credentials(&self) -> Vec<crate::core::config::CredentialConfig> {
         self.get_cached().credentials.clone()
     }
+
+    /// HTTP client for requests to a provider (its own when it has `network` settings)
+    ///
+    /// Providers whose client cannot be built are not routed to, so this only
+    /// fails when the configuration changed after the provider was selected.
+    pub fn provider_http_client(&self, provider: &str) -> Result<reqwest::Client> {
+        self.get_cached()
+            .clients
+            .client(provider)
+            .cloned()
+            .map_err(|reason| {
+                AppError::Internal(format!(
+                    "Provider '{}' is unavailable: {}",
+                    provider, reason
+                ))
+            })
+    }
+
+    /// TTFT timeout for streams of a provider
+    pub fn ttft_timeout_secs(&self, provider: &str) -> Option<u64> {
+        self.get_cached().clients.ttft_timeout_secs(provider)
+    }
+
+    /// Guardrails for a request of a credential attached to the named rules
+    pub fn get_guardrails(&self, names: &[String]) -> Option<Guardrails> {
+        if names.is_empty() {
+            return None;
+        }
+        self.get_cached().guardrails.for_credential(names)
+    }
 }
 
 impl HasCredentials for AppState {
@@ -324,6 +422,7 @@ struct StreamingContext {
     gemini_model: Option<String>,
     prompt_tokens_for_fallback: Option<usize>,
     ttft_timeout_secs: Option<u64>,
+    watchdog: StreamWatchdog,
     generation_data: GenerationData,
     trace_id: Option<String>,
     api_key_name: String,
@@ -589,6 +688,7 @@ async fn handle_streaming_response(
         ctx.gemini_model,
         ctx.prompt_tokens_for_fallback,
         ctx.ttft_timeout_secs,
+        ctx.watchdog,
         langfuse_data,
         Some(ctx.request_id),
         Some("/v1/chat/completions".to_string()),
@@ -967,7 +1067,7 @@ pub async fn chat_completions(
                 let provider_service = state.get_provider_service();
 
                 let request = build_upstream_request(
-                    &state.http_client,
+                    &state.provider_http_client(&provider.name)?,
                     &url,
                     &payload,
                     UpstreamAuth::Bearer(&provider.api_key),
@@ -1033,7 +1133,9 @@ pub async fn chat_completions(
                             provider_name: provider.name.clone(),
                             gemini_model: gemini_model.clone(),
                             prompt_tokens_for_fallback,
-                            ttft_timeout_secs: state.config.ttft_timeout_secs,
+                            ttft_timeout_secs: state.ttft_timeout_secs(&provider.name),
+                            watchdog: StreamWatchdog::from_config(&state.config, Protocol::OpenAI)
+                                .reporting_to(provider_service.clone(), &provider.name),
                             generation_data,
                             trace_id: trace_id.clone(),
                             api_key_name: api_key_name.clone(),
@@ -1140,14 +1242,16 @@ pub async fn chat_completions(
 pub async fn completions(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    verified_jwt: Option<Extension<VerifiedJwt>>,
     Json(payload): Json<serde_json::Value>,
 ) -> Result<Response> {
     let request_id = generate_request_id();
-    let key_config = verify_auth(
+    let key_config = verify_auth_with(
         &headers,
         &state,
         AuthFormat::MultiFormat,
         Some("/v1/completions"),
+        verified_jwt.as_ref().map(|Extension(jwt)| jwt),
     )?;
     let api_key_name = get_key_name(&key_config);
 
@@ -1183,6 +1287,53 @@ pub async fn completions(
 
         let url = format!("{}/completions", provider.api_base);
 
+        // Personal data is replaced with placeholders before leaving the proxy
+        let pii_redactor = PiiRedactor::for_request(
+            provider.provider_params.get(PII_REDACTION_PARAM),
+            key_config
+                .as_ref()
+                .and_then(|config| config.pii_redaction.as_ref()),
+        )
+        .map_err(|e| AppError::Config(anyhow::anyhow!("Provider '{}': {}", provider.name, e)))?;
+        let mut payload = payload;
+
+        // Content policy rules attached to the credential
+        let guardrails = key_config
+            .as_ref()
+            .and_then(|config| state.get_guardrails(&config.guardrails));
+        let client = extract_client(&headers);
+        let violation_record = |response_status_code: Option<i32>| ErrorLogRecord {
+            request_id: request_id.clone(),
+            endpoint: "/v1/completions".to_string(),
+            client_protocol: Protocol::OpenAI.to_string(),
+            provider_name: provider.name.clone(),
+            provider_protocol: provider_type_to_protocol(&provider.provider_type).to_string(),
+            mapped_model: model_label.clone(),
+            response_status_code,
+            credential_name: api_key_name.clone(),
+            client: client.clone(),
+            ..Default::default()
+        };
+        if let (Some(guardrails), Some(fields)) = (&guardrails, payload.as_object_mut()) {
+            let checked =
+                guardrails.check_fields(fields, &["prompt", "suffix"], GuardrailDirection::Input);
+            if let Some(message) =
+                log_guardrail_violations(Some(guardrails), || violation_record(None))
+            {
+                return Err(AppError::BadRequest(message));
+            }
+            checked?;
+        }
+        let violation_record = violation_record(Some(StatusCode::OK.as_u16() as i32));
+
+        if let Some(redactor) = &pii_redactor {
+            for field in ["prompt", "suffix"] {
+                if let Some(value) = payload.get_mut(field) {
+                    redactor.redact_value(value);
+                }
+            }
+        }
+
         // Execute request within provider context scope
         PROVIDER_CONTEXT
             .scope(provider.name.clone(), async move {
@@ -1198,7 +1349,7 @@ pub async fn completions(
                 let provider_service = state.get_provider_service();
 
                 let request = build_upstream_request(
-                    &state.http_client,
+                    &state.provider_http_client(&provider.name)?,
                     &url,
                     &payload,
                     UpstreamAuth::Bearer(&provider.api_key),
@@ -1274,6 +1425,34 @@ pub async fn completions(
                         return Ok(error_response);
                     }
                 };
+                let mut response_data = response_data;
+                if let Some(redactor) = &pii_redactor {
+                    if let Some(choices) = response_data
+                        .get_mut("choices")
+                        .and_then(|c| c.as_array_mut())
+                    {
+                        for text in choices.iter_mut().filter_map(|c| c.get_mut("text")) {
+                            redactor.restore_value(text);
+                        }
+                    }
+                }
+                if let Some(guardrails) = &guardrails {
+                    let checked = response_data
+                        .get_mut("choices")
+                        .and_then(|c| c.as_array_mut())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|choice| choice.as_object_mut())
+                        .try_for_each(|choice| {
+                            guardrails.check_fields(choice, &["text"], GuardrailDirection::Output)
+                        });
+                    if let Some(message) =
+                        log_guardrail_violations(Some(guardrails), || violation_record)
+                    {
+                        return Err(AppError::BadRequest(message));
+                    }
+                    checked?;
+                }
                 let final_response = build_json_response(
                     StatusCode::OK,
                     response_data,
@@ -5,7 +5,6 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::HeaderMap,
     routing::{get, post},
     Json, Router,
 };
@@ -13,7 +12,8 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use utoipa::ToSchema;
 
-use crate::api::admin::{verify_admin_auth, AdminError, AdminState};
+use crate::api::admin::{AdminError, AdminState};
+use crate::api::admin_auth::{require_role, AdminRole};
 use crate::api::models::{CheckProviderHealthRequest, CheckProviderHealthResponse};
 use crate::services::health_check_service::{check_providers_health, HealthCheckService};
 
@@ -185,11 +185,8 @@ pub struct GetProviderHealthQuery {
 )]
 pub async fn check_health(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Json(request): Json<HealthCheckRequest>,
 ) -> Result<Json<HealthCheckResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     tracing::info!(
         provider_ids = ?request.provider_ids,
         models = ?request.models,
@@ -202,6 +199,7 @@ pub async fn check_health(
     let health_statuses = check_providers_health(
         state.dynamic_config.database(),
         &state.http_client,
+        &state.network_defaults,
         request.provider_ids,
         request.models,
         request.timeout_secs,
@@ -255,12 +253,9 @@ pub async fn check_health(
 )]
 pub async fn get_provider_health(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Path(provider_id): Path<i32>,
     Query(query): Query<GetProviderHealthQuery>,
 ) -> Result<Json<ProviderHealthStatus>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     // Get provider to verify it exists
     let db = state.dynamic_config.database();
     let _provider = db.get_provider(provider_id).await?.ok_or_else(|| {
@@ -286,6 +281,7 @@ pub async fn get_provider_health(
     let health_statuses = check_providers_health(
         db,
         &state.http_client,
+        &state.network_defaults,
         Some(vec![provider_id]),
         model_list,
         query.timeout_secs,
@@ -324,12 +320,9 @@ pub async fn get_provider_health(
 )]
 pub async fn check_provider_health_concurrent(
     State(state): State<Arc<AdminState>>,
-    headers: HeaderMap,
     Path(provider_id): Path<i32>,
     Json(request): Json<CheckProviderHealthRequest>,
 ) -> Result<Json<CheckProviderHealthResponse>, AdminError> {
-    verify_admin_auth(&headers, &state.admin_key)?;
-
     // Get provider to verify it exists
     let db = state.dynamic_config.database();
     let provider = db.get_provider(provider_id).await?.ok_or_else(|| {
@@ -357,7 +350,12 @@ pub async fn check_provider_health_concurrent(
     );
 
     // Create service and check provider health
-    let service = HealthCheckService::new(state.http_client.clone(), request.timeout_secs);
+    let service = HealthCheckService::for_provider(
+        &provider,
+        &state.http_client,
+        &state.network_defaults,
+        request.timeout_secs,
+    );
     let result = service
         .check_provider_health_concurrent(&provider, request.models, request.max_concurrent)
         .await;
@@ -373,16 +371,28 @@ pub async fn check_provider_health_concurrent(
 }
 
 /// Create health check router
-pub fn health_router() -> Router<Arc<AdminState>> {
+///
+/// Health checks send real requests upstream, so they require the operator role.
+pub fn health_router(state: &Arc<AdminState>) -> Router<Arc<AdminState>> {
     Router::new()
-        .route("/check", post(check_health))
-        .route("/providers/:provider_id", get(get_provider_health))
+        .route(
+            "/check",
+            require_role(post(check_health), state, AdminRole::Operator),
+        )
+        .route(
+            "/providers/:provider_id",
+            require_role(get(get_provider_health), state, AdminRole::Operator),
+        )
 }
 
 /// Create provider health router (for /admin/v1/providers/{id}/health endpoint)
-pub fn provider_health_router() -> Router<Arc<AdminState>> {
+pub fn provider_health_router(state: &Arc<AdminState>) -> Router<Arc<AdminState>> {
     Router::new().route(
         "/:provider_id/health",
-        post(check_provider_health_concurrent),
+        require_role(
+            post(check_provider_health_concurrent),
+            state,
+            AdminRole::Operator,
+        ),
     )
 }
@@ -0,0 +1,689 @@
+//! Fetching of remote images for providers that only accept inline data.
+//!
+//! Providers whose `image_inlining` param is enabled (the default for Gemini)
+//! get every http(s) image URL of a request replaced with base64 data:
+//!
+//! ```json
+//! "image_inlining": {
+//!     "max_fetch_bytes": 20971520,
+//!     "timeout_secs": 10,
+//!     "max_pixels": 4000000,
+//!     "max_bytes": 5242880,
+//!     "allowed_hosts": ["*.example.com"],
+//!     "denied_hosts": ["internal.example.com"]
+//! }
+//! ```
+//!
+//! `"image_inlining": true` enables the defaults, `false` disables inlining.
+//!
+//! Fetches are limited in size and time. Only hosts passing the allow/deny
+//! lists are contacted, and hosts resolving to loopback, private or
+//! link-local addresses are refused unless `allow_private_networks` is set;
+//! the checked addresses are pinned for the connection and every redirect is
+//! checked again. Images over `max_pixels` or `max_bytes` are downscaled.
+//! An invalid param disables fetching for the provider rather than falling
+//! back to the defaults, and is rejected when written through the admin API.
+//!
+//! Fetched images are cached by URL together with the fetch policy they were
+//! downloaded under, so a provider never gets an image its own limits or host
+//! lists would have refused. The cache is bounded by the total size of the
+//! cached images.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bytes::Bytes;
+use futures::future::try_join_all;
+use futures::StreamExt;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use lazy_static::lazy_static;
+use reqwest::{redirect, Url};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::api::models::Provider;
+use crate::core::error::Result;
+use crate::core::AppError;
+use crate::transformer::image_inliner::{remote_image_urls, ImageInliner, InlineImage};
+use crate::transformer::{Protocol, Transformer};
+
+/// Provider param configuring image URL inlining.
+pub const IMAGE_INLINING_PARAM: &str = "image_inlining";
+
+const DEFAULT_MAX_FETCH_BYTES: usize = 20 * 1024 * 1024;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const MAX_REDIRECTS: usize = 3;
+const CACHE_TTL: Duration = Duration::from_secs(600);
+/// Total size of the cached images, in bytes.
+const CACHE_MAX_BYTES: usize = 128 * 1024 * 1024;
+
+/// JPEG quality used when re-encoding to fit `max_bytes`.
+const JPEG_QUALITY: u8 = 85;
+/// Downscaling steps tried to fit `max_bytes` before giving up.
+const MAX_SHRINK_STEPS: usize = 6;
+
+lazy_static! {
+    static ref IMAGE_CACHE: Mutex<ImageCache> = Mutex::new(ImageCache::default());
+}
+
+/// Per-provider image inlining settings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageInliningConfig {
+    /// Whether URLs are inlined (defaults to true for Gemini providers)
+    pub enabled: Option<bool>,
+    /// Largest image downloaded, in bytes
+    pub max_fetch_bytes: usize,
+    /// Time limit of one download, redirects included
+    pub timeout_secs: u64,
+    /// Images with more pixels are downscaled to this many
+    pub max_pixels: Option<u64>,
+    /// Images with larger encodings are re-encoded and downscaled to fit
+    pub max_bytes: Option<usize>,
+    /// Host patterns allowed to be fetched (`*.example.com`); empty allows all
+    pub allowed_hosts: Vec<String>,
+    /// Host patterns never fetched
+    pub denied_hosts: Vec<String>,
+    /// Allow hosts resolving to loopback, private or link-local addresses
+    pub allow_private_networks: bool,
+}
+
+impl Default for ImageInliningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            max_fetch_bytes: DEFAULT_MAX_FETCH_BYTES,
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            max_pixels: None,
+            max_bytes: None,
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            allow_private_networks: false,
+        }
+    }
+}
+
+impl ImageInliningConfig {
+    /// Inlining settings of a provider, or `None` when inlining is disabled.
+    ///
+    /// An invalid param is an error: falling back to the defaults would drop
+    /// the operator's host lists and limits.
+    pub fn for_provider(
+        provider: &Provider,
+        protocol: Protocol,
+    ) -> std::result::Result<Option<Self>, String> {
+        let config = Self::from_param(provider.provider_params.get(IMAGE_INLINING_PARAM))?;
+        Ok(config
+            .enabled
+            .unwrap_or(protocol == Protocol::Gemini)
+            .then_some(config))
+    }
+
+    /// Parse the `image_inlining` param, `None` standing for an absent param.
+    pub fn from_param(value: Option<&Value>) -> std::result::Result<Self, String> {
+        match value {
+            None => Ok(Self::default()),
+            Some(Value::Bool(enabled)) => Ok(Self {
+                enabled: Some(*enabled),
+                ..Default::default()
+            }),
+            Some(value) => serde_json::from_value::<Self>(value.clone())
+                .map(|config| Self {
+                    enabled: config.enabled.or(Some(true)),
+                    ..config
+                })
+                .map_err(|e| format!("invalid {} param: {}", IMAGE_INLINING_PARAM, e)),
+        }
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        if self.denied_hosts.iter().any(|p| host_matches(p, &host)) {
+            return false;
+        }
+        self.allowed_hosts.is_empty() || self.allowed_hosts.iter().any(|p| host_matches(p, &host))
+    }
+}
+
+/// The parts of the fetch policy that decide whether an image may be served.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    url: String,
+    max_fetch_bytes: usize,
+    allowed_hosts: Vec<String>,
+    denied_hosts: Vec<String>,
+    allow_private_networks: bool,
+}
+
+impl CacheKey {
+    fn new(url: &str, config: &ImageInliningConfig) -> Self {
+        Self {
+            url: url.to_string(),
+            max_fetch_bytes: config.max_fetch_bytes,
+            allowed_hosts: config.allowed_hosts.clone(),
+            denied_hosts: config.denied_hosts.clone(),
+            allow_private_networks: config.allow_private_networks,
+        }
+    }
+}
+
+/// Recently fetched images, bounded by their total size.
+#[derive(Default)]
+struct ImageCache {
+    entries: HashMap<CacheKey, (Arc<FetchedImage>, Instant)>,
+    total_bytes: usize,
+}
+
+impl ImageCache {
+    fn get(&mut self, key: &CacheKey) -> Option<Arc<FetchedImage>> {
+        let (image, stored_at) = self.entries.get(key)?;
+        if stored_at.elapsed() < CACHE_TTL {
+            return Some(image.clone());
+        }
+        self.remove(key);
+        None
+    }
+
+    fn insert(&mut self, key: CacheKey, image: Arc<FetchedImage>, max_bytes: usize) {
+        let size = image.bytes.len();
+        if size > max_bytes {
+            return;
+        }
+        self.remove(&key);
+        if self.total_bytes + size > max_bytes {
+            self.entries
+                .retain(|_, (_, stored_at)| stored_at.elapsed() < CACHE_TTL);
+            self.total_bytes = self.entries.values().map(|(i, _)| i.bytes.len()).sum();
+        }
+        while self.total_bytes + size > max_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, stored_at))| *stored_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        self.total_bytes += size;
+        self.entries.insert(key, (image, Instant::now()));
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some((image, _)) = self.entries.remove(key) {
+            self.total_bytes -= image.bytes.len();
+        }
+    }
+}
+
+/// An image as downloaded.
+#[derive(Debug)]
+struct FetchedImage {
+    bytes: Bytes,
+    format: Option<ImageFormat>,
+    media_type: String,
+}
+
+/// Fetch the remote images of a request for inlining.
+///
+/// `payload` is the client request; `None` is returned when it references no
+/// remote images. Any image that cannot be fetched fails the request.
+pub async fn prefetch_images(
+    client_transformer: &dyn Transformer,
+    payload: &Value,
+    config: &ImageInliningConfig,
+) -> Result<Option<ImageInliner>> {
+    let request = client_transformer.transform_request_out(payload.clone())?;
+    let urls = remote_image_urls(&request);
+    if urls.is_empty() {
+        return Ok(None);
+    }
+    let images = try_join_all(urls.iter().map(|url| inline_image(url, config))).await?;
+    Ok(Some(ImageInliner::new(
+        urls.into_iter().zip(images).collect::<HashMap<_, _>>(),
+    )))
+}
+
+async fn inline_image(url: &str, config: &ImageInliningConfig) -> Result<InlineImage> {
+    let parsed = Url::parse(url)
+        .map_err(|e| AppError::BadRequest(format!("Invalid image URL '{}': {}", url, e)))?;
+    let addrs = check_url(&parsed, config).await?;
+
+    let key = CacheKey::new(url, config);
+    let cached = IMAGE_CACHE.lock().unwrap().get(&key);
+    let image = match cached {
+        Some(image) => image,
+        None => {
+            let image = Arc::new(fetch_image(parsed, addrs, config).await?);
+            IMAGE_CACHE
+                .lock()
+                .unwrap()
+                .insert(key, image.clone(), CACHE_MAX_BYTES);
+            image
+        }
+    };
+
+    let (bytes, media_type) = fit_image(&image, config.max_pixels, config.max_bytes)
+        .map_err(|e| AppError::BadRequest(format!("Image '{}' {}", url, e)))?;
+    Ok(InlineImage {
+        media_type,
+        data: STANDARD.encode(bytes),
+    })
+}
+
+/// Check a URL against the fetch policy and resolve the addresses to connect to.
+async fn check_url(url: &Url, config: &ImageInliningConfig) -> Result<Vec<SocketAddr>> {
+    let denied = |reason: &str| AppError::BadRequest(format!("Image URL '{}' {}", url, reason));
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(denied("must use http or https"));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| denied("has no host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    if !config.host_allowed(host) {
+        return Err(denied("is not allowed"));
+    }
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| denied(&format!("could not be resolved: {}", e)))?
+            .collect(),
+    };
+    if addrs.is_empty() {
+        return Err(denied("could not be resolved"));
+    }
+    if !config.allow_private_networks && addrs.iter().any(|a| !is_public_ip(a.ip())) {
+        return Err(denied("resolves to a non-public address"));
+    }
+    Ok(addrs)
+}
+
+async fn fetch_image(
+    mut url: Url,
+    mut addrs: Vec<SocketAddr>,
+    config: &ImageInliningConfig,
+) -> Result<FetchedImage> {
+    let failed = |url: &Url, reason: String| {
+        AppError::BadRequest(format!("Failed to fetch image '{}': {}", url, reason))
+    };
+    let deadline = Instant::now() + Duration::from_secs(config.timeout_secs);
+
+    for _ in 0..=MAX_REDIRECTS {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut builder = reqwest::Client::builder()
+            .redirect(redirect::Policy::none())
+            .timeout(remaining);
+        if let Some(host) = url.host_str() {
+            builder = builder.resolve_to_addrs(host, &addrs);
+        }
+        let client = builder.build().map_err(|e| failed(&url, e.to_string()))?;
+        let response = client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| failed(&url, e.to_string()))?;
+
+        let status = response.status();
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| failed(&url, format!("redirect {} without location", status)))?;
+            url = url
+                .join(location)
+                .map_err(|e| failed(&url, e.to_string()))?;
+            addrs = check_url(&url, config).await?;
+            continue;
+        }
+        if !status.is_success() {
+            return Err(failed(&url, format!("status {}", status)));
+        }
+        if response
+            .content_length()
+            .is_some_and(|len| len as usize > config.max_fetch_bytes)
+        {
+            return Err(failed(&url, "image too large".to_string()));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.split(';').next().unwrap_or(v).trim().to_ascii_lowercase());
+
+        let mut body = Vec::new();
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| failed(&url, e.to_string()))?;
+            if body.len() + chunk.len() > config.max_fetch_bytes {
+                return Err(failed(&url, "image too large".to_string()));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        let format = image::guess_format(&body).ok();
+        let media_type = match (format, content_type) {
+            (Some(format), _) => format.to_mime_type().to_string(),
+            (None, Some(content_type)) if content_type.starts_with("image/") => content_type,
+            _ => return Err(failed(&url, "not an image".to_string())),
+        };
+        return Ok(FetchedImage {
+            bytes: Bytes::from(body),
+            format,
+            media_type,
+        });
+    }
+    Err(failed(&url, "too many redirects".to_string()))
+}
+
+/// Downscale and re-encode an image to fit the pixel and byte limits.
+fn fit_image(
+    image: &FetchedImage,
+    max_pixels: Option<u64>,
+    max_bytes: Option<usize>,
+) -> std::result::Result<(Bytes, String), String> {
+    let original = || Ok((image.bytes.clone(), image.media_type.clone()));
+    if max_pixels.is_none() && max_bytes.is_none() {
+        return original();
+    }
+    let Some(format) = image.format else {
+        // Formats that cannot be decoded are passed through when they fit
+        return match max_bytes {
+            Some(max) if image.bytes.len() > max => Err("is too large to be resized".to_string()),
+            _ => original(),
+        };
+    };
+
+    let (width, height) = ImageReader::with_format(Cursor::new(&image.bytes), format)
+        .into_dimensions()
+        .map_err(|e| format!("could not be decoded: {}", e))?;
+    let too_many_pixels = max_pixels.is_some_and(|max| width as u64 * height as u64 > max);
+    let too_many_bytes = max_bytes.is_some_and(|max| image.bytes.len() > max);
+    if !too_many_pixels && !too_many_bytes {
+        return original();
+    }
+
+    let mut decoded = image::load_from_memory_with_format(&image.bytes, format)
+        .map_err(|e| format!("could not be decoded: {}", e))?;
+    if let Some(max) = max_pixels.filter(|_| too_many_pixels) {
+        let scale = (max as f64 / (width as f64 * height as f64)).sqrt();
+        decoded = shrink(&decoded, scale);
+    }
+
+    // Lossless formats keep their format unless they must fit a byte budget
+    let (mut encoded, mut media_type) = match format {
+        ImageFormat::Jpeg => (encode_jpeg(&decoded)?, "image/jpeg"),
+        _ => (encode_png(&decoded)?, "image/png"),
+    };
+    if let Some(max) = max_bytes {
+        let mut steps = 0;
+        if encoded.len() > max && media_type == "image/png" {
+            encoded = encode_jpeg(&decoded)?;
+            media_type = "image/jpeg";
+        }
+        while encoded.len() > max {
+            if steps == MAX_SHRINK_STEPS {
+                return Err(format!("does not fit in {} bytes", max));
+            }
+            decoded = shrink(&decoded, 0.75);
+            encoded = encode_jpeg(&decoded)?;
+            steps += 1;
+        }
+    }
+    Ok((Bytes::from(encoded), media_type.to_string()))
+}
+
+fn shrink(image: &DynamicImage, scale: f64) -> DynamicImage {
+    let width = ((image.width() as f64 * scale) as u32).max(1);
+    let height = ((image.height() as f64 * scale) as u32).max(1);
+    image.resize(width, height, FilterType::Triangle)
+}
+
+fn encode_png(image: &DynamicImage) -> std::result::Result<Vec<u8>, String> {
+    let mut out = Cursor::new(Vec::new());
+    image
+        .write_to(&mut out, ImageFormat::Png)
+        .map_err(|e| format!("could not be encoded: {}", e))?;
+    Ok(out.into_inner())
+}
+
+fn encode_jpeg(image: &DynamicImage) -> std::result::Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY)
+        .encode_image(&image.to_rgb8())
+        .map_err(|e| format!("could not be encoded: {}", e))?;
+    Ok(out)
+}
+
+/// Match a host against an exact host or a `*.domain` pattern.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+/// Whether an address is reachable on the public internet.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+    use serde_json::json;
+
+    fn provider(params: Value) -> Provider {
+        Provider {
+            name: "p".to_string(),
+            api_base: "http://localhost".to_string(),
+            api_key: "k".to_string(),
+            weight: 1,
+            model_mapping: HashMap::new(),
+            provider_type: "gemini".to_string(),
+            provider_params: serde_json::from_value(params).unwrap(),
+        }
+    }
+
+    fn png(width: u32, height: u32) -> FetchedImage {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            image::Rgb([
+                (x * 7 % 256) as u8,
+                (y * 13 % 256) as u8,
+                ((x ^ y) % 256) as u8,
+            ])
+        }));
+        FetchedImage {
+            bytes: Bytes::from(encode_png(&image).unwrap()),
+            format: Some(ImageFormat::Png),
+            media_type: "image/png".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_config_defaults_per_protocol() {
+        let config = |params: Value, protocol| {
+            ImageInliningConfig::for_provider(&provider(params), protocol).unwrap()
+        };
+        assert!(config(json!({}), Protocol::Gemini).is_some());
+        assert!(config(json!({}), Protocol::OpenAI).is_none());
+        assert!(config(json!({"image_inlining": false}), Protocol::Gemini).is_none());
+
+        let config = config(
+            json!({"image_inlining": {"max_pixels": 100}}),
+            Protocol::Anthropic,
+        )
+        .unwrap();
+        assert_eq!(config.max_pixels, Some(100));
+        assert_eq!(config.max_fetch_bytes, DEFAULT_MAX_FETCH_BYTES);
+    }
+
+    #[test]
+    fn test_invalid_config_is_an_error() {
+        for params in [
+            json!({"image_inlining": {"denied_host": ["internal.example.com"]}}),
+            json!({"image_inlining": {"max_fetch_bytes": "large"}}),
+            json!({"image_inlining": "yes"}),
+        ] {
+            assert!(
+                ImageInliningConfig::for_provider(&provider(params.clone()), Protocol::Gemini)
+                    .is_err(),
+                "{}",
+                params
+            );
+        }
+    }
+
+    #[test]
+    fn test_cache_is_keyed_by_policy() {
+        let mut cache = ImageCache::default();
+        let url = "https://img.example.com/a.png";
+        let loose = ImageInliningConfig {
+            max_fetch_bytes: 1000,
+            ..Default::default()
+        };
+        let strict = ImageInliningConfig {
+            max_fetch_bytes: 10,
+            ..Default::default()
+        };
+        let denying = ImageInliningConfig {
+            denied_hosts: vec!["img.example.com".to_string()],
+            ..loose.clone()
+        };
+        cache.insert(CacheKey::new(url, &loose), Arc::new(png(4, 4)), 1 << 20);
+
+        assert!(cache.get(&CacheKey::new(url, &loose)).is_some());
+        assert!(cache.get(&CacheKey::new(url, &strict)).is_none());
+        assert!(cache.get(&CacheKey::new(url, &denying)).is_none());
+    }
+
+    #[test]
+    fn test_cache_is_bounded_by_size() {
+        let mut cache = ImageCache::default();
+        let config = ImageInliningConfig::default();
+        let image = Arc::new(png(8, 8));
+        let size = image.bytes.len();
+        let key = |i: usize| CacheKey::new(&format!("https://example.com/{}.png", i), &config);
+
+        for i in 0..5 {
+            cache.insert(key(i), image.clone(), size * 3);
+        }
+        assert_eq!(cache.entries.len(), 3);
+        assert_eq!(cache.total_bytes, size * 3);
+        assert!(cache.get(&key(0)).is_none());
+        assert!(cache.get(&key(4)).is_some());
+
+        // Images larger than the whole cache are not kept
+        cache.insert(key(9), Arc::new(png(64, 64)), size * 3);
+        assert!(cache.get(&key(9)).is_none());
+        assert_eq!(cache.total_bytes, size * 3);
+    }
+
+    #[test]
+    fn test_host_lists() {
+        let config = ImageInliningConfig {
+            allowed_hosts: vec!["*.example.com".to_string()],
+            denied_hosts: vec!["private.example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(config.host_allowed("img.example.com"));
+        assert!(!config.host_allowed("example.com"));
+        assert!(!config.host_allowed("badexample.com"));
+        assert!(!config.host_allowed("PRIVATE.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_check_url_refuses_private_addresses() {
+        let config = ImageInliningConfig::default();
+        for url in [
+            "http://127.0.0.1/a.png",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.1.2.3/a.png",
+            "http://[::1]/a.png",
+            "file:///etc/passwd",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(check_url(&url, &config).await.is_err(), "{}", url);
+        }
+
+        let config = ImageInliningConfig {
+            allow_private_networks: true,
+            ..Default::default()
+        };
+        let url = Url::parse("http://127.0.0.1:8080/a.png").unwrap();
+        assert_eq!(
+            check_url(&url, &config).await.unwrap(),
+            vec!["127.0.0.1:8080".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_public_ip_ranges() {
+        assert!(is_public_ip("8.8.8.8".parse().unwrap()));
+        assert!(is_public_ip("2606:4700::1111".parse().unwrap()));
+        assert!(!is_public_ip("100.64.0.1".parse().unwrap()));
+        assert!(!is_public_ip("fd00::1".parse().unwrap()));
+        assert!(!is_public_ip("::ffff:192.168.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_fit_image_downscales_to_limits() {
+        let image = png(200, 100);
+        let (bytes, media_type) = fit_image(&image, None, None).unwrap();
+        assert_eq!(bytes, image.bytes);
+        assert_eq!(media_type, "image/png");
+
+        let (bytes, media_type) = fit_image(&image, Some(5_000), None).unwrap();
+        let resized = image::load_from_memory(&bytes).unwrap();
+        assert!(resized.width() as u64 * resized.height() as u64 <= 5_000);
+        assert_eq!(media_type, "image/png");
+
+        let (bytes, media_type) = fit_image(&image, None, Some(4_000)).unwrap();
+        assert!(bytes.len() <= 4_000);
+        assert_eq!(media_type, "image/jpeg");
+
+        assert!(fit_image(&image, None, Some(10)).is_err());
+    }
+}
@@ -0,0 +1,692 @@
+//! OpenAI-compatible image generation endpoints.
+//!
+//! Serves `/v1/images/generations` (JSON body) and `/v1/images/edits`
+//! (multipart form). OpenAI-type providers receive the request unchanged apart
+//! from model mapping. Gemini / GCP Vertex image models are reached through a
+//! translation to `generateContent` with the `IMAGE` response modality, and
+//! the returned `inlineData` parts are converted into `b64_json` / `url` items.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Multipart, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    Extension, Json,
+};
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+
+use crate::api::auth::VerifiedJwt;
+use crate::api::gemini3::is_gemini3_image;
+use crate::api::media::MediaRequest;
+use crate::api::models::{GcpVertexConfig, Provider};
+use crate::api::multipart::MultipartForm;
+use crate::api::proxy::ProxyState;
+use crate::api::upstream::{
+    build_gcp_vertex_url_with_actions, build_json_response, build_upstream_request,
+    parse_upstream_json_or_error_with_log, record_token_metrics, StatusErrorResponseMode,
+    UpstreamAuth,
+};
+use crate::core::config::GuardrailDirection;
+use crate::core::error_types::{ERROR_TYPE_API, ERROR_TYPE_INVALID_REQUEST};
+use crate::core::jsonl_logger::{log_provider_response, log_request};
+use crate::core::logging::PROVIDER_CONTEXT;
+use crate::core::request_logger::{log_request_record, RequestLogRecord};
+use crate::core::{AppError, Result};
+use crate::transformer::Protocol;
+use crate::with_request_context;
+
+/// Body limit for `/v1/images/edits` (multipart uploads exceed axum's 2MB default).
+pub const IMAGE_EDIT_BODY_LIMIT: usize = 50 * 1024 * 1024;
+
+/// Aspect ratios accepted by Gemini `imageConfig.aspectRatio`.
+const GEMINI_ASPECT_RATIOS: &[&str] = &[
+    "1:1", "2:3", "3:2", "3:4", "4:3", "4:5", "5:4", "9:16", "16:9", "21:9",
+];
+
+/// Normalized image request shared by the generation and edit endpoints.
+///
+/// Generations carry the JSON body in `form.fields`; edits carry the text
+/// form fields plus the uploaded images and mask.
+#[derive(Debug, Clone, Default)]
+pub struct ImageRequest {
+    pub form: MultipartForm,
+}
+
+impl ImageRequest {
+    pub fn model(&self) -> Option<&str> {
+        self.form.fields.get("model").and_then(|v| v.as_str())
+    }
+
+    pub fn prompt(&self) -> &str {
+        self.form
+            .fields
+            .get("prompt")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Number of images requested. Multipart fields arrive as strings.
+    pub fn n(&self) -> u32 {
+        match self.form.fields.get("n") {
+            Some(Value::Number(n)) => n.as_u64().unwrap_or(1) as u32,
+            Some(Value::String(s)) => s.trim().parse().unwrap_or(1),
+            _ => 1,
+        }
+        .max(1)
+    }
+
+    pub fn response_format(&self) -> &str {
+        self.form
+            .fields
+            .get("response_format")
+            .and_then(|v| v.as_str())
+            .unwrap_or("b64_json")
+    }
+
+    pub fn size(&self) -> Option<&str> {
+        self.form.fields.get("size").and_then(|v| v.as_str())
+    }
+}
+
+// ============================================================================
+// Endpoint Handlers
+// ============================================================================
+
+/// OpenAI-compatible image generation endpoint
+pub async fn image_generations(
+    State(state): State<Arc<ProxyState>>,
+    headers: HeaderMap,
+    verified_jwt: Option<Extension<VerifiedJwt>>,
+    Json(payload): Json<Value>,
+) -> Result<Response> {
+    let Value::Object(fields) = payload else {
+        return Err(AppError::BadRequest(
+            "Request body must be a JSON object".to_string(),
+        ));
+    };
+    let request = ImageRequest {
+        form: MultipartForm {
+            fields,
+            uploads: Vec::new(),
+        },
+    };
+    let verified_jwt = verified_jwt.map(|Extension(jwt)| jwt);
+    handle_image_request(
+        state,
+        headers,
+        verified_jwt.as_ref(),
+        "/v1/images/generations",
+        request,
+    )
+    .await
+}
+
+/// OpenAI-compatible image edit endpoint (multipart form data)
+pub async fn image_edits(
+    State(state): State<Arc<ProxyState>>,
+    headers: HeaderMap,
+    multipart: Multipart,
+) -> Result<Response> {
+    let request = read_image_multipart(multipart).await?;
+    handle_image_request(state, headers, None, "/v1/images/edits", request).await
+}
+
+/// Read an edit form, filling in missing upload content types from magic bytes.
+async fn read_image_multipart(multipart: Multipart) -> Result<ImageRequest> {
+    let mut form = MultipartForm::read(multipart).await?;
+    for upload in &mut form.uploads {
+        if upload.content_type.is_none() {
+            upload.content_type = detect_image_mime(&upload.data).map(str::to_string);
+        }
+    }
+    Ok(ImageRequest { form })
+}
+
+/// Shared handler for image generation and edit requests.
+async fn handle_image_request(
+    state: Arc<ProxyState>,
+    headers: HeaderMap,
+    verified_jwt: Option<&VerifiedJwt>,
+    path: &'static str,
+    mut request: ImageRequest,
+) -> Result<Response> {
+    let media = MediaRequest::authorize(
+        &state,
+        headers,
+        verified_jwt,
+        path,
+        "Image",
+        request.model(),
+    )?;
+
+    with_request_context!(
+        media.request_id.clone(),
+        media.api_key_name.clone(),
+        async move {
+            let Some(original_model) = request.model().map(|m| m.to_string()) else {
+                return Err(AppError::BadRequest("model is required".to_string()));
+            };
+            if request.prompt().is_empty() {
+                return Err(AppError::BadRequest("prompt is required".to_string()));
+            }
+            let route = match media.route(&state, &original_model, request.form.log_body()) {
+                Ok(route) => route,
+                Err(response) => return Ok(*response),
+            };
+            let fields = &mut request.form.fields;
+            if let Err(response) = route
+                .check_guardrails(fields, &["prompt"], GuardrailDirection::Input)
+                .and_then(|_| route.redact_fields(fields, &["prompt"], true))
+            {
+                return Ok(*response);
+            }
+            let provider = &route.provider;
+
+            log_request(route.request_id(), path, &provider.name, &route.log_body);
+
+            PROVIDER_CONTEXT
+                .scope(provider.name.clone(), async move {
+                    let provider = &route.provider;
+                    let upstream = match build_image_upstream_request(
+                        &state,
+                        provider,
+                        route.provider_protocol,
+                        &route.mapped_model,
+                        path,
+                        &request,
+                    ) {
+                        Ok(upstream) => upstream,
+                        Err(message) => {
+                            tracing::warn!(
+                                request_id = %route.request_id(),
+                                provider = %provider.name,
+                                model = %route.mapped_model,
+                                error = %message,
+                                "Image request not routable to provider"
+                            );
+                            return Ok(route.fail(
+                                StatusCode::BAD_REQUEST,
+                                ERROR_TYPE_INVALID_REQUEST,
+                                "invalid_request",
+                                message,
+                            ));
+                        }
+                    };
+
+                    let mode = if upstream.translated {
+                        StatusErrorResponseMode::Protocol
+                    } else {
+                        StatusErrorResponseMode::Passthrough
+                    };
+                    let response = match route.send(upstream.request, &upstream.url, mode).await {
+                        Ok(resp) => resp,
+                        Err(error_response) => return Ok(error_response),
+                    };
+
+                    let (status, response_data) = match parse_upstream_json_or_error_with_log(
+                        response,
+                        &route.upstream_ctx(),
+                        "Failed to parse image response",
+                    )
+                    .await
+                    {
+                        Ok(parsed) => parsed,
+                        Err((error_message, error_response)) => {
+                            log_request_record(RequestLogRecord {
+                                status_code: Some(StatusCode::BAD_GATEWAY.as_u16() as i32),
+                                error_category: Some("invalid_response".to_string()),
+                                error_message: Some(error_message),
+                                ..route.record()
+                            });
+                            return Ok(error_response);
+                        }
+                    };
+
+                    log_provider_response(
+                        route.request_id(),
+                        &provider.name,
+                        status.as_u16(),
+                        None,
+                        &summarize_image_response(&response_data),
+                    );
+
+                    let client_response = if upstream.translated {
+                        let created = chrono::Utc::now().timestamp();
+                        match gemini_to_openai_image_response(
+                            &response_data,
+                            request.response_format(),
+                            created,
+                        ) {
+                            Some(converted) => converted,
+                            None => {
+                                return Ok(route.fail(
+                                    StatusCode::BAD_GATEWAY,
+                                    ERROR_TYPE_API,
+                                    "invalid_response",
+                                    gemini_no_image_message(&response_data),
+                                ));
+                            }
+                        }
+                    } else {
+                        response_data
+                    };
+
+                    let (input_tokens, output_tokens) = image_usage_tokens(&client_response);
+                    if input_tokens > 0 || output_tokens > 0 {
+                        record_token_metrics(
+                            input_tokens as u64,
+                            output_tokens as u64,
+                            &route.effective_model,
+                            &provider.name,
+                            route.api_key_name(),
+                            &route.client,
+                        );
+                    }
+
+                    log_request_record(RequestLogRecord {
+                        status_code: Some(status.as_u16() as i32),
+                        input_tokens,
+                        output_tokens,
+                        total_tokens: input_tokens + output_tokens,
+                        ..route.record()
+                    });
+
+                    Ok(build_json_response(
+                        StatusCode::OK,
+                        client_response,
+                        Some(&route.effective_model),
+                        Some(&provider.name),
+                        Some(route.api_key_name()),
+                    ))
+                })
+                .await
+        }
+    )
+}
+
+/// Upstream request prepared for a specific provider.
+struct ImageUpstream {
+    request: reqwest::RequestBuilder,
+    url: String,
+    /// Whether the response must be translated back to the OpenAI images format.
+    translated: bool,
+}
+
+fn build_image_upstream_request(
+    state: &ProxyState,
+    provider: &Provider,
+    provider_protocol: Protocol,
+    mapped_model: &str,
+    path: &str,
+    request: &ImageRequest,
+) -> std::result::Result<ImageUpstream, String> {
+    let http_client = &state
+        .app_state
+        .provider_http_client(&provider.name)
+        .map_err(|e| e.to_string())?;
+    match provider_protocol {
+        Protocol::OpenAI => {
+            let endpoint = path.trim_start_matches("/v1");
+            let url = format!("{}{}", provider.api_base, endpoint);
+            let request = if request.form.uploads.is_empty() {
+                let mut payload = request.form.fields.clone();
+                payload.insert("model".to_string(), json!(mapped_model));
+                build_upstream_request(
+                    http_client,
+                    &url,
+                    &Value::Object(payload),
+                    UpstreamAuth::Bearer(&provider.api_key),
+                    None,
+                    None,
+                )
+            } else {
+                http_client
+                    .post(&url)
+                    .bearer_auth(&provider.api_key)
+                    .multipart(request.form.to_reqwest_form(mapped_model)?)
+            };
+            Ok(ImageUpstream {
+                request,
+                url,
+                translated: false,
+            })
+        }
+        Protocol::Gemini | Protocol::GcpVertex => {
+            if !is_gemini3_image(mapped_model) {
+                return Err(format!(
+                    "Model '{}' on provider '{}' does not support image generation",
+                    mapped_model, provider.name
+                ));
+            }
+            let gcp_config = GcpVertexConfig::from_provider(provider)
+                .unwrap_or_else(|| GcpVertexConfig::from_provider_with_defaults(provider));
+            // Gemini always uses generateContent, Vertex the configured action
+            let action = match provider_protocol {
+                Protocol::Gemini => "generateContent",
+                _ => gcp_config.blocking_action.as_str(),
+            };
+            let url = build_gcp_vertex_url_with_actions(
+                &provider.api_base,
+                &gcp_config.project,
+                &gcp_config.location,
+                &gcp_config.publisher,
+                mapped_model,
+                false,
+                action,
+                &gcp_config.streaming_action,
+            )?;
+            let payload = openai_image_to_gemini_request(request);
+            let request = build_upstream_request(
+                http_client,
+                &url,
+                &payload,
+                UpstreamAuth::Bearer(&provider.api_key),
+                None,
+                None,
+            );
+            Ok(ImageUpstream {
+                request,
+                url,
+                translated: true,
+            })
+        }
+        Protocol::Anthropic | Protocol::ResponseApi => Err(format!(
+            "Provider '{}' ({}) does not support image generation",
+            provider.name, provider.provider_type
+        )),
+    }
+}
+
+// ============================================================================
+// OpenAI Images <-> Gemini translation
+// ============================================================================
+
+/// Translate an OpenAI images request into a Gemini `generateContent` body.
+///
+/// Uploaded images (edits) are sent as `inlineData` parts after the prompt;
+/// `size` is mapped to `imageConfig.aspectRatio` when it reduces to a ratio
+/// Gemini supports.
+pub fn openai_image_to_gemini_request(request: &ImageRequest) -> Value {
+    let mut parts = vec![json!({"text": request.prompt()})];
+    for upload in &request.form.uploads {
+        let mime = upload.content_type.as_deref().unwrap_or("image/png");
+        parts.push(json!({
+            "inlineData": {
+                "mimeType": mime,
+                "data": BASE64_STANDARD.encode(&upload.data),
+            }
+        }));
+    }
+
+    let mut generation_config = json!({"responseModalities": ["TEXT", "IMAGE"]});
+    let n = request.n();
+    if n > 1 {
+        generation_config["candidateCount"] = json!(n);
+    }
+    if let Some(ratio) = request.size().and_then(size_to_aspect_ratio) {
+        generation_config["imageConfig"] = json!({"aspectRatio": ratio});
+    }
+
+    json!({
+        "contents": [{"role": "user", "parts": parts}],
+        "generationConfig": generation_config,
+    })
+}
+
+/// Convert a Gemini `generateContent` response into an OpenAI images response.
+///
+/// Returns `None` when the response carries no image parts. `url` output uses
+/// `data:` URLs since the proxy does not host generated images.
+pub fn gemini_to_openai_image_response(
+    response: &Value,
+    response_format: &str,
+    created: i64,
+) -> Option<Value> {
+    let mut data = Vec::new();
+    let candidates = response.get("candidates").and_then(|c| c.as_array());
+    for candidate in candidates.into_iter().flatten() {
+        let parts = candidate
+            .get("content")
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.as_array());
+        for part in parts.into_iter().flatten() {
+            let Some(inline) = part.get("inlineData").or_else(|| part.get("inline_data")) else {
+                continue;
+            };
+            let Some(b64) = inline.get("data").and_then(|d| d.as_str()) else {
+                continue;
+            };
+            let mime = inline
+                .get("mimeType")
+                .or_else(|| inline.get("mime_type"))
+                .and_then(|m| m.as_str())
+                .unwrap_or("image/png");
+            if response_format == "url" {
+                data.push(json!({"url": format!("data:{};base64,{}", mime, b64)}));
+            } else {
+                data.push(json!({"b64_json": b64}));
+            }
+        }
+    }
+    if data.is_empty() {
+        return None;
+    }
+
+    let mut result = json!({"created": created, "data": data});
+    if let Some(usage) = response.get("usageMetadata") {
+        let input = usage
+            .get("promptTokenCount")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0);
+        let output = usage
+            .get("candidatesTokenCount")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0);
+        result["usage"] = json!({
+            "input_tokens": input,
+            "output_tokens": output,
+            "total_tokens": input + output,
+        });
+    }
+    Some(result)
+}
+
+/// Map an OpenAI `size` ("1024x1536") to a Gemini aspect ratio ("2:3").
+fn size_to_aspect_ratio(size: &str) -> Option<&'static str> {
+    let (w, h) = size.split_once('x')?;
+    let (w, h): (u32, u32) = (w.trim().parse().ok()?, h.trim().parse().ok()?);
+    if w == 0 || h == 0 {
+        return None;
+    }
+    let (mut a, mut b) = (w, h);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    let ratio = format!("{}:{}", w / a, h / a);
+    GEMINI_ASPECT_RATIOS.iter().copied().find(|r| *r == ratio)
+}
+
+/// Describe why a Gemini response produced no image (e.g. safety block).
+fn gemini_no_image_message(response: &Value) -> String {
+    let finish_reason = response
+        .get("candidates")
+        .and_then(|c| c.get(0))
+        .and_then(|c| c.get("finishReason"))
+        .and_then(|r| r.as_str());
+    let block_reason = response
+        .get("promptFeedback")
+        .and_then(|f| f.get("blockReason"))
+        .and_then(|r| r.as_str());
+    match (block_reason, finish_reason) {
+        (Some(reason), _) => format!("Image generation blocked by provider: {}", reason),
+        (None, Some(reason)) => format!("Provider returned no image (finish reason: {})", reason),
+        (None, None) => "Provider returned no image".to_string(),
+    }
+}
+
+/// Token usage from an OpenAI images response (`gpt-image-*` models report it).
+fn image_usage_tokens(response: &Value) -> (i32, i32) {
+    let usage = response.get("usage");
+    let read = |key: &str| {
+        usage
+            .and_then(|u| u.get(key))
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0) as i32
+    };
+    (read("input_tokens"), read("output_tokens"))
+}
+
+/// Replace base64 payloads with their length so JSONL logs stay readable.
+fn summarize_image_response(response: &Value) -> Value {
+    match response {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| match (k.as_str(), v) {
+                    ("b64_json" | "data", Value::String(s)) if s.len() > 256 => {
+                        (k.clone(), json!(format!("<{} bytes base64>", s.len())))
+                    }
+                    _ => (k.clone(), summarize_image_response(v)),
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(summarize_image_response).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Detect image media type from magic bytes.
+fn detect_image_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::multipart::FormUpload;
+
+    fn request(fields: Value) -> ImageRequest {
+        ImageRequest {
+            form: MultipartForm {
+                fields: fields.as_object().unwrap().clone(),
+                uploads: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_image_request_accessors_accept_form_strings() {
+        let req = request(json!({"model": "m", "prompt": "a cat", "n": "3"}));
+        assert_eq!(req.model(), Some("m"));
+        assert_eq!(req.prompt(), "a cat");
+        assert_eq!(req.n(), 3);
+        assert_eq!(req.response_format(), "b64_json");
+
+        let req = request(json!({"n": 0, "response_format": "url"}));
+        assert_eq!(req.n(), 1);
+        assert_eq!(req.response_format(), "url");
+    }
+
+    #[test]
+    fn test_openai_image_to_gemini_request() {
+        let mut req = request(json!({"prompt": "a cat", "n": 2, "size": "1024x1536"}));
+        req.form.uploads.push(FormUpload {
+            field: "image".to_string(),
+            filename: Some("cat.png".to_string()),
+            content_type: Some("image/png".to_string()),
+            data: bytes::Bytes::from_static(b"png"),
+        });
+
+        let body = openai_image_to_gemini_request(&req);
+        let parts = &body["contents"][0]["parts"];
+        assert_eq!(parts[0]["text"], "a cat");
+        assert_eq!(parts[1]["inlineData"]["mimeType"], "image/png");
+        assert_eq!(
+            parts[1]["inlineData"]["data"],
+            BASE64_STANDARD.encode(b"png")
+        );
+        assert_eq!(body["generationConfig"]["candidateCount"], 2);
+        assert_eq!(
+            body["generationConfig"]["imageConfig"]["aspectRatio"],
+            "2:3"
+        );
+        assert_eq!(
+            body["generationConfig"]["responseModalities"],
+            json!(["TEXT", "IMAGE"])
+        );
+    }
+
+    #[test]
+    fn test_size_to_aspect_ratio() {
+        assert_eq!(size_to_aspect_ratio("1024x1024"), Some("1:1"));
+        assert_eq!(size_to_aspect_ratio("1792x1024"), None);
+        assert_eq!(size_to_aspect_ratio("1920x1080"), Some("16:9"));
+        assert_eq!(size_to_aspect_ratio("auto"), None);
+        assert_eq!(size_to_aspect_ratio("0x10"), None);
+    }
+
+    #[test]
+    fn test_gemini_to_openai_image_response_b64_and_url() {
+        let gemini = json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"text": "Here is your image"},
+                    {"inlineData": {"mimeType": "image/jpeg", "data": "QUJD"}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 5, "candidatesTokenCount": 1290}
+        });
+
+        let b64 = gemini_to_openai_image_response(&gemini, "b64_json", 42).unwrap();
+        assert_eq!(b64["created"], 42);
+        assert_eq!(b64["data"], json!([{"b64_json": "QUJD"}]));
+        assert_eq!(b64["usage"]["total_tokens"], 1295);
+        assert_eq!(image_usage_tokens(&b64), (5, 1290));
+
+        let url = gemini_to_openai_image_response(&gemini, "url", 42).unwrap();
+        assert_eq!(url["data"][0]["url"], "data:image/jpeg;base64,QUJD");
+    }
+
+    #[test]
+    fn test_gemini_response_without_image() {
+        let gemini = json!({
+            "candidates": [{"content": {"parts": [{"text": "no"}]}, "finishReason": "SAFETY"}]
+        });
+        assert!(gemini_to_openai_image_response(&gemini, "b64_json", 0).is_none());
+        assert_eq!(
+            gemini_no_image_message(&gemini),
+            "Provider returned no image (finish reason: SAFETY)"
+        );
+    }
+
+    #[test]
+    fn test_detect_image_mime() {
+        assert_eq!(
+            detect_image_mime(b"\x89PNG\r\n\x1a\nrest"),
+            Some("image/png")
+        );
+        assert_eq!(detect_image_mime(b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+        assert_eq!(detect_image_mime(b"RIFF0000WEBPVP8 "), Some("image/webp"));
+        assert_eq!(detect_image_mime(b"text"), None);
+    }
+
+    #[test]
+    fn test_summarize_image_response_truncates_base64() {
+        let long = "A".repeat(1000);
+        let summary = summarize_image_response(&json!({"data": [{"b64_json": long}]}));
+        assert_eq!(summary["data"][0]["b64_json"], "<1000 bytes base64>");
+    }
+}
@@ -0,0 +1,424 @@
+//! Request handling shared by the OpenAI-compatible media endpoints
+//! (`/v1/images/*` and `/v1/audio/*`).
+//!
+//! These endpoints bypass the transformer pipeline, so authentication,
+//! provider selection, the request-log record and the logging of upstream
+//! failures live here instead. The handlers only build the upstream request
+//! and interpret the response.
+
+use std::time::Instant;
+
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use serde_json::{Map, Value};
+
+use crate::api::auth::{check_model_permission, verify_auth_with, AuthFormat, VerifiedJwt};
+use crate::api::models::Provider;
+use crate::api::proxy::{log_guardrail_violations, ProxyState};
+use crate::api::upstream::{
+    build_protocol_error_response, execute_upstream_request_or_transport_error,
+    split_upstream_status_error_with_log, StatusErrorResponseMode, UpstreamContext,
+};
+use crate::core::config::{GuardrailDirection, PiiRedactionConfig};
+use crate::core::error_logger::{log_error, mask_headers, ErrorCategory, ErrorLogRecord};
+use crate::core::error_types::{ERROR_TYPE_API, ERROR_TYPE_INVALID_REQUEST};
+use crate::core::logging::generate_request_id;
+use crate::core::middleware::extract_client;
+use crate::core::request_logger::{log_request_record, RequestLogRecord};
+use crate::core::utils::{get_key_name, strip_provider_suffix};
+use crate::core::Result;
+use crate::services::ProviderService;
+use crate::transformer::pii_redaction::PII_REDACTION_PARAM;
+use crate::transformer::{provider_type_to_protocol, Guardrails, PiiRedactor, Protocol};
+
+/// An authenticated media request, before provider selection.
+pub struct MediaRequest {
+    pub request_id: String,
+    pub api_key_name: String,
+    headers: HeaderMap,
+    path: &'static str,
+    /// Capitalized request kind used in log messages ("Image", "Audio")
+    kind: &'static str,
+    /// PII redaction settings of the credential
+    pii_redaction: Option<PiiRedactionConfig>,
+    /// Content policy rules attached to the credential
+    guardrails: Option<Guardrails>,
+    start: Instant,
+}
+
+impl MediaRequest {
+    /// Authenticate the caller and check its permission for `model`.
+    ///
+    /// Multipart bodies bypass model_permission_middleware, so the check is
+    /// done here for every media endpoint. `verified_jwt` is the token the
+    /// middleware already verified for JSON bodies.
+    pub fn authorize(
+        state: &ProxyState,
+        headers: HeaderMap,
+        verified_jwt: Option<&VerifiedJwt>,
+        path: &'static str,
+        kind: &'static str,
+        model: Option<&str>,
+    ) -> Result<Self> {
+        let start = Instant::now();
+        let request_id = headers
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+            .unwrap_or_else(generate_request_id);
+        let key_config = verify_auth_with(
+            &headers,
+            &state.app_state,
+            AuthFormat::MultiFormat,
+            Some(path),
+            verified_jwt,
+        )?;
+        check_model_permission(model, &key_config)?;
+        Ok(Self {
+            request_id,
+            api_key_name: get_key_name(&key_config),
+            headers,
+            path,
+            kind,
+            guardrails: key_config
+                .as_ref()
+                .and_then(|config| state.app_state.get_guardrails(&config.guardrails)),
+            pii_redaction: key_config.and_then(|config| config.pii_redaction),
+            start,
+        })
+    }
+
+    /// Select the provider for `model`.
+    ///
+    /// A selection failure or invalid PII redaction settings are returned as
+    /// the client error response.
+    pub fn route(
+        self,
+        state: &ProxyState,
+        model: &str,
+        log_body: Value,
+    ) -> std::result::Result<MediaRoute, Box<Response>> {
+        let effective_model =
+            strip_provider_suffix(model, state.app_state.config.provider_suffix.as_deref());
+
+        let provider_service = state.app_state.get_provider_service();
+        let provider = match provider_service.get_next_provider(Some(&effective_model)) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    request_id = %self.request_id,
+                    error = %err,
+                    model = %effective_model,
+                    "Provider selection failed"
+                );
+                return Err(Box::new(build_protocol_error_response(
+                    Protocol::OpenAI,
+                    StatusCode::BAD_REQUEST,
+                    ERROR_TYPE_INVALID_REQUEST,
+                    &err,
+                    Some(&effective_model),
+                    None,
+                    Some(&self.api_key_name),
+                )));
+            }
+        };
+
+        let pii_redactor = match PiiRedactor::for_request(
+            provider.provider_params.get(PII_REDACTION_PARAM),
+            self.pii_redaction.as_ref(),
+        ) {
+            Ok(redactor) => redactor,
+            Err(err) => {
+                return Err(Box::new(build_protocol_error_response(
+                    Protocol::OpenAI,
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ERROR_TYPE_API,
+                    &format!("Provider '{}': {}", provider.name, err),
+                    Some(&effective_model),
+                    Some(&provider.name),
+                    Some(&self.api_key_name),
+                )));
+            }
+        };
+
+        let provider_protocol = provider_type_to_protocol(&provider.provider_type);
+        let mapped_model = provider.get_mapped_model(&effective_model);
+        let base_record = RequestLogRecord {
+            request_id: self.request_id.clone(),
+            endpoint: Some(self.path.to_string()),
+            credential_name: Some(self.api_key_name.clone()),
+            model_requested: Some(effective_model.clone()),
+            model_mapped: Some(mapped_model.clone()),
+            provider_name: Some(provider.name.clone()),
+            provider_type: Some(provider.provider_type.clone()),
+            client_protocol: Some(Protocol::OpenAI.to_string()),
+            provider_protocol: Some(provider_protocol.to_string()),
+            request_headers: serde_json::to_string(&mask_headers(&self.headers)).ok(),
+            ..Default::default()
+        };
+
+        Ok(MediaRoute {
+            client: extract_client(&self.headers),
+            effective_model,
+            provider_service,
+            provider,
+            provider_protocol,
+            mapped_model,
+            log_body,
+            pii_redactor,
+            base_record,
+            request: self,
+        })
+    }
+}
+
+/// A media request routed to a provider.
+pub struct MediaRoute {
+    pub client: String,
+    pub effective_model: String,
+    pub provider_service: ProviderService,
+    pub provider: Provider,
+    pub provider_protocol: Protocol,
+    pub mapped_model: String,
+    /// Request body as written to the JSONL and error logs
+    pub log_body: Value,
+    pii_redactor: Option<PiiRedactor>,
+    base_record: RequestLogRecord,
+    request: MediaRequest,
+}
+
+impl MediaRoute {
+    pub fn request_id(&self) -> &str {
+        &self.request.request_id
+    }
+
+    pub fn api_key_name(&self) -> &str {
+        &self.request.api_key_name
+    }
+
+    pub fn start(&self) -> Instant {
+        self.request.start
+    }
+
+    /// Request log record of this request, timed up to now.
+    pub fn record(&self) -> RequestLogRecord {
+        RequestLogRecord {
+            total_duration_ms: Some(elapsed_ms(self.request.start)),
+            pii_redactions: self
+                .pii_redactor
+                .as_ref()
+                .and_then(PiiRedactor::log_summary),
+            ..self.base_record.clone()
+        }
+    }
+
+    pub fn has_guardrails(&self) -> bool {
+        self.request.guardrails.is_some()
+    }
+
+    /// Apply the credential's guardrails to the text fields `names`.
+    ///
+    /// Violations are recorded in `error_logs`; a blocking rule fails the
+    /// request.
+    pub fn check_guardrails(
+        &self,
+        fields: &mut Map<String, Value>,
+        names: &[&str],
+        direction: GuardrailDirection,
+    ) -> std::result::Result<(), Box<Response>> {
+        let Some(guardrails) = &self.request.guardrails else {
+            return Ok(());
+        };
+        // A blocking rule is reported by log_guardrail_violations
+        let _ = guardrails.check_fields(fields, names, direction);
+        let blocked = log_guardrail_violations(Some(guardrails), || ErrorLogRecord {
+            request_id: self.request.request_id.clone(),
+            endpoint: self.request.path.to_string(),
+            client_protocol: Protocol::OpenAI.to_string(),
+            provider_name: self.provider.name.clone(),
+            provider_protocol: self.provider_protocol.to_string(),
+            mapped_model: self.mapped_model.clone(),
+            credential_name: self.request.api_key_name.clone(),
+            client: self.client.clone(),
+            ..Default::default()
+        });
+        match blocked {
+            Some(message) => Err(Box::new(self.fail(
+                StatusCode::BAD_REQUEST,
+                ERROR_TYPE_INVALID_REQUEST,
+                ErrorCategory::GuardrailViolation.as_str(),
+                message,
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Redact personal data in the text fields `names` of the request body.
+    ///
+    /// Media responses are not text, so placeholders cannot be restored.
+    /// Where a placeholder would end up in the output (speech input),
+    /// `allow_placeholders` is false and a field containing personal data
+    /// rejects the request instead.
+    pub fn redact_fields(
+        &self,
+        fields: &mut Map<String, Value>,
+        names: &[&str],
+        allow_placeholders: bool,
+    ) -> std::result::Result<(), Box<Response>> {
+        let Some(redactor) = &self.pii_redactor else {
+            return Ok(());
+        };
+        for name in names {
+            let Some(value) = fields.get_mut(*name) else {
+                continue;
+            };
+            let original = value.clone();
+            redactor.redact_value(value);
+            if !allow_placeholders && *value != original {
+                return Err(Box::new(self.fail(
+                    StatusCode::BAD_REQUEST,
+                    ERROR_TYPE_INVALID_REQUEST,
+                    "invalid_request",
+                    format!("'{}' contains personal data and cannot be redacted", name),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn upstream_ctx(&self) -> UpstreamContext<'_> {
+        UpstreamContext {
+            protocol: Protocol::OpenAI,
+            model: Some(&self.effective_model),
+            provider: &self.provider.name,
+            api_key_name: Some(&self.request.api_key_name),
+            request_id: Some(&self.request.request_id),
+        }
+    }
+
+    /// Log a failed request and build the client error response.
+    pub fn fail(
+        &self,
+        status: StatusCode,
+        error_type: &str,
+        category: &str,
+        message: String,
+    ) -> Response {
+        let response = self.error_response(status, error_type, &message);
+        log_request_record(RequestLogRecord {
+            status_code: Some(status.as_u16() as i32),
+            error_category: Some(category.to_string()),
+            error_message: Some(message),
+            ..self.record()
+        });
+        response
+    }
+
+    /// OpenAI-format error response attributed to this request.
+    fn error_response(&self, status: StatusCode, error_type: &str, message: &str) -> Response {
+        build_protocol_error_response(
+            Protocol::OpenAI,
+            status,
+            error_type,
+            message,
+            Some(&self.effective_model),
+            Some(&self.provider.name),
+            Some(&self.request.api_key_name),
+        )
+    }
+
+    /// Send the upstream request.
+    ///
+    /// Transport failures and error statuses are logged and returned as the
+    /// client response; `mode` decides how error bodies are relayed.
+    pub async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        url: &str,
+        mode: StatusErrorResponseMode,
+    ) -> std::result::Result<reqwest::Response, Response> {
+        let kind = self.request.kind;
+        let upstream_ctx = self.upstream_ctx();
+        let response = match execute_upstream_request_or_transport_error(
+            request,
+            &self.provider_service,
+            &upstream_ctx,
+            Some(url),
+            Some(&self.effective_model),
+            &format!("{} request failed", kind),
+        )
+        .await
+        {
+            Ok(resp) => resp,
+            Err((error_message, error_response)) => {
+                log_request_record(RequestLogRecord {
+                    error_category: Some("transport".to_string()),
+                    error_message: Some(error_message),
+                    ..self.record()
+                });
+                return Err(error_response);
+            }
+        };
+
+        let status = response.status();
+        match split_upstream_status_error_with_log(
+            response,
+            mode,
+            &upstream_ctx,
+            ERROR_TYPE_API,
+            &format!(
+                "Backend API returned error for {} request",
+                kind.to_lowercase()
+            ),
+            true,
+            true,
+        )
+        .await
+        {
+            Ok(resp) => Ok(resp),
+            Err((payload, error_response)) => {
+                let category = if status.is_server_error() {
+                    ErrorCategory::Provider5xx
+                } else {
+                    ErrorCategory::Provider4xx
+                };
+                let error_message = format!("HTTP {} from {}", status, self.provider.name);
+                if status.as_u16() != 429 {
+                    log_error(ErrorLogRecord {
+                        request_id: self.request.request_id.clone(),
+                        error_category: category,
+                        error_message: error_message.clone(),
+                        error_code: Some(status.as_u16() as i32),
+                        endpoint: self.request.path.to_string(),
+                        client_protocol: Protocol::OpenAI.to_string(),
+                        request_headers: Some(mask_headers(&self.request.headers)),
+                        request_body: Some(self.log_body.clone()),
+                        provider_name: self.provider.name.clone(),
+                        provider_api_base: self.provider.api_base.clone(),
+                        provider_protocol: self.provider_protocol.to_string(),
+                        mapped_model: self.mapped_model.clone(),
+                        response_status_code: Some(status.as_u16() as i32),
+                        response_body: Some(payload.body),
+                        credential_name: self.request.api_key_name.clone(),
+                        client: self.client.clone(),
+                        total_duration_ms: Some(elapsed_ms(self.request.start)),
+                        ..Default::default()
+                    });
+                }
+                log_request_record(RequestLogRecord {
+                    status_code: Some(status.as_u16() as i32),
+                    error_category: Some(category.as_str().to_string()),
+                    error_message: Some(error_message),
+                    ..self.record()
+                });
+                Err(error_response)
+            }
+        }
+    }
+}
+
+pub fn elapsed_ms(start: Instant) -> i32 {
+    start.elapsed().as_millis().min(i32::MAX as u128) as i32
+}
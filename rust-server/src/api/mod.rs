@@ -4,22 +4,33 @@
 //! streaming support, and admin API for the endpoints.
 
 pub mod admin;
+pub mod admin_auth;
+pub mod audio;
 pub mod auth;
 pub mod claude;
 pub mod claude_models;
+pub mod context_guard;
 pub mod disconnect;
 pub mod gcp_vertex;
 pub mod gemini3;
 pub mod handlers;
 pub mod health;
+pub mod image_fetch;
+pub mod images;
+pub mod media;
 pub mod models;
+pub mod multipart;
 pub mod proxy;
 pub mod rectifier;
+pub mod stream_watchdog;
 pub mod streaming;
+pub mod token_count;
 pub mod upstream;
+pub mod upstream_mode;
 
 // Re-export commonly used types
 pub use admin::{admin_router, combined_openapi, AdminApiDoc, AdminState, V1ApiDoc};
+pub use audio::{audio_speech, audio_transcriptions, audio_translations};
 pub use auth::{hash_key, verify_auth, AuthFormat};
 pub use claude::{count_tokens as claude_count_tokens, create_message as claude_create_message};
 pub use claude_models::{
@@ -34,13 +45,15 @@ pub use health::{
     health_router, HealthCheckRequest, HealthCheckResponse, HealthStatus, ModelHealthStatus,
     ProviderHealthStatus,
 };
+pub use images::{image_edits, image_generations};
 pub use models::{
     ApiErrorDetail, ApiErrorResponse, ChatCompletionRequest, ChatCompletionResponse,
     ModelInfoListV1, ModelInfoQueryParams, ModelInfoQueryParamsV1, ModelList,
-    PaginatedModelInfoList, Provider,
+    PaginatedModelInfoList, Provider, TokenCountResponse,
 };
 pub use proxy::{
-    chat_completions_v2, completions_v2, count_tokens_v2, handle_proxy_request, list_model_info_v1,
-    list_model_info_v2, list_models_v2, messages_v2, responses_v2, ProxyState,
+    chat_completions_v2, chat_count_tokens_v2, completions_v2, count_tokens_v2,
+    handle_proxy_request, list_model_info_v1, list_model_info_v2, list_models_v2, messages_v2,
+    responses_v2, ProxyState,
 };
 pub use streaming::{create_sse_stream, rewrite_model_in_response};
@@ -227,6 +227,14 @@ impl Provider {
     pub fn get_param(&self, key: &str) -> Option<&str> {
         self.provider_params.get(key).and_then(|v| v.as_str())
     }
+
+    /// Get a boolean flag from provider_params (missing or non-bool means false).
+    pub fn get_param_bool(&self, key: &str) -> bool {
+        self.provider_params
+            .get(key)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
 }
 
 /// GCP Vertex AI specific configuration extracted from provider_params.
@@ -455,6 +463,19 @@ pub struct ModelList {
     pub data: Vec<ModelInfo>,
 }
 
+/// Prompt token count for an OpenAI-style request.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[schema(example = json!({
+    "object": "token_count",
+    "model": "gpt-4",
+    "input_tokens": 42
+}))]
+pub struct TokenCountResponse {
+    pub object: String,
+    pub model: String,
+    pub input_tokens: i32,
+}
+
 /// LiteLLM-compatible model params for /v1/model/info.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[schema(example = json!({
@@ -0,0 +1,151 @@
+//! Multipart form helpers shared by the file-upload endpoints
+//! (`/v1/images/edits`, `/v1/audio/transcriptions`, `/v1/audio/translations`).
+//!
+//! Forms are read fully into memory so the `model` field can be mapped before
+//! the form is rebuilt for the upstream provider.
+
+use axum::extract::Multipart;
+use serde_json::{json, Map, Value};
+
+use crate::core::{AppError, Result};
+
+/// File part of a multipart request.
+#[derive(Debug, Clone)]
+pub struct FormUpload {
+    pub field: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: bytes::Bytes,
+}
+
+/// Multipart request split into text fields and file uploads.
+#[derive(Debug, Clone, Default)]
+pub struct MultipartForm {
+    /// Text fields as JSON strings, keyed by field name.
+    pub fields: Map<String, Value>,
+    pub uploads: Vec<FormUpload>,
+}
+
+impl MultipartForm {
+    /// Read every field of the multipart body.
+    pub async fn read(mut multipart: Multipart) -> Result<Self> {
+        let mut form = Self::default();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Invalid multipart body: {}", e)))?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+            let filename = field.file_name().map(|s| s.to_string());
+            let content_type = field.content_type().map(|s| s.to_string());
+            let data = field
+                .bytes()
+                .await
+                .map_err(|e| AppError::BadRequest(format!("Invalid multipart field: {}", e)))?;
+
+            if filename.is_some() {
+                form.uploads.push(FormUpload {
+                    field: name,
+                    filename,
+                    content_type,
+                    data,
+                });
+            } else {
+                let text = String::from_utf8_lossy(&data).into_owned();
+                form.fields.insert(name, Value::String(text));
+            }
+        }
+        Ok(form)
+    }
+
+    /// Rebuild the form for an upstream provider, replacing `model` with the mapped model.
+    pub fn to_reqwest_form(
+        &self,
+        mapped_model: &str,
+    ) -> std::result::Result<reqwest::multipart::Form, String> {
+        let mut form = reqwest::multipart::Form::new();
+        for (name, value) in &self.fields {
+            let text = match (name.as_str(), value) {
+                ("model", _) => mapped_model.to_string(),
+                (_, Value::String(s)) => s.clone(),
+                (_, other) => other.to_string(),
+            };
+            form = form.text(name.clone(), text);
+        }
+        for upload in &self.uploads {
+            let mut part = reqwest::multipart::Part::bytes(upload.data.to_vec());
+            if let Some(content_type) = &upload.content_type {
+                part = part
+                    .mime_str(content_type)
+                    .map_err(|e| format!("Invalid content type '{}': {}", content_type, e))?;
+            }
+            if let Some(filename) = &upload.filename {
+                part = part.file_name(filename.clone());
+            }
+            form = form.part(upload.field.clone(), part);
+        }
+        Ok(form)
+    }
+
+    /// JSON view of the form for logging, with upload bytes summarized.
+    pub fn log_body(&self) -> Value {
+        let mut body = self.fields.clone();
+        if !self.uploads.is_empty() {
+            let uploads: Vec<Value> = self
+                .uploads
+                .iter()
+                .map(|u| {
+                    json!({
+                        "field": u.field,
+                        "filename": u.filename,
+                        "content_type": u.content_type,
+                        "bytes": u.data.len(),
+                    })
+                })
+                .collect();
+            body.insert("uploads".to_string(), Value::Array(uploads));
+        }
+        Value::Object(body)
+    }
+
+    /// First upload with the given field name.
+    pub fn upload(&self, field: &str) -> Option<&FormUpload> {
+        self.uploads.iter().find(|u| u.field == field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_body_summarizes_uploads() {
+        let mut form = MultipartForm::default();
+        form.fields.insert("model".to_string(), json!("whisper-1"));
+        form.uploads.push(FormUpload {
+            field: "file".to_string(),
+            filename: Some("a.wav".to_string()),
+            content_type: Some("audio/wav".to_string()),
+            data: bytes::Bytes::from_static(b"RIFF"),
+        });
+
+        let body = form.log_body();
+        assert_eq!(body["model"], "whisper-1");
+        assert_eq!(body["uploads"][0]["filename"], "a.wav");
+        assert_eq!(body["uploads"][0]["bytes"], 4);
+        assert!(form.upload("file").is_some());
+        assert!(form.upload("image").is_none());
+    }
+
+    #[test]
+    fn test_to_reqwest_form_rejects_invalid_mime() {
+        let mut form = MultipartForm::default();
+        form.uploads.push(FormUpload {
+            field: "file".to_string(),
+            filename: None,
+            content_type: Some("not a mime".to_string()),
+            data: bytes::Bytes::new(),
+        });
+        assert!(form.to_reqwest_form("m").is_err());
+    }
+}
@@ -10,37 +10,51 @@ use std::time::Instant;
 use axum::{
     body::Body,
     extract::{Query, State},
-    http::{HeaderMap, StatusCode},
+    http::{HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use futures::StreamExt;
 use serde_json::{json, Value};
 use tokio::select;
 
-use crate::api::auth::{verify_auth, AuthFormat};
+use crate::api::auth::{
+    check_model_permission, verify_auth, verify_auth_with, AuthFormat, VerifiedJwt,
+};
 use crate::api::claude_models::{ClaudeTokenCountRequest, ClaudeTokenCountResponse};
+use crate::api::context_guard::{enforce_context_limit, ContextGuardOutcome};
 use crate::api::disconnect::DisconnectStream;
 use crate::api::gemini3::{normalize_request_payload, strip_gemini3_provider_fields};
 use crate::api::handlers::AppState;
+use crate::api::image_fetch::{prefetch_images, ImageInliningConfig};
 use crate::api::models::{
     GcpVertexConfig, LiteLlmParams, ModelInfo, ModelInfoDetails, ModelInfoEntry, ModelInfoListV1,
     ModelInfoQueryParams, ModelInfoQueryParamsV1, ModelList, PaginatedModelInfoList,
+    TokenCountResponse,
 };
 use crate::api::rectifier::sanitize_provider_payload;
+use crate::api::stream_watchdog::{stream_error_event, StreamWatchdog, WatchEvent};
 use crate::api::streaming::{
     calculate_message_tokens_with_tools, create_sse_stream, StreamRequestLogContext,
 };
+use crate::api::token_count::count_tokens_upstream;
 use crate::api::upstream::{
-    attach_response_extensions, build_json_response, build_protocol_error_response,
-    build_protocol_upstream_request, build_provider_debug_headers,
+    attach_response_extensions, build_context_length_exceeded_response, build_json_response,
+    build_protocol_error_response, build_protocol_upstream_request, build_provider_debug_headers,
     build_unexpected_status_split_response, build_upstream_request,
     execute_upstream_request_or_transport_error, finalize_non_streaming_response,
     parse_upstream_json_or_error_with_log, split_upstream_status_error_with_log,
     StatusErrorResponseMode, UpstreamAuth, UpstreamContext, UpstreamErrorPayload,
 };
+use crate::api::upstream_mode::{
+    convert_upstream_response, resolve_upstream_mode, set_upstream_stream,
+};
+use crate::core::config::UpstreamMode;
 use crate::core::error_logger::{log_error, mask_headers, ErrorCategory, ErrorLogRecord};
-use crate::core::error_types::{ERROR_TYPE_API, ERROR_TYPE_INVALID_REQUEST, ERROR_TYPE_TIMEOUT};
+use crate::core::error_types::{
+    ERROR_CATEGORY_GUARDRAIL_VIOLATION, ERROR_CATEGORY_TIMEOUT, ERROR_CODE_GUARDRAIL_VIOLATION,
+    ERROR_TYPE_API, ERROR_TYPE_INVALID_REQUEST, ERROR_TYPE_TIMEOUT,
+};
 use crate::core::header_policy::sanitize_anthropic_beta_header;
 use crate::core::jsonl_logger::{log_provider_request, log_provider_response, log_request};
 use crate::core::langfuse::{fail_generation_if_sampled, init_langfuse_trace, GenerationData};
@@ -54,12 +68,24 @@ use crate::core::stream_metrics::{record_stream_metrics, StreamStats};
 use crate::core::utils::{get_key_name, strip_provider_suffix};
 use crate::core::StreamCancelHandle;
 use crate::core::{AppError, Result};
+use crate::transformer::choices::MultiChoiceStreamState;
+use crate::transformer::pii_redaction::PII_REDACTION_PARAM;
+use crate::transformer::structured_output::{
+    add_repair_turn, unwrap_structured_output_tool, validate_structured_output,
+};
+use crate::transformer::tool_arguments::TOOL_ARGUMENT_VALIDATION_PARAM;
+use crate::transformer::tool_normalizer::TOOL_SCHEMA_PARAM;
+use crate::transformer::ResponseFormat;
 use crate::transformer::{
-    provider_type_to_protocol, CrossProtocolStreamState, Protocol, ProtocolDetector, SseEvent,
-    SseParser, TransformContext, TransformPipeline, Transformer, TransformerRegistry,
+    provider_type_to_protocol, CrossProtocolStreamState, Guardrails, PiiRedactor, Protocol,
+    ProtocolDetector, SseEvent, SseParser, ToolArgumentChecker, ToolEmulationTransformer,
+    ToolNormalizer, TransformContext, TransformPipeline, Transformer, TransformerRegistry,
 };
 use crate::with_request_context;
 
+/// Response header reporting how many prompt tokens the context guard removed.
+const CONTEXT_TRIMMED_TOKENS_HEADER: &str = "x-context-trimmed-tokens";
+
 // ============================================================================
 // Cross-Protocol Streaming State
 // ============================================================================
@@ -82,6 +108,7 @@ pub(crate) struct CrossProtocolStreamingState {
     pub(crate) first_token_time: Option<std::time::Instant>,
     pub(crate) finalized: bool,
     pub(crate) sse_parser: SseParser,
+    pub(crate) watchdog: StreamWatchdog,
     pub(crate) cancel_rx: tokio::sync::watch::Receiver<bool>,
     pub(crate) cancel_handle: crate::core::StreamCancelHandle,
     pub(crate) request_id: String,
@@ -91,6 +118,66 @@ pub(crate) struct CrossProtocolStreamingState {
     pub(crate) mapped_model: String,
     pub(crate) provider_type: String,
     pub(crate) request_headers: Option<String>,
+    pub(crate) pii_redactions: Option<Value>,
+    pub(crate) guardrails: Option<Guardrails>,
+}
+
+/// Upstream byte stream of one emulated choice, tagged with its index; `None`
+/// marks the end of that choice's stream.
+type ChoiceLane = std::pin::Pin<
+    Box<
+        dyn futures::stream::Stream<
+                Item = (
+                    usize,
+                    Option<std::result::Result<bytes::Bytes, reqwest::Error>>,
+                ),
+            > + Send,
+    >,
+>;
+
+/// Streaming state for an emulated multi-choice request (`n > 1`).
+struct MultiChoiceStreamingState {
+    lanes: futures::stream::SelectAll<ChoiceLane>,
+    stream_state: MultiChoiceStreamState,
+    parsers: Vec<SseParser>,
+    provider_t: Option<Arc<dyn Transformer>>,
+    client_t: Option<Arc<dyn Transformer>>,
+    client_protocol: Protocol,
+    provider_protocol: Protocol,
+    start_time: Instant,
+    first_token_time: Option<Instant>,
+    finalized: bool,
+    watchdog: StreamWatchdog,
+    cancel_rx: tokio::sync::watch::Receiver<bool>,
+    cancel_handle: StreamCancelHandle,
+    request_id: String,
+    endpoint: String,
+    credential_name: String,
+    model: String,
+    mapped_model: String,
+    provider: String,
+    provider_type: String,
+    client: String,
+    request_headers: Option<String>,
+    pii_redactions: Option<Value>,
+    guardrails: Option<Guardrails>,
+}
+
+// ============================================================================
+// Structured Output Validation
+// ============================================================================
+
+/// Provider param that enables server-side validation of structured outputs.
+const STRUCTURED_OUTPUT_VALIDATION_PARAM: &str = "structured_output_validation";
+
+/// Everything needed to validate a structured output and re-send the request
+/// once with a repair instruction.
+struct StructuredOutputRepair {
+    format: ResponseFormat,
+    url: String,
+    api_key: String,
+    headers: HeaderMap,
+    anthropic_beta_header: Option<String>,
 }
 
 // ============================================================================
@@ -154,6 +241,7 @@ pub struct ProxyContext {
 pub async fn handle_proxy_request(
     state: Arc<ProxyState>,
     headers: HeaderMap,
+    verified_jwt: Option<&VerifiedJwt>,
     path: &str,
     payload: Value,
 ) -> Result<Response> {
@@ -163,11 +251,12 @@ pub async fn handle_proxy_request(
         .and_then(|v| v.to_str().ok())
         .map(String::from)
         .unwrap_or_else(generate_request_id);
-    let key_config = verify_auth(
+    let key_config = verify_auth_with(
         &headers,
         &state.app_state,
         AuthFormat::MultiFormat,
         Some(path),
+        verified_jwt,
     )?;
     let api_key_name = get_key_name(&key_config);
 
@@ -229,6 +318,63 @@ pub async fn handle_proxy_request(
             "Protocol detection for streaming request"
         );
 
+        // Models without native function calling get tools through the prompt
+        let model_metadata = provider.get_model_metadata(&effective_model);
+        let tool_emulation = model_metadata
+            .as_ref()
+            .filter(|m| m.supports_function_calling == Some(false) && has_tools(&payload))
+            .map(|m| ToolEmulationTransformer::new(m.tool_prompt_template.clone()));
+        // Tool names and schemas the provider would reject are rewritten, and
+        // tool calls renamed back in the response
+        let tool_normalizer = if tool_emulation.is_none() {
+            ToolNormalizer::for_request(
+                &payload,
+                provider_protocol,
+                provider.provider_params.get(TOOL_SCHEMA_PARAM),
+            )
+        } else {
+            None
+        };
+
+        // Image URLs the provider cannot load are fetched and sent inline
+        let image_config = ImageInliningConfig::for_provider(&provider, provider_protocol)
+            .map_err(|e| {
+                AppError::Config(anyhow::anyhow!("Provider '{}': {}", provider.name, e))
+            })?;
+        let image_inliner = match image_config {
+            Some(config) => {
+                let client_transformer =
+                    state.transformer_registry.get_or_error(client_protocol)?;
+                prefetch_images(client_transformer.as_ref(), &payload, &config).await?
+            }
+            None => None,
+        };
+
+        // Upstreams pinned to one mode are called in it and converted back
+        let upstream_streaming = resolve_upstream_mode(&provider, model_metadata.as_ref())
+            .map(|mode| mode == UpstreamMode::Stream)
+            .unwrap_or(generation_data.is_streaming);
+
+        let mut tool_arguments =
+            ToolArgumentChecker::new(original_model.clone(), provider.name.clone());
+        if provider.get_param_bool(TOOL_ARGUMENT_VALIDATION_PARAM) {
+            tool_arguments = tool_arguments.with_schemas(&payload);
+        }
+
+        // Personal data is replaced with placeholders before leaving the proxy
+        let pii_redactor = PiiRedactor::for_request(
+            provider.provider_params.get(PII_REDACTION_PARAM),
+            key_config
+                .as_ref()
+                .and_then(|config| config.pii_redaction.as_ref()),
+        )
+        .map_err(|e| AppError::Config(anyhow::anyhow!("Provider '{}': {}", provider.name, e)))?;
+
+        // Content policy rules attached to the credential
+        let guardrails = key_config
+            .as_ref()
+            .and_then(|config| state.app_state.get_guardrails(&config.guardrails));
+
         // Build transform context
         let transform_ctx = TransformContext {
             request_id: request_id.clone(),
@@ -238,7 +384,19 @@ pub async fn handle_proxy_request(
             mapped_model: provider.get_mapped_model(&effective_model),
             provider_name: provider.name.clone(),
             provider_type: provider.provider_type.clone(),
-            stream: generation_data.is_streaming,
+            stream: upstream_streaming,
+            supports_pdf_input: model_metadata.as_ref().and_then(|m| m.supports_pdf_input),
+            structured_output: matches!(
+                provider_protocol,
+                Protocol::Anthropic | Protocol::GcpVertex
+            ) && client_protocol != provider_protocol
+                && requested_json_format(&state, client_protocol, &payload).is_some(),
+            image_inliner,
+            guardrails,
+            pii_redactor,
+            tool_emulation,
+            tool_normalizer,
+            tool_arguments,
             ..Default::default()
         };
 
@@ -249,16 +407,93 @@ pub async fn handle_proxy_request(
         generation_data.mapped_model = transform_ctx.mapped_model.clone();
 
         // Transform request with bypass optimization
-        let (mut provider_payload, bypassed) = state
+        let transformed = state
+            .transform_pipeline
+            .transform_request_with_bypass(payload.clone(), &transform_ctx);
+        if let Some(message) =
+            log_guardrail_violations(transform_ctx.guardrails.as_ref(), || ErrorLogRecord {
+                request_id: request_id.clone(),
+                endpoint: path.to_string(),
+                client_protocol: client_protocol.to_string(),
+                provider_name: provider.name.clone(),
+                provider_protocol: provider_protocol.to_string(),
+                mapped_model: transform_ctx.mapped_model.clone(),
+                credential_name: api_key_name.clone(),
+                client: client.clone(),
+                is_streaming: generation_data.is_streaming,
+                ..Default::default()
+            })
+        {
+            return Ok(build_protocol_error_response(
+                client_protocol,
+                StatusCode::BAD_REQUEST,
+                ERROR_TYPE_INVALID_REQUEST,
+                &message,
+                Some(&effective_model),
+                Some(&provider.name),
+                Some(&api_key_name),
+            ));
+        }
+        let (mut provider_payload, bypassed) = transformed?;
+
+        // Providers without native multi-choice support get one call per choice
+        let choice_count = state
             .transform_pipeline
-            .transform_request_with_bypass(payload.clone(), &transform_ctx)?;
+            .choice_fan_out(&payload, &transform_ctx)?;
 
         // Sanitize payload before sending to provider
         sanitize_provider_payload(&mut provider_payload);
+        if upstream_streaming != generation_data.is_streaming {
+            set_upstream_stream(&mut provider_payload, provider_protocol, upstream_streaming);
+        }
 
         // Ensure every tool_use/tool_call has a matching tool_result
         ensure_tool_use_result_pairing(&mut provider_payload);
 
+        // Reject or trim prompts over the model's input window before sending
+        let mut trimmed_tokens = 0;
+        if let Some(limit) = model_metadata.as_ref().and_then(|m| m.max_input_tokens) {
+            let trimming = key_config
+                .as_ref()
+                .and_then(|k| k.context_trimming.as_ref())
+                .or_else(|| model_metadata.as_ref()?.context_trimming.as_ref());
+            match enforce_context_limit(
+                &mut provider_payload,
+                provider_protocol,
+                &transform_ctx.mapped_model,
+                limit as usize,
+                trimming,
+            ) {
+                ContextGuardOutcome::Fits => {}
+                ContextGuardOutcome::Trimmed { removed_tokens } => {
+                    tracing::info!(
+                        request_id = %request_id,
+                        model = %effective_model,
+                        removed_tokens = removed_tokens,
+                        "Trimmed conversation to fit context window"
+                    );
+                    trimmed_tokens = removed_tokens;
+                }
+                ContextGuardOutcome::Exceeded { tokens, limit } => {
+                    tracing::warn!(
+                        request_id = %request_id,
+                        model = %effective_model,
+                        tokens = tokens,
+                        limit = limit,
+                        "Rejected request exceeding context window"
+                    );
+                    return Ok(build_context_length_exceeded_response(
+                        client_protocol,
+                        tokens,
+                        limit,
+                        Some(&effective_model),
+                        Some(&provider.name),
+                        Some(&api_key_name),
+                    ));
+                }
+            }
+        }
+
         // Record bypass or cross-protocol metrics
         let metrics = get_metrics();
         if bypassed {
@@ -318,12 +553,12 @@ pub async fn handle_proxy_request(
                     &gcp_config.location,
                     &gcp_config.publisher,
                     &transform_ctx.mapped_model,
-                    generation_data.is_streaming,
+                    upstream_streaming,
                     &blocking_act,
                     &streaming_act,
                 ) {
                     Ok(mut url) => {
-                        if provider_protocol == Protocol::Gemini && generation_data.is_streaming {
+                        if provider_protocol == Protocol::Gemini && upstream_streaming {
                             url.push_str("?alt=sse");
                         }
                         url
@@ -422,15 +657,22 @@ pub async fn handle_proxy_request(
                     headers.get("anthropic-beta").and_then(|v| v.to_str().ok()),
                 );
 
-                let request = build_protocol_upstream_request(
-                    &state.app_state.http_client,
-                    &url,
-                    provider_protocol,
-                    &provider.api_key,
-                    &headers,
-                    anthropic_beta_header.as_deref(),
-                    &provider_payload,
-                );
+                let structured_output = if !generation_data.is_streaming
+                    && !upstream_streaming
+                    && provider.get_param_bool(STRUCTURED_OUTPUT_VALIDATION_PARAM)
+                {
+                    requested_json_format(&state, client_protocol, &payload).map(|format| {
+                        StructuredOutputRepair {
+                            format,
+                            url: url.clone(),
+                            api_key: provider.api_key.clone(),
+                            headers: headers.clone(),
+                            anthropic_beta_header: anthropic_beta_header.clone(),
+                        }
+                    })
+                } else {
+                    None
+                };
 
                 let upstream_ctx = UpstreamContext {
                     protocol: client_protocol,
@@ -440,17 +682,32 @@ pub async fn handle_proxy_request(
                     request_id: Some(&request_id),
                 };
 
-                let response = match execute_upstream_request_or_transport_error(
-                    request,
-                    &provider_service,
-                    &upstream_ctx,
-                    None,
-                    Some(&effective_model),
-                    "HTTP request failed",
-                )
-                .await
+                let http_client = state.app_state.provider_http_client(&provider.name)?;
+                let upstream_requests = (0..choice_count).map(|_| {
+                    execute_upstream_request_or_transport_error(
+                        build_protocol_upstream_request(
+                            &http_client,
+                            &url,
+                            provider_protocol,
+                            &provider.api_key,
+                            &headers,
+                            anthropic_beta_header.as_deref(),
+                            &provider_payload,
+                        ),
+                        &provider_service,
+                        &upstream_ctx,
+                        None,
+                        Some(&effective_model),
+                        "HTTP request failed",
+                    )
+                });
+
+                let mut responses = match futures::future::join_all(upstream_requests)
+                    .await
+                    .into_iter()
+                    .collect::<std::result::Result<Vec<_>, _>>()
                 {
-                    Ok(resp) => resp,
+                    Ok(resps) => resps,
                     Err((error_message, error_response)) => {
                         log_request_record(RequestLogRecord {
                             request_id: request_id.clone(),
@@ -469,12 +726,26 @@ pub async fn handle_proxy_request(
                             error_category: Some("transport".to_string()),
                             error_message: Some(error_message),
                             request_headers: masked_headers_str.clone(),
+                            pii_redactions: transform_ctx
+                                .pii_redactor
+                                .as_ref()
+                                .and_then(PiiRedactor::log_summary),
                             ..Default::default()
                         });
                         return Ok(error_response);
                     }
                 };
 
+                // Surface the first failed choice through the regular error path
+                if let Some(failed) = responses
+                    .iter()
+                    .position(|r| r.status().is_client_error() || r.status().is_server_error())
+                {
+                    responses.swap(0, failed);
+                }
+                let response = responses.remove(0);
+                let extra_responses = responses;
+
                 let status = response.status();
 
                 // Handle error responses
@@ -589,12 +860,65 @@ pub async fn handle_proxy_request(
                         ),
                         error_message: Some(format!("HTTP {} from {}", status, provider.name)),
                         request_headers: masked_headers_str.clone(),
+                        pii_redactions: transform_ctx
+                            .pii_redactor
+                            .as_ref()
+                            .and_then(PiiRedactor::log_summary),
                         ..Default::default()
                     });
 
                     return Ok(error_response);
                 }
 
+                // Convert responses of an upstream pinned to the other mode
+                let (response, extra_responses) = if upstream_streaming
+                    == generation_data.is_streaming
+                {
+                    (response, extra_responses)
+                } else {
+                    let provider_t = state
+                        .transformer_registry
+                        .get_or_error(provider_protocol)?
+                        .clone();
+                    let conversions = std::iter::once(response).chain(extra_responses).map(|r| {
+                        convert_upstream_response(
+                            r,
+                            provider_t.as_ref(),
+                            generation_data.is_streaming,
+                            &effective_model,
+                            &provider.name,
+                            request_start,
+                        )
+                    });
+                    match futures::future::join_all(conversions)
+                        .await
+                        .into_iter()
+                        .collect::<Result<Vec<_>>>()
+                    {
+                        Ok(mut converted) => {
+                            let response = converted.remove(0);
+                            (response, converted)
+                        }
+                        Err(err) => {
+                            tracing::error!(
+                                request_id = %request_id,
+                                provider = %provider.name,
+                                error = %err,
+                                "Failed to convert upstream response mode"
+                            );
+                            return Ok(build_protocol_error_response(
+                                client_protocol,
+                                StatusCode::BAD_GATEWAY,
+                                ERROR_TYPE_API,
+                                &err.to_string(),
+                                Some(&effective_model),
+                                Some(&provider.name),
+                                Some(&api_key_name),
+                            ));
+                        }
+                    }
+                };
+
                 // Handle successful response
                 if generation_data.is_streaming {
                     // Pre-calculate input tokens for usage fallback
@@ -656,6 +980,7 @@ pub async fn handle_proxy_request(
                         input_tokens,
                         client.clone(),
                         masked_headers_str.clone(),
+                        extra_responses,
                     )
                     .await
                 } else {
@@ -670,11 +995,22 @@ pub async fn handle_proxy_request(
                         request_start,
                         path,
                         masked_headers_str.clone(),
+                        structured_output,
+                        extra_responses,
                     )
                     .await
                 }
             })
             .await
+            .map(|mut response| {
+                if trimmed_tokens > 0 {
+                    response.headers_mut().insert(
+                        CONTEXT_TRIMMED_TOKENS_HEADER,
+                        HeaderValue::from(trimmed_tokens),
+                    );
+                }
+                response
+            })
     })
 }
 
@@ -704,25 +1040,7 @@ pub(crate) fn ensure_tool_use_result_pairing(payload: &mut Value) {
     };
 
     // Collect all tool_result / tool role IDs present in the conversation.
-    let mut result_ids: HashSet<String> = HashSet::new();
-    for msg in messages.iter() {
-        // OpenAI format: role:"tool" with tool_call_id
-        if msg.get("role").and_then(|r| r.as_str()) == Some("tool") {
-            if let Some(id) = msg.get("tool_call_id").and_then(|v| v.as_str()) {
-                result_ids.insert(id.to_string());
-            }
-        }
-        // Anthropic format: content blocks with type:"tool_result"
-        if let Some(content) = msg.get("content").and_then(|c| c.as_array()) {
-            for block in content {
-                if block.get("type").and_then(|t| t.as_str()) == Some("tool_result") {
-                    if let Some(id) = block.get("tool_use_id").and_then(|v| v.as_str()) {
-                        result_ids.insert(id.to_string());
-                    }
-                }
-            }
-        }
-    }
+    let result_ids: HashSet<String> = messages.iter().flat_map(tool_result_ids).collect();
 
     // Walk messages, collect orphaned tool_use IDs per assistant message index.
     // We insert placeholder results right after the assistant message that
@@ -733,31 +1051,10 @@ pub(crate) fn ensure_tool_use_result_pairing(payload: &mut Value) {
         if !is_assistant {
             continue;
         }
-        let mut orphans: Vec<String> = Vec::new();
-
-        // OpenAI format: tool_calls array on assistant message
-        if let Some(calls) = msg.get("tool_calls").and_then(|v| v.as_array()) {
-            for call in calls {
-                if let Some(id) = call.get("id").and_then(|v| v.as_str()) {
-                    if !result_ids.contains(id) {
-                        orphans.push(id.to_string());
-                    }
-                }
-            }
-        }
-
-        // Anthropic format: tool_use content blocks
-        if let Some(content) = msg.get("content").and_then(|c| c.as_array()) {
-            for block in content {
-                if block.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                    if let Some(id) = block.get("id").and_then(|v| v.as_str()) {
-                        if !result_ids.contains(id) {
-                            orphans.push(id.to_string());
-                        }
-                    }
-                }
-            }
-        }
+        let orphans: Vec<String> = tool_use_ids(msg)
+            .into_iter()
+            .filter(|id| !result_ids.contains(id))
+            .collect();
 
         if !orphans.is_empty() {
             inserts.push((i, orphans));
@@ -803,6 +1100,95 @@ pub(crate) fn ensure_tool_use_result_pairing(payload: &mut Value) {
     }
 }
 
+/// IDs of the tool calls introduced by a single conversation entry.
+///
+/// Recognizes OpenAI `tool_calls`, Anthropic `tool_use` blocks, Responses API
+/// `function_call` items and Gemini `functionCall` parts (which fall back to
+/// the function name when the call carries no id).
+pub(crate) fn tool_use_ids(msg: &Value) -> Vec<String> {
+    let mut ids = Vec::new();
+    if let Some(calls) = msg.get("tool_calls").and_then(|v| v.as_array()) {
+        ids.extend(
+            calls
+                .iter()
+                .filter_map(|call| call.get("id").and_then(|v| v.as_str()))
+                .map(String::from),
+        );
+    }
+    if let Some(content) = msg.get("content").and_then(|c| c.as_array()) {
+        ids.extend(
+            content
+                .iter()
+                .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                .filter_map(|block| block.get("id").and_then(|v| v.as_str()))
+                .map(String::from),
+        );
+    }
+    if msg.get("type").and_then(|t| t.as_str()) == Some("function_call") {
+        ids.extend(
+            msg.get("call_id")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+        );
+    }
+    if let Some(parts) = msg.get("parts").and_then(|p| p.as_array()) {
+        ids.extend(
+            parts
+                .iter()
+                .filter_map(|part| part.get("functionCall"))
+                .filter_map(gemini_function_id),
+        );
+    }
+    ids
+}
+
+/// IDs of the tool calls answered by a single conversation entry.
+///
+/// Counterpart of [`tool_use_ids`] for `tool` role messages, `tool_result`
+/// blocks, `function_call_output` items and Gemini `functionResponse` parts.
+pub(crate) fn tool_result_ids(msg: &Value) -> Vec<String> {
+    let mut ids = Vec::new();
+    if msg.get("role").and_then(|r| r.as_str()) == Some("tool") {
+        ids.extend(
+            msg.get("tool_call_id")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+        );
+    }
+    if let Some(content) = msg.get("content").and_then(|c| c.as_array()) {
+        ids.extend(
+            content
+                .iter()
+                .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("tool_result"))
+                .filter_map(|block| block.get("tool_use_id").and_then(|v| v.as_str()))
+                .map(String::from),
+        );
+    }
+    if msg.get("type").and_then(|t| t.as_str()) == Some("function_call_output") {
+        ids.extend(
+            msg.get("call_id")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+        );
+    }
+    if let Some(parts) = msg.get("parts").and_then(|p| p.as_array()) {
+        ids.extend(
+            parts
+                .iter()
+                .filter_map(|part| part.get("functionResponse"))
+                .filter_map(gemini_function_id),
+        );
+    }
+    ids
+}
+
+fn gemini_function_id(call: &Value) -> Option<String> {
+    call.get("id")
+        .or_else(|| call.get("name"))
+        .and_then(|v| v.as_str())
+        .map(String::from)
+}
+
 /// Extract model from request based on protocol
 fn extract_model_from_request(payload: &Value, protocol: Protocol) -> String {
     match protocol {
@@ -819,6 +1205,14 @@ fn extract_model_from_request(payload: &Value, protocol: Protocol) -> String {
     }
 }
 
+/// Whether the client request defines any tools.
+fn has_tools(payload: &Value) -> bool {
+    payload
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .is_some_and(|tools| !tools.is_empty())
+}
+
 /// Extract stream flag from request payload
 fn extract_stream_flag(payload: &Value) -> bool {
     payload
@@ -906,6 +1300,7 @@ fn record_streaming_completion(
     output_tokens: usize,
     status_code: i32,
     error_category: Option<&str>,
+    pii_redactions: Option<Value>,
 ) {
     let stats = StreamStats {
         model: model_requested.to_string(),
@@ -944,10 +1339,13 @@ fn record_streaming_completion(
         error_category,
         request_headers,
     );
-    log_request_record(record);
+    log_request_record(RequestLogRecord {
+        pii_redactions,
+        ..record
+    });
 }
 
-fn rebuild_sse_event(event: &SseEvent) -> Option<String> {
+pub(crate) fn rebuild_sse_event(event: &SseEvent) -> Option<String> {
     if let Some(raw) = event.raw.as_ref() {
         return Some(raw.clone());
     }
@@ -1150,10 +1548,15 @@ fn record_disconnect_metrics(state: &CrossProtocolStreamingState) {
             .unwrap_or(0),
         499,
         Some("client_disconnect"),
+        state.pii_redactions.clone(),
     );
 }
 
-fn record_completion_metrics(state: &CrossProtocolStreamingState) {
+fn record_completion_metrics(
+    state: &CrossProtocolStreamingState,
+    status_code: i32,
+    error_category: Option<&str>,
+) {
     let final_usage = state.stream_state.get_final_usage(None);
     record_streaming_completion(
         &state.request_id,
@@ -1177,11 +1580,375 @@ fn record_completion_metrics(state: &CrossProtocolStreamingState) {
             .as_ref()
             .map(|u| u.output_tokens as usize)
             .unwrap_or(0),
-        200,
-        None,
+        status_code,
+        error_category,
+        state.pii_redactions.clone(),
     );
 }
 
+/// Record a request's guardrail violations in `error_logs`.
+///
+/// `record` provides the request fields of the log entry. Returns the
+/// client-facing message when a blocking rule fired.
+pub(crate) fn log_guardrail_violations(
+    guardrails: Option<&Guardrails>,
+    record: impl FnOnce() -> ErrorLogRecord,
+) -> Option<String> {
+    let guardrails = guardrails?;
+    let violations = guardrails.take_violations();
+    let blocked = guardrails.blocked_message();
+    if violations.is_empty() {
+        return blocked;
+    }
+    let summary = violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ");
+    let record = record();
+    tracing::warn!(
+        request_id = %record.request_id,
+        credential_name = %record.credential_name,
+        violations = %summary,
+        "Guardrail violations"
+    );
+    let status = blocked.as_ref().map(|_| 400);
+    log_error(ErrorLogRecord {
+        error_category: ErrorCategory::GuardrailViolation,
+        error_message: match &blocked {
+            Some(message) => format!("{}: {}", message, summary),
+            None => format!("Guardrail violations: {}", summary),
+        },
+        error_code: status,
+        response_status_code: status.or(record.response_status_code),
+        ..record
+    });
+    blocked
+}
+
+fn log_stream_guardrail_violations(state: &CrossProtocolStreamingState) -> Option<String> {
+    log_guardrail_violations(state.guardrails.as_ref(), || ErrorLogRecord {
+        request_id: state.request_id.clone(),
+        endpoint: state.endpoint.clone(),
+        client_protocol: state.client_protocol.to_string(),
+        provider_name: state.provider.clone(),
+        provider_protocol: state.provider_protocol.to_string(),
+        mapped_model: state.mapped_model.clone(),
+        credential_name: state.credential_name.clone(),
+        client: state.client.clone(),
+        is_streaming: true,
+        ..Default::default()
+    })
+}
+
+/// End a stream cut off by a guardrail with an error event after `output`.
+fn end_guardrail_blocked_stream(
+    state: &mut CrossProtocolStreamingState,
+    output: axum::body::Bytes,
+) -> axum::body::Bytes {
+    let message = log_stream_guardrail_violations(state).unwrap_or_default();
+    let mut output = output.to_vec();
+    output.extend_from_slice(
+        stream_error_event(
+            state.client_protocol,
+            400,
+            ERROR_TYPE_INVALID_REQUEST,
+            ERROR_CODE_GUARDRAIL_VIOLATION,
+            &message,
+        )
+        .as_bytes(),
+    );
+    state.cancel_handle.mark_completed();
+    record_completion_metrics(state, 400, Some(ERROR_CATEGORY_GUARDRAIL_VIOLATION));
+    state.finalized = true;
+    axum::body::Bytes::from(output)
+}
+
+fn log_multi_choice_guardrail_violations(state: &MultiChoiceStreamingState) -> Option<String> {
+    log_guardrail_violations(state.guardrails.as_ref(), || ErrorLogRecord {
+        request_id: state.request_id.clone(),
+        endpoint: state.endpoint.clone(),
+        client_protocol: state.client_protocol.to_string(),
+        provider_name: state.provider.clone(),
+        provider_protocol: state.provider_protocol.to_string(),
+        mapped_model: state.mapped_model.clone(),
+        credential_name: state.credential_name.clone(),
+        client: state.client.clone(),
+        is_streaming: true,
+        ..Default::default()
+    })
+}
+
+/// End a multi-choice stream cut off by a guardrail with an error event after `output`.
+fn end_multi_choice_guardrail_blocked(
+    state: &mut MultiChoiceStreamingState,
+    mut output: String,
+) -> String {
+    let message = log_multi_choice_guardrail_violations(state).unwrap_or_default();
+    output.push_str(&stream_error_event(
+        state.client_protocol,
+        400,
+        ERROR_TYPE_INVALID_REQUEST,
+        ERROR_CODE_GUARDRAIL_VIOLATION,
+        &message,
+    ));
+    state.cancel_handle.mark_completed();
+    record_multi_choice_completion(state, 400, Some(ERROR_CATEGORY_GUARDRAIL_VIOLATION));
+    state.finalized = true;
+    output
+}
+
+/// Transform SSE events of one choice and tag the output with its choice index.
+fn transform_choice_events(
+    state: &mut MultiChoiceStreamingState,
+    choice: usize,
+    events: Vec<SseEvent>,
+) -> String {
+    let (Some(provider_t), Some(client_t)) = (state.provider_t.clone(), state.client_t.clone())
+    else {
+        return String::new();
+    };
+    let mut output = String::new();
+    for sse_event in events {
+        let Some(raw) = rebuild_sse_event(&sse_event) else {
+            continue;
+        };
+        // Unparseable events are dropped: they cannot be attributed to a choice
+        let Ok(unified_chunks) = provider_t.transform_stream_chunk_in(&bytes::Bytes::from(raw))
+        else {
+            continue;
+        };
+        for chunk in state.stream_state.process_chunks(choice, unified_chunks) {
+            if let Ok(formatted) =
+                client_t.transform_stream_chunk_out(&chunk, state.client_protocol)
+            {
+                output.push_str(&formatted);
+            }
+        }
+    }
+    output
+}
+
+/// Format closing chunks of the multi-choice stream for the client.
+fn format_choice_chunks(
+    state: &MultiChoiceStreamingState,
+    chunks: Vec<crate::transformer::UnifiedStreamChunk>,
+) -> String {
+    let Some(client_t) = state.client_t.as_ref() else {
+        return String::new();
+    };
+    chunks
+        .iter()
+        .filter_map(|chunk| {
+            client_t
+                .transform_stream_chunk_out(chunk, state.client_protocol)
+                .ok()
+        })
+        .collect()
+}
+
+fn record_multi_choice_completion(
+    state: &MultiChoiceStreamingState,
+    status_code: i32,
+    error_category: Option<&str>,
+) {
+    let usage = state.stream_state.usage();
+    record_streaming_completion(
+        &state.request_id,
+        &state.endpoint,
+        &state.credential_name,
+        &state.model,
+        &state.mapped_model,
+        &state.provider,
+        &state.provider_type,
+        &state.client,
+        state.client_protocol,
+        state.provider_protocol,
+        state.request_headers.as_deref(),
+        state.start_time,
+        state.first_token_time,
+        usage.input_tokens.max(0) as usize,
+        usage.output_tokens.max(0) as usize,
+        status_code,
+        error_category,
+        state.pii_redactions.clone(),
+    );
+}
+
+/// Stream an emulated multi-choice request.
+///
+/// The upstream streams are read concurrently and interleaved into one client
+/// stream, each chunk carrying the index of the choice it belongs to.
+#[allow(clippy::too_many_arguments)]
+fn stream_multi_choice_response(
+    responses: Vec<reqwest::Response>,
+    state: &Arc<ProxyState>,
+    ctx: &TransformContext,
+    watchdog: StreamWatchdog,
+    api_key_name: &str,
+    request_start: Instant,
+    endpoint: &str,
+    input_tokens: Option<usize>,
+    client: String,
+    masked_headers: Option<String>,
+) -> Response {
+    let choices = responses.len();
+    let lanes = futures::stream::select_all(responses.into_iter().enumerate().map(
+        |(choice, response)| -> ChoiceLane {
+            Box::pin(
+                response
+                    .bytes_stream()
+                    .map(move |chunk| (choice, Some(chunk)))
+                    .chain(futures::stream::once(async move { (choice, None) })),
+            )
+        },
+    ));
+
+    let cancel_handle = StreamCancelHandle::new();
+    let streaming_state = MultiChoiceStreamingState {
+        lanes,
+        stream_state: {
+            let mut stream_state =
+                MultiChoiceStreamState::new(&ctx.original_model, choices, input_tokens);
+            if ctx.tool_emulation.is_some() {
+                stream_state = stream_state.with_tool_emulation();
+            }
+            if ctx.structured_output {
+                stream_state = stream_state.with_structured_output();
+            }
+            if let Some(ref normalizer) = ctx.tool_normalizer {
+                stream_state = stream_state.with_tool_normalizer(normalizer);
+            }
+            if let Some(ref redactor) = ctx.pii_redactor {
+                stream_state = stream_state.with_pii_restorer(redactor);
+            }
+            if let Some(ref guardrails) = ctx.guardrails {
+                stream_state = stream_state.with_guardrails(guardrails);
+            }
+            stream_state.with_tool_arguments(&ctx.tool_arguments)
+        },
+        parsers: (0..choices).map(|_| SseParser::new()).collect(),
+        provider_t: state
+            .transformer_registry
+            .get(ctx.provider_protocol)
+            .cloned(),
+        client_t: state.transformer_registry.get(ctx.client_protocol).cloned(),
+        client_protocol: ctx.client_protocol,
+        provider_protocol: ctx.provider_protocol,
+        start_time: request_start,
+        first_token_time: None,
+        finalized: false,
+        watchdog,
+        cancel_rx: cancel_handle.subscribe(),
+        cancel_handle: cancel_handle.clone(),
+        request_id: ctx.request_id.clone(),
+        endpoint: endpoint.to_string(),
+        credential_name: api_key_name.to_string(),
+        model: ctx.original_model.clone(),
+        mapped_model: ctx.mapped_model.clone(),
+        provider: ctx.provider_name.clone(),
+        provider_type: ctx.provider_type.clone(),
+        client,
+        request_headers: masked_headers,
+        pii_redactions: ctx.pii_redactor.as_ref().and_then(PiiRedactor::log_summary),
+        guardrails: ctx.guardrails.clone(),
+    };
+
+    let transform_stream = futures::stream::unfold(streaming_state, |mut state| async move {
+        if state.finalized {
+            return None;
+        }
+
+        let next = {
+            let lane_future = state.watchdog.next(&mut state.lanes);
+            let cancel_future = async {
+                let mut rx = state.cancel_rx.clone();
+                let _ = rx.changed().await;
+            };
+            select! {
+                next = lane_future => next,
+                _ = cancel_future => {
+                    tracing::info!(
+                        provider = %state.provider,
+                        model = %state.model,
+                        "Client disconnected during multi-choice streaming, cancelling streams"
+                    );
+                    get_metrics().client_disconnects_total.inc();
+                    record_multi_choice_completion(&state, 499, Some("client_disconnect"));
+                    return None;
+                }
+            }
+        };
+
+        let next = match next {
+            WatchEvent::Item(next) => next,
+            WatchEvent::Keepalive => {
+                let heartbeat = state.watchdog.keepalive_event();
+                return Some((Ok(axum::body::Bytes::from(heartbeat)), state));
+            }
+            WatchEvent::IdleTimeout => {
+                let output = state.watchdog.idle_timeout_event();
+                state.cancel_handle.mark_completed();
+                record_multi_choice_completion(&state, 504, Some(ERROR_CATEGORY_TIMEOUT));
+                state.finalized = true;
+                return Some((Ok(axum::body::Bytes::from(output)), state));
+            }
+        };
+
+        match next {
+            Some((choice, Some(Ok(bytes)))) => {
+                if state.first_token_time.is_none() {
+                    state.first_token_time = Some(Instant::now());
+                }
+                let events = state.parsers[choice].parse(&bytes);
+                let output = transform_choice_events(&mut state, choice, events);
+                if state.stream_state.guardrail_blocked() {
+                    let output = end_multi_choice_guardrail_blocked(&mut state, output);
+                    return Some((Ok(axum::body::Bytes::from(output)), state));
+                }
+                Some((
+                    Ok::<_, std::io::Error>(axum::body::Bytes::from(output)),
+                    state,
+                ))
+            }
+            Some((_, Some(Err(e)))) => Some((Err(std::io::Error::other(e.to_string())), state)),
+            Some((choice, None)) => {
+                // Flush the choice's parser buffer and close its blocks
+                let events = state.parsers[choice].parse(b"\n\n");
+                let mut output = transform_choice_events(&mut state, choice, events);
+                let closing = state.stream_state.finish_choice(choice);
+                output.push_str(&format_choice_chunks(&state, closing));
+                if state.stream_state.guardrail_blocked() {
+                    output = end_multi_choice_guardrail_blocked(&mut state, output);
+                }
+                Some((Ok(axum::body::Bytes::from(output)), state))
+            }
+            None => {
+                let closing = state.stream_state.finalize();
+                let output = format_choice_chunks(&state, closing);
+                state.cancel_handle.mark_completed();
+                log_multi_choice_guardrail_violations(&state);
+                record_multi_choice_completion(&state, 200, None);
+                state.finalized = true;
+                Some((Ok(axum::body::Bytes::from(output)), state))
+            }
+        }
+    });
+
+    let body = Body::from_stream(DisconnectStream {
+        stream: Body::from_stream(transform_stream).into_data_stream(),
+        cancel_handle,
+    });
+
+    Response::builder()
+        .status(200)
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .header("Connection", "keep-alive")
+        .body(body)
+        .unwrap()
+}
+
 /// Handle streaming response with protocol conversion
 #[allow(clippy::too_many_arguments)]
 async fn handle_streaming_proxy_response(
@@ -1197,6 +1964,7 @@ async fn handle_streaming_proxy_response(
     input_tokens: Option<usize>,
     client: String,
     masked_headers: Option<String>,
+    extra_responses: Vec<reqwest::Response>,
 ) -> Result<Response> {
     let client_protocol = ctx.client_protocol;
     let provider_protocol = ctx.provider_protocol;
@@ -1205,8 +1973,17 @@ async fn handle_streaming_proxy_response(
     let provider_type_str = ctx.provider_type.clone();
     let request_id = ctx.request_id.clone();
 
+    // Heartbeats and idle timeout errors are sent in the client's protocol
+    let watchdog = StreamWatchdog::from_config(&state.app_state.config, client_protocol)
+        .reporting_to(state.app_state.get_provider_service(), &provider_name);
+
     // For same-protocol streaming, we can use bypass optimization
-    if client_protocol == provider_protocol {
+    if client_protocol == provider_protocol
+        && ctx.tool_emulation.is_none()
+        && ctx.tool_normalizer.is_none()
+        && ctx.pii_redactor.is_none()
+        && ctx.guardrails.is_none()
+    {
         // Direct passthrough with model rewriting
         let langfuse_data = if trace_id.is_some() {
             Some(generation_data)
@@ -1224,7 +2001,8 @@ async fn handle_streaming_proxy_response(
             provider_name.clone(),
             Some(ctx.mapped_model.clone()),
             input_tokens,
-            state.app_state.config.ttft_timeout_secs,
+            state.app_state.ttft_timeout_secs(&provider_name),
+            watchdog,
             langfuse_data,
             Some(request_id.clone()),
             Some(endpoint.to_string()),
@@ -1275,6 +2053,28 @@ async fn handle_streaming_proxy_response(
                 ))
             }
         }
+    } else if !extra_responses.is_empty() {
+        let mut responses = extra_responses;
+        responses.insert(0, response);
+        let mut resp = stream_multi_choice_response(
+            responses,
+            state,
+            &ctx,
+            watchdog,
+            api_key_name,
+            request_start,
+            endpoint,
+            input_tokens,
+            client,
+            masked_headers,
+        );
+        attach_response_extensions(
+            &mut resp,
+            Some(&model_label),
+            Some(&provider_name),
+            Some(api_key_name),
+        );
+        Ok(resp)
     } else {
         // Cross-protocol streaming requires chunk-by-chunk transformation
         // with state tracking to emit proper event sequences
@@ -1297,7 +2097,26 @@ async fn handle_streaming_proxy_response(
         let masked = masked_headers;
         let streaming_state = CrossProtocolStreamingState {
             stream: Box::pin(response.bytes_stream()),
-            stream_state: CrossProtocolStreamState::with_input_tokens(&model_label, input_tokens),
+            stream_state: {
+                let mut stream_state =
+                    CrossProtocolStreamState::with_input_tokens(&model_label, input_tokens);
+                if ctx.tool_emulation.is_some() {
+                    stream_state = stream_state.with_tool_emulation();
+                }
+                if ctx.structured_output {
+                    stream_state = stream_state.with_structured_output();
+                }
+                if let Some(ref normalizer) = ctx.tool_normalizer {
+                    stream_state = stream_state.with_tool_normalizer(normalizer.clone());
+                }
+                if let Some(ref redactor) = ctx.pii_redactor {
+                    stream_state = stream_state.with_pii_restorer(redactor.clone());
+                }
+                if let Some(ref guardrails) = ctx.guardrails {
+                    stream_state = stream_state.with_guardrails(guardrails.clone());
+                }
+                stream_state.with_tool_arguments(ctx.tool_arguments.clone())
+            },
             registry: state.transformer_registry.clone(),
             client_protocol,
             provider_protocol,
@@ -1308,6 +2127,7 @@ async fn handle_streaming_proxy_response(
             first_token_time: None,
             finalized: false,
             sse_parser: SseParser::new(),
+            watchdog,
             cancel_rx,
             cancel_handle: cancel_handle_for_completion,
             request_id: request_id.clone(),
@@ -1317,6 +2137,8 @@ async fn handle_streaming_proxy_response(
             mapped_model: ctx.mapped_model.clone(),
             provider_type: provider_type_str.clone(),
             request_headers: masked,
+            pii_redactions: ctx.pii_redactor.as_ref().and_then(PiiRedactor::log_summary),
+            guardrails: ctx.guardrails.clone(),
         };
 
         let transform_stream = futures::stream::unfold(streaming_state, |mut state| async move {
@@ -1325,7 +2147,7 @@ async fn handle_streaming_proxy_response(
             }
 
             let chunk_result = {
-                let stream_future = state.stream.next();
+                let stream_future = state.watchdog.next(&mut state.stream);
                 let cancel_future = async {
                     let mut rx = state.cancel_rx.clone();
                     let _ = rx.changed().await;
@@ -1344,16 +2166,40 @@ async fn handle_streaming_proxy_response(
                 }
             };
 
+            let chunk_result = match chunk_result {
+                WatchEvent::Item(chunk) => chunk,
+                WatchEvent::Keepalive => {
+                    let heartbeat = state.watchdog.keepalive_event();
+                    return Some((Ok(axum::body::Bytes::from(heartbeat)), state));
+                }
+                WatchEvent::IdleTimeout => {
+                    let output = state.watchdog.idle_timeout_event();
+                    state.cancel_handle.mark_completed();
+                    record_completion_metrics(&state, 504, Some(ERROR_CATEGORY_TIMEOUT));
+                    state.finalized = true;
+                    return Some((Ok(axum::body::Bytes::from(output)), state));
+                }
+            };
+
             match chunk_result {
                 Some(Ok(bytes)) => {
                     let output = process_stream_bytes(&mut state, &bytes);
+                    if state.stream_state.guardrail_blocked() {
+                        let output = end_guardrail_blocked_stream(&mut state, output);
+                        return Some((Ok(output), state));
+                    }
                     Some((Ok::<_, std::io::Error>(output), state))
                 }
                 Some(Err(e)) => Some((Err(std::io::Error::other(e.to_string())), state)),
                 None => {
                     let output = finalize_cross_protocol_stream(&mut state);
+                    if state.stream_state.guardrail_blocked() {
+                        let output = end_guardrail_blocked_stream(&mut state, output);
+                        return Some((Ok(output), state));
+                    }
                     state.cancel_handle.mark_completed();
-                    record_completion_metrics(&state);
+                    log_stream_guardrail_violations(&state);
+                    record_completion_metrics(&state, 200, None);
                     state.finalized = true;
                     Some((Ok(output), state))
                 }
@@ -1396,10 +2242,12 @@ async fn handle_non_streaming_proxy_response(
     mut generation_data: GenerationData,
     trace_id: Option<String>,
     api_key_name: &str,
-    _request_payload: Value,
+    request_payload: Value,
     request_start: Instant,
     endpoint: &str,
     masked_headers: Option<String>,
+    structured_output: Option<StructuredOutputRepair>,
+    extra_responses: Vec<reqwest::Response>,
 ) -> Result<Response> {
     let client_protocol = ctx.client_protocol;
     let model_label = ctx.original_model.clone();
@@ -1477,10 +2325,82 @@ async fn handle_non_streaming_proxy_response(
         );
     }
 
+    // Validate structured output and retry once with a repair turn if needed
+    let response_data = match structured_output {
+        Some(repair) => {
+            enforce_structured_output(state, &ctx, &repair, &request_payload, response_data).await
+        }
+        None => response_data,
+    };
+
+    // Collect the other choices of an emulated multi-choice request
+    let mut choice_data = Vec::with_capacity(extra_responses.len());
+    for extra_response in extra_responses {
+        match parse_upstream_json_or_error_with_log(
+            extra_response,
+            &UpstreamContext {
+                protocol: client_protocol,
+                model: Some(&model_label),
+                provider: &provider_name,
+                api_key_name: Some(api_key_name),
+                request_id: Some(&request_id),
+            },
+            "Failed to parse response",
+        )
+        .await
+        {
+            Ok((extra_status, data)) => {
+                log_provider_response(
+                    &request_id,
+                    &provider_name,
+                    extra_status.as_u16(),
+                    None,
+                    &data,
+                );
+                choice_data.push(data);
+            }
+            Err((error_message, error_response)) => {
+                fail_generation_if_sampled(&trace_id, &mut generation_data, error_message);
+                return Ok(error_response);
+            }
+        }
+    }
+
     // Transform response using pipeline with bypass optimization
-    let (client_response, bypassed) = state
-        .transform_pipeline
-        .transform_response_with_bypass(response_data, &ctx)?;
+    let transformed = if choice_data.is_empty() {
+        state
+            .transform_pipeline
+            .transform_response_with_bypass(response_data, &ctx)
+    } else {
+        choice_data.insert(0, response_data);
+        state
+            .transform_pipeline
+            .transform_responses(choice_data, &ctx)
+            .map(|response| (response, false))
+    };
+    if let Some(message) = log_guardrail_violations(ctx.guardrails.as_ref(), || ErrorLogRecord {
+        request_id: request_id.clone(),
+        endpoint: endpoint.to_string(),
+        client_protocol: ctx.client_protocol.to_string(),
+        provider_name: provider_name.clone(),
+        provider_protocol: ctx.provider_protocol.to_string(),
+        mapped_model: ctx.mapped_model.clone(),
+        response_status_code: Some(status.as_u16() as i32),
+        credential_name: api_key_name.to_string(),
+        ..Default::default()
+    }) {
+        fail_generation_if_sampled(&trace_id, &mut generation_data, message.clone());
+        return Ok(build_protocol_error_response(
+            client_protocol,
+            StatusCode::BAD_REQUEST,
+            ERROR_TYPE_INVALID_REQUEST,
+            &message,
+            Some(&model_label),
+            Some(&provider_name),
+            Some(api_key_name),
+        ));
+    }
+    let (client_response, bypassed) = transformed?;
 
     // Record bypass metrics for response
     if bypassed {
@@ -1533,6 +2453,7 @@ async fn handle_non_streaming_proxy_response(
         total_tokens: input_tokens + output_tokens,
         total_duration_ms: Some(request_start.elapsed().as_millis().min(i32::MAX as u128) as i32),
         request_headers: masked_headers,
+        pii_redactions: ctx.pii_redactor.as_ref().and_then(PiiRedactor::log_summary),
         ..Default::default()
     });
 
@@ -1548,6 +2469,158 @@ async fn handle_non_streaming_proxy_response(
     ))
 }
 
+/// JSON response format requested by the client, if any.
+fn requested_json_format(
+    state: &ProxyState,
+    client_protocol: Protocol,
+    payload: &Value,
+) -> Option<ResponseFormat> {
+    state
+        .transformer_registry
+        .get(client_protocol)?
+        .transform_request_out(payload.clone())
+        .ok()?
+        .parameters
+        .response_format
+        .filter(|format| format.is_json())
+}
+
+/// Extract the model's text answer from a raw provider response.
+fn provider_output_text(
+    state: &ProxyState,
+    ctx: &TransformContext,
+    response_data: &Value,
+) -> Option<String> {
+    state
+        .transformer_registry
+        .get(ctx.provider_protocol)?
+        .transform_response_in(response_data.clone(), &ctx.original_model)
+        .ok()
+        .map(|mut response| {
+            if ctx.structured_output {
+                unwrap_structured_output_tool(&mut response);
+            }
+            response.text_content()
+        })
+}
+
+/// Validate a non-streaming structured output against the requested schema.
+///
+/// On failure the request is re-sent once with the invalid answer and the
+/// validation errors appended. The repaired response is used only if it
+/// validates; otherwise the original response is returned unchanged.
+async fn enforce_structured_output(
+    state: &Arc<ProxyState>,
+    ctx: &TransformContext,
+    repair: &StructuredOutputRepair,
+    request_payload: &Value,
+    response_data: Value,
+) -> Value {
+    let record = |result: &str| {
+        get_metrics()
+            .structured_output_validations
+            .with_label_values(&[&ctx.original_model, &ctx.provider_name, result])
+            .inc();
+    };
+
+    let Some(output) = provider_output_text(state, ctx, &response_data) else {
+        return response_data;
+    };
+    let error = match validate_structured_output(&output, &repair.format) {
+        Ok(()) => {
+            record("valid");
+            return response_data;
+        }
+        Err(error) => error,
+    };
+
+    tracing::warn!(
+        request_id = %ctx.request_id,
+        provider = %ctx.provider_name,
+        model = %ctx.original_model,
+        error = %error,
+        "Structured output failed validation, retrying with repair prompt"
+    );
+
+    let repaired =
+        match send_structured_output_repair(state, ctx, repair, request_payload, &output, &error)
+            .await
+        {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::warn!(
+                    request_id = %ctx.request_id,
+                    provider = %ctx.provider_name,
+                    error = %err,
+                    "Structured output repair request failed"
+                );
+                record("invalid");
+                return response_data;
+            }
+        };
+
+    let repaired_valid = provider_output_text(state, ctx, &repaired)
+        .map(|text| validate_structured_output(&text, &repair.format).is_ok())
+        .unwrap_or(false);
+    if repaired_valid {
+        record("repaired");
+        repaired
+    } else {
+        record("invalid");
+        response_data
+    }
+}
+
+/// Re-send the request with a repair turn and return the raw provider response.
+async fn send_structured_output_repair(
+    state: &Arc<ProxyState>,
+    ctx: &TransformContext,
+    repair: &StructuredOutputRepair,
+    request_payload: &Value,
+    invalid_output: &str,
+    error: &str,
+) -> std::result::Result<Value, String> {
+    let mut provider_payload = state
+        .transform_pipeline
+        .transform_request_with(request_payload.clone(), ctx, |unified| {
+            add_repair_turn(unified, invalid_output, error)
+        })
+        .map_err(|e| e.to_string())?;
+    sanitize_provider_payload(&mut provider_payload);
+    ensure_tool_use_result_pairing(&mut provider_payload);
+    normalize_gemini3_provider_payload(&mut provider_payload, ctx.provider_protocol);
+
+    let response = build_protocol_upstream_request(
+        &state
+            .app_state
+            .provider_http_client(&ctx.provider_name)
+            .map_err(|e| e.to_string())?,
+        &repair.url,
+        ctx.provider_protocol,
+        &repair.api_key,
+        &repair.headers,
+        repair.anthropic_beta_header.as_deref(),
+        &provider_payload,
+    )
+    .send()
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {} from {}", status, ctx.provider_name));
+    }
+    let data: Value = response.json().await.map_err(|e| e.to_string())?;
+    log_provider_response(
+        &ctx.request_id,
+        &ctx.provider_name,
+        status.as_u16(),
+        None,
+        &data,
+    );
+    Ok(data)
+}
+
 // ============================================================================
 // Endpoint Handlers
 // ============================================================================
@@ -1556,27 +2629,40 @@ async fn handle_non_streaming_proxy_response(
 pub async fn chat_completions_v2(
     State(state): State<Arc<ProxyState>>,
     headers: HeaderMap,
+    verified_jwt: Option<Extension<VerifiedJwt>>,
     Json(payload): Json<Value>,
 ) -> Result<Response> {
-    handle_proxy_request(state, headers, "/v1/chat/completions", payload).await
+    let verified_jwt = verified_jwt.as_ref().map(|Extension(jwt)| jwt);
+    handle_proxy_request(
+        state,
+        headers,
+        verified_jwt,
+        "/v1/chat/completions",
+        payload,
+    )
+    .await
 }
 
 /// Anthropic-compatible messages endpoint using transformer pipeline
 pub async fn messages_v2(
     State(state): State<Arc<ProxyState>>,
     headers: HeaderMap,
+    verified_jwt: Option<Extension<VerifiedJwt>>,
     Json(payload): Json<Value>,
 ) -> Result<Response> {
-    handle_proxy_request(state, headers, "/v1/messages", payload).await
+    let verified_jwt = verified_jwt.as_ref().map(|Extension(jwt)| jwt);
+    handle_proxy_request(state, headers, verified_jwt, "/v1/messages", payload).await
 }
 
 /// Response API endpoint using transformer pipeline
 pub async fn responses_v2(
     State(state): State<Arc<ProxyState>>,
     headers: HeaderMap,
+    verified_jwt: Option<Extension<VerifiedJwt>>,
     Json(payload): Json<Value>,
 ) -> Result<Response> {
-    handle_proxy_request(state, headers, "/v1/responses", payload).await
+    let verified_jwt = verified_jwt.as_ref().map(|Extension(jwt)| jwt);
+    handle_proxy_request(state, headers, verified_jwt, "/v1/responses", payload).await
 }
 
 /// List available models (V2).
@@ -1840,9 +2926,9 @@ pub async fn list_model_info_v2(
                     }
                     "weight" => {
                         if sort_asc {
-                            data.sort_by(|a, b| a.model_info.weight.cmp(&b.model_info.weight));
+                            data.sort_by_key(|a| a.model_info.weight);
                         } else {
-                            data.sort_by(|a, b| b.model_info.weight.cmp(&a.model_info.weight));
+                            data.sort_by_key(|b| std::cmp::Reverse(b.model_info.weight));
                         }
                     }
                     _ => {
@@ -1943,7 +3029,7 @@ pub async fn completions_v2(
                     );
 
                     let request = build_upstream_request(
-                        &state.app_state.http_client,
+                        &state.app_state.provider_http_client(&provider.name)?,
                         &url,
                         &payload,
                         UpstreamAuth::Bearer(&provider.api_key),
@@ -2024,22 +3110,38 @@ pub async fn completions_v2(
 
 /// Claude token counting endpoint (V2).
 ///
-/// Provides accurate token count for the given messages using tiktoken.
+/// Forwards to the provider's count_tokens API when it opted in via
+/// `upstream_count_tokens`, otherwise estimates locally with tiktoken.
 pub async fn count_tokens_v2(
     State(state): State<Arc<ProxyState>>,
     headers: HeaderMap,
     Json(claude_request): Json<ClaudeTokenCountRequest>,
 ) -> Result<Json<ClaudeTokenCountResponse>> {
     let request_id = generate_request_id();
-    let _key_config = verify_auth(
+    let key_config = verify_auth(
         &headers,
         &state.app_state,
         AuthFormat::MultiFormat,
         Some("/v2/messages/count_tokens"),
     )?;
+    check_model_permission(Some(&claude_request.model), &key_config)?;
 
     REQUEST_ID
         .scope(request_id.clone(), async move {
+            let upstream_payload = serde_json::to_value(&claude_request)?;
+            if let Some(input_tokens) = count_tokens_upstream(
+                &state.app_state,
+                &state.transform_pipeline,
+                Protocol::Anthropic,
+                &upstream_payload,
+                &headers,
+                key_config.as_ref(),
+            )
+            .await
+            {
+                return Ok(Json(ClaudeTokenCountResponse { input_tokens }));
+            }
+
             let model = &claude_request.model;
             let messages_value = build_claude_messages_for_token_count(
                 &claude_request.system,
@@ -2064,6 +3166,72 @@ pub async fn count_tokens_v2(
         .await
 }
 
+/// OpenAI-style token counting endpoint (V2).
+///
+/// Accepts a chat completion request body and returns the prompt size, using
+/// the same upstream counting and local fallback as the Claude endpoint.
+pub async fn chat_count_tokens_v2(
+    State(state): State<Arc<ProxyState>>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> Result<Json<TokenCountResponse>> {
+    let request_id = generate_request_id();
+    let key_config = verify_auth(
+        &headers,
+        &state.app_state,
+        AuthFormat::MultiFormat,
+        Some("/v2/chat/completions/count_tokens"),
+    )?;
+
+    REQUEST_ID
+        .scope(request_id.clone(), async move {
+            let model = payload
+                .get("model")
+                .and_then(|m| m.as_str())
+                .ok_or_else(|| AppError::BadRequest("model is required".to_string()))?
+                .to_string();
+            check_model_permission(Some(&model), &key_config)?;
+            let messages = payload
+                .get("messages")
+                .and_then(|m| m.as_array())
+                .ok_or_else(|| AppError::BadRequest("messages must be an array".to_string()))?;
+
+            let input_tokens = match count_tokens_upstream(
+                &state.app_state,
+                &state.transform_pipeline,
+                Protocol::OpenAI,
+                &payload,
+                &headers,
+                key_config.as_ref(),
+            )
+            .await
+            {
+                Some(count) => count,
+                None => {
+                    let tools = payload
+                        .get("tools")
+                        .and_then(|t| t.as_array())
+                        .map(|t| t.as_slice());
+                    let total_tokens = calculate_message_tokens_with_tools(
+                        messages,
+                        &model,
+                        tools,
+                        payload.get("tool_choice"),
+                    )
+                    .map_err(AppError::BadRequest)?;
+                    std::cmp::max(1, total_tokens) as i32
+                }
+            };
+
+            Ok(Json(TokenCountResponse {
+                object: "token_count".to_string(),
+                model,
+                input_tokens,
+            }))
+        })
+        .await
+}
+
 // ============================================================================
 // Token Counting Helpers (for V2)
 // ============================================================================
@@ -2201,6 +3369,7 @@ mod tests {
             first_token_time: None,
             finalized: false,
             sse_parser: SseParser::new(),
+            watchdog: StreamWatchdog::default(),
             cancel_rx,
             cancel_handle,
             request_id: "req_test".to_string(),
@@ -2210,6 +3379,8 @@ mod tests {
             mapped_model: "gpt-4".to_string(),
             provider_type: "openai".to_string(),
             request_headers: None,
+            pii_redactions: None,
+            guardrails: None,
         }
     }
 
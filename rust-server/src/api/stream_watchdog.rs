@@ -0,0 +1,273 @@
+//! Idle timeout and keepalive heartbeats for streamed responses.
+//!
+//! [`StreamWatchdog::next`] waits for the next upstream chunk and wakes up
+//! early for two reasons:
+//!
+//! - the upstream has been silent for the keepalive interval, so a heartbeat
+//!   should be sent to keep intermediaries from closing the connection
+//! - the upstream stalled for longer than the idle timeout after its first
+//!   chunk, so the stream should be ended with a timeout error
+//!
+//! Heartbeats and error events are formatted for the client's protocol;
+//! [`stream_error_event`] also ends streams cut off for other reasons.
+
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use serde_json::json;
+use tokio::select;
+use tokio::time::Instant;
+
+use crate::core::config::AppConfig;
+use crate::core::error_types::{ERROR_CODE_STREAM_IDLE_TIMEOUT, ERROR_TYPE_TIMEOUT};
+use crate::services::ProviderService;
+use crate::transformer::Protocol;
+
+/// Anthropic clients expect `ping` events as heartbeats.
+const ANTHROPIC_PING_EVENT: &str = "event: ping\ndata: {\"type\": \"ping\"}\n\n";
+
+/// SSE comment line, ignored by every SSE client.
+const KEEPALIVE_COMMENT: &str = ": keepalive\n\n";
+
+/// Outcome of waiting on the upstream stream.
+#[derive(Debug)]
+pub enum WatchEvent<T> {
+    /// The upstream produced an item, or ended (`None`)
+    Item(Option<T>),
+    /// The upstream is silent and a heartbeat is due
+    Keepalive,
+    /// The upstream stalled longer than the idle timeout
+    IdleTimeout,
+}
+
+/// Per-stream idle timeout and keepalive timer.
+#[derive(Clone, Default)]
+pub struct StreamWatchdog {
+    protocol: Protocol,
+    idle_timeout: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    last_chunk: Option<Instant>,
+    last_write: Option<Instant>,
+    provider: Option<(ProviderService, String)>,
+}
+
+impl StreamWatchdog {
+    /// Create a watchdog for a client speaking `protocol`.
+    pub fn new(
+        protocol: Protocol,
+        idle_timeout_secs: Option<u64>,
+        keepalive_secs: Option<u64>,
+    ) -> Self {
+        Self {
+            protocol,
+            idle_timeout: idle_timeout_secs
+                .filter(|s| *s > 0)
+                .map(Duration::from_secs),
+            keepalive_interval: keepalive_secs.filter(|s| *s > 0).map(Duration::from_secs),
+            ..Default::default()
+        }
+    }
+
+    /// Create a watchdog with the server-wide idle timeout and keepalive interval.
+    pub fn from_config(config: &AppConfig, protocol: Protocol) -> Self {
+        Self::new(
+            protocol,
+            config.stream_idle_timeout_secs,
+            config.sse_keepalive_secs,
+        )
+    }
+
+    /// Report idle timeouts of this provider to adaptive routing.
+    pub fn reporting_to(mut self, service: ProviderService, provider_name: &str) -> Self {
+        self.provider = Some((service, provider_name.to_string()));
+        self
+    }
+
+    /// Wait for the next upstream item, a due heartbeat or an idle timeout.
+    ///
+    /// The idle timeout only applies once the first item arrived; the wait for
+    /// the first token is governed by the TTFT timeout.
+    pub async fn next<S>(&mut self, stream: &mut S) -> WatchEvent<S::Item>
+    where
+        S: Stream + Unpin,
+    {
+        let started = Instant::now();
+        let idle_deadline = self
+            .idle_timeout
+            .zip(self.last_chunk)
+            .map(|(timeout, last)| last + timeout);
+        let keepalive_deadline = self
+            .keepalive_interval
+            .map(|interval| self.last_write.unwrap_or(started) + interval);
+
+        select! {
+            item = stream.next() => {
+                let now = Instant::now();
+                self.last_chunk = Some(now);
+                self.last_write = Some(now);
+                WatchEvent::Item(item)
+            }
+            _ = sleep_until(idle_deadline) => {
+                tracing::warn!(
+                    provider = self.provider.as_ref().map(|(_, name)| name.as_str()).unwrap_or(""),
+                    idle_secs = self.idle_timeout.map(|t| t.as_secs()).unwrap_or(0),
+                    "Upstream stream idle timeout"
+                );
+                if let Some((service, name)) = &self.provider {
+                    service.report_transport_error(name);
+                }
+                WatchEvent::IdleTimeout
+            }
+            _ = sleep_until(keepalive_deadline) => {
+                self.last_write = Some(Instant::now());
+                WatchEvent::Keepalive
+            }
+        }
+    }
+
+    /// Heartbeat event in the client's protocol.
+    pub fn keepalive_event(&self) -> &'static str {
+        match self.protocol {
+            Protocol::Anthropic | Protocol::GcpVertex => ANTHROPIC_PING_EVENT,
+            _ => KEEPALIVE_COMMENT,
+        }
+    }
+
+    /// Error event ending a stream that stalled, in the client's protocol.
+    pub fn idle_timeout_event(&self) -> String {
+        let message = format!(
+            "Upstream stream idle for more than {} seconds",
+            self.idle_timeout.map(|t| t.as_secs()).unwrap_or(0)
+        );
+        stream_error_event(
+            self.protocol,
+            504,
+            ERROR_TYPE_TIMEOUT,
+            ERROR_CODE_STREAM_IDLE_TIMEOUT,
+            &message,
+        )
+    }
+}
+
+/// Error event ending a stream early, in the client's protocol.
+pub fn stream_error_event(
+    protocol: Protocol,
+    status: u16,
+    error_type: &str,
+    code: &str,
+    message: &str,
+) -> String {
+    match protocol {
+        Protocol::Anthropic | Protocol::GcpVertex => {
+            let event = json!({
+                "type": "error",
+                "error": {"type": error_type, "message": message}
+            });
+            format!("event: error\ndata: {}\n\n", event)
+        }
+        Protocol::ResponseApi => {
+            let event = json!({
+                "type": "error",
+                "code": code,
+                "message": message,
+                "param": null
+            });
+            format!("event: error\ndata: {}\n\n", event)
+        }
+        Protocol::Gemini => {
+            let gemini_status = match status {
+                400 => "INVALID_ARGUMENT",
+                504 => "DEADLINE_EXCEEDED",
+                _ => "INTERNAL",
+            };
+            let event = json!({
+                "error": {"code": status, "message": message, "status": gemini_status}
+            });
+            format!("data: {}\n\n", event)
+        }
+        Protocol::OpenAI => {
+            let event = json!({
+                "error": {
+                    "message": message,
+                    "type": error_type,
+                    "code": code
+                }
+            });
+            format!("data: {}\n\ndata: [DONE]\n\n", event)
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => futures::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stalled_after_first_chunk() -> impl Stream<Item = u8> + Unpin {
+        Box::pin(futures::stream::once(async { 1u8 }).chain(futures::stream::pending()))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_keepalive_then_idle_timeout() {
+        let mut stream = stalled_after_first_chunk();
+        let mut watchdog = StreamWatchdog::new(Protocol::Anthropic, Some(30), Some(12));
+
+        assert!(matches!(
+            watchdog.next(&mut stream).await,
+            WatchEvent::Item(Some(1))
+        ));
+        for _ in 0..2 {
+            assert!(matches!(
+                watchdog.next(&mut stream).await,
+                WatchEvent::Keepalive
+            ));
+        }
+        assert!(matches!(
+            watchdog.next(&mut stream).await,
+            WatchEvent::IdleTimeout
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_idle_timeout_before_first_chunk() {
+        let mut stream = futures::stream::pending::<u8>();
+        let mut watchdog = StreamWatchdog::new(Protocol::OpenAI, Some(1), Some(5));
+
+        assert!(matches!(
+            watchdog.next(&mut stream).await,
+            WatchEvent::Keepalive
+        ));
+    }
+
+    #[test]
+    fn test_events_follow_client_protocol() {
+        let anthropic = StreamWatchdog::new(Protocol::Anthropic, Some(60), None);
+        assert_eq!(anthropic.keepalive_event(), ANTHROPIC_PING_EVENT);
+        let event = anthropic.idle_timeout_event();
+        assert!(event.starts_with("event: error\n"));
+        assert!(event.contains("\"timeout_error\""));
+
+        let openai = StreamWatchdog::new(Protocol::OpenAI, Some(60), None);
+        assert_eq!(openai.keepalive_event(), KEEPALIVE_COMMENT);
+        let event = openai.idle_timeout_event();
+        assert!(event.contains(ERROR_CODE_STREAM_IDLE_TIMEOUT));
+        assert!(event.ends_with("data: [DONE]\n\n"));
+
+        let gemini = StreamWatchdog::new(Protocol::Gemini, Some(60), None);
+        assert!(gemini.idle_timeout_event().contains("DEADLINE_EXCEEDED"));
+        assert!(stream_error_event(
+            Protocol::Gemini,
+            400,
+            "invalid_request_error",
+            "x",
+            "blocked"
+        )
+        .contains("INVALID_ARGUMENT"));
+    }
+}
@@ -14,9 +14,11 @@
 
 use crate::api::gemini3::normalize_response_payload;
 use crate::api::models::Usage;
+use crate::api::stream_watchdog::{StreamWatchdog, WatchEvent};
 use crate::core::error::AppError;
 use crate::core::error_types::{
-    ERROR_CODE_PROVIDER, ERROR_CODE_TTFT_TIMEOUT, ERROR_TYPE_STREAM, ERROR_TYPE_TIMEOUT,
+    ERROR_CATEGORY_TIMEOUT, ERROR_CODE_PROVIDER, ERROR_CODE_TTFT_TIMEOUT, ERROR_TYPE_STREAM,
+    ERROR_TYPE_TIMEOUT,
 };
 use crate::core::jsonl_logger::{log_provider_streaming_response, log_streaming_response};
 use crate::core::langfuse::{finish_generation_if_sampled, GenerationData};
@@ -35,7 +37,7 @@ use base64::Engine;
 use bytes::Bytes;
 use chrono::Utc;
 use dashmap::DashMap;
-use futures::stream::{Stream, StreamExt};
+use futures::stream::Stream;
 use reqwest::Response;
 use serde_json::{json, Value};
 use std::borrow::Cow;
@@ -121,6 +123,8 @@ struct StreamState {
     client: String,
     /// TTFT timeout in seconds (None = disabled)
     ttft_timeout_secs: Option<u64>,
+    /// Idle timeout and keepalive heartbeats
+    watchdog: StreamWatchdog,
     /// Whether the first chunk has been received
     first_chunk_received: bool,
     /// Langfuse generation data (None if Langfuse disabled or not sampled)
@@ -153,6 +157,8 @@ struct StreamState {
     provider_protocol: Option<String>,
     /// Masked request headers for request logging
     request_headers: Option<String>,
+    /// Status and error category of a stream ended by the proxy, e.g. on idle timeout
+    failure: Option<(u16, &'static str)>,
 }
 
 impl StreamState {
@@ -185,6 +191,7 @@ impl StreamState {
             api_key_name,
             client,
             ttft_timeout_secs,
+            watchdog: StreamWatchdog::default(),
             first_chunk_received: false,
             generation_data,
             accumulated_output: Vec::new(),
@@ -201,6 +208,7 @@ impl StreamState {
             client_protocol: None,
             provider_protocol: None,
             request_headers: None,
+            failure: None,
         }
     }
 }
@@ -598,50 +606,38 @@ fn get_image_dimensions(data: &str) -> TokenCountResult<(u32, u32)> {
     let img_data = fetch_image_bytes(data)?;
 
     match get_image_type(&img_data) {
-        Some("png") => {
-            if img_data.len() >= 24 {
-                let width =
-                    u32::from_be_bytes([img_data[16], img_data[17], img_data[18], img_data[19]]);
-                let height =
-                    u32::from_be_bytes([img_data[20], img_data[21], img_data[22], img_data[23]]);
-                return Ok((width, height));
-            }
+        Some("png") if img_data.len() >= 24 => {
+            let width =
+                u32::from_be_bytes([img_data[16], img_data[17], img_data[18], img_data[19]]);
+            let height =
+                u32::from_be_bytes([img_data[20], img_data[21], img_data[22], img_data[23]]);
+            return Ok((width, height));
         }
-        Some("gif") => {
-            if img_data.len() >= 10 {
-                let width = u16::from_le_bytes([img_data[6], img_data[7]]) as u32;
-                let height = u16::from_le_bytes([img_data[8], img_data[9]]) as u32;
-                return Ok((width, height));
-            }
+        Some("gif") if img_data.len() >= 10 => {
+            let width = u16::from_le_bytes([img_data[6], img_data[7]]) as u32;
+            let height = u16::from_le_bytes([img_data[8], img_data[9]]) as u32;
+            return Ok((width, height));
         }
         Some("jpeg") => {
             if let Some((width, height)) = parse_jpeg_dimensions(&img_data) {
                 return Ok((width, height));
             }
         }
-        Some("webp") => {
-            if img_data.len() >= 30 {
-                if &img_data[12..16] == b"VP8X" {
-                    let width =
-                        u32::from_le_bytes([img_data[24], img_data[25], img_data[26], 0]) + 1;
-                    let height =
-                        u32::from_le_bytes([img_data[27], img_data[28], img_data[29], 0]) + 1;
-                    return Ok((width, height));
-                } else if &img_data[12..16] == b"VP8 " {
-                    let width = u16::from_le_bytes([img_data[26], img_data[27]]) & 0x3FFF;
-                    let height = u16::from_le_bytes([img_data[28], img_data[29]]) & 0x3FFF;
-                    return Ok((width as u32, height as u32));
-                } else if &img_data[12..16] == b"VP8L" {
-                    let bits = u32::from_le_bytes([
-                        img_data[21],
-                        img_data[22],
-                        img_data[23],
-                        img_data[24],
-                    ]);
-                    let width = (bits & 0x3FFF) + 1;
-                    let height = ((bits >> 14) & 0x3FFF) + 1;
-                    return Ok((width, height));
-                }
+        Some("webp") if img_data.len() >= 30 => {
+            if &img_data[12..16] == b"VP8X" {
+                let width = u32::from_le_bytes([img_data[24], img_data[25], img_data[26], 0]) + 1;
+                let height = u32::from_le_bytes([img_data[27], img_data[28], img_data[29], 0]) + 1;
+                return Ok((width, height));
+            } else if &img_data[12..16] == b"VP8 " {
+                let width = u16::from_le_bytes([img_data[26], img_data[27]]) & 0x3FFF;
+                let height = u16::from_le_bytes([img_data[28], img_data[29]]) & 0x3FFF;
+                return Ok((width as u32, height as u32));
+            } else if &img_data[12..16] == b"VP8L" {
+                let bits =
+                    u32::from_le_bytes([img_data[21], img_data[22], img_data[23], img_data[24]]);
+                let width = (bits & 0x3FFF) + 1;
+                let height = ((bits >> 14) & 0x3FFF) + 1;
+                return Ok((width, height));
             }
         }
         _ => {}
@@ -925,6 +921,7 @@ fn format_type(props: &Value, indent: usize) -> String {
 /// * `gemini_model` - Model name for Gemini 3 normalization
 /// * `input_tokens` - Optional input token count for fallback calculation
 /// * `ttft_timeout_secs` - Optional TTFT timeout in seconds
+/// * `watchdog` - Idle timeout and keepalive heartbeats for the client's protocol
 /// * `generation_data` - Optional Langfuse generation data for tracing
 /// * `request_id` - Optional request ID for JSONL logging
 /// * `endpoint` - Optional endpoint for JSONL logging
@@ -948,6 +945,7 @@ pub async fn create_sse_stream(
     gemini_model: Option<String>,
     input_tokens: Option<usize>,
     ttft_timeout_secs: Option<u64>,
+    watchdog: StreamWatchdog,
     generation_data: Option<GenerationData>,
     request_id: Option<String>,
     endpoint: Option<String>,
@@ -982,6 +980,7 @@ pub async fn create_sse_stream(
 
     // Set JSONL logging parameters (endpoint and request_payload are no longer needed - request is logged separately)
     initial_state.request_id = request_id.unwrap_or_default();
+    initial_state.watchdog = watchdog;
     initial_state.endpoint = endpoint;
     // Note: request_payload parameter is kept for API compatibility but not used
     let _ = request_payload;
@@ -1008,8 +1007,11 @@ pub async fn create_sse_stream(
         let chunk_result = if !state.first_chunk_received {
             // First chunk - apply TTFT timeout if configured
             if let Some(timeout_secs) = state.ttft_timeout_secs {
-                let stream_future = state.stream.next();
-                let timeout_future = tokio::time::sleep(Duration::from_secs(timeout_secs));
+                let stream_future = state.watchdog.next(&mut state.stream);
+                // Measured from the stream start, as heartbeats end the wait early
+                let timeout_future = tokio::time::sleep_until(
+                    (state.start_time + Duration::from_secs(timeout_secs)).into(),
+                );
 
                 // Create cancellation future
                 let cancel_future = async {
@@ -1071,7 +1073,7 @@ pub async fn create_sse_stream(
                 }
             } else {
                 // No timeout, but still need to check cancellation
-                let stream_future = state.stream.next();
+                let stream_future = state.watchdog.next(&mut state.stream);
                 let cancel_future = async {
                     if let Some(rx) = &mut state.cancel_rx {
                         let _ = rx.changed().await;
@@ -1097,8 +1099,8 @@ pub async fn create_sse_stream(
                 }
             }
         } else {
-            // Subsequent chunks - no timeout, but check cancellation
-            let stream_future = state.stream.next();
+            // Subsequent chunks - idle timeout applied by the watchdog
+            let stream_future = state.watchdog.next(&mut state.stream);
             let cancel_future = async {
                 if let Some(rx) = &mut state.cancel_rx {
                     let _ = rx.changed().await;
@@ -1124,6 +1126,21 @@ pub async fn create_sse_stream(
             }
         };
 
+        let chunk_result = match chunk_result {
+            WatchEvent::Item(chunk) => chunk,
+            WatchEvent::Keepalive => {
+                let heartbeat = state.watchdog.keepalive_event().as_bytes().to_vec();
+                return Some((Ok(heartbeat), state));
+            }
+            WatchEvent::IdleTimeout => {
+                // Idle timeout - send error event and terminate stream
+                let error_message = state.watchdog.idle_timeout_event();
+                state.stream = Box::pin(futures::stream::empty());
+                state.failure = Some((504, ERROR_CATEGORY_TIMEOUT));
+                return Some((Ok(error_message.into_bytes()), state));
+            }
+        };
+
         match chunk_result {
             Some(Ok(bytes)) => {
                 state.first_chunk_received = true;
@@ -1569,6 +1586,10 @@ fn finalize_stream(state: &mut StreamState) {
         first_token_time: state.provider_first_token_time,
     };
     record_stream_metrics(&stats);
+    let (status_code, error_category) = match state.failure {
+        Some((status, category)) => (status, Some(category)),
+        None => (200, None),
+    };
 
     // Log request record for same-protocol streaming
     if !state.request_id.is_empty() {
@@ -1588,7 +1609,7 @@ fn finalize_stream(state: &mut StreamState) {
             client_protocol: state.client_protocol.clone(),
             provider_protocol: state.provider_protocol.clone(),
             is_streaming: true,
-            status_code: Some(200),
+            status_code: Some(status_code as i32),
             input_tokens: final_input_tokens as i32,
             output_tokens: final_output_tokens as i32,
             total_tokens: (final_input_tokens + final_output_tokens) as i32,
@@ -1596,6 +1617,7 @@ fn finalize_stream(state: &mut StreamState) {
                 state.start_time.elapsed().as_millis().min(i32::MAX as u128) as i32
             ),
             ttft_ms: ttft,
+            error_category: error_category.map(str::to_string),
             request_headers: state.request_headers.clone(),
             ..Default::default()
         });
@@ -1631,8 +1653,8 @@ fn finalize_stream(state: &mut StreamState) {
         // Log client-facing streaming response
         log_streaming_response(
             &state.request_id,
-            200, // Streaming responses are always 200 if we reach finalize
-            None,
+            status_code,
+            error_category,
             state.accumulated_sse_data.clone(),
         );
 
@@ -0,0 +1,381 @@
+//! Upstream-accurate token counting.
+//!
+//! Providers that set `upstream_count_tokens: true` in `provider_params` have
+//! count requests translated to their native protocol and forwarded to the
+//! provider's own counting endpoint:
+//!
+//! | Provider type | Endpoint |
+//! |---------------|----------|
+//! | anthropic     | `POST {api_base}/v1/messages/count_tokens` |
+//! | gcp-vertex    | `.../publishers/anthropic/models/count-tokens:rawPredict` |
+//! | gemini        | `.../publishers/{publisher}/models/{model}:countTokens` |
+//!
+//! The credential's PII redaction and guardrails apply to the forwarded
+//! request as they do to proxied requests; a blocked request is counted
+//! locally. Results are cached by request hash. Every failure yields `None`
+//! so callers fall back to local estimation.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::http::HeaderMap;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::api::handlers::AppState;
+use crate::api::models::{GcpVertexConfig, Provider};
+use crate::api::upstream::{build_gcp_vertex_url_with_actions, build_protocol_upstream_request};
+use crate::core::config::CredentialConfig;
+use crate::core::header_policy::sanitize_anthropic_beta_header;
+use crate::core::logging::REQUEST_ID;
+use crate::core::utils::strip_provider_suffix;
+use crate::transformer::pii_redaction::PII_REDACTION_PARAM;
+use crate::transformer::{
+    provider_type_to_protocol, PiiRedactor, Protocol, TransformContext, TransformPipeline,
+    TransformerRegistry,
+};
+
+/// Provider param enabling upstream token counting.
+pub const UPSTREAM_COUNT_TOKENS_PARAM: &str = "upstream_count_tokens";
+
+const UPSTREAM_COUNT_TIMEOUT: Duration = Duration::from_secs(10);
+const CACHE_TTL: Duration = Duration::from_secs(300);
+const CACHE_CAPACITY: usize = 10_000;
+
+/// Fields accepted by Anthropic's count_tokens endpoint.
+const ANTHROPIC_COUNT_FIELDS: &[&str] = &[
+    "model",
+    "messages",
+    "system",
+    "tools",
+    "tool_choice",
+    "thinking",
+];
+
+/// Fields accepted by Gemini's countTokens endpoint.
+const GEMINI_COUNT_FIELDS: &[&str] = &["contents", "systemInstruction", "tools"];
+
+lazy_static! {
+    static ref COUNT_CACHE: DashMap<String, (i32, Instant)> = DashMap::new();
+    static ref DEFAULT_PIPELINE: TransformPipeline =
+        TransformPipeline::new(Arc::new(TransformerRegistry::new()));
+}
+
+/// Pipeline for handlers that do not carry a [`crate::api::ProxyState`].
+pub fn default_pipeline() -> &'static TransformPipeline {
+    &DEFAULT_PIPELINE
+}
+
+/// Count input tokens with the provider serving `model`, if it opted in.
+///
+/// `payload` is a request in `client_protocol` format; only the parts that
+/// contribute to the prompt are forwarded.
+pub async fn count_tokens_upstream(
+    app_state: &AppState,
+    pipeline: &TransformPipeline,
+    client_protocol: Protocol,
+    payload: &Value,
+    headers: &HeaderMap,
+    credential: Option<&CredentialConfig>,
+) -> Option<i32> {
+    let model = payload.get("model").and_then(|m| m.as_str())?;
+    let effective_model = strip_provider_suffix(model, app_state.config.provider_suffix.as_deref());
+    let provider = app_state
+        .get_provider_service()
+        .get_next_provider(Some(&effective_model))
+        .ok()?;
+    if !provider.get_param_bool(UPSTREAM_COUNT_TOKENS_PARAM) {
+        return None;
+    }
+    let provider_protocol = provider_type_to_protocol(&provider.provider_type);
+    if !matches!(
+        provider_protocol,
+        Protocol::Anthropic | Protocol::GcpVertex | Protocol::Gemini
+    ) {
+        return None;
+    }
+    let pii_redactor = PiiRedactor::for_request(
+        provider.provider_params.get(PII_REDACTION_PARAM),
+        credential.and_then(|config| config.pii_redaction.as_ref()),
+    )
+    .map_err(|err| {
+        tracing::warn!(provider = %provider.name, error = %err, "Invalid PII redaction settings");
+    })
+    .ok()?;
+
+    let ctx = TransformContext {
+        request_id: REQUEST_ID.try_with(|id| id.clone()).unwrap_or_default(),
+        client_protocol,
+        provider_protocol,
+        original_model: model.to_string(),
+        mapped_model: provider.get_mapped_model(&effective_model),
+        provider_name: provider.name.clone(),
+        provider_type: provider.provider_type.clone(),
+        pii_redactor,
+        guardrails: credential.and_then(|config| app_state.get_guardrails(&config.guardrails)),
+        ..Default::default()
+    };
+    // Count requests carry no max_tokens, which Anthropic request parsing
+    // requires; it is dropped again when building the count body.
+    let mut request = payload.clone();
+    if client_protocol == Protocol::Anthropic {
+        if let Some(obj) = request.as_object_mut() {
+            obj.entry("max_tokens").or_insert(Value::from(1));
+        }
+    }
+    let translated = pipeline
+        .transform_request(request, &ctx)
+        .map_err(|err| {
+            tracing::debug!(error = %err, "Token count request translation failed");
+        })
+        .ok()?;
+    let (url, body) =
+        build_count_request(&provider, provider_protocol, &ctx.mapped_model, translated)
+            .map_err(|err| {
+                tracing::warn!(provider = %provider.name, error = %err, "Invalid count_tokens URL");
+            })
+            .ok()?;
+
+    let key = cache_key(&provider.name, &url, &body);
+    if let Some(count) = cached_count(&key) {
+        return Some(count);
+    }
+
+    let anthropic_beta_header = sanitize_anthropic_beta_header(
+        &provider.provider_type,
+        &provider.provider_params,
+        headers.get("anthropic-beta").and_then(|v| v.to_str().ok()),
+    );
+    let result = build_protocol_upstream_request(
+        &app_state.provider_http_client(&provider.name).ok()?,
+        &url,
+        provider_protocol,
+        &provider.api_key,
+        headers,
+        anthropic_beta_header.as_deref(),
+        &body,
+    )
+    .timeout(UPSTREAM_COUNT_TIMEOUT)
+    .send()
+    .await;
+
+    let response = match result {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::warn!(
+                provider = %provider.name,
+                status = %response.status(),
+                "Upstream count_tokens failed, falling back to local estimate"
+            );
+            return None;
+        }
+        Err(err) => {
+            tracing::warn!(
+                provider = %provider.name,
+                error = %err,
+                "Upstream count_tokens request failed, falling back to local estimate"
+            );
+            return None;
+        }
+    };
+    let count = parse_count(provider_protocol, &response.json::<Value>().await.ok()?)?;
+    store_count(key, count);
+    Some(count)
+}
+
+/// Build the provider URL and the request body containing only countable fields.
+fn build_count_request(
+    provider: &Provider,
+    protocol: Protocol,
+    mapped_model: &str,
+    translated: Value,
+) -> Result<(String, Value), String> {
+    match protocol {
+        Protocol::Anthropic => {
+            let mut body = retain_fields(translated, ANTHROPIC_COUNT_FIELDS);
+            body.insert("model".to_string(), Value::String(mapped_model.to_string()));
+            Ok((
+                format!("{}/v1/messages/count_tokens", provider.api_base),
+                Value::Object(body),
+            ))
+        }
+        Protocol::GcpVertex => {
+            let gcp = GcpVertexConfig::from_provider_with_defaults(provider);
+            let url = build_gcp_vertex_url_with_actions(
+                &provider.api_base,
+                &gcp.project,
+                &gcp.location,
+                &gcp.publisher,
+                "count-tokens",
+                false,
+                "rawPredict",
+                "rawPredict",
+            )?;
+            let mut body = retain_fields(translated, ANTHROPIC_COUNT_FIELDS);
+            body.insert("model".to_string(), Value::String(mapped_model.to_string()));
+            Ok((url, Value::Object(body)))
+        }
+        _ => {
+            let gcp = GcpVertexConfig::from_provider_with_defaults(provider);
+            let url = build_gcp_vertex_url_with_actions(
+                &provider.api_base,
+                &gcp.project,
+                &gcp.location,
+                &gcp.publisher,
+                mapped_model,
+                false,
+                "countTokens",
+                "countTokens",
+            )?;
+            Ok((
+                url,
+                Value::Object(retain_fields(translated, GEMINI_COUNT_FIELDS)),
+            ))
+        }
+    }
+}
+
+fn retain_fields(payload: Value, fields: &[&str]) -> Map<String, Value> {
+    match payload {
+        Value::Object(mut map) => {
+            map.retain(|key, _| fields.contains(&key.as_str()));
+            map
+        }
+        _ => Map::new(),
+    }
+}
+
+fn parse_count(protocol: Protocol, body: &Value) -> Option<i32> {
+    let field = match protocol {
+        Protocol::Gemini => "totalTokens",
+        _ => "input_tokens",
+    };
+    body.get(field)
+        .and_then(|v| v.as_i64())
+        .map(|n| n.clamp(0, i32::MAX as i64) as i32)
+}
+
+fn cache_key(provider: &str, url: &str, body: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(provider.as_bytes());
+    hasher.update([0]);
+    hasher.update(url.as_bytes());
+    hasher.update([0]);
+    hasher.update(body.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+fn cached_count(key: &str) -> Option<i32> {
+    let entry = COUNT_CACHE.get(key)?;
+    let (count, stored_at) = *entry;
+    drop(entry);
+    if stored_at.elapsed() < CACHE_TTL {
+        Some(count)
+    } else {
+        COUNT_CACHE.remove(key);
+        None
+    }
+}
+
+fn store_count(key: String, count: i32) {
+    if COUNT_CACHE.len() >= CACHE_CAPACITY {
+        COUNT_CACHE.retain(|_, (_, stored_at)| stored_at.elapsed() < CACHE_TTL);
+        if COUNT_CACHE.len() >= CACHE_CAPACITY {
+            COUNT_CACHE.clear();
+        }
+    }
+    COUNT_CACHE.insert(key, (count, Instant::now()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn provider(provider_type: &str) -> Provider {
+        let mut provider_params = HashMap::new();
+        provider_params.insert("gcp_project".to_string(), json!("proj"));
+        provider_params.insert("gcp_location".to_string(), json!("us-east5"));
+        provider_params.insert("gcp_publisher".to_string(), json!("google"));
+        Provider {
+            name: "p".to_string(),
+            api_base: "https://upstream.example".to_string(),
+            api_key: "k".to_string(),
+            weight: 1,
+            model_mapping: HashMap::new(),
+            provider_type: provider_type.to_string(),
+            provider_params,
+        }
+    }
+
+    #[test]
+    fn test_anthropic_count_request_drops_generation_fields() {
+        let (url, body) = build_count_request(
+            &provider("anthropic"),
+            Protocol::Anthropic,
+            "claude-x",
+            json!({"model": "alias", "max_tokens": 4096, "stream": false, "messages": [], "system": "s"}),
+        )
+        .unwrap();
+        assert_eq!(url, "https://upstream.example/v1/messages/count_tokens");
+        assert_eq!(
+            body,
+            json!({"model": "claude-x", "messages": [], "system": "s"})
+        );
+    }
+
+    #[test]
+    fn test_vertex_anthropic_count_request_url() {
+        let (url, body) = build_count_request(
+            &provider("gcp-vertex"),
+            Protocol::GcpVertex,
+            "claude-x@2025",
+            json!({"max_tokens": 10, "messages": []}),
+        )
+        .unwrap();
+        assert_eq!(
+            url,
+            "https://upstream.example/v1/projects/proj/locations/us-east5/publishers/google/models/count-tokens:rawPredict"
+        );
+        assert_eq!(body["model"], "claude-x@2025");
+        assert!(body.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn test_gemini_count_request() {
+        let (url, body) = build_count_request(
+            &provider("gemini"),
+            Protocol::Gemini,
+            "gemini-2.5-pro",
+            json!({"contents": [], "generationConfig": {"maxOutputTokens": 5}}),
+        )
+        .unwrap();
+        assert!(url.ends_with("/models/gemini-2.5-pro:countTokens"));
+        assert_eq!(body, json!({"contents": []}));
+    }
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(
+            parse_count(Protocol::Anthropic, &json!({"input_tokens": 42})),
+            Some(42)
+        );
+        assert_eq!(
+            parse_count(Protocol::Gemini, &json!({"totalTokens": 7})),
+            Some(7)
+        );
+        assert_eq!(parse_count(Protocol::Gemini, &json!({})), None);
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let key = cache_key("p", "u", &json!({"a": 1}));
+        assert_ne!(key, cache_key("p", "u", &json!({"a": 2})));
+        assert_eq!(cached_count(&key), None);
+        store_count(key.clone(), 11);
+        assert_eq!(cached_count(&key), Some(11));
+    }
+}
@@ -16,7 +16,10 @@ use serde_json::{json, Value};
 
 const MAX_ERROR_MESSAGE_LEN: usize = 500;
 
-use crate::core::error_types::{ERROR_TYPE_API, ERROR_TYPE_TIMEOUT};
+use crate::core::error_types::{
+    ERROR_CODE_CONTEXT_LENGTH_EXCEEDED, ERROR_TYPE_API, ERROR_TYPE_INVALID_REQUEST,
+    ERROR_TYPE_TIMEOUT,
+};
 
 /// Common context for upstream operations, reducing parameter passing.
 #[derive(Clone, Copy)]
@@ -373,6 +376,42 @@ pub fn build_protocol_error_response(
     build_json_response(status, body, model, provider, api_key_name)
 }
 
+/// Build a 400 response for a prompt exceeding the model's input limit.
+///
+/// Mirrors the shape each provider uses for this condition so that clients
+/// relying on native detection (OpenAI `code`, Anthropic "prompt is too long")
+/// handle the early rejection the same way as an upstream one.
+pub fn build_context_length_exceeded_response(
+    protocol: Protocol,
+    tokens: usize,
+    limit: usize,
+    model: Option<&str>,
+    provider: Option<&str>,
+    api_key_name: Option<&str>,
+) -> Response {
+    let body = match protocol {
+        Protocol::Anthropic | Protocol::GcpVertex => json!({
+            "type": "error",
+            "error": {
+                "type": ERROR_TYPE_INVALID_REQUEST,
+                "message": format!("prompt is too long: {} tokens > {} maximum", tokens, limit)
+            }
+        }),
+        Protocol::OpenAI | Protocol::ResponseApi | Protocol::Gemini => json!({
+            "error": {
+                "message": format!(
+                    "This model's maximum context length is {} tokens. However, your messages resulted in {} tokens.",
+                    limit, tokens
+                ),
+                "type": ERROR_TYPE_INVALID_REQUEST,
+                "param": "messages",
+                "code": ERROR_CODE_CONTEXT_LENGTH_EXCEEDED
+            }
+        }),
+    };
+    build_json_response(StatusCode::BAD_REQUEST, body, model, provider, api_key_name)
+}
+
 /// Classify upstream transport errors into HTTP status/type/message.
 fn classify_upstream_error(error: &reqwest::Error) -> (StatusCode, &'static str, String) {
     let status = if error.is_timeout() {
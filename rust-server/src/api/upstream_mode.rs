@@ -0,0 +1,241 @@
+//! Streaming ↔ blocking emulation for upstreams that support only one mode.
+//!
+//! A provider param or a model's `upstream_mode` pins how the upstream is
+//! called. When that differs from what the client asked for, the upstream
+//! response is converted into the provider's own format for the other mode,
+//! so the regular streaming and non-streaming handlers run unchanged:
+//!
+//! - a stream is collected into one complete response ([`stream_to_blocking`])
+//! - a complete response is replayed as a stream ([`blocking_to_stream`])
+
+use std::time::Instant;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use crate::api::models::Provider;
+use crate::api::proxy::rebuild_sse_event;
+use crate::core::config::{ModelMappingEntry, UpstreamMode};
+use crate::core::error::Result;
+use crate::core::metrics::get_metrics;
+use crate::core::AppError;
+use crate::transformer::stream::{response_to_chunks, ChunkAccumulator, SseParser};
+use crate::transformer::{Protocol, Transformer};
+
+/// Provider param pinning the upstream mode (`"stream"` or `"blocking"`).
+pub const UPSTREAM_MODE_PARAM: &str = "upstream_mode";
+
+/// Mode the upstream must be called in, if it is pinned.
+///
+/// The model's `upstream_mode` wins over the provider param; models marked
+/// `supports_streaming: false` are always called blocking.
+pub fn resolve_upstream_mode(
+    provider: &Provider,
+    metadata: Option<&ModelMappingEntry>,
+) -> Option<UpstreamMode> {
+    if let Some(mode) = metadata.and_then(|m| m.upstream_mode) {
+        return Some(mode);
+    }
+    if metadata.and_then(|m| m.supports_streaming) == Some(false) {
+        return Some(UpstreamMode::Blocking);
+    }
+    match provider.get_param(UPSTREAM_MODE_PARAM) {
+        Some("stream") => Some(UpstreamMode::Stream),
+        Some("blocking") => Some(UpstreamMode::Blocking),
+        Some(other) => {
+            tracing::warn!(
+                provider = %provider.name,
+                value = %other,
+                "Ignoring unknown upstream_mode provider param"
+            );
+            None
+        }
+        None => None,
+    }
+}
+
+/// Set the stream flag of a provider payload.
+///
+/// Gemini selects the mode through the URL instead. OpenAI only reports
+/// usage at the end of a stream when asked to.
+pub fn set_upstream_stream(payload: &mut Value, protocol: Protocol, stream: bool) {
+    if protocol == Protocol::Gemini {
+        return;
+    }
+    let Some(obj) = payload.as_object_mut() else {
+        return;
+    };
+    if stream {
+        obj.insert("stream".to_string(), json!(true));
+        if protocol == Protocol::OpenAI {
+            obj.insert("stream_options".to_string(), json!({"include_usage": true}));
+        }
+    } else {
+        obj.remove("stream");
+        obj.remove("stream_options");
+    }
+}
+
+/// Convert an upstream response to the mode the client asked for.
+pub async fn convert_upstream_response(
+    response: reqwest::Response,
+    provider_t: &dyn Transformer,
+    client_stream: bool,
+    model: &str,
+    provider_name: &str,
+    request_start: Instant,
+) -> Result<reqwest::Response> {
+    if client_stream {
+        blocking_to_stream(response, provider_t, model).await
+    } else {
+        stream_to_blocking(response, provider_t, model, provider_name, request_start).await
+    }
+}
+
+/// Collect a streamed upstream response into a complete provider response.
+///
+/// The time to the first upstream bytes is recorded as TTFT, since the
+/// non-streaming handler has no notion of it.
+pub async fn stream_to_blocking(
+    response: reqwest::Response,
+    provider_t: &dyn Transformer,
+    model: &str,
+    provider_name: &str,
+    request_start: Instant,
+) -> Result<reqwest::Response> {
+    let status = response.status();
+    let mut parser = SseParser::new();
+    let mut accumulator = ChunkAccumulator::new();
+    let mut first_byte: Option<Instant> = None;
+
+    let mut body = response.bytes_stream();
+    while let Some(bytes) = body.next().await {
+        let bytes = bytes?;
+        first_byte.get_or_insert_with(Instant::now);
+        accumulate_events(&mut accumulator, parser.parse(&bytes), provider_t);
+    }
+    accumulate_events(&mut accumulator, parser.parse(b"\n\n"), provider_t);
+
+    if let Some(first) = first_byte {
+        get_metrics()
+            .ttft
+            .with_label_values(&["provider", model, provider_name])
+            .observe(first.duration_since(request_start).as_secs_f64());
+    }
+
+    let unified = accumulator.build_response();
+    let body = provider_t.transform_response_out(&unified, provider_t.protocol())?;
+    build_response(status, "application/json", body.to_string())
+}
+
+fn accumulate_events(
+    accumulator: &mut ChunkAccumulator,
+    events: Vec<crate::transformer::SseEvent>,
+    provider_t: &dyn Transformer,
+) {
+    for event in events {
+        let Some(raw) = rebuild_sse_event(&event) else {
+            continue;
+        };
+        match provider_t.transform_stream_chunk_in(&Bytes::from(raw)) {
+            Ok(chunks) => chunks.iter().for_each(|c| accumulator.add_chunk(c)),
+            Err(e) => tracing::debug!(error = %e, "Skipping unparsable upstream stream event"),
+        }
+    }
+}
+
+/// Replay a complete upstream response as a provider event stream.
+pub async fn blocking_to_stream(
+    response: reqwest::Response,
+    provider_t: &dyn Transformer,
+    model: &str,
+) -> Result<reqwest::Response> {
+    let status = response.status();
+    let raw: Value = response.json().await?;
+
+    // Streamed Gemini chunks share the shape of the complete response
+    if provider_t.protocol() == Protocol::Gemini {
+        return build_response(status, "text/event-stream", format!("data: {}\n\n", raw));
+    }
+
+    let unified = provider_t.transform_response_in(raw, model)?;
+    let mut body = String::new();
+    for chunk in response_to_chunks(&unified) {
+        body.push_str(&provider_t.transform_stream_chunk_out(&chunk, provider_t.protocol())?);
+    }
+    build_response(status, "text/event-stream", body)
+}
+
+fn build_response(
+    status: reqwest::StatusCode,
+    content_type: &str,
+    body: String,
+) -> Result<reqwest::Response> {
+    let response = reqwest_http::Response::builder()
+        .status(status.as_u16())
+        .header("content-type", content_type)
+        .body(body)
+        .map_err(|e| AppError::Internal(format!("Failed to build response: {}", e)))?;
+    Ok(reqwest::Response::from(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn provider(params: Value) -> Provider {
+        Provider {
+            name: "p".to_string(),
+            api_base: "http://localhost".to_string(),
+            api_key: "k".to_string(),
+            weight: 1,
+            model_mapping: HashMap::new(),
+            provider_type: "openai".to_string(),
+            provider_params: serde_json::from_value(params).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_upstream_mode_precedence() {
+        let stream_provider = provider(json!({"upstream_mode": "stream"}));
+        assert_eq!(
+            resolve_upstream_mode(&stream_provider, None),
+            Some(UpstreamMode::Stream)
+        );
+
+        let entry = ModelMappingEntry {
+            supports_streaming: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_upstream_mode(&stream_provider, Some(&entry)),
+            Some(UpstreamMode::Blocking)
+        );
+
+        let entry = ModelMappingEntry {
+            upstream_mode: Some(UpstreamMode::Stream),
+            ..entry
+        };
+        assert_eq!(
+            resolve_upstream_mode(&provider(json!({})), Some(&entry)),
+            Some(UpstreamMode::Stream)
+        );
+        assert_eq!(resolve_upstream_mode(&provider(json!({})), None), None);
+    }
+
+    #[test]
+    fn test_set_upstream_stream() {
+        let mut payload = json!({"model": "gpt-4"});
+        set_upstream_stream(&mut payload, Protocol::OpenAI, true);
+        assert_eq!(payload["stream"], json!(true));
+        assert_eq!(payload["stream_options"]["include_usage"], json!(true));
+
+        set_upstream_stream(&mut payload, Protocol::OpenAI, false);
+        assert_eq!(payload, json!({"model": "gpt-4"}));
+
+        set_upstream_stream(&mut payload, Protocol::Gemini, true);
+        assert_eq!(payload, json!({"model": "gpt-4"}));
+    }
+}
@@ -5,8 +5,11 @@
 //! Dynamic configuration is loaded from the database.
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::net::IpAddr;
 use utoipa::ToSchema;
 
 /// Extended model mapping entry with metadata.
@@ -68,6 +71,190 @@ pub struct ModelMappingEntry {
     /// Model operation mode (chat, completion, embedding, image_generation)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mode: Option<String>,
+
+    /// Trim conversations exceeding `max_input_tokens` instead of rejecting them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_trimming: Option<ContextTrimming>,
+
+    /// Local tokenizer for usage estimates (`tiktoken`, `claude`, a name in
+    /// `TOKENIZER_DIR` or a path to `tokenizer.json`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<String>,
+
+    /// System prompt template for emulated tool calling when
+    /// `supports_function_calling` is false (`{tools}` marks the tool list)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_prompt_template: Option<String>,
+
+    /// Call the upstream in this mode regardless of the client's `stream`
+    /// flag (overrides the provider's `upstream_mode` param)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_mode: Option<UpstreamMode>,
+}
+
+/// How the proxy calls an upstream that only supports one response mode.
+///
+/// Responses are converted back to the mode the client asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamMode {
+    /// Always stream from the upstream
+    Stream,
+    /// Always make blocking upstream calls
+    Blocking,
+}
+
+/// Conversation trimming strategy for requests over a model's input limit.
+///
+/// System prompts and the latest turn are always kept, and tool calls are
+/// never separated from their results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ContextTrimming {
+    /// Drop the oldest turns until the conversation fits
+    DropOldest,
+    /// Keep system messages plus the last `messages` messages, then drop oldest if still too long
+    KeepLast { messages: usize },
+    /// Drop turns around the middle, keeping the first turn and the most recent ones
+    MiddleOut,
+}
+
+/// Personal data redacted from prompts before they are sent to a provider.
+///
+/// Detected values are replaced with placeholders such as `<EMAIL_1>` and
+/// restored in the response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PiiRedactionConfig {
+    /// Whether redaction is applied (a credential can turn off its provider's setting)
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Built-in entity types to detect
+    #[serde(default = "PiiEntity::all")]
+    pub entities: Vec<PiiEntity>,
+    /// Additional patterns, checked before the built-in entities
+    #[serde(default)]
+    pub patterns: Vec<PiiPattern>,
+}
+
+impl Default for PiiRedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            entities: PiiEntity::all(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Built-in personal data entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiEntity {
+    /// Email addresses
+    Email,
+    /// Phone numbers with 9 to 15 digits
+    Phone,
+    /// Payment card numbers passing the Luhn check
+    CreditCard,
+    /// US social security numbers (`123-45-6789`)
+    NationalId,
+}
+
+impl PiiEntity {
+    pub fn all() -> Vec<Self> {
+        vec![
+            PiiEntity::Email,
+            PiiEntity::Phone,
+            PiiEntity::CreditCard,
+            PiiEntity::NationalId,
+        ]
+    }
+
+    /// Placeholder label, e.g. `EMAIL` for `<EMAIL_1>`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            PiiEntity::Email => "EMAIL",
+            PiiEntity::Phone => "PHONE",
+            PiiEntity::CreditCard => "CREDIT_CARD",
+            PiiEntity::NationalId => "NATIONAL_ID",
+        }
+    }
+}
+
+/// Custom regex pattern redacted under its own placeholder label.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PiiPattern {
+    /// Placeholder label (`[A-Z][A-Z0-9_]*`), e.g. `IBAN` for `<IBAN_1>`
+    pub label: String,
+    /// Regular expression matching the values to redact
+    pub regex: String,
+}
+
+/// Content policy rule applied to the prompts and completions of the
+/// credentials it is attached to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GuardrailRule {
+    /// Unique name, referenced from credentials' `guardrails`
+    pub name: String,
+    /// What the rule detects
+    pub kind: GuardrailKind,
+    /// Deny phrases, regexes, or extra secret formats depending on `kind`
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// What happens when the rule matches
+    #[serde(default)]
+    pub action: GuardrailAction,
+    /// Whether prompts, completions or both are scanned
+    #[serde(default)]
+    pub direction: GuardrailDirection,
+    /// Whether the rule is applied
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// What a guardrail rule detects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailKind {
+    /// Built-in secret formats (private keys, cloud and API credentials) plus
+    /// `patterns` as regexes for in-house token formats
+    Secrets,
+    /// `patterns` are phrases matched case-insensitively on word boundaries
+    DenyPhrases,
+    /// `patterns` are regular expressions
+    Regex,
+}
+
+/// Action taken when a guardrail rule matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailAction {
+    /// Reject the request, or cut the response off with an error
+    #[default]
+    Block,
+    /// Replace the match with `[REDACTED:<rule>]`
+    Redact,
+    /// Only record the violation
+    Log,
+}
+
+/// Traffic a guardrail rule is applied to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardrailDirection {
+    /// Prompts sent to the provider
+    Input,
+    /// Completions returned to the client, including streamed ones
+    Output,
+    /// Prompts and completions
+    #[default]
+    Both,
+}
+
+impl GuardrailDirection {
+    pub fn includes(self, direction: GuardrailDirection) -> bool {
+        self == GuardrailDirection::Both || self == direction
+    }
 }
 
 /// Union type for backward-compatible model mapping.
@@ -156,6 +343,15 @@ pub struct AppConfig {
     #[serde(default)]
     pub ttft_timeout_secs: Option<u64>,
 
+    /// Maximum silence between upstream chunks once a stream has started
+    /// If set, the stream is ended with a timeout error event
+    #[serde(default)]
+    pub stream_idle_timeout_secs: Option<u64>,
+
+    /// Interval of SSE heartbeats sent to clients while the upstream is silent
+    #[serde(default)]
+    pub sse_keepalive_secs: Option<u64>,
+
     /// List of credentials with optional rate limiting
     #[serde(default)]
     pub credentials: Vec<CredentialConfig>,
@@ -172,6 +368,10 @@ pub struct AppConfig {
     /// Maximum tokens limit for max_tokens clamping
     #[serde(default = "default_max_tokens_limit")]
     pub max_tokens_limit: u32,
+
+    /// Content guardrail rules, attached to credentials by name
+    #[serde(default)]
+    pub guardrail_rules: Vec<GuardrailRule>,
 }
 
 /// Configuration for a credential with optional rate limiting.
@@ -198,6 +398,84 @@ pub struct CredentialConfig {
     /// List of models this credential can access (empty = all models allowed)
     #[serde(default)]
     pub allowed_models: Vec<String>,
+
+    /// Conversation trimming opt-in; takes precedence over the model's setting
+    #[serde(default)]
+    pub context_trimming: Option<ContextTrimming>,
+
+    /// Expiry time (None = never expires)
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Key replaced by the last rotation, accepted until its grace period ends
+    #[serde(default)]
+    pub previous_key: Option<PreviousCredentialKey>,
+
+    /// Networks requests may come from (empty = any address)
+    #[serde(default)]
+    pub allowed_cidrs: Vec<IpNet>,
+
+    /// PII redaction settings; take precedence over the provider's setting
+    #[serde(default)]
+    pub pii_redaction: Option<PiiRedactionConfig>,
+
+    /// Names of the guardrail rules applied to this credential's traffic
+    #[serde(default)]
+    pub guardrails: Vec<String>,
+}
+
+impl CredentialConfig {
+    /// Whether this credential is enabled and not expired at `now`.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.enabled && self.expires_at.is_none_or(|t| t > now)
+    }
+
+    /// Whether a request presenting a key with hash `key_hash` authenticates
+    /// as this credential at `now`.
+    pub fn accepts_key_hash(&self, key_hash: &str, now: DateTime<Utc>) -> bool {
+        if !self.is_active(now) {
+            return false;
+        }
+        self.credential_key == key_hash
+            || self
+                .previous_key
+                .as_ref()
+                .is_some_and(|p| p.key_hash == key_hash && p.expires_at > now)
+    }
+
+    /// Whether a request from `client_ip` may use this credential.
+    ///
+    /// Credentials without allowlists accept any address; credentials with
+    /// one reject requests whose address is unknown.
+    pub fn allows_ip(&self, client_ip: Option<IpAddr>) -> bool {
+        if self.allowed_cidrs.is_empty() {
+            return true;
+        }
+        let Some(ip) = client_ip else {
+            return false;
+        };
+        let ip = ip.to_canonical();
+        self.allowed_cidrs.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// Parse a CIDR range, or a single address as a host range.
+pub fn parse_network(value: &str) -> std::result::Result<IpNet, String> {
+    let value = value.trim();
+    value
+        .parse::<IpNet>()
+        .map(|net| net.trunc())
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("'{}' is not a valid CIDR range or IP address", value))
+}
+
+/// A rotated-out credential key still inside its grace period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviousCredentialKey {
+    /// Hash of the previous key
+    pub key_hash: String,
+    /// When the previous key stops being accepted
+    pub expires_at: DateTime<Utc>,
 }
 
 /// Rate limiting configuration for a credential.
@@ -324,6 +602,12 @@ impl AppConfig {
         let ttft_timeout_secs = std::env::var("TTFT_TIMEOUT_SECS")
             .ok()
             .and_then(|t| t.parse().ok());
+        let stream_idle_timeout_secs = std::env::var("STREAM_IDLE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|t| t.parse().ok());
+        let sse_keepalive_secs = std::env::var("SSE_KEEPALIVE_SECS")
+            .ok()
+            .and_then(|t| t.parse().ok());
         let provider_suffix = std::env::var("PROVIDER_SUFFIX").ok();
         let min_tokens_limit = std::env::var("MIN_TOKENS_LIMIT")
             .ok()
@@ -340,10 +624,13 @@ impl AppConfig {
             verify_ssl,
             request_timeout_secs,
             ttft_timeout_secs,
+            stream_idle_timeout_secs,
+            sse_keepalive_secs,
             credentials: vec![],
             provider_suffix,
             min_tokens_limit,
             max_tokens_limit,
+            guardrail_rules: vec![],
         })
     }
 }
@@ -478,4 +765,79 @@ mod tests {
             std::env::remove_var("PROVIDER_SUFFIX");
         }
     }
+
+    fn credential(expires_at: Option<DateTime<Utc>>) -> CredentialConfig {
+        CredentialConfig {
+            credential_key: "new-hash".to_string(),
+            name: "ci".to_string(),
+            description: None,
+            rate_limit: None,
+            enabled: true,
+            allowed_models: vec![],
+            context_trimming: None,
+            expires_at,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
+        }
+    }
+
+    #[test]
+    fn test_credential_expiry_rejects_key() {
+        let now = Utc::now();
+        assert!(credential(None).accepts_key_hash("new-hash", now));
+        assert!(
+            credential(Some(now + chrono::Duration::hours(1))).accepts_key_hash("new-hash", now)
+        );
+        assert!(!credential(Some(now)).accepts_key_hash("new-hash", now));
+
+        let mut disabled = credential(None);
+        disabled.enabled = false;
+        assert!(!disabled.accepts_key_hash("new-hash", now));
+    }
+
+    #[test]
+    fn test_rotated_key_accepted_during_grace_period() {
+        let now = Utc::now();
+        let mut config = credential(None);
+        config.previous_key = Some(PreviousCredentialKey {
+            key_hash: "old-hash".to_string(),
+            expires_at: now + chrono::Duration::minutes(10),
+        });
+        assert!(config.accepts_key_hash("old-hash", now));
+        assert!(config.accepts_key_hash("new-hash", now));
+        assert!(!config.accepts_key_hash("old-hash", now + chrono::Duration::minutes(10)));
+        assert!(!config.accepts_key_hash("other-hash", now));
+    }
+
+    #[test]
+    fn test_parse_network() {
+        assert_eq!(
+            parse_network("10.1.2.3/8").unwrap().to_string(),
+            "10.0.0.0/8"
+        );
+        assert_eq!(
+            parse_network(" 192.0.2.1 ").unwrap().to_string(),
+            "192.0.2.1/32"
+        );
+        assert_eq!(
+            parse_network("2001:db8::1").unwrap().to_string(),
+            "2001:db8::1/128"
+        );
+        assert!(parse_network("10.0.0.0/33").is_err());
+        assert!(parse_network("office").is_err());
+    }
+
+    #[test]
+    fn test_allows_ip_matches_ipv4_mapped_addresses() {
+        let mut config = credential(None);
+        let mapped: IpAddr = "::ffff:10.20.30.40".parse().unwrap();
+        assert!(config.allows_ip(None));
+
+        config.allowed_cidrs = vec![parse_network("10.0.0.0/8").unwrap()];
+        assert!(config.allows_ip(Some(mapped)));
+        assert!(!config.allows_ip(Some("11.0.0.1".parse().unwrap())));
+        assert!(!config.allows_ip(None));
+    }
 }
@@ -0,0 +1,318 @@
+//! Configuration history snapshots, diffs and rollback helpers.
+//!
+//! Every `config_version` bump records a snapshot of all provider,
+//! credential and guardrail rule rows in the `config_history` table (see
+//! migrations 000013 and 000017).
+//! Rows are kept as the JSON of the table row so that columns added later
+//! are captured without code changes; restoring an older snapshot gives such
+//! columns their defaults. Provider API keys are only kept when encrypted at
+//! rest; plaintext keys are stored as `null`.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use utoipa::ToSchema;
+
+/// Fields holding secrets (or their hashes), masked in API output.
+pub const SECRET_FIELDS: &[&str] = &["api_key", "credential_key", "previous_key_hash"];
+
+/// Fields that change on every write or use and carry no configuration meaning.
+const IGNORED_FIELDS: &[&str] = &["updated_at", "last_used_at"];
+
+const MASKED: &str = "***";
+
+/// Errors restoring a configuration version.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigRestoreError {
+    #[error("Configuration version {0} not found in history")]
+    NotFound(i64),
+    #[error(
+        "Cannot restore providers {0:?}: their API keys were not stored (encryption at rest was not configured) and they no longer exist"
+    )]
+    MissingApiKeys(Vec<String>),
+    #[error(
+        "Cannot restore providers {0:?}: their stored API keys cannot be decrypted with the configured master keys"
+    )]
+    UndecryptableApiKeys(Vec<String>),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+fn row_id(row: &Value) -> Option<i64> {
+    row.get("id").and_then(Value::as_i64)
+}
+
+fn mask(field: &str, value: &Value) -> Value {
+    if SECRET_FIELDS.contains(&field) && !value.is_null() {
+        Value::String(MASKED.to_string())
+    } else {
+        value.clone()
+    }
+}
+
+/// Mask secret fields of snapshot rows for API output.
+///
+/// Stored secrets become `"***"`; secrets that were not stored stay `null`.
+pub fn redact_rows(rows: &[Value]) -> Vec<Value> {
+    rows.iter()
+        .map(|row| match row {
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), mask(k, v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        })
+        .collect()
+}
+
+/// Fill in provider API keys that were redacted in the snapshot with the
+/// current value of the same provider.
+///
+/// Returns the provider keys that cannot be restored because their API key
+/// was not stored and the provider no longer exists.
+pub fn fill_redacted_api_keys(
+    providers: &mut [Value],
+    current_keys: &HashMap<i64, String>,
+) -> Result<(), Vec<String>> {
+    let mut missing = Vec::new();
+    for row in providers.iter_mut() {
+        if !row.get("api_key").is_none_or(Value::is_null) {
+            continue;
+        }
+        match row_id(row).and_then(|id| current_keys.get(&id)) {
+            Some(key) => row["api_key"] = Value::String(key.clone()),
+            None => missing.push(
+                row.get("provider_key")
+                    .and_then(Value::as_str)
+                    .unwrap_or("<unknown>")
+                    .to_string(),
+            ),
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(missing)
+    }
+}
+
+/// Statement inserting snapshot rows (bound as `$1`) into `table`, whose
+/// current columns are `table_columns`; None when there are no rows.
+///
+/// Only columns present in the snapshot are inserted. Columns added after
+/// the snapshot was recorded then take their defaults instead of NULL,
+/// which NOT NULL columns would reject; columns dropped since are ignored.
+pub fn restore_insert_sql(table: &str, rows: &[Value], table_columns: &[String]) -> Option<String> {
+    if rows.is_empty() {
+        return None;
+    }
+    let columns = table_columns
+        .iter()
+        .filter(|column| rows.iter().any(|row| row.get(column.as_str()).is_some()))
+        .map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(format!(
+        "INSERT INTO {table} ({columns}) SELECT {columns} FROM jsonb_populate_recordset(NULL::{table}, $1)"
+    ))
+}
+
+/// A changed field of a resource.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct FieldChange {
+    pub from: Value,
+    pub to: Value,
+}
+
+/// A resource present in both versions with different field values.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ResourceChange {
+    /// Row ID
+    pub id: i64,
+    /// Provider key or credential name (as of the newer version)
+    pub label: Option<String>,
+    /// Changed fields; secrets are masked
+    pub fields: BTreeMap<String, FieldChange>,
+}
+
+/// Differences of one resource type between two versions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct ResourceDiff {
+    /// Rows only in the newer version (secrets masked)
+    pub added: Vec<Value>,
+    /// Rows only in the older version (secrets masked)
+    pub removed: Vec<Value>,
+    pub changed: Vec<ResourceChange>,
+}
+
+impl ResourceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Structured diff of provider and credential rows, matched by ID.
+///
+/// `label_field` names the row field shown as the resource label.
+pub fn diff_rows(from: &[Value], to: &[Value], label_field: &str) -> ResourceDiff {
+    let from_by_id: BTreeMap<i64, &Value> =
+        from.iter().filter_map(|r| Some((row_id(r)?, r))).collect();
+    let to_by_id: BTreeMap<i64, &Value> = to.iter().filter_map(|r| Some((row_id(r)?, r))).collect();
+
+    let mut diff = ResourceDiff::default();
+    for (id, old) in &from_by_id {
+        if !to_by_id.contains_key(id) {
+            diff.removed.extend(redact_rows(std::slice::from_ref(*old)));
+        }
+    }
+    for (id, new) in &to_by_id {
+        let Some(old) = from_by_id.get(id) else {
+            diff.added.extend(redact_rows(std::slice::from_ref(*new)));
+            continue;
+        };
+
+        let empty = serde_json::Map::new();
+        let old_fields = old.as_object().unwrap_or(&empty);
+        let new_fields = new.as_object().unwrap_or(&empty);
+        let mut fields = BTreeMap::new();
+        for name in old_fields.keys().chain(new_fields.keys()) {
+            if IGNORED_FIELDS.contains(&name.as_str()) || fields.contains_key(name) {
+                continue;
+            }
+            let before = old_fields.get(name).unwrap_or(&Value::Null);
+            let after = new_fields.get(name).unwrap_or(&Value::Null);
+            if before != after {
+                fields.insert(
+                    name.clone(),
+                    FieldChange {
+                        from: mask(name, before),
+                        to: mask(name, after),
+                    },
+                );
+            }
+        }
+
+        if !fields.is_empty() {
+            diff.changed.push(ResourceChange {
+                id: *id,
+                label: new
+                    .get(label_field)
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                fields,
+            });
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(id: i64, mapping: Value, api_key: Value) -> Value {
+        json!({
+            "id": id,
+            "provider_key": format!("p{}", id),
+            "api_key": api_key,
+            "model_mapping": mapping,
+            "updated_at": format!("2024-01-0{}T00:00:00Z", id),
+        })
+    }
+
+    #[test]
+    fn test_diff_reports_added_removed_and_changed_rows() {
+        let from = vec![
+            provider(1, json!({"gpt-4": "gpt-4o"}), json!("enc:v1:a")),
+            provider(2, json!({}), Value::Null),
+        ];
+        let mut changed = provider(1, json!({"gpt-4": "gpt-4.1"}), json!("enc:v1:b"));
+        changed["updated_at"] = json!("2024-02-01T00:00:00Z");
+        let to = vec![changed, provider(3, json!({}), json!("enc:v1:c"))];
+
+        let diff = diff_rows(&from, &to, "provider_key");
+
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0]["id"], 2);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0]["api_key"], "***");
+        assert_eq!(diff.changed.len(), 1);
+        let change = &diff.changed[0];
+        assert_eq!(change.label.as_deref(), Some("p1"));
+        assert_eq!(
+            change.fields.keys().collect::<Vec<_>>(),
+            vec!["api_key", "model_mapping"]
+        );
+        assert_eq!(change.fields["api_key"].from, json!("***"));
+        assert_eq!(
+            change.fields["model_mapping"].to,
+            json!({"gpt-4": "gpt-4.1"})
+        );
+    }
+
+    #[test]
+    fn test_identical_rows_have_empty_diff() {
+        let rows = vec![provider(1, json!({}), Value::Null)];
+        assert!(diff_rows(&rows, &rows, "provider_key").is_empty());
+    }
+
+    #[test]
+    fn test_redact_rows_masks_only_stored_secrets() {
+        let rows = redact_rows(&[
+            provider(1, json!({}), json!("enc:v1:a")),
+            json!({"id": 2, "name": "ci", "credential_key": "abc123", "previous_key_hash": "def456"}),
+            provider(3, json!({}), Value::Null),
+            json!({"id": 4, "name": "cd", "credential_key": "789abc", "previous_key_hash": null}),
+        ]);
+        assert_eq!(rows[0]["api_key"], "***");
+        assert_eq!(rows[1]["credential_key"], "***");
+        assert_eq!(rows[1]["previous_key_hash"], "***");
+        assert_eq!(rows[1]["name"], "ci");
+        assert!(rows[2]["api_key"].is_null());
+        assert!(rows[3]["previous_key_hash"].is_null());
+    }
+
+    #[test]
+    fn test_fill_redacted_api_keys_uses_current_keys() {
+        let mut providers = vec![
+            provider(1, json!({}), json!("enc:v1:a")),
+            provider(2, json!({}), Value::Null),
+        ];
+        let current = HashMap::from([(2, "sk-current".to_string())]);
+        fill_redacted_api_keys(&mut providers, &current).unwrap();
+        assert_eq!(providers[0]["api_key"], "enc:v1:a");
+        assert_eq!(providers[1]["api_key"], "sk-current");
+    }
+
+    #[test]
+    fn test_restore_insert_sql_leaves_out_columns_newer_than_snapshot() {
+        let columns: Vec<String> = ["id", "name", "allowed_cidrs", "guardrails"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        // Recorded before allowed_cidrs and guardrails existed, and with a
+        // column dropped since
+        let rows = vec![json!({"id": 1, "name": "ci", "legacy": true})];
+
+        assert_eq!(
+            restore_insert_sql("credentials", &rows, &columns).as_deref(),
+            Some(
+                "INSERT INTO credentials (\"id\", \"name\") SELECT \"id\", \"name\" \
+                 FROM jsonb_populate_recordset(NULL::credentials, $1)"
+            )
+        );
+        assert_eq!(restore_insert_sql("credentials", &[], &columns), None);
+    }
+
+    #[test]
+    fn test_fill_redacted_api_keys_reports_deleted_providers() {
+        let mut providers = vec![provider(4, json!({}), Value::Null)];
+        assert_eq!(
+            fill_redacted_api_keys(&mut providers, &HashMap::new()),
+            Err(vec!["p4".to_string()])
+        );
+    }
+}
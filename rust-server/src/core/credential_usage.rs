@@ -0,0 +1,182 @@
+//! Async tracker that batches credential `last_used_at` updates into the database.
+//!
+//! Mirrors the request_logger.rs pattern: MPSC channel → batch UPDATE.
+//! Authentication only enqueues the credential key hash; repeated uses of the
+//! same credential between flushes collapse into one row update.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::{mpsc, oneshot};
+
+const FLUSH_INTERVAL_SECS: u64 = 30;
+const MAX_PENDING: usize = 500;
+
+struct CredentialUse {
+    key_hash: String,
+    used_at: DateTime<Utc>,
+}
+
+pub struct CredentialUsageTracker {
+    tx: mpsc::Sender<CredentialUse>,
+    done_rx: Mutex<Option<oneshot::Receiver<()>>>,
+}
+
+impl CredentialUsageTracker {
+    pub fn new(pool: PgPool) -> Self {
+        let (tx, rx) = mpsc::channel(10000);
+        let (done_tx, done_rx) = oneshot::channel();
+        tokio::spawn(Self::writer_task(rx, pool, done_tx));
+        Self {
+            tx,
+            done_rx: Mutex::new(Some(done_rx)),
+        }
+    }
+
+    pub fn record(&self, key_hash: &str) {
+        let usage = CredentialUse {
+            key_hash: key_hash.to_string(),
+            used_at: Utc::now(),
+        };
+        if let Err(e) = self.tx.try_send(usage) {
+            tracing::debug!("Credential usage channel full, dropping update: {}", e);
+        }
+    }
+
+    async fn writer_task(
+        mut rx: mpsc::Receiver<CredentialUse>,
+        pool: PgPool,
+        done_tx: oneshot::Sender<()>,
+    ) {
+        let mut pending: HashMap<String, DateTime<Utc>> = HashMap::new();
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(FLUSH_INTERVAL_SECS));
+
+        loop {
+            tokio::select! {
+                Some(usage) = rx.recv() => {
+                    merge_use(&mut pending, usage);
+                    if pending.len() >= MAX_PENDING {
+                        Self::flush(&pool, &mut pending).await;
+                    }
+                }
+                _ = interval.tick() => {
+                    if !pending.is_empty() {
+                        Self::flush(&pool, &mut pending).await;
+                    }
+                }
+                else => {
+                    // Channel closed — flush remaining updates
+                    if !pending.is_empty() {
+                        Self::flush(&pool, &mut pending).await;
+                    }
+                    break;
+                }
+            }
+        }
+
+        tracing::info!("Credential usage tracker stopped");
+        let _ = done_tx.send(());
+    }
+
+    async fn flush(pool: &PgPool, pending: &mut HashMap<String, DateTime<Utc>>) {
+        let (hashes, used_at): (Vec<String>, Vec<DateTime<Utc>>) = pending.drain().unzip();
+
+        // Only touches last_used_at, which does not bump config_version.
+        let result = sqlx::query(
+            r#"
+            UPDATE credentials AS c
+            SET last_used_at = u.used_at
+            FROM UNNEST($1::VARCHAR[], $2::TIMESTAMPTZ[]) AS u(key_hash, used_at)
+            WHERE c.credential_key = u.key_hash
+              AND (c.last_used_at IS NULL OR c.last_used_at < u.used_at)
+            "#,
+        )
+        .bind(&hashes)
+        .bind(&used_at)
+        .execute(pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::error!("Failed to flush credential usage to database: {}", e);
+        }
+    }
+}
+
+fn merge_use(pending: &mut HashMap<String, DateTime<Utc>>, usage: CredentialUse) {
+    pending
+        .entry(usage.key_hash)
+        .and_modify(|t| *t = (*t).max(usage.used_at))
+        .or_insert(usage.used_at);
+}
+
+// Mutex<Option<…>> allows shutdown to take (drop) the sender, triggering writer flush
+static CREDENTIAL_USAGE_TRACKER: OnceLock<Mutex<Option<CredentialUsageTracker>>> = OnceLock::new();
+
+pub fn init_credential_usage_tracker(pool: PgPool) {
+    let tracker = CredentialUsageTracker::new(pool);
+    CREDENTIAL_USAGE_TRACKER.get_or_init(|| Mutex::new(Some(tracker)));
+    tracing::info!("Credential usage tracker initialized");
+}
+
+/// Record a successful authentication of the credential with key hash `key_hash`.
+pub fn record_credential_use(key_hash: &str) {
+    if let Some(mutex) = CREDENTIAL_USAGE_TRACKER.get() {
+        if let Ok(guard) = mutex.lock() {
+            if let Some(ref tracker) = *guard {
+                tracker.record(key_hash);
+            }
+        }
+    }
+}
+
+/// Graceful shutdown: drops the sender so the writer task flushes pending updates and exits.
+pub async fn shutdown_credential_usage_tracker() {
+    if let Some(mutex) = CREDENTIAL_USAGE_TRACKER.get() {
+        let (taken, done_rx) = {
+            let mut guard = mutex.lock().unwrap_or_else(|e| e.into_inner());
+            let tracker = guard.take();
+            let rx = tracker
+                .as_ref()
+                .and_then(|t| t.done_rx.lock().ok().and_then(|mut r| r.take()));
+            (tracker, rx)
+        };
+        if taken.is_some() {
+            drop(taken);
+            if let Some(rx) = done_rx {
+                let _ = tokio::time::timeout(std::time::Duration::from_secs(5), rx).await;
+            }
+            tracing::info!("Credential usage tracker shut down");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_merge_use_keeps_latest_timestamp_per_credential() {
+        let now = Utc::now();
+        let mut pending = HashMap::new();
+        for (hash, used_at) in [
+            ("a", now - Duration::seconds(5)),
+            ("a", now),
+            ("a", now - Duration::seconds(10)),
+            ("b", now - Duration::seconds(1)),
+        ] {
+            merge_use(
+                &mut pending,
+                CredentialUse {
+                    key_hash: hash.to_string(),
+                    used_at,
+                },
+            );
+        }
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending["a"], now);
+        assert_eq!(pending["b"], now - Duration::seconds(1));
+    }
+}
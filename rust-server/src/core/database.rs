@@ -3,8 +3,14 @@
 //! PostgreSQL only - optimized for production use.
 //! Migrations are managed externally by golang-migrate.
 
-use crate::core::config::ModelMappingValue;
+use crate::core::config::{
+    parse_network, ContextTrimming, GuardrailRule, ModelMappingValue, PiiRedactionConfig,
+    PreviousCredentialKey,
+};
+use crate::core::config_history::{fill_redacted_api_keys, restore_insert_sql, ConfigRestoreError};
+use crate::core::key_encryption::{decrypt_api_key, is_encrypted, ProviderKeyCipher};
 use chrono::{DateTime, Utc};
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use sqlx::{FromRow, PgPool};
@@ -122,6 +128,8 @@ fn encode_password(password: &str) -> String {
 #[derive(Clone)]
 pub struct Database {
     pool: PgPool,
+    /// Master keys for provider API keys (None = stored in plaintext)
+    key_cipher: Option<Arc<ProviderKeyCipher>>,
 }
 
 impl Database {
@@ -134,13 +142,112 @@ impl Database {
             .connect(&config.url)
             .await?;
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            key_cipher: None,
+        })
+    }
+
+    /// Encrypt provider API keys at rest with the given master keys.
+    pub fn with_key_cipher(mut self, cipher: ProviderKeyCipher) -> Self {
+        self.key_cipher = Some(Arc::new(cipher));
+        self
     }
 
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 
+    /// Master keys for provider API keys, if encryption is enabled.
+    pub fn key_cipher(&self) -> Option<&ProviderKeyCipher> {
+        self.key_cipher.as_deref()
+    }
+
+    /// Encrypt an API key for storage (unchanged when encryption is disabled).
+    fn encrypt_api_key(&self, api_key: &str) -> Result<String, sqlx::Error> {
+        match self.key_cipher() {
+            Some(cipher) => cipher
+                .encrypt(api_key)
+                .map_err(|e| sqlx::Error::Encode(Box::new(e))),
+            None => Ok(api_key.to_string()),
+        }
+    }
+
+    /// Replace the stored API key of a provider row with its plaintext.
+    fn decrypt_provider(
+        &self,
+        mut provider: ProviderEntity,
+    ) -> Result<ProviderEntity, sqlx::Error> {
+        provider.api_key = decrypt_api_key(self.key_cipher(), &provider.api_key).map_err(|e| {
+            tracing::error!(provider = %provider.provider_key, error = %e, "Failed to decrypt provider API key");
+            sqlx::Error::Decode(Box::new(e))
+        })?;
+        Ok(provider)
+    }
+
+    fn decrypt_providers(
+        &self,
+        providers: Vec<ProviderEntity>,
+    ) -> Result<Vec<ProviderEntity>, sqlx::Error> {
+        providers
+            .into_iter()
+            .map(|p| self.decrypt_provider(p))
+            .collect()
+    }
+
+    /// Encrypt provider API keys still stored in plaintext.
+    ///
+    /// Run at startup, so rows written before encryption was enabled are
+    /// migrated. Returns the number of rows encrypted.
+    pub async fn encrypt_plaintext_provider_keys(&self) -> Result<usize, sqlx::Error> {
+        self.rewrite_provider_keys(|stored| !is_encrypted(stored))
+            .await
+    }
+
+    /// Re-encrypt every provider API key not sealed under the primary master key.
+    ///
+    /// Used after a master key rotation, before the retired key is removed.
+    /// Returns the number of rows re-encrypted.
+    pub async fn reencrypt_provider_keys(&self) -> Result<usize, sqlx::Error> {
+        let Some(cipher) = self.key_cipher.clone() else {
+            return Ok(0);
+        };
+        self.rewrite_provider_keys(|stored| cipher.needs_reencryption(stored))
+            .await
+    }
+
+    async fn rewrite_provider_keys(
+        &self,
+        needs_rewrite: impl Fn(&str) -> bool,
+    ) -> Result<usize, sqlx::Error> {
+        let Some(cipher) = self.key_cipher() else {
+            return Ok(0);
+        };
+        let mut tx = self.pool.begin().await?;
+        let rows: Vec<(i32, String)> =
+            sqlx::query_as("SELECT id, api_key FROM providers ORDER BY id FOR UPDATE")
+                .fetch_all(&mut *tx)
+                .await?;
+
+        let mut rewritten = 0;
+        for (id, stored) in rows {
+            if !needs_rewrite(&stored) {
+                continue;
+            }
+            let reencrypted = cipher
+                .reencrypt(&stored)
+                .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+            sqlx::query("UPDATE providers SET api_key = $2 WHERE id = $1")
+                .bind(id)
+                .bind(reencrypted)
+                .execute(&mut *tx)
+                .await?;
+            rewritten += 1;
+        }
+        tx.commit().await?;
+        Ok(rewritten)
+    }
+
     /// Check if migrations have been applied (by golang-migrate)
     pub async fn check_migrations(&self) -> Result<bool, sqlx::Error> {
         let exists: bool = sqlx::query_scalar(
@@ -167,6 +274,118 @@ impl Database {
         Ok(version)
     }
 
+    /// List recorded configuration versions, newest first, with the total count
+    pub async fn list_config_history(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<ConfigVersionSummary>, i64), sqlx::Error> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM config_history")
+            .fetch_one(&self.pool)
+            .await?;
+        let items = sqlx::query_as::<_, ConfigVersionSummary>(
+            r#"
+            SELECT version, created_at, change_source,
+                   jsonb_array_length(providers) AS provider_count,
+                   jsonb_array_length(credentials) AS credential_count
+            FROM config_history
+            ORDER BY version DESC
+            LIMIT $1 OFFSET $2
+            "#,
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((items, total))
+    }
+
+    /// Get the configuration snapshot recorded for a version
+    pub async fn get_config_snapshot(
+        &self,
+        version: i64,
+    ) -> Result<Option<ConfigHistoryEntity>, sqlx::Error> {
+        sqlx::query_as::<_, ConfigHistoryEntity>(
+            r#"
+            SELECT version, created_at, change_source, providers, credentials, guardrail_rules
+            FROM config_history
+            WHERE version = $1
+            "#,
+        )
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Atomically replace all providers, credentials and guardrail rules with
+    /// the snapshot of `version`. The restore is recorded as a single new version, which is
+    /// returned.
+    pub async fn restore_config_version(&self, version: i64) -> Result<i64, ConfigRestoreError> {
+        let mut tx = self.pool.begin().await?;
+        // Triggers would record a version per statement; record one at the end instead.
+        sqlx::query("SET LOCAL llm_proxy.defer_config_version = 'on'")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "LOCK TABLE providers, credentials, guardrail_rules IN SHARE ROW EXCLUSIVE MODE",
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let snapshot = sqlx::query_as::<_, ConfigHistoryEntity>(
+            r#"
+            SELECT version, created_at, change_source, providers, credentials, guardrail_rules
+            FROM config_history
+            WHERE version = $1
+            "#,
+        )
+        .bind(version)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ConfigRestoreError::NotFound(version))?;
+        let mut providers = snapshot.providers.0;
+
+        let current_keys: HashMap<i64, String> =
+            sqlx::query_as::<_, (i32, String)>("SELECT id, api_key FROM providers")
+                .fetch_all(&mut *tx)
+                .await?
+                .into_iter()
+                .map(|(id, key)| (i64::from(id), key))
+                .collect();
+        fill_redacted_api_keys(&mut providers, &current_keys)
+            .map_err(ConfigRestoreError::MissingApiKeys)?;
+        // Snapshots keep keys encrypted under the master key of their time,
+        // which may have been rotated out since.
+        let undecryptable: Vec<String> = providers
+            .iter()
+            .filter(|row| {
+                row.get("api_key")
+                    .and_then(serde_json::Value::as_str)
+                    .is_some_and(|key| decrypt_api_key(self.key_cipher.as_deref(), key).is_err())
+            })
+            .map(|row| {
+                row["provider_key"]
+                    .as_str()
+                    .unwrap_or("<unknown>")
+                    .to_string()
+            })
+            .collect();
+        if !undecryptable.is_empty() {
+            return Err(ConfigRestoreError::UndecryptableApiKeys(undecryptable));
+        }
+
+        restore_table(&mut tx, "providers", &providers).await?;
+        restore_table(&mut tx, "credentials", &snapshot.credentials.0).await?;
+        restore_table(&mut tx, "guardrail_rules", &snapshot.guardrail_rules.0).await?;
+
+        let new_version: i64 = sqlx::query_scalar("SELECT record_config_version($1)")
+            .bind(format!("rollback:{}", version))
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(new_version)
+    }
+
     /// Load all enabled providers from database
     pub async fn load_providers(&self) -> Result<Vec<ProviderEntity>, sqlx::Error> {
         let providers = sqlx::query_as::<_, ProviderEntity>(
@@ -179,7 +398,7 @@ impl Database {
         )
         .fetch_all(&self.pool)
         .await?;
-        Ok(providers)
+        self.decrypt_providers(providers)
     }
 
     /// Load all providers (including disabled)
@@ -193,7 +412,7 @@ impl Database {
         )
         .fetch_all(&self.pool)
         .await?;
-        Ok(providers)
+        self.decrypt_providers(providers)
     }
 
     /// Get provider by ID (auto-increment integer)
@@ -208,7 +427,7 @@ impl Database {
         .bind(id)
         .fetch_optional(&self.pool)
         .await?;
-        Ok(provider)
+        provider.map(|p| self.decrypt_provider(p)).transpose()
     }
 
     /// Get provider by provider_key (unique string identifier)
@@ -226,7 +445,7 @@ impl Database {
         .bind(provider_key)
         .fetch_optional(&self.pool)
         .await?;
-        Ok(provider)
+        provider.map(|p| self.decrypt_provider(p)).transpose()
     }
 
     /// Create a new provider
@@ -244,14 +463,14 @@ impl Database {
         .bind(&provider.provider_key)
         .bind(&provider.provider_type)
         .bind(&provider.api_base)
-        .bind(&provider.api_key)
+        .bind(self.encrypt_api_key(&provider.api_key)?)
         .bind(sqlx::types::Json(&provider.model_mapping))
         .bind(provider.weight)
         .bind(provider.is_enabled)
         .bind(sqlx::types::Json(&provider.provider_params))
         .fetch_one(&self.pool)
         .await?;
-        Ok(entity)
+        self.decrypt_provider(entity)
     }
 
     /// Update an existing provider
@@ -278,14 +497,20 @@ impl Database {
         .bind(id)
         .bind(&update.provider_type)
         .bind(&update.api_base)
-        .bind(&update.api_key)
+        .bind(
+            update
+                .api_key
+                .as_deref()
+                .map(|key| self.encrypt_api_key(key))
+                .transpose()?,
+        )
         .bind(update.model_mapping.as_ref().map(sqlx::types::Json))
         .bind(update.weight)
         .bind(update.is_enabled)
         .bind(update.provider_params.as_ref().map(sqlx::types::Json))
         .fetch_optional(&self.pool)
         .await?;
-        Ok(entity)
+        entity.map(|e| self.decrypt_provider(e)).transpose()
     }
 
     /// Delete a provider
@@ -301,7 +526,7 @@ impl Database {
     pub async fn load_credentials(&self) -> Result<Vec<CredentialEntity>, sqlx::Error> {
         let credentials = sqlx::query_as::<_, CredentialEntity>(
             r#"
-            SELECT id, credential_key, name, allowed_models, rate_limit, is_enabled, created_at, updated_at
+            SELECT id, credential_key, name, allowed_models, allowed_cidrs, rate_limit, is_enabled, context_trimming, pii_redaction, guardrails, expires_at, last_used_at, previous_key_hash, previous_key_expires_at, created_at, updated_at
             FROM credentials
             WHERE is_enabled = true
             ORDER BY id
@@ -316,7 +541,7 @@ impl Database {
     pub async fn load_all_credentials(&self) -> Result<Vec<CredentialEntity>, sqlx::Error> {
         let credentials = sqlx::query_as::<_, CredentialEntity>(
             r#"
-            SELECT id, credential_key, name, allowed_models, rate_limit, is_enabled, created_at, updated_at
+            SELECT id, credential_key, name, allowed_models, allowed_cidrs, rate_limit, is_enabled, context_trimming, pii_redaction, guardrails, expires_at, last_used_at, previous_key_hash, previous_key_expires_at, created_at, updated_at
             FROM credentials
             ORDER BY id
             "#,
@@ -330,7 +555,7 @@ impl Database {
     pub async fn get_credential(&self, id: i32) -> Result<Option<CredentialEntity>, sqlx::Error> {
         let credential = sqlx::query_as::<_, CredentialEntity>(
             r#"
-            SELECT id, credential_key, name, allowed_models, rate_limit, is_enabled, created_at, updated_at
+            SELECT id, credential_key, name, allowed_models, allowed_cidrs, rate_limit, is_enabled, context_trimming, pii_redaction, guardrails, expires_at, last_used_at, previous_key_hash, previous_key_expires_at, created_at, updated_at
             FROM credentials
             WHERE id = $1
             "#,
@@ -348,7 +573,7 @@ impl Database {
     ) -> Result<Option<CredentialEntity>, sqlx::Error> {
         let credential = sqlx::query_as::<_, CredentialEntity>(
             r#"
-            SELECT id, credential_key, name, allowed_models, rate_limit, is_enabled, created_at, updated_at
+            SELECT id, credential_key, name, allowed_models, allowed_cidrs, rate_limit, is_enabled, context_trimming, pii_redaction, guardrails, expires_at, last_used_at, previous_key_hash, previous_key_expires_at, created_at, updated_at
             FROM credentials
             WHERE credential_key = $1 AND is_enabled = true
             "#,
@@ -367,9 +592,9 @@ impl Database {
         let credential_key = hash_key(&credential.key);
         let entity = sqlx::query_as::<_, CredentialEntity>(
             r#"
-            INSERT INTO credentials (credential_key, name, allowed_models, rate_limit, is_enabled)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, credential_key, name, allowed_models, rate_limit, is_enabled, created_at, updated_at
+            INSERT INTO credentials (credential_key, name, allowed_models, rate_limit, is_enabled, context_trimming, expires_at, allowed_cidrs, pii_redaction, guardrails)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id, credential_key, name, allowed_models, allowed_cidrs, rate_limit, is_enabled, context_trimming, pii_redaction, guardrails, expires_at, last_used_at, previous_key_hash, previous_key_expires_at, created_at, updated_at
             "#,
         )
         .bind(&credential_key)
@@ -377,6 +602,11 @@ impl Database {
         .bind(sqlx::types::Json(&credential.allowed_models))
         .bind(credential.rate_limit)
         .bind(credential.is_enabled)
+        .bind(credential.context_trimming.as_ref().map(sqlx::types::Json))
+        .bind(credential.expires_at)
+        .bind(sqlx::types::Json(&credential.allowed_cidrs))
+        .bind(credential.pii_redaction.as_ref().map(sqlx::types::Json))
+        .bind(sqlx::types::Json(&credential.guardrails))
         .fetch_one(&self.pool)
         .await?;
         Ok(entity)
@@ -397,9 +627,14 @@ impl Database {
                 allowed_models = COALESCE($4, allowed_models),
                 rate_limit = COALESCE($5, rate_limit),
                 is_enabled = COALESCE($6, is_enabled),
+                context_trimming = COALESCE($7, context_trimming),
+                expires_at = COALESCE($8, expires_at),
+                allowed_cidrs = COALESCE($9, allowed_cidrs),
+                pii_redaction = COALESCE($10, pii_redaction),
+                guardrails = COALESCE($11, guardrails),
                 updated_at = NOW()
             WHERE id = $1
-            RETURNING id, credential_key, name, allowed_models, rate_limit, is_enabled, created_at, updated_at
+            RETURNING id, credential_key, name, allowed_models, allowed_cidrs, rate_limit, is_enabled, context_trimming, pii_redaction, guardrails, expires_at, last_used_at, previous_key_hash, previous_key_expires_at, created_at, updated_at
             "#,
         )
         .bind(id)
@@ -408,6 +643,37 @@ impl Database {
         .bind(update.allowed_models.as_ref().map(sqlx::types::Json))
         .bind(update.rate_limit)
         .bind(update.is_enabled)
+        .bind(update.context_trimming.as_ref().map(sqlx::types::Json))
+        .bind(update.expires_at)
+        .bind(update.allowed_cidrs.as_ref().map(sqlx::types::Json))
+        .bind(update.pii_redaction.as_ref().map(sqlx::types::Json))
+        .bind(update.guardrails.as_ref().map(sqlx::types::Json))
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(entity)
+    }
+
+    /// Replace a credential's key, keeping the old key valid for `grace_period_secs`
+    pub async fn rotate_credential(
+        &self,
+        id: i32,
+        new_key: &str,
+        grace_period_secs: i64,
+    ) -> Result<Option<CredentialEntity>, sqlx::Error> {
+        let entity = sqlx::query_as::<_, CredentialEntity>(
+            r#"
+            UPDATE credentials
+            SET previous_key_hash = credential_key,
+                previous_key_expires_at = NOW() + make_interval(secs => $3),
+                credential_key = $2,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, credential_key, name, allowed_models, allowed_cidrs, rate_limit, is_enabled, context_trimming, pii_redaction, guardrails, expires_at, last_used_at, previous_key_hash, previous_key_expires_at, created_at, updated_at
+            "#,
+        )
+        .bind(id)
+        .bind(hash_key(new_key))
+        .bind(grace_period_secs as f64)
         .fetch_optional(&self.pool)
         .await?;
         Ok(entity)
@@ -421,6 +687,298 @@ impl Database {
             .await?;
         Ok(result.rows_affected() > 0)
     }
+
+    /// Load all enabled guardrail rules
+    pub async fn load_guardrail_rules(&self) -> Result<Vec<GuardrailRuleEntity>, sqlx::Error> {
+        sqlx::query_as::<_, GuardrailRuleEntity>(
+            r#"
+            SELECT id, name, kind, patterns, action, direction, is_enabled, created_at, updated_at
+            FROM guardrail_rules
+            WHERE is_enabled = true
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// List all guardrail rules (including disabled)
+    pub async fn list_guardrail_rules(&self) -> Result<Vec<GuardrailRuleEntity>, sqlx::Error> {
+        sqlx::query_as::<_, GuardrailRuleEntity>(
+            r#"
+            SELECT id, name, kind, patterns, action, direction, is_enabled, created_at, updated_at
+            FROM guardrail_rules
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Get guardrail rule by ID
+    pub async fn get_guardrail_rule(
+        &self,
+        id: i32,
+    ) -> Result<Option<GuardrailRuleEntity>, sqlx::Error> {
+        sqlx::query_as::<_, GuardrailRuleEntity>(
+            r#"
+            SELECT id, name, kind, patterns, action, direction, is_enabled, created_at, updated_at
+            FROM guardrail_rules
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Create a new guardrail rule
+    pub async fn create_guardrail_rule(
+        &self,
+        rule: &GuardrailRule,
+    ) -> Result<GuardrailRuleEntity, sqlx::Error> {
+        sqlx::query_as::<_, GuardrailRuleEntity>(
+            r#"
+            INSERT INTO guardrail_rules (name, kind, patterns, action, direction, is_enabled)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, name, kind, patterns, action, direction, is_enabled, created_at, updated_at
+            "#,
+        )
+        .bind(&rule.name)
+        .bind(enum_str(&rule.kind))
+        .bind(sqlx::types::Json(&rule.patterns))
+        .bind(enum_str(&rule.action))
+        .bind(enum_str(&rule.direction))
+        .bind(rule.enabled)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Replace a guardrail rule
+    pub async fn update_guardrail_rule(
+        &self,
+        id: i32,
+        rule: &GuardrailRule,
+    ) -> Result<Option<GuardrailRuleEntity>, sqlx::Error> {
+        sqlx::query_as::<_, GuardrailRuleEntity>(
+            r#"
+            UPDATE guardrail_rules
+            SET name = $2,
+                kind = $3,
+                patterns = $4,
+                action = $5,
+                direction = $6,
+                is_enabled = $7,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, name, kind, patterns, action, direction, is_enabled, created_at, updated_at
+            "#,
+        )
+        .bind(id)
+        .bind(&rule.name)
+        .bind(enum_str(&rule.kind))
+        .bind(sqlx::types::Json(&rule.patterns))
+        .bind(enum_str(&rule.action))
+        .bind(enum_str(&rule.direction))
+        .bind(rule.enabled)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Delete a guardrail rule
+    pub async fn delete_guardrail_rule(&self, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM guardrail_rules WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Names of the credentials a guardrail rule is attached to
+    pub async fn credentials_using_guardrail(
+        &self,
+        rule_name: &str,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT name FROM credentials WHERE guardrails ? $1 ORDER BY id")
+            .bind(rule_name)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// List all admin tokens
+    pub async fn list_admin_tokens(&self) -> Result<Vec<AdminTokenEntity>, sqlx::Error> {
+        sqlx::query_as::<_, AdminTokenEntity>(
+            r#"
+            SELECT id, name, token_hash, token_preview, role, expires_at, is_enabled, created_by, created_at
+            FROM admin_tokens
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Get admin token by ID
+    pub async fn get_admin_token(&self, id: i32) -> Result<Option<AdminTokenEntity>, sqlx::Error> {
+        sqlx::query_as::<_, AdminTokenEntity>(
+            r#"
+            SELECT id, name, token_hash, token_preview, role, expires_at, is_enabled, created_by, created_at
+            FROM admin_tokens
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Get admin token by token hash (for authentication)
+    pub async fn get_admin_token_by_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<AdminTokenEntity>, sqlx::Error> {
+        sqlx::query_as::<_, AdminTokenEntity>(
+            r#"
+            SELECT id, name, token_hash, token_preview, role, expires_at, is_enabled, created_by, created_at
+            FROM admin_tokens
+            WHERE token_hash = $1
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Create a new admin token
+    pub async fn create_admin_token(
+        &self,
+        token: &CreateAdminToken,
+    ) -> Result<AdminTokenEntity, sqlx::Error> {
+        sqlx::query_as::<_, AdminTokenEntity>(
+            r#"
+            INSERT INTO admin_tokens (name, token_hash, token_preview, role, expires_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, name, token_hash, token_preview, role, expires_at, is_enabled, created_by, created_at
+            "#,
+        )
+        .bind(&token.name)
+        .bind(hash_key(&token.token))
+        .bind(create_key_preview(&token.token))
+        .bind(&token.role)
+        .bind(token.expires_at)
+        .bind(&token.created_by)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Delete an admin token
+    pub async fn delete_admin_token(&self, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM admin_tokens WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record a mutating admin call
+    pub async fn insert_admin_audit_log(
+        &self,
+        entry: &NewAdminAuditLog,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO admin_audit_logs (actor, actor_role, action, resource_type, resource_id, before, after)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&entry.actor)
+        .bind(&entry.actor_role)
+        .bind(&entry.action)
+        .bind(&entry.resource_type)
+        .bind(&entry.resource_id)
+        .bind(entry.before.as_ref().map(sqlx::types::Json))
+        .bind(entry.after.as_ref().map(sqlx::types::Json))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// List admin audit records, newest first, with the total matching count
+    pub async fn list_admin_audit_logs(
+        &self,
+        filter: &AdminAuditLogFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AdminAuditLogEntity>, i64), sqlx::Error> {
+        const CONDITIONS: &str = r#"
+            WHERE ($1::VARCHAR IS NULL OR actor = $1)
+              AND ($2::VARCHAR IS NULL OR resource_type = $2)
+              AND ($3::VARCHAR IS NULL OR resource_id = $3)
+              AND ($4::VARCHAR IS NULL OR action = $4)
+              AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+              AND ($6::TIMESTAMPTZ IS NULL OR created_at <= $6)
+        "#;
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM admin_audit_logs {}",
+            CONDITIONS
+        ))
+        .bind(&filter.actor)
+        .bind(&filter.resource_type)
+        .bind(&filter.resource_id)
+        .bind(&filter.action)
+        .bind(filter.start_time)
+        .bind(filter.end_time)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let items = sqlx::query_as::<_, AdminAuditLogEntity>(&format!(
+            r#"
+            SELECT id, created_at, actor, actor_role, action, resource_type, resource_id, before, after
+            FROM admin_audit_logs
+            {}
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7 OFFSET $8
+            "#,
+            CONDITIONS
+        ))
+        .bind(&filter.actor)
+        .bind(&filter.resource_type)
+        .bind(&filter.resource_id)
+        .bind(&filter.action)
+        .bind(filter.start_time)
+        .bind(filter.end_time)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((items, total))
+    }
+}
+
+/// Replace the rows of `table` with the rows of a configuration snapshot.
+async fn restore_table(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    table: &str,
+    rows: &[serde_json::Value],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("DELETE FROM {}", table))
+        .execute(&mut **tx)
+        .await?;
+    let table_columns: Vec<String> = sqlx::query_scalar(
+        "SELECT column_name::text FROM information_schema.columns
+         WHERE table_schema = current_schema() AND table_name = $1
+         ORDER BY ordinal_position",
+    )
+    .bind(table)
+    .fetch_all(&mut **tx)
+    .await?;
+    if let Some(insert) = restore_insert_sql(table, rows, &table_columns) {
+        sqlx::query(&insert)
+            .bind(sqlx::types::Json(rows))
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
 }
 
 /// Provider entity from database
@@ -447,7 +1005,7 @@ pub struct ProviderEntity {
     pub provider_type: String,
     /// Base URL for the provider API
     pub api_base: String,
-    /// API key for authentication (stored encrypted)
+    /// API key for authentication (encrypted at rest when master keys are configured)
     #[schema(value_type = String)]
     pub api_key: String,
     /// Model name mapping (request model -> provider model or extended entry)
@@ -549,16 +1107,84 @@ pub struct CredentialEntity {
     /// List of models this credential can access (empty = all models)
     #[sqlx(json)]
     pub allowed_models: Vec<String>,
+    /// CIDR ranges requests may come from (empty = any address)
+    #[sqlx(json)]
+    pub allowed_cidrs: Vec<String>,
     /// Rate limit in requests per second (null = unlimited)
     pub rate_limit: Option<i32>,
     /// Whether this credential is enabled
     pub is_enabled: bool,
+    /// Conversation trimming strategy (null = reject over-long requests)
+    #[sqlx(json(nullable))]
+    pub context_trimming: Option<ContextTrimming>,
+    /// PII redaction settings (null = use the provider's setting)
+    #[sqlx(json(nullable))]
+    pub pii_redaction: Option<PiiRedactionConfig>,
+    /// Names of the guardrail rules applied to this credential's traffic
+    #[sqlx(json)]
+    pub guardrails: Vec<String>,
+    /// Expiry time (None = never expires)
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Last successful authentication (recorded in batches, may lag slightly)
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// Hash of the key replaced by the last rotation
+    pub previous_key_hash: Option<String>,
+    /// When the previous key stops being accepted
+    pub previous_key_expires_at: Option<DateTime<Utc>>,
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
     /// Last update timestamp
     pub updated_at: DateTime<Utc>,
 }
 
+impl CredentialEntity {
+    /// Whether the credential has expired at `now`.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether the credential expires within `window` after `now` (and has not yet).
+    pub fn expires_within(&self, now: DateTime<Utc>, window: chrono::Duration) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at > now && expires_at <= now + window)
+    }
+
+    /// Key replaced by the last rotation, while it is recorded.
+    pub fn previous_key(&self) -> Option<PreviousCredentialKey> {
+        Some(PreviousCredentialKey {
+            key_hash: self.previous_key_hash.clone()?,
+            expires_at: self.previous_key_expires_at?,
+        })
+    }
+
+    /// Parsed `allowed_cidrs`, or None when an entry fails to parse.
+    ///
+    /// The admin API rejects invalid entries, so this only happens for rows
+    /// edited directly. Callers must then disable the credential: an empty
+    /// allowlist would accept every address.
+    pub fn allowed_networks(&self) -> Option<Vec<IpNet>> {
+        self.allowed_cidrs
+            .iter()
+            .map(|cidr| parse_network(cidr))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .inspect_err(|e| {
+                tracing::error!(
+                    credential = %self.name,
+                    error = %e,
+                    "Invalid CIDR in allowlist, disabling credential"
+                )
+            })
+            .ok()
+    }
+
+    /// Whether the credential has not been used since `cutoff`.
+    ///
+    /// Credentials never used count from their creation time.
+    pub fn unused_since(&self, cutoff: DateTime<Utc>) -> bool {
+        self.last_used_at.unwrap_or(self.created_at) < cutoff
+    }
+}
+
 /// Create credential request
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[schema(example = json!({
@@ -581,6 +1207,21 @@ pub struct CreateCredential {
     /// Whether this credential is enabled (default: true)
     #[serde(default = "default_true")]
     pub is_enabled: bool,
+    /// Conversation trimming strategy (null = reject over-long requests)
+    #[serde(default)]
+    pub context_trimming: Option<ContextTrimming>,
+    /// Expiry time (null = never expires)
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// CIDR ranges requests may come from (empty = any address)
+    #[serde(default)]
+    pub allowed_cidrs: Vec<String>,
+    /// PII redaction settings (null = use the provider's setting)
+    #[serde(default)]
+    pub pii_redaction: Option<PiiRedactionConfig>,
+    /// Names of the guardrail rules applied to this credential's traffic
+    #[serde(default)]
+    pub guardrails: Vec<String>,
 }
 
 /// Update credential request
@@ -601,6 +1242,16 @@ pub struct UpdateCredential {
     pub rate_limit: Option<i32>,
     /// Whether this credential is enabled
     pub is_enabled: Option<bool>,
+    /// Conversation trimming strategy
+    pub context_trimming: Option<ContextTrimming>,
+    /// Expiry time
+    pub expires_at: Option<DateTime<Utc>>,
+    /// CIDR ranges requests may come from (empty = any address)
+    pub allowed_cidrs: Option<Vec<String>>,
+    /// PII redaction settings
+    pub pii_redaction: Option<PiiRedactionConfig>,
+    /// Names of the guardrail rules applied to this credential's traffic
+    pub guardrails: Option<Vec<String>>,
 }
 
 fn default_true() -> bool {
@@ -611,6 +1262,169 @@ fn default_weight() -> i32 {
     1
 }
 
+/// Configuration snapshot recorded for a version
+#[derive(Debug, Clone, FromRow)]
+pub struct ConfigHistoryEntity {
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    /// What recorded the version (table name, "baseline" or "rollback:<version>")
+    pub change_source: String,
+    /// Provider rows; API keys are null unless encrypted at rest
+    pub providers: sqlx::types::Json<Vec<serde_json::Value>>,
+    /// Credential rows
+    pub credentials: sqlx::types::Json<Vec<serde_json::Value>>,
+    /// Guardrail rule rows (empty for versions recorded before rules existed)
+    pub guardrail_rules: sqlx::types::Json<Vec<serde_json::Value>>,
+}
+
+/// Summary of a recorded configuration version
+#[derive(Debug, Clone, FromRow)]
+pub struct ConfigVersionSummary {
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub change_source: String,
+    pub provider_count: i32,
+    pub credential_count: i32,
+}
+
+/// Guardrail rule entity from database
+#[derive(Debug, Clone, FromRow)]
+pub struct GuardrailRuleEntity {
+    /// Auto-increment rule ID
+    pub id: i32,
+    /// Unique name, referenced from credentials
+    pub name: String,
+    /// "secrets", "deny_phrases" or "regex"
+    pub kind: String,
+    /// Deny phrases, regexes, or extra secret formats depending on `kind`
+    #[sqlx(json)]
+    pub patterns: Vec<String>,
+    /// "block", "redact" or "log"
+    pub action: String,
+    /// "input", "output" or "both"
+    pub direction: String,
+    /// Whether this rule is enabled
+    pub is_enabled: bool,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+    /// Last update timestamp
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GuardrailRuleEntity {
+    /// The rule as configuration, or `None` if a stored value is unknown.
+    pub fn to_rule(&self) -> Option<GuardrailRule> {
+        fn parse<T: serde::de::DeserializeOwned>(value: &str) -> Option<T> {
+            serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+        }
+        Some(GuardrailRule {
+            name: self.name.clone(),
+            kind: parse(&self.kind)?,
+            patterns: self.patterns.clone(),
+            action: parse(&self.action)?,
+            direction: parse(&self.direction)?,
+            enabled: self.is_enabled,
+        })
+    }
+}
+
+/// Serialized name of a unit enum variant (e.g. "deny_phrases").
+fn enum_str<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Admin token entity from database
+#[derive(Debug, Clone, FromRow)]
+pub struct AdminTokenEntity {
+    /// Auto-increment admin token ID
+    pub id: i32,
+    /// Human-readable name, recorded as the actor in audit records
+    pub name: String,
+    /// SHA-256 hash of the token
+    pub token_hash: String,
+    /// Masked preview of the token
+    pub token_preview: String,
+    /// Role granted by the token ("viewer", "operator" or "admin")
+    pub role: String,
+    /// Expiry time (None = never expires)
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Whether this token is enabled
+    pub is_enabled: bool,
+    /// Admin who created the token
+    pub created_by: Option<String>,
+    /// Creation timestamp
+    pub created_at: DateTime<Utc>,
+}
+
+impl AdminTokenEntity {
+    /// Whether the token can authenticate at `now`.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.is_enabled && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// Create admin token request
+#[derive(Debug, Clone)]
+pub struct CreateAdminToken {
+    /// Human-readable name
+    pub name: String,
+    /// The token value (will be hashed for storage)
+    pub token: String,
+    /// Role granted by the token
+    pub role: String,
+    /// Expiry time (None = never expires)
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Admin creating the token
+    pub created_by: Option<String>,
+}
+
+/// Admin audit record from database
+#[derive(Debug, Clone, FromRow)]
+pub struct AdminAuditLogEntity {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub actor: String,
+    pub actor_role: String,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    pub before: Option<sqlx::types::Json<serde_json::Value>>,
+    pub after: Option<sqlx::types::Json<serde_json::Value>>,
+}
+
+/// Admin audit record to insert
+#[derive(Debug, Clone)]
+pub struct NewAdminAuditLog {
+    /// Name of the admin token (or "bootstrap" for ADMIN_KEY)
+    pub actor: String,
+    /// Role the actor acted with
+    pub actor_role: String,
+    /// Action performed (e.g. "create", "update", "delete")
+    pub action: String,
+    /// Kind of resource changed (e.g. "provider", "credential")
+    pub resource_type: String,
+    /// ID of the resource changed, if any
+    pub resource_id: Option<String>,
+    /// Resource state before the call
+    pub before: Option<serde_json::Value>,
+    /// Resource state after the call
+    pub after: Option<serde_json::Value>,
+}
+
+/// Filter for listing admin audit records
+#[derive(Debug, Clone, Default)]
+pub struct AdminAuditLogFilter {
+    pub actor: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    pub action: Option<String>,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+}
+
 /// Hash a key for secure storage using SHA-256
 pub fn hash_key(key: &str) -> String {
     let mut hasher = Sha256::new();
@@ -633,6 +1447,7 @@ pub fn create_key_preview(key: &str) -> String {
 pub struct RuntimeConfig {
     pub providers: Vec<ProviderEntity>,
     pub credentials: Vec<CredentialEntity>,
+    pub guardrail_rules: Vec<GuardrailRuleEntity>,
     pub version: i64,
     pub loaded_at: DateTime<Utc>,
 }
@@ -641,11 +1456,13 @@ impl RuntimeConfig {
     pub async fn load_from_db(db: &Database) -> Result<Self, sqlx::Error> {
         let providers = db.load_providers().await?;
         let credentials = db.load_credentials().await?;
+        let guardrail_rules = db.load_guardrail_rules().await?;
         let version = db.get_config_version().await?;
 
         Ok(Self {
             providers,
             credentials,
+            guardrail_rules,
             version,
             loaded_at: Utc::now(),
         })
@@ -775,4 +1592,32 @@ mod tests {
         assert_eq!(encode_password("with space"), "with%20space");
         assert_eq!(encode_password("user:pass"), "user%3Apass");
     }
+
+    #[test]
+    fn test_allowed_networks_fails_closed() {
+        let now = Utc::now();
+        let mut credential = CredentialEntity {
+            id: 1,
+            credential_key: hash_key("sk-test"),
+            name: "test".to_string(),
+            allowed_models: vec![],
+            allowed_cidrs: vec!["10.0.0.0/8".to_string(), "192.168.1.7".to_string()],
+            rate_limit: None,
+            is_enabled: true,
+            context_trimming: None,
+            pii_redaction: None,
+            guardrails: vec![],
+            expires_at: None,
+            last_used_at: None,
+            previous_key_hash: None,
+            previous_key_expires_at: None,
+            created_at: now,
+            updated_at: now,
+        };
+        assert_eq!(credential.allowed_networks().map(|n| n.len()), Some(2));
+
+        // One bad entry must not leave an empty, allow-all list behind
+        credential.allowed_cidrs = vec!["10.0.0.0/33".to_string()];
+        assert_eq!(credential.allowed_networks(), None);
+    }
 }
@@ -12,6 +12,9 @@ pub const ERROR_TYPE_STREAM: &str = "stream_error";
 
 pub const ERROR_CODE_PROVIDER: &str = "provider_error";
 pub const ERROR_CODE_TTFT_TIMEOUT: &str = "ttft_timeout";
+pub const ERROR_CODE_STREAM_IDLE_TIMEOUT: &str = "stream_idle_timeout";
+pub const ERROR_CODE_CONTEXT_LENGTH_EXCEEDED: &str = "context_length_exceeded";
+pub const ERROR_CODE_GUARDRAIL_VIOLATION: &str = "guardrail_violation";
 
 pub const ERROR_CATEGORY_PROVIDER_4XX: &str = "provider_4xx";
 pub const ERROR_CATEGORY_PROVIDER_5XX: &str = "provider_5xx";
@@ -20,6 +23,8 @@ pub const ERROR_CATEGORY_NETWORK_ERROR: &str = "network_error";
 pub const ERROR_CATEGORY_CONNECT_ERROR: &str = "connect_error";
 pub const ERROR_CATEGORY_STREAM_ERROR: &str = "stream_error";
 pub const ERROR_CATEGORY_INTERNAL_ERROR: &str = "internal_error";
+pub const ERROR_CATEGORY_NETWORK_RESTRICTION: &str = "network_restriction";
+pub const ERROR_CATEGORY_GUARDRAIL_VIOLATION: &str = "guardrail_violation";
 
 pub const PROVIDER_EJECTION_REASON_RATE_LIMIT: &str = "429";
 pub const PROVIDER_EJECTION_REASON_SERVER_5XX: &str = "5xx";
@@ -34,6 +39,10 @@ pub enum ErrorCategoryCode {
     ConnectError,
     StreamError,
     InternalError,
+    /// Request from an address outside the credential's allowlist
+    NetworkRestriction,
+    /// Request or response content matched a guardrail rule
+    GuardrailViolation,
 }
 
 impl ErrorCategoryCode {
@@ -46,6 +55,8 @@ impl ErrorCategoryCode {
             Self::ConnectError => ERROR_CATEGORY_CONNECT_ERROR,
             Self::StreamError => ERROR_CATEGORY_STREAM_ERROR,
             Self::InternalError => ERROR_CATEGORY_INTERNAL_ERROR,
+            Self::NetworkRestriction => ERROR_CATEGORY_NETWORK_RESTRICTION,
+            Self::GuardrailViolation => ERROR_CATEGORY_GUARDRAIL_VIOLATION,
         }
     }
 }
@@ -92,6 +103,14 @@ mod tests {
         assert_eq!(ErrorCategoryCode::ConnectError.as_str(), "connect_error");
         assert_eq!(ErrorCategoryCode::StreamError.as_str(), "stream_error");
         assert_eq!(ErrorCategoryCode::InternalError.as_str(), "internal_error");
+        assert_eq!(
+            ErrorCategoryCode::NetworkRestriction.as_str(),
+            "network_restriction"
+        );
+        assert_eq!(
+            ErrorCategoryCode::GuardrailViolation.as_str(),
+            "guardrail_violation"
+        );
     }
 
     #[test]
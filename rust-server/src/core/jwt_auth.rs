@@ -0,0 +1,839 @@
+//! JWT bearer authentication for client requests.
+//!
+//! Services holding short-lived JWTs from an identity provider can call the
+//! proxy without a static credential key. Tokens are verified against a JWKS
+//! fetched from `JWT_JWKS_URL` or read from `JWT_JWKS_FILE`; the key set is
+//! cached and refreshed every `JWT_JWKS_CACHE_SECS`, and early when a token
+//! names a key ID that is not cached yet.
+//!
+//! A verified token maps to a credential profile: an existing credential,
+//! named by `JWT_CLAIM_PROFILES` rules or `JWT_DEFAULT_PROFILE`, whose
+//! allowed models and rate limit apply to the request. The identity from
+//! `JWT_USER_CLAIM` (default `sub`) replaces the credential name, so request
+//! logs and Langfuse traces record the user rather than the profile.
+//!
+//! Supported algorithms: RS256/384/512, PS256/384/512, ES256, ES384 and EdDSA
+//! (Ed25519). Tokens must carry `exp`.
+
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::Notify;
+
+/// Env var with the URL of the JWKS.
+pub const JWKS_URL_ENV: &str = "JWT_JWKS_URL";
+
+/// Env var naming a local JWKS file, used when `JWT_JWKS_URL` is unset.
+pub const JWKS_FILE_ENV: &str = "JWT_JWKS_FILE";
+
+const DEFAULT_CACHE_SECS: u64 = 300;
+const DEFAULT_LEEWAY_SECS: i64 = 60;
+const DEFAULT_USER_CLAIM: &str = "sub";
+
+/// Shortest time between two JWKS loads triggered by unknown key IDs.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors of JWT configuration and verification.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum JwtError {
+    #[error("invalid JWT configuration: {0}")]
+    InvalidConfig(String),
+    #[error("cannot load JWKS: {0}")]
+    JwksLoad(String),
+    #[error("malformed JWT")]
+    Malformed,
+    #[error("unsupported JWT algorithm '{0}'")]
+    UnsupportedAlgorithm(String),
+    #[error("no JWKS key matches the JWT")]
+    UnknownKey,
+    #[error("invalid JWT signature")]
+    InvalidSignature,
+    #[error("JWT has expired")]
+    Expired,
+    #[error("JWT is not valid yet")]
+    NotYetValid,
+    #[error("JWT issuer is not accepted")]
+    InvalidIssuer,
+    #[error("JWT audience is not accepted")]
+    InvalidAudience,
+    #[error("JWT is missing the '{0}' claim")]
+    MissingClaim(String),
+    #[error("no credential profile matches the JWT claims")]
+    NoProfile,
+}
+
+/// Where the JWKS is loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwksSource {
+    Url(String),
+    File(String),
+}
+
+/// Maps tokens whose `claim` holds `value` to the credential named `profile`.
+///
+/// Array claims such as `groups` match when any element equals `value`;
+/// the value `*` matches any token carrying the claim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimProfileRule {
+    pub claim: String,
+    pub value: String,
+    pub profile: String,
+}
+
+impl ClaimProfileRule {
+    fn matches(&self, claims: &serde_json::Map<String, Value>) -> bool {
+        let Some(claim) = claims.get(&self.claim) else {
+            return false;
+        };
+        if self.value == "*" {
+            return !claim.is_null();
+        }
+        match claim {
+            Value::Array(items) => items.iter().any(|v| claim_str(v) == Some(&self.value)),
+            other => claim_str(other) == Some(&self.value),
+        }
+    }
+}
+
+fn claim_str(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// JWT authentication settings.
+#[derive(Debug, Clone)]
+pub struct JwtAuthConfig {
+    pub source: JwksSource,
+    /// Required `iss` value (None = any issuer)
+    pub issuer: Option<String>,
+    /// Accepted `aud` values; a token must name at least one (empty = any audience)
+    pub audiences: Vec<String>,
+    /// Claim holding the user identity
+    pub user_claim: String,
+    /// Rules mapping claims to credential profiles, first match wins
+    pub profile_rules: Vec<ClaimProfileRule>,
+    /// Profile for tokens no rule matches (None = reject them)
+    pub default_profile: Option<String>,
+    /// How long a loaded JWKS is used before it is loaded again
+    pub cache_ttl: Duration,
+    /// Clock skew tolerated for `exp` and `nbf`, in seconds
+    pub leeway_secs: i64,
+}
+
+impl JwtAuthConfig {
+    /// Load the settings from the environment; None when no JWKS is configured.
+    pub fn from_env() -> Result<Option<Self>, JwtError> {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        let source = match (env(JWKS_URL_ENV), env(JWKS_FILE_ENV)) {
+            (Some(url), _) => JwksSource::Url(url),
+            (None, Some(path)) => JwksSource::File(path),
+            (None, None) => return Ok(None),
+        };
+
+        let cache_secs = match env("JWT_JWKS_CACHE_SECS") {
+            Some(v) => v.parse().map_err(|_| {
+                JwtError::InvalidConfig(format!("JWT_JWKS_CACHE_SECS '{}' is not a number", v))
+            })?,
+            None => DEFAULT_CACHE_SECS,
+        };
+        let leeway_secs = match env("JWT_LEEWAY_SECS") {
+            Some(v) => v.parse().map_err(|_| {
+                JwtError::InvalidConfig(format!("JWT_LEEWAY_SECS '{}' is not a number", v))
+            })?,
+            None => DEFAULT_LEEWAY_SECS,
+        };
+        let profile_rules = match env("JWT_CLAIM_PROFILES") {
+            Some(v) => parse_profile_rules(&v)?,
+            None => Vec::new(),
+        };
+        let default_profile = env("JWT_DEFAULT_PROFILE");
+        if profile_rules.is_empty() && default_profile.is_none() {
+            return Err(JwtError::InvalidConfig(
+                "JWT_CLAIM_PROFILES or JWT_DEFAULT_PROFILE must be set".to_string(),
+            ));
+        }
+
+        Ok(Some(Self {
+            source,
+            issuer: env("JWT_ISSUER"),
+            audiences: env("JWT_AUDIENCE")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            user_claim: env("JWT_USER_CLAIM").unwrap_or_else(|| DEFAULT_USER_CLAIM.to_string()),
+            profile_rules,
+            default_profile,
+            cache_ttl: Duration::from_secs(cache_secs.max(1)),
+            leeway_secs: leeway_secs.max(0),
+        }))
+    }
+}
+
+/// Parse `JWT_CLAIM_PROFILES`: comma-separated `<claim>:<value>=<profile>`.
+///
+/// The claim ends at the first `:` and the profile starts after the last
+/// `=`, so values may contain `:` (e.g. `system:serviceaccount:ci:deployer`).
+pub fn parse_profile_rules(spec: &str) -> Result<Vec<ClaimProfileRule>, JwtError> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let invalid = || {
+                JwtError::InvalidConfig(format!(
+                    "JWT_CLAIM_PROFILES entry '{}' is not <claim>:<value>=<profile>",
+                    entry
+                ))
+            };
+            let (claim, rest) = entry.split_once(':').ok_or_else(invalid)?;
+            let (value, profile) = rest.rsplit_once('=').ok_or_else(invalid)?;
+            let (claim, value, profile) = (claim.trim(), value.trim(), profile.trim());
+            if claim.is_empty() || value.is_empty() || profile.is_empty() {
+                return Err(invalid());
+            }
+            Ok(ClaimProfileRule {
+                claim: claim.to_string(),
+                value: value.to_string(),
+                profile: profile.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// A caller authenticated by JWT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwtIdentity {
+    /// Value of the user claim
+    pub user: String,
+    /// Name of the credential supplying allowed models and rate limits
+    pub profile: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EcCurve {
+    P256,
+    P384,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum KeyMaterial {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    Ec { curve: EcCurve, point: Vec<u8> },
+    Ed25519(Vec<u8>),
+}
+
+/// A signature verification key from the JWKS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VerificationKey {
+    kid: Option<String>,
+    alg: Option<String>,
+    material: KeyMaterial,
+}
+
+impl VerificationKey {
+    fn verify(&self, alg: &str, message: &[u8], sig: &[u8]) -> Result<(), JwtError> {
+        if self.alg.as_deref().is_some_and(|a| a != alg) {
+            return Err(JwtError::UnknownKey);
+        }
+        let verified = match (&self.material, alg) {
+            (KeyMaterial::Rsa { n, e }, _) => {
+                let params = match alg {
+                    "RS256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+                    "RS384" => &signature::RSA_PKCS1_2048_8192_SHA384,
+                    "RS512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+                    "PS256" => &signature::RSA_PSS_2048_8192_SHA256,
+                    "PS384" => &signature::RSA_PSS_2048_8192_SHA384,
+                    "PS512" => &signature::RSA_PSS_2048_8192_SHA512,
+                    _ => return Err(JwtError::UnknownKey),
+                };
+                RsaPublicKeyComponents { n, e }
+                    .verify(params, message, sig)
+                    .is_ok()
+            }
+            (KeyMaterial::Ec { curve, point }, _) => {
+                let params = match (curve, alg) {
+                    (EcCurve::P256, "ES256") => &signature::ECDSA_P256_SHA256_FIXED,
+                    (EcCurve::P384, "ES384") => &signature::ECDSA_P384_SHA384_FIXED,
+                    _ => return Err(JwtError::UnknownKey),
+                };
+                UnparsedPublicKey::new(params, point)
+                    .verify(message, sig)
+                    .is_ok()
+            }
+            (KeyMaterial::Ed25519(x), "EdDSA") => UnparsedPublicKey::new(&signature::ED25519, x)
+                .verify(message, sig)
+                .is_ok(),
+            _ => return Err(JwtError::UnknownKey),
+        };
+        if verified {
+            Ok(())
+        } else {
+            Err(JwtError::InvalidSignature)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default)]
+    alg: Option<String>,
+    #[serde(default, rename = "use")]
+    key_use: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Value>,
+}
+
+fn b64(value: Option<&String>) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(value?.trim_end_matches('=')).ok()
+}
+
+impl Jwk {
+    fn into_key(self) -> Option<VerificationKey> {
+        if self.key_use.as_deref().is_some_and(|u| u != "sig") {
+            return None;
+        }
+        let material = match (self.kty.as_str(), self.crv.as_deref()) {
+            ("RSA", _) => KeyMaterial::Rsa {
+                n: b64(self.n.as_ref())?,
+                e: b64(self.e.as_ref())?,
+            },
+            ("EC", Some(crv @ ("P-256" | "P-384"))) => {
+                let mut point = vec![0x04];
+                point.extend(b64(self.x.as_ref())?);
+                point.extend(b64(self.y.as_ref())?);
+                KeyMaterial::Ec {
+                    curve: if crv == "P-256" {
+                        EcCurve::P256
+                    } else {
+                        EcCurve::P384
+                    },
+                    point,
+                }
+            }
+            ("OKP", Some("Ed25519")) => KeyMaterial::Ed25519(b64(self.x.as_ref())?),
+            _ => return None,
+        };
+        Some(VerificationKey {
+            kid: self.kid,
+            alg: self.alg,
+            material,
+        })
+    }
+}
+
+/// Parse a JWKS document, skipping keys that are not usable for signatures.
+fn parse_jwks(document: &str) -> Result<Vec<VerificationKey>, JwtError> {
+    let set: JwkSet =
+        serde_json::from_str(document).map_err(|e| JwtError::JwksLoad(e.to_string()))?;
+    let keys: Vec<_> = set
+        .keys
+        .into_iter()
+        .filter_map(|k| serde_json::from_value::<Jwk>(k).ok()?.into_key())
+        .collect();
+    if keys.is_empty() {
+        return Err(JwtError::JwksLoad(
+            "JWKS contains no supported signing keys".to_string(),
+        ));
+    }
+    Ok(keys)
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+const SUPPORTED_ALGORITHMS: &[&str] = &[
+    "RS256", "RS384", "RS512", "PS256", "PS384", "PS512", "ES256", "ES384", "EdDSA",
+];
+
+/// Verifies JWTs against a cached JWKS and maps them to credential profiles.
+pub struct JwtAuthenticator {
+    config: JwtAuthConfig,
+    keys: ArcSwap<Vec<VerificationKey>>,
+    refresh: Notify,
+}
+
+impl std::fmt::Debug for JwtAuthenticator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtAuthenticator")
+            .field("source", &self.config.source)
+            .field("keys", &self.keys.load().len())
+            .finish()
+    }
+}
+
+impl JwtAuthenticator {
+    /// Create an authenticator with no keys loaded yet.
+    pub fn new(config: JwtAuthConfig) -> Self {
+        Self {
+            config,
+            keys: ArcSwap::from_pointee(Vec::new()),
+            refresh: Notify::new(),
+        }
+    }
+
+    pub fn config(&self) -> &JwtAuthConfig {
+        &self.config
+    }
+
+    /// Load the JWKS from its source and replace the cached keys.
+    pub async fn load_keys(&self, http_client: &reqwest::Client) -> Result<usize, JwtError> {
+        let document = match &self.config.source {
+            JwksSource::Url(url) => {
+                let response = http_client
+                    .get(url)
+                    .timeout(FETCH_TIMEOUT)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| JwtError::JwksLoad(e.to_string()))?;
+                response
+                    .text()
+                    .await
+                    .map_err(|e| JwtError::JwksLoad(e.to_string()))?
+            }
+            JwksSource::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| JwtError::JwksLoad(format!("cannot read {}: {}", path, e)))?,
+        };
+        self.set_keys_from_jwks(&document)
+    }
+
+    /// Replace the cached keys with those of a JWKS document.
+    pub fn set_keys_from_jwks(&self, document: &str) -> Result<usize, JwtError> {
+        let keys = parse_jwks(document)?;
+        let count = keys.len();
+        self.keys.store(Arc::new(keys));
+        Ok(count)
+    }
+
+    /// Verify `token` and resolve the caller's identity and profile.
+    pub fn authenticate(&self, token: &str, now: i64) -> Result<JwtIdentity, JwtError> {
+        let claims = self.verify(token, now)?;
+        self.identity(&claims)
+    }
+
+    fn verify(&self, token: &str, now: i64) -> Result<serde_json::Map<String, Value>, JwtError> {
+        let (signing_input, sig) = token.rsplit_once('.').ok_or(JwtError::Malformed)?;
+        let (header, payload) = signing_input
+            .split_once('.')
+            .filter(|(_, payload)| !payload.contains('.'))
+            .ok_or(JwtError::Malformed)?;
+        let decode = |part: &str| {
+            URL_SAFE_NO_PAD
+                .decode(part)
+                .map_err(|_| JwtError::Malformed)
+        };
+        let header: JwtHeader =
+            serde_json::from_slice(&decode(header)?).map_err(|_| JwtError::Malformed)?;
+        let claims: serde_json::Map<String, Value> =
+            serde_json::from_slice(&decode(payload)?).map_err(|_| JwtError::Malformed)?;
+        let sig = decode(sig)?;
+
+        if !SUPPORTED_ALGORITHMS.contains(&header.alg.as_str()) {
+            return Err(JwtError::UnsupportedAlgorithm(header.alg));
+        }
+
+        self.verify_signature(&header, signing_input.as_bytes(), &sig)?;
+        self.validate_claims(&claims, now)?;
+        Ok(claims)
+    }
+
+    fn verify_signature(
+        &self,
+        header: &JwtHeader,
+        message: &[u8],
+        sig: &[u8],
+    ) -> Result<(), JwtError> {
+        let keys = self.keys.load();
+        let candidates = keys
+            .iter()
+            .filter(|k| header.kid.is_none() || k.kid == header.kid);
+
+        let mut result = Err(JwtError::UnknownKey);
+        for key in candidates {
+            match key.verify(&header.alg, message, sig) {
+                Ok(()) => return Ok(()),
+                Err(JwtError::UnknownKey) => {}
+                Err(e) => result = Err(e),
+            }
+        }
+        if result == Err(JwtError::UnknownKey) {
+            // The provider may have rotated its keys since the last load
+            self.refresh.notify_one();
+        }
+        result
+    }
+
+    fn validate_claims(
+        &self,
+        claims: &serde_json::Map<String, Value>,
+        now: i64,
+    ) -> Result<(), JwtError> {
+        let leeway = self.config.leeway_secs;
+        let exp = claims
+            .get("exp")
+            .and_then(Value::as_i64)
+            .ok_or_else(|| JwtError::MissingClaim("exp".to_string()))?;
+        if exp + leeway <= now {
+            return Err(JwtError::Expired);
+        }
+        if claims
+            .get("nbf")
+            .and_then(Value::as_i64)
+            .is_some_and(|nbf| nbf - leeway > now)
+        {
+            return Err(JwtError::NotYetValid);
+        }
+
+        if let Some(issuer) = &self.config.issuer {
+            if claims.get("iss").and_then(Value::as_str) != Some(issuer.as_str()) {
+                return Err(JwtError::InvalidIssuer);
+            }
+        }
+
+        if !self.config.audiences.is_empty() {
+            let accepted = |aud: &Value| {
+                aud.as_str()
+                    .is_some_and(|a| self.config.audiences.iter().any(|x| x == a))
+            };
+            let ok = match claims.get("aud") {
+                Some(Value::Array(auds)) => auds.iter().any(accepted),
+                Some(aud) => accepted(aud),
+                None => false,
+            };
+            if !ok {
+                return Err(JwtError::InvalidAudience);
+            }
+        }
+        Ok(())
+    }
+
+    fn identity(&self, claims: &serde_json::Map<String, Value>) -> Result<JwtIdentity, JwtError> {
+        let user = claims
+            .get(&self.config.user_claim)
+            .and_then(|v| match v {
+                Value::String(s) if !s.is_empty() => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .ok_or_else(|| JwtError::MissingClaim(self.config.user_claim.clone()))?;
+
+        let profile = self
+            .config
+            .profile_rules
+            .iter()
+            .find(|rule| rule.matches(claims))
+            .map(|rule| rule.profile.clone())
+            .or_else(|| self.config.default_profile.clone())
+            .ok_or(JwtError::NoProfile)?;
+
+        Ok(JwtIdentity { user, profile })
+    }
+
+    /// Reload the JWKS every cache TTL, or sooner when a token names an
+    /// unknown key (at most once per `MIN_REFRESH_INTERVAL`).
+    async fn refresh_task(self: Arc<Self>, http_client: reqwest::Client) {
+        let mut last_load = Instant::now();
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(self.config.cache_ttl) => {}
+                _ = self.refresh.notified() => {
+                    let since = last_load.elapsed();
+                    if since < MIN_REFRESH_INTERVAL {
+                        tokio::time::sleep(MIN_REFRESH_INTERVAL - since).await;
+                    }
+                }
+            }
+            last_load = Instant::now();
+            match self.load_keys(&http_client).await {
+                Ok(count) => tracing::debug!(keys = count, "JWKS refreshed"),
+                Err(e) => tracing::warn!(error = %e, "Failed to refresh JWKS, keeping cached keys"),
+            }
+        }
+    }
+}
+
+static JWT_AUTHENTICATOR: OnceLock<Arc<JwtAuthenticator>> = OnceLock::new();
+
+/// Enable JWT authentication when a JWKS is configured.
+///
+/// A JWKS that cannot be loaded at startup is logged and retried in the
+/// background, so the proxy still starts while the identity provider is down.
+pub async fn init_jwt_auth(http_client: reqwest::Client) -> Result<(), JwtError> {
+    let Some(config) = JwtAuthConfig::from_env()? else {
+        return Ok(());
+    };
+    let authenticator = Arc::new(JwtAuthenticator::new(config));
+    match authenticator.load_keys(&http_client).await {
+        Ok(count) => tracing::info!(
+            source = ?authenticator.config.source,
+            keys = count,
+            "JWT authentication enabled"
+        ),
+        Err(e) => tracing::error!(
+            source = ?authenticator.config.source,
+            error = %e,
+            "JWT authentication enabled but the JWKS could not be loaded, retrying in background"
+        ),
+    }
+    tokio::spawn(authenticator.clone().refresh_task(http_client));
+    let _ = JWT_AUTHENTICATOR.set(authenticator);
+    Ok(())
+}
+
+/// The JWT authenticator, if JWT authentication is enabled.
+pub fn jwt_authenticator() -> Option<&'static JwtAuthenticator> {
+    JWT_AUTHENTICATOR.get().map(Arc::as_ref)
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    /// An ES256 signing key and the matching authenticator.
+    pub struct TestIssuer {
+        key_pair: EcdsaKeyPair,
+        rng: SystemRandom,
+        pub kid: String,
+    }
+
+    impl TestIssuer {
+        pub fn new(kid: &str) -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+            let key_pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            Self {
+                key_pair,
+                rng,
+                kid: kid.to_string(),
+            }
+        }
+
+        pub fn jwks(&self) -> String {
+            let point = self.key_pair.public_key().as_ref();
+            serde_json::json!({
+                "keys": [{
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "kid": self.kid,
+                    "use": "sig",
+                    "alg": "ES256",
+                    "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+                }]
+            })
+            .to_string()
+        }
+
+        pub fn sign(&self, claims: &Value) -> String {
+            let header = serde_json::json!({"alg": "ES256", "typ": "JWT", "kid": self.kid});
+            let signing_input = format!(
+                "{}.{}",
+                URL_SAFE_NO_PAD.encode(header.to_string()),
+                URL_SAFE_NO_PAD.encode(claims.to_string())
+            );
+            let sig = self
+                .key_pair
+                .sign(&self.rng, signing_input.as_bytes())
+                .unwrap();
+            format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(sig.as_ref()))
+        }
+    }
+
+    pub fn config() -> JwtAuthConfig {
+        JwtAuthConfig {
+            source: JwksSource::File("unused".to_string()),
+            issuer: Some("https://idp.example.com".to_string()),
+            audiences: vec!["llm-proxy".to_string()],
+            user_claim: DEFAULT_USER_CLAIM.to_string(),
+            profile_rules: parse_profile_rules("groups:ml-team=ml-research").unwrap(),
+            default_profile: None,
+            cache_ttl: Duration::from_secs(DEFAULT_CACHE_SECS),
+            leeway_secs: DEFAULT_LEEWAY_SECS,
+        }
+    }
+
+    pub fn authenticator(issuer: &TestIssuer, config: JwtAuthConfig) -> JwtAuthenticator {
+        let authenticator = JwtAuthenticator::new(config);
+        authenticator.set_keys_from_jwks(&issuer.jwks()).unwrap();
+        authenticator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::*;
+    use super::*;
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn claims() -> Value {
+        json!({
+            "iss": "https://idp.example.com",
+            "aud": ["llm-proxy", "other"],
+            "sub": "svc-batch",
+            "groups": ["ml-team", "eng"],
+            "exp": NOW + 300,
+        })
+    }
+
+    #[test]
+    fn test_valid_token_maps_to_profile() {
+        let issuer = TestIssuer::new("k1");
+        let auth = authenticator(&issuer, config());
+        let identity = auth.authenticate(&issuer.sign(&claims()), NOW).unwrap();
+        assert_eq!(
+            identity,
+            JwtIdentity {
+                user: "svc-batch".to_string(),
+                profile: "ml-research".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_claim_checks() {
+        let issuer = TestIssuer::new("k1");
+        let auth = authenticator(&issuer, config());
+        let with = |field: &str, value: Value| {
+            let mut c = claims();
+            c[field] = value;
+            auth.authenticate(&issuer.sign(&c), NOW)
+        };
+
+        assert_eq!(with("exp", json!(NOW - 120)), Err(JwtError::Expired));
+        assert!(with("exp", json!(NOW - 30)).is_ok(), "within leeway");
+        assert_eq!(with("nbf", json!(NOW + 600)), Err(JwtError::NotYetValid));
+        assert_eq!(
+            with("iss", json!("https://evil.example.com")),
+            Err(JwtError::InvalidIssuer)
+        );
+        assert_eq!(with("aud", json!("other")), Err(JwtError::InvalidAudience));
+        assert_eq!(with("groups", json!(["eng"])), Err(JwtError::NoProfile));
+        assert_eq!(
+            with("sub", Value::Null),
+            Err(JwtError::MissingClaim("sub".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_default_profile_applies_when_no_rule_matches() {
+        let issuer = TestIssuer::new("k1");
+        let mut config = config();
+        config.default_profile = Some("default".to_string());
+        let auth = authenticator(&issuer, config);
+        let mut c = claims();
+        c["groups"] = json!([]);
+        assert_eq!(
+            auth.authenticate(&issuer.sign(&c), NOW).unwrap().profile,
+            "default"
+        );
+    }
+
+    #[test]
+    fn test_signature_and_key_checks() {
+        let issuer = TestIssuer::new("k1");
+        let auth = authenticator(&issuer, config());
+        let token = issuer.sign(&claims());
+
+        // Payload swapped under the original signature
+        let mut forged = claims();
+        forged["sub"] = json!("admin");
+        let forged_token = issuer.sign(&forged);
+        let (head, _) = forged_token.rsplit_once('.').unwrap();
+        let (_, sig) = token.rsplit_once('.').unwrap();
+        assert_eq!(
+            auth.authenticate(&format!("{}.{}", head, sig), NOW),
+            Err(JwtError::InvalidSignature)
+        );
+
+        // Token signed by a key that is not in the JWKS
+        let stranger = TestIssuer::new("k2");
+        assert_eq!(
+            auth.authenticate(&stranger.sign(&claims()), NOW),
+            Err(JwtError::UnknownKey)
+        );
+
+        // Unsigned tokens are never accepted
+        let none = format!(
+            "{}.{}.",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+            URL_SAFE_NO_PAD.encode(claims().to_string())
+        );
+        assert_eq!(
+            auth.authenticate(&none, NOW),
+            Err(JwtError::UnsupportedAlgorithm("none".to_string()))
+        );
+        assert_eq!(
+            auth.authenticate("sk-static-key", NOW),
+            Err(JwtError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_parse_profile_rules() {
+        let rules =
+            parse_profile_rules("groups:ml-team=ml, sub:system:serviceaccount:ci:deployer=ci")
+                .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].claim, "sub");
+        assert_eq!(rules[1].value, "system:serviceaccount:ci:deployer");
+        assert_eq!(rules[1].profile, "ci");
+        assert!(parse_profile_rules("groups=ml").is_err());
+        assert!(parse_profile_rules("groups:=ml").is_err());
+    }
+
+    #[test]
+    fn test_parse_jwks_skips_unusable_keys() {
+        let issuer = TestIssuer::new("k1");
+        let mut jwks: Value = serde_json::from_str(&issuer.jwks()).unwrap();
+        jwks["keys"].as_array_mut().unwrap().extend([
+            json!({"kty": "oct", "k": "c2VjcmV0"}),
+            json!({"kty": "RSA", "use": "enc", "n": "AQAB", "e": "AQAB"}),
+        ]);
+        let keys = parse_jwks(&jwks.to_string()).unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].kid.as_deref(), Some("k1"));
+        assert!(parse_jwks(r#"{"keys": []}"#).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+//! Envelope encryption of provider API keys stored in the database.
+//!
+//! Each API key is sealed with its own random data key (AES-256-GCM), and the
+//! data key is sealed with a master key. Stored values look like
+//!
+//! ```text
+//! enc:v1:<master key id>:<base64 wrapped data key>:<base64 sealed api key>
+//! ```
+//!
+//! Master keys come from `PROVIDER_KEY_ENCRYPTION_KEYS`, a comma-separated list
+//! of `<key id>:<base64 32-byte key>`, or from the file named by
+//! `PROVIDER_KEY_ENCRYPTION_KEY_FILE` with one such entry per line. The first
+//! key encrypts; all keys decrypt, so a rotation adds the new key first,
+//! re-encrypts the stored keys and then drops the old key.
+//!
+//! Values without the `enc:` prefix are plaintext rows written before
+//! encryption was enabled. They are encrypted in place at startup only when
+//! `PROVIDER_KEY_ENCRYPT_EXISTING=true`, or on demand by the re-encrypt admin
+//! endpoint: the Python server reads the same table and cannot decrypt them.
+
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Env var listing the master keys.
+pub const ENCRYPTION_KEYS_ENV: &str = "PROVIDER_KEY_ENCRYPTION_KEYS";
+
+/// Env var naming a file listing the master keys.
+pub const ENCRYPTION_KEY_FILE_ENV: &str = "PROVIDER_KEY_ENCRYPTION_KEY_FILE";
+
+/// Env var opting in to encrypting plaintext keys already stored at startup.
+pub const ENCRYPT_EXISTING_ENV: &str = "PROVIDER_KEY_ENCRYPT_EXISTING";
+
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const KEY_LEN: usize = 32;
+
+/// Associated data binding sealed API keys to their purpose.
+const API_KEY_AAD: &[u8] = b"provider_api_key";
+
+/// Errors of provider key encryption.
+#[derive(Debug, thiserror::Error)]
+pub enum KeyEncryptionError {
+    #[error("invalid master key configuration: {0}")]
+    InvalidConfig(String),
+    #[error("provider API key is encrypted with unknown master key '{0}'")]
+    UnknownKeyId(String),
+    #[error("provider API key is encrypted but no master key is configured")]
+    NotConfigured,
+    #[error("malformed encrypted provider API key")]
+    Malformed,
+    #[error("provider API key could not be decrypted")]
+    DecryptFailed,
+    #[error("provider API key could not be encrypted")]
+    EncryptFailed,
+}
+
+/// Master keys encrypting and decrypting provider API keys.
+pub struct ProviderKeyCipher {
+    primary_id: String,
+    keys: HashMap<String, LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl std::fmt::Debug for ProviderKeyCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut key_ids: Vec<_> = self.keys.keys().collect();
+        key_ids.sort();
+        f.debug_struct("ProviderKeyCipher")
+            .field("primary_id", &self.primary_id)
+            .field("key_ids", &key_ids)
+            .finish()
+    }
+}
+
+impl ProviderKeyCipher {
+    /// Load the master keys from the environment, if configured.
+    pub fn from_env() -> Result<Option<Self>, KeyEncryptionError> {
+        if let Ok(keys) = std::env::var(ENCRYPTION_KEYS_ENV) {
+            return Self::parse(keys.split(',')).map(Some);
+        }
+        if let Ok(path) = std::env::var(ENCRYPTION_KEY_FILE_ENV) {
+            let contents = std::fs::read_to_string(&path).map_err(|e| {
+                KeyEncryptionError::InvalidConfig(format!("cannot read {}: {}", path, e))
+            })?;
+            let lines = contents
+                .lines()
+                .filter(|line| !line.trim_start().starts_with('#'));
+            return Self::parse(lines).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Parse `<key id>:<base64 key>` entries; the first one is the primary key.
+    pub fn parse<'a>(
+        entries: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, KeyEncryptionError> {
+        let mut primary_id = None;
+        let mut keys = HashMap::new();
+        for entry in entries.into_iter().map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, key) = entry.split_once(':').ok_or_else(|| {
+                KeyEncryptionError::InvalidConfig("entries must be <key id>:<base64 key>".into())
+            })?;
+            let id = id.trim();
+            if id.is_empty() {
+                return Err(KeyEncryptionError::InvalidConfig(
+                    "key ids must be non-empty".into(),
+                ));
+            }
+            let bytes = STANDARD.decode(key.trim()).map_err(|_| {
+                KeyEncryptionError::InvalidConfig(format!("key '{}' is not valid base64", id))
+            })?;
+            if bytes.len() != KEY_LEN {
+                return Err(KeyEncryptionError::InvalidConfig(format!(
+                    "key '{}' must be {} bytes",
+                    id, KEY_LEN
+                )));
+            }
+            if keys.insert(id.to_string(), aead_key(&bytes)?).is_some() {
+                return Err(KeyEncryptionError::InvalidConfig(format!(
+                    "duplicate key id '{}'",
+                    id
+                )));
+            }
+            primary_id.get_or_insert_with(|| id.to_string());
+        }
+        let primary_id = primary_id
+            .ok_or_else(|| KeyEncryptionError::InvalidConfig("no master key given".into()))?;
+        Ok(Self {
+            primary_id,
+            keys,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// Id of the master key new values are encrypted with.
+    pub fn primary_key_id(&self) -> &str {
+        &self.primary_id
+    }
+
+    /// Encrypt an API key with a fresh data key under the primary master key.
+    pub fn encrypt(&self, api_key: &str) -> Result<String, KeyEncryptionError> {
+        let mut data_key = [0u8; KEY_LEN];
+        self.rng
+            .fill(&mut data_key)
+            .map_err(|_| KeyEncryptionError::EncryptFailed)?;
+        let sealed = self.seal(&aead_key(&data_key)?, api_key.as_bytes(), API_KEY_AAD)?;
+        self.envelope(&data_key, &sealed)
+    }
+
+    /// Decrypt a stored API key; plaintext values are returned unchanged.
+    pub fn decrypt(&self, stored: &str) -> Result<String, KeyEncryptionError> {
+        let Some(parts) = EnvelopeParts::parse(stored)? else {
+            return Ok(stored.to_string());
+        };
+        let data_key = self.unwrap_data_key(&parts)?;
+        let plaintext = open(&aead_key(&data_key)?, &parts.sealed, API_KEY_AAD)?;
+        String::from_utf8(plaintext).map_err(|_| KeyEncryptionError::Malformed)
+    }
+
+    /// Whether a stored value is plaintext or sealed under a non-primary key.
+    pub fn needs_reencryption(&self, stored: &str) -> bool {
+        match EnvelopeParts::parse(stored) {
+            Ok(Some(parts)) => parts.key_id != self.primary_id,
+            Ok(None) => true,
+            Err(_) => false,
+        }
+    }
+
+    /// Encrypt a plaintext value, or re-wrap the data key of an encrypted value
+    /// under the primary master key without touching the sealed API key.
+    pub fn reencrypt(&self, stored: &str) -> Result<String, KeyEncryptionError> {
+        match EnvelopeParts::parse(stored)? {
+            None => self.encrypt(stored),
+            Some(parts) => {
+                let data_key = self.unwrap_data_key(&parts)?;
+                self.envelope(&data_key, &parts.sealed)
+            }
+        }
+    }
+
+    fn envelope(&self, data_key: &[u8], sealed: &[u8]) -> Result<String, KeyEncryptionError> {
+        let master = &self.keys[&self.primary_id];
+        let wrapped = self.seal(master, data_key, self.primary_id.as_bytes())?;
+        Ok(format!(
+            "{}{}:{}:{}",
+            ENCRYPTED_PREFIX,
+            self.primary_id,
+            STANDARD.encode(wrapped),
+            STANDARD.encode(sealed)
+        ))
+    }
+
+    fn unwrap_data_key(&self, parts: &EnvelopeParts) -> Result<Vec<u8>, KeyEncryptionError> {
+        let master = self
+            .keys
+            .get(&parts.key_id)
+            .ok_or_else(|| KeyEncryptionError::UnknownKeyId(parts.key_id.clone()))?;
+        open(master, &parts.wrapped_key, parts.key_id.as_bytes())
+    }
+
+    /// Seal `plaintext`, returning the nonce followed by ciphertext and tag.
+    fn seal(
+        &self,
+        key: &LessSafeKey,
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> Result<Vec<u8>, KeyEncryptionError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| KeyEncryptionError::EncryptFailed)?;
+        let mut in_out = plaintext.to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad),
+            &mut in_out,
+        )
+        .map_err(|_| KeyEncryptionError::EncryptFailed)?;
+        let mut out = nonce.to_vec();
+        out.extend_from_slice(&in_out);
+        Ok(out)
+    }
+}
+
+/// Whether plaintext keys already stored should be encrypted at startup.
+pub fn encrypt_existing_from_env() -> bool {
+    std::env::var(ENCRYPT_EXISTING_ENV)
+        .map(|value| {
+            matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// Whether a stored API key value is encrypted.
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Decrypt a stored API key with an optional cipher.
+///
+/// Without a cipher only plaintext values can be read.
+pub fn decrypt_api_key(
+    cipher: Option<&ProviderKeyCipher>,
+    stored: &str,
+) -> Result<String, KeyEncryptionError> {
+    match cipher {
+        Some(cipher) => cipher.decrypt(stored),
+        None if is_encrypted(stored) => Err(KeyEncryptionError::NotConfigured),
+        None => Ok(stored.to_string()),
+    }
+}
+
+struct EnvelopeParts {
+    key_id: String,
+    wrapped_key: Vec<u8>,
+    sealed: Vec<u8>,
+}
+
+impl EnvelopeParts {
+    fn parse(stored: &str) -> Result<Option<Self>, KeyEncryptionError> {
+        let Some(rest) = stored.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(None);
+        };
+        let mut parts = rest.splitn(3, ':');
+        let (Some(key_id), Some(wrapped), Some(sealed)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(KeyEncryptionError::Malformed);
+        };
+        let decode = |s: &str| {
+            STANDARD
+                .decode(s)
+                .map_err(|_| KeyEncryptionError::Malformed)
+        };
+        Ok(Some(Self {
+            key_id: key_id.to_string(),
+            wrapped_key: decode(wrapped)?,
+            sealed: decode(sealed)?,
+        }))
+    }
+}
+
+fn aead_key(bytes: &[u8]) -> Result<LessSafeKey, KeyEncryptionError> {
+    UnboundKey::new(&AES_256_GCM, bytes)
+        .map(LessSafeKey::new)
+        .map_err(|_| KeyEncryptionError::InvalidConfig("invalid AES-256 key".into()))
+}
+
+/// Open a value produced by [`ProviderKeyCipher::seal`].
+fn open(key: &LessSafeKey, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, KeyEncryptionError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(KeyEncryptionError::Malformed);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce =
+        Nonce::try_assume_unique_for_key(nonce).map_err(|_| KeyEncryptionError::Malformed)?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(aad), &mut in_out)
+        .map_err(|_| KeyEncryptionError::DecryptFailed)?;
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, byte: u8) -> String {
+        format!("{}:{}", id, STANDARD.encode([byte; KEY_LEN]))
+    }
+
+    fn cipher(entries: &[String]) -> ProviderKeyCipher {
+        ProviderKeyCipher::parse(entries.iter().map(String::as_str)).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cipher = cipher(&[entry("k1", 1)]);
+        let stored = cipher.encrypt("sk-secret").unwrap();
+        assert!(stored.starts_with("enc:v1:k1:"));
+        assert!(!stored.contains("sk-secret"));
+        assert_ne!(stored, cipher.encrypt("sk-secret").unwrap());
+        assert_eq!(cipher.decrypt(&stored).unwrap(), "sk-secret");
+        assert!(!cipher.needs_reencryption(&stored));
+    }
+
+    #[test]
+    fn test_plaintext_rows_pass_through_and_need_encryption() {
+        let cipher = cipher(&[entry("k1", 1)]);
+        assert_eq!(cipher.decrypt("sk-plain").unwrap(), "sk-plain");
+        assert!(cipher.needs_reencryption("sk-plain"));
+
+        let stored = cipher.reencrypt("sk-plain").unwrap();
+        assert_eq!(cipher.decrypt(&stored).unwrap(), "sk-plain");
+    }
+
+    #[test]
+    fn test_rotation_rewraps_under_new_primary() {
+        let old = cipher(&[entry("k1", 1)]);
+        let stored = old.encrypt("sk-secret").unwrap();
+
+        let rotating = cipher(&[entry("k2", 2), entry("k1", 1)]);
+        assert_eq!(rotating.primary_key_id(), "k2");
+        assert_eq!(rotating.decrypt(&stored).unwrap(), "sk-secret");
+        assert!(rotating.needs_reencryption(&stored));
+
+        let rotated = rotating.reencrypt(&stored).unwrap();
+        assert!(rotated.starts_with("enc:v1:k2:"));
+        // The sealed API key is kept, only the data key is re-wrapped
+        assert_eq!(
+            rotated.rsplit(':').next().unwrap(),
+            stored.rsplit(':').next().unwrap()
+        );
+
+        let new = cipher(&[entry("k2", 2)]);
+        assert_eq!(new.decrypt(&rotated).unwrap(), "sk-secret");
+        assert!(matches!(
+            new.decrypt(&stored),
+            Err(KeyEncryptionError::UnknownKeyId(id)) if id == "k1"
+        ));
+    }
+
+    #[test]
+    fn test_tampering_and_missing_cipher_fail() {
+        let cipher = cipher(&[entry("k1", 1)]);
+        let stored = cipher.encrypt("sk-secret").unwrap();
+        let mut tampered = stored.clone();
+        tampered.replace_range(stored.len() - 4.., "AAAA");
+        assert!(cipher.decrypt(&tampered).is_err());
+
+        assert!(matches!(
+            decrypt_api_key(None, &stored),
+            Err(KeyEncryptionError::NotConfigured)
+        ));
+        assert_eq!(decrypt_api_key(None, "sk-plain").unwrap(), "sk-plain");
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_keys() {
+        assert!(ProviderKeyCipher::parse(["k1"]).is_err());
+        assert!(ProviderKeyCipher::parse(["k1:c2hvcnQ="]).is_err());
+        assert!(ProviderKeyCipher::parse(Vec::<&str>::new()).is_err());
+        let duplicate = [entry("k1", 1), entry("k1", 2)];
+        assert!(ProviderKeyCipher::parse(duplicate.iter().map(String::as_str)).is_err());
+    }
+}
@@ -27,6 +27,22 @@ tokio::task_local! {
     pub static API_KEY_NAME: String;
 }
 
+tokio::task_local! {
+    /// Task-local storage for the client address of the current request.
+    ///
+    /// Set by `client_ip_middleware` from the peer address and trusted
+    /// `X-Forwarded-For` hops; used to check credential network allowlists.
+    pub static CLIENT_IP: Option<std::net::IpAddr>;
+}
+
+tokio::task_local! {
+    /// Task-local storage for the verified client certificate of the current request.
+    ///
+    /// Set by `client_ip_middleware` on TLS connections that presented one;
+    /// used to authenticate services by certificate.
+    pub static CLIENT_CERTIFICATE: Option<crate::core::tls::ClientCertificate>;
+}
+
 /// Get the current provider name from context, if set.
 ///
 /// Returns an empty string if no provider context is set.
@@ -52,6 +68,19 @@ pub fn get_api_key_name() -> String {
         .unwrap_or_else(|_| "anonymous".to_string())
 }
 
+/// Get the client address of the current request, if known.
+pub fn get_client_ip() -> Option<std::net::IpAddr> {
+    CLIENT_IP.try_with(|ip| *ip).ok().flatten()
+}
+
+/// Get the verified client certificate of the current request, if any.
+pub fn get_client_certificate() -> Option<crate::core::tls::ClientCertificate> {
+    CLIENT_CERTIFICATE
+        .try_with(|certificate| certificate.clone())
+        .ok()
+        .flatten()
+}
+
 /// Generate a new unique request ID using UUID v4.
 ///
 /// Returns a string representation of the UUID.
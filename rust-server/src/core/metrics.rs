@@ -4,8 +4,8 @@
 //! for tracking requests, latency, token usage, and provider health.
 
 use prometheus::{
-    register_gauge_vec, register_histogram_vec, register_int_counter_vec, GaugeVec, HistogramVec,
-    IntCounterVec,
+    register_counter_vec, register_gauge_vec, register_histogram_vec, register_int_counter_vec,
+    CounterVec, GaugeVec, HistogramVec, IntCounterVec,
 };
 use std::sync::OnceLock;
 
@@ -57,6 +57,18 @@ pub struct Metrics {
 
     /// Total number of client disconnects
     pub client_disconnects_total: prometheus::IntCounter,
+
+    /// Total audio seconds processed by audio endpoints (transcription input / speech output)
+    pub audio_duration_seconds: CounterVec,
+
+    /// Structured output validation outcomes (valid / repaired / invalid)
+    pub structured_output_validations: IntCounterVec,
+
+    /// Tool call argument validation outcomes (valid / repaired / invalid / schema_mismatch)
+    pub tool_argument_validations: IntCounterVec,
+
+    /// Requests rejected because the client address is outside the credential's allowlist
+    pub credential_network_rejections: IntCounterVec,
 }
 
 static METRICS: OnceLock<Metrics> = OnceLock::new();
@@ -203,6 +215,34 @@ pub fn init_metrics() -> &'static Metrics {
         )
         .expect("Failed to register client_disconnects_total metric");
 
+        let audio_duration_seconds = register_counter_vec!(
+            "llm_proxy_audio_duration_seconds_total",
+            "Total audio seconds processed by audio endpoints",
+            &["model", "provider", "endpoint", "api_key_name"]
+        )
+        .expect("Failed to register audio_duration_seconds metric");
+
+        let structured_output_validations = register_int_counter_vec!(
+            "llm_proxy_structured_output_validations_total",
+            "Structured output validation outcomes against the requested JSON schema",
+            &["model", "provider", "result"]
+        )
+        .expect("Failed to register structured_output_validations metric");
+
+        let tool_argument_validations = register_int_counter_vec!(
+            "llm_proxy_tool_argument_validations_total",
+            "Tool call argument validation outcomes of provider responses",
+            &["model", "provider", "result"]
+        )
+        .expect("Failed to register tool_argument_validations metric");
+
+        let credential_network_rejections = register_int_counter_vec!(
+            "llm_proxy_credential_network_rejections_total",
+            "Requests rejected because the client address is not in the credential's CIDR allowlist",
+            &["api_key_name"]
+        )
+        .expect("Failed to register credential_network_rejections metric");
+
         Metrics {
             request_count,
             request_duration,
@@ -219,6 +259,10 @@ pub fn init_metrics() -> &'static Metrics {
             bypass_streaming_bytes,
             cross_protocol_requests,
             client_disconnects_total,
+            audio_duration_seconds,
+            structured_output_validations,
+            tool_argument_validations,
+            credential_network_rejections,
         }
     })
 }
@@ -232,6 +276,14 @@ pub fn get_metrics() -> &'static Metrics {
     METRICS.get().expect("Metrics not initialized")
 }
 
+/// Get the global metrics instance if it has been initialized.
+///
+/// For code paths that also run outside the server, such as transformers
+/// exercised directly in tests.
+pub fn try_get_metrics() -> Option<&'static Metrics> {
+    METRICS.get()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -621,6 +673,75 @@ mod tests {
         assert_eq!(after, initial + 1);
     }
 
+    #[test]
+    fn test_audio_duration_seconds_metric() {
+        let metrics = init_metrics();
+        let labels = [
+            "whisper-unique",
+            "openai-audio",
+            "/v1/audio/transcriptions",
+            "test-key",
+        ];
+
+        let initial = metrics
+            .audio_duration_seconds
+            .with_label_values(&labels)
+            .get();
+        metrics
+            .audio_duration_seconds
+            .with_label_values(&labels)
+            .inc_by(12.5);
+        let after = metrics
+            .audio_duration_seconds
+            .with_label_values(&labels)
+            .get();
+
+        assert!((after - initial - 12.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_structured_output_validations_metric() {
+        let metrics = init_metrics();
+        let labels = ["schema-model-unique", "schema-provider", "repaired"];
+
+        let initial = metrics
+            .structured_output_validations
+            .with_label_values(&labels)
+            .get();
+        metrics
+            .structured_output_validations
+            .with_label_values(&labels)
+            .inc();
+        let after = metrics
+            .structured_output_validations
+            .with_label_values(&labels)
+            .get();
+
+        assert_eq!(after, initial + 1);
+    }
+
+    #[test]
+    fn test_tool_argument_validations_metric() {
+        let metrics = init_metrics();
+        let labels = ["tool-args-model-unique", "tool-args-provider", "invalid"];
+
+        let initial = metrics
+            .tool_argument_validations
+            .with_label_values(&labels)
+            .get();
+        metrics
+            .tool_argument_validations
+            .with_label_values(&labels)
+            .inc();
+        let after = metrics
+            .tool_argument_validations
+            .with_label_values(&labels)
+            .get();
+
+        assert_eq!(after, initial + 1);
+        assert!(try_get_metrics().is_some());
+    }
+
     #[test]
     fn test_provider_latency_metric() {
         let metrics = init_metrics();
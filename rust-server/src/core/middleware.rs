@@ -4,19 +4,26 @@
 //! duration, active requests, and status codes. It also provides model
 //! permission checking middleware.
 
-use crate::api::auth::{check_model_permission, hash_key};
+use crate::api::auth::{
+    check_model_permission, resolve_certificate_credential, resolve_credential, VerifiedJwt,
+};
 use crate::core::config::CredentialConfig;
-use crate::core::logging::generate_request_id;
+use crate::core::jwt_auth::jwt_authenticator;
+use crate::core::logging::{
+    generate_request_id, get_client_certificate, CLIENT_CERTIFICATE, CLIENT_IP,
+};
 use crate::core::metrics::get_metrics;
+use crate::core::tls::{tls_server, ClientCertificate, TlsServer};
 use axum::{
     body::Body,
-    extract::{Request, State},
+    extract::{ConnectInfo, Request, State},
     http::{HeaderMap, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -30,6 +37,11 @@ const MODEL_CHECK_PATHS: &[&str] = &[
     "/v2/chat/completions",
     "/v2/messages",
     "/v2/responses",
+    "/v1/images/generations",
+    "/v1/images/edits",
+    "/v1/audio/transcriptions",
+    "/v1/audio/translations",
+    "/v1/audio/speech",
     "/chat/completions",
     "/messages",
     "/responses",
@@ -74,6 +86,79 @@ pub async fn request_id_middleware(mut request: Request, next: Next) -> Response
     response
 }
 
+/// Env var with the number of reverse proxies in front of the server.
+pub const TRUSTED_PROXY_HOPS_ENV: &str = "TRUSTED_PROXY_HOPS";
+
+/// How the client address of a request is determined.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientIpConfig {
+    /// Number of trusted reverse proxies appending to `X-Forwarded-For`
+    /// (0 = use the peer address)
+    pub trusted_proxy_hops: usize,
+}
+
+impl ClientIpConfig {
+    /// Load the settings from the environment.
+    ///
+    /// An unparseable hop count is an error rather than 0: falling back to
+    /// the peer address would check allowlists against the proxy's address.
+    pub fn from_env() -> Result<Self, String> {
+        let trusted_proxy_hops = match std::env::var(TRUSTED_PROXY_HOPS_ENV) {
+            Ok(v) if !v.trim().is_empty() => v.trim().parse().map_err(|_| {
+                format!(
+                    "{} '{}' is not a non-negative number",
+                    TRUSTED_PROXY_HOPS_ENV, v
+                )
+            })?,
+            _ => 0,
+        };
+        Ok(Self { trusted_proxy_hops })
+    }
+}
+
+/// Resolve the client address of a request.
+///
+/// Without trusted proxies this is the peer address. With `hops` trusted
+/// proxies, it is the `X-Forwarded-For` entry appended by the outermost one;
+/// entries left of it are client-supplied and ignored. Returns None when the
+/// header has fewer entries than there are trusted proxies.
+pub fn resolve_client_ip(peer: Option<IpAddr>, headers: &HeaderMap, hops: usize) -> Option<IpAddr> {
+    if hops == 0 {
+        return peer;
+    }
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    let index = forwarded.len().checked_sub(hops)?;
+    forwarded[index].parse().ok()
+}
+
+/// Make the client address and certificate available to authentication via
+/// [`CLIENT_IP`] and [`CLIENT_CERTIFICATE`].
+pub async fn client_ip_middleware(
+    State(config): State<ClientIpConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client_ip = resolve_client_ip(peer, request.headers(), config.trusted_proxy_hops);
+    let certificate = request.extensions().get::<ClientCertificate>().cloned();
+    CLIENT_IP
+        .scope(
+            client_ip,
+            CLIENT_CERTIFICATE.scope(certificate, next.run(request)),
+        )
+        .await
+}
+
 /// Known client patterns for User-Agent mapping
 /// Each tuple: (pattern to match in UA, normalized client name)
 /// Order matters - more specific patterns should come first
@@ -231,16 +316,30 @@ fn extract_api_key_from_headers(headers: &HeaderMap) -> Option<String> {
         })
 }
 
-/// Find credential config by API key
+/// Find credential config by API key or JWT, with the verified JWT if any
 fn find_credential_by_key(
     api_key: &str,
     credentials: &[CredentialConfig],
-) -> Option<CredentialConfig> {
-    let key_hash = hash_key(api_key);
-    credentials
-        .iter()
-        .find(|c| c.enabled && c.credential_key == key_hash)
-        .cloned()
+) -> Option<(CredentialConfig, Option<VerifiedJwt>)> {
+    resolve_credential(
+        api_key,
+        credentials,
+        jwt_authenticator(),
+        None,
+        chrono::Utc::now(),
+    )
+    .ok()
+}
+
+/// Find credential config by verified client certificate
+fn find_credential_by_certificate(credentials: &[CredentialConfig]) -> Option<CredentialConfig> {
+    resolve_certificate_credential(
+        &get_client_certificate()?,
+        credentials,
+        tls_server().map(TlsServer::config),
+        chrono::Utc::now(),
+    )
+    .ok()
 }
 
 /// Model permission middleware for AppState
@@ -261,7 +360,7 @@ pub async fn model_permission_middleware<S: HasCredentials>(
     let headers = request.headers().clone();
 
     // Read body using axum's Bytes extractor approach
-    let (parts, body) = request.into_parts();
+    let (mut parts, body) = request.into_parts();
     let bytes = match axum::body::to_bytes(body, usize::MAX).await {
         Ok(b) => b,
         Err(_) => {
@@ -299,19 +398,22 @@ pub async fn model_permission_middleware<S: HasCredentials>(
         return next.run(request).await;
     }
 
-    // Get API key from headers
-    let api_key = match extract_api_key_from_headers(&headers) {
-        Some(key) => key,
-        None => {
-            // No API key, auth middleware will handle it
-            let request = Request::from_parts(parts, Body::from(bytes));
-            return next.run(request).await;
-        }
+    // Find credential config by API key, or by client certificate without one
+    let (credential_config, verified_jwt) = match extract_api_key_from_headers(&headers) {
+        Some(api_key) => match find_credential_by_key(&api_key, &credentials) {
+            Some((credential, verified_jwt)) => (Some(credential), verified_jwt),
+            None => (None, None),
+        },
+        None => match find_credential_by_certificate(&credentials) {
+            Some(credential) => (Some(credential), None),
+            None => {
+                // No API key, auth middleware will handle it
+                let request = Request::from_parts(parts, Body::from(bytes));
+                return next.run(request).await;
+            }
+        },
     };
 
-    // Find credential config
-    let credential_config = find_credential_by_key(&api_key, &credentials);
-
     // Check model permission
     if let Err(e) = check_model_permission(Some(&model), &credential_config) {
         tracing::warn!(
@@ -333,6 +435,11 @@ pub async fn model_permission_middleware<S: HasCredentials>(
             .into_response();
     }
 
+    // Let the handler's authentication reuse the verified token
+    if let Some(verified_jwt) = verified_jwt {
+        parts.extensions.insert(verified_jwt);
+    }
+
     // Rebuild request with body and proceed
     let request = Request::from_parts(parts, Body::from(bytes));
     next.run(request).await
@@ -1111,4 +1218,58 @@ mod tests {
         );
         assert_eq!(extract_client(&headers), "apifox");
     }
+
+    #[test]
+    fn test_resolve_client_ip_trusted_hops() {
+        let peer: Option<IpAddr> = Some("10.0.0.2".parse().unwrap());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "6.6.6.6, 203.0.113.7, 10.0.0.1".parse().unwrap(),
+        );
+
+        assert_eq!(resolve_client_ip(peer, &headers, 0), peer);
+        assert_eq!(
+            resolve_client_ip(peer, &headers, 1),
+            Some("10.0.0.1".parse().unwrap())
+        );
+        // Two trusted proxies: the spoofed leftmost entry is ignored
+        assert_eq!(
+            resolve_client_ip(peer, &headers, 2),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        assert_eq!(resolve_client_ip(peer, &headers, 4), None);
+        assert_eq!(resolve_client_ip(peer, &HeaderMap::new(), 1), None);
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_middleware_scopes_client_ip() {
+        use crate::core::logging::get_client_ip;
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { get_client_ip().map(|ip| ip.to_string()).unwrap_or_default() }),
+            )
+            .layer(middleware::from_fn_with_state(
+                ClientIpConfig {
+                    trusted_proxy_hops: 1,
+                },
+                client_ip_middleware,
+            ));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header("x-forwarded-for", "198.51.100.4")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"198.51.100.4");
+    }
 }
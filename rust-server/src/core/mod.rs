@@ -12,19 +12,25 @@
 
 pub mod cancel;
 pub mod config;
+pub mod config_history;
+pub mod credential_usage;
 pub mod database;
 pub mod error;
 pub mod error_logger;
 pub mod error_types;
 pub mod header_policy;
 pub mod jsonl_logger;
+pub mod jwt_auth;
+pub mod key_encryption;
 pub mod langfuse;
 pub mod logging;
 pub mod metrics;
 pub mod middleware;
+pub mod provider_network;
 pub mod rate_limiter;
 pub mod request_logger;
 pub mod stream_metrics;
+pub mod tls;
 pub mod token_counter;
 pub mod tokenizer;
 pub mod utils;
@@ -56,7 +62,7 @@ pub use langfuse::{
     GenerationData, LangfuseConfig, LangfuseService,
 };
 pub use logging::{get_provider_context, PROVIDER_CONTEXT};
-pub use metrics::{get_metrics, init_metrics, Metrics};
+pub use metrics::{get_metrics, init_metrics, try_get_metrics, Metrics};
 pub use middleware::{
     admin_logging_middleware, model_permission_middleware, request_id_middleware, HasCredentials,
     MetricsMiddleware, ModelName, ProviderName, RequestId,
@@ -0,0 +1,543 @@
+//! Outbound network settings of providers.
+//!
+//! All providers share one HTTP client unless their `network` param asks for
+//! something else:
+//!
+//! ```json
+//! "network": {
+//!     "connect_timeout_secs": 5,
+//!     "read_timeout_secs": 900,
+//!     "ttft_timeout_secs": 120,
+//!     "proxy": "socks5h://egress.internal:1080",
+//!     "ca_bundle": "/etc/llm-proxy/private-ca.pem",
+//!     "client_cert": "/etc/llm-proxy/client.pem",
+//!     "client_key": "/etc/llm-proxy/client.key",
+//!     "verify_ssl": true
+//! }
+//! ```
+//!
+//! `read_timeout_secs` limits the whole upstream response and replaces
+//! `REQUEST_TIMEOUT_SECS`; `ttft_timeout_secs` replaces `TTFT_TIMEOUT_SECS`.
+//! The proxy may be `http://`, `https://`, `socks5://` or `socks5h://` (DNS
+//! resolved by the proxy); SOCKS proxy hosts themselves are resolved when the
+//! client is built. The CA bundle is trusted in addition to the system
+//! roots; the client certificate and PKCS#8 key are PEM files.
+//!
+//! One client is built per distinct set of settings and shared by the
+//! providers using it. Clients are rebuilt, and the files read again, when
+//! the configuration is reloaded; if a rebuild fails the previous client for
+//! the same settings is kept. A provider with invalid settings or no usable
+//! client is taken out of routing rather than sent over the shared client.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::core::config::AppConfig;
+
+/// Provider param configuring the provider's HTTP client.
+pub const NETWORK_PARAM: &str = "network";
+
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Client settings that apply to every provider without its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkDefaults {
+    pub verify_ssl: bool,
+    pub request_timeout_secs: u64,
+}
+
+impl NetworkDefaults {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            verify_ssl: config.verify_ssl,
+            request_timeout_secs: config.request_timeout_secs,
+        }
+    }
+
+    /// Builder with the pool and keep-alive settings of all upstream clients.
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        reqwest::Client::builder()
+            .danger_accept_invalid_certs(!self.verify_ssl)
+            .timeout(Duration::from_secs(self.request_timeout_secs))
+            .pool_max_idle_per_host(100)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_keepalive(Duration::from_secs(60))
+            .http2_keep_alive_interval(Duration::from_secs(30))
+            .http2_keep_alive_timeout(Duration::from_secs(10))
+    }
+}
+
+/// Network settings from a provider's `network` param.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkSettings {
+    /// Time limit for establishing the connection
+    pub connect_timeout_secs: Option<u64>,
+    /// Time limit for the whole upstream response
+    pub read_timeout_secs: Option<u64>,
+    /// Time limit for the first streamed token
+    pub ttft_timeout_secs: Option<u64>,
+    /// HTTP(S) or SOCKS5 proxy URL
+    pub proxy: Option<String>,
+    /// PEM bundle of additional trusted CA certificates
+    pub ca_bundle: Option<String>,
+    /// PEM client certificate chain
+    pub client_cert: Option<String>,
+    /// PEM PKCS#8 key of the client certificate
+    pub client_key: Option<String>,
+    /// Overrides `VERIFY_SSL`
+    pub verify_ssl: Option<bool>,
+}
+
+impl NetworkSettings {
+    /// Parse and check the `network` param; None when it is absent.
+    pub fn from_params(params: &HashMap<String, Value>) -> Result<Option<Self>, String> {
+        let Some(value) = params.get(NETWORK_PARAM) else {
+            return Ok(None);
+        };
+        let settings: Self = serde_json::from_value(value.clone())
+            .map_err(|e| format!("invalid {} param: {}", NETWORK_PARAM, e))?;
+
+        let timeouts = [
+            ("connect_timeout_secs", settings.connect_timeout_secs),
+            ("read_timeout_secs", settings.read_timeout_secs),
+            ("ttft_timeout_secs", settings.ttft_timeout_secs),
+        ];
+        if let Some((name, _)) = timeouts.iter().find(|(_, v)| *v == Some(0)) {
+            return Err(format!("{}.{} must be positive", NETWORK_PARAM, name));
+        }
+        if settings.client_cert.is_some() != settings.client_key.is_some() {
+            return Err(format!(
+                "{}.client_cert and {}.client_key must be set together",
+                NETWORK_PARAM, NETWORK_PARAM
+            ));
+        }
+        if let Some(proxy) = &settings.proxy {
+            // Only the syntax: reqwest resolves SOCKS proxy hosts when the
+            // proxy is created, which is left to building the client
+            let invalid =
+                |reason: &str| format!("invalid {}.proxy '{}': {}", NETWORK_PARAM, proxy, reason);
+            let url = reqwest::Url::parse(proxy).map_err(|e| invalid(&e.to_string()))?;
+            if !PROXY_SCHEMES.contains(&url.scheme()) {
+                return Err(invalid("unsupported scheme"));
+            }
+            if url.host_str().is_none() {
+                return Err(invalid("missing host"));
+            }
+        }
+        Ok(Some(settings))
+    }
+
+    /// The settings that shape the client, without per-request timeouts.
+    fn client_settings(&self) -> Self {
+        Self {
+            ttft_timeout_secs: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the shared client can be used.
+    fn uses_default_client(&self) -> bool {
+        self.client_settings() == Self::default()
+    }
+
+    /// Build a client with these settings; fails when a file cannot be used.
+    pub fn build_client(&self, defaults: &NetworkDefaults) -> Result<reqwest::Client, String> {
+        let read =
+            |path: &str| std::fs::read(path).map_err(|e| format!("cannot read '{}': {}", path, e));
+
+        let mut builder = NetworkDefaults {
+            verify_ssl: self.verify_ssl.unwrap_or(defaults.verify_ssl),
+            request_timeout_secs: self
+                .read_timeout_secs
+                .unwrap_or(defaults.request_timeout_secs),
+        }
+        .client_builder();
+        if let Some(secs) = self.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(|e| e.to_string())?);
+        }
+        if let Some(path) = &self.ca_bundle {
+            let certs = reqwest::Certificate::from_pem_bundle(&read(path)?)
+                .map_err(|e| format!("invalid CA bundle '{}': {}", path, e))?;
+            if certs.is_empty() {
+                return Err(format!("no certificates in CA bundle '{}'", path));
+            }
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let (Some(cert), Some(key)) = (&self.client_cert, &self.client_key) {
+            let identity = reqwest::Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)
+                .map_err(|e| format!("invalid client certificate '{}': {}", cert, e))?;
+            builder = builder.identity(identity);
+        }
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+/// Client for a provider with the given params, built without caching.
+///
+/// Used outside the request path (health checks). Providers without settings
+/// of their own get `default`; settings that are invalid or cannot be used
+/// are an error, never a fallback to `default`.
+pub fn provider_client(
+    params: &HashMap<String, Value>,
+    default: &reqwest::Client,
+    defaults: &NetworkDefaults,
+) -> Result<reqwest::Client, String> {
+    match NetworkSettings::from_params(params)? {
+        Some(settings) if !settings.uses_default_client() => settings.build_client(defaults),
+        _ => Ok(default.clone()),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ProviderNetwork {
+    /// Settings the client was built with (without per-request timeouts)
+    settings: NetworkSettings,
+    client: reqwest::Client,
+    ttft_timeout_secs: Option<u64>,
+}
+
+/// HTTP clients and timeouts of the configured providers.
+///
+/// A provider whose settings are invalid, or whose client cannot be built
+/// and has no previous client, is unavailable: it must not be routed to, as
+/// the default client would bypass its proxy or certificates.
+#[derive(Debug, Clone)]
+pub struct ProviderClients {
+    default: reqwest::Client,
+    default_ttft_timeout_secs: Option<u64>,
+    providers: HashMap<String, ProviderNetwork>,
+    unavailable: HashMap<String, String>,
+    client_count: usize,
+}
+
+impl ProviderClients {
+    /// Build the clients of `config.providers`, sharing `default` where possible.
+    ///
+    /// When a client cannot be built (e.g. a certificate file is being
+    /// replaced), the client `previous` built with the same settings is kept.
+    pub fn new(
+        default: reqwest::Client,
+        config: &AppConfig,
+        previous: Option<&ProviderClients>,
+    ) -> Self {
+        let defaults = NetworkDefaults::from_config(config);
+        let mut built: HashMap<NetworkSettings, reqwest::Client> = HashMap::new();
+        let mut providers = HashMap::new();
+        let mut unavailable = HashMap::new();
+
+        for provider in &config.providers {
+            let result =
+                NetworkSettings::from_params(&provider.provider_params).and_then(|settings| {
+                    match settings {
+                        Some(settings) => {
+                            Self::client_for(&settings, &default, &defaults, previous, &mut built)
+                                .map(|client| Some((settings, client)))
+                        }
+                        None => Ok(None),
+                    }
+                });
+            match result {
+                Ok(Some((settings, client))) => {
+                    providers.insert(
+                        provider.name.clone(),
+                        ProviderNetwork {
+                            settings: settings.client_settings(),
+                            client,
+                            ttft_timeout_secs: settings.ttft_timeout_secs,
+                        },
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!(
+                        provider = %provider.name,
+                        error = %e,
+                        "Provider HTTP client unavailable, provider excluded from routing"
+                    );
+                    unavailable.insert(provider.name.clone(), e);
+                }
+            }
+        }
+
+        if !built.is_empty() {
+            tracing::info!(
+                clients = built.len(),
+                providers = providers.len(),
+                "Built provider HTTP clients"
+            );
+        }
+        Self {
+            default,
+            default_ttft_timeout_secs: config.ttft_timeout_secs,
+            providers,
+            unavailable,
+            client_count: built.len(),
+        }
+    }
+
+    fn client_for(
+        settings: &NetworkSettings,
+        default: &reqwest::Client,
+        defaults: &NetworkDefaults,
+        previous: Option<&ProviderClients>,
+        built: &mut HashMap<NetworkSettings, reqwest::Client>,
+    ) -> Result<reqwest::Client, String> {
+        if settings.uses_default_client() {
+            return Ok(default.clone());
+        }
+        let key = settings.client_settings();
+        if let Some(client) = built.get(&key) {
+            return Ok(client.clone());
+        }
+        let client = key.build_client(defaults).or_else(|e| {
+            let last_good = previous
+                .and_then(|p| p.providers.values().find(|n| n.settings == key))
+                .map(|n| n.client.clone())
+                .ok_or_else(|| e.clone())?;
+            tracing::warn!(error = %e, "Failed to rebuild provider HTTP client, keeping the previous one");
+            Ok::<_, String>(last_good)
+        })?;
+        built.insert(key, client.clone());
+        Ok(client)
+    }
+
+    /// The client used for requests to a provider.
+    ///
+    /// Providers without settings of their own use the default client;
+    /// unavailable providers fail with the reason.
+    pub fn client(&self, provider: &str) -> Result<&reqwest::Client, &str> {
+        if let Some(reason) = self.unavailable.get(provider) {
+            return Err(reason);
+        }
+        Ok(self
+            .providers
+            .get(provider)
+            .map(|p| &p.client)
+            .unwrap_or(&self.default))
+    }
+
+    /// Whether a provider can be routed to.
+    pub fn is_available(&self, provider: &str) -> bool {
+        !self.unavailable.contains_key(provider)
+    }
+
+    /// The TTFT timeout of a provider's streams.
+    pub fn ttft_timeout_secs(&self, provider: &str) -> Option<u64> {
+        self.providers
+            .get(provider)
+            .and_then(|p| p.ttft_timeout_secs)
+            .or(self.default_ttft_timeout_secs)
+    }
+
+    /// Number of clients built besides the default one.
+    pub fn client_count(&self) -> usize {
+        self.client_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::{ProviderConfig, ServerConfig};
+    use serde_json::json;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+    fn params(network: Value) -> HashMap<String, Value> {
+        HashMap::from([(NETWORK_PARAM.to_string(), network)])
+    }
+
+    fn provider(name: &str, network: Option<Value>) -> ProviderConfig {
+        ProviderConfig {
+            name: name.to_string(),
+            api_base: "http://localhost:8000".to_string(),
+            api_key: "key".to_string(),
+            weight: 1,
+            model_mapping: HashMap::new(),
+            provider_type: "openai".to_string(),
+            provider_params: network.map(params).unwrap_or_default(),
+        }
+    }
+
+    fn config(providers: Vec<ProviderConfig>) -> AppConfig {
+        AppConfig {
+            providers,
+            server: ServerConfig::default(),
+            verify_ssl: true,
+            request_timeout_secs: 300,
+            ttft_timeout_secs: Some(30),
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
+            credentials: vec![],
+            provider_suffix: None,
+            min_tokens_limit: 1,
+            max_tokens_limit: 128000,
+            guardrail_rules: vec![],
+        }
+    }
+
+    #[test]
+    fn test_from_params_validation() {
+        assert_eq!(NetworkSettings::from_params(&HashMap::new()), Ok(None));
+
+        let settings = NetworkSettings::from_params(&params(json!({
+            "connect_timeout_secs": 5,
+            "proxy": "socks5h://egress.internal:1080"
+        })))
+        .unwrap()
+        .unwrap();
+        assert_eq!(settings.connect_timeout_secs, Some(5));
+
+        for invalid in [
+            json!({"connect_timeout": 5}),
+            json!({"read_timeout_secs": 0}),
+            json!({"client_cert": "/tmp/client.pem"}),
+            json!({"proxy": "not a url"}),
+            json!({"proxy": "ftp://egress.internal"}),
+            json!("socks5://egress:1080"),
+        ] {
+            assert!(
+                NetworkSettings::from_params(&params(invalid.clone())).is_err(),
+                "{} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_provider_clients_share_clients_per_profile() {
+        let proxy = json!({"proxy": "http://egress.internal:3128"});
+        let clients = ProviderClients::new(
+            reqwest::Client::new(),
+            &config(vec![
+                provider("plain", None),
+                provider("egress-a", Some(proxy.clone())),
+                provider("egress-b", Some(proxy)),
+                provider("slow", Some(json!({"read_timeout_secs": 900}))),
+                provider("ttft-only", Some(json!({"ttft_timeout_secs": 120}))),
+            ]),
+            None,
+        );
+
+        // One client behind the proxy, one with the long timeout
+        assert_eq!(clients.client_count(), 2);
+        assert_eq!(clients.ttft_timeout_secs("plain"), Some(30));
+        assert_eq!(clients.ttft_timeout_secs("egress-a"), Some(30));
+        assert_eq!(clients.ttft_timeout_secs("ttft-only"), Some(120));
+        assert_eq!(clients.ttft_timeout_secs("unknown"), Some(30));
+    }
+
+    #[test]
+    fn test_unusable_settings_make_provider_unavailable() {
+        let app_config = config(vec![
+            provider("plain", None),
+            provider(
+                "private",
+                Some(json!({"ca_bundle": "/nonexistent/ca.pem", "ttft_timeout_secs": 60})),
+            ),
+            provider(
+                "typo",
+                Some(json!({"proxi": "http://egress.internal:3128"})),
+            ),
+        ]);
+        let clients = ProviderClients::new(reqwest::Client::new(), &app_config, None);
+
+        assert_eq!(clients.client_count(), 0);
+        assert!(clients.client("plain").is_ok());
+        assert!(clients
+            .client("private")
+            .unwrap_err()
+            .contains("/nonexistent/ca.pem"));
+        assert!(!clients.is_available("typo"));
+    }
+
+    #[test]
+    fn test_rebuild_keeps_last_good_client() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca_bundle = dir.path().join("ca.pem");
+        std::fs::copy(format!("{}/ca.pem", FIXTURES), &ca_bundle).unwrap();
+        let network = json!({"ca_bundle": ca_bundle.display().to_string()});
+        let unchanged = config(vec![provider("private", Some(network))]);
+
+        let clients = ProviderClients::new(reqwest::Client::new(), &unchanged, None);
+        assert!(clients.is_available("private"));
+
+        // The bundle is briefly missing while a reload happens
+        std::fs::remove_file(&ca_bundle).unwrap();
+        let rebuilt = ProviderClients::new(reqwest::Client::new(), &unchanged, Some(&clients));
+        assert!(rebuilt.is_available("private"));
+
+        // Changed settings have no previous client to keep
+        let changed = config(vec![provider(
+            "private",
+            Some(json!({"ca_bundle": ca_bundle.display().to_string(), "verify_ssl": false})),
+        )]);
+        let rebuilt = ProviderClients::new(reqwest::Client::new(), &changed, Some(&clients));
+        assert!(!rebuilt.is_available("private"));
+    }
+
+    #[test]
+    fn test_build_client_with_ca_bundle_and_client_certificate() {
+        let defaults = NetworkDefaults {
+            verify_ssl: true,
+            request_timeout_secs: 300,
+        };
+        let settings = NetworkSettings {
+            ca_bundle: Some(format!("{}/ca.pem", FIXTURES)),
+            client_cert: Some(format!("{}/client.pem", FIXTURES)),
+            client_key: Some(format!("{}/client.key", FIXTURES)),
+            ..Default::default()
+        };
+        assert!(settings.build_client(&defaults).is_ok());
+
+        let not_a_bundle = NetworkSettings {
+            ca_bundle: Some(format!("{}/client.key", FIXTURES)),
+            ..Default::default()
+        };
+        assert!(not_a_bundle.build_client(&defaults).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requests_go_through_configured_proxy() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let proxy = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("via proxy"))
+            .mount(&proxy)
+            .await;
+
+        let clients = ProviderClients::new(
+            reqwest::Client::new(),
+            &config(vec![provider(
+                "egress",
+                Some(json!({"proxy": proxy.uri(), "connect_timeout_secs": 2})),
+            )]),
+            None,
+        );
+        let body = clients
+            .client("egress")
+            .unwrap()
+            .get("http://upstream.invalid/v1/models")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "via proxy");
+
+        let received = proxy.received_requests().await.unwrap();
+        assert_eq!(received[0].url.host_str(), Some("upstream.invalid"));
+    }
+}
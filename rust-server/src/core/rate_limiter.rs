@@ -275,6 +275,12 @@ mod tests {
             }),
             enabled,
             allowed_models: vec![],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         }
     }
 
@@ -9,6 +9,7 @@ use sqlx::PgPool;
 use std::sync::{Mutex, OnceLock};
 use tokio::sync::{mpsc, oneshot};
 
+#[derive(Clone)]
 pub struct RequestLogRecord {
     pub request_id: String,
     pub endpoint: Option<String>,
@@ -26,6 +27,10 @@ pub struct RequestLogRecord {
     pub total_tokens: i32,
     pub total_duration_ms: Option<i32>,
     pub ttft_ms: Option<i32>,
+    /// Audio duration for audio endpoints (usage unit for transcription/speech)
+    pub audio_duration_ms: Option<i32>,
+    /// Distinct values redacted per PII placeholder label (never the values themselves)
+    pub pii_redactions: Option<serde_json::Value>,
     pub error_category: Option<String>,
     pub error_message: Option<String>,
     pub request_headers: Option<String>,
@@ -53,6 +58,8 @@ impl Default for RequestLogRecord {
             total_tokens: 0,
             total_duration_ms: None,
             ttft_ms: None,
+            audio_duration_ms: None,
+            pii_redactions: None,
             error_category: None,
             error_message: None,
             request_headers: None,
@@ -170,14 +177,14 @@ impl RequestLogger {
         }
 
         let count = buffer.len();
-        let cols = 22;
+        let cols = 24;
         let mut sql = String::from(
             "INSERT INTO request_logs (\
              timestamp, request_id, endpoint, credential_name, \
              model_requested, model_mapped, provider_name, provider_type, \
              client_protocol, provider_protocol, is_streaming, status_code, \
              input_tokens, output_tokens, total_tokens, \
-             total_duration_ms, ttft_ms, \
+             total_duration_ms, ttft_ms, audio_duration_ms, pii_redactions, \
              error_category, error_message, \
              request_headers, request_body, response_body\
              ) VALUES ",
@@ -231,6 +238,8 @@ impl RequestLogger {
                 .bind(record.total_tokens)
                 .bind(record.total_duration_ms)
                 .bind(record.ttft_ms)
+                .bind(record.audio_duration_ms)
+                .bind(record.pii_redactions)
                 .bind(record.error_category)
                 .bind(record.error_message)
                 .bind(record.request_headers)
@@ -0,0 +1,658 @@
+//! Native TLS termination for the proxy listener.
+//!
+//! Plain HTTP is served unless `TLS_CERT_FILE` and `TLS_KEY_FILE` name a PEM
+//! certificate chain and private key. The files are checked every
+//! `TLS_RELOAD_INTERVAL_SECS` (default 30) and reloaded when they change, so
+//! renewed certificates are picked up without a restart; new connections use
+//! the new certificate while open ones keep theirs. A file that fails to load
+//! is logged and the previous certificate stays in use.
+//!
+//! Setting `TLS_CLIENT_CA_FILE` enables client certificate verification
+//! against that CA bundle. `TLS_CLIENT_AUTH=required` (default) rejects
+//! handshakes without a valid certificate; `optional` also accepts clients
+//! without one, who then authenticate with a key as usual.
+//!
+//! A verified client certificate authenticates requests that carry no key
+//! when its common name maps to a credential profile in
+//! `TLS_CLIENT_CERT_PROFILES`. Like JWT profiles, the credential supplies
+//! allowed models and rate limits while the common name replaces its name
+//! in logs.
+
+use std::future::Future;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use arc_swap::ArcSwap;
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+const DEFAULT_RELOAD_SECS: u64 = 30;
+
+/// Time a client gets to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors of TLS configuration and certificate loading.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TlsError {
+    #[error("invalid TLS configuration: {0}")]
+    InvalidConfig(String),
+    #[error("cannot load {path}: {message}")]
+    Load { path: String, message: String },
+}
+
+/// Whether clients must present a certificate when a client CA is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientAuth {
+    Required,
+    Optional,
+}
+
+/// Maps client certificates with common name `common_name` to the
+/// credential named `profile`; `*` matches any verified certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertProfileRule {
+    pub common_name: String,
+    pub profile: String,
+}
+
+/// TLS listener settings.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first
+    pub cert_file: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_file: PathBuf,
+    /// PEM CA bundle verifying client certificates (None = no client auth)
+    pub client_ca_file: Option<PathBuf>,
+    pub client_auth: ClientAuth,
+    /// How often the files are checked for changes
+    pub reload_interval: Duration,
+    /// Rules mapping client certificates to credential profiles, first match wins
+    pub profile_rules: Vec<CertProfileRule>,
+}
+
+impl TlsConfig {
+    /// Load the settings from the environment; None when TLS is not configured.
+    pub fn from_env() -> Result<Option<Self>, TlsError> {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        let (cert_file, key_file) = match (env("TLS_CERT_FILE"), env("TLS_KEY_FILE")) {
+            (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+            (None, None) => return Ok(None),
+            _ => {
+                return Err(TlsError::InvalidConfig(
+                    "TLS_CERT_FILE and TLS_KEY_FILE must be set together".to_string(),
+                ))
+            }
+        };
+
+        let client_auth = match env("TLS_CLIENT_AUTH").as_deref() {
+            None | Some("required") => ClientAuth::Required,
+            Some("optional") => ClientAuth::Optional,
+            Some(other) => {
+                return Err(TlsError::InvalidConfig(format!(
+                    "TLS_CLIENT_AUTH '{}' is not 'required' or 'optional'",
+                    other
+                )))
+            }
+        };
+        let reload_secs = match env("TLS_RELOAD_INTERVAL_SECS") {
+            Some(v) => v.parse().map_err(|_| {
+                TlsError::InvalidConfig(format!("TLS_RELOAD_INTERVAL_SECS '{}' is not a number", v))
+            })?,
+            None => DEFAULT_RELOAD_SECS,
+        };
+        let client_ca_file = env("TLS_CLIENT_CA_FILE").map(PathBuf::from);
+        let profile_rules = match env("TLS_CLIENT_CERT_PROFILES") {
+            Some(v) => parse_cert_profile_rules(&v)?,
+            None => Vec::new(),
+        };
+        if !profile_rules.is_empty() && client_ca_file.is_none() {
+            return Err(TlsError::InvalidConfig(
+                "TLS_CLIENT_CERT_PROFILES requires TLS_CLIENT_CA_FILE".to_string(),
+            ));
+        }
+
+        Ok(Some(Self {
+            cert_file,
+            key_file,
+            client_ca_file,
+            client_auth,
+            reload_interval: Duration::from_secs(reload_secs.max(1)),
+            profile_rules,
+        }))
+    }
+
+    /// The credential profile a client certificate maps to.
+    pub fn profile_for(&self, certificate: &ClientCertificate) -> Option<&str> {
+        self.profile_rules
+            .iter()
+            .find(|rule| {
+                rule.common_name == "*"
+                    || certificate.common_name.as_deref() == Some(rule.common_name.as_str())
+            })
+            .map(|rule| rule.profile.as_str())
+    }
+
+    fn watched_files(&self) -> Vec<&Path> {
+        let mut files = vec![self.cert_file.as_path(), self.key_file.as_path()];
+        files.extend(self.client_ca_file.as_deref());
+        files
+    }
+}
+
+/// Parse `TLS_CLIENT_CERT_PROFILES`: comma-separated `<common name>=<profile>`.
+///
+/// The profile starts after the last `=`, so common names may contain `=`.
+pub fn parse_cert_profile_rules(spec: &str) -> Result<Vec<CertProfileRule>, TlsError> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let invalid = || {
+                TlsError::InvalidConfig(format!(
+                    "TLS_CLIENT_CERT_PROFILES entry '{}' is not <common name>=<profile>",
+                    entry
+                ))
+            };
+            let (common_name, profile) = entry.rsplit_once('=').ok_or_else(invalid)?;
+            let (common_name, profile) = (common_name.trim(), profile.trim());
+            if common_name.is_empty() || profile.is_empty() {
+                return Err(invalid());
+            }
+            Ok(CertProfileRule {
+                common_name: common_name.to_string(),
+                profile: profile.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn load_error(path: &Path, message: impl ToString) -> TlsError {
+    TlsError::Load {
+        path: path.display().to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let file = std::fs::File::open(path).map_err(|e| load_error(path, e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| load_error(path, e))?;
+    if certs.is_empty() {
+        return Err(load_error(path, "no PEM certificates found"));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, TlsError> {
+    let file = std::fs::File::open(path).map_err(|e| load_error(path, e))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| load_error(path, e))?
+        .ok_or_else(|| load_error(path, "no PEM private key found"))
+}
+
+/// Build the rustls server configuration from the certificate files.
+pub fn load_server_config(config: &TlsConfig) -> Result<Arc<ServerConfig>, TlsError> {
+    let provider: Arc<CryptoProvider> = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| TlsError::InvalidConfig(e.to_string()))?;
+
+    let builder = match &config.client_ca_file {
+        Some(ca_file) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_file)? {
+                roots.add(cert).map_err(|e| load_error(ca_file, e))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = match config.client_auth {
+                ClientAuth::Required => verifier,
+                ClientAuth::Optional => verifier.allow_unauthenticated(),
+            };
+            builder.with_client_cert_verifier(verifier.build().map_err(|e| load_error(ca_file, e))?)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(load_certs(&config.cert_file)?, load_key(&config.key_file)?)
+        .map_err(|e| load_error(&config.key_file, e))?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(server_config))
+}
+
+type FileStamp = Vec<Option<(SystemTime, u64)>>;
+
+fn file_stamp(files: &[&Path]) -> FileStamp {
+    files
+        .iter()
+        .map(|path| {
+            std::fs::metadata(path)
+                .ok()
+                .and_then(|m| Some((m.modified().ok()?, m.len())))
+        })
+        .collect()
+}
+
+/// The current TLS configuration of the listener, swapped on reload.
+pub struct TlsServer {
+    config: TlsConfig,
+    server_config: ArcSwap<ServerConfig>,
+    stamp: Mutex<FileStamp>,
+}
+
+impl TlsServer {
+    /// Load the certificate files; fails when they cannot be used.
+    pub fn new(config: TlsConfig) -> Result<Self, TlsError> {
+        let stamp = file_stamp(&config.watched_files());
+        let server_config = load_server_config(&config)?;
+        Ok(Self {
+            config,
+            server_config: ArcSwap::new(server_config),
+            stamp: Mutex::new(stamp),
+        })
+    }
+
+    pub fn config(&self) -> &TlsConfig {
+        &self.config
+    }
+
+    /// Reload the certificate files if any of them changed since the last load.
+    ///
+    /// Returns whether a new configuration was installed.
+    pub fn reload_if_changed(&self) -> Result<bool, TlsError> {
+        let stamp = file_stamp(&self.config.watched_files());
+        let mut last = self.stamp.lock().unwrap_or_else(|e| e.into_inner());
+        if *last == stamp {
+            return Ok(false);
+        }
+        // Remember the files even when they fail to load, so a broken
+        // certificate is reported once rather than on every check
+        *last = stamp;
+        self.server_config.store(load_server_config(&self.config)?);
+        Ok(true)
+    }
+
+    async fn reload_task(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.config.reload_interval);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match self.reload_if_changed() {
+                Ok(true) => tracing::info!(
+                    cert_file = %self.config.cert_file.display(),
+                    "TLS certificate reloaded"
+                ),
+                Ok(false) => {}
+                Err(e) => tracing::error!(
+                    error = %e,
+                    "Failed to reload TLS certificate, keeping the previous one"
+                ),
+            }
+        }
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.server_config.load_full())
+    }
+}
+
+static TLS_SERVER: OnceLock<Arc<TlsServer>> = OnceLock::new();
+
+/// Enable TLS when certificate files are configured.
+///
+/// Unlike the JWKS, certificates that cannot be loaded at startup are an
+/// error: serving plain HTTP instead would silently downgrade clients.
+pub fn init_tls() -> Result<Option<Arc<TlsServer>>, TlsError> {
+    let Some(config) = TlsConfig::from_env()? else {
+        return Ok(None);
+    };
+    let server = Arc::new(TlsServer::new(config)?);
+    tracing::info!(
+        cert_file = %server.config.cert_file.display(),
+        client_auth = ?server.config.client_ca_file.as_ref().map(|_| server.config.client_auth),
+        profiles = server.config.profile_rules.len(),
+        "TLS enabled"
+    );
+    tokio::spawn(server.clone().reload_task());
+    let _ = TLS_SERVER.set(server.clone());
+    Ok(Some(server))
+}
+
+/// The TLS listener configuration, if TLS is enabled.
+pub fn tls_server() -> Option<&'static TlsServer> {
+    TLS_SERVER.get().map(Arc::as_ref)
+}
+
+/// Serve `app` over TLS until `shutdown` completes.
+///
+/// Requests carry the peer address as `ConnectInfo<SocketAddr>`, like
+/// `axum::serve` with connect info, and the verified client certificate as
+/// a [`ClientCertificate`] extension. Open connections are drained on
+/// shutdown.
+pub async fn serve_tls(
+    listener: TcpListener,
+    app: Router,
+    tls: Arc<TlsServer>,
+    shutdown: impl Future<Output = ()>,
+) -> std::io::Result<()> {
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to accept connection");
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+
+        let acceptor = tls.acceptor();
+        let app = app.clone();
+        let builder = builder.clone();
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        tracing::debug!(peer = %peer, error = %e, "TLS handshake failed");
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!(peer = %peer, "TLS handshake timed out");
+                        return;
+                    }
+                };
+            let certificate = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| ClientCertificate::from_der(cert));
+
+            let service = app.map_request(move |mut request: axum::http::Request<Incoming>| {
+                request.extensions_mut().insert(ConnectInfo(peer));
+                if let Some(certificate) = &certificate {
+                    request.extensions_mut().insert(certificate.clone());
+                }
+                request
+            });
+            let connection = builder
+                .serve_connection_with_upgrades(
+                    TokioIo::new(stream),
+                    TowerToHyperService::new(service),
+                )
+                .into_owned();
+            if let Err(e) = watcher.watch(connection).await {
+                tracing::debug!(peer = %peer, error = %e, "TLS connection closed with error");
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+    Ok(())
+}
+
+// ============================================================================
+// Client Certificates
+// ============================================================================
+
+/// Identity of a verified client certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Subject distinguished name in certificate order, e.g. `O=Acme,CN=billing`
+    pub subject: String,
+    pub common_name: Option<String>,
+}
+
+impl ClientCertificate {
+    /// Read the subject of a DER certificate; None when it cannot be parsed.
+    ///
+    /// Only called for certificates rustls has already verified, so this
+    /// walks the structure without validating it.
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, certificate, _) = der_tlv(der)?;
+        let (_, tbs, _) = der_tlv(certificate)?;
+        let mut rest = tbs;
+        // Skip the optional version, then serial, signature, issuer and validity
+        if rest.first() == Some(&0xa0) {
+            rest = der_tlv(rest)?.2;
+        }
+        for _ in 0..4 {
+            rest = der_tlv(rest)?.2;
+        }
+        let (_, mut rdns, _) = der_tlv(rest)?;
+
+        let mut attributes = Vec::new();
+        let mut common_name = None;
+        while !rdns.is_empty() {
+            let (_, mut set, next) = der_tlv(rdns)?;
+            rdns = next;
+            while !set.is_empty() {
+                let (_, attribute, next) = der_tlv(set)?;
+                set = next;
+                let (_, oid, value) = der_tlv(attribute)?;
+                let (tag, value, _) = der_tlv(value)?;
+                let value = der_string(tag, value)?;
+                let name = attribute_name(oid);
+                if name == "CN" && common_name.is_none() {
+                    common_name = Some(value.clone());
+                }
+                attributes.push(format!("{}={}", name, value));
+            }
+        }
+
+        Some(Self {
+            subject: attributes.join(","),
+            common_name,
+        })
+    }
+
+    /// Name recorded for requests authenticated by this certificate.
+    pub fn identity(&self) -> &str {
+        self.common_name.as_deref().unwrap_or(&self.subject)
+    }
+}
+
+/// Split a DER element into tag, contents and the bytes after it.
+fn der_tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, &b| (len << 8) | b as usize);
+        (len, &rest[count..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+fn der_string(tag: u8, value: &[u8]) -> Option<String> {
+    match tag {
+        // UTF8String, PrintableString, IA5String, TeletexString
+        0x0c | 0x13 | 0x16 | 0x14 => Some(String::from_utf8_lossy(value).into_owned()),
+        // BMPString
+        0x1e => {
+            let units: Vec<u16> = value
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16(&units).ok()
+        }
+        _ => None,
+    }
+}
+
+fn attribute_name(oid: &[u8]) -> String {
+    match oid {
+        [0x55, 0x04, 0x03] => "CN".to_string(),
+        [0x55, 0x04, 0x06] => "C".to_string(),
+        [0x55, 0x04, 0x07] => "L".to_string(),
+        [0x55, 0x04, 0x08] => "ST".to_string(),
+        [0x55, 0x04, 0x0a] => "O".to_string(),
+        [0x55, 0x04, 0x0b] => "OU".to_string(),
+        _ => oid_to_string(oid),
+    }
+}
+
+fn oid_to_string(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for &b in oid {
+        value = (value << 7) | u64::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    arcs.iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(FIXTURES).join(name)
+    }
+
+    fn config() -> TlsConfig {
+        TlsConfig {
+            cert_file: fixture("server.pem"),
+            key_file: fixture("server.key"),
+            client_ca_file: None,
+            client_auth: ClientAuth::Required,
+            reload_interval: Duration::from_secs(30),
+            profile_rules: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_cert_profile_rules() {
+        let rules = parse_cert_profile_rules("billing-svc=billing, *=internal").unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                CertProfileRule {
+                    common_name: "billing-svc".to_string(),
+                    profile: "billing".to_string(),
+                },
+                CertProfileRule {
+                    common_name: "*".to_string(),
+                    profile: "internal".to_string(),
+                },
+            ]
+        );
+        assert!(parse_cert_profile_rules("billing-svc").is_err());
+        assert!(parse_cert_profile_rules("=billing").is_err());
+    }
+
+    #[test]
+    fn test_client_certificate_subject() {
+        let certs = load_certs(&fixture("client.pem")).unwrap();
+        let certificate = ClientCertificate::from_der(&certs[0]).unwrap();
+        assert_eq!(certificate.subject, "O=Acme,OU=Platform,CN=billing-svc");
+        assert_eq!(certificate.identity(), "billing-svc");
+
+        let mut config = config();
+        config.profile_rules = parse_cert_profile_rules("search-svc=search").unwrap();
+        assert_eq!(config.profile_for(&certificate), None);
+        config.profile_rules = parse_cert_profile_rules("billing-svc=billing,*=any").unwrap();
+        assert_eq!(config.profile_for(&certificate), Some("billing"));
+    }
+
+    #[test]
+    fn test_oid_to_string() {
+        // 1.2.840.113549.1.9.1 (emailAddress)
+        let oid = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01];
+        assert_eq!(oid_to_string(&oid), "1.2.840.113549.1.9.1");
+    }
+
+    #[test]
+    fn test_load_server_config_rejects_mismatched_key() {
+        assert!(load_server_config(&config()).is_ok());
+
+        let mut mismatched = config();
+        mismatched.key_file = fixture("client.key");
+        assert!(load_server_config(&mismatched).is_err());
+
+        let mut missing = config();
+        missing.client_ca_file = Some(fixture("missing.pem"));
+        assert!(matches!(
+            load_server_config(&missing),
+            Err(TlsError::Load { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reload_only_when_files_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_file = dir.path().join("tls.pem");
+        let key_file = dir.path().join("tls.key");
+        std::fs::copy(fixture("server.pem"), &cert_file).unwrap();
+        std::fs::copy(fixture("server.key"), &key_file).unwrap();
+        let server = TlsServer::new(TlsConfig {
+            cert_file: cert_file.clone(),
+            key_file: key_file.clone(),
+            ..config()
+        })
+        .unwrap();
+        let before = server.server_config.load_full();
+
+        assert_eq!(server.reload_if_changed(), Ok(false));
+
+        // A half-written renewal is rejected and the old certificate kept
+        std::fs::write(&key_file, "").unwrap();
+        assert!(server.reload_if_changed().is_err());
+        assert!(Arc::ptr_eq(&before, &server.server_config.load_full()));
+
+        std::fs::copy(fixture("server2.pem"), &cert_file).unwrap();
+        std::fs::copy(fixture("server2.key"), &key_file).unwrap();
+        assert_eq!(server.reload_if_changed(), Ok(true));
+        assert!(!Arc::ptr_eq(&before, &server.server_config.load_full()));
+    }
+}
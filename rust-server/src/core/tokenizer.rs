@@ -4,13 +4,38 @@
 //! LLM providers. It supports:
 //! - tiktoken (OpenAI models, default fallback)
 //! - HuggingFace tokenizer (Claude models with -bedrock/-vertex suffix)
+//! - Local `tokenizer.json` files for self-hosted models (Gemini, Llama, Qwen, ...)
 //!
 //! Claude tokenizer is embedded in the binary for offline usage.
+//!
+//! # Tokenizer registry
+//!
+//! A model resolves to a tokenizer in this order:
+//! 1. `tokenizer` on its `ModelMappingEntry`
+//! 2. `tokenizer` in its provider's `provider_params`
+//! 3. The embedded Claude tokenizer for Claude models
+//! 4. A model family tokenizer found in `TOKENIZER_DIR` (e.g. `qwen/tokenizer.json`
+//!    for `qwen2.5-72b-instruct`)
+//! 5. tiktoken
+//!
+//! A configured tokenizer name is either `tiktoken`, `claude`, a path to a
+//! `tokenizer.json` file (or a directory containing one), or a name looked up in
+//! `TOKENIZER_DIR` as `<name>.json` or `<name>/tokenizer.json`. Files are loaded
+//! on first use and cached until [`TokenizerRegistry::reload`].
 
+use arc_swap::ArcSwap;
+use dashmap::DashMap;
 use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokenizers::Tokenizer as HfTokenizer;
 use tracing::{debug, warn};
+use utoipa::ToSchema;
+
+use crate::api::models::match_model_pattern;
+use crate::core::config::{ModelMappingValue, ProviderConfig};
 
 /// Embedded Anthropic Claude tokenizer JSON (from litellm)
 /// This allows Claude tokenization without network access
@@ -19,17 +44,45 @@ const EMBEDDED_CLAUDE_TOKENIZER_JSON: &str = include_str!("tokenizers/anthropic_
 /// Special marker for embedded Claude tokenizer
 const CLAUDE_EMBEDDED_MARKER: &str = "__embedded_claude__";
 
+/// Upper bound on cached selections; wildcard mappings match unbounded model names.
+const MAX_CACHED_SELECTIONS: usize = 4096;
+
+/// Environment variable naming the directory of local tokenizers
+pub const TOKENIZER_DIR_ENV: &str = "TOKENIZER_DIR";
+
+/// Provider param naming the tokenizer for all of a provider's models
+pub const TOKENIZER_PARAM: &str = "tokenizer";
+
+/// Model families picked up from `TOKENIZER_DIR` without explicit configuration
+const MODEL_FAMILIES: &[&str] = &[
+    "gemini", "gemma", "llama", "qwen", "mistral", "deepseek", "glm", "kimi",
+];
+
 /// Tokenizer type enum
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
 pub enum TokenizerType {
     /// OpenAI tiktoken tokenizer
     Tiktoken,
-    /// HuggingFace tokenizer (embedded Claude)
+    /// HuggingFace tokenizer (embedded Claude or local tokenizer.json)
     HuggingFace,
 }
 
+/// Where a model's tokenizer choice came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerSource {
+    /// `tokenizer` on the model mapping entry
+    ModelMapping,
+    /// `tokenizer` in the provider params
+    ProviderParams,
+    /// Model family tokenizer found in `TOKENIZER_DIR`
+    ModelFamily,
+    /// Built-in rules (embedded Claude or tiktoken)
+    Default,
+}
+
 /// Tokenizer selection result
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TokenizerSelection {
     /// Type of tokenizer to use
     pub tokenizer_type: TokenizerType,
@@ -37,6 +90,8 @@ pub struct TokenizerSelection {
     pub hf_repo: Option<String>,
     /// Model name for tiktoken (if applicable)
     pub tiktoken_model: Option<String>,
+    /// Where the selection came from
+    pub source: TokenizerSource,
 }
 
 /// Cached embedded Claude tokenizer (loaded once on first use)
@@ -57,6 +112,240 @@ static EMBEDDED_CLAUDE_TOKENIZER: Lazy<Option<Arc<HfTokenizer>>> = Lazy::new(||
     }
 });
 
+/// Global registry, configured from `TOKENIZER_DIR` at startup
+static TOKENIZER_REGISTRY: Lazy<TokenizerRegistry> =
+    Lazy::new(|| TokenizerRegistry::new(std::env::var(TOKENIZER_DIR_ENV).ok().map(PathBuf::from)));
+
+/// Get the global tokenizer registry
+pub fn tokenizer_registry() -> &'static TokenizerRegistry {
+    &TOKENIZER_REGISTRY
+}
+
+/// Tokenizers configured for one provider's models
+#[derive(Debug, Default)]
+struct ProviderTokenizers {
+    model_mapping: HashMap<String, ModelMappingValue>,
+    provider_tokenizer: Option<String>,
+}
+
+/// Registry resolving models to tokenizers and caching loaded tokenizer files.
+pub struct TokenizerRegistry {
+    dir: Option<PathBuf>,
+    providers: ArcSwap<Vec<ProviderTokenizers>>,
+    selections: DashMap<String, TokenizerSelection>,
+    /// Loaded tokenizers by name; `None` caches a failed load
+    loaded: DashMap<String, Option<Arc<HfTokenizer>>>,
+}
+
+impl TokenizerRegistry {
+    /// Create a registry looking up tokenizer names in `dir`.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            providers: ArcSwap::from_pointee(Vec::new()),
+            selections: DashMap::new(),
+            loaded: DashMap::new(),
+        }
+    }
+
+    /// Directory searched for named tokenizers.
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Replace the per-model tokenizer configuration.
+    pub fn sync_from_providers(&self, providers: &[ProviderConfig]) {
+        let configured = providers
+            .iter()
+            .map(|p| ProviderTokenizers {
+                model_mapping: p.model_mapping.clone(),
+                provider_tokenizer: p
+                    .provider_params
+                    .get(TOKENIZER_PARAM)
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+            })
+            .collect();
+        self.providers.store(Arc::new(configured));
+        self.selections.clear();
+    }
+
+    /// Drop cached selections and loaded tokenizers so files are re-read.
+    ///
+    /// Returns the number of tokenizers that were loaded.
+    pub fn reload(&self) -> usize {
+        let count = self.loaded.iter().filter(|e| e.value().is_some()).count();
+        self.loaded.clear();
+        self.selections.clear();
+        count
+    }
+
+    /// Select the tokenizer for a model (request or mapped name).
+    ///
+    /// Only selections resolved through the configured model mappings are
+    /// cached, so arbitrary client model names cannot grow the cache.
+    pub fn select(&self, model: &str) -> TokenizerSelection {
+        if let Some(selection) = self.selections.get(model) {
+            return selection.clone();
+        }
+        match self.select_configured(model) {
+            Some(selection) => {
+                if self.selections.len() < MAX_CACHED_SELECTIONS {
+                    self.selections.insert(model.to_string(), selection.clone());
+                }
+                selection
+            }
+            None => self.select_default(model),
+        }
+    }
+
+    fn select_configured(&self, model: &str) -> Option<TokenizerSelection> {
+        let providers = self.providers.load();
+        providers.iter().find_map(|p| {
+            let entry = match_model_pattern(model, &p.model_mapping).or_else(|| {
+                p.model_mapping
+                    .values()
+                    .find(|v| v.mapped_model() == model)
+                    .cloned()
+            })?;
+            self.configured_selection(model, &entry, p.provider_tokenizer.as_deref())
+        })
+    }
+
+    /// Selection from a model mapping entry's or provider's tokenizer setting.
+    pub fn configured_selection(
+        &self,
+        model: &str,
+        entry: &ModelMappingValue,
+        provider_tokenizer: Option<&str>,
+    ) -> Option<TokenizerSelection> {
+        match entry.metadata().and_then(|m| m.tokenizer.as_deref()) {
+            Some(name) => Some(self.named_selection(model, name, TokenizerSource::ModelMapping)),
+            None => provider_tokenizer
+                .map(|name| self.named_selection(model, name, TokenizerSource::ProviderParams)),
+        }
+    }
+
+    fn named_selection(
+        &self,
+        model: &str,
+        name: &str,
+        source: TokenizerSource,
+    ) -> TokenizerSelection {
+        match name {
+            "tiktoken" => TokenizerSelection {
+                tokenizer_type: TokenizerType::Tiktoken,
+                hf_repo: None,
+                tiktoken_model: Some(normalize_tiktoken_model(model)),
+                source,
+            },
+            "claude" => TokenizerSelection {
+                tokenizer_type: TokenizerType::HuggingFace,
+                hf_repo: Some(CLAUDE_EMBEDDED_MARKER.to_string()),
+                tiktoken_model: None,
+                source,
+            },
+            _ => TokenizerSelection {
+                tokenizer_type: TokenizerType::HuggingFace,
+                hf_repo: Some(name.to_string()),
+                tiktoken_model: None,
+                source,
+            },
+        }
+    }
+
+    /// Selection from built-in rules and model families in the tokenizer directory.
+    pub fn select_default(&self, model: &str) -> TokenizerSelection {
+        let model_lower = model.to_lowercase();
+
+        // All Claude models use Anthropic's official tokenizer (embedded)
+        if model_lower.contains("claude") {
+            return TokenizerSelection {
+                tokenizer_type: TokenizerType::HuggingFace,
+                hf_repo: Some(CLAUDE_EMBEDDED_MARKER.to_string()),
+                tiktoken_model: None,
+                source: TokenizerSource::Default,
+            };
+        }
+
+        if let Some(family) = MODEL_FAMILIES
+            .iter()
+            .find(|family| model_lower.contains(*family) && self.resolve_path(family).is_some())
+        {
+            return TokenizerSelection {
+                tokenizer_type: TokenizerType::HuggingFace,
+                hf_repo: Some(family.to_string()),
+                tiktoken_model: None,
+                source: TokenizerSource::ModelFamily,
+            };
+        }
+
+        // Default to tiktoken for all other models (OpenAI, etc.)
+        TokenizerSelection {
+            tokenizer_type: TokenizerType::Tiktoken,
+            hf_repo: None,
+            tiktoken_model: Some(normalize_tiktoken_model(model)),
+            source: TokenizerSource::Default,
+        }
+    }
+
+    /// Locate the `tokenizer.json` for a tokenizer name.
+    pub fn resolve_path(&self, name: &str) -> Option<PathBuf> {
+        let as_path = Path::new(name);
+        let mut candidates = Vec::new();
+        if as_path.is_absolute() || name.contains('/') || name.ends_with(".json") {
+            candidates.push(as_path.to_path_buf());
+            candidates.push(as_path.join("tokenizer.json"));
+        } else if let Some(dir) = &self.dir {
+            candidates.push(dir.join(format!("{}.json", name)));
+            candidates.push(dir.join(name).join("tokenizer.json"));
+        }
+        candidates.into_iter().find(|p| p.is_file())
+    }
+
+    /// Get a tokenizer by name, loading it on first use.
+    pub fn load(&self, name: &str) -> Option<Arc<HfTokenizer>> {
+        if name == CLAUDE_EMBEDDED_MARKER {
+            return EMBEDDED_CLAUDE_TOKENIZER.clone();
+        }
+        if let Some(tokenizer) = self.loaded.get(name) {
+            return tokenizer.clone();
+        }
+        let tokenizer = match self.resolve_path(name) {
+            Some(path) => match HfTokenizer::from_file(&path) {
+                Ok(tokenizer) => {
+                    debug!(tokenizer = name, path = %path.display(), "Loaded local tokenizer");
+                    Some(Arc::new(tokenizer))
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to load tokenizer {} from {}: {}. Falling back to tiktoken.",
+                        name,
+                        path.display(),
+                        e
+                    );
+                    None
+                }
+            },
+            None => {
+                warn!("Tokenizer {} not found. Falling back to tiktoken.", name);
+                None
+            }
+        };
+        self.loaded.insert(name.to_string(), tokenizer.clone());
+        tokenizer
+    }
+
+    /// Whether a tokenizer is currently loaded in memory.
+    pub fn is_loaded(&self, name: &str) -> bool {
+        name == CLAUDE_EMBEDDED_MARKER
+            || self
+                .loaded
+                .get(name)
+                .is_some_and(|tokenizer| tokenizer.is_some())
+    }
+}
+
 /// Select the appropriate tokenizer for a given model
 ///
 /// # Arguments
@@ -67,25 +356,10 @@ static EMBEDDED_CLAUDE_TOKENIZER: Lazy<Option<Arc<HfTokenizer>>> = Lazy::new(||
 ///
 /// # Claude Model Handling
 /// - All Claude models (containing "claude") use embedded Claude tokenizer
+/// - Models with a configured tokenizer or a family tokenizer use it
 /// - All other models use tiktoken as fallback
 pub fn select_tokenizer(model: &str) -> TokenizerSelection {
-    let model_lower = model.to_lowercase();
-
-    // All Claude models use Anthropic's official tokenizer (embedded)
-    if model_lower.contains("claude") {
-        return TokenizerSelection {
-            tokenizer_type: TokenizerType::HuggingFace,
-            hf_repo: Some(CLAUDE_EMBEDDED_MARKER.to_string()),
-            tiktoken_model: None,
-        };
-    }
-
-    // Default to tiktoken for all other models (OpenAI, etc.)
-    TokenizerSelection {
-        tokenizer_type: TokenizerType::Tiktoken,
-        hf_repo: None,
-        tiktoken_model: Some(normalize_tiktoken_model(model)),
-    }
+    tokenizer_registry().select(model)
 }
 
 /// Normalize model name for tiktoken
@@ -100,23 +374,15 @@ fn normalize_tiktoken_model(model: &str) -> String {
     }
 }
 
-/// Get the embedded Claude tokenizer
+/// Get a HuggingFace tokenizer by name
 ///
 /// # Arguments
-/// * `repo` - The HuggingFace repository name (must be `__embedded_claude__`)
+/// * `repo` - `__embedded_claude__` or a tokenizer name known to the registry
 ///
 /// # Returns
 /// An `Option<Arc<HfTokenizer>>` if the tokenizer was loaded successfully
 pub fn get_hf_tokenizer(repo: &str) -> Option<Arc<HfTokenizer>> {
-    if repo == CLAUDE_EMBEDDED_MARKER {
-        EMBEDDED_CLAUDE_TOKENIZER.clone()
-    } else {
-        warn!(
-            "Unknown tokenizer repo: {}. Only embedded Claude tokenizer is supported.",
-            repo
-        );
-        None
-    }
+    tokenizer_registry().load(repo)
 }
 
 /// Count tokens using HuggingFace tokenizer
@@ -216,6 +482,105 @@ mod tests {
         assert_eq!(normalize_tiktoken_model("unknown"), "gpt-3.5-turbo");
     }
 
+    fn extended(tokenizer: &str) -> ModelMappingValue {
+        ModelMappingValue::Extended(crate::core::config::ModelMappingEntry {
+            mapped_model: "Qwen/Qwen2.5-72B-Instruct".to_string(),
+            tokenizer: Some(tokenizer.to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_registry_model_mapping_tokenizer() {
+        let registry = TokenizerRegistry::new(None);
+        let selection = registry
+            .configured_selection("qwen-72b", &extended("claude"), Some("tiktoken"))
+            .unwrap();
+        assert_eq!(selection.tokenizer_type, TokenizerType::HuggingFace);
+        assert_eq!(selection.hf_repo, Some("__embedded_claude__".to_string()));
+        assert_eq!(selection.source, TokenizerSource::ModelMapping);
+    }
+
+    #[test]
+    fn test_registry_provider_tokenizer() {
+        let registry = TokenizerRegistry::new(None);
+        let entry: ModelMappingValue = "Qwen/Qwen2.5-72B-Instruct".into();
+        let selection = registry
+            .configured_selection("qwen-72b", &entry, Some("qwen"))
+            .unwrap();
+        assert_eq!(selection.hf_repo, Some("qwen".to_string()));
+        assert_eq!(selection.source, TokenizerSource::ProviderParams);
+
+        assert!(registry
+            .configured_selection("qwen-72b", &entry, None)
+            .is_none());
+    }
+
+    #[test]
+    fn test_registry_model_family_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("qwen")).unwrap();
+        std::fs::write(dir.path().join("qwen").join("tokenizer.json"), "{}").unwrap();
+
+        let registry = TokenizerRegistry::new(Some(dir.path().to_path_buf()));
+        let selection = registry.select("qwen2.5-72b-instruct");
+        assert_eq!(selection.hf_repo, Some("qwen".to_string()));
+        assert_eq!(selection.source, TokenizerSource::ModelFamily);
+
+        let selection = registry.select("llama-3-70b");
+        assert_eq!(selection.tokenizer_type, TokenizerType::Tiktoken);
+        assert_eq!(selection.source, TokenizerSource::Default);
+    }
+
+    #[test]
+    fn test_registry_invalid_tokenizer_file_is_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.json"), "not json").unwrap();
+
+        let registry = TokenizerRegistry::new(Some(dir.path().to_path_buf()));
+        assert!(registry.resolve_path("broken").is_some());
+        assert!(registry.load("broken").is_none());
+        assert!(!registry.is_loaded("broken"));
+        assert!(registry.load("missing").is_none());
+        assert_eq!(registry.reload(), 0);
+    }
+
+    #[test]
+    fn test_registry_sync_from_providers() {
+        let registry = TokenizerRegistry::new(None);
+        let mut model_mapping = HashMap::new();
+        model_mapping.insert("qwen-72b".to_string(), extended("claude"));
+        let provider = ProviderConfig {
+            name: "vllm".to_string(),
+            api_base: "http://localhost:8000".to_string(),
+            api_key: "key".to_string(),
+            weight: 1,
+            model_mapping,
+            provider_type: "openai".to_string(),
+            provider_params: HashMap::new(),
+        };
+
+        assert_eq!(
+            registry.select("qwen-72b").tokenizer_type,
+            TokenizerType::Tiktoken
+        );
+        registry.sync_from_providers(&[provider]);
+        // Both the client-facing and the mapped model name resolve
+        assert_eq!(
+            registry.select("qwen-72b").hf_repo,
+            Some("__embedded_claude__".to_string())
+        );
+        assert_eq!(
+            registry.select("Qwen/Qwen2.5-72B-Instruct").hf_repo,
+            Some("__embedded_claude__".to_string())
+        );
+        assert_eq!(registry.selections.len(), 2);
+
+        // Unmapped models are resolved without being cached
+        registry.select("some-unknown-model");
+        assert_eq!(registry.selections.len(), 2);
+    }
+
     #[test]
     fn test_get_tokenizer_info() {
         let info = get_tokenizer_info("gpt-4");
@@ -21,6 +21,12 @@ use crate::core::config::CredentialConfig;
 ///     rate_limit: None,
 ///     enabled: true,
 ///     allowed_models: vec![],
+///     context_trimming: None,
+///     expires_at: None,
+///     previous_key: None,
+///     allowed_cidrs: vec![],
+///     pii_redaction: None,
+///     guardrails: vec![],
 /// });
 /// assert_eq!(get_key_name(&config), "my-key");
 ///
@@ -171,6 +177,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec![],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert_eq!(get_key_name(&config), "test-key");
     }
@@ -190,6 +202,12 @@ mod tests {
             rate_limit: None,
             enabled: true,
             allowed_models: vec![],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         });
         assert_eq!(get_key_name(&config), "");
     }
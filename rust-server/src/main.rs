@@ -5,6 +5,7 @@
 
 use anyhow::Result;
 use axum::{
+    extract::DefaultBodyLimit,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
@@ -12,17 +13,30 @@ use axum::{
 use chrono::Local;
 use llm_proxy_rust::{
     admin_router,
+    api::audio::AUDIO_UPLOAD_BODY_LIMIT,
+    api::images::IMAGE_EDIT_BODY_LIMIT,
     api::{
-        chat_completions_v2, claude_count_tokens, completions, completions_v2, count_tokens_v2,
-        gcp_vertex_proxy, list_model_info_v1, list_model_info_v2, list_models, list_models_v2,
-        messages_v2, metrics_handler, responses_v2, AdminState, AppState, ProxyState,
+        audio_speech, audio_transcriptions, audio_translations, chat_completions_v2,
+        chat_count_tokens_v2, claude_count_tokens, completions, completions_v2, count_tokens_v2,
+        gcp_vertex_proxy, image_edits, image_generations, list_model_info_v1, list_model_info_v2,
+        list_models, list_models_v2, messages_v2, metrics_handler, responses_v2, AdminState,
+        AppState, ProxyState,
     },
     combined_openapi,
     core::{
-        admin_logging_middleware, init_error_logger, init_jsonl_logger, init_langfuse_service,
-        init_metrics, init_request_logger, model_permission_middleware, request_id_middleware,
-        shutdown_request_logger, AppConfig, Database, DatabaseConfig, DynamicConfig,
-        MetricsMiddleware, RateLimiter, RuntimeConfig,
+        admin_logging_middleware,
+        credential_usage::{init_credential_usage_tracker, shutdown_credential_usage_tracker},
+        init_error_logger, init_jsonl_logger, init_langfuse_service, init_metrics,
+        init_request_logger,
+        jwt_auth::init_jwt_auth,
+        key_encryption::{encrypt_existing_from_env, ProviderKeyCipher, ENCRYPTION_KEYS_ENV},
+        middleware::{client_ip_middleware, ClientIpConfig},
+        model_permission_middleware,
+        provider_network::NetworkDefaults,
+        request_id_middleware, shutdown_request_logger,
+        tls::{init_tls, serve_tls},
+        AppConfig, Database, DatabaseConfig, DynamicConfig, MetricsMiddleware, RateLimiter,
+        RuntimeConfig,
     },
     services::ProviderService,
 };
@@ -124,7 +138,7 @@ async fn async_main() -> Result<()> {
     // Connect to database
     let db_config = DatabaseConfig::from_url(&db_url);
     tracing::info!("Connecting to database...");
-    let db = Database::connect(&db_config).await?;
+    let mut db = Database::connect(&db_config).await?;
     tracing::info!("Database connected successfully");
 
     // Check if migrations have been applied
@@ -134,6 +148,28 @@ async fn async_main() -> Result<()> {
         ));
     }
 
+    // Encrypt provider API keys at rest when master keys are configured
+    match ProviderKeyCipher::from_env()? {
+        Some(cipher) => {
+            tracing::info!(
+                primary_key_id = %cipher.primary_key_id(),
+                "Provider API key encryption enabled"
+            );
+            db = db.with_key_cipher(cipher);
+            // Opt-in: the Python server cannot read encrypted keys
+            if encrypt_existing_from_env() {
+                let encrypted = db.encrypt_plaintext_provider_keys().await?;
+                if encrypted > 0 {
+                    tracing::info!(count = encrypted, "Encrypted plaintext provider API keys");
+                }
+            }
+        }
+        None => tracing::warn!(
+            "{} not set, provider API keys are stored in plaintext",
+            ENCRYPTION_KEYS_ENV
+        ),
+    }
+
     let db = Arc::new(db);
 
     // Initialize error logger with database pool
@@ -142,6 +178,9 @@ async fn async_main() -> Result<()> {
     // Initialize request logger with database pool
     init_request_logger(db.pool().clone());
 
+    // Track credential last-used times in batches
+    init_credential_usage_tracker(db.pool().clone());
+
     // Load configuration from database (empty config if database is empty)
     let runtime_config = if db.is_empty().await? {
         tracing::info!("Database is empty. Server will start with no providers/credentials.");
@@ -149,6 +188,7 @@ async fn async_main() -> Result<()> {
         RuntimeConfig {
             providers: vec![],
             credentials: vec![],
+            guardrail_rules: vec![],
             version: 0,
             loaded_at: chrono::Utc::now(),
         }
@@ -174,18 +214,38 @@ async fn async_main() -> Result<()> {
     // Create HTTP client
     let http_client = create_http_client(&base_config);
 
+    // Accept JWTs from the identity provider when a JWKS is configured
+    init_jwt_auth(http_client.clone()).await?;
+
     // Create admin state
     let admin_state = Arc::new(AdminState {
         dynamic_config: dynamic_config.clone(),
         admin_key,
         http_client: http_client.clone(),
+        network_defaults: NetworkDefaults::from_config(&base_config),
     });
 
+    // Client address resolution for credential CIDR allowlists
+    let client_ip = ClientIpConfig::from_env().map_err(anyhow::Error::msg)?;
+
     // Build router
-    let app = build_router(dynamic_config, admin_state, base_config, http_client);
+    let app = build_router(
+        dynamic_config,
+        admin_state,
+        base_config,
+        http_client,
+        client_ip,
+    );
+
+    // Terminate TLS natively when certificate files are configured
+    let tls = init_tls()?;
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    tracing::info!("Starting LLM API Proxy on {}", addr);
+    tracing::info!(
+        "Starting LLM API Proxy on {} ({})",
+        addr,
+        if tls.is_some() { "HTTPS" } else { "HTTP" }
+    );
     tracing::info!("OpenAI API: /v1/chat/completions, /v1/completions, /v1/models");
     tracing::info!("Claude API: /v1/messages, /v1/messages/count_tokens");
     tracing::info!("Admin API: /admin/v1/*");
@@ -193,12 +253,21 @@ async fn async_main() -> Result<()> {
     tracing::info!("Metrics endpoint: /metrics");
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    match tls {
+        Some(tls) => serve_tls(listener, app, tls, shutdown_signal()).await?,
+        None => {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(shutdown_signal())
+            .await?
+        }
+    }
 
     // Flush pending logs before exit
     shutdown_request_logger().await;
+    shutdown_credential_usage_tracker().await;
 
     Ok(())
 }
@@ -233,6 +302,7 @@ fn build_router(
     admin_state: Arc<AdminState>,
     base_config: AppConfig,
     http_client: reqwest::Client,
+    client_ip: ClientIpConfig,
 ) -> Router {
     // Admin routes with logging middleware
     let admin_routes =
@@ -301,13 +371,35 @@ fn build_router(
         .route("/v2/completions", post(completions_v2))
         .route("/v2/messages", post(messages_v2))
         .route("/v2/messages/count_tokens", post(count_tokens_v2))
+        .route(
+            "/v2/chat/completions/count_tokens",
+            post(chat_count_tokens_v2),
+        )
         .route("/v2/responses", post(responses_v2))
         .route("/v2/models", get(list_models_v2))
         .route("/v2/model/info", get(list_model_info_v2))
         // v1 endpoints (uses transformer for GCP Vertex support)
         .route("/v1/messages", post(messages_v2))
         .route("/v1/chat/completions", post(chat_completions_v2))
+        .route(
+            "/v1/chat/completions/count_tokens",
+            post(chat_count_tokens_v2),
+        )
         .route("/v1/responses", post(responses_v2))
+        .route("/v1/images/generations", post(image_generations))
+        .route(
+            "/v1/images/edits",
+            post(image_edits).layer(DefaultBodyLimit::max(IMAGE_EDIT_BODY_LIMIT)),
+        )
+        .route(
+            "/v1/audio/transcriptions",
+            post(audio_transcriptions).layer(DefaultBodyLimit::max(AUDIO_UPLOAD_BODY_LIMIT)),
+        )
+        .route(
+            "/v1/audio/translations",
+            post(audio_translations).layer(DefaultBodyLimit::max(AUDIO_UPLOAD_BODY_LIMIT)),
+        )
+        .route("/v1/audio/speech", post(audio_speech))
         // Root API routes (map to v2 handlers)
         .route("/chat/completions", post(chat_completions_v2))
         .route("/messages", post(messages_v2))
@@ -339,6 +431,10 @@ fn build_router(
         // Placeholder for Claude Code telemetry (returns 200 to suppress 404 noise)
         .route("/api/event_logging/batch", post(event_logging_placeholder))
         .layer(axum::middleware::from_fn(request_id_middleware))
+        .layer(axum::middleware::from_fn_with_state(
+            client_ip,
+            client_ip_middleware,
+        ))
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
 }
@@ -364,18 +460,27 @@ fn convert_runtime_to_app_config(runtime: &RuntimeConfig, base: &AppConfig) -> A
     let credentials: Vec<CredentialConfig> = runtime
         .credentials
         .iter()
-        .map(|c| CredentialConfig {
-            credential_key: c.credential_key.clone(), // Use hash for comparison
-            name: c.name.clone(),
-            description: None,
-            rate_limit: c
-                .rate_limit
-                .map(|rps| llm_proxy_rust::core::config::RateLimitConfig {
-                    requests_per_second: rps as u32,
-                    burst_size: rps as u32,
-                }),
-            enabled: c.is_enabled,
-            allowed_models: c.allowed_models.clone(),
+        .map(|c| {
+            let allowed_cidrs = c.allowed_networks();
+            CredentialConfig {
+                credential_key: c.credential_key.clone(), // Use hash for comparison
+                name: c.name.clone(),
+                description: None,
+                rate_limit: c
+                    .rate_limit
+                    .map(|rps| llm_proxy_rust::core::config::RateLimitConfig {
+                        requests_per_second: rps as u32,
+                        burst_size: rps as u32,
+                    }),
+                enabled: c.is_enabled && allowed_cidrs.is_some(),
+                allowed_models: c.allowed_models.clone(),
+                context_trimming: c.context_trimming.clone(),
+                expires_at: c.expires_at,
+                previous_key: c.previous_key(),
+                allowed_cidrs: allowed_cidrs.unwrap_or_default(),
+                pii_redaction: c.pii_redaction.clone(),
+                guardrails: c.guardrails.clone(),
+            }
         })
         .collect();
 
@@ -385,23 +490,24 @@ fn convert_runtime_to_app_config(runtime: &RuntimeConfig, base: &AppConfig) -> A
         verify_ssl: base.verify_ssl,
         request_timeout_secs: base.request_timeout_secs,
         ttft_timeout_secs: base.ttft_timeout_secs,
+        stream_idle_timeout_secs: base.stream_idle_timeout_secs,
+        sse_keepalive_secs: base.sse_keepalive_secs,
         credentials,
         provider_suffix: base.provider_suffix.clone(),
         min_tokens_limit: base.min_tokens_limit,
         max_tokens_limit: base.max_tokens_limit,
+        guardrail_rules: runtime
+            .guardrail_rules
+            .iter()
+            .filter_map(|r| r.to_rule())
+            .collect(),
     }
 }
 
 /// Create HTTP client with connection pooling
 fn create_http_client(config: &AppConfig) -> reqwest::Client {
-    reqwest::Client::builder()
-        .danger_accept_invalid_certs(!config.verify_ssl)
-        .timeout(std::time::Duration::from_secs(config.request_timeout_secs))
-        .pool_max_idle_per_host(100)
-        .pool_idle_timeout(std::time::Duration::from_secs(90))
-        .tcp_keepalive(std::time::Duration::from_secs(60))
-        .http2_keep_alive_interval(std::time::Duration::from_secs(30))
-        .http2_keep_alive_timeout(std::time::Duration::from_secs(10))
+    NetworkDefaults::from_config(config)
+        .client_builder()
         .build()
         .expect("Failed to build HTTP client")
 }
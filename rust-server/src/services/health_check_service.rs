@@ -9,6 +9,7 @@ use crate::api::upstream::{
     build_gcp_vertex_url_with_actions, build_upstream_request, extract_error_message, UpstreamAuth,
 };
 use crate::core::database::{Database, ProviderEntity};
+use crate::core::provider_network::{provider_client, NetworkDefaults};
 use chrono::Utc;
 use reqwest::Client;
 use serde_json::json;
@@ -19,7 +20,8 @@ use tokio::time::{timeout, Duration};
 
 /// Service for checking provider health
 pub struct HealthCheckService {
-    client: Client,
+    /// Client for the checked provider, or why it cannot be built
+    client: std::result::Result<Client, String>,
     timeout_secs: u64,
 }
 
@@ -27,7 +29,24 @@ impl HealthCheckService {
     /// Create a new health check service
     pub fn new(client: Client, timeout_secs: u64) -> Self {
         Self {
-            client,
+            client: Ok(client),
+            timeout_secs,
+        }
+    }
+
+    /// Create a health check service for a provider, using the client its
+    /// `network` param configures like proxied requests do
+    ///
+    /// When that client cannot be built, every model is reported unhealthy
+    /// with the reason instead of being checked over `default`.
+    pub fn for_provider(
+        provider: &ProviderEntity,
+        default: &Client,
+        network_defaults: &NetworkDefaults,
+        timeout_secs: u64,
+    ) -> Self {
+        Self {
+            client: provider_client(&provider.provider_params, default, network_defaults),
             timeout_secs,
         }
     }
@@ -251,11 +270,23 @@ impl HealthCheckService {
     ///
     /// Model health status with test result
     async fn test_model(
-        client: &Client,
+        client: &std::result::Result<Client, String>,
         provider: &ProviderEntity,
         model: &str,
         timeout_secs: u64,
     ) -> ModelHealthStatus {
+        let client = match client {
+            Ok(client) => client,
+            Err(e) => {
+                return ModelHealthStatus {
+                    model: model.to_string(),
+                    status: HealthStatus::Unhealthy,
+                    response_time_ms: None,
+                    error: Some(format!("Provider HTTP client unavailable: {}", e)),
+                };
+            }
+        };
+
         // Get the actual model name from mapping
         let actual_model = provider
             .model_mapping
@@ -584,7 +615,8 @@ impl HealthCheckService {
 /// # Arguments
 ///
 /// * `db` - Database instance
-/// * `client` - HTTP client to use for health checks
+/// * `client` - HTTP client to use for providers without `network` params
+/// * `network_defaults` - Client settings the `network` params of providers override
 /// * `provider_ids` - Optional list of provider IDs to check (None = all enabled providers)
 /// * `models` - Optional list of models to test (None = default test models)
 /// * `timeout_secs` - Timeout for each model test
@@ -596,6 +628,7 @@ impl HealthCheckService {
 pub async fn check_providers_health(
     db: &Arc<Database>,
     client: &Client,
+    network_defaults: &NetworkDefaults,
     provider_ids: Option<Vec<i32>>,
     models: Option<Vec<String>>,
     timeout_secs: u64,
@@ -621,7 +654,8 @@ pub async fn check_providers_health(
     // Check all providers with controlled concurrency
     let mut tasks = Vec::new();
     for provider in providers {
-        let service_clone = HealthCheckService::new(client.clone(), timeout_secs);
+        let service_clone =
+            HealthCheckService::for_provider(&provider, client, network_defaults, timeout_secs);
         let models_clone = models.clone();
         let sem = semaphore.clone();
 
@@ -1160,4 +1194,25 @@ mod tests {
         assert!(model_names.contains(&"custom-model-a"));
         assert!(model_names.contains(&"custom-model-b"));
     }
+
+    #[tokio::test]
+    async fn test_unusable_network_settings_report_unhealthy() {
+        let mut provider = create_test_provider();
+        provider.provider_params = sqlx::types::Json(HashMap::from([(
+            "network".to_string(),
+            json!({"ca_bundle": "/nonexistent/ca.pem"}),
+        )]));
+        let defaults = NetworkDefaults {
+            verify_ssl: true,
+            request_timeout_secs: 10,
+        };
+        let service = HealthCheckService::for_provider(&provider, &Client::new(), &defaults, 1);
+
+        let result = service
+            .check_provider_health(&provider, Some(vec!["gpt-4".to_string()]))
+            .await;
+        assert_eq!(result.status, HealthStatus::Unhealthy);
+        let error = result.models[0].error.as_deref().unwrap();
+        assert!(error.contains("/nonexistent/ca.pem"), "{}", error);
+    }
 }
@@ -119,7 +119,8 @@ impl AdaptiveRoutingConfig {
 pub struct ProviderService {
     providers: Arc<Vec<Provider>>,
     weights: Arc<Vec<u32>>,
-    weighted_index: Arc<WeightedIndex<u32>>,
+    /// None when there are no providers to choose from
+    weighted_index: Option<Arc<WeightedIndex<u32>>>,
     runtime_states: Arc<DashMap<String, ProviderRuntimeState>>,
     adaptive_config: Arc<AdaptiveRoutingConfig>,
 }
@@ -156,10 +157,13 @@ impl ProviderService {
     ///     verify_ssl: true,
     ///     request_timeout_secs: 300,
     ///     ttft_timeout_secs: None,
+    ///     stream_idle_timeout_secs: None,
+    ///     sse_keepalive_secs: None,
     ///     credentials: vec![],
     ///     provider_suffix: None,
     ///     min_tokens_limit: 1,
     ///     max_tokens_limit: 128000,
+    ///     guardrail_rules: vec![],
     /// };
     /// let service = ProviderService::new(config);
     /// ```
@@ -183,7 +187,13 @@ impl ProviderService {
             .collect();
 
         let weights: Vec<u32> = providers.iter().map(|p| p.weight).collect();
-        let weighted_index = WeightedIndex::new(&weights).expect("Failed to create weighted index");
+        let weighted_index = if providers.is_empty() {
+            None
+        } else {
+            Some(Arc::new(
+                WeightedIndex::new(&weights).expect("Failed to create weighted index"),
+            ))
+        };
         let runtime_states = DashMap::new();
 
         for provider in &providers {
@@ -194,7 +204,7 @@ impl ProviderService {
         let service = Self {
             providers: Arc::new(providers),
             weights: Arc::new(weights),
-            weighted_index: Arc::new(weighted_index),
+            weighted_index,
             runtime_states: Arc::new(runtime_states),
             adaptive_config: Arc::new(adaptive_config),
         };
@@ -238,7 +248,10 @@ impl ProviderService {
         let mut rng = thread_rng();
 
         let Some(model_name) = model else {
-            let index = self.weighted_index.sample(&mut rng);
+            let Some(weighted_index) = &self.weighted_index else {
+                return Err("No provider configured".to_string());
+            };
+            let index = weighted_index.sample(&mut rng);
             return Ok(self.providers[index].clone());
         };
 
@@ -858,10 +871,13 @@ mod tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 1,
             max_tokens_limit: 128000,
+            guardrail_rules: vec![],
         }
     }
 
@@ -880,10 +896,13 @@ mod tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 1,
             max_tokens_limit: 128000,
+            guardrail_rules: vec![],
         }
     }
 
@@ -1249,10 +1268,13 @@ mod tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 1,
             max_tokens_limit: 128000,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -1303,10 +1325,13 @@ mod tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 1,
             max_tokens_limit: 128000,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -1344,10 +1369,13 @@ mod tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 1,
             max_tokens_limit: 128000,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -1391,10 +1419,13 @@ mod tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 1,
             max_tokens_limit: 128000,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -1456,10 +1487,13 @@ mod tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 1,
             max_tokens_limit: 128000,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
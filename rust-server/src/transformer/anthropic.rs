@@ -3,10 +3,11 @@
 //! Handles conversion between Anthropic Messages API format and
 //! the Unified Internal Format.
 
+use super::structured_output::{structured_output_tool, STRUCTURED_OUTPUT_TOOL_NAME};
 use super::{
-    ChunkType, Protocol, Result, Role, StopReason, Transformer, UnifiedContent, UnifiedMessage,
-    UnifiedParameters, UnifiedRequest, UnifiedResponse, UnifiedStreamChunk, UnifiedTool,
-    UnifiedToolCall, UnifiedUsage,
+    ChunkType, Protocol, ReasoningConfig, ReasoningEffort, Result, Role, StopReason, Transformer,
+    UnifiedContent, UnifiedMessage, UnifiedParameters, UnifiedRequest, UnifiedResponse,
+    UnifiedStreamChunk, UnifiedTool, UnifiedToolCall, UnifiedUsage,
 };
 use crate::core::AppError;
 use bytes::Bytes;
@@ -23,6 +24,9 @@ lazy_static! {
         Regex::new(r"^x-anthropic-billing-header:\s*").unwrap();
 }
 
+/// Smallest thinking budget the Messages API accepts.
+const MIN_THINKING_BUDGET: i32 = 1024;
+
 /// Strip x-anthropic-billing-header prefix from text if present.
 fn strip_billing_header(text: &str) -> String {
     BILLING_HEADER_REGEX.replace(text, "").to_string()
@@ -55,6 +59,12 @@ pub enum AnthropicContentBlock {
     Text { text: String },
     #[serde(rename = "image")]
     Image { source: AnthropicImageSource },
+    #[serde(rename = "document")]
+    Document {
+        source: AnthropicDocumentSource,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -85,6 +95,24 @@ pub struct AnthropicImageSource {
     pub data: String,
 }
 
+/// Anthropic document source.
+///
+/// `base64` and `text` sources carry `media_type` and `data`, `url` sources an
+/// `url` and `file` sources a `file_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnthropicDocumentSource {
+    #[serde(rename = "type")]
+    pub source_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+}
+
 /// Anthropic system prompt (can be string or array).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -316,6 +344,26 @@ impl AnthropicTransformer {
                     UnifiedContent::image_url(&source.data)
                 }
             }
+            AnthropicContentBlock::Document { source, title } => {
+                let data = match source.source_type.as_str() {
+                    "url" => source.url.clone(),
+                    "file" => source.file_id.clone(),
+                    _ => source.data.clone(),
+                };
+                let media_type = source.media_type.clone().unwrap_or_else(|| {
+                    match source.source_type.as_str() {
+                        "text" => "text/plain".to_string(),
+                        "url" | "file" => String::new(),
+                        _ => "application/pdf".to_string(),
+                    }
+                });
+                UnifiedContent::Document {
+                    source_type: source.source_type.clone(),
+                    media_type,
+                    data: data.unwrap_or_default(),
+                    title: title.clone(),
+                }
+            }
             AnthropicContentBlock::ToolUse { id, name, input } => {
                 UnifiedContent::tool_use(id, name, input.clone())
             }
@@ -350,6 +398,51 @@ impl AnthropicTransformer {
                     data: data.clone(),
                 },
             }),
+            UnifiedContent::Document {
+                source_type,
+                media_type,
+                data,
+                title,
+            } => {
+                let source = match source_type.as_str() {
+                    "url" => AnthropicDocumentSource {
+                        source_type: "url".to_string(),
+                        media_type: None,
+                        data: None,
+                        url: Some(data.clone()),
+                        file_id: None,
+                    },
+                    "file" => AnthropicDocumentSource {
+                        source_type: "file".to_string(),
+                        media_type: None,
+                        data: None,
+                        url: None,
+                        file_id: Some(data.clone()),
+                    },
+                    "text" => AnthropicDocumentSource {
+                        source_type: "text".to_string(),
+                        media_type: Some("text/plain".to_string()),
+                        data: Some(data.clone()),
+                        url: None,
+                        file_id: None,
+                    },
+                    _ => AnthropicDocumentSource {
+                        source_type: "base64".to_string(),
+                        media_type: Some(if media_type.is_empty() {
+                            "application/pdf".to_string()
+                        } else {
+                            media_type.clone()
+                        }),
+                        data: Some(data.clone()),
+                        url: None,
+                        file_id: None,
+                    },
+                };
+                Some(AnthropicContentBlock::Document {
+                    source,
+                    title: title.clone(),
+                })
+            }
             UnifiedContent::ToolUse { id, name, input } => Some(AnthropicContentBlock::ToolUse {
                 id: id.clone(),
                 name: name.clone(),
@@ -372,6 +465,41 @@ impl AnthropicTransformer {
         }
     }
 
+    /// Convert an Anthropic thinking config to the unified reasoning config.
+    ///
+    /// Returns `None` for thinking types without a unified equivalent.
+    fn thinking_to_reasoning(thinking: &AnthropicThinking) -> Option<ReasoningConfig> {
+        match thinking.thinking_type.as_str() {
+            "enabled" => Some(ReasoningConfig {
+                effort: None,
+                budget_tokens: thinking.budget_tokens,
+                include_thoughts: Some(true),
+            }),
+            "disabled" => Some(ReasoningConfig {
+                effort: Some(ReasoningEffort::None),
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// Convert the unified reasoning config to an Anthropic thinking config.
+    ///
+    /// Effort levels map to a budget, clamped to the 1024 token minimum.
+    fn reasoning_to_thinking(reasoning: &ReasoningConfig) -> Option<AnthropicThinking> {
+        if reasoning.is_disabled() {
+            return Some(AnthropicThinking {
+                thinking_type: "disabled".to_string(),
+                budget_tokens: None,
+            });
+        }
+        let budget = reasoning.resolved_budget()?;
+        Some(AnthropicThinking {
+            thinking_type: "enabled".to_string(),
+            budget_tokens: Some(budget.max(MIN_THINKING_BUDGET)),
+        })
+    }
+
     /// Convert Anthropic message to unified message.
     fn message_to_unified(msg: &AnthropicMessage) -> UnifiedMessage {
         let role = msg.role.parse().unwrap_or(Role::User);
@@ -737,7 +865,11 @@ impl Transformer for AnthropicTransformer {
 
         // Build parameters
         let mut extra = HashMap::new();
-        if let Some(thinking) = request.thinking {
+        let reasoning = request
+            .thinking
+            .as_ref()
+            .and_then(Self::thinking_to_reasoning);
+        if let (None, Some(thinking)) = (&reasoning, request.thinking) {
             extra.insert("thinking".to_string(), json!(thinking));
         }
 
@@ -748,6 +880,9 @@ impl Transformer for AnthropicTransformer {
             top_k: request.top_k,
             stop_sequences: request.stop_sequences,
             stream: request.stream,
+            response_format: None,
+            n: None,
+            reasoning,
             extra,
         };
 
@@ -841,13 +976,56 @@ impl Transformer for AnthropicTransformer {
         // Extract thinking config
         let thinking = unified
             .parameters
-            .extra
-            .get("thinking")
-            .and_then(|v| serde_json::from_value::<AnthropicThinking>(v.clone()).ok());
+            .reasoning
+            .as_ref()
+            .and_then(Self::reasoning_to_thinking)
+            .or_else(|| {
+                unified
+                    .parameters
+                    .extra
+                    .get("thinking")
+                    .and_then(|v| serde_json::from_value::<AnthropicThinking>(v.clone()).ok())
+            });
+
+        // Emulate response_format with a dedicated tool. It is forced only when the
+        // client has no tools of its own; extended thinking does not allow forcing.
+        let mut tools = tools;
+        let mut tool_choice = unified.tool_choice.clone();
+        if let Some(tool) = unified
+            .parameters
+            .response_format
+            .as_ref()
+            .and_then(structured_output_tool)
+        {
+            tools.get_or_insert_with(Vec::new).push(AnthropicTool {
+                name: tool.name,
+                description: tool.description,
+                input_schema: tool.input_schema,
+            });
+            if unified.tools.is_empty() && tool_choice.is_none() {
+                let thinking_enabled = thinking
+                    .as_ref()
+                    .map(|t| t.thinking_type == "enabled")
+                    .unwrap_or(false);
+                tool_choice = Some(if thinking_enabled {
+                    json!({"type": "auto"})
+                } else {
+                    json!({"type": "tool", "name": STRUCTURED_OUTPUT_TOOL_NAME})
+                });
+            }
+        }
+
+        // The thinking budget counts against max_tokens, which must stay above it
+        let mut max_tokens = unified.parameters.max_tokens.unwrap_or(4096);
+        if let Some(budget) = thinking.as_ref().and_then(|t| t.budget_tokens) {
+            if budget >= max_tokens {
+                max_tokens += budget;
+            }
+        }
 
         let mut request = json!({
             "model": unified.model,
-            "max_tokens": unified.parameters.max_tokens.unwrap_or(4096),
+            "max_tokens": max_tokens,
             "messages": messages,
         });
 
@@ -872,7 +1050,7 @@ impl Transformer for AnthropicTransformer {
         if let Some(ref tools) = tools {
             request["tools"] = json!(tools);
         }
-        if let Some(ref tool_choice) = unified.tool_choice {
+        if let Some(ref tool_choice) = tool_choice {
             request["tool_choice"] = tool_choice.clone();
         }
         if let Some(ref thinking) = thinking {
@@ -922,6 +1100,7 @@ impl Transformer for AnthropicTransformer {
             output_tokens: response.usage.output_tokens,
             cache_read_tokens: response.usage.cache_read_input_tokens,
             cache_write_tokens: response.usage.cache_creation_input_tokens,
+            reasoning_tokens: None,
         };
 
         Ok(UnifiedResponse {
@@ -931,6 +1110,7 @@ impl Transformer for AnthropicTransformer {
             stop_reason,
             usage,
             tool_calls,
+            additional_choices: vec![],
         })
     }
 
@@ -1016,8 +1196,10 @@ impl Transformer for AnthropicTransformer {
                                 output_tokens: message.usage.output_tokens,
                                 cache_read_tokens: message.usage.cache_read_input_tokens,
                                 cache_write_tokens: message.usage.cache_creation_input_tokens,
+                                reasoning_tokens: None,
                             },
                             tool_calls: vec![],
+                            additional_choices: vec![],
                         };
                         chunks.push(UnifiedStreamChunk::message_start(unified_response));
                     }
@@ -1064,6 +1246,7 @@ impl Transformer for AnthropicTransformer {
                             output_tokens: usage.output_tokens,
                             cache_read_tokens: usage.cache_read_input_tokens,
                             cache_write_tokens: usage.cache_creation_input_tokens,
+                            reasoning_tokens: None,
                         };
                         chunks.push(UnifiedStreamChunk::message_delta(
                             stop_reason,
@@ -1225,6 +1408,7 @@ impl Transformer for AnthropicTransformer {
                                             t,
                                             Some("text")
                                                 | Some("image")
+                                                | Some("document")
                                                 | Some("tool_use")
                                                 | Some("tool_result")
                                         )
@@ -1266,6 +1450,89 @@ mod tests {
         assert_eq!(transformer.protocol(), Protocol::Anthropic);
     }
 
+    #[test]
+    fn test_thinking_maps_to_reasoning() {
+        let transformer = AnthropicTransformer::new();
+        let raw = json!({
+            "model": "claude-3",
+            "max_tokens": 1024,
+            "thinking": {"type": "enabled", "budget_tokens": 4000},
+            "messages": [{"role": "user", "content": "Hi"}]
+        });
+
+        let mut unified = transformer.transform_request_out(raw).unwrap();
+        let reasoning = unified.parameters.reasoning.clone().unwrap();
+        assert_eq!(reasoning.budget_tokens, Some(4000));
+        assert_eq!(reasoning.include_thoughts, Some(true));
+        assert!(!unified.parameters.extra.contains_key("thinking"));
+
+        // Budget above max_tokens raises max_tokens
+        let back = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(back["thinking"]["budget_tokens"], 4000);
+        assert_eq!(back["max_tokens"], 5024);
+
+        // Effort-only configs get a budget, clamped to the API minimum
+        unified.parameters.reasoning = Some(ReasoningConfig {
+            effort: Some(ReasoningEffort::Medium),
+            ..Default::default()
+        });
+        let back = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(back["thinking"]["type"], "enabled");
+        assert_eq!(back["thinking"]["budget_tokens"], 8192);
+
+        unified.parameters.reasoning = Some(ReasoningConfig {
+            effort: Some(ReasoningEffort::None),
+            ..Default::default()
+        });
+        let back = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(back["thinking"], json!({"type": "disabled"}));
+    }
+
+    #[test]
+    fn test_document_blocks_roundtrip() {
+        let transformer = AnthropicTransformer::new();
+        let raw = json!({
+            "model": "claude-3",
+            "max_tokens": 1024,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0="}, "title": "report.pdf"},
+                    {"type": "document", "source": {"type": "text", "media_type": "text/plain", "data": "plain notes"}},
+                    {"type": "document", "source": {"type": "url", "url": "https://example.com/a.pdf"}},
+                    {"type": "text", "text": "Summarize"}
+                ]
+            }]
+        });
+
+        let unified = transformer.transform_request_out(raw).unwrap();
+        let content = &unified.messages[0].content;
+        assert!(matches!(
+            &content[0],
+            UnifiedContent::Document { source_type, media_type, data, title }
+                if source_type == "base64" && media_type == "application/pdf"
+                    && data == "JVBERi0=" && title.as_deref() == Some("report.pdf")
+        ));
+        assert!(matches!(
+            &content[1],
+            UnifiedContent::Document { source_type, data, .. } if source_type == "text" && data == "plain notes"
+        ));
+        assert!(matches!(
+            &content[2],
+            UnifiedContent::Document { source_type, data, .. }
+                if source_type == "url" && data == "https://example.com/a.pdf"
+        ));
+
+        let back = transformer.transform_request_in(&unified).unwrap();
+        let blocks = &back["messages"][0]["content"];
+        assert_eq!(blocks[0]["type"], "document");
+        assert_eq!(blocks[0]["source"]["media_type"], "application/pdf");
+        assert_eq!(blocks[0]["title"], "report.pdf");
+        assert_eq!(blocks[1]["source"]["type"], "text");
+        assert_eq!(blocks[2]["source"]["url"], "https://example.com/a.pdf");
+        assert!(blocks[2]["source"].get("data").is_none());
+    }
+
     #[test]
     fn test_transform_request_out() {
         let transformer = AnthropicTransformer::new();
@@ -1299,6 +1566,54 @@ mod tests {
         assert!(raw["system"].is_string());
     }
 
+    #[test]
+    fn test_response_format_becomes_forced_tool() {
+        let transformer = AnthropicTransformer::new();
+        let mut unified = UnifiedRequest::new("claude-3-opus", vec![UnifiedMessage::user("Hi")]);
+        unified.parameters.response_format = Some(crate::transformer::ResponseFormat::JsonSchema {
+            name: "person".to_string(),
+            description: None,
+            schema: json!({"type": "object", "properties": {"name": {"type": "string"}}}),
+            strict: None,
+        });
+
+        let raw = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(raw["tools"][0]["name"], STRUCTURED_OUTPUT_TOOL_NAME);
+        assert_eq!(
+            raw["tools"][0]["input_schema"]["properties"]["name"]["type"],
+            "string"
+        );
+        assert_eq!(
+            raw["tool_choice"],
+            json!({"type": "tool", "name": STRUCTURED_OUTPUT_TOOL_NAME})
+        );
+
+        let mut response = transformer
+            .transform_response_in(
+                json!({
+                    "id": "msg_1",
+                    "type": "message",
+                    "role": "assistant",
+                    "model": "claude-3-opus",
+                    "content": [{
+                        "type": "tool_use",
+                        "id": "toolu_1",
+                        "name": STRUCTURED_OUTPUT_TOOL_NAME,
+                        "input": {"name": "Ada"}
+                    }],
+                    "stop_reason": "tool_use",
+                    "usage": {"input_tokens": 5, "output_tokens": 7}
+                }),
+                "claude-3-opus",
+            )
+            .unwrap();
+        assert_eq!(response.tool_calls.len(), 1);
+        crate::transformer::structured_output::unwrap_structured_output_tool(&mut response);
+        assert!(response.tool_calls.is_empty());
+        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(response.text_content(), r#"{"name":"Ada"}"#);
+    }
+
     #[test]
     fn test_transform_response_in() {
         let transformer = AnthropicTransformer::new();
@@ -0,0 +1,353 @@
+//! Emulation of multi-choice (`n > 1`) requests for providers that return a
+//! single completion per call.
+//!
+//! The proxy sends one upstream request per requested choice. Non-streaming
+//! responses are merged with [`merge_choices`]; streams are interleaved through
+//! [`MultiChoiceStreamState`], which tags every chunk with its choice index and
+//! reports the summed usage once all choices have finished.
+
+use super::guardrails::Guardrails;
+use super::pii_redaction::PiiRedactor;
+use super::stream::CrossProtocolStreamState;
+use super::tool_arguments::ToolArgumentChecker;
+use super::tool_normalizer::ToolNormalizer;
+use super::unified::{ChunkType, UnifiedChoice, UnifiedResponse, UnifiedStreamChunk, UnifiedUsage};
+
+/// Upper bound on emulated choices, i.e. parallel upstream calls per request.
+pub const MAX_EMULATED_CHOICES: u32 = 8;
+
+/// Add `other` to `total`, keeping cache and reasoning counters only when reported.
+fn add_usage(total: &mut UnifiedUsage, other: &UnifiedUsage) {
+    total.input_tokens += other.input_tokens;
+    total.output_tokens += other.output_tokens;
+    if let Some(read) = other.cache_read_tokens {
+        *total.cache_read_tokens.get_or_insert(0) += read;
+    }
+    if let Some(write) = other.cache_write_tokens {
+        *total.cache_write_tokens.get_or_insert(0) += write;
+    }
+    if let Some(reasoning) = other.reasoning_tokens {
+        *total.reasoning_tokens.get_or_insert(0) += reasoning;
+    }
+}
+
+/// Merge single-choice responses into one response with indexed choices.
+///
+/// The first response provides the id and the first choice; usage is summed
+/// across all responses since every emulated choice is a billed call.
+pub fn merge_choices(responses: Vec<UnifiedResponse>) -> Option<UnifiedResponse> {
+    let mut responses = responses.into_iter();
+    let mut merged = responses.next()?;
+    for response in responses {
+        add_usage(&mut merged.usage, &response.usage);
+        merged.additional_choices.push(UnifiedChoice {
+            content: response.content,
+            stop_reason: response.stop_reason,
+            tool_calls: response.tool_calls,
+        });
+        merged
+            .additional_choices
+            .extend(response.additional_choices);
+    }
+    Some(merged)
+}
+
+/// Stream state for several emulated choices sharing one client stream.
+///
+/// Each choice keeps its own [`CrossProtocolStreamState`]. Message-level
+/// events are emitted once for the whole stream: the first `message_start`,
+/// and a final usage-only `message_delta` plus `message_stop` from
+/// [`finalize`](Self::finalize).
+pub struct MultiChoiceStreamState {
+    lanes: Vec<CrossProtocolStreamState>,
+    lane_usage: Vec<Option<UnifiedUsage>>,
+    finished: Vec<bool>,
+    message_started: bool,
+    usage: UnifiedUsage,
+}
+
+impl MultiChoiceStreamState {
+    /// Create state for `choices` streams of the same model.
+    pub fn new(model: &str, choices: usize, input_tokens: Option<usize>) -> Self {
+        Self {
+            lanes: (0..choices)
+                .map(|_| CrossProtocolStreamState::with_input_tokens(model, input_tokens))
+                .collect(),
+            lane_usage: vec![None; choices],
+            finished: vec![false; choices],
+            message_started: false,
+            usage: UnifiedUsage::default(),
+        }
+    }
+
+    /// Parse prompt-emulated tool calls out of every choice's text.
+    pub fn with_tool_emulation(mut self) -> Self {
+        self.lanes = self
+            .lanes
+            .into_iter()
+            .map(CrossProtocolStreamState::with_tool_emulation)
+            .collect();
+        self
+    }
+
+    /// Re-emit the structured output tool as text in every choice.
+    pub fn with_structured_output(mut self) -> Self {
+        self.lanes = self
+            .lanes
+            .into_iter()
+            .map(CrossProtocolStreamState::with_structured_output)
+            .collect();
+        self
+    }
+
+    /// Report every choice's tool argument outcomes through the given checker.
+    pub fn with_tool_arguments(mut self, checker: &ToolArgumentChecker) -> Self {
+        self.lanes = self
+            .lanes
+            .into_iter()
+            .map(|lane| lane.with_tool_arguments(checker.clone()))
+            .collect();
+        self
+    }
+
+    /// Rename every choice's tool calls back to the client's tool names.
+    pub fn with_tool_normalizer(mut self, normalizer: &ToolNormalizer) -> Self {
+        self.lanes = self
+            .lanes
+            .into_iter()
+            .map(|lane| lane.with_tool_normalizer(normalizer.clone()))
+            .collect();
+        self
+    }
+
+    /// Restore values redacted by `redactor` in every choice's deltas.
+    pub fn with_pii_restorer(mut self, redactor: &PiiRedactor) -> Self {
+        self.lanes = self
+            .lanes
+            .into_iter()
+            .map(|lane| lane.with_pii_restorer(redactor.clone()))
+            .collect();
+        self
+    }
+
+    /// Apply the output rules of `guardrails` to every choice's deltas.
+    pub fn with_guardrails(mut self, guardrails: &Guardrails) -> Self {
+        self.lanes = self
+            .lanes
+            .into_iter()
+            .map(|lane| lane.with_guardrails(guardrails.clone()))
+            .collect();
+        self
+    }
+
+    /// Whether a guardrail cut any choice's stream off.
+    pub fn guardrail_blocked(&self) -> bool {
+        self.lanes
+            .iter()
+            .any(CrossProtocolStreamState::guardrail_blocked)
+    }
+
+    /// Number of choices being streamed.
+    pub fn choices(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// Process unified chunks of one choice.
+    pub fn process_chunks(
+        &mut self,
+        choice: usize,
+        chunks: Vec<UnifiedStreamChunk>,
+    ) -> Vec<UnifiedStreamChunk> {
+        let processed = self.lanes[choice].process_chunks(chunks);
+        self.relabel(choice, processed)
+    }
+
+    /// Close the stream of one choice once its upstream response ended.
+    pub fn finish_choice(&mut self, choice: usize) -> Vec<UnifiedStreamChunk> {
+        if self.finished[choice] {
+            return vec![];
+        }
+        let closing = self.lanes[choice].finalize();
+        let output = self.relabel(choice, closing);
+        self.finished[choice] = true;
+
+        let usage = self.lane_usage[choice]
+            .take()
+            .or_else(|| self.lanes[choice].get_final_usage(None))
+            .unwrap_or_default();
+        add_usage(&mut self.usage, &usage);
+        output
+    }
+
+    /// Whether every choice has finished.
+    pub fn is_finished(&self) -> bool {
+        self.finished.iter().all(|f| *f)
+    }
+
+    /// Usage summed over the finished choices.
+    pub fn usage(&self) -> &UnifiedUsage {
+        &self.usage
+    }
+
+    /// Close all remaining choices and emit the stream-level closing events.
+    pub fn finalize(&mut self) -> Vec<UnifiedStreamChunk> {
+        let mut output = Vec::new();
+        for choice in 0..self.lanes.len() {
+            output.extend(self.finish_choice(choice));
+        }
+        if self.message_started {
+            let mut usage_delta =
+                UnifiedStreamChunk::message_delta(super::StopReason::EndTurn, self.usage.clone());
+            usage_delta.stop_reason = None;
+            output.push(usage_delta);
+        }
+        output.push(UnifiedStreamChunk::message_stop());
+        output
+    }
+
+    fn relabel(
+        &mut self,
+        choice: usize,
+        chunks: Vec<UnifiedStreamChunk>,
+    ) -> Vec<UnifiedStreamChunk> {
+        let mut output = Vec::with_capacity(chunks.len());
+        for mut chunk in chunks {
+            chunk.choice_index = choice;
+            match chunk.chunk_type {
+                ChunkType::MessageStart => {
+                    if self.message_started {
+                        continue;
+                    }
+                    self.message_started = true;
+                }
+                ChunkType::Ping if choice != 0 => continue,
+                ChunkType::MessageDelta => {
+                    if let Some(usage) = chunk.usage.take() {
+                        if usage.input_tokens > 0 || usage.output_tokens > 0 {
+                            self.lane_usage[choice] = Some(usage);
+                        }
+                    }
+                    if chunk.stop_reason.is_none() {
+                        continue;
+                    }
+                }
+                ChunkType::MessageStop => continue,
+                _ => {}
+            }
+            output.push(chunk);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::unified::{StopReason, UnifiedContent};
+
+    #[test]
+    fn test_merge_choices_sums_usage() {
+        let responses = vec![
+            UnifiedResponse::text("msg_1", "claude", "one", UnifiedUsage::new(10, 3)),
+            UnifiedResponse::text("msg_2", "claude", "two", UnifiedUsage::new(10, 4)),
+            UnifiedResponse::text("msg_3", "claude", "three", UnifiedUsage::new(10, 5)),
+        ];
+
+        let merged = merge_choices(responses).unwrap();
+
+        assert_eq!(merged.id, "msg_1");
+        assert_eq!(merged.text_content(), "one");
+        assert_eq!(merged.additional_choices.len(), 2);
+        assert!(matches!(
+            &merged.additional_choices[1].content[..],
+            [UnifiedContent::Text { text }] if text == "three"
+        ));
+        assert_eq!(merged.usage.input_tokens, 30);
+        assert_eq!(merged.usage.output_tokens, 12);
+        assert!(merge_choices(vec![]).is_none());
+    }
+
+    #[test]
+    fn test_multi_choice_stream_interleaves_choices() {
+        let mut state = MultiChoiceStreamState::new("claude", 2, None);
+        let start = |id: &str| {
+            UnifiedStreamChunk::message_start(UnifiedResponse::new(
+                id,
+                "claude",
+                vec![],
+                None,
+                UnifiedUsage::default(),
+            ))
+        };
+
+        let mut output = state.process_chunks(0, vec![start("msg_1")]);
+        output.extend(state.process_chunks(1, vec![start("msg_2")]));
+        output.extend(state.process_chunks(
+            1,
+            vec![UnifiedStreamChunk::content_block_delta(
+                0,
+                UnifiedContent::text("b"),
+            )],
+        ));
+        output.extend(state.process_chunks(
+            0,
+            vec![
+                UnifiedStreamChunk::content_block_delta(0, UnifiedContent::text("a")),
+                UnifiedStreamChunk::message_delta(StopReason::EndTurn, UnifiedUsage::new(5, 2)),
+                UnifiedStreamChunk::message_stop(),
+            ],
+        ));
+        output.extend(state.finish_choice(0));
+        assert!(!state.is_finished());
+        output.extend(state.process_chunks(
+            1,
+            vec![UnifiedStreamChunk::message_delta(
+                StopReason::MaxTokens,
+                UnifiedUsage::new(5, 3),
+            )],
+        ));
+        output.extend(state.finalize());
+        assert!(state.is_finished());
+
+        let starts = output
+            .iter()
+            .filter(|c| c.chunk_type == ChunkType::MessageStart)
+            .count();
+        assert_eq!(starts, 1);
+
+        let text: Vec<(usize, String)> = output
+            .iter()
+            .filter_map(|c| match &c.delta {
+                Some(UnifiedContent::Text { text }) => Some((c.choice_index, text.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, vec![(1, "b".to_string()), (0, "a".to_string())]);
+
+        let finishes: Vec<(usize, Option<StopReason>)> = output
+            .iter()
+            .filter(|c| c.chunk_type == ChunkType::MessageDelta)
+            .map(|c| (c.choice_index, c.stop_reason.clone()))
+            .collect();
+        assert_eq!(
+            finishes,
+            vec![
+                (0, Some(StopReason::EndTurn)),
+                (1, Some(StopReason::MaxTokens)),
+                (0, None)
+            ]
+        );
+
+        let last_delta = output
+            .iter()
+            .rev()
+            .find(|c| c.chunk_type == ChunkType::MessageDelta)
+            .unwrap();
+        let usage = last_delta.usage.as_ref().unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (10, 5));
+        assert_eq!(
+            output.last().map(|c| c.chunk_type.clone()),
+            Some(ChunkType::MessageStop)
+        );
+    }
+}
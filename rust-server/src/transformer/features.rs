@@ -428,6 +428,7 @@ mod tests {
         let mut chunk = UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockDelta,
             index: 0,
+            choice_index: 0,
             delta: Some(UnifiedContent::thinking("thinking...", None)),
             usage: None,
             stop_reason: None,
@@ -448,6 +449,7 @@ mod tests {
         let mut chunk = UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockDelta,
             index: 0,
+            choice_index: 0,
             delta: Some(UnifiedContent::thinking("thinking...", None)),
             usage: None,
             stop_reason: None,
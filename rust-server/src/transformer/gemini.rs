@@ -4,10 +4,12 @@
 //! and the Unified Internal Format.
 
 use super::{
-    ChunkType, Protocol, Result, Role, StopReason, Transformer, UnifiedContent, UnifiedMessage,
-    UnifiedParameters, UnifiedRequest, UnifiedResponse, UnifiedStreamChunk, UnifiedTool,
-    UnifiedToolCall, UnifiedUsage,
+    ChunkType, Protocol, ReasoningConfig, ReasoningEffort, ResponseFormat, Result, Role,
+    StopReason, Transformer, UnifiedChoice, UnifiedContent, UnifiedMessage, UnifiedParameters,
+    UnifiedRequest, UnifiedResponse, UnifiedStreamChunk, UnifiedTool, UnifiedToolCall,
+    UnifiedUsage,
 };
+use crate::api::gemini3::{is_gemini3_model, map_reasoning_effort_to_thinking_level};
 use crate::core::AppError;
 use bytes::Bytes;
 use serde_json::{json, Value};
@@ -83,7 +85,11 @@ impl GeminiTransformer {
                 .and_then(|m| m.as_str())
                 .unwrap_or("");
             let data = inline.get("data").and_then(|d| d.as_str()).unwrap_or("");
-            result.push(UnifiedContent::image_base64(mime, data));
+            if Self::is_document_mime(mime) {
+                result.push(UnifiedContent::document_base64(mime, data));
+            } else {
+                result.push(UnifiedContent::image_base64(mime, data));
+            }
             if let Some(sig) = part.get("thoughtSignature").and_then(|s| s.as_str()) {
                 result.push(UnifiedContent::thinking("", Some(sig.to_string())));
             }
@@ -94,7 +100,21 @@ impl GeminiTransformer {
                 .get("fileUri")
                 .and_then(|u| u.as_str())
                 .unwrap_or("");
-            result.push(UnifiedContent::image_url(uri));
+            let mime = file_data
+                .get("mimeType")
+                .and_then(|m| m.as_str())
+                .or_else(|| Self::mime_type_from_url(uri))
+                .unwrap_or("");
+            if Self::is_document_mime(mime) {
+                result.push(UnifiedContent::Document {
+                    source_type: "url".to_string(),
+                    media_type: mime.to_string(),
+                    data: uri.to_string(),
+                    title: None,
+                });
+            } else {
+                result.push(UnifiedContent::image_url(uri));
+            }
             if let Some(sig) = part.get("thoughtSignature").and_then(|s| s.as_str()) {
                 result.push(UnifiedContent::thinking("", Some(sig.to_string())));
             }
@@ -159,6 +179,23 @@ impl GeminiTransformer {
                     Some(json!({"inlineData": {"mimeType": media_type, "data": data}}))
                 }
             }
+            UnifiedContent::Document {
+                source_type,
+                media_type,
+                data,
+                ..
+            } => match source_type.as_str() {
+                "text" => Some(json!({"text": data})),
+                "url" | "file" => {
+                    let mime = if media_type.is_empty() {
+                        Self::mime_type_from_url(data).unwrap_or("application/pdf")
+                    } else {
+                        media_type.as_str()
+                    };
+                    Some(json!({"fileData": {"mimeType": mime, "fileUri": data}}))
+                }
+                _ => Some(json!({"inlineData": {"mimeType": media_type, "data": data}})),
+            },
             _ => None,
         }
     }
@@ -421,6 +458,89 @@ impl GeminiTransformer {
         Value::Object(result)
     }
 
+    /// Map `responseMimeType` / `responseSchema` (or `responseJsonSchema`) to the UIF.
+    /// Convert one response candidate to a unified choice.
+    fn candidate_to_unified(candidate: &Value) -> UnifiedChoice {
+        let parts = candidate
+            .get("content")
+            .and_then(|c| c.get("parts"))
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        let mut content = Vec::new();
+        let mut tool_calls = Vec::new();
+
+        for part in &parts {
+            for uc in Self::part_to_unified_vec(part) {
+                if let UnifiedContent::ToolUse { id, name, input } = &uc {
+                    tool_calls.push(UnifiedToolCall {
+                        id: id.clone(),
+                        name: name.clone(),
+                        arguments: input.clone(),
+                    });
+                }
+                content.push(uc);
+            }
+        }
+
+        let stop_reason = candidate
+            .get("finishReason")
+            .and_then(|r| r.as_str())
+            .map(Self::finish_reason_to_unified);
+
+        UnifiedChoice {
+            content,
+            stop_reason,
+            tool_calls,
+        }
+    }
+
+    /// Build one response candidate from unified content, tool calls and stop reason.
+    fn unified_choice_to_candidate(
+        index: usize,
+        content: &[UnifiedContent],
+        tool_calls: &[UnifiedToolCall],
+        stop_reason: Option<&StopReason>,
+    ) -> Value {
+        let mut parts = Self::unified_contents_to_parts(content);
+        // Append tool_calls not in content
+        for tc in tool_calls {
+            parts.push(json!({"functionCall": {"name": tc.name, "args": tc.arguments}}));
+        }
+
+        let finish_reason = stop_reason
+            .map(Self::unified_to_finish_reason)
+            .unwrap_or("STOP");
+
+        json!({
+            "index": index,
+            "content": {"role": "model", "parts": parts},
+            "finishReason": finish_reason,
+        })
+    }
+
+    fn response_format_from_generation_config(gen_config: &Value) -> Option<ResponseFormat> {
+        let mime = gen_config
+            .get("responseMimeType")
+            .and_then(|m| m.as_str())?;
+        if mime != "application/json" {
+            return None;
+        }
+        let schema = gen_config
+            .get("responseJsonSchema")
+            .or_else(|| gen_config.get("responseSchema"));
+        Some(match schema {
+            Some(schema) => ResponseFormat::JsonSchema {
+                name: "response".to_string(),
+                description: None,
+                schema: schema.clone(),
+                strict: None,
+            },
+            None => ResponseFormat::JsonObject,
+        })
+    }
+
     fn unified_tool_to_gemini(tool: &UnifiedTool) -> Value {
         let mut decl = json!({
             "name": tool.name,
@@ -433,6 +553,11 @@ impl GeminiTransformer {
     }
 
     /// Infer MIME type from a URL's file extension.
+    /// Whether an inline or file part MIME type denotes a document rather than media.
+    fn is_document_mime(mime: &str) -> bool {
+        mime == "application/pdf"
+    }
+
     fn mime_type_from_url(url: &str) -> Option<&'static str> {
         let path = url.split('?').next().unwrap_or(url);
         let ext = path.rsplit('.').next()?.to_lowercase();
@@ -474,7 +599,13 @@ impl GeminiTransformer {
 
     // -- Helpers: Usage --
 
+    /// Parse `usageMetadata`. Gemini reports thoughts apart from candidates;
+    /// they are folded into the output tokens like other protocols do.
     fn parse_usage(usage_meta: &Value) -> UnifiedUsage {
+        let thoughts = usage_meta
+            .get("thoughtsTokenCount")
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32);
         UnifiedUsage {
             input_tokens: usage_meta
                 .get("promptTokenCount")
@@ -483,14 +614,75 @@ impl GeminiTransformer {
             output_tokens: usage_meta
                 .get("candidatesTokenCount")
                 .and_then(|v| v.as_i64())
-                .unwrap_or(0) as i32,
+                .unwrap_or(0) as i32
+                + thoughts.unwrap_or(0),
             cache_read_tokens: usage_meta
                 .get("cachedContentTokenCount")
                 .and_then(|v| v.as_i64())
                 .map(|v| v as i32),
             cache_write_tokens: None,
+            reasoning_tokens: thoughts,
         }
     }
+
+    /// Build `usageMetadata`, splitting reasoning tokens back out of the candidates.
+    fn usage_to_metadata(usage: &UnifiedUsage) -> Value {
+        let thoughts = usage.reasoning_tokens.unwrap_or(0);
+        let mut meta = json!({
+            "promptTokenCount": usage.input_tokens,
+            "candidatesTokenCount": usage.output_tokens - thoughts,
+            "totalTokenCount": usage.input_tokens + usage.output_tokens,
+        });
+        if let Some(thoughts) = usage.reasoning_tokens {
+            meta["thoughtsTokenCount"] = json!(thoughts);
+        }
+        meta
+    }
+
+    // -- Helpers: Reasoning --
+
+    /// Parse `generationConfig.thinkingConfig`.
+    ///
+    /// A `thinkingBudget` of -1 (dynamic thinking) carries no budget.
+    fn thinking_config_to_reasoning(config: &Value) -> Option<ReasoningConfig> {
+        let config = config.as_object()?;
+        let reasoning = ReasoningConfig {
+            effort: config
+                .get("thinkingLevel")
+                .and_then(|v| v.as_str())
+                .and_then(ReasoningEffort::parse),
+            budget_tokens: config
+                .get("thinkingBudget")
+                .and_then(|v| v.as_i64())
+                .filter(|v| *v >= 0)
+                .map(|v| v as i32),
+            include_thoughts: config.get("includeThoughts").and_then(|v| v.as_bool()),
+        };
+        (reasoning != ReasoningConfig::default()).then_some(reasoning)
+    }
+
+    /// Build `thinkingConfig`: Gemini 3 models take a `thinkingLevel`,
+    /// older thinking models a `thinkingBudget`.
+    fn reasoning_to_thinking_config(reasoning: &ReasoningConfig, model: &str) -> Value {
+        let mut config = json!({});
+        if is_gemini3_model(model) {
+            if let Some(level) = reasoning
+                .resolved_effort()
+                .and_then(|e| map_reasoning_effort_to_thinking_level(e.as_str(), model))
+            {
+                config["thinkingLevel"] = json!(level);
+            }
+        } else if let Some(budget) = reasoning.resolved_budget() {
+            config["thinkingBudget"] = json!(budget);
+        }
+        let include_thoughts = reasoning
+            .include_thoughts
+            .or_else(|| reasoning.is_disabled().then_some(false));
+        if let Some(include_thoughts) = include_thoughts {
+            config["includeThoughts"] = json!(include_thoughts);
+        }
+        config
+    }
 }
 
 impl Default for GeminiTransformer {
@@ -551,6 +743,14 @@ impl Transformer for GeminiTransformer {
                         .collect()
                 }),
             stream: false,
+            response_format: Self::response_format_from_generation_config(&gen_config),
+            n: gen_config
+                .get("candidateCount")
+                .and_then(|v| v.as_u64())
+                .map(|v| v as u32),
+            reasoning: gen_config
+                .get("thinkingConfig")
+                .and_then(Self::thinking_config_to_reasoning),
             extra: Default::default(),
         };
 
@@ -682,6 +882,32 @@ impl Transformer for GeminiTransformer {
         if let Some(ref stop) = unified.parameters.stop_sequences {
             gen_config["stopSequences"] = json!(stop);
         }
+        match unified.parameters.response_format {
+            Some(ResponseFormat::JsonObject) => {
+                gen_config["responseMimeType"] = json!("application/json");
+            }
+            Some(ResponseFormat::JsonSchema { ref schema, .. }) => {
+                gen_config["responseMimeType"] = json!("application/json");
+                gen_config["responseSchema"] = Self::sanitize_schema_for_gemini(schema);
+            }
+            Some(ResponseFormat::Text) | None => {}
+        }
+        // Streamed multi-candidate responses are emulated (see supports_multiple_choices)
+        if let Some(n) = unified.parameters.n.filter(|n| *n > 1) {
+            if !unified.parameters.stream {
+                gen_config["candidateCount"] = json!(n);
+            }
+        }
+        if let Some(ref reasoning) = unified.parameters.reasoning {
+            let thinking_config = Self::reasoning_to_thinking_config(reasoning, &unified.model);
+            if thinking_config
+                .as_object()
+                .map(|o| !o.is_empty())
+                .unwrap_or(false)
+            {
+                gen_config["thinkingConfig"] = thinking_config;
+            }
+        }
         if gen_config
             .as_object()
             .map(|o| !o.is_empty())
@@ -728,37 +954,15 @@ impl Transformer for GeminiTransformer {
             .and_then(|c| c.as_array())
             .ok_or_else(|| AppError::BadRequest("Missing 'candidates' field".into()))?;
 
-        let candidate = candidates
-            .first()
+        let mut candidates = candidates.iter().map(Self::candidate_to_unified);
+        let UnifiedChoice {
+            content,
+            stop_reason,
+            tool_calls,
+        } = candidates
+            .next()
             .ok_or_else(|| AppError::BadRequest("Empty candidates array".into()))?;
-
-        let parts = candidate
-            .get("content")
-            .and_then(|c| c.get("parts"))
-            .and_then(|p| p.as_array())
-            .cloned()
-            .unwrap_or_default();
-
-        let mut content = Vec::new();
-        let mut tool_calls = Vec::new();
-
-        for part in &parts {
-            for uc in Self::part_to_unified_vec(part) {
-                if let UnifiedContent::ToolUse { id, name, input } = &uc {
-                    tool_calls.push(UnifiedToolCall {
-                        id: id.clone(),
-                        name: name.clone(),
-                        arguments: input.clone(),
-                    });
-                }
-                content.push(uc);
-            }
-        }
-
-        let stop_reason = candidate
-            .get("finishReason")
-            .and_then(|r| r.as_str())
-            .map(Self::finish_reason_to_unified);
+        let additional_choices: Vec<UnifiedChoice> = candidates.collect();
 
         let usage = raw
             .get("usageMetadata")
@@ -782,6 +986,7 @@ impl Transformer for GeminiTransformer {
             stop_reason,
             usage,
             tool_calls,
+            additional_choices,
         })
     }
 
@@ -790,28 +995,24 @@ impl Transformer for GeminiTransformer {
         unified: &UnifiedResponse,
         _client_protocol: Protocol,
     ) -> Result<Value> {
-        let mut parts = Self::unified_contents_to_parts(&unified.content);
-        // Append tool_calls not in content
-        for tc in &unified.tool_calls {
-            parts.push(json!({"functionCall": {"name": tc.name, "args": tc.arguments}}));
+        let mut candidates = vec![Self::unified_choice_to_candidate(
+            0,
+            &unified.content,
+            &unified.tool_calls,
+            unified.stop_reason.as_ref(),
+        )];
+        for (i, choice) in unified.additional_choices.iter().enumerate() {
+            candidates.push(Self::unified_choice_to_candidate(
+                i + 1,
+                &choice.content,
+                &choice.tool_calls,
+                choice.stop_reason.as_ref(),
+            ));
         }
 
-        let finish_reason = unified
-            .stop_reason
-            .as_ref()
-            .map(Self::unified_to_finish_reason)
-            .unwrap_or("STOP");
-
         let response = json!({
-            "candidates": [{
-                "content": {"role": "model", "parts": parts},
-                "finishReason": finish_reason,
-            }],
-            "usageMetadata": {
-                "promptTokenCount": unified.usage.input_tokens,
-                "candidatesTokenCount": unified.usage.output_tokens,
-                "totalTokenCount": unified.usage.input_tokens + unified.usage.output_tokens,
-            },
+            "candidates": candidates,
+            "usageMetadata": Self::usage_to_metadata(&unified.usage),
             "modelVersion": unified.model,
         });
 
@@ -862,6 +1063,7 @@ impl Transformer for GeminiTransformer {
                         stop_reason: None,
                         usage,
                         tool_calls: vec![],
+                        additional_choices: vec![],
                     };
                     chunks.push(UnifiedStreamChunk::message_start(msg));
 
@@ -1019,7 +1221,10 @@ impl Transformer for GeminiTransformer {
                         _ => return Ok(String::new()),
                     };
                     let event = json!({
-                        "candidates": [{"content": {"role": "model", "parts": [part]}}]
+                        "candidates": [{
+                            "index": chunk.choice_index,
+                            "content": {"role": "model", "parts": [part]}
+                        }]
                     });
                     Ok(format!("data: {}\n\n", event))
                 } else {
@@ -1033,15 +1238,13 @@ impl Transformer for GeminiTransformer {
                     .as_ref()
                     .map(Self::unified_to_finish_reason)
                     .unwrap_or("STOP");
-                let usage = chunk.usage.as_ref().map(|u| {
-                    json!({
-                        "promptTokenCount": u.input_tokens,
-                        "candidatesTokenCount": u.output_tokens,
-                        "totalTokenCount": u.input_tokens + u.output_tokens,
-                    })
-                });
+                let usage = chunk.usage.as_ref().map(Self::usage_to_metadata);
                 let event = json!({
-                    "candidates": [{"content": {"role": "model", "parts": []}, "finishReason": finish_reason}],
+                    "candidates": [{
+                        "index": chunk.choice_index,
+                        "content": {"role": "model", "parts": []},
+                        "finishReason": finish_reason
+                    }],
                     "usageMetadata": usage,
                 });
                 Ok(format!("data: {}\n\n", event))
@@ -1055,6 +1258,20 @@ impl Transformer for GeminiTransformer {
         "/v1/projects"
     }
 
+    fn requested_choices(&self, raw: &Value) -> u32 {
+        raw.get("generationConfig")
+            .and_then(|g| g.get("candidateCount"))
+            .and_then(|n| n.as_u64())
+            .map(|n| n.clamp(1, u32::MAX as u64) as u32)
+            .unwrap_or(1)
+    }
+
+    fn supports_multiple_choices(&self, stream: bool) -> bool {
+        // candidateCount is honored for generateContent only; streamed
+        // candidates would interleave in one SSE stream
+        !stream
+    }
+
     fn can_handle(&self, raw: &Value) -> bool {
         // Gemini requests have "contents" (not "messages") and optionally "generationConfig"
         raw.get("contents").is_some()
@@ -1076,6 +1293,95 @@ mod tests {
         assert_eq!(t.protocol(), Protocol::Gemini);
     }
 
+    #[test]
+    fn test_thinking_config_mapping() {
+        let t = GeminiTransformer::new();
+        let raw = json!({
+            "contents": [{"role": "user", "parts": [{"text": "Hi"}]}],
+            "generationConfig": {"thinkingConfig": {"thinkingBudget": 2048, "includeThoughts": true}}
+        });
+        let mut unified = t.transform_request_out(raw).unwrap();
+        let reasoning = unified.parameters.reasoning.clone().unwrap();
+        assert_eq!(reasoning.budget_tokens, Some(2048));
+        assert_eq!(reasoning.include_thoughts, Some(true));
+
+        unified.model = "gemini-2.5-pro".to_string();
+        let back = t.transform_request_in(&unified).unwrap();
+        assert_eq!(
+            back["generationConfig"]["thinkingConfig"],
+            json!({"thinkingBudget": 2048, "includeThoughts": true})
+        );
+
+        // Gemini 3 takes a thinking level instead of a budget
+        unified.model = "gemini-3-pro-preview".to_string();
+        unified.parameters.reasoning = Some(ReasoningConfig {
+            effort: Some(ReasoningEffort::High),
+            ..Default::default()
+        });
+        let back = t.transform_request_in(&unified).unwrap();
+        assert_eq!(
+            back["generationConfig"]["thinkingConfig"],
+            json!({"thinkingLevel": "high"})
+        );
+    }
+
+    #[test]
+    fn test_thoughts_token_count() {
+        let t = GeminiTransformer::new();
+        let raw = json!({
+            "candidates": [{"content": {"role": "model", "parts": [{"text": "Hi"}]}, "finishReason": "STOP"}],
+            "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "thoughtsTokenCount": 20}
+        });
+        let unified = t.transform_response_in(raw, "gemini-2.5-pro").unwrap();
+        assert_eq!(unified.usage.output_tokens, 25);
+        assert_eq!(unified.usage.reasoning_tokens, Some(20));
+
+        let out = t
+            .transform_response_out(&unified, Protocol::Gemini)
+            .unwrap();
+        assert_eq!(out["usageMetadata"]["candidatesTokenCount"], 5);
+        assert_eq!(out["usageMetadata"]["thoughtsTokenCount"], 20);
+    }
+
+    #[test]
+    fn test_pdf_parts_map_to_documents() {
+        let t = GeminiTransformer::new();
+        let raw = json!({
+            "contents": [{
+                "role": "user",
+                "parts": [
+                    {"inlineData": {"mimeType": "application/pdf", "data": "JVBERi0="}},
+                    {"fileData": {"mimeType": "application/pdf", "fileUri": "gs://bucket/a.pdf"}},
+                    {"inlineData": {"mimeType": "image/png", "data": "iVBOR"}}
+                ]
+            }]
+        });
+
+        let unified = t.transform_request_out(raw).unwrap();
+        let content = &unified.messages[0].content;
+        assert!(matches!(
+            &content[0],
+            UnifiedContent::Document { source_type, media_type, .. }
+                if source_type == "base64" && media_type == "application/pdf"
+        ));
+        assert!(matches!(
+            &content[1],
+            UnifiedContent::Document { source_type, data, .. }
+                if source_type == "url" && data == "gs://bucket/a.pdf"
+        ));
+        assert!(matches!(&content[2], UnifiedContent::Image { .. }));
+
+        let mut unified = unified;
+        unified.messages[0]
+            .content
+            .push(UnifiedContent::document_text("plain notes"));
+        let back = t.transform_request_in(&unified).unwrap();
+        let parts = &back["contents"][0]["parts"];
+        assert_eq!(parts[0]["inlineData"]["mimeType"], "application/pdf");
+        assert_eq!(parts[1]["fileData"]["fileUri"], "gs://bucket/a.pdf");
+        assert_eq!(parts[3]["text"], "plain notes");
+    }
+
     #[test]
     fn test_can_handle() {
         let t = GeminiTransformer::new();
@@ -1303,6 +1609,7 @@ mod tests {
             stop_reason: Some(StopReason::EndTurn),
             usage: UnifiedUsage::new(10, 20),
             tool_calls: vec![],
+            additional_choices: vec![],
         };
 
         let raw = t
@@ -1448,4 +1755,94 @@ mod tests {
         assert_eq!(gemini_req["generationConfig"]["maxOutputTokens"], 1024);
         assert_eq!(gemini_req["generationConfig"]["temperature"], 0.7);
     }
+
+    #[test]
+    fn test_candidate_count_from_n() {
+        use super::super::openai::OpenAITransformer;
+
+        let openai = OpenAITransformer::new();
+        let gemini = GeminiTransformer::new();
+        let mut unified = openai
+            .transform_request_out(json!({
+                "model": "gemini-pro",
+                "messages": [{"role": "user", "content": "Hi"}],
+                "n": 3
+            }))
+            .unwrap();
+
+        let gemini_req = gemini.transform_request_in(&unified).unwrap();
+        assert_eq!(gemini_req["generationConfig"]["candidateCount"], 3);
+        assert!(gemini.supports_multiple_choices(false));
+
+        // Streamed candidates are emulated with separate calls instead
+        unified.parameters.stream = true;
+        let gemini_req = gemini.transform_request_in(&unified).unwrap();
+        assert!(gemini_req.get("generationConfig").is_none());
+        assert!(!gemini.supports_multiple_choices(true));
+
+        let response = json!({
+            "candidates": [
+                {"index": 0, "content": {"role": "model", "parts": [{"text": "a"}]}, "finishReason": "STOP"},
+                {"index": 1, "content": {"role": "model", "parts": [{"text": "b"}]}, "finishReason": "MAX_TOKENS"}
+            ],
+            "usageMetadata": {"promptTokenCount": 3, "candidatesTokenCount": 2}
+        });
+        let unified = gemini
+            .transform_response_in(response, "gemini-pro")
+            .unwrap();
+        assert_eq!(unified.additional_choices.len(), 1);
+
+        let out = openai
+            .transform_response_out(&unified, Protocol::OpenAI)
+            .unwrap();
+        assert_eq!(out["choices"][1]["message"]["content"], "b");
+        assert_eq!(out["choices"][1]["finish_reason"], "length");
+    }
+
+    #[test]
+    fn test_response_format_to_gemini_schema() {
+        use super::super::openai::OpenAITransformer;
+
+        let openai = OpenAITransformer::new();
+        let gemini = GeminiTransformer::new();
+        let request = json!({
+            "model": "gemini-pro",
+            "messages": [{"role": "user", "content": "Who?"}],
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "person",
+                    "schema": {
+                        "type": "object",
+                        "properties": {"name": {"type": ["string", "null"]}},
+                        "additionalProperties": false
+                    }
+                }
+            }
+        });
+
+        let unified = openai.transform_request_out(request).unwrap();
+        let gemini_req = gemini.transform_request_in(&unified).unwrap();
+        let config = &gemini_req["generationConfig"];
+        assert_eq!(config["responseMimeType"], "application/json");
+        assert!(config["responseSchema"]
+            .get("additionalProperties")
+            .is_none());
+        assert_eq!(
+            config["responseSchema"]["properties"]["name"]["nullable"],
+            true
+        );
+
+        // Gemini client → UIF
+        let roundtrip = gemini
+            .transform_request_out(json!({
+                "contents": [{"role": "user", "parts": [{"text": "Who?"}]}],
+                "generationConfig": {"responseMimeType": "application/json"}
+            }))
+            .unwrap();
+        assert_eq!(
+            roundtrip.parameters.response_format,
+            Some(ResponseFormat::JsonObject)
+        );
+    }
 }
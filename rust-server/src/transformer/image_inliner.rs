@@ -0,0 +1,148 @@
+//! Inline replacement of image URLs with base64 data.
+//!
+//! Some providers only accept images as inline data (Gemini `inlineData`,
+//! some Vertex setups) and reject http(s) URLs. The images are fetched ahead
+//! of the transformation by `crate::api::image_fetch`, since the pipeline is
+//! synchronous; [`ImageInliner`] then swaps every fetched URL for its data.
+
+use std::collections::HashMap;
+
+use super::features::FeatureTransformer;
+use super::unified::{UnifiedContent, UnifiedRequest, UnifiedResponse, UnifiedStreamChunk};
+use crate::core::error::Result;
+
+/// Image data fetched for a URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineImage {
+    /// Detected MIME type
+    pub media_type: String,
+    /// Base64 encoded image bytes
+    pub data: String,
+}
+
+/// Replaces image URLs with previously fetched base64 data.
+#[derive(Debug, Clone, Default)]
+pub struct ImageInliner {
+    images: HashMap<String, InlineImage>,
+}
+
+impl ImageInliner {
+    /// Create an inliner for images fetched by URL.
+    pub fn new(images: HashMap<String, InlineImage>) -> Self {
+        Self { images }
+    }
+
+    fn inline(&self, content: &mut UnifiedContent) {
+        if let UnifiedContent::Image {
+            source_type,
+            media_type,
+            data,
+        } = content
+        {
+            if source_type != "url" {
+                return;
+            }
+            if let Some(image) = self.images.get(data.as_str()) {
+                *source_type = "base64".to_string();
+                *media_type = image.media_type.clone();
+                *data = image.data.clone();
+            }
+        }
+    }
+}
+
+/// Remote (http or https) image URLs referenced by a request, deduplicated.
+pub fn remote_image_urls(request: &UnifiedRequest) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for content in request.messages.iter().flat_map(|m| &m.content) {
+        if let UnifiedContent::Image {
+            source_type, data, ..
+        } = content
+        {
+            let remote = data.starts_with("http://") || data.starts_with("https://");
+            if source_type == "url" && remote && !urls.contains(data) {
+                urls.push(data.clone());
+            }
+        }
+    }
+    urls
+}
+
+impl FeatureTransformer for ImageInliner {
+    fn transform_request(&self, request: &mut UnifiedRequest) -> Result<()> {
+        for message in &mut request.messages {
+            message.content.iter_mut().for_each(|c| self.inline(c));
+        }
+        Ok(())
+    }
+
+    fn transform_response(&self, _response: &mut UnifiedResponse) -> Result<()> {
+        Ok(())
+    }
+
+    fn transform_stream_chunk(&self, _chunk: &mut UnifiedStreamChunk) -> Result<()> {
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "image_inliner"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::unified::{Role, UnifiedMessage};
+
+    fn request() -> UnifiedRequest {
+        UnifiedRequest::new(
+            "gemini-2.5-pro",
+            vec![UnifiedMessage::with_content(
+                Role::User,
+                vec![
+                    UnifiedContent::text("Describe these"),
+                    UnifiedContent::image_url("https://example.com/cat.png"),
+                    UnifiedContent::image_url("gs://bucket/dog.png"),
+                    UnifiedContent::image_url("https://example.com/cat.png"),
+                ],
+            )],
+        )
+    }
+
+    #[test]
+    fn test_remote_image_urls_skips_non_http_and_duplicates() {
+        assert_eq!(
+            remote_image_urls(&request()),
+            vec!["https://example.com/cat.png".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_fetched_urls_become_base64() {
+        let inliner = ImageInliner::new(HashMap::from([(
+            "https://example.com/cat.png".to_string(),
+            InlineImage {
+                media_type: "image/png".to_string(),
+                data: "iVBORw0KGgo=".to_string(),
+            },
+        )]));
+        let mut request = request();
+        inliner.transform_request(&mut request).unwrap();
+
+        let content = &request.messages[0].content;
+        assert!(matches!(
+            &content[1],
+            UnifiedContent::Image { source_type, media_type, data }
+                if source_type == "base64" && media_type == "image/png" && data == "iVBORw0KGgo="
+        ));
+        assert!(matches!(
+            &content[2],
+            UnifiedContent::Image { source_type, data, .. }
+                if source_type == "url" && data == "gs://bucket/dog.png"
+        ));
+        assert!(matches!(
+            &content[3],
+            UnifiedContent::Image { source_type, .. } if source_type == "base64"
+        ));
+    }
+}
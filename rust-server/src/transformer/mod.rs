@@ -33,14 +33,22 @@
 //! ```
 
 pub mod anthropic;
+pub mod choices;
 pub mod detector;
 pub mod features;
 pub mod gcp_vertex;
 pub mod gemini;
+pub mod guardrails;
+pub mod image_inliner;
 pub mod openai;
 pub mod passthrough;
+pub mod pii_redaction;
 pub mod response_api;
 pub mod stream;
+pub mod structured_output;
+pub mod tool_arguments;
+pub mod tool_emulation;
+pub mod tool_normalizer;
 pub mod unified;
 
 use bytes::Bytes;
@@ -51,10 +59,16 @@ pub use detector::ProtocolDetector;
 pub use features::{
     FeatureTransformer, FeatureTransformerChain, ReasoningTransformer, TokenLimitTransformer,
 };
+pub use guardrails::Guardrails;
+pub use image_inliner::ImageInliner;
 pub use passthrough::{should_bypass, transform_request_bypass, PassthroughTransformer};
+pub use pii_redaction::PiiRedactor;
 pub use stream::CrossProtocolStreamState;
 pub use stream::SseEvent;
 pub use stream::SseParser;
+pub use tool_arguments::ToolArgumentChecker;
+pub use tool_emulation::ToolEmulationTransformer;
+pub use tool_normalizer::ToolNormalizer;
 pub use unified::provider_type_to_protocol;
 pub use unified::*;
 
@@ -129,6 +143,19 @@ pub trait Transformer: Send + Sync {
     ///
     /// Used by the protocol detector to auto-detect the format.
     fn can_handle(&self, raw: &serde_json::Value) -> bool;
+
+    /// Number of choices a client request in this protocol asks for.
+    fn requested_choices(&self, _raw: &serde_json::Value) -> u32 {
+        1
+    }
+
+    /// Whether the provider returns several choices from a single call.
+    ///
+    /// When false, multi-choice requests are emulated with parallel calls
+    /// (see [`TransformPipeline::choice_fan_out`]).
+    fn supports_multiple_choices(&self, _stream: bool) -> bool {
+        false
+    }
 }
 
 // ============================================================================
@@ -222,8 +249,24 @@ pub struct TransformContext {
     pub provider_name: String,
     /// Provider type (e.g. "openai", "anthropic", "gcp_vertex")
     pub provider_type: String,
-    /// Whether streaming is enabled
+    /// Whether the upstream is called in streaming mode
     pub stream: bool,
+    /// Whether the target model accepts PDF documents (`None` when unknown)
+    pub supports_pdf_input: Option<bool>,
+    /// Whether the client asked for a JSON response format emulated with a tool
+    pub structured_output: bool,
+    /// Fetched image data replacing image URLs the provider cannot load
+    pub image_inliner: Option<ImageInliner>,
+    /// Content policy rules of the credential
+    pub guardrails: Option<Guardrails>,
+    /// Placeholder substitution of personal data sent to the provider
+    pub pii_redactor: Option<PiiRedactor>,
+    /// Prompt-based tool calling for models without native function calling
+    pub tool_emulation: Option<ToolEmulationTransformer>,
+    /// Tool name mapping and schema rewriting for the provider
+    pub tool_normalizer: Option<ToolNormalizer>,
+    /// Repair, validation and reporting of tool call arguments in responses
+    pub tool_arguments: ToolArgumentChecker,
     /// Extra metadata
     pub metadata: HashMap<String, serde_json::Value>,
 }
@@ -320,12 +363,24 @@ impl TransformPipeline {
         &self,
         raw: serde_json::Value,
         ctx: &TransformContext,
+    ) -> Result<serde_json::Value> {
+        self.transform_request_with(raw, ctx, |_| {})
+    }
+
+    /// Transform a client request to provider format, letting the caller adjust
+    /// the UIF after feature transformers ran (e.g. to append a repair turn).
+    pub fn transform_request_with(
+        &self,
+        raw: serde_json::Value,
+        ctx: &TransformContext,
+        adjust: impl FnOnce(&mut UnifiedRequest),
     ) -> Result<serde_json::Value> {
         let client_transformer = self.registry.get_or_error(ctx.client_protocol)?;
         let provider_transformer = self.registry.get_or_error(ctx.provider_protocol)?;
 
         // Step 1: Client format → Unified
         let mut unified = client_transformer.transform_request_out(raw)?;
+        tool_arguments::repair_request_arguments(&mut unified);
 
         // Update model name if mapped
         if !ctx.mapped_model.is_empty() && ctx.mapped_model != ctx.original_model {
@@ -336,6 +391,25 @@ impl TransformPipeline {
         if let Some(ref features) = self.feature_transformers {
             features.transform_request(&mut unified)?;
         }
+        if ctx.supports_pdf_input == Some(false) {
+            strip_unsupported_documents(&mut unified)?;
+        }
+        if let Some(ref inliner) = ctx.image_inliner {
+            inliner.transform_request(&mut unified)?;
+        }
+        if let Some(ref guardrails) = ctx.guardrails {
+            guardrails.transform_request(&mut unified)?;
+        }
+        if let Some(ref redactor) = ctx.pii_redactor {
+            redactor.transform_request(&mut unified)?;
+        }
+        if let Some(ref emulation) = ctx.tool_emulation {
+            emulation.transform_request(&mut unified)?;
+        }
+        if let Some(ref normalizer) = ctx.tool_normalizer {
+            normalizer.transform_request(&mut unified)?;
+        }
+        adjust(&mut unified);
 
         // Step 3: Unified → Provider format
         provider_transformer.transform_request_in(&unified)
@@ -355,16 +429,101 @@ impl TransformPipeline {
 
         // Restore original model name for client
         unified.model = ctx.original_model.clone();
+        if ctx.structured_output {
+            structured_output::unwrap_structured_output_tool(&mut unified);
+        }
 
         // Step 2: Apply feature transformers to UIF
         if let Some(ref features) = self.feature_transformers {
             features.transform_response(&mut unified)?;
         }
+        if let Some(ref emulation) = ctx.tool_emulation {
+            emulation.transform_response(&mut unified)?;
+        }
+        if let Some(ref normalizer) = ctx.tool_normalizer {
+            normalizer.transform_response(&mut unified)?;
+        }
+        if let Some(ref redactor) = ctx.pii_redactor {
+            redactor.transform_response(&mut unified)?;
+        }
+        if let Some(ref guardrails) = ctx.guardrails {
+            guardrails.transform_response(&mut unified)?;
+        }
+        ctx.tool_arguments.check_response(&mut unified);
 
         // Step 3: Unified → Client format
         client_transformer.transform_response_out(&unified, ctx.client_protocol)
     }
 
+    /// Number of upstream calls needed to serve the client's requested choices.
+    ///
+    /// Returns 1 unless the client asked for several choices (OpenAI `n`,
+    /// Gemini `candidateCount`) and the provider cannot return them from a
+    /// single call, in which case each choice becomes its own upstream request.
+    pub fn choice_fan_out(&self, raw: &serde_json::Value, ctx: &TransformContext) -> Result<usize> {
+        if ctx.is_same_protocol() {
+            return Ok(1);
+        }
+        let client_transformer = self.registry.get_or_error(ctx.client_protocol)?;
+        let provider_transformer = self.registry.get_or_error(ctx.provider_protocol)?;
+
+        let requested = client_transformer.requested_choices(raw);
+        if requested <= 1 || provider_transformer.supports_multiple_choices(ctx.stream) {
+            return Ok(1);
+        }
+        if requested > choices::MAX_EMULATED_CHOICES {
+            return Err(AppError::BadRequest(format!(
+                "n must be at most {} for provider '{}'",
+                choices::MAX_EMULATED_CHOICES,
+                ctx.provider_name
+            )));
+        }
+        Ok(requested as usize)
+    }
+
+    /// Transform the provider responses of an emulated multi-choice request
+    /// into a single client response with one choice per response.
+    pub fn transform_responses(
+        &self,
+        raws: Vec<serde_json::Value>,
+        ctx: &TransformContext,
+    ) -> Result<serde_json::Value> {
+        let client_transformer = self.registry.get_or_error(ctx.client_protocol)?;
+        let provider_transformer = self.registry.get_or_error(ctx.provider_protocol)?;
+
+        let mut responses = Vec::with_capacity(raws.len());
+        for raw in raws {
+            let mut unified =
+                provider_transformer.transform_response_in(raw, &ctx.original_model)?;
+            if ctx.structured_output {
+                structured_output::unwrap_structured_output_tool(&mut unified);
+            }
+            if let Some(ref features) = self.feature_transformers {
+                features.transform_response(&mut unified)?;
+            }
+            if let Some(ref emulation) = ctx.tool_emulation {
+                emulation.transform_response(&mut unified)?;
+            }
+            if let Some(ref normalizer) = ctx.tool_normalizer {
+                normalizer.transform_response(&mut unified)?;
+            }
+            if let Some(ref redactor) = ctx.pii_redactor {
+                redactor.transform_response(&mut unified)?;
+            }
+            if let Some(ref guardrails) = ctx.guardrails {
+                guardrails.transform_response(&mut unified)?;
+            }
+            ctx.tool_arguments.check_response(&mut unified);
+            responses.push(unified);
+        }
+
+        let mut unified = choices::merge_choices(responses)
+            .ok_or_else(|| AppError::Internal("No responses to merge".to_string()))?;
+        unified.model = ctx.original_model.clone();
+
+        client_transformer.transform_response_out(&unified, ctx.client_protocol)
+    }
+
     /// Transform a streaming chunk.
     ///
     /// This applies feature transformers to the unified stream chunk.
@@ -394,11 +553,22 @@ impl TransformPipeline {
     /// Bypass mode is used when:
     /// 1. Client and provider use the same protocol
     /// 2. No feature transformers are configured
+    /// 3. Tool calling is not emulated for the model
+    /// 4. No tool names or schemas need normalizing for the provider
+    /// 5. Neither image inlining, PII redaction nor guardrails apply
+    /// 6. The model is not known to reject PDF documents
     ///
     /// In bypass mode, requests/responses pass through with minimal transformation
     /// (only model name mapping is applied).
     pub fn should_bypass(&self, ctx: &TransformContext) -> bool {
-        ctx.is_same_protocol() && !self.has_features()
+        ctx.is_same_protocol()
+            && !self.has_features()
+            && ctx.tool_emulation.is_none()
+            && ctx.tool_normalizer.is_none()
+            && ctx.image_inliner.is_none()
+            && ctx.pii_redactor.is_none()
+            && ctx.guardrails.is_none()
+            && ctx.supports_pdf_input != Some(false)
     }
 
     /// Transform request with bypass optimization.
@@ -480,6 +650,36 @@ impl TransformPipeline {
 // Sanitization Helpers
 // ============================================================================
 
+/// Prepare documents for a model that cannot read PDFs.
+///
+/// Plain-text documents are inlined as text; binary, URL and file documents
+/// are rejected since the provider would drop or refuse them.
+fn strip_unsupported_documents(unified: &mut UnifiedRequest) -> Result<()> {
+    for message in &mut unified.messages {
+        for content in &mut message.content {
+            if let UnifiedContent::Document {
+                source_type,
+                data,
+                title,
+                ..
+            } = content
+            {
+                if source_type != "text" {
+                    return Err(AppError::BadRequest(
+                        "Model does not support PDF input".to_string(),
+                    ));
+                }
+                let text = match title {
+                    Some(title) => format!("{}\n\n{}", title, data),
+                    None => std::mem::take(data),
+                };
+                *content = UnifiedContent::text(text);
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Sanitize empty assistant messages in Anthropic-format payloads.
 ///
 /// Anthropic API requires all messages to have non-empty content,
@@ -549,6 +749,89 @@ mod tests {
         assert!(!ctx.is_same_protocol());
     }
 
+    #[test]
+    fn test_choice_fan_out() {
+        let pipeline = TransformPipeline::new(Arc::new(TransformerRegistry::new()));
+        let raw = serde_json::json!({
+            "model": "m",
+            "messages": [{"role": "user", "content": "hi"}],
+            "n": 3
+        });
+        let mut ctx = TransformContext::new("test-123");
+        ctx.client_protocol = Protocol::OpenAI;
+
+        ctx.provider_protocol = Protocol::Anthropic;
+        assert_eq!(pipeline.choice_fan_out(&raw, &ctx).unwrap(), 3);
+
+        // Native support: OpenAI `n`, Gemini `candidateCount` when not streaming
+        ctx.provider_protocol = Protocol::OpenAI;
+        assert_eq!(pipeline.choice_fan_out(&raw, &ctx).unwrap(), 1);
+        ctx.provider_protocol = Protocol::Gemini;
+        assert_eq!(pipeline.choice_fan_out(&raw, &ctx).unwrap(), 1);
+        ctx.stream = true;
+        assert_eq!(pipeline.choice_fan_out(&raw, &ctx).unwrap(), 3);
+
+        let too_many = serde_json::json!({"model": "m", "messages": [], "n": 50});
+        assert!(pipeline.choice_fan_out(&too_many, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_documents_without_pdf_support() {
+        let pipeline = TransformPipeline::new(Arc::new(TransformerRegistry::new()));
+        let mut ctx = TransformContext::new("test-123");
+        ctx.client_protocol = Protocol::Anthropic;
+        ctx.provider_protocol = Protocol::OpenAI;
+        ctx.supports_pdf_input = Some(false);
+
+        let text_doc = serde_json::json!({
+            "model": "m",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": [
+                {"type": "document", "source": {"type": "text", "media_type": "text/plain", "data": "notes"}}
+            ]}]
+        });
+        let out = pipeline.transform_request(text_doc, &ctx).unwrap();
+        assert_eq!(out["messages"][0]["content"], "notes");
+
+        let pdf_doc = serde_json::json!({
+            "model": "m",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": [
+                {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0="}}
+            ]}]
+        });
+        let err = pipeline
+            .transform_request(pdf_doc.clone(), &ctx)
+            .unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+
+        ctx.supports_pdf_input = None;
+        let out = pipeline.transform_request(pdf_doc, &ctx).unwrap();
+        assert_eq!(out["messages"][0]["content"][0]["type"], "file");
+    }
+
+    #[test]
+    fn test_documents_without_pdf_support_same_protocol() {
+        let pipeline = TransformPipeline::new(Arc::new(TransformerRegistry::new()));
+        let mut ctx = TransformContext::new("test-123");
+        ctx.client_protocol = Protocol::Anthropic;
+        ctx.provider_protocol = Protocol::Anthropic;
+        ctx.supports_pdf_input = Some(false);
+        assert!(!pipeline.should_bypass(&ctx));
+
+        let pdf_doc = serde_json::json!({
+            "model": "m",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": [
+                {"type": "document", "source": {"type": "base64", "media_type": "application/pdf", "data": "JVBERi0="}}
+            ]}]
+        });
+        let err = pipeline
+            .transform_request_with_bypass(pdf_doc, &ctx)
+            .unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+    }
+
     // -------------------------------------------------------------------------
     // Pipeline with Feature Transformers Tests
     // -------------------------------------------------------------------------
@@ -635,6 +918,7 @@ mod tests {
         let mut chunk = UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockDelta,
             index: 0,
+            choice_index: 0,
             delta: Some(UnifiedContent::thinking("thinking...", None)),
             usage: None,
             stop_reason: None,
@@ -656,6 +940,7 @@ mod tests {
         let mut chunk = UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockDelta,
             index: 0,
+            choice_index: 0,
             delta: Some(UnifiedContent::thinking("thinking...", None)),
             usage: None,
             stop_reason: None,
@@ -3,10 +3,12 @@
 //! Handles conversion between OpenAI Chat Completions API format and
 //! the Unified Internal Format.
 
+use super::structured_output::{parse_openai_response_format, to_openai_response_format};
+use super::tool_arguments::parse_raw_arguments;
 use super::{
-    ChunkType, Protocol, Result, Role, StopReason, Transformer, UnifiedContent, UnifiedMessage,
-    UnifiedParameters, UnifiedRequest, UnifiedResponse, UnifiedStreamChunk, UnifiedTool,
-    UnifiedToolCall, UnifiedUsage,
+    ChunkType, Protocol, ReasoningConfig, ReasoningEffort, Result, Role, StopReason, Transformer,
+    UnifiedChoice, UnifiedContent, UnifiedMessage, UnifiedParameters, UnifiedRequest,
+    UnifiedResponse, UnifiedStreamChunk, UnifiedTool, UnifiedToolCall, UnifiedUsage,
 };
 use crate::core::AppError;
 use bytes::Bytes;
@@ -54,6 +56,19 @@ pub enum OpenAIContentPart {
     Text { text: String },
     #[serde(rename = "image_url")]
     ImageUrl { image_url: OpenAIImageUrl },
+    #[serde(rename = "file")]
+    File { file: OpenAIFile },
+}
+
+/// OpenAI file content part (inline `file_data` data URL or uploaded `file_id`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAIFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
 }
 
 /// OpenAI image URL structure.
@@ -120,6 +135,8 @@ pub struct OpenAIChatRequest {
     pub tools: Option<Vec<OpenAITool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
@@ -151,6 +168,42 @@ pub struct OpenAIUsage {
     pub prompt_tokens: i32,
     pub completion_tokens: i32,
     pub total_tokens: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<OpenAICompletionTokensDetails>,
+}
+
+/// OpenAI completion token breakdown.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpenAICompletionTokensDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<i32>,
+}
+
+impl OpenAIUsage {
+    /// Convert to unified usage, keeping the reasoning token breakdown.
+    fn to_unified(&self) -> UnifiedUsage {
+        UnifiedUsage {
+            reasoning_tokens: self
+                .completion_tokens_details
+                .as_ref()
+                .and_then(|d| d.reasoning_tokens),
+            ..UnifiedUsage::new(self.prompt_tokens, self.completion_tokens)
+        }
+    }
+
+    /// Build OpenAI usage from unified usage.
+    fn from_unified(usage: &UnifiedUsage) -> Self {
+        OpenAIUsage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: usage.total_tokens(),
+            completion_tokens_details: usage.reasoning_tokens.map(|tokens| {
+                OpenAICompletionTokensDetails {
+                    reasoning_tokens: Some(tokens),
+                }
+            }),
+        }
+    }
 }
 
 /// OpenAI streaming chunk.
@@ -256,6 +309,7 @@ impl OpenAITransformer {
                                 UnifiedContent::image_url(&image_url.url)
                             }
                         }
+                        OpenAIContentPart::File { file } => Self::file_to_unified(file),
                     })
                     .collect()
             }
@@ -270,8 +324,7 @@ impl OpenAITransformer {
                 calls
                     .iter()
                     .map(|tc| {
-                        let arguments: Value =
-                            serde_json::from_str(&tc.function.arguments).unwrap_or(json!({}));
+                        let arguments = parse_raw_arguments(&tc.function.arguments);
                         // Extract thought_signature from tool_call provider_specific_fields
                         if let Some(ref psf) = tc.provider_specific_fields {
                             if let Some(sig) = psf.get("thought_signature").and_then(|s| s.as_str())
@@ -434,6 +487,32 @@ impl OpenAITransformer {
         }
     }
 
+    /// Convert an OpenAI file part to a unified document.
+    fn file_to_unified(file: &OpenAIFile) -> UnifiedContent {
+        let (source_type, media_type, data) = match (&file.file_data, &file.file_id) {
+            (Some(file_data), _) => match file_data
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(','))
+            {
+                Some((header, data)) => (
+                    "base64",
+                    header.split(';').next().unwrap_or("application/pdf"),
+                    data.to_string(),
+                ),
+                // Bare base64 without a data URL prefix
+                None => ("base64", "application/pdf", file_data.clone()),
+            },
+            (None, Some(file_id)) => ("file", "", file_id.clone()),
+            (None, None) => ("base64", "application/pdf", String::new()),
+        };
+        UnifiedContent::Document {
+            source_type: source_type.to_string(),
+            media_type: media_type.to_string(),
+            data,
+            title: file.filename.clone(),
+        }
+    }
+
     /// Convert unified content to OpenAI content part.
     fn unified_to_content_part(content: &UnifiedContent) -> Option<OpenAIContentPart> {
         match content {
@@ -452,6 +531,29 @@ impl OpenAITransformer {
                     image_url: OpenAIImageUrl { url, detail: None },
                 })
             }
+            UnifiedContent::Document {
+                source_type,
+                media_type,
+                data,
+                title,
+            } => match source_type.as_str() {
+                "file" => Some(OpenAIContentPart::File {
+                    file: OpenAIFile {
+                        file_data: None,
+                        file_id: Some(data.clone()),
+                        filename: title.clone(),
+                    },
+                }),
+                // Chat Completions has no plain-text or URL documents; pass them as text
+                "text" | "url" => Some(OpenAIContentPart::Text { text: data.clone() }),
+                _ => Some(OpenAIContentPart::File {
+                    file: OpenAIFile {
+                        file_data: Some(format!("data:{};base64,{}", media_type, data)),
+                        file_id: None,
+                        filename: Some(title.clone().unwrap_or_else(|| "document.pdf".to_string())),
+                    },
+                }),
+            },
             // Other content types don't have direct OpenAI equivalents in content
             _ => None,
         }
@@ -552,6 +654,139 @@ impl OpenAITransformer {
             }
         })
     }
+
+    /// Build one OpenAI choice from unified content, tool calls and stop reason.
+    fn unified_choice_to_openai(
+        index: i32,
+        content_blocks: &[UnifiedContent],
+        tool_calls_in: &[UnifiedToolCall],
+        stop_reason: Option<&StopReason>,
+    ) -> OpenAIChoice {
+        // Convert content to OpenAI format - separate text and thinking content
+        let mut text_parts: Vec<OpenAIContentPart> = Vec::new();
+        let mut reasoning_content: Option<String> = None;
+        let mut thinking_blocks: Vec<Value> = Vec::new();
+        let mut thought_signatures: Vec<String> = Vec::new();
+
+        for c in content_blocks {
+            match c {
+                UnifiedContent::Text { text } => {
+                    text_parts.push(OpenAIContentPart::Text { text: text.clone() });
+                }
+                UnifiedContent::Thinking { text, signature } => {
+                    if text.is_empty() && signature.is_some() {
+                        // Signature-only block — collect for thinking_blocks and provider_specific_fields
+                        let sig = signature.as_ref().unwrap();
+                        thought_signatures.push(sig.clone());
+                        // If there's already a thinking block without signature, attach to it
+                        if let Some(last_block) = thinking_blocks.last_mut() {
+                            if last_block.get("signature").is_none() {
+                                last_block["signature"] = json!(sig);
+                            }
+                        }
+                    } else {
+                        // Collect thinking content for reasoning_content field
+                        match &mut reasoning_content {
+                            Some(existing) => {
+                                existing.push_str(text);
+                            }
+                            None => {
+                                reasoning_content = Some(text.clone());
+                            }
+                        }
+                        // Also add to thinking_blocks
+                        thinking_blocks.push(json!({
+                            "type": "thinking",
+                            "thinking": text,
+                        }));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Build provider_specific_fields with thought_signatures
+        let provider_specific_fields = if !thought_signatures.is_empty() {
+            Some(json!({ "thought_signatures": thought_signatures }))
+        } else {
+            None
+        };
+
+        // Only include thinking_blocks if non-empty
+        let thinking_blocks_opt = if thinking_blocks.is_empty() {
+            None
+        } else {
+            Some(thinking_blocks)
+        };
+
+        // Build content field
+        let content = if text_parts.is_empty() {
+            None
+        } else if text_parts.len() == 1 {
+            if let OpenAIContentPart::Text { text } = &text_parts[0] {
+                Some(OpenAIContent::Text(text.clone()))
+            } else {
+                None
+            }
+        } else {
+            Some(OpenAIContent::Parts(text_parts))
+        };
+
+        // Convert tool calls — encode thought_signatures into tool_call_id
+        // and set provider_specific_fields.thought_signature on each tool call
+        let tool_calls: Option<Vec<OpenAIToolCall>> = if tool_calls_in.is_empty() {
+            None
+        } else {
+            // Collect the last signature to encode into all tool call IDs (litellm compat)
+            let last_sig = thought_signatures.last().cloned();
+            Some(
+                tool_calls_in
+                    .iter()
+                    .map(|tc| {
+                        let (id, psf) = if let Some(ref sig) = last_sig {
+                            (
+                                format!(
+                                    "{}{}{}",
+                                    tc.id,
+                                    crate::api::gemini3::THOUGHT_SIGNATURE_SEPARATOR,
+                                    sig
+                                ),
+                                Some(json!({ "thought_signature": sig })),
+                            )
+                        } else {
+                            (tc.id.clone(), None)
+                        };
+                        OpenAIToolCall {
+                            id,
+                            call_type: "function".to_string(),
+                            function: OpenAIFunctionCall {
+                                name: tc.name.clone(),
+                                arguments: serde_json::to_string(&tc.arguments).unwrap_or_default(),
+                            },
+                            provider_specific_fields: psf,
+                        }
+                    })
+                    .collect(),
+            )
+        };
+
+        let finish_reason = stop_reason.map(Self::stop_reason_to_finish_reason);
+
+        OpenAIChoice {
+            index,
+            message: OpenAIMessage {
+                role: "assistant".to_string(),
+                content,
+                reasoning_content,
+                thinking_blocks: thinking_blocks_opt,
+                provider_specific_fields,
+                name: None,
+                tool_calls,
+                tool_call_id: None,
+            },
+            finish_reason: finish_reason.map(|s| s.to_string()),
+        }
+    }
 }
 
 impl Transformer for OpenAITransformer {
@@ -595,6 +830,26 @@ impl Transformer for OpenAITransformer {
             })
             .collect();
 
+        // Lift response_format into the UIF; unknown format types stay in extra
+        let mut extra = request.extra;
+        let response_format = extra
+            .get("response_format")
+            .and_then(parse_openai_response_format);
+        if response_format.is_some() {
+            extra.remove("response_format");
+        }
+        let reasoning = extra
+            .get("reasoning_effort")
+            .and_then(|v| v.as_str())
+            .and_then(ReasoningEffort::parse)
+            .map(|effort| ReasoningConfig {
+                effort: Some(effort),
+                ..Default::default()
+            });
+        if reasoning.is_some() {
+            extra.remove("reasoning_effort");
+        }
+
         // Build parameters
         let parameters = UnifiedParameters {
             temperature: request.temperature,
@@ -603,7 +858,10 @@ impl Transformer for OpenAITransformer {
             top_k: None,
             stop_sequences: request.stop,
             stream: request.stream.unwrap_or(false),
-            extra: request.extra,
+            response_format,
+            n: request.n,
+            reasoning,
+            extra,
         };
 
         // Normalize tool_choice from OpenAI format to UIF format
@@ -749,6 +1007,20 @@ impl Transformer for OpenAITransformer {
         if let Some(ref tools) = tools {
             request["tools"] = json!(tools);
         }
+        if let Some(ref response_format) = unified.parameters.response_format {
+            request["response_format"] = to_openai_response_format(response_format);
+        }
+        if let Some(n) = unified.parameters.n.filter(|n| *n > 1) {
+            request["n"] = json!(n);
+        }
+        if let Some(effort) = unified
+            .parameters
+            .reasoning
+            .as_ref()
+            .and_then(|r| r.resolved_effort())
+        {
+            request["reasoning_effort"] = json!(effort.as_str());
+        }
         if let Some(ref tool_choice) = unified.tool_choice {
             // Convert Anthropic tool_choice format to OpenAI format
             // Anthropic: {"type": "auto"} | {"type": "any"} | {"type": "tool", "name": "xxx"}
@@ -793,9 +1065,11 @@ impl Transformer for OpenAITransformer {
         let response: OpenAIChatResponse =
             serde_json::from_value(raw).map_err(|e| AppError::BadRequest(e.to_string()))?;
 
-        let choice = response
-            .choices
-            .first()
+        let mut choices = response.choices;
+        choices.sort_by_key(|c| c.index);
+        let mut choices = choices.into_iter();
+        let choice = choices
+            .next()
             .ok_or_else(|| AppError::BadRequest("No choices in response".to_string()))?;
 
         // Reuse message_to_unified to parse content, thinking_blocks,
@@ -807,10 +1081,21 @@ impl Transformer for OpenAITransformer {
             .as_ref()
             .map(|r| Self::finish_reason_to_stop_reason(r));
 
-        let usage = response
-            .usage
-            .map(|u| UnifiedUsage::new(u.prompt_tokens, u.completion_tokens))
-            .unwrap_or_default();
+        let additional_choices = choices
+            .map(|c| {
+                let msg = Self::message_to_unified(&c.message)?;
+                Ok(UnifiedChoice {
+                    content: msg.content,
+                    stop_reason: c
+                        .finish_reason
+                        .as_ref()
+                        .map(|r| Self::finish_reason_to_stop_reason(r)),
+                    tool_calls: msg.tool_calls,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let usage = response.usage.map(|u| u.to_unified()).unwrap_or_default();
 
         Ok(UnifiedResponse {
             id: response.id,
@@ -819,6 +1104,7 @@ impl Transformer for OpenAITransformer {
             stop_reason,
             usage,
             tool_calls: unified_msg.tool_calls,
+            additional_choices,
         })
     }
 
@@ -827,144 +1113,28 @@ impl Transformer for OpenAITransformer {
         unified: &UnifiedResponse,
         _client_protocol: Protocol,
     ) -> Result<Value> {
-        // Convert content to OpenAI format - separate text and thinking content
-        let mut text_parts: Vec<OpenAIContentPart> = Vec::new();
-        let mut reasoning_content: Option<String> = None;
-        let mut thinking_blocks: Vec<Value> = Vec::new();
-        let mut thought_signatures: Vec<String> = Vec::new();
-
-        for c in &unified.content {
-            match c {
-                UnifiedContent::Text { text } => {
-                    text_parts.push(OpenAIContentPart::Text { text: text.clone() });
-                }
-                UnifiedContent::Thinking { text, signature } => {
-                    if text.is_empty() && signature.is_some() {
-                        // Signature-only block — collect for thinking_blocks and provider_specific_fields
-                        let sig = signature.as_ref().unwrap();
-                        thought_signatures.push(sig.clone());
-                        // If there's already a thinking block without signature, attach to it
-                        if let Some(last_block) = thinking_blocks.last_mut() {
-                            if last_block.get("signature").is_none() {
-                                last_block["signature"] = json!(sig);
-                            }
-                        }
-                    } else {
-                        // Collect thinking content for reasoning_content field
-                        match &mut reasoning_content {
-                            Some(existing) => {
-                                existing.push_str(text);
-                            }
-                            None => {
-                                reasoning_content = Some(text.clone());
-                            }
-                        }
-                        // Also add to thinking_blocks
-                        thinking_blocks.push(json!({
-                            "type": "thinking",
-                            "thinking": text,
-                        }));
-                    }
-                }
-                _ => {}
-            }
+        let mut choices = vec![Self::unified_choice_to_openai(
+            0,
+            &unified.content,
+            &unified.tool_calls,
+            unified.stop_reason.as_ref(),
+        )];
+        for (i, choice) in unified.additional_choices.iter().enumerate() {
+            choices.push(Self::unified_choice_to_openai(
+                i as i32 + 1,
+                &choice.content,
+                &choice.tool_calls,
+                choice.stop_reason.as_ref(),
+            ));
         }
 
-        // Build provider_specific_fields with thought_signatures
-        let provider_specific_fields = if !thought_signatures.is_empty() {
-            Some(json!({ "thought_signatures": thought_signatures }))
-        } else {
-            None
-        };
-
-        // Only include thinking_blocks if non-empty
-        let thinking_blocks_opt = if thinking_blocks.is_empty() {
-            None
-        } else {
-            Some(thinking_blocks)
-        };
-
-        // Build content field
-        let content = if text_parts.is_empty() {
-            None
-        } else if text_parts.len() == 1 {
-            if let OpenAIContentPart::Text { text } = &text_parts[0] {
-                Some(OpenAIContent::Text(text.clone()))
-            } else {
-                None
-            }
-        } else {
-            Some(OpenAIContent::Parts(text_parts))
-        };
-
-        // Convert tool calls — encode thought_signatures into tool_call_id
-        // and set provider_specific_fields.thought_signature on each tool call
-        let tool_calls: Option<Vec<OpenAIToolCall>> = if unified.tool_calls.is_empty() {
-            None
-        } else {
-            // Collect the last signature to encode into all tool call IDs (litellm compat)
-            let last_sig = thought_signatures.last().cloned();
-            Some(
-                unified
-                    .tool_calls
-                    .iter()
-                    .map(|tc| {
-                        let (id, psf) = if let Some(ref sig) = last_sig {
-                            (
-                                format!(
-                                    "{}{}{}",
-                                    tc.id,
-                                    crate::api::gemini3::THOUGHT_SIGNATURE_SEPARATOR,
-                                    sig
-                                ),
-                                Some(json!({ "thought_signature": sig })),
-                            )
-                        } else {
-                            (tc.id.clone(), None)
-                        };
-                        OpenAIToolCall {
-                            id,
-                            call_type: "function".to_string(),
-                            function: OpenAIFunctionCall {
-                                name: tc.name.clone(),
-                                arguments: serde_json::to_string(&tc.arguments).unwrap_or_default(),
-                            },
-                            provider_specific_fields: psf,
-                        }
-                    })
-                    .collect(),
-            )
-        };
-
-        let finish_reason = unified
-            .stop_reason
-            .as_ref()
-            .map(Self::stop_reason_to_finish_reason);
-
         let response = OpenAIChatResponse {
             id: unified.id.clone(),
             object: "chat.completion".to_string(),
             created: chrono::Utc::now().timestamp(),
             model: unified.model.clone(),
-            choices: vec![OpenAIChoice {
-                index: 0,
-                message: OpenAIMessage {
-                    role: "assistant".to_string(),
-                    content,
-                    reasoning_content,
-                    thinking_blocks: thinking_blocks_opt,
-                    provider_specific_fields,
-                    name: None,
-                    tool_calls,
-                    tool_call_id: None,
-                },
-                finish_reason: finish_reason.map(|s| s.to_string()),
-            }],
-            usage: Some(OpenAIUsage {
-                prompt_tokens: unified.usage.input_tokens,
-                completion_tokens: unified.usage.output_tokens,
-                total_tokens: unified.usage.total_tokens(),
-            }),
+            choices,
+            usage: Some(OpenAIUsage::from_unified(&unified.usage)),
         };
 
         serde_json::to_value(response).map_err(AppError::Serialization)
@@ -1107,7 +1277,7 @@ impl Transformer for OpenAITransformer {
                         let usage = stream_chunk
                             .usage
                             .as_ref()
-                            .map(|u| UnifiedUsage::new(u.prompt_tokens, u.completion_tokens))
+                            .map(OpenAIUsage::to_unified)
                             .unwrap_or_default();
                         chunks.push(UnifiedStreamChunk::message_delta(stop_reason, usage));
                         emitted_message_delta = true;
@@ -1120,8 +1290,7 @@ impl Transformer for OpenAITransformer {
                     if let Some(ref usage) = stream_chunk.usage {
                         // Emit a message_delta with usage but no stop_reason change
                         // This handles the case where usage comes in a separate chunk
-                        let unified_usage =
-                            UnifiedUsage::new(usage.prompt_tokens, usage.completion_tokens);
+                        let unified_usage = usage.to_unified();
                         chunks.push(UnifiedStreamChunk::message_delta(
                             StopReason::EndTurn,
                             unified_usage,
@@ -1150,7 +1319,7 @@ impl Transformer for OpenAITransformer {
                         "created": chrono::Utc::now().timestamp(),
                         "model": "model",
                         "choices": [{
-                            "index": 0,
+                            "index": chunk.choice_index,
                             "delta": {
                                 "tool_calls": [{
                                     "index": chunk.index,
@@ -1179,7 +1348,7 @@ impl Transformer for OpenAITransformer {
                                 "created": chrono::Utc::now().timestamp(),
                                 "model": "model",
                                 "choices": [{
-                                    "index": chunk.index,
+                                    "index": chunk.choice_index,
                                     "delta": { "content": text },
                                     "finish_reason": null
                                 }]
@@ -1196,7 +1365,7 @@ impl Transformer for OpenAITransformer {
                                     "created": chrono::Utc::now().timestamp(),
                                     "model": "model",
                                     "choices": [{
-                                        "index": chunk.index,
+                                        "index": chunk.choice_index,
                                         "delta": {
                                             "provider_specific_fields": {
                                                 "thought_signatures": [sig]
@@ -1214,7 +1383,7 @@ impl Transformer for OpenAITransformer {
                                 "created": chrono::Utc::now().timestamp(),
                                 "model": "model",
                                 "choices": [{
-                                    "index": chunk.index,
+                                    "index": chunk.choice_index,
                                     "delta": { "reasoning_content": text },
                                     "finish_reason": null
                                 }]
@@ -1232,7 +1401,7 @@ impl Transformer for OpenAITransformer {
                                 "created": chrono::Utc::now().timestamp(),
                                 "model": "model",
                                 "choices": [{
-                                    "index": 0,
+                                    "index": chunk.choice_index,
                                     "delta": {
                                         "tool_calls": [{
                                             "index": index,
@@ -1263,18 +1432,14 @@ impl Transformer for OpenAITransformer {
                     "created": chrono::Utc::now().timestamp(),
                     "model": "model",
                     "choices": [{
-                        "index": 0,
+                        "index": chunk.choice_index,
                         "delta": {},
                         "finish_reason": finish_reason
                     }]
                 });
 
                 if let Some(ref usage) = chunk.usage {
-                    openai_chunk["usage"] = json!({
-                        "prompt_tokens": usage.input_tokens,
-                        "completion_tokens": usage.output_tokens,
-                        "total_tokens": usage.total_tokens()
-                    });
+                    openai_chunk["usage"] = json!(OpenAIUsage::from_unified(usage));
                 }
 
                 Ok(format!("data: {}\n\n", openai_chunk))
@@ -1288,6 +1453,17 @@ impl Transformer for OpenAITransformer {
         "/v1/chat/completions"
     }
 
+    fn requested_choices(&self, raw: &Value) -> u32 {
+        raw.get("n")
+            .and_then(|n| n.as_u64())
+            .map(|n| n.clamp(1, u32::MAX as u64) as u32)
+            .unwrap_or(1)
+    }
+
+    fn supports_multiple_choices(&self, _stream: bool) -> bool {
+        true
+    }
+
     fn can_handle(&self, raw: &Value) -> bool {
         // OpenAI format: has "messages" array and no Anthropic-specific fields
         raw.get("messages").is_some()
@@ -1319,6 +1495,7 @@ impl Transformer for OpenAITransformer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transformer::ResponseFormat;
 
     #[test]
     fn test_openai_transformer_protocol() {
@@ -1326,6 +1503,93 @@ mod tests {
         assert_eq!(transformer.protocol(), Protocol::OpenAI);
     }
 
+    #[test]
+    fn test_reasoning_effort_and_reasoning_tokens() {
+        let transformer = OpenAITransformer::new();
+        let raw = json!({
+            "model": "o3",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "reasoning_effort": "high"
+        });
+        let mut unified = transformer.transform_request_out(raw).unwrap();
+        assert_eq!(
+            unified.parameters.reasoning.as_ref().unwrap().effort,
+            Some(ReasoningEffort::High)
+        );
+        assert!(!unified.parameters.extra.contains_key("reasoning_effort"));
+
+        // Budget-only configs (e.g. from Anthropic) resolve to an effort level
+        unified.parameters.reasoning = Some(ReasoningConfig {
+            budget_tokens: Some(2048),
+            include_thoughts: Some(true),
+            ..Default::default()
+        });
+        let back = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(back["reasoning_effort"], "low");
+
+        let response = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "o3",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}],
+            "usage": {
+                "prompt_tokens": 5,
+                "completion_tokens": 40,
+                "total_tokens": 45,
+                "completion_tokens_details": {"reasoning_tokens": 32}
+            }
+        });
+        let unified = transformer.transform_response_in(response, "o3").unwrap();
+        assert_eq!(unified.usage.reasoning_tokens, Some(32));
+        let out = transformer
+            .transform_response_out(&unified, Protocol::OpenAI)
+            .unwrap();
+        assert_eq!(
+            out["usage"]["completion_tokens_details"]["reasoning_tokens"],
+            32
+        );
+    }
+
+    #[test]
+    fn test_file_content_part_roundtrip() {
+        let transformer = OpenAITransformer::new();
+        let raw = json!({
+            "model": "gpt-4o",
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "file", "file": {"file_data": "data:application/pdf;base64,JVBERi0=", "filename": "report.pdf"}},
+                    {"type": "file", "file": {"file_id": "file-abc"}},
+                    {"type": "text", "text": "Summarize"}
+                ]
+            }]
+        });
+
+        let unified = transformer.transform_request_out(raw).unwrap();
+        let content = &unified.messages[0].content;
+        assert!(matches!(
+            &content[0],
+            UnifiedContent::Document { source_type, media_type, data, title }
+                if source_type == "base64" && media_type == "application/pdf"
+                    && data == "JVBERi0=" && title.as_deref() == Some("report.pdf")
+        ));
+        assert!(matches!(
+            &content[1],
+            UnifiedContent::Document { source_type, data, .. } if source_type == "file" && data == "file-abc"
+        ));
+
+        let back = transformer.transform_request_in(&unified).unwrap();
+        let parts = &back["messages"][0]["content"];
+        assert_eq!(parts[0]["type"], "file");
+        assert_eq!(
+            parts[0]["file"]["file_data"],
+            "data:application/pdf;base64,JVBERi0="
+        );
+        assert_eq!(parts[0]["file"]["filename"], "report.pdf");
+        assert_eq!(parts[1]["file"]["file_id"], "file-abc");
+    }
+
     #[test]
     fn test_transform_request_out() {
         let transformer = OpenAITransformer::new();
@@ -1347,6 +1611,77 @@ mod tests {
         assert_eq!(unified.parameters.max_tokens, Some(100));
     }
 
+    #[test]
+    fn test_response_format_roundtrip() {
+        let transformer = OpenAITransformer::new();
+        let response_format = json!({
+            "type": "json_schema",
+            "json_schema": {"name": "person", "schema": {"type": "object"}, "strict": true}
+        });
+        let raw = json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hello!"}],
+            "response_format": response_format
+        });
+
+        let unified = transformer.transform_request_out(raw).unwrap();
+        assert!(matches!(
+            unified.parameters.response_format,
+            Some(ResponseFormat::JsonSchema { ref name, .. }) if name == "person"
+        ));
+        assert!(!unified.parameters.extra.contains_key("response_format"));
+
+        let out = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(out["response_format"], response_format);
+    }
+
+    #[test]
+    fn test_multiple_choices_roundtrip() {
+        let transformer = OpenAITransformer::new();
+        let raw = json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Hello!"}],
+            "n": 2
+        });
+        assert_eq!(transformer.requested_choices(&raw), 2);
+
+        let unified = transformer.transform_request_out(raw).unwrap();
+        assert_eq!(unified.parameters.n, Some(2));
+        assert!(!unified.parameters.extra.contains_key("n"));
+        let out = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(out["n"], 2);
+
+        let response = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4",
+            "choices": [
+                {"index": 1, "message": {"role": "assistant", "content": "second"}, "finish_reason": "length"},
+                {"index": 0, "message": {"role": "assistant", "content": "first"}, "finish_reason": "stop"}
+            ],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12}
+        });
+        let unified = transformer
+            .transform_response_in(response, "gpt-4")
+            .unwrap();
+        assert_eq!(unified.text_content(), "first");
+        assert_eq!(unified.additional_choices.len(), 1);
+        assert_eq!(
+            unified.additional_choices[0].stop_reason,
+            Some(StopReason::MaxTokens)
+        );
+
+        let out = transformer
+            .transform_response_out(&unified, Protocol::OpenAI)
+            .unwrap();
+        assert_eq!(out["choices"][0]["index"], 0);
+        assert_eq!(out["choices"][0]["message"]["content"], "first");
+        assert_eq!(out["choices"][1]["index"], 1);
+        assert_eq!(out["choices"][1]["message"]["content"], "second");
+        assert_eq!(out["choices"][1]["finish_reason"], "length");
+    }
+
     #[test]
     fn test_transform_request_in() {
         let transformer = OpenAITransformer::new();
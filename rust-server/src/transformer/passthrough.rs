@@ -317,6 +317,10 @@ impl Transformer for PassthroughTransformer {
                     .unwrap_or(0) as i32,
                 cache_read_tokens: None,
                 cache_write_tokens: None,
+                reasoning_tokens: u
+                    .pointer("/completion_tokens_details/reasoning_tokens")
+                    .and_then(|t| t.as_i64())
+                    .map(|t| t as i32),
             })
             .unwrap_or_default();
 
@@ -395,6 +399,7 @@ impl Transformer for PassthroughTransformer {
                     chunks.push(UnifiedStreamChunk {
                         chunk_type: ChunkType::ContentBlockDelta,
                         index: 0,
+                        choice_index: 0,
                         delta: Some(UnifiedContent::text(content)),
                         usage: None,
                         stop_reason: None,
@@ -422,6 +427,7 @@ impl Transformer for PassthroughTransformer {
                     chunks.push(UnifiedStreamChunk {
                         chunk_type: ChunkType::MessageDelta,
                         index: 0,
+                        choice_index: 0,
                         delta: None,
                         usage: None,
                         stop_reason: Some(stop_reason),
@@ -0,0 +1,819 @@
+//! Reversible redaction of personal data in prompts.
+//!
+//! Emails, phone numbers, card numbers, national IDs and custom patterns are
+//! replaced with stable placeholders such as `<EMAIL_1>` before a request
+//! leaves the proxy. The same value always maps to the same placeholder
+//! within a request, so the model can still refer to it, and placeholders in
+//! the response are swapped back for the original values.
+//!
+//! Redaction is enabled per provider through the `pii_redaction` provider
+//! param, or per credential (which takes precedence):
+//!
+//! ```json
+//! {"pii_redaction": {"entities": ["email", "phone"], "patterns": [{"label": "IBAN", "regex": "[A-Z]{2}\\d{2}[A-Z0-9]{11,30}"}]}}
+//! ```
+//!
+//! Thinking blocks in the request are left untouched, since providers
+//! verify their signatures. Streams are restored by [`PiiStreamRestorer`],
+//! driven from `CrossProtocolStreamState`, which holds back text that could
+//! be the start of a placeholder split across deltas.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use super::features::FeatureTransformer;
+use super::unified::{
+    ChunkType, UnifiedContent, UnifiedMessage, UnifiedRequest, UnifiedResponse, UnifiedStreamChunk,
+    UnifiedToolCall,
+};
+use crate::core::config::{PiiEntity, PiiRedactionConfig};
+use crate::core::error::Result;
+
+/// Provider param enabling PII redaction (`true` or a [`PiiRedactionConfig`]).
+pub const PII_REDACTION_PARAM: &str = "pii_redaction";
+
+/// Longest placeholder held back while waiting for the rest of a stream delta.
+const MAX_PLACEHOLDER_LEN: usize = 64;
+
+lazy_static! {
+    static ref PLACEHOLDER_RE: Regex = Regex::new(r"<[A-Z][A-Z0-9_]*_[0-9]+>").unwrap();
+    static ref LABEL_RE: Regex = Regex::new(r"^[A-Z][A-Z0-9_]*$").unwrap();
+    static ref EMAIL_RE: Regex =
+        Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap();
+    static ref PHONE_RE: Regex =
+        Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{2,4}(?:[ .-]?\d{2,4}){2,4}")
+            .unwrap();
+    static ref CREDIT_CARD_RE: Regex = Regex::new(r"\d(?:[ -]?\d){12,18}").unwrap();
+    static ref NATIONAL_ID_RE: Regex = Regex::new(r"\d{3}-\d{2}-\d{4}").unwrap();
+}
+
+// ============================================================================
+// Detection
+// ============================================================================
+
+/// A pattern and the placeholder label of its matches.
+#[derive(Debug)]
+struct Detector {
+    label: String,
+    regex: Regex,
+    /// Extra check on a match (checksums, digit counts)
+    accept: fn(&str) -> bool,
+    /// Whether matches must not touch letters or digits on either side
+    bounded: bool,
+}
+
+impl Detector {
+    fn builtin(entity: PiiEntity) -> Self {
+        let (regex, accept): (&Regex, fn(&str) -> bool) = match entity {
+            PiiEntity::Email => (&EMAIL_RE, |_| true),
+            PiiEntity::Phone => (&PHONE_RE, |m| (9..=15).contains(&digit_count(m))),
+            PiiEntity::CreditCard => (&CREDIT_CARD_RE, passes_luhn),
+            PiiEntity::NationalId => (&NATIONAL_ID_RE, is_valid_ssn),
+        };
+        Self {
+            label: entity.label().to_string(),
+            regex: regex.clone(),
+            accept,
+            bounded: true,
+        }
+    }
+}
+
+fn digit_count(s: &str) -> usize {
+    s.chars().filter(char::is_ascii_digit).count()
+}
+
+fn passes_luhn(s: &str) -> bool {
+    let digits: Vec<u32> = s.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// SSN area, group and serial numbers that are never issued are rejected.
+fn is_valid_ssn(s: &str) -> bool {
+    let mut parts = s.split('-');
+    let (Some(area), Some(group), Some(serial)) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+fn is_word_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+// ============================================================================
+// Redactor
+// ============================================================================
+
+/// Placeholders assigned within one request.
+#[derive(Debug, Default)]
+struct Vault {
+    /// Original value by placeholder
+    originals: HashMap<String, String>,
+    /// Placeholder by original value
+    placeholders: HashMap<String, String>,
+    /// Distinct values redacted per label
+    counts: BTreeMap<String, usize>,
+}
+
+impl Vault {
+    fn placeholder(&mut self, label: &str, original: &str) -> String {
+        if let Some(placeholder) = self.placeholders.get(original) {
+            return placeholder.clone();
+        }
+        let count = self.counts.entry(label.to_string()).or_default();
+        *count += 1;
+        let placeholder = format!("<{}_{}>", label, count);
+        self.originals
+            .insert(placeholder.clone(), original.to_string());
+        self.placeholders
+            .insert(original.to_string(), placeholder.clone());
+        placeholder
+    }
+}
+
+/// Redacts personal data from one request and restores it in the response.
+///
+/// Clones share the placeholders assigned so far, so the redactor can be
+/// handed to the stream state after the request was redacted.
+#[derive(Debug, Clone)]
+pub struct PiiRedactor {
+    detectors: Arc<Vec<Detector>>,
+    vault: Arc<Mutex<Vault>>,
+}
+
+impl PiiRedactor {
+    /// Create a redactor, failing on invalid custom labels or patterns.
+    pub fn new(config: &PiiRedactionConfig) -> std::result::Result<Self, String> {
+        let mut detectors = Vec::new();
+        for pattern in &config.patterns {
+            if !LABEL_RE.is_match(&pattern.label) {
+                return Err(format!(
+                    "Invalid PII pattern label '{}': use uppercase letters, digits and '_'",
+                    pattern.label
+                ));
+            }
+            let regex = Regex::new(&pattern.regex)
+                .map_err(|e| format!("Invalid PII pattern '{}' regex: {}", pattern.label, e))?;
+            detectors.push(Detector {
+                label: pattern.label.clone(),
+                regex,
+                accept: |_| true,
+                bounded: false,
+            });
+        }
+        // Most specific entities first: a valid card or SSN also looks like a phone number
+        for entity in [
+            PiiEntity::Email,
+            PiiEntity::CreditCard,
+            PiiEntity::NationalId,
+            PiiEntity::Phone,
+        ] {
+            if config.entities.contains(&entity) {
+                detectors.push(Detector::builtin(entity));
+            }
+        }
+        Ok(Self {
+            detectors: Arc::new(detectors),
+            vault: Arc::new(Mutex::new(Vault::default())),
+        })
+    }
+
+    /// Redactor for a request, or `None` when redaction is disabled.
+    ///
+    /// The credential's settings take precedence over the provider param.
+    /// Invalid settings are an error: falling back to the defaults would
+    /// quietly drop the custom patterns.
+    pub fn for_request(
+        provider_param: Option<&Value>,
+        credential: Option<&PiiRedactionConfig>,
+    ) -> std::result::Result<Option<Self>, String> {
+        let config = match credential {
+            Some(config) => config.clone(),
+            None => match Self::provider_config(provider_param)? {
+                Some(config) => config,
+                None => return Ok(None),
+            },
+        };
+        if !config.enabled {
+            return Ok(None);
+        }
+        Self::new(&config).map(Some)
+    }
+
+    /// Settings of the provider param, or `None` when it is absent or `false`.
+    pub fn provider_config(
+        provider_param: Option<&Value>,
+    ) -> std::result::Result<Option<PiiRedactionConfig>, String> {
+        match provider_param {
+            None | Some(Value::Bool(false)) => Ok(None),
+            Some(Value::Bool(true)) => Ok(Some(PiiRedactionConfig::default())),
+            Some(value) => serde_json::from_value(value.clone())
+                .map(Some)
+                .map_err(|e| format!("invalid {} param: {}", PII_REDACTION_PARAM, e)),
+        }
+    }
+
+    /// Replace personal data in `text` with placeholders.
+    pub fn redact_text(&self, text: &str) -> String {
+        let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+        for (priority, detector) in self.detectors.iter().enumerate() {
+            for m in detector.regex.find_iter(text) {
+                if m.is_empty() || !(detector.accept)(m.as_str()) {
+                    continue;
+                }
+                if detector.bounded
+                    && (is_word_char(text[..m.start()].chars().next_back())
+                        || is_word_char(text[m.end()..].chars().next()))
+                {
+                    continue;
+                }
+                spans.push((m.start(), m.end(), priority));
+            }
+        }
+        if spans.is_empty() {
+            return text.to_string();
+        }
+        // Leftmost match wins, then the longest, then the earlier detector
+        spans.sort_by_key(|&(start, end, priority)| (start, std::cmp::Reverse(end), priority));
+
+        let mut vault = self.vault.lock().unwrap_or_else(|e| e.into_inner());
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, priority) in spans {
+            if start < last {
+                continue;
+            }
+            output.push_str(&text[last..start]);
+            output.push_str(&vault.placeholder(&self.detectors[priority].label, &text[start..end]));
+            last = end;
+        }
+        output.push_str(&text[last..]);
+        output
+    }
+
+    /// Replace known placeholders in `text` with the original values.
+    pub fn restore_text(&self, text: &str) -> String {
+        self.restore_with(text, |original| original.to_string())
+    }
+
+    /// Restore placeholders inside JSON source text, escaping the originals.
+    fn restore_json_fragment(&self, text: &str) -> String {
+        self.restore_with(text, |original| {
+            let quoted = Value::String(original.to_string()).to_string();
+            quoted[1..quoted.len() - 1].to_string()
+        })
+    }
+
+    fn restore_with(&self, text: &str, render: impl Fn(&str) -> String) -> String {
+        if !text.contains('<') {
+            return text.to_string();
+        }
+        let vault = self.vault.lock().unwrap_or_else(|e| e.into_inner());
+        PLACEHOLDER_RE
+            .replace_all(text, |caps: &regex::Captures| {
+                match vault.originals.get(&caps[0]) {
+                    Some(original) => render(original),
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// Redact every string inside a JSON value, for bodies that bypass the
+    /// transformer pipeline.
+    pub fn redact_value(&self, value: &mut Value) {
+        Self::map_strings(value, &|s: &str| self.redact_text(s));
+    }
+
+    /// Restore every string inside a JSON value.
+    pub fn restore_value(&self, value: &mut Value) {
+        Self::map_strings(value, &|s: &str| self.restore_text(s));
+    }
+
+    /// Apply `f` to every string inside a JSON value (object keys excluded).
+    fn map_strings(value: &mut Value, f: &impl Fn(&str) -> String) {
+        match value {
+            Value::String(s) => *s = f(s),
+            Value::Array(items) => items.iter_mut().for_each(|v| Self::map_strings(v, f)),
+            Value::Object(fields) => fields.values_mut().for_each(|v| Self::map_strings(v, f)),
+            _ => {}
+        }
+    }
+
+    fn redact_message(&self, message: &mut UnifiedMessage) {
+        let redact = |s: &str| self.redact_text(s);
+        for content in &mut message.content {
+            match content {
+                UnifiedContent::Text { text } => *text = self.redact_text(text),
+                UnifiedContent::ToolUse { input, .. } => Self::map_strings(input, &redact),
+                UnifiedContent::ToolResult { content, .. } => Self::map_strings(content, &redact),
+                UnifiedContent::Document {
+                    source_type, data, ..
+                } if source_type == "text" => *data = self.redact_text(data),
+                _ => {}
+            }
+        }
+        for call in &mut message.tool_calls {
+            Self::map_strings(&mut call.arguments, &redact);
+        }
+    }
+
+    fn restore_content(&self, content: &mut [UnifiedContent], tool_calls: &mut [UnifiedToolCall]) {
+        let restore = |s: &str| self.restore_text(s);
+        for block in content {
+            match block {
+                UnifiedContent::Text { text } | UnifiedContent::Thinking { text, .. } => {
+                    *text = self.restore_text(text)
+                }
+                UnifiedContent::ToolUse { input, .. } => Self::map_strings(input, &restore),
+                _ => {}
+            }
+        }
+        for call in tool_calls {
+            Self::map_strings(&mut call.arguments, &restore);
+        }
+    }
+
+    /// Restore the placeholders of a single stream delta or block start.
+    fn restore_block(&self, content: &mut UnifiedContent) {
+        match content {
+            UnifiedContent::Text { text } | UnifiedContent::Thinking { text, .. } => {
+                *text = self.restore_text(text)
+            }
+            UnifiedContent::ToolUse { input, .. } => {
+                Self::map_strings(input, &|s: &str| self.restore_text(s))
+            }
+            UnifiedContent::ToolInputDelta { partial_json, .. } => {
+                *partial_json = self.restore_json_fragment(partial_json)
+            }
+            _ => {}
+        }
+    }
+
+    /// Distinct values redacted so far, by placeholder label.
+    pub fn redaction_counts(&self) -> BTreeMap<String, usize> {
+        self.vault
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .counts
+            .clone()
+    }
+
+    /// Redaction counts for the request log, or `None` when nothing was redacted.
+    pub fn log_summary(&self) -> Option<Value> {
+        let counts = self.redaction_counts();
+        (!counts.is_empty()).then(|| serde_json::json!(counts))
+    }
+}
+
+impl FeatureTransformer for PiiRedactor {
+    fn transform_request(&self, request: &mut UnifiedRequest) -> Result<()> {
+        if let Some(system) = request.system.as_mut() {
+            *system = self.redact_text(system);
+        }
+        request
+            .messages
+            .iter_mut()
+            .for_each(|message| self.redact_message(message));
+        Ok(())
+    }
+
+    fn transform_response(&self, response: &mut UnifiedResponse) -> Result<()> {
+        self.restore_content(&mut response.content, &mut response.tool_calls);
+        for choice in &mut response.additional_choices {
+            self.restore_content(&mut choice.content, &mut choice.tool_calls);
+        }
+        Ok(())
+    }
+
+    fn transform_stream_chunk(&self, chunk: &mut UnifiedStreamChunk) -> Result<()> {
+        // Placeholders split across deltas are handled by PiiStreamRestorer
+        if let Some(delta) = chunk.delta.as_mut() {
+            self.restore_block(delta);
+        }
+        if let Some(block) = chunk.content_block.as_mut() {
+            self.restore_block(block);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "pii_redaction"
+    }
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Text of a content block held back until more of the stream arrives.
+#[derive(Debug, Clone)]
+pub(super) struct HeldDelta {
+    pub(super) text: String,
+    /// Empty delta of the block's kind, filled with the held text on release
+    pub(super) template: UnifiedContent,
+}
+
+/// Restores placeholders in streamed deltas.
+///
+/// A delta ending in something that could grow into a placeholder (`<EMA`)
+/// has that tail held back until the next delta of the same block, the
+/// block's end or the end of the message.
+#[derive(Debug, Clone)]
+pub struct PiiStreamRestorer {
+    redactor: PiiRedactor,
+    /// Held back text by (choice index, block index)
+    held: HashMap<(usize, usize), HeldDelta>,
+}
+
+impl PiiStreamRestorer {
+    /// Create a restorer for the placeholders assigned by `redactor`.
+    pub fn new(redactor: PiiRedactor) -> Self {
+        Self {
+            redactor,
+            held: HashMap::new(),
+        }
+    }
+
+    /// Process unified chunks, restoring placeholders in their deltas.
+    pub fn process(&mut self, chunks: Vec<UnifiedStreamChunk>) -> Vec<UnifiedStreamChunk> {
+        let mut result = Vec::new();
+        for mut chunk in chunks {
+            match chunk.chunk_type {
+                ChunkType::ContentBlockDelta => {
+                    if self.restore_delta(&mut chunk) {
+                        result.push(chunk);
+                    }
+                }
+                ChunkType::ContentBlockStart => {
+                    if let Some(block) = chunk.content_block.as_mut() {
+                        self.redactor.restore_block(block);
+                    }
+                    result.push(chunk);
+                }
+                ChunkType::ContentBlockStop => {
+                    result.extend(self.release((chunk.choice_index, chunk.index)));
+                    result.push(chunk);
+                }
+                ChunkType::MessageDelta | ChunkType::MessageStop => {
+                    result.extend(self.flush());
+                    result.push(chunk);
+                }
+                _ => result.push(chunk),
+            }
+        }
+        result
+    }
+
+    /// Emit all held back text.
+    pub fn flush(&mut self) -> Vec<UnifiedStreamChunk> {
+        let mut keys: Vec<(usize, usize)> = self.held.keys().copied().collect();
+        keys.sort_unstable();
+        keys.into_iter()
+            .filter_map(|key| self.release(key))
+            .collect()
+    }
+
+    /// Restore a delta in place; returns false when all of it is held back.
+    fn restore_delta(&mut self, chunk: &mut UnifiedStreamChunk) -> bool {
+        let Some(delta) = chunk.delta.as_mut() else {
+            return true;
+        };
+        let (text, is_json, has_signature) = match delta {
+            UnifiedContent::Text { text } => (text, false, false),
+            UnifiedContent::Thinking { text, signature } => (text, false, signature.is_some()),
+            UnifiedContent::ToolInputDelta { partial_json, .. } => (partial_json, true, false),
+            _ => return true,
+        };
+
+        let mut pending = self
+            .held
+            .remove(&(chunk.choice_index, chunk.index))
+            .map(|held| held.text)
+            .unwrap_or_default();
+        pending.push_str(text);
+        let keep = held_back_len(&pending);
+        let tail = pending.split_off(pending.len() - keep);
+        *text = if is_json {
+            self.redactor.restore_json_fragment(&pending)
+        } else {
+            self.redactor.restore_text(&pending)
+        };
+        let empty = text.is_empty();
+
+        if !tail.is_empty() {
+            let mut template = delta.clone();
+            set_delta_text(&mut template, String::new());
+            if let UnifiedContent::Thinking { signature, .. } = &mut template {
+                *signature = None;
+            }
+            self.held.insert(
+                (chunk.choice_index, chunk.index),
+                HeldDelta {
+                    text: tail,
+                    template,
+                },
+            );
+        }
+        !empty || has_signature
+    }
+
+    fn release(&mut self, key: (usize, usize)) -> Option<UnifiedStreamChunk> {
+        let (choice_index, index) = key;
+        let held = self.held.remove(&key)?;
+        let mut delta = held.template;
+        let text = match delta {
+            UnifiedContent::ToolInputDelta { .. } => {
+                self.redactor.restore_json_fragment(&held.text)
+            }
+            _ => self.redactor.restore_text(&held.text),
+        };
+        set_delta_text(&mut delta, text);
+        let mut chunk = UnifiedStreamChunk::content_block_delta(index, delta);
+        chunk.choice_index = choice_index;
+        Some(chunk)
+    }
+}
+
+pub(super) fn set_delta_text(delta: &mut UnifiedContent, value: String) {
+    match delta {
+        UnifiedContent::Text { text } | UnifiedContent::Thinking { text, .. } => *text = value,
+        UnifiedContent::ToolInputDelta { partial_json, .. } => *partial_json = value,
+        _ => {}
+    }
+}
+
+/// Length of the suffix of `text` that could still grow into a placeholder.
+fn held_back_len(text: &str) -> usize {
+    let Some(start) = text.rfind('<') else {
+        return 0;
+    };
+    let tail = &text[start + 1..];
+    let partial = tail.len() < MAX_PLACEHOLDER_LEN
+        && tail
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_');
+    if partial {
+        text.len() - start
+    } else {
+        0
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::PiiPattern;
+    use crate::transformer::unified::{Role, UnifiedUsage};
+    use serde_json::json;
+
+    fn redactor() -> PiiRedactor {
+        PiiRedactor::new(&PiiRedactionConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_redacts_builtin_entities_with_stable_placeholders() {
+        let redactor = redactor();
+        let redacted = redactor.redact_text(
+            "Mail jane.doe@example.com or call +1 415-555-0132. Card 4111 1111 1111 1111, \
+             SSN 123-45-6789. Again: jane.doe@example.com",
+        );
+        assert_eq!(
+            redacted,
+            "Mail <EMAIL_1> or call <PHONE_1>. Card <CREDIT_CARD_1>, \
+             SSN <NATIONAL_ID_1>. Again: <EMAIL_1>"
+        );
+        assert_eq!(
+            redactor.restore_text("Reply to <EMAIL_1> about <UNKNOWN_1>"),
+            "Reply to jane.doe@example.com about <UNKNOWN_1>"
+        );
+        assert_eq!(
+            redactor.log_summary(),
+            Some(json!({"CREDIT_CARD": 1, "EMAIL": 1, "NATIONAL_ID": 1, "PHONE": 1}))
+        );
+    }
+
+    #[test]
+    fn test_ignores_numbers_failing_validation() {
+        let redactor = redactor();
+        let text = "Order 4111 1111 1111 1112 shipped on 2024-01-15, id abc12345678";
+        assert_eq!(redactor.redact_text(text), text);
+        assert_eq!(redactor.log_summary(), None);
+
+        let ids_only = PiiRedactor::new(&PiiRedactionConfig {
+            entities: vec![PiiEntity::NationalId],
+            ..Default::default()
+        })
+        .unwrap();
+        let text = "SSN 000-12-3456 or 666-12-3456 or 123-00-4567";
+        assert_eq!(ids_only.redact_text(text), text);
+    }
+
+    #[test]
+    fn test_custom_patterns_and_invalid_config() {
+        let config = PiiRedactionConfig {
+            entities: vec![],
+            patterns: vec![PiiPattern {
+                label: "EMPLOYEE_ID".to_string(),
+                regex: r"EMP-\d{6}".to_string(),
+            }],
+            ..Default::default()
+        };
+        let redactor = PiiRedactor::new(&config).unwrap();
+        assert_eq!(
+            redactor.redact_text("EMP-123456 (jane@example.com)"),
+            "<EMPLOYEE_ID_1> (jane@example.com)"
+        );
+
+        let mut invalid = config.clone();
+        invalid.patterns[0].label = "employee".to_string();
+        assert!(PiiRedactor::new(&invalid).is_err());
+        invalid.patterns[0].label = "EMPLOYEE".to_string();
+        invalid.patterns[0].regex = "(".to_string();
+        assert!(PiiRedactor::new(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_for_request_prefers_credential_settings() {
+        let disabled = PiiRedactionConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let enabled = |param: Option<&Value>, credential| {
+            PiiRedactor::for_request(param, credential)
+                .unwrap()
+                .is_some()
+        };
+        assert!(enabled(Some(&json!(true)), None));
+        assert!(!enabled(Some(&json!(true)), Some(&disabled)));
+        assert!(!enabled(None, None));
+        assert!(!enabled(Some(&json!(false)), None));
+        assert!(enabled(None, Some(&PiiRedactionConfig::default())));
+    }
+
+    #[test]
+    fn test_for_request_rejects_invalid_settings() {
+        for param in [
+            json!("yes"),
+            json!({"entities": ["passport"]}),
+            json!({"patterns": [{"label": "EMPLOYEE", "regex": "("}]}),
+        ] {
+            assert!(
+                PiiRedactor::for_request(Some(&param), None).is_err(),
+                "{}",
+                param
+            );
+        }
+
+        let invalid = PiiRedactionConfig {
+            patterns: vec![PiiPattern {
+                label: "employee".to_string(),
+                regex: r"EMP-\d{6}".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(PiiRedactor::for_request(None, Some(&invalid)).is_err());
+    }
+
+    #[test]
+    fn test_request_redacted_and_response_restored() {
+        let redactor = redactor();
+        let mut assistant = UnifiedMessage::with_content(Role::Assistant, vec![]);
+        assistant.tool_calls.push(UnifiedToolCall {
+            id: "call_1".to_string(),
+            name: "lookup".to_string(),
+            arguments: json!({"email": "bob@corp.io"}),
+        });
+        let mut request = UnifiedRequest::new(
+            "gpt-4",
+            vec![
+                UnifiedMessage::user("Contact bob@corp.io"),
+                assistant,
+                UnifiedMessage::with_content(
+                    Role::User,
+                    vec![UnifiedContent::tool_result(
+                        "call_1",
+                        json!([{"type": "text", "text": "phone 020 7946 0958"}]),
+                        false,
+                    )],
+                ),
+            ],
+        )
+        .with_system("Customer: bob@corp.io");
+        redactor.transform_request(&mut request).unwrap();
+
+        assert_eq!(request.system.as_deref(), Some("Customer: <EMAIL_1>"));
+        assert_eq!(request.messages[0].text_content(), "Contact <EMAIL_1>");
+        assert_eq!(
+            request.messages[1].tool_calls[0].arguments,
+            json!({"email": "<EMAIL_1>"})
+        );
+        assert!(matches!(
+            &request.messages[2].content[0],
+            UnifiedContent::ToolResult { content, .. } if content[0]["text"] == "phone <PHONE_1>"
+        ));
+
+        let mut response =
+            UnifiedResponse::text("id", "gpt-4", "Emailed <EMAIL_1>", UnifiedUsage::default());
+        response.tool_calls.push(UnifiedToolCall {
+            id: "call_2".to_string(),
+            name: "send".to_string(),
+            arguments: json!({"to": "<EMAIL_1>"}),
+        });
+        redactor.transform_response(&mut response).unwrap();
+        assert_eq!(response.text_content(), "Emailed bob@corp.io");
+        assert_eq!(
+            response.tool_calls[0].arguments,
+            json!({"to": "bob@corp.io"})
+        );
+    }
+
+    fn deltas(chunks: &[UnifiedStreamChunk]) -> String {
+        chunks
+            .iter()
+            .filter_map(|c| match &c.delta {
+                Some(UnifiedContent::Text { text }) => Some(text.as_str()),
+                Some(UnifiedContent::ToolInputDelta { partial_json, .. }) => {
+                    Some(partial_json.as_str())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stream_restores_placeholders_split_across_deltas() {
+        let config = PiiRedactionConfig {
+            patterns: vec![PiiPattern {
+                label: "QUOTE".to_string(),
+                regex: r#""[^"]*""#.to_string(),
+            }],
+            ..Default::default()
+        };
+        let redactor = PiiRedactor::new(&config).unwrap();
+        redactor.redact_text(r#"ann@example.com, "quoted""#);
+        let mut restorer = PiiStreamRestorer::new(redactor);
+
+        let mut output = Vec::new();
+        for part in ["Write to <", "EMA", "IL_1> and <QUOTE_", "1>", " or <b>"] {
+            output.extend(
+                restorer.process(vec![UnifiedStreamChunk::content_block_delta(
+                    0,
+                    UnifiedContent::text(part),
+                )]),
+            );
+        }
+        output.extend(
+            restorer.process(vec![UnifiedStreamChunk::content_block_delta(
+                1,
+                UnifiedContent::tool_input_delta(0, r#"{"to": <QUOTE_"#),
+            )]),
+        );
+        output.extend(
+            restorer.process(vec![UnifiedStreamChunk::content_block_delta(
+                1,
+                UnifiedContent::tool_input_delta(0, "1>}"),
+            )]),
+        );
+        output.extend(restorer.process(vec![UnifiedStreamChunk::content_block_stop(1)]));
+        output.extend(
+            restorer.process(vec![UnifiedStreamChunk::content_block_delta(
+                0,
+                UnifiedContent::text(" <EMAIL_"),
+            )]),
+        );
+        output.extend(restorer.process(vec![UnifiedStreamChunk::message_delta(
+            crate::transformer::StopReason::EndTurn,
+            UnifiedUsage::default(),
+        )]));
+
+        assert_eq!(
+            deltas(&output),
+            r#"Write to ann@example.com and "quoted" or <b>{"to": \"quoted\"} <EMAIL_"#
+        );
+        assert!(output
+            .iter()
+            .all(|c| !matches!(&c.delta, Some(UnifiedContent::Text { text }) if text.is_empty())));
+    }
+}
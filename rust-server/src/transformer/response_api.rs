@@ -9,10 +9,12 @@
 //! - Structured output with JSON schemas
 //! - Multiple modalities
 
+use super::structured_output::parse_openai_response_format;
+use super::tool_arguments::parse_raw_arguments;
 use super::{
-    ChunkType, Protocol, Result, Role, StopReason, Transformer, UnifiedContent, UnifiedMessage,
-    UnifiedParameters, UnifiedRequest, UnifiedResponse, UnifiedStreamChunk, UnifiedTool,
-    UnifiedToolCall, UnifiedUsage,
+    ChunkType, Protocol, ReasoningConfig, ReasoningEffort, ResponseFormat, Result, Role,
+    StopReason, Transformer, UnifiedContent, UnifiedMessage, UnifiedParameters, UnifiedRequest,
+    UnifiedResponse, UnifiedStreamChunk, UnifiedTool, UnifiedToolCall, UnifiedUsage,
 };
 use crate::core::AppError;
 use bytes::Bytes;
@@ -66,7 +68,16 @@ pub enum ResponseContentPart {
     #[serde(rename = "input_image")]
     InputImage { image_url: String },
     #[serde(rename = "input_file")]
-    InputFile { file_id: String },
+    InputFile {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_data: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_url: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+    },
     #[serde(rename = "tool_use")]
     ToolUse {
         id: String,
@@ -169,6 +180,31 @@ pub struct ResponseUsage {
     pub input_tokens: i32,
     pub output_tokens: i32,
     pub total_tokens: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens_details: Option<ResponseOutputTokensDetails>,
+}
+
+/// Response API output token breakdown.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ResponseOutputTokensDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<i32>,
+}
+
+impl ResponseUsage {
+    /// Build Response API usage from unified usage.
+    fn from_unified(usage: &UnifiedUsage) -> Self {
+        ResponseUsage {
+            input_tokens: usage.input_tokens,
+            output_tokens: usage.output_tokens,
+            total_tokens: usage.total_tokens(),
+            output_tokens_details: usage.reasoning_tokens.map(|tokens| {
+                ResponseOutputTokensDetails {
+                    reasoning_tokens: Some(tokens),
+                }
+            }),
+        }
+    }
 }
 
 /// Response API response.
@@ -221,6 +257,46 @@ impl ResponseApiTransformer {
         }
     }
 
+    /// Convert a Response API `input_file` part to unified content.
+    ///
+    /// Inline data and URLs become documents; a bare `file_id` stays a file reference.
+    fn input_file_to_unified(
+        file_id: &Option<String>,
+        file_data: &Option<String>,
+        file_url: &Option<String>,
+        filename: &Option<String>,
+    ) -> UnifiedContent {
+        if let Some(file_data) = file_data {
+            let (media_type, data) = match file_data
+                .strip_prefix("data:")
+                .and_then(|rest| rest.split_once(','))
+            {
+                Some((header, data)) => {
+                    (header.split(';').next().unwrap_or("application/pdf"), data)
+                }
+                None => ("application/pdf", file_data.as_str()),
+            };
+            return UnifiedContent::Document {
+                source_type: "base64".to_string(),
+                media_type: media_type.to_string(),
+                data: data.to_string(),
+                title: filename.clone(),
+            };
+        }
+        if let Some(file_url) = file_url {
+            return UnifiedContent::Document {
+                source_type: "url".to_string(),
+                media_type: String::new(),
+                data: file_url.clone(),
+                title: filename.clone(),
+            };
+        }
+        UnifiedContent::File {
+            file_id: file_id.clone().unwrap_or_default(),
+            filename: filename.clone(),
+        }
+    }
+
     /// Convert Response API content to unified content.
     fn content_to_unified(content: &ResponseContent) -> Vec<UnifiedContent> {
         match content {
@@ -233,16 +309,18 @@ impl ResponseApiTransformer {
                     ResponseContentPart::InputImage { image_url } => {
                         UnifiedContent::image_url(image_url)
                     }
-                    ResponseContentPart::InputFile { file_id } => UnifiedContent::File {
-                        file_id: file_id.clone(),
-                        filename: None,
-                    },
+                    ResponseContentPart::InputFile {
+                        file_id,
+                        file_data,
+                        file_url,
+                        filename,
+                    } => Self::input_file_to_unified(file_id, file_data, file_url, filename),
                     ResponseContentPart::ToolUse {
                         id,
                         name,
                         arguments,
                     } => {
-                        let args: Value = serde_json::from_str(arguments).unwrap_or(json!({}));
+                        let args = parse_raw_arguments(arguments);
                         UnifiedContent::tool_use(id, name, args)
                     }
                     ResponseContentPart::ToolResult {
@@ -291,9 +369,42 @@ impl ResponseApiTransformer {
                 UnifiedContent::Image { data, .. } => Some(ResponseContentPart::InputImage {
                     image_url: data.clone(),
                 }),
-                UnifiedContent::File { file_id, .. } => Some(ResponseContentPart::InputFile {
-                    file_id: file_id.clone(),
-                }),
+                UnifiedContent::File { file_id, filename } => {
+                    Some(ResponseContentPart::InputFile {
+                        file_id: Some(file_id.clone()),
+                        file_data: None,
+                        file_url: None,
+                        filename: filename.clone(),
+                    })
+                }
+                UnifiedContent::Document {
+                    source_type,
+                    media_type,
+                    data,
+                    title,
+                } => {
+                    let (file_id, file_data, file_url) = match source_type.as_str() {
+                        "text" => {
+                            return Some(ResponseContentPart::InputText { text: data.clone() })
+                        }
+                        "file" => (Some(data.clone()), None, None),
+                        "url" => (None, None, Some(data.clone())),
+                        _ => (
+                            None,
+                            Some(format!("data:{};base64,{}", media_type, data)),
+                            None,
+                        ),
+                    };
+                    let filename = title
+                        .clone()
+                        .or_else(|| file_data.as_ref().map(|_| "document.pdf".to_string()));
+                    Some(ResponseContentPart::InputFile {
+                        file_id,
+                        file_data,
+                        file_url,
+                        filename,
+                    })
+                }
                 UnifiedContent::ToolUse { id, name, input } => Some(ResponseContentPart::ToolUse {
                     id: id.clone(),
                     name: name.clone(),
@@ -420,7 +531,7 @@ impl ResponseApiTransformer {
                     arguments,
                     ..
                 } => {
-                    let args: Value = serde_json::from_str(arguments).unwrap_or(json!({}));
+                    let args = parse_raw_arguments(arguments);
                     content.push(UnifiedContent::tool_use(call_id, name, args.clone()));
                     tool_calls.push(UnifiedToolCall {
                         id: id.clone(),
@@ -435,6 +546,38 @@ impl ResponseApiTransformer {
     }
 
     /// Convert status to stop reason.
+    /// Parse the usage object of a streamed `response.completed`/`response.done` event.
+    fn stream_usage_to_unified(usage: &Value) -> UnifiedUsage {
+        let tokens = |key: &str| usage.get(key).and_then(|t| t.as_i64()).unwrap_or(0) as i32;
+        UnifiedUsage {
+            reasoning_tokens: usage
+                .pointer("/output_tokens_details/reasoning_tokens")
+                .and_then(|t| t.as_i64())
+                .map(|t| t as i32),
+            ..UnifiedUsage::new(tokens("input_tokens"), tokens("output_tokens"))
+        }
+    }
+
+    /// Parse the `reasoning` request option (`effort`, `summary`).
+    fn reasoning_from_request(reasoning: &Value) -> Option<ReasoningConfig> {
+        let effort = reasoning
+            .get("effort")
+            .and_then(|e| e.as_str())
+            .and_then(ReasoningEffort::parse);
+        let include_thoughts = reasoning
+            .get("summary")
+            .filter(|s| !s.is_null())
+            .map(|_| true);
+        if effort.is_none() && include_thoughts.is_none() {
+            return None;
+        }
+        Some(ReasoningConfig {
+            effort,
+            budget_tokens: None,
+            include_thoughts,
+        })
+    }
+
     fn status_to_stop_reason(status: &str) -> StopReason {
         match status {
             "completed" => StopReason::EndTurn,
@@ -467,6 +610,33 @@ impl Transformer for ResponseApiTransformer {
         // Convert tools
         let tools = Self::tools_to_unified(&request.tools);
 
+        // Structured output: `text.format`, or the legacy chat-style `response_format`
+        let mut extra = request.extra;
+        let mut response_format = request
+            .response_format
+            .as_ref()
+            .and_then(parse_openai_response_format);
+        let mut text_emptied = false;
+        if let Some(text) = extra.get_mut("text").and_then(|t| t.as_object_mut()) {
+            if let Some(format) = text
+                .get("format")
+                .and_then(|f| serde_json::from_value::<ResponseFormat>(f.clone()).ok())
+            {
+                response_format = Some(format);
+                text.remove("format");
+                text_emptied = text.is_empty();
+            }
+        }
+        if text_emptied {
+            extra.remove("text");
+        }
+        let reasoning = extra
+            .get("reasoning")
+            .and_then(Self::reasoning_from_request);
+        if reasoning.is_some() {
+            extra.remove("reasoning");
+        }
+
         // Build parameters
         let parameters = UnifiedParameters {
             temperature: request.temperature,
@@ -475,7 +645,10 @@ impl Transformer for ResponseApiTransformer {
             top_k: None,
             stop_sequences: None,
             stream: request.stream,
-            extra: request.extra,
+            response_format,
+            n: None,
+            reasoning,
+            extra,
         };
 
         Ok(UnifiedRequest {
@@ -523,12 +696,33 @@ impl Transformer for ResponseApiTransformer {
         if unified.parameters.stream {
             request["stream"] = json!(true);
         }
+        if let Some(ref reasoning) = unified.parameters.reasoning {
+            let mut config = json!({});
+            if let Some(effort) = reasoning.resolved_effort() {
+                config["effort"] = json!(effort.as_str());
+            }
+            if reasoning.include_thoughts == Some(true) {
+                config["summary"] = json!("auto");
+            }
+            if config.as_object().map(|o| !o.is_empty()).unwrap_or(false) {
+                request["reasoning"] = config;
+            }
+        }
 
         // Add extra parameters
         for (key, value) in &unified.parameters.extra {
             request[key] = value.clone();
         }
 
+        // Structured output goes to text.format (merged with other text options)
+        if let Some(ref format) = unified.parameters.response_format {
+            if !request.get("text").map(|t| t.is_object()).unwrap_or(false) {
+                request["text"] = json!({});
+            }
+            request["text"]["format"] =
+                serde_json::to_value(format).map_err(AppError::Serialization)?;
+        }
+
         Ok(request)
     }
 
@@ -539,7 +733,14 @@ impl Transformer for ResponseApiTransformer {
         let (content, tool_calls) = Self::output_to_unified(&response.output);
         let stop_reason = Some(Self::status_to_stop_reason(&response.status));
 
-        let usage = UnifiedUsage::new(response.usage.input_tokens, response.usage.output_tokens);
+        let usage = UnifiedUsage {
+            reasoning_tokens: response
+                .usage
+                .output_tokens_details
+                .as_ref()
+                .and_then(|d| d.reasoning_tokens),
+            ..UnifiedUsage::new(response.usage.input_tokens, response.usage.output_tokens)
+        };
 
         Ok(UnifiedResponse {
             id: response.id,
@@ -548,6 +749,7 @@ impl Transformer for ResponseApiTransformer {
             stop_reason,
             usage,
             tool_calls,
+            additional_choices: vec![],
         })
     }
 
@@ -609,11 +811,7 @@ impl Transformer for ResponseApiTransformer {
             output,
             status: status.to_string(),
             status_details: None,
-            usage: ResponseUsage {
-                input_tokens: unified.usage.input_tokens,
-                output_tokens: unified.usage.output_tokens,
-                total_tokens: unified.usage.total_tokens(),
-            },
+            usage: ResponseUsage::from_unified(&unified.usage),
         };
 
         serde_json::to_value(response).map_err(AppError::Serialization)
@@ -665,6 +863,7 @@ impl Transformer for ResponseApiTransformer {
                                         stop_reason: None,
                                         usage: UnifiedUsage::default(),
                                         tool_calls: vec![],
+                                        additional_choices: vec![],
                                     };
                                     chunks
                                         .push(UnifiedStreamChunk::message_start(unified_response));
@@ -682,18 +881,7 @@ impl Transformer for ResponseApiTransformer {
                                 let usage = json
                                     .get("response")
                                     .and_then(|r| r.get("usage"))
-                                    .map(|u| {
-                                        UnifiedUsage::new(
-                                            u.get("input_tokens")
-                                                .and_then(|t| t.as_i64())
-                                                .unwrap_or(0)
-                                                as i32,
-                                            u.get("output_tokens")
-                                                .and_then(|t| t.as_i64())
-                                                .unwrap_or(0)
-                                                as i32,
-                                        )
-                                    })
+                                    .map(Self::stream_usage_to_unified)
                                     .unwrap_or_default();
                                 chunks.push(UnifiedStreamChunk::message_delta(
                                     StopReason::EndTurn,
@@ -701,36 +889,24 @@ impl Transformer for ResponseApiTransformer {
                                 ));
                                 chunks.push(UnifiedStreamChunk::message_stop());
                             }
-                            "response.done" => {
-                                // response.done is the final event; if we haven't
-                                // already emitted message_stop via response.completed,
-                                // extract usage from here and terminate.
+                            // response.done is the final event; if we haven't
+                            // already emitted message_stop via response.completed,
+                            // extract usage from here and terminate.
+                            "response.done"
                                 if !chunks
                                     .iter()
-                                    .any(|c| c.chunk_type == ChunkType::MessageStop)
-                                {
-                                    let usage = json
-                                        .get("response")
-                                        .and_then(|r| r.get("usage"))
-                                        .map(|u| {
-                                            UnifiedUsage::new(
-                                                u.get("input_tokens")
-                                                    .and_then(|t| t.as_i64())
-                                                    .unwrap_or(0)
-                                                    as i32,
-                                                u.get("output_tokens")
-                                                    .and_then(|t| t.as_i64())
-                                                    .unwrap_or(0)
-                                                    as i32,
-                                            )
-                                        })
-                                        .unwrap_or_default();
-                                    chunks.push(UnifiedStreamChunk::message_delta(
-                                        StopReason::EndTurn,
-                                        usage,
-                                    ));
-                                    chunks.push(UnifiedStreamChunk::message_stop());
-                                }
+                                    .any(|c| c.chunk_type == ChunkType::MessageStop) =>
+                            {
+                                let usage = json
+                                    .get("response")
+                                    .and_then(|r| r.get("usage"))
+                                    .map(Self::stream_usage_to_unified)
+                                    .unwrap_or_default();
+                                chunks.push(UnifiedStreamChunk::message_delta(
+                                    StopReason::EndTurn,
+                                    usage,
+                                ));
+                                chunks.push(UnifiedStreamChunk::message_stop());
                             }
                             _ => {}
                         }
@@ -780,11 +956,7 @@ impl Transformer for ResponseApiTransformer {
                     "type": "response.completed",
                     "response": {
                         "status": "completed",
-                        "usage": chunk.usage.as_ref().map(|u| json!({
-                            "input_tokens": u.input_tokens,
-                            "output_tokens": u.output_tokens,
-                            "total_tokens": u.total_tokens()
-                        }))
+                        "usage": chunk.usage.as_ref().map(ResponseUsage::from_unified)
                     }
                 })
             }
@@ -822,6 +994,71 @@ mod tests {
         assert_eq!(transformer.protocol(), Protocol::ResponseApi);
     }
 
+    #[test]
+    fn test_reasoning_option_mapping() {
+        let transformer = ResponseApiTransformer::new();
+        let raw = json!({
+            "model": "o3",
+            "input": "Hi",
+            "reasoning": {"effort": "medium", "summary": "auto"}
+        });
+        let mut unified = transformer.transform_request_out(raw).unwrap();
+        let reasoning = unified.parameters.reasoning.clone().unwrap();
+        assert_eq!(reasoning.effort, Some(ReasoningEffort::Medium));
+        assert_eq!(reasoning.include_thoughts, Some(true));
+        assert!(!unified.parameters.extra.contains_key("reasoning"));
+
+        // Anthropic-style budget resolves to an effort level
+        unified.parameters.reasoning = Some(ReasoningConfig {
+            budget_tokens: Some(24576),
+            ..Default::default()
+        });
+        let back = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(back["reasoning"], json!({"effort": "high"}));
+    }
+
+    #[test]
+    fn test_input_file_documents() {
+        let transformer = ResponseApiTransformer::new();
+        let raw = json!({
+            "model": "gpt-4o",
+            "input": [{
+                "type": "message",
+                "role": "user",
+                "content": [
+                    {"type": "input_file", "file_data": "data:application/pdf;base64,JVBERi0=", "filename": "report.pdf"},
+                    {"type": "input_file", "file_url": "https://example.com/a.pdf"},
+                    {"type": "input_file", "file_id": "file-abc"}
+                ]
+            }]
+        });
+
+        let unified = transformer.transform_request_out(raw).unwrap();
+        let content = &unified.messages[0].content;
+        assert!(matches!(
+            &content[0],
+            UnifiedContent::Document { source_type, data, title, .. }
+                if source_type == "base64" && data == "JVBERi0=" && title.as_deref() == Some("report.pdf")
+        ));
+        assert!(matches!(
+            &content[1],
+            UnifiedContent::Document { source_type, .. } if source_type == "url"
+        ));
+        assert!(
+            matches!(&content[2], UnifiedContent::File { file_id, .. } if file_id == "file-abc")
+        );
+
+        let back = transformer.transform_request_in(&unified).unwrap();
+        let parts = &back["input"][0]["content"];
+        assert_eq!(
+            parts[0]["file_data"],
+            "data:application/pdf;base64,JVBERi0="
+        );
+        assert_eq!(parts[0]["filename"], "report.pdf");
+        assert_eq!(parts[1]["file_url"], "https://example.com/a.pdf");
+        assert_eq!(parts[2]["file_id"], "file-abc");
+    }
+
     #[test]
     fn test_transform_request_out_simple() {
         let transformer = ResponseApiTransformer::new();
@@ -865,6 +1102,45 @@ mod tests {
         assert_eq!(raw["max_output_tokens"], 100);
     }
 
+    #[test]
+    fn test_text_format_roundtrip() {
+        let transformer = ResponseApiTransformer::new();
+        let format = json!({
+            "type": "json_schema",
+            "name": "person",
+            "schema": {"type": "object"},
+            "strict": true
+        });
+        let raw = json!({
+            "model": "gpt-4o",
+            "input": "Who?",
+            "text": {"format": format, "verbosity": "low"}
+        });
+
+        let unified = transformer.transform_request_out(raw).unwrap();
+        assert!(unified.parameters.response_format.is_some());
+        assert_eq!(
+            unified.parameters.extra["text"],
+            json!({"verbosity": "low"})
+        );
+
+        let out = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(out["text"]["format"], format);
+        assert_eq!(out["text"]["verbosity"], "low");
+
+        // Chat Completions client → Response API provider
+        let openai = crate::transformer::openai::OpenAITransformer::new();
+        let unified = openai
+            .transform_request_out(json!({
+                "model": "gpt-4o",
+                "messages": [{"role": "user", "content": "Who?"}],
+                "response_format": {"type": "json_object"}
+            }))
+            .unwrap();
+        let out = transformer.transform_request_in(&unified).unwrap();
+        assert_eq!(out["text"]["format"], json!({"type": "json_object"}));
+    }
+
     #[test]
     fn test_transform_response_in() {
         let transformer = ResponseApiTransformer::new();
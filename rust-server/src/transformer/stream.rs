@@ -3,6 +3,12 @@
 //! This module provides utilities for handling SSE (Server-Sent Events) streams
 //! and converting between different streaming formats.
 
+use super::guardrails::{GuardrailStreamScanner, Guardrails};
+use super::pii_redaction::{PiiRedactor, PiiStreamRestorer};
+use super::structured_output::STRUCTURED_OUTPUT_TOOL_NAME;
+use super::tool_arguments::{parse_raw_arguments, ArgumentScanner, ToolArgumentChecker};
+use super::tool_emulation::ToolCallStreamParser;
+use super::tool_normalizer::ToolNormalizer;
 use super::UnifiedStreamChunk;
 use crate::core::OutboundTokenCounter;
 
@@ -204,6 +210,22 @@ pub struct CrossProtocolStreamState {
     pub provider_input_tokens: Option<i32>,
     /// Cached tool information for synthesizing content_block_start
     pub tool_info_cache: std::collections::HashMap<usize, ToolInfo>,
+    /// Whether the structured output tool is re-emitted as text
+    pub structured_output: bool,
+    /// Block indices of the structured output tool, re-emitted as text
+    pub structured_output_blocks: std::collections::HashSet<usize>,
+    /// Parser for prompt-emulated tool calls in text deltas
+    pub tool_call_parser: Option<ToolCallStreamParser>,
+    /// Restores client tool names renamed for the provider
+    pub tool_normalizer: Option<ToolNormalizer>,
+    /// Swaps PII placeholders in deltas back for the original values
+    pub pii_restorer: Option<PiiStreamRestorer>,
+    /// Redacts or cuts off completions matching guardrail rules
+    pub guardrail_scanner: Option<GuardrailStreamScanner>,
+    /// Reports the outcome of tool arguments checked at block close
+    pub tool_arguments: ToolArgumentChecker,
+    /// Argument scanners (with the tool call index) by content block index
+    argument_scanners: std::collections::HashMap<usize, (usize, ArgumentScanner)>,
 }
 
 impl Default for CrossProtocolStreamState {
@@ -225,6 +247,14 @@ impl Default for CrossProtocolStreamState {
             stop_reason: None,
             provider_input_tokens: None,
             tool_info_cache: std::collections::HashMap::new(),
+            structured_output: false,
+            structured_output_blocks: std::collections::HashSet::new(),
+            tool_call_parser: None,
+            tool_normalizer: None,
+            pii_restorer: None,
+            guardrail_scanner: None,
+            tool_arguments: ToolArgumentChecker::default(),
+            argument_scanners: std::collections::HashMap::new(),
         }
     }
 }
@@ -255,6 +285,49 @@ impl CrossProtocolStreamState {
         }
     }
 
+    /// Parse prompt-emulated tool calls out of the streamed text.
+    pub fn with_tool_emulation(mut self) -> Self {
+        self.tool_call_parser = Some(ToolCallStreamParser::new());
+        self
+    }
+
+    /// Re-emit the structured output tool of a JSON response format as text.
+    pub fn with_structured_output(mut self) -> Self {
+        self.structured_output = true;
+        self
+    }
+
+    /// Report tool argument outcomes through the given checker.
+    pub fn with_tool_arguments(mut self, checker: ToolArgumentChecker) -> Self {
+        self.tool_arguments = checker;
+        self
+    }
+
+    /// Rename streamed tool calls back to the client's tool names.
+    pub fn with_tool_normalizer(mut self, normalizer: ToolNormalizer) -> Self {
+        self.tool_normalizer = Some(normalizer);
+        self
+    }
+
+    /// Restore values redacted from the request by `redactor`.
+    pub fn with_pii_restorer(mut self, redactor: PiiRedactor) -> Self {
+        self.pii_restorer = Some(PiiStreamRestorer::new(redactor));
+        self
+    }
+
+    /// Apply the output rules of `guardrails` to the streamed deltas.
+    pub fn with_guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.guardrail_scanner = Some(GuardrailStreamScanner::new(guardrails));
+        self
+    }
+
+    /// Whether a guardrail cut the stream off; the caller ends it with an error.
+    pub fn guardrail_blocked(&self) -> bool {
+        self.guardrail_scanner
+            .as_ref()
+            .is_some_and(GuardrailStreamScanner::is_blocked)
+    }
+
     /// Accumulate output tokens from chunk text.
     ///
     /// This method counts tokens in generated text and adds them to the usage.
@@ -295,8 +368,27 @@ impl CrossProtocolStreamState {
     /// the target protocol.
     pub fn process_chunks(&mut self, chunks: Vec<UnifiedStreamChunk>) -> Vec<UnifiedStreamChunk> {
         let mut result = Vec::new();
+        let mut chunks = match self.tool_call_parser.as_mut() {
+            Some(parser) => parser.process(chunks),
+            None => chunks,
+        };
+        if let Some(ref normalizer) = self.tool_normalizer {
+            chunks
+                .iter_mut()
+                .for_each(|chunk| normalizer.restore_chunk(chunk));
+        }
+        if let Some(restorer) = self.pii_restorer.as_mut() {
+            chunks = restorer.process(chunks);
+        }
+        if let Some(scanner) = self.guardrail_scanner.as_mut() {
+            chunks = scanner.process(chunks);
+        }
+
+        for mut chunk in chunks {
+            if self.structured_output {
+                self.unwrap_structured_output(&mut chunk);
+            }
 
-        for chunk in chunks {
             // Cache tool information from content blocks for later use
             self.cache_tool_info(&chunk);
 
@@ -351,6 +443,19 @@ impl CrossProtocolStreamState {
                         self.current_block_index = index;
                     }
 
+                    // Track tool arguments to validate them when the block closes
+                    if let Some(super::UnifiedContent::ToolInputDelta {
+                        index: tool_index,
+                        partial_json,
+                    }) = chunk.delta.as_mut()
+                    {
+                        let (_, scanner) = self
+                            .argument_scanners
+                            .entry(index)
+                            .or_insert_with(|| (*tool_index, ArgumentScanner::new()));
+                        *partial_json = scanner.push(partial_json);
+                    }
+
                     // Accumulate output tokens from content
                     if let Some(ref delta) = chunk.delta {
                         match delta {
@@ -360,10 +465,10 @@ impl CrossProtocolStreamState {
                             }
                             // Tool input delta - accumulate the partial JSON for token counting
                             // When LLM returns tool_calls, the arguments are streamed as partial JSON
-                            super::UnifiedContent::ToolInputDelta { partial_json, .. } => {
-                                if !partial_json.is_empty() {
-                                    self.accumulate_output_tokens(partial_json);
-                                }
+                            super::UnifiedContent::ToolInputDelta { partial_json, .. }
+                                if !partial_json.is_empty() =>
+                            {
+                                self.accumulate_output_tokens(partial_json);
                             }
                             // Thinking content - also accumulate for token counting
                             super::UnifiedContent::Thinking { text, .. } => {
@@ -378,18 +483,14 @@ impl CrossProtocolStreamState {
                 super::ChunkType::ContentBlockStop => {
                     let index = chunk.index;
                     if !self.stopped_blocks.contains(&index) {
+                        self.finish_tool_arguments(index, &mut result);
                         self.stopped_blocks.insert(index);
                     }
                     result.push(chunk);
                 }
                 super::ChunkType::MessageDelta => {
                     // Close any open content blocks before message_delta
-                    for idx in self.started_blocks.iter() {
-                        if !self.stopped_blocks.contains(idx) {
-                            result.push(UnifiedStreamChunk::content_block_stop(*idx));
-                            self.stopped_blocks.insert(*idx);
-                        }
-                    }
+                    self.close_open_blocks(&mut result);
 
                     // DEBUG: Log usage information before get_final_usage
                     tracing::debug!(
@@ -426,12 +527,7 @@ impl CrossProtocolStreamState {
                 }
                 super::ChunkType::MessageStop => {
                     // Ensure all content blocks are closed
-                    for idx in self.started_blocks.clone().iter() {
-                        if !self.stopped_blocks.contains(idx) {
-                            result.push(UnifiedStreamChunk::content_block_stop(*idx));
-                            self.stopped_blocks.insert(*idx);
-                        }
-                    }
+                    self.close_open_blocks(&mut result);
 
                     self.message_stopped = true;
                     result.push(chunk);
@@ -445,6 +541,80 @@ impl CrossProtocolStreamState {
         result
     }
 
+    /// Re-emit the structured output tool (Anthropic `response_format` emulation)
+    /// as a text block: its input JSON deltas become text deltas, and a `tool_use`
+    /// stop reason becomes `end_turn` when no other tool was called.
+    fn unwrap_structured_output(&mut self, chunk: &mut UnifiedStreamChunk) {
+        match chunk.chunk_type {
+            super::ChunkType::ContentBlockStart => {
+                if let Some(super::UnifiedContent::ToolUse { name, .. }) = &chunk.content_block {
+                    if name == STRUCTURED_OUTPUT_TOOL_NAME {
+                        self.structured_output_blocks.insert(chunk.index);
+                        chunk.content_block = Some(super::UnifiedContent::text(""));
+                    }
+                }
+            }
+            super::ChunkType::ContentBlockDelta
+                if self.structured_output_blocks.contains(&chunk.index) =>
+            {
+                if let Some(super::UnifiedContent::ToolInputDelta { partial_json, .. }) =
+                    chunk.delta.take()
+                {
+                    chunk.delta = Some(super::UnifiedContent::text(partial_json));
+                }
+            }
+            super::ChunkType::MessageDelta
+                if !self.structured_output_blocks.is_empty()
+                    && self.tool_info_cache.is_empty()
+                    && chunk.stop_reason == Some(super::StopReason::ToolUse) =>
+            {
+                chunk.stop_reason = Some(super::StopReason::EndTurn);
+            }
+            _ => {}
+        }
+    }
+
+    /// Close every started content block that is still open.
+    fn close_open_blocks(&mut self, result: &mut Vec<UnifiedStreamChunk>) {
+        let mut open: Vec<usize> = self
+            .started_blocks
+            .difference(&self.stopped_blocks)
+            .copied()
+            .collect();
+        open.sort_unstable();
+        for idx in open {
+            self.finish_tool_arguments(idx, result);
+            result.push(UnifiedStreamChunk::content_block_stop(idx));
+            self.stopped_blocks.insert(idx);
+        }
+    }
+
+    /// Complete the streamed arguments of a closing tool block.
+    ///
+    /// Emits the repair suffix (e.g. missing closing brackets) as a last
+    /// delta and reports the outcome.
+    fn finish_tool_arguments(&mut self, index: usize, result: &mut Vec<UnifiedStreamChunk>) {
+        let Some((tool_index, mut scanner)) = self.argument_scanners.remove(&index) else {
+            return;
+        };
+        let (suffix, arguments, outcome) = scanner.finish();
+        if !suffix.is_empty() {
+            result.push(UnifiedStreamChunk::content_block_delta(
+                index,
+                super::UnifiedContent::ToolInputDelta {
+                    index: tool_index,
+                    partial_json: suffix,
+                },
+            ));
+        }
+        let name = self
+            .tool_info_cache
+            .get(&index)
+            .map(|info| info.name.as_str())
+            .unwrap_or("unknown_tool");
+        self.tool_arguments.record(name, &arguments, outcome);
+    }
+
     /// Cache tool information from content blocks for later synthesizing.
     fn cache_tool_info(&mut self, chunk: &UnifiedStreamChunk) {
         // Cache tool info from ContentBlockStart
@@ -497,6 +667,7 @@ impl CrossProtocolStreamState {
             stop_reason: None,
             usage: super::UnifiedUsage::default(),
             tool_calls: vec![],
+            additional_choices: vec![],
         };
         UnifiedStreamChunk::message_start(message)
     }
@@ -558,13 +729,28 @@ impl CrossProtocolStreamState {
             "finalize() called"
         );
 
-        // Close any open content blocks
-        for idx in self.started_blocks.clone().iter() {
-            if !self.stopped_blocks.contains(idx) {
-                result.push(UnifiedStreamChunk::content_block_stop(*idx));
-                self.stopped_blocks.insert(*idx);
-            }
+        // Emit text still held back by the tool call parser, without re-parsing it
+        if let Some(mut parser) = self.tool_call_parser.take() {
+            let flushed = parser.flush();
+            result.extend(self.process_chunks(flushed));
+            self.tool_call_parser = Some(parser);
+        }
+        if let Some(mut restorer) = self.pii_restorer.take() {
+            let flushed = restorer.flush();
+            result.extend(self.process_chunks(flushed));
+            self.pii_restorer = Some(restorer);
         }
+        if let Some(mut scanner) = self.guardrail_scanner.take() {
+            let flushed = scanner.flush();
+            result.extend(self.process_chunks(flushed));
+            self.guardrail_scanner = Some(scanner);
+        }
+        if self.guardrail_blocked() {
+            return result;
+        }
+
+        // Close any open content blocks
+        self.close_open_blocks(&mut result);
 
         // Emit message_delta if not yet emitted
         if !self.message_delta_emitted && self.message_started {
@@ -603,54 +789,211 @@ impl CrossProtocolStreamState {
 // ============================================================================
 
 /// Accumulates streaming chunks for final response assembly.
+///
+/// Content blocks are collected per choice in the order they were started;
+/// tool calls are reported in `tool_calls` with their streamed arguments.
 #[derive(Debug, Default)]
-#[allow(dead_code)]
 pub struct ChunkAccumulator {
-    content: Vec<String>,
-    tool_calls: Vec<serde_json::Value>,
+    choices: Vec<AccumulatedChoice>,
     usage: Option<super::UnifiedUsage>,
-    stop_reason: Option<super::StopReason>,
     message_id: Option<String>,
     model: Option<String>,
 }
 
+/// Blocks and stop reason of one accumulated choice.
+#[derive(Debug, Default)]
+struct AccumulatedChoice {
+    blocks: Vec<(usize, AccumulatedBlock)>,
+    stop_reason: Option<super::StopReason>,
+}
+
+/// A content block being assembled from deltas.
+#[derive(Debug)]
+enum AccumulatedBlock {
+    Text(String),
+    Thinking(String, Option<String>),
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+        arguments: String,
+    },
+}
+
+impl AccumulatedChoice {
+    fn block(&mut self, index: usize) -> Option<&mut AccumulatedBlock> {
+        self.blocks
+            .iter_mut()
+            .rev()
+            .find(|(i, _)| *i == index)
+            .map(|(_, block)| block)
+    }
+
+    fn start_block(&mut self, index: usize, content: &super::UnifiedContent) {
+        let block = match content {
+            super::UnifiedContent::Text { text } => AccumulatedBlock::Text(text.clone()),
+            super::UnifiedContent::Thinking { text, signature } => {
+                AccumulatedBlock::Thinking(text.clone(), signature.clone())
+            }
+            super::UnifiedContent::ToolUse { id, name, input } => AccumulatedBlock::ToolUse {
+                id: id.clone(),
+                name: name.clone(),
+                input: input.clone(),
+                arguments: String::new(),
+            },
+            _ => return,
+        };
+        self.blocks.push((index, block));
+    }
+
+    fn add_delta(&mut self, index: usize, delta: &super::UnifiedContent) {
+        use super::UnifiedContent;
+        match (self.block(index), delta) {
+            (Some(AccumulatedBlock::Text(text)), UnifiedContent::Text { text: more }) => {
+                text.push_str(more);
+            }
+            (
+                Some(AccumulatedBlock::Thinking(text, signature)),
+                UnifiedContent::Thinking {
+                    text: more,
+                    signature: more_signature,
+                },
+            ) => {
+                text.push_str(more);
+                if more_signature.is_some() {
+                    *signature = more_signature.clone();
+                }
+            }
+            (
+                Some(AccumulatedBlock::ToolUse { arguments, .. }),
+                UnifiedContent::ToolInputDelta { partial_json, .. },
+            ) => arguments.push_str(partial_json),
+            // Deltas without a started block (some providers never send one)
+            (_, UnifiedContent::Text { .. } | UnifiedContent::Thinking { .. }) => {
+                self.start_block(index, delta);
+            }
+            _ => {}
+        }
+    }
+
+    fn build(&self) -> super::UnifiedChoice {
+        let mut choice = super::UnifiedChoice {
+            stop_reason: self.stop_reason.clone(),
+            ..Default::default()
+        };
+        for (_, block) in &self.blocks {
+            match block {
+                AccumulatedBlock::Text(text) => {
+                    choice.content.push(super::UnifiedContent::text(text));
+                }
+                AccumulatedBlock::Thinking(text, signature) => choice
+                    .content
+                    .push(super::UnifiedContent::thinking(text, signature.clone())),
+                AccumulatedBlock::ToolUse {
+                    id,
+                    name,
+                    input,
+                    arguments,
+                } => choice.tool_calls.push(super::UnifiedToolCall {
+                    id: id.clone(),
+                    name: name.clone(),
+                    arguments: if arguments.is_empty() {
+                        input.clone()
+                    } else {
+                        parse_raw_arguments(arguments)
+                    },
+                }),
+            }
+        }
+        choice
+    }
+}
+
 impl ChunkAccumulator {
     /// Create a new chunk accumulator.
     pub fn new() -> Self {
         Self::default()
     }
 
+    fn choice(&mut self, index: usize) -> &mut AccumulatedChoice {
+        if self.choices.len() <= index {
+            self.choices.resize_with(index + 1, Default::default);
+        }
+        &mut self.choices[index]
+    }
+
     /// Add a unified stream chunk.
     pub fn add_chunk(&mut self, chunk: &UnifiedStreamChunk) {
         match chunk.chunk_type {
             super::ChunkType::MessageStart => {
                 if let Some(ref message) = chunk.message {
-                    self.message_id = Some(message.id.clone());
-                    self.model = Some(message.model.clone());
+                    self.message_id.get_or_insert_with(|| message.id.clone());
+                    self.model.get_or_insert_with(|| message.model.clone());
+                }
+                if let Some(ref usage) = chunk.usage {
+                    self.merge_usage(usage);
+                }
+            }
+            super::ChunkType::ContentBlockStart => {
+                if let Some(ref block) = chunk.content_block {
+                    self.choice(chunk.choice_index)
+                        .start_block(chunk.index, block);
                 }
             }
             super::ChunkType::ContentBlockDelta => {
                 if let Some(ref delta) = chunk.delta {
-                    if let Some(text) = delta.as_text() {
-                        self.content.push(text.to_string());
-                    }
+                    self.choice(chunk.choice_index)
+                        .add_delta(chunk.index, delta);
                 }
             }
             super::ChunkType::MessageDelta => {
                 if let Some(ref usage) = chunk.usage {
-                    self.usage = Some(usage.clone());
+                    self.merge_usage(usage);
                 }
                 if let Some(ref reason) = chunk.stop_reason {
-                    self.stop_reason = Some(reason.clone());
+                    self.choice(chunk.choice_index)
+                        .stop_reason
+                        .get_or_insert_with(|| reason.clone());
                 }
             }
             _ => {}
         }
     }
 
-    /// Get accumulated text content.
+    /// Keep the highest count of every usage field seen so far.
+    ///
+    /// Providers report usage cumulatively, sometimes split over the start
+    /// and the final delta of the stream.
+    fn merge_usage(&mut self, usage: &super::UnifiedUsage) {
+        let total = self.usage.get_or_insert_with(Default::default);
+        total.input_tokens = total.input_tokens.max(usage.input_tokens);
+        total.output_tokens = total.output_tokens.max(usage.output_tokens);
+        for (field, value) in [
+            (&mut total.cache_read_tokens, usage.cache_read_tokens),
+            (&mut total.cache_write_tokens, usage.cache_write_tokens),
+            (&mut total.reasoning_tokens, usage.reasoning_tokens),
+        ] {
+            if let Some(value) = value {
+                *field = Some(field.map_or(value, |current| current.max(value)));
+            }
+        }
+    }
+
+    /// Get accumulated text content of the first choice.
     pub fn text_content(&self) -> String {
-        self.content.join("")
+        self.choices
+            .first()
+            .map(|choice| {
+                choice
+                    .blocks
+                    .iter()
+                    .filter_map(|(_, block)| match block {
+                        AccumulatedBlock::Text(text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
     /// Get usage statistics.
@@ -658,9 +1001,9 @@ impl ChunkAccumulator {
         self.usage.as_ref()
     }
 
-    /// Get stop reason.
+    /// Get stop reason of the first choice.
     pub fn stop_reason(&self) -> Option<&super::StopReason> {
-        self.stop_reason.as_ref()
+        self.choices.first().and_then(|c| c.stop_reason.as_ref())
     }
 
     /// Get message ID.
@@ -675,18 +1018,118 @@ impl ChunkAccumulator {
 
     /// Build a unified response from accumulated chunks.
     pub fn build_response(&self) -> super::UnifiedResponse {
+        let mut choices = self.choices.iter().map(AccumulatedChoice::build);
+        let first = choices.next().unwrap_or_default();
         super::UnifiedResponse {
             id: self
                 .message_id
                 .clone()
                 .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
             model: self.model.clone().unwrap_or_else(|| "unknown".to_string()),
-            content: vec![super::UnifiedContent::text(self.text_content())],
-            stop_reason: self.stop_reason.clone(),
+            content: first.content,
+            stop_reason: first.stop_reason,
             usage: self.usage.clone().unwrap_or_default(),
-            tool_calls: vec![],
+            tool_calls: first.tool_calls,
+            additional_choices: choices.collect(),
+        }
+    }
+}
+
+/// Replay a complete response as a unified chunk stream.
+///
+/// Every content block is sent whole in a single delta; tool calls are sent
+/// as one argument delta. Additional choices follow the first one with their
+/// own choice index.
+pub fn response_to_chunks(response: &super::UnifiedResponse) -> Vec<UnifiedStreamChunk> {
+    use super::UnifiedContent;
+
+    let mut start = response.clone();
+    start.content.clear();
+    start.tool_calls.clear();
+    start.additional_choices.clear();
+    start.stop_reason = None;
+    start.usage = super::UnifiedUsage {
+        input_tokens: response.usage.input_tokens,
+        ..Default::default()
+    };
+    let mut chunks = vec![UnifiedStreamChunk::message_start(start)];
+
+    let first = super::UnifiedChoice {
+        content: response.content.clone(),
+        stop_reason: response.stop_reason.clone(),
+        tool_calls: response.tool_calls.clone(),
+    };
+    for (choice_index, choice) in std::iter::once(&first)
+        .chain(&response.additional_choices)
+        .enumerate()
+    {
+        let mut choice_chunks = Vec::new();
+        let mut blocks: Vec<(UnifiedContent, UnifiedContent)> = Vec::new();
+        let mut tool_index = 0;
+        for content in &choice.content {
+            match content {
+                UnifiedContent::Text { text } => {
+                    blocks.push((UnifiedContent::text(""), UnifiedContent::text(text)));
+                }
+                UnifiedContent::Thinking { text, signature } => blocks.push((
+                    UnifiedContent::thinking("", None),
+                    UnifiedContent::thinking(text, signature.clone()),
+                )),
+                _ => {}
+            }
+        }
+        let content_calls = choice.content.iter().filter_map(|content| match content {
+            UnifiedContent::ToolUse { id, name, input } => Some((id, name, input)),
+            _ => None,
+        });
+        let extra_calls = choice
+            .tool_calls
+            .iter()
+            .filter(|call| {
+                !choice
+                    .content
+                    .iter()
+                    .any(|c| matches!(c, UnifiedContent::ToolUse { id, .. } if *id == call.id))
+            })
+            .map(|call| (&call.id, &call.name, &call.arguments));
+        for (id, name, input) in content_calls.chain(extra_calls) {
+            let arguments = match input {
+                serde_json::Value::String(raw) => raw.clone(),
+                other => other.to_string(),
+            };
+            blocks.push((
+                UnifiedContent::tool_use(id, name, serde_json::json!({})),
+                UnifiedContent::tool_input_delta(tool_index, arguments),
+            ));
+            tool_index += 1;
         }
+
+        for (index, (block, delta)) in blocks.into_iter().enumerate() {
+            choice_chunks.push(UnifiedStreamChunk::content_block_start(index, block));
+            choice_chunks.push(UnifiedStreamChunk::content_block_delta(index, delta));
+            choice_chunks.push(UnifiedStreamChunk::content_block_stop(index));
+        }
+        // Usage covers the whole response and is reported once
+        let mut delta = UnifiedStreamChunk::message_delta(
+            choice
+                .stop_reason
+                .clone()
+                .unwrap_or(super::StopReason::EndTurn),
+            response.usage.clone(),
+        );
+        if choice_index != 0 {
+            delta.usage = None;
+        }
+        choice_chunks.push(delta);
+
+        for chunk in &mut choice_chunks {
+            chunk.choice_index = choice_index;
+        }
+        chunks.extend(choice_chunks);
     }
+
+    chunks.push(UnifiedStreamChunk::message_stop());
+    chunks
 }
 
 // ============================================================================
@@ -817,6 +1260,7 @@ mod tests {
         acc.add_chunk(&UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockDelta,
             index: 0,
+            choice_index: 0,
             delta: Some(UnifiedContent::text("Hello")),
             usage: None,
             stop_reason: None,
@@ -827,6 +1271,7 @@ mod tests {
         acc.add_chunk(&UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockDelta,
             index: 0,
+            choice_index: 0,
             delta: Some(UnifiedContent::text(" World")),
             usage: None,
             stop_reason: None,
@@ -838,6 +1283,7 @@ mod tests {
         acc.add_chunk(&UnifiedStreamChunk {
             chunk_type: ChunkType::MessageDelta,
             index: 0,
+            choice_index: 0,
             delta: None,
             usage: Some(UnifiedUsage::new(10, 5)),
             stop_reason: Some(StopReason::EndTurn),
@@ -850,6 +1296,65 @@ mod tests {
         assert_eq!(acc.stop_reason(), Some(&StopReason::EndTurn));
     }
 
+    #[test]
+    fn test_response_to_chunks_round_trips_through_accumulator() {
+        use super::super::{
+            StopReason, UnifiedChoice, UnifiedContent, UnifiedResponse, UnifiedToolCall,
+            UnifiedUsage,
+        };
+
+        let mut response = UnifiedResponse::new(
+            "msg_1",
+            "claude",
+            vec![
+                UnifiedContent::thinking("Let me check.", Some("sig".to_string())),
+                UnifiedContent::text("Checking the weather."),
+            ],
+            Some(StopReason::ToolUse),
+            UnifiedUsage::new(12, 7),
+        );
+        response.tool_calls.push(UnifiedToolCall {
+            id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments: serde_json::json!({"city": "Paris"}),
+        });
+        response.additional_choices.push(UnifiedChoice {
+            content: vec![UnifiedContent::text("Second")],
+            stop_reason: Some(StopReason::EndTurn),
+            tool_calls: vec![],
+        });
+
+        let mut acc = ChunkAccumulator::new();
+        for chunk in response_to_chunks(&response) {
+            acc.add_chunk(&chunk);
+        }
+        let rebuilt = acc.build_response();
+
+        assert_eq!(rebuilt.id, "msg_1");
+        assert_eq!(rebuilt.text_content(), "Checking the weather.");
+        assert!(matches!(
+            &rebuilt.content[0],
+            UnifiedContent::Thinking { text, signature: Some(sig) }
+                if text == "Let me check." && sig == "sig"
+        ));
+        assert_eq!(rebuilt.tool_calls.len(), 1);
+        assert_eq!(rebuilt.tool_calls[0].name, "get_weather");
+        assert_eq!(
+            rebuilt.tool_calls[0].arguments,
+            serde_json::json!({"city": "Paris"})
+        );
+        assert_eq!(rebuilt.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(
+            (rebuilt.usage.input_tokens, rebuilt.usage.output_tokens),
+            (12, 7)
+        );
+        assert_eq!(rebuilt.additional_choices.len(), 1);
+        assert_eq!(
+            rebuilt.additional_choices[0].stop_reason,
+            Some(StopReason::EndTurn)
+        );
+    }
+
     // =========================================================================
     // CrossProtocolStreamState Tests
     // =========================================================================
@@ -1053,6 +1558,7 @@ mod tests {
             stop_reason: None,
             usage: UnifiedUsage::default(),
             tool_calls: vec![],
+            additional_choices: vec![],
         };
         let chunks = vec![UnifiedStreamChunk::message_start(message)];
 
@@ -1517,6 +2023,50 @@ mod tests {
         assert_eq!(usage.output_tokens, initial_output);
     }
 
+    #[test]
+    fn test_truncated_tool_arguments_repaired_at_block_close() {
+        use super::super::{UnifiedContent, UnifiedStreamChunk};
+
+        let mut state = CrossProtocolStreamState::new("gpt-4");
+        let chunks = vec![
+            UnifiedStreamChunk::content_block_start(
+                0,
+                UnifiedContent::tool_use("call_1", "get_weather", serde_json::json!({})),
+            ),
+            UnifiedStreamChunk::content_block_delta(
+                0,
+                UnifiedContent::tool_input_delta(0, "{\"location\": \"San Fr"),
+            ),
+            UnifiedStreamChunk::message_delta(
+                super::super::StopReason::ToolUse,
+                Default::default(),
+            ),
+        ];
+
+        let output = state.process_chunks(chunks);
+        let arguments: String = output
+            .iter()
+            .filter_map(|c| match &c.delta {
+                Some(UnifiedContent::ToolInputDelta { partial_json, .. }) => {
+                    Some(partial_json.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(arguments, "{\"location\": \"San Fr\"}");
+
+        // The repair is emitted before the block is closed
+        let stop = output
+            .iter()
+            .position(|c| c.chunk_type == super::super::ChunkType::ContentBlockStop)
+            .unwrap();
+        let last_delta = output
+            .iter()
+            .rposition(|c| c.chunk_type == super::super::ChunkType::ContentBlockDelta)
+            .unwrap();
+        assert!(last_delta < stop);
+    }
+
     #[test]
     fn test_v2_api_mixed_text_and_tool_input_delta() {
         use super::super::{UnifiedContent, UnifiedStreamChunk};
@@ -1642,4 +2192,97 @@ mod tests {
             "output_tokens should reflect the tool arguments JSON"
         );
     }
+
+    #[test]
+    fn test_structured_output_tool_streamed_as_text() {
+        use super::super::{StopReason, UnifiedContent, UnifiedStreamChunk, UnifiedUsage};
+
+        let mut state = CrossProtocolStreamState::new("claude").with_structured_output();
+        let chunks = vec![
+            UnifiedStreamChunk::content_block_start(
+                0,
+                UnifiedContent::tool_use(
+                    "toolu_1",
+                    STRUCTURED_OUTPUT_TOOL_NAME,
+                    serde_json::json!({}),
+                ),
+            ),
+            UnifiedStreamChunk::content_block_delta(
+                0,
+                UnifiedContent::tool_input_delta(0, "{\"name\":"),
+            ),
+            UnifiedStreamChunk::content_block_delta(
+                0,
+                UnifiedContent::tool_input_delta(0, "\"Ada\"}"),
+            ),
+            UnifiedStreamChunk::content_block_stop(0),
+            UnifiedStreamChunk::message_delta(StopReason::ToolUse, UnifiedUsage::new(3, 4)),
+        ];
+
+        let result = state.process_chunks(chunks);
+
+        let text: String = result
+            .iter()
+            .filter_map(|c| c.delta.as_ref().and_then(|d| d.as_text()))
+            .collect();
+        assert_eq!(text, "{\"name\":\"Ada\"}");
+        assert!(result
+            .iter()
+            .all(|c| !matches!(c.content_block, Some(UnifiedContent::ToolUse { .. }))));
+        let delta = result
+            .iter()
+            .find(|c| c.chunk_type == super::super::ChunkType::MessageDelta)
+            .unwrap();
+        assert_eq!(delta.stop_reason, Some(StopReason::EndTurn));
+        assert!(state.tool_info_cache.is_empty());
+
+        // Without a requested JSON format the tool call is left alone
+        let mut state = CrossProtocolStreamState::new("claude");
+        let result = state.process_chunks(vec![UnifiedStreamChunk::content_block_start(
+            0,
+            UnifiedContent::tool_use(
+                "toolu_1",
+                STRUCTURED_OUTPUT_TOOL_NAME,
+                serde_json::json!({}),
+            ),
+        )]);
+        assert!(result
+            .iter()
+            .any(|c| matches!(c.content_block, Some(UnifiedContent::ToolUse { .. }))));
+    }
+
+    #[test]
+    fn test_pii_placeholders_restored_and_flushed_on_finalize() {
+        use super::super::{UnifiedContent, UnifiedStreamChunk};
+        use crate::core::config::PiiRedactionConfig;
+
+        let redactor = PiiRedactor::new(&PiiRedactionConfig::default()).unwrap();
+        redactor.redact_text("ann@example.com");
+        let mut state = CrossProtocolStreamState::new("gpt-4").with_pii_restorer(redactor);
+
+        let mut output = state.process_chunks(vec![
+            UnifiedStreamChunk::content_block_start(0, UnifiedContent::text("")),
+            UnifiedStreamChunk::content_block_delta(0, UnifiedContent::text("Hi <EMAIL")),
+            UnifiedStreamChunk::content_block_delta(0, UnifiedContent::text("_1>, see <EMAIL_1")),
+        ]);
+        output.extend(state.finalize());
+
+        let text: String = output
+            .iter()
+            .filter_map(|c| match &c.delta {
+                Some(UnifiedContent::Text { text }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hi ann@example.com, see <EMAIL_1");
+        let flushed = output
+            .iter()
+            .rposition(|c| c.chunk_type == super::super::ChunkType::ContentBlockDelta)
+            .unwrap();
+        let stop = output
+            .iter()
+            .position(|c| c.chunk_type == super::super::ChunkType::ContentBlockStop)
+            .unwrap();
+        assert!(flushed < stop);
+    }
 }
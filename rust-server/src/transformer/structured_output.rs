@@ -0,0 +1,277 @@
+//! Structured output (`response_format`) helpers shared by the protocol transformers.
+//!
+//! The requested format lives in `UnifiedParameters::response_format` and is
+//! mapped per provider:
+//!
+//! - OpenAI Chat Completions: `response_format`
+//! - Response API: `text.format`
+//! - Gemini: `generationConfig.responseMimeType` / `responseSchema`
+//! - Anthropic: a single tool named [`STRUCTURED_OUTPUT_TOOL_NAME`] whose input
+//!   is unwrapped back into message text on the way out
+//!
+//! Validation against the schema is optional and happens in the proxy handler
+//! (see `provider_params.structured_output_validation`).
+
+use serde_json::{json, Value};
+
+use super::unified::{
+    ResponseFormat, Role, StopReason, UnifiedContent, UnifiedMessage, UnifiedRequest,
+    UnifiedResponse, UnifiedTool,
+};
+
+/// Reserved tool name used to emulate structured output on Anthropic.
+///
+/// Prefixed so it does not collide with the client's own tools; it is only
+/// unwrapped when the request asked for a JSON response format.
+pub const STRUCTURED_OUTPUT_TOOL_NAME: &str = "__llm_proxy_json_response";
+
+/// Parse an OpenAI Chat Completions `response_format` value.
+///
+/// Returns `None` for unknown format types so the caller can pass them through.
+pub fn parse_openai_response_format(value: &Value) -> Option<ResponseFormat> {
+    match value.get("type").and_then(|t| t.as_str())? {
+        "text" => Some(ResponseFormat::Text),
+        "json_object" => Some(ResponseFormat::JsonObject),
+        "json_schema" => {
+            let spec = value.get("json_schema")?;
+            Some(ResponseFormat::JsonSchema {
+                name: spec
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or("response")
+                    .to_string(),
+                description: spec
+                    .get("description")
+                    .and_then(|d| d.as_str())
+                    .map(String::from),
+                schema: spec.get("schema").cloned().unwrap_or_else(|| json!({})),
+                strict: spec.get("strict").and_then(|s| s.as_bool()),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Build an OpenAI Chat Completions `response_format` value.
+pub fn to_openai_response_format(format: &ResponseFormat) -> Value {
+    match format {
+        ResponseFormat::Text => json!({"type": "text"}),
+        ResponseFormat::JsonObject => json!({"type": "json_object"}),
+        ResponseFormat::JsonSchema {
+            name,
+            description,
+            schema,
+            strict,
+        } => {
+            let mut spec = json!({"name": name, "schema": schema});
+            if let Some(description) = description {
+                spec["description"] = json!(description);
+            }
+            if let Some(strict) = strict {
+                spec["strict"] = json!(strict);
+            }
+            json!({"type": "json_schema", "json_schema": spec})
+        }
+    }
+}
+
+/// Tool definition that carries the requested schema for Anthropic.
+pub fn structured_output_tool(format: &ResponseFormat) -> Option<UnifiedTool> {
+    let (description, schema) = match format {
+        ResponseFormat::Text => return None,
+        ResponseFormat::JsonObject => (None, json!({"type": "object"})),
+        ResponseFormat::JsonSchema {
+            description,
+            schema,
+            ..
+        } => (description.clone(), schema.clone()),
+    };
+    Some(UnifiedTool::function(
+        STRUCTURED_OUTPUT_TOOL_NAME,
+        Some(description.unwrap_or_else(|| {
+            "Respond to the user by calling this tool with the final answer as its input."
+                .to_string()
+        })),
+        schema,
+    ))
+}
+
+/// Replace the structured output tool call with its JSON input as plain text.
+pub fn unwrap_structured_output_tool(response: &mut UnifiedResponse) {
+    let mut unwrapped = false;
+    for content in response.content.iter_mut() {
+        if let UnifiedContent::ToolUse { name, input, .. } = content {
+            if name == STRUCTURED_OUTPUT_TOOL_NAME {
+                *content = UnifiedContent::text(input.to_string());
+                unwrapped = true;
+            }
+        }
+    }
+    if !unwrapped {
+        return;
+    }
+    response
+        .tool_calls
+        .retain(|call| call.name != STRUCTURED_OUTPUT_TOOL_NAME);
+    if response.tool_calls.is_empty() && response.stop_reason == Some(StopReason::ToolUse) {
+        response.stop_reason = Some(StopReason::EndTurn);
+    }
+}
+
+/// Check model output against the requested format.
+///
+/// Returns a human-readable description of the first problems found.
+pub fn validate_structured_output(
+    text: &str,
+    format: &ResponseFormat,
+) -> std::result::Result<(), String> {
+    if !format.is_json() {
+        return Ok(());
+    }
+    let instance: Value = serde_json::from_str(text.trim())
+        .map_err(|e| format!("output is not valid JSON: {}", e))?;
+
+    let Some(schema) = format.schema() else {
+        return if instance.is_object() {
+            Ok(())
+        } else {
+            Err("output is not a JSON object".to_string())
+        };
+    };
+
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| format!("schema could not be compiled: {}", e))?;
+    let errors: Vec<String> = validator
+        .iter_errors(&instance)
+        .take(5)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            if path.is_empty() {
+                e.to_string()
+            } else {
+                format!("{}: {}", path, e)
+            }
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// Append the invalid answer and a repair instruction to the conversation.
+pub fn add_repair_turn(request: &mut UnifiedRequest, invalid_output: &str, error: &str) {
+    request
+        .messages
+        .push(UnifiedMessage::assistant(invalid_output));
+    request.messages.push(UnifiedMessage::new(
+        Role::User,
+        format!(
+            "Your previous response did not match the required JSON schema ({}). \
+             Reply again with only the corrected JSON.",
+            error
+        ),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::unified::{UnifiedToolCall, UnifiedUsage};
+
+    fn person_format() -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            name: "person".to_string(),
+            description: None,
+            schema: json!({
+                "type": "object",
+                "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+                "required": ["name", "age"]
+            }),
+            strict: Some(true),
+        }
+    }
+
+    #[test]
+    fn test_openai_response_format_roundtrip() {
+        let raw = json!({
+            "type": "json_schema",
+            "json_schema": {"name": "person", "schema": {"type": "object"}, "strict": true}
+        });
+        let format = parse_openai_response_format(&raw).unwrap();
+        assert_eq!(to_openai_response_format(&format), raw);
+
+        assert_eq!(
+            parse_openai_response_format(&json!({"type": "json_object"})),
+            Some(ResponseFormat::JsonObject)
+        );
+        assert_eq!(parse_openai_response_format(&json!({"type": "xml"})), None);
+    }
+
+    #[test]
+    fn test_unwrap_structured_output_tool() {
+        let input = json!({"name": "Ada", "age": 36});
+        let mut response = UnifiedResponse::new(
+            "msg_1",
+            "claude",
+            vec![UnifiedContent::tool_use(
+                "toolu_1",
+                STRUCTURED_OUTPUT_TOOL_NAME,
+                input.clone(),
+            )],
+            Some(StopReason::ToolUse),
+            UnifiedUsage::new(1, 1),
+        );
+        response.tool_calls.push(UnifiedToolCall {
+            id: "toolu_1".to_string(),
+            name: STRUCTURED_OUTPUT_TOOL_NAME.to_string(),
+            arguments: input.clone(),
+        });
+
+        unwrap_structured_output_tool(&mut response);
+
+        assert!(response.tool_calls.is_empty());
+        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+        let text: Value = serde_json::from_str(&response.text_content()).unwrap();
+        assert_eq!(text, input);
+    }
+
+    #[test]
+    fn test_unwrap_keeps_other_tool_calls() {
+        let mut response = UnifiedResponse::new(
+            "msg_1",
+            "claude",
+            vec![UnifiedContent::tool_use("toolu_1", "search", json!({}))],
+            Some(StopReason::ToolUse),
+            UnifiedUsage::default(),
+        );
+        unwrap_structured_output_tool(&mut response);
+        assert_eq!(response.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(response.content[0].content_type(), "tool_use");
+    }
+
+    #[test]
+    fn test_validate_structured_output() {
+        let format = person_format();
+        assert!(validate_structured_output(r#"{"name": "Ada", "age": 36}"#, &format).is_ok());
+
+        let err = validate_structured_output(r#"{"name": "Ada"}"#, &format).unwrap_err();
+        assert!(err.contains("age"));
+
+        let err = validate_structured_output("not json", &format).unwrap_err();
+        assert!(err.contains("not valid JSON"));
+
+        assert!(validate_structured_output("[1]", &ResponseFormat::JsonObject).is_err());
+        assert!(validate_structured_output("anything", &ResponseFormat::Text).is_ok());
+    }
+
+    #[test]
+    fn test_add_repair_turn() {
+        let mut request = UnifiedRequest::new("gpt-4", vec![UnifiedMessage::user("hi")]);
+        add_repair_turn(&mut request, "{}", "missing name");
+        assert_eq!(request.messages.len(), 3);
+        assert_eq!(request.messages[1].role, Role::Assistant);
+        assert!(request.messages[2].text_content().contains("missing name"));
+    }
+}
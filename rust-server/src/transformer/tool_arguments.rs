@@ -0,0 +1,452 @@
+//! Validation and repair of tool call arguments.
+//!
+//! Smaller models sometimes produce truncated or malformed JSON as tool
+//! arguments. [`ArgumentScanner`] tracks the JSON structure of the arguments
+//! as they arrive and applies a conservative repair:
+//!
+//! - text after the complete top-level value is dropped
+//! - trailing commas before a closing bracket or at the end are dropped
+//! - an unterminated string is closed, a dangling key gets a `null` value
+//! - open objects and arrays are closed
+//!
+//! Anything else (e.g. a broken literal) is reported as invalid. Streams feed
+//! the scanner delta by delta from `CrossProtocolStreamState` and append the
+//! repair when the block closes; non-streaming responses run the same pass
+//! through [`ToolArgumentChecker::check_response`]. Arguments can optionally
+//! be validated against the tool's input schema
+//! (see `provider_params.tool_argument_validation`).
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::tool_normalizer::raw_tool_definitions;
+use super::unified::{UnifiedContent, UnifiedRequest, UnifiedResponse, UnifiedToolCall};
+use crate::core::try_get_metrics;
+
+/// Provider param enabling validation of tool arguments against input schemas.
+pub const TOOL_ARGUMENT_VALIDATION_PARAM: &str = "tool_argument_validation";
+
+/// Outcome of checking the arguments of one tool call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentsOutcome {
+    /// Valid JSON as produced by the model
+    Valid,
+    /// Malformed JSON fixed by the conservative repair
+    Repaired,
+    /// Malformed JSON that could not be repaired
+    Invalid,
+    /// Valid (or repaired) JSON that does not match the tool's input schema
+    SchemaMismatch,
+}
+
+impl ArgumentsOutcome {
+    /// Metric label of the outcome.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArgumentsOutcome::Valid => "valid",
+            ArgumentsOutcome::Repaired => "repaired",
+            ArgumentsOutcome::Invalid => "invalid",
+            ArgumentsOutcome::SchemaMismatch => "schema_mismatch",
+        }
+    }
+}
+
+// ============================================================================
+// Scanner
+// ============================================================================
+
+/// Incremental scanner over the JSON text of tool arguments.
+#[derive(Debug, Clone, Default)]
+pub struct ArgumentScanner {
+    /// Text passed through so far
+    text: String,
+    /// Commas (and following whitespace) held back until the next token
+    held: String,
+    /// Open `{` / `[` brackets
+    stack: Vec<char>,
+    in_string: bool,
+    escaped: bool,
+    /// Whether the top-level value is complete
+    complete: bool,
+    /// Whether any input was dropped
+    dropped: bool,
+}
+
+impl ArgumentScanner {
+    /// Create an empty scanner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed the next piece of argument text.
+    ///
+    /// Returns the part that can be passed on; trailing commas are held back
+    /// and text after the complete top-level value is dropped.
+    pub fn push(&mut self, partial: &str) -> String {
+        let mut out = String::with_capacity(partial.len());
+        for c in partial.chars() {
+            if self.complete {
+                self.dropped |= !c.is_whitespace();
+                continue;
+            }
+            if self.in_string {
+                out.push(c);
+                if self.escaped {
+                    self.escaped = false;
+                } else if c == '\\' {
+                    self.escaped = true;
+                } else if c == '"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+            match c {
+                ',' => self.held.push(c),
+                c if c.is_whitespace() => {
+                    if self.held.is_empty() {
+                        out.push(c);
+                    } else {
+                        self.held.push(c);
+                    }
+                }
+                '}' | ']' => {
+                    if !self.held.is_empty() {
+                        self.held.clear();
+                        self.dropped = true;
+                    }
+                    out.push(c);
+                    if self.stack.pop().is_some() && self.stack.is_empty() {
+                        self.complete = true;
+                    }
+                }
+                _ => {
+                    out.push_str(&std::mem::take(&mut self.held));
+                    out.push(c);
+                    match c {
+                        '{' | '[' => self.stack.push(c),
+                        '"' => self.in_string = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        self.text.push_str(&out);
+        out
+    }
+
+    /// Finish the arguments.
+    ///
+    /// Returns the text to append so the arguments parse, the parsed value
+    /// and the outcome. Empty arguments are valid and parse as `{}`.
+    pub fn finish(&mut self) -> (String, Value, ArgumentsOutcome) {
+        if self.text.trim().is_empty() {
+            let outcome = if self.dropped {
+                ArgumentsOutcome::Invalid
+            } else {
+                ArgumentsOutcome::Valid
+            };
+            return (String::new(), json!({}), outcome);
+        }
+        if !self.held.is_empty() {
+            self.held.clear();
+            self.dropped = true;
+        }
+
+        let mut prefix = String::new();
+        if self.in_string {
+            prefix.push('"');
+        } else if self.text.trim_end().ends_with(':') {
+            prefix.push_str("null");
+        }
+        let closers: String = self
+            .stack
+            .iter()
+            .rev()
+            .map(|open| if *open == '{' { '}' } else { ']' })
+            .collect();
+
+        let mut candidates = vec![format!("{}{}", prefix, closers)];
+        // An unterminated key inside an object still needs a value
+        if self.stack.last() == Some(&'{') && (self.in_string || self.text.ends_with('"')) {
+            candidates.push(format!("{}:null{}", prefix, closers));
+        }
+        for suffix in candidates {
+            if let Ok(value) = serde_json::from_str::<Value>(&format!("{}{}", self.text, suffix)) {
+                let outcome = if suffix.is_empty() && !self.dropped {
+                    ArgumentsOutcome::Valid
+                } else {
+                    ArgumentsOutcome::Repaired
+                };
+                return (suffix, value, outcome);
+            }
+        }
+        (String::new(), json!({}), ArgumentsOutcome::Invalid)
+    }
+}
+
+/// Parse complete argument text, applying the conservative repair.
+pub fn repair_arguments(text: &str) -> (Value, ArgumentsOutcome) {
+    let mut scanner = ArgumentScanner::new();
+    scanner.push(text);
+    let (_, value, outcome) = scanner.finish();
+    (value, outcome)
+}
+
+/// Parse argument text from a provider, keeping malformed text as a string.
+///
+/// The string is repaired later by [`ToolArgumentChecker::check_response`]
+/// (or [`repair_request_arguments`]), which can report the outcome.
+pub fn parse_raw_arguments(text: &str) -> Value {
+    if text.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+}
+
+/// Repair malformed arguments of earlier tool calls in the conversation.
+pub fn repair_request_arguments(request: &mut UnifiedRequest) {
+    for message in &mut request.messages {
+        for call in &mut message.tool_calls {
+            if let Some(text) = call.arguments.as_str() {
+                call.arguments = repair_arguments(text).0;
+            }
+        }
+        for block in &mut message.content {
+            if let UnifiedContent::ToolUse { input, .. } = block {
+                if let Some(text) = input.as_str() {
+                    *input = repair_arguments(text).0;
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Checker
+// ============================================================================
+
+/// Checks tool call arguments of one request, logging and counting outcomes.
+#[derive(Debug, Clone, Default)]
+pub struct ToolArgumentChecker {
+    /// Model label for metrics
+    model: String,
+    /// Provider label for metrics
+    provider: String,
+    /// Input schemas by client tool name, when schema validation is enabled
+    schemas: HashMap<String, Value>,
+}
+
+impl ToolArgumentChecker {
+    /// Create a checker reporting under the given model and provider.
+    pub fn new(model: impl Into<String>, provider: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            provider: provider.into(),
+            schemas: HashMap::new(),
+        }
+    }
+
+    /// Validate arguments against the input schemas of the raw client request.
+    pub fn with_schemas(mut self, raw: &Value) -> Self {
+        self.schemas = raw_tool_definitions(raw)
+            .into_iter()
+            .filter_map(|(name, schema)| Some((name.to_string(), schema?.clone())))
+            .collect();
+        self
+    }
+
+    /// Check the parsed arguments of a finished tool call and record the outcome.
+    ///
+    /// `outcome` is the result of parsing; schema validation may downgrade it.
+    pub fn record(&self, name: &str, arguments: &Value, outcome: ArgumentsOutcome) {
+        let mut outcome = outcome;
+        let mut detail = String::new();
+        if outcome != ArgumentsOutcome::Invalid {
+            if let Some(errors) = self.schema_errors(name, arguments) {
+                outcome = ArgumentsOutcome::SchemaMismatch;
+                detail = errors;
+            }
+        }
+
+        match outcome {
+            ArgumentsOutcome::Valid => {}
+            ArgumentsOutcome::Repaired => {
+                tracing::info!(tool = %name, model = %self.model, provider = %self.provider, "Repaired malformed tool call arguments");
+            }
+            ArgumentsOutcome::Invalid => {
+                tracing::warn!(tool = %name, model = %self.model, provider = %self.provider, "Tool call arguments are not valid JSON");
+            }
+            ArgumentsOutcome::SchemaMismatch => {
+                tracing::warn!(tool = %name, model = %self.model, provider = %self.provider, errors = %detail, "Tool call arguments do not match the input schema");
+            }
+        }
+        if let Some(metrics) = try_get_metrics() {
+            metrics
+                .tool_argument_validations
+                .with_label_values(&[&self.model, &self.provider, outcome.as_str()])
+                .inc();
+        }
+    }
+
+    fn schema_errors(&self, name: &str, arguments: &Value) -> Option<String> {
+        let schema = self.schemas.get(name)?;
+        let validator = jsonschema::validator_for(schema).ok()?;
+        let errors: Vec<String> = validator
+            .iter_errors(arguments)
+            .take(5)
+            .map(|e| e.to_string())
+            .collect();
+        (!errors.is_empty()).then(|| errors.join("; "))
+    }
+
+    /// Check a single argument value, repairing it if it is still raw text.
+    fn check(&self, name: &str, arguments: &mut Value) {
+        let outcome = match arguments {
+            Value::String(text) => {
+                let (value, outcome) = repair_arguments(text);
+                *arguments = value;
+                outcome
+            }
+            _ => ArgumentsOutcome::Valid,
+        };
+        self.record(name, arguments, outcome);
+    }
+
+    fn check_calls(&self, content: &mut [UnifiedContent], tool_calls: &mut [UnifiedToolCall]) {
+        for call in tool_calls.iter_mut() {
+            self.check(&call.name, &mut call.arguments);
+        }
+        for block in content {
+            if let UnifiedContent::ToolUse { id, name, input } = block {
+                // Calls listed in `tool_calls` as well were already counted
+                match tool_calls.iter().find(|call| call.id == *id) {
+                    Some(call) if input.is_string() => *input = call.arguments.clone(),
+                    Some(_) => {}
+                    None => self.check(name, input),
+                }
+            }
+        }
+    }
+
+    /// Check and repair the tool call arguments of a non-streaming response.
+    pub fn check_response(&self, response: &mut UnifiedResponse) {
+        self.check_calls(&mut response.content, &mut response.tool_calls);
+        for choice in &mut response.additional_choices {
+            self.check_calls(&mut choice.content, &mut choice.tool_calls);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(parts: &[&str]) -> (String, Value, ArgumentsOutcome) {
+        let mut scanner = ArgumentScanner::new();
+        let mut text: String = parts.iter().map(|p| scanner.push(p)).collect();
+        let (suffix, value, outcome) = scanner.finish();
+        text.push_str(&suffix);
+        (text, value, outcome)
+    }
+
+    #[test]
+    fn test_valid_arguments_pass_through() {
+        let (text, value, outcome) = scan(&["{\"city\": ", "\"Par", "is\", \"days\": [1, 2]}"]);
+        assert_eq!(text, "{\"city\": \"Paris\", \"days\": [1, 2]}");
+        assert_eq!(value, json!({"city": "Paris", "days": [1, 2]}));
+        assert_eq!(outcome, ArgumentsOutcome::Valid);
+
+        assert_eq!(repair_arguments("").1, ArgumentsOutcome::Valid);
+        assert_eq!(repair_arguments("").0, json!({}));
+    }
+
+    #[test]
+    fn test_truncated_arguments_are_closed() {
+        let (text, value, outcome) = scan(&["{\"city\": \"Par"]);
+        assert_eq!(text, "{\"city\": \"Par\"}");
+        assert_eq!(value, json!({"city": "Par"}));
+        assert_eq!(outcome, ArgumentsOutcome::Repaired);
+
+        assert_eq!(
+            repair_arguments("{\"a\": [1, {\"b\": 2").0,
+            json!({"a": [1, {"b": 2}]})
+        );
+        assert_eq!(repair_arguments("{\"a\": ").0, json!({"a": null}));
+        assert_eq!(
+            repair_arguments("{\"a\": 1, \"b").0,
+            json!({"a": 1, "b": null})
+        );
+    }
+
+    #[test]
+    fn test_trailing_commas_and_garbage_are_dropped() {
+        let (text, value, outcome) = scan(&["{\"a\": 1,", " }", "\n</tool_call> done"]);
+        assert_eq!(text, "{\"a\": 1}");
+        assert_eq!(value, json!({"a": 1}));
+        assert_eq!(outcome, ArgumentsOutcome::Repaired);
+
+        let (text, _, _) = scan(&["{\"a\": [1,", "2]}"]);
+        assert_eq!(text, "{\"a\": [1,2]}");
+        assert_eq!(repair_arguments("{\"a\": 1,").0, json!({"a": 1}));
+        // Commas inside strings are content
+        assert_eq!(
+            repair_arguments("{\"a\": \"x,}\"}").1,
+            ArgumentsOutcome::Valid
+        );
+    }
+
+    #[test]
+    fn test_unrepairable_arguments_are_invalid() {
+        let (value, outcome) = repair_arguments("{\"a\": tru");
+        assert_eq!(outcome, ArgumentsOutcome::Invalid);
+        assert_eq!(value, json!({}));
+        assert_eq!(repair_arguments("not json").1, ArgumentsOutcome::Invalid);
+    }
+
+    #[test]
+    fn test_checker_repairs_raw_response_arguments() {
+        let checker = ToolArgumentChecker::new("m", "p");
+        let mut response = UnifiedResponse::new("r", "m", vec![], None, Default::default());
+        response.tool_calls.push(UnifiedToolCall {
+            id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments: parse_raw_arguments("{\"city\": \"Paris\"}}"),
+        });
+        response.content.push(UnifiedContent::tool_use(
+            "call_1",
+            "get_weather",
+            parse_raw_arguments("{\"city\": \"Paris\"}}"),
+        ));
+
+        checker.check_response(&mut response);
+        assert_eq!(response.tool_calls[0].arguments, json!({"city": "Paris"}));
+        assert!(matches!(
+            &response.content[0],
+            UnifiedContent::ToolUse { input, .. } if *input == json!({"city": "Paris"})
+        ));
+    }
+
+    #[test]
+    fn test_checker_schema_validation() {
+        let raw = json!({
+            "tools": [{"name": "get_weather", "input_schema": {
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "required": ["city"]
+            }}]
+        });
+        let checker = ToolArgumentChecker::new("m", "p").with_schemas(&raw);
+        assert!(checker
+            .schema_errors("get_weather", &json!({"city": "Paris"}))
+            .is_none());
+        assert!(checker
+            .schema_errors("get_weather", &json!({}))
+            .unwrap()
+            .contains("city"));
+        assert!(checker.schema_errors("other", &json!({})).is_none());
+    }
+}
@@ -0,0 +1,749 @@
+//! Prompt-based tool calling for models without native function calling.
+//!
+//! Model mappings with `supports_function_calling: false` get their tool
+//! definitions rendered into the system prompt instead of the provider's
+//! `tools` field. The model is asked to answer with blocks like
+//!
+//! ```text
+//! <tool_call>
+//! {"name": "get_weather", "arguments": {"city": "Paris"}}
+//! </tool_call>
+//! ```
+//!
+//! which are parsed back into native tool calls, so clients keep receiving
+//! OpenAI `tool_calls` or Anthropic `tool_use` blocks. Earlier tool calls and
+//! tool results in the conversation are rendered as text in the same format.
+//!
+//! Streams are parsed by [`ToolCallStreamParser`], driven from
+//! `CrossProtocolStreamState`.
+
+use serde_json::{json, Value};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use super::features::FeatureTransformer;
+use super::unified::{
+    ChunkType, Role, StopReason, UnifiedContent, UnifiedMessage, UnifiedRequest, UnifiedResponse,
+    UnifiedStreamChunk, UnifiedToolCall,
+};
+use crate::core::error::Result;
+
+/// Marker opening a tool call in model output.
+pub const TOOL_CALL_OPEN: &str = "<tool_call>";
+
+/// Marker closing a tool call in model output.
+pub const TOOL_CALL_CLOSE: &str = "</tool_call>";
+
+/// Placeholder replaced with the rendered tool definitions.
+pub const TOOLS_PLACEHOLDER: &str = "{tools}";
+
+/// Default system prompt section describing the available tools.
+pub const DEFAULT_TOOL_PROMPT_TEMPLATE: &str = "# Tools
+
+You may call one or more of the following tools to help with the user's request:
+
+{tools}
+
+To call a tool, reply with one block per call in exactly this format:
+<tool_call>
+{\"name\": \"<tool name>\", \"arguments\": {<arguments as a JSON object>}}
+</tool_call>
+
+Tool results are sent back to you in <tool_response> blocks. Only call the tools listed above.";
+
+// ============================================================================
+// Feature Transformer
+// ============================================================================
+
+/// Feature transformer emulating tool calling through the prompt.
+///
+/// # Example
+///
+/// ```ignore
+/// use llm_proxy_rust::transformer::tool_emulation::ToolEmulationTransformer;
+///
+/// let transformer = ToolEmulationTransformer::new(None);
+/// transformer.transform_request(&mut request)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ToolEmulationTransformer {
+    /// System prompt template containing [`TOOLS_PLACEHOLDER`]
+    template: String,
+}
+
+impl Default for ToolEmulationTransformer {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ToolEmulationTransformer {
+    /// Create a transformer using `template`, or the default template.
+    pub fn new(template: Option<String>) -> Self {
+        Self {
+            template: template.unwrap_or_else(|| DEFAULT_TOOL_PROMPT_TEMPLATE.to_string()),
+        }
+    }
+
+    /// Render the tool section of the system prompt.
+    fn render_tools_prompt(&self, request: &UnifiedRequest) -> String {
+        let tools = request
+            .tools
+            .iter()
+            .map(|tool| {
+                let mut definition = json!({"name": tool.name, "parameters": tool.input_schema});
+                if let Some(description) = &tool.description {
+                    definition["description"] = json!(description);
+                }
+                definition.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut prompt = if self.template.contains(TOOLS_PLACEHOLDER) {
+            self.template.replace(TOOLS_PLACEHOLDER, &tools)
+        } else {
+            format!("{}\n\n{}", self.template, tools)
+        };
+        if let Some(instruction) = tool_choice_instruction(request.tool_choice.as_ref()) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&instruction);
+        }
+        prompt
+    }
+}
+
+impl FeatureTransformer for ToolEmulationTransformer {
+    fn transform_request(&self, request: &mut UnifiedRequest) -> Result<()> {
+        render_tool_history(&mut request.messages);
+
+        let disabled = matches!(
+            request.tool_choice.as_ref().and_then(tool_choice_type),
+            Some("none")
+        );
+        if !request.tools.is_empty() && !disabled {
+            let prompt = self.render_tools_prompt(request);
+            request.system = Some(match request.system.take() {
+                Some(system) if !system.is_empty() => format!("{}\n\n{}", system, prompt),
+                _ => prompt,
+            });
+        }
+        request.tools.clear();
+        request.tool_choice = None;
+        Ok(())
+    }
+
+    fn transform_response(&self, response: &mut UnifiedResponse) -> Result<()> {
+        let (content, calls) = extract_tool_calls(std::mem::take(&mut response.content));
+        response.content = content;
+        if !calls.is_empty() {
+            response.tool_calls.extend(calls);
+            response.stop_reason = Some(StopReason::ToolUse);
+        }
+        for choice in &mut response.additional_choices {
+            let (content, calls) = extract_tool_calls(std::mem::take(&mut choice.content));
+            choice.content = content;
+            if !calls.is_empty() {
+                choice.tool_calls.extend(calls);
+                choice.stop_reason = Some(StopReason::ToolUse);
+            }
+        }
+        Ok(())
+    }
+
+    fn transform_stream_chunk(&self, _chunk: &mut UnifiedStreamChunk) -> Result<()> {
+        // Tool calls span several chunks; streams use ToolCallStreamParser instead
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "tool_emulation"
+    }
+}
+
+/// Type of a `tool_choice` value (`auto`, `none`, `required`/`any`, `function`/`tool`).
+fn tool_choice_type(tool_choice: &Value) -> Option<&str> {
+    tool_choice
+        .as_str()
+        .or_else(|| tool_choice.get("type").and_then(|t| t.as_str()))
+}
+
+/// Extra prompt instruction for a forcing `tool_choice`.
+fn tool_choice_instruction(tool_choice: Option<&Value>) -> Option<String> {
+    let tool_choice = tool_choice?;
+    match tool_choice_type(tool_choice)? {
+        "required" | "any" => Some("You must call at least one tool.".to_string()),
+        "function" | "tool" => {
+            let name = tool_choice
+                .get("function")
+                .and_then(|f| f.get("name"))
+                .or_else(|| tool_choice.get("name"))
+                .and_then(|n| n.as_str())?;
+            Some(format!("You must call the `{}` tool.", name))
+        }
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Request History
+// ============================================================================
+
+/// Render a tool call the way the model is asked to write it.
+fn render_tool_call(name: &str, arguments: &Value) -> String {
+    format!(
+        "{}\n{}\n{}",
+        TOOL_CALL_OPEN,
+        json!({"name": name, "arguments": arguments}),
+        TOOL_CALL_CLOSE
+    )
+}
+
+/// Render a tool result as text.
+fn render_tool_response(name: Option<&str>, content: &Value, is_error: bool) -> String {
+    let body = match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .map(|part| match part.get("text").and_then(|t| t.as_str()) {
+                Some(text) => text.to_string(),
+                None => part.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    let mut attributes = String::new();
+    if let Some(name) = name {
+        attributes.push_str(&format!(" name=\"{}\"", name));
+    }
+    if is_error {
+        attributes.push_str(" error=\"true\"");
+    }
+    format!("<tool_response{}>\n{}\n</tool_response>", attributes, body)
+}
+
+/// Replace tool calls and tool results in the conversation with text.
+///
+/// Tool messages become user messages, since the model has no tool role.
+fn render_tool_history(messages: &mut [UnifiedMessage]) {
+    let mut names: HashMap<String, String> = HashMap::new();
+
+    for message in messages.iter_mut() {
+        let mut content = Vec::with_capacity(message.content.len());
+        let mut has_result = false;
+        for block in std::mem::take(&mut message.content) {
+            match block {
+                UnifiedContent::ToolUse { id, name, input } => {
+                    content.push(UnifiedContent::text(render_tool_call(&name, &input)));
+                    names.insert(id, name);
+                }
+                UnifiedContent::ToolResult {
+                    tool_use_id,
+                    content: result,
+                    is_error,
+                } => {
+                    let name = names.get(&tool_use_id).map(String::as_str);
+                    has_result = true;
+                    content.push(UnifiedContent::text(render_tool_response(
+                        name, &result, is_error,
+                    )));
+                }
+                other => content.push(other),
+            }
+        }
+
+        for call in std::mem::take(&mut message.tool_calls) {
+            if let Entry::Vacant(entry) = names.entry(call.id) {
+                content.push(UnifiedContent::text(render_tool_call(
+                    &call.name,
+                    &call.arguments,
+                )));
+                entry.insert(call.name);
+            }
+        }
+
+        if message.role == Role::Tool {
+            // OpenAI tool messages carry the result as plain content
+            if !has_result {
+                let name = message
+                    .tool_call_id
+                    .as_ref()
+                    .and_then(|id| names.get(id))
+                    .map(String::as_str);
+                let text = content
+                    .iter()
+                    .filter_map(|c| c.as_text())
+                    .collect::<Vec<_>>()
+                    .join("");
+                content.retain(|c| c.as_text().is_none());
+                content.insert(
+                    0,
+                    UnifiedContent::text(render_tool_response(name, &Value::String(text), false)),
+                );
+            }
+            message.role = Role::User;
+        }
+        message.tool_call_id = None;
+        message.content = content;
+    }
+}
+
+// ============================================================================
+// Response Parsing
+// ============================================================================
+
+/// Parse the body of a `<tool_call>` block.
+///
+/// Accepts `arguments` or `parameters`, as an object or a JSON-encoded string.
+pub fn parse_tool_call(body: &str) -> Option<UnifiedToolCall> {
+    let value: Value = serde_json::from_str(body.trim()).ok()?;
+    let name = value.get("name")?.as_str()?.to_string();
+    let arguments = match value.get("arguments").or_else(|| value.get("parameters")) {
+        Some(Value::String(encoded)) => serde_json::from_str(encoded).ok()?,
+        Some(arguments) => arguments.clone(),
+        None => json!({}),
+    };
+    Some(UnifiedToolCall {
+        id: new_tool_call_id(),
+        name,
+        arguments,
+    })
+}
+
+fn new_tool_call_id() -> String {
+    format!("call_{}", uuid::Uuid::new_v4().simple())
+}
+
+/// A piece of model output: plain text or a parsed tool call.
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    ToolCall(UnifiedToolCall),
+}
+
+/// Split text into text and tool call segments.
+///
+/// Malformed or unterminated blocks are kept as text.
+fn split_segments(text: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(TOOL_CALL_OPEN) {
+        let body_start = start + TOOL_CALL_OPEN.len();
+        let Some(end) = rest[body_start..].find(TOOL_CALL_CLOSE) else {
+            break;
+        };
+        let block_end = body_start + end + TOOL_CALL_CLOSE.len();
+        segments.push(Segment::Text(rest[..start].to_string()));
+        segments.push(match parse_tool_call(&rest[body_start..body_start + end]) {
+            Some(call) => Segment::ToolCall(call),
+            None => Segment::Text(rest[start..block_end].to_string()),
+        });
+        rest = &rest[block_end..];
+    }
+    segments.push(Segment::Text(rest.to_string()));
+    segments
+}
+
+/// Move tool calls found in text content into native tool calls.
+fn extract_tool_calls(content: Vec<UnifiedContent>) -> (Vec<UnifiedContent>, Vec<UnifiedToolCall>) {
+    let mut out = Vec::with_capacity(content.len());
+    let mut calls = Vec::new();
+    for block in content {
+        let UnifiedContent::Text { text } = block else {
+            out.push(block);
+            continue;
+        };
+        if !text.contains(TOOL_CALL_OPEN) {
+            out.push(UnifiedContent::text(text));
+            continue;
+        }
+        let mut pending = String::new();
+        for segment in split_segments(&text) {
+            match segment {
+                Segment::Text(text) => pending.push_str(&text),
+                Segment::ToolCall(call) => calls.push(call),
+            }
+        }
+        if !pending.trim().is_empty() {
+            out.push(UnifiedContent::text(pending.trim()));
+        }
+    }
+    (out, calls)
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Incremental parser turning streamed `<tool_call>` blocks into tool_use chunks.
+///
+/// Text deltas are held back only while they could be the start of a tool
+/// call marker. Each complete call is emitted as a `content_block_start`,
+/// one `tool_input_delta` and a `content_block_stop` on its own block index.
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallStreamParser {
+    /// Text not yet emitted (possible marker prefix or an open tool call)
+    buffer: String,
+    /// Whether `buffer` holds the body of an open tool call
+    in_tool_call: bool,
+    /// Whitespace held back so it does not open a text block of its own
+    pending_whitespace: String,
+    /// Block index of the open text block
+    text_index: Option<usize>,
+    /// Next free content block index
+    next_index: usize,
+    /// Number of tool calls emitted
+    tool_calls: usize,
+}
+
+impl ToolCallStreamParser {
+    /// Create a new parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether any tool call has been emitted.
+    pub fn has_tool_calls(&self) -> bool {
+        self.tool_calls > 0
+    }
+
+    /// Process unified chunks, rewriting text deltas that contain tool calls.
+    pub fn process(&mut self, chunks: Vec<UnifiedStreamChunk>) -> Vec<UnifiedStreamChunk> {
+        let mut result = Vec::new();
+        for mut chunk in chunks {
+            match chunk.chunk_type {
+                ChunkType::ContentBlockDelta
+                    if matches!(chunk.delta, Some(UnifiedContent::Text { .. })) =>
+                {
+                    if let Some(UnifiedContent::Text { text }) = chunk.delta.take() {
+                        self.feed(&text, &mut result);
+                    }
+                }
+                ChunkType::ContentBlockStart
+                    if matches!(chunk.content_block, Some(UnifiedContent::Text { .. })) =>
+                {
+                    // Text blocks are opened on demand with our own indices
+                }
+                ChunkType::ContentBlockStart | ChunkType::ContentBlockDelta => {
+                    self.next_index = self.next_index.max(chunk.index + 1);
+                    result.push(chunk);
+                }
+                ChunkType::MessageDelta | ChunkType::MessageStop => {
+                    result.extend(self.flush());
+                    if chunk.chunk_type == ChunkType::MessageDelta
+                        && self.has_tool_calls()
+                        && matches!(
+                            chunk.stop_reason,
+                            None | Some(StopReason::EndTurn) | Some(StopReason::StopSequence)
+                        )
+                    {
+                        chunk.stop_reason = Some(StopReason::ToolUse);
+                    }
+                    result.push(chunk);
+                }
+                _ => result.push(chunk),
+            }
+        }
+        result
+    }
+
+    /// Emit any held back text, including an unterminated tool call.
+    pub fn flush(&mut self) -> Vec<UnifiedStreamChunk> {
+        let mut result = Vec::new();
+        let mut text = std::mem::take(&mut self.buffer);
+        if self.in_tool_call {
+            text.insert_str(0, TOOL_CALL_OPEN);
+            self.in_tool_call = false;
+        }
+        if !text.is_empty() {
+            self.emit_text(&text, &mut result);
+        }
+        self.pending_whitespace.clear();
+        result
+    }
+
+    fn feed(&mut self, text: &str, result: &mut Vec<UnifiedStreamChunk>) {
+        self.buffer.push_str(text);
+        loop {
+            if self.in_tool_call {
+                let Some(end) = self.buffer.find(TOOL_CALL_CLOSE) else {
+                    return;
+                };
+                let body: String = self.buffer.drain(..end).collect();
+                self.buffer.drain(..TOOL_CALL_CLOSE.len());
+                self.in_tool_call = false;
+                match parse_tool_call(&body) {
+                    Some(call) => self.emit_tool_call(call, result),
+                    None => {
+                        let raw = format!("{}{}{}", TOOL_CALL_OPEN, body, TOOL_CALL_CLOSE);
+                        self.emit_text(&raw, result);
+                    }
+                }
+            } else if let Some(start) = self.buffer.find(TOOL_CALL_OPEN) {
+                let before: String = self.buffer.drain(..start).collect();
+                self.buffer.drain(..TOOL_CALL_OPEN.len());
+                self.in_tool_call = true;
+                if !before.is_empty() {
+                    self.emit_text(&before, result);
+                }
+            } else {
+                // Hold back a suffix that could still grow into the open marker
+                let keep = (1..TOOL_CALL_OPEN.len())
+                    .rev()
+                    .find(|&len| self.buffer.ends_with(&TOOL_CALL_OPEN[..len]))
+                    .unwrap_or(0);
+                let emit: String = self.buffer.drain(..self.buffer.len() - keep).collect();
+                if !emit.is_empty() {
+                    self.emit_text(&emit, result);
+                }
+                return;
+            }
+        }
+    }
+
+    fn emit_text(&mut self, text: &str, result: &mut Vec<UnifiedStreamChunk>) {
+        if text.trim().is_empty() {
+            self.pending_whitespace.push_str(text);
+            return;
+        }
+        let index = match self.text_index {
+            Some(index) => index,
+            None => {
+                let index = self.next_index;
+                self.next_index += 1;
+                self.text_index = Some(index);
+                index
+            }
+        };
+        let text = format!("{}{}", std::mem::take(&mut self.pending_whitespace), text);
+        result.push(UnifiedStreamChunk::content_block_delta(
+            index,
+            UnifiedContent::text(text),
+        ));
+    }
+
+    fn emit_tool_call(&mut self, call: UnifiedToolCall, result: &mut Vec<UnifiedStreamChunk>) {
+        if let Some(index) = self.text_index.take() {
+            result.push(UnifiedStreamChunk::content_block_stop(index));
+        }
+        self.pending_whitespace.clear();
+
+        let index = self.next_index;
+        self.next_index += 1;
+        self.tool_calls += 1;
+        result.push(UnifiedStreamChunk::content_block_start(
+            index,
+            UnifiedContent::tool_use(call.id, call.name, json!({})),
+        ));
+        result.push(UnifiedStreamChunk::content_block_delta(
+            index,
+            UnifiedContent::tool_input_delta(index, call.arguments.to_string()),
+        ));
+        result.push(UnifiedStreamChunk::content_block_stop(index));
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::unified::{UnifiedTool, UnifiedUsage};
+
+    fn weather_tool() -> UnifiedTool {
+        UnifiedTool::function(
+            "get_weather",
+            Some("Get the weather".to_string()),
+            json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+        )
+    }
+
+    fn text_delta(text: &str) -> UnifiedStreamChunk {
+        UnifiedStreamChunk::content_block_delta(0, UnifiedContent::text(text))
+    }
+
+    #[test]
+    fn test_transform_request_renders_tools_into_system() {
+        let transformer = ToolEmulationTransformer::new(None);
+        let mut request = UnifiedRequest::new("qwen", vec![UnifiedMessage::user("Weather?")])
+            .with_system("Be brief.");
+        request.tools.push(weather_tool());
+        request.tool_choice =
+            Some(json!({"type": "function", "function": {"name": "get_weather"}}));
+
+        transformer.transform_request(&mut request).unwrap();
+
+        let system = request.system.unwrap();
+        assert!(system.starts_with("Be brief.\n\n# Tools"));
+        assert!(system.contains(r#""name":"get_weather""#));
+        assert!(system.contains("You must call the `get_weather` tool."));
+        assert!(request.tools.is_empty());
+        assert!(request.tool_choice.is_none());
+    }
+
+    #[test]
+    fn test_transform_request_custom_template_and_none_choice() {
+        let transformer = ToolEmulationTransformer::new(Some("Tools:\n{tools}".to_string()));
+        let mut request = UnifiedRequest::new("qwen", vec![UnifiedMessage::user("hi")]);
+        request.tools.push(weather_tool());
+        transformer.transform_request(&mut request).unwrap();
+        assert!(request.system.as_deref().unwrap().starts_with("Tools:\n{"));
+
+        let mut request = UnifiedRequest::new("qwen", vec![UnifiedMessage::user("hi")]);
+        request.tools.push(weather_tool());
+        request.tool_choice = Some(json!("none"));
+        transformer.transform_request(&mut request).unwrap();
+        assert!(request.system.is_none());
+    }
+
+    #[test]
+    fn test_transform_request_renders_tool_history() {
+        let transformer = ToolEmulationTransformer::new(None);
+        let assistant =
+            UnifiedMessage::with_content(Role::Assistant, vec![]).with_tool_call(UnifiedToolCall {
+                id: "call_1".to_string(),
+                name: "get_weather".to_string(),
+                arguments: json!({"city": "Paris"}),
+            });
+        let mut tool = UnifiedMessage::new(Role::Tool, "Sunny");
+        tool.tool_call_id = Some("call_1".to_string());
+        let anthropic_result = UnifiedMessage::with_content(
+            Role::User,
+            vec![UnifiedContent::tool_result("call_1", json!("Rainy"), true)],
+        );
+        let mut request = UnifiedRequest::new(
+            "qwen",
+            vec![
+                UnifiedMessage::user("Weather?"),
+                assistant,
+                tool,
+                anthropic_result,
+            ],
+        );
+
+        transformer.transform_request(&mut request).unwrap();
+
+        let messages = &request.messages;
+        assert!(messages[1].tool_calls.is_empty());
+        assert_eq!(
+            messages[1].text_content(),
+            "<tool_call>\n{\"arguments\":{\"city\":\"Paris\"},\"name\":\"get_weather\"}\n</tool_call>"
+        );
+        assert_eq!(messages[2].role, Role::User);
+        assert!(messages[2].tool_call_id.is_none());
+        assert_eq!(
+            messages[2].text_content(),
+            "<tool_response name=\"get_weather\">\nSunny\n</tool_response>"
+        );
+        assert_eq!(
+            messages[3].text_content(),
+            "<tool_response name=\"get_weather\" error=\"true\">\nRainy\n</tool_response>"
+        );
+    }
+
+    #[test]
+    fn test_transform_response_extracts_tool_calls() {
+        let transformer = ToolEmulationTransformer::new(None);
+        let mut response = UnifiedResponse::text(
+            "msg_1",
+            "qwen",
+            "Let me check.\n<tool_call>\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Paris\"}}\n</tool_call>\n<tool_call>{\"name\": \"get_time\", \"arguments\": \"{\\\"tz\\\": \\\"CET\\\"}\"}</tool_call>",
+            UnifiedUsage::new(10, 20),
+        );
+
+        transformer.transform_response(&mut response).unwrap();
+
+        assert_eq!(response.text_content(), "Let me check.");
+        assert_eq!(response.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(response.tool_calls.len(), 2);
+        assert_eq!(response.tool_calls[0].name, "get_weather");
+        assert_eq!(response.tool_calls[0].arguments, json!({"city": "Paris"}));
+        assert!(response.tool_calls[0].id.starts_with("call_"));
+        assert_eq!(response.tool_calls[1].arguments, json!({"tz": "CET"}));
+    }
+
+    #[test]
+    fn test_transform_response_keeps_malformed_block_as_text() {
+        let transformer = ToolEmulationTransformer::new(None);
+        let text = "<tool_call>not json</tool_call>";
+        let mut response = UnifiedResponse::text("msg_1", "qwen", text, UnifiedUsage::new(1, 1));
+
+        transformer.transform_response(&mut response).unwrap();
+
+        assert_eq!(response.text_content(), text);
+        assert!(response.tool_calls.is_empty());
+        assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+    }
+
+    #[test]
+    fn test_stream_parser_split_markers() {
+        let mut parser = ToolCallStreamParser::new();
+        let mut out = Vec::new();
+        for piece in [
+            "Checking",
+            " now.\n<tool",
+            "_call>\n{\"name\": \"get_weather\",",
+            " \"arguments\": {\"city\": \"Paris\"}}\n</tool_",
+            "call>\n",
+        ] {
+            out.extend(parser.process(vec![text_delta(piece)]));
+        }
+        out.extend(parser.process(vec![UnifiedStreamChunk::message_delta(
+            StopReason::EndTurn,
+            UnifiedUsage::default(),
+        )]));
+
+        let text: String = out
+            .iter()
+            .filter_map(|c| c.delta.as_ref().and_then(|d| d.as_text()))
+            .collect();
+        assert_eq!(text, "Checking now.\n");
+
+        let start = out
+            .iter()
+            .find(|c| c.chunk_type == ChunkType::ContentBlockStart)
+            .unwrap();
+        assert_eq!(start.index, 1);
+        assert!(matches!(
+            &start.content_block,
+            Some(UnifiedContent::ToolUse { name, .. }) if name == "get_weather"
+        ));
+        assert!(out.iter().any(|c| matches!(
+            &c.delta,
+            Some(UnifiedContent::ToolInputDelta { partial_json, .. })
+                if partial_json == r#"{"city":"Paris"}"#
+        )));
+        assert!(out
+            .iter()
+            .any(|c| c.chunk_type == ChunkType::ContentBlockStop && c.index == 0));
+        assert_eq!(out.last().unwrap().stop_reason, Some(StopReason::ToolUse));
+    }
+
+    #[test]
+    fn test_stream_parser_plain_text_passthrough() {
+        let mut parser = ToolCallStreamParser::new();
+        let out = parser.process(vec![text_delta("a < b"), text_delta(" and <tool")]);
+        assert_eq!(out.len(), 2);
+        // The possible marker prefix is flushed as text at the end
+        let flushed = parser.flush();
+        assert_eq!(flushed[0].delta.as_ref().unwrap().as_text(), Some("<tool"));
+        assert!(!parser.has_tool_calls());
+    }
+
+    #[test]
+    fn test_stream_parser_unterminated_call_flushed_as_text() {
+        let mut parser = ToolCallStreamParser::new();
+        assert!(parser
+            .process(vec![text_delta("<tool_call>{\"name\": \"x\"")])
+            .is_empty());
+        let flushed = parser.flush();
+        assert_eq!(
+            flushed[0].delta.as_ref().unwrap().as_text(),
+            Some("<tool_call>{\"name\": \"x\"")
+        );
+    }
+}
@@ -0,0 +1,846 @@
+//! Tool name and schema normalization for the target provider.
+//!
+//! Providers reject tool definitions for different reasons: OpenAI and
+//! Anthropic only accept names of up to 64 characters from `[A-Za-z0-9_-]`,
+//! Gemini additionally requires a leading letter or underscore and rejects
+//! `$ref`/`$defs` in schemas, and some backends refuse `oneOf`.
+//!
+//! [`ToolNormalizer`] rewrites tool definitions before they are sent:
+//!
+//! - Invalid names are escaped and, when too long, shortened with a hash
+//!   suffix. The mapping is deterministic and kept per request so tool calls
+//!   in responses and streams are renamed back to what the client defined.
+//! - Schemas are rewritten according to the provider's [`SchemaDialect`]:
+//!   local `$ref`s inlined, `oneOf` turned into `anyOf`, unsupported keywords
+//!   stripped. Dialect rules can be overridden per provider through the
+//!   `tool_schema` provider param.
+
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+use super::features::FeatureTransformer;
+use super::unified::{
+    Protocol, Role, UnifiedContent, UnifiedRequest, UnifiedResponse, UnifiedStreamChunk,
+    UnifiedToolCall,
+};
+use crate::core::error::Result;
+use crate::core::AppError;
+
+/// Provider param overriding the schema dialect rules of a provider.
+///
+/// ```json
+/// {"tool_schema": {"inline_refs": true, "one_of_to_any_of": true, "strip_keywords": ["format"]}}
+/// ```
+pub const TOOL_SCHEMA_PARAM: &str = "tool_schema";
+
+/// Length of the hash suffix appended to shortened or colliding names.
+const HASH_SUFFIX_LEN: usize = 8;
+
+/// Maximum number of schema nodes produced by inlining `$ref`s.
+///
+/// Definitions referenced several times per level expand exponentially.
+const MAX_INLINED_SCHEMA_NODES: usize = 10_000;
+
+/// Schema keywords whose value is a single subschema.
+const SUBSCHEMA_KEYWORDS: &[&str] = &[
+    "items",
+    "additionalProperties",
+    "additionalItems",
+    "contains",
+    "not",
+    "if",
+    "then",
+    "else",
+    "propertyNames",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+];
+
+/// Schema keywords whose value is an array of subschemas.
+const SUBSCHEMA_LIST_KEYWORDS: &[&str] = &["anyOf", "allOf", "oneOf", "prefixItems"];
+
+/// Schema keywords whose value maps names to subschemas.
+const SUBSCHEMA_MAP_KEYWORDS: &[&str] = &[
+    "properties",
+    "patternProperties",
+    "dependentSchemas",
+    "$defs",
+    "definitions",
+];
+
+// ============================================================================
+// Name Rules
+// ============================================================================
+
+/// Tool name constraints of a provider protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolNameRules {
+    /// Maximum name length
+    pub max_len: usize,
+    /// Whether `.` and `:` are accepted besides `[A-Za-z0-9_-]`
+    pub allow_dot_colon: bool,
+    /// Whether the name must start with a letter or underscore
+    pub leading_letter: bool,
+}
+
+impl ToolNameRules {
+    /// Name rules of the given provider protocol.
+    pub fn for_protocol(protocol: Protocol) -> Self {
+        match protocol {
+            Protocol::Gemini => Self {
+                max_len: 64,
+                allow_dot_colon: true,
+                leading_letter: true,
+            },
+            Protocol::OpenAI
+            | Protocol::ResponseApi
+            | Protocol::Anthropic
+            | Protocol::GcpVertex => Self {
+                max_len: 64,
+                allow_dot_colon: false,
+                leading_letter: false,
+            },
+        }
+    }
+
+    fn allows(&self, c: char) -> bool {
+        c.is_ascii_alphanumeric()
+            || c == '_'
+            || c == '-'
+            || (self.allow_dot_colon && (c == '.' || c == ':'))
+    }
+
+    /// Whether the provider accepts `name` as is.
+    pub fn is_valid(&self, name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= self.max_len
+            && name.chars().all(|c| self.allows(c))
+            && (!self.leading_letter
+                || name
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_'))
+    }
+
+    /// Escape `name` into a name the provider accepts.
+    ///
+    /// Disallowed characters become `_`; names longer than the limit are
+    /// truncated and suffixed with a hash of the original name.
+    pub fn sanitize(&self, name: &str) -> String {
+        let mut escaped: String = name
+            .chars()
+            .map(|c| if self.allows(c) { c } else { '_' })
+            .collect();
+        if escaped.is_empty() {
+            escaped.push_str("tool");
+        }
+        if self.leading_letter
+            && !escaped
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        {
+            escaped.insert(0, '_');
+        }
+        if escaped.len() > self.max_len {
+            escaped = self.with_hash_suffix(&escaped, name);
+        }
+        escaped
+    }
+
+    /// Replace the tail of `base` with a hash of `original`, within the limit.
+    fn with_hash_suffix(&self, base: &str, original: &str) -> String {
+        let digest = hex::encode(Sha256::digest(original.as_bytes()));
+        let keep = self.max_len.saturating_sub(HASH_SUFFIX_LEN + 1);
+        // Escaped names are ASCII, so byte truncation is safe
+        let prefix = &base[..base.len().min(keep)];
+        format!("{}_{}", prefix, &digest[..HASH_SUFFIX_LEN])
+    }
+}
+
+// ============================================================================
+// Schema Dialect
+// ============================================================================
+
+/// JSON Schema rewrite rules of a provider.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDialect {
+    /// Inline local `$ref`s and drop `$defs`/`definitions`
+    pub inline_refs: bool,
+    /// Turn `oneOf` into `anyOf`
+    pub one_of_to_any_of: bool,
+    /// Keywords removed from every (sub)schema
+    pub strip_keywords: Vec<String>,
+}
+
+impl SchemaDialect {
+    /// Default rules of the given provider protocol.
+    pub fn for_protocol(protocol: Protocol) -> Self {
+        match protocol {
+            Protocol::Gemini => Self {
+                inline_refs: true,
+                one_of_to_any_of: true,
+                strip_keywords: Vec::new(),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// Apply overrides from the `tool_schema` provider param.
+    ///
+    /// Only the keys present in `overrides` replace the protocol defaults.
+    pub fn with_overrides(mut self, overrides: &Value) -> Self {
+        if let Some(inline_refs) = overrides.get("inline_refs").and_then(|v| v.as_bool()) {
+            self.inline_refs = inline_refs;
+        }
+        if let Some(one_of) = overrides.get("one_of_to_any_of").and_then(|v| v.as_bool()) {
+            self.one_of_to_any_of = one_of;
+        }
+        if let Some(keywords) = overrides.get("strip_keywords").and_then(|v| v.as_array()) {
+            self.strip_keywords = keywords
+                .iter()
+                .filter_map(|k| k.as_str().map(str::to_string))
+                .collect();
+        }
+        self
+    }
+
+    /// Whether the dialect never changes a schema.
+    pub fn is_empty(&self) -> bool {
+        !self.inline_refs && !self.one_of_to_any_of && self.strip_keywords.is_empty()
+    }
+
+    /// Whether applying the dialect would change `schema`.
+    pub fn rewrites(&self, schema: &Value) -> bool {
+        match schema {
+            Value::Object(obj) => {
+                obj.keys().any(|key| self.rewrites_keyword(key))
+                    || for_each_subschema(obj).any(|sub| self.rewrites(sub))
+            }
+            Value::Array(items) => items.iter().any(|item| self.rewrites(item)),
+            _ => false,
+        }
+    }
+
+    fn rewrites_keyword(&self, key: &str) -> bool {
+        (self.inline_refs && matches!(key, "$ref" | "$defs" | "definitions"))
+            || (self.one_of_to_any_of && key == "oneOf")
+            || self.strip_keywords.iter().any(|k| k == key)
+    }
+
+    /// Rewrite a tool input schema.
+    ///
+    /// Fails when inlining `$ref`s would exceed [`MAX_INLINED_SCHEMA_NODES`].
+    pub fn apply(&self, schema: &Value) -> std::result::Result<Value, String> {
+        let mut schema = if self.inline_refs {
+            let mut budget = MAX_INLINED_SCHEMA_NODES;
+            inline_refs(schema, schema, &mut Vec::new(), &mut budget).ok_or_else(|| {
+                format!(
+                    "input schema expands to more than {} nodes when inlining $ref definitions",
+                    MAX_INLINED_SCHEMA_NODES
+                )
+            })?
+        } else {
+            schema.clone()
+        };
+        if self.inline_refs {
+            if let Some(obj) = schema.as_object_mut() {
+                obj.remove("$defs");
+                obj.remove("definitions");
+            }
+        }
+        self.rewrite_keywords(&mut schema);
+        Ok(schema)
+    }
+
+    fn rewrite_keywords(&self, schema: &mut Value) {
+        let Some(obj) = schema.as_object_mut() else {
+            return;
+        };
+        for keyword in &self.strip_keywords {
+            obj.remove(keyword);
+        }
+        if self.one_of_to_any_of {
+            if let Some(one_of) = obj.remove("oneOf") {
+                // A schema with both keeps its anyOf; oneOf only narrows it further
+                obj.entry("anyOf").or_insert(one_of);
+            }
+        }
+        for (key, value) in obj.iter_mut() {
+            let key = key.as_str();
+            if SUBSCHEMA_KEYWORDS.contains(&key) {
+                self.rewrite_keywords(value);
+            } else if SUBSCHEMA_LIST_KEYWORDS.contains(&key) {
+                if let Some(items) = value.as_array_mut() {
+                    items
+                        .iter_mut()
+                        .for_each(|item| self.rewrite_keywords(item));
+                } else {
+                    self.rewrite_keywords(value);
+                }
+            } else if SUBSCHEMA_MAP_KEYWORDS.contains(&key) {
+                if let Some(map) = value.as_object_mut() {
+                    map.values_mut().for_each(|sub| self.rewrite_keywords(sub));
+                }
+            }
+        }
+    }
+}
+
+/// Iterate over the direct subschemas of a schema object.
+fn for_each_subschema(obj: &Map<String, Value>) -> impl Iterator<Item = &Value> {
+    obj.iter().flat_map(|(key, value)| {
+        let key = key.as_str();
+        let subs: Vec<&Value> = if SUBSCHEMA_KEYWORDS.contains(&key) {
+            vec![value]
+        } else if SUBSCHEMA_LIST_KEYWORDS.contains(&key) {
+            value
+                .as_array()
+                .map(|items| items.iter().collect())
+                .unwrap_or_else(|| vec![value])
+        } else if SUBSCHEMA_MAP_KEYWORDS.contains(&key) {
+            value
+                .as_object()
+                .map(|map| map.values().collect())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        subs
+    })
+}
+
+/// Resolve a local JSON pointer reference (`#/$defs/Name`) against `root`.
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    root.pointer(pointer)
+}
+
+/// Inline local `$ref`s of `schema`, guarding against recursive definitions.
+///
+/// A reference that is already being expanded is replaced by a plain
+/// object schema, since providers cannot express recursion without `$ref`.
+/// Every produced node is taken from `budget`; `None` means it ran out.
+fn inline_refs(
+    schema: &Value,
+    root: &Value,
+    expanding: &mut Vec<String>,
+    budget: &mut usize,
+) -> Option<Value> {
+    *budget = budget.checked_sub(1)?;
+    let inlined = match schema {
+        Value::Object(obj) => {
+            if let Some(reference) = obj.get("$ref").and_then(|r| r.as_str()) {
+                let resolved = match resolve_ref(root, reference) {
+                    Some(target) if !expanding.iter().any(|r| r == reference) => {
+                        expanding.push(reference.to_string());
+                        let inlined = inline_refs(target, root, expanding, budget);
+                        expanding.pop();
+                        inlined?
+                    }
+                    Some(_) => serde_json::json!({"type": "object"}),
+                    // Remote or unknown references cannot be inlined; keep them
+                    None => return Some(schema.clone()),
+                };
+                let mut merged = match resolved {
+                    Value::Object(map) => map,
+                    other => return Some(other),
+                };
+                merged.remove("$defs");
+                merged.remove("definitions");
+                // Sibling keywords (e.g. a description) take precedence
+                for (key, value) in obj {
+                    if key != "$ref" {
+                        merged.insert(key.clone(), inline_refs(value, root, expanding, budget)?);
+                    }
+                }
+                return Some(Value::Object(merged));
+            }
+            let mut result = Map::with_capacity(obj.len());
+            for (key, value) in obj {
+                let k = key.as_str();
+                let value = if SUBSCHEMA_KEYWORDS.contains(&k)
+                    || SUBSCHEMA_LIST_KEYWORDS.contains(&k)
+                {
+                    inline_refs(value, root, expanding, budget)?
+                } else if SUBSCHEMA_MAP_KEYWORDS.contains(&k) {
+                    match value.as_object() {
+                        Some(map) => Value::Object(
+                            map.iter()
+                                .map(|(name, sub)| {
+                                    Some((name.clone(), inline_refs(sub, root, expanding, budget)?))
+                                })
+                                .collect::<Option<_>>()?,
+                        ),
+                        None => value.clone(),
+                    }
+                } else {
+                    value.clone()
+                };
+                result.insert(key.clone(), value);
+            }
+            Value::Object(result)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| inline_refs(item, root, expanding, budget))
+                .collect::<Option<_>>()?,
+        ),
+        other => other.clone(),
+    };
+    Some(inlined)
+}
+
+// ============================================================================
+// Raw Tool Definitions
+// ============================================================================
+
+/// Tool names and input schemas of a raw client request, in any protocol.
+///
+/// Handles OpenAI Chat (`function.name`/`function.parameters`), Response API
+/// (`name`/`parameters`), Anthropic (`name`/`input_schema`) and Gemini
+/// (`functionDeclarations[]`) tool definitions.
+pub fn raw_tool_definitions(raw: &Value) -> Vec<(&str, Option<&Value>)> {
+    let mut definitions = Vec::new();
+    let Some(tools) = raw.get("tools").and_then(|t| t.as_array()) else {
+        return definitions;
+    };
+    for tool in tools {
+        if let Some(function) = tool.get("function") {
+            if let Some(name) = function.get("name").and_then(|n| n.as_str()) {
+                definitions.push((name, function.get("parameters")));
+            }
+        } else if let Some(declarations) = tool
+            .get("functionDeclarations")
+            .or_else(|| tool.get("function_declarations"))
+            .and_then(|d| d.as_array())
+        {
+            for declaration in declarations {
+                if let Some(name) = declaration.get("name").and_then(|n| n.as_str()) {
+                    let schema = declaration
+                        .get("parameters")
+                        .or_else(|| declaration.get("parametersJsonSchema"));
+                    definitions.push((name, schema));
+                }
+            }
+        } else if let Some(name) = tool.get("name").and_then(|n| n.as_str()) {
+            let schema = tool.get("input_schema").or_else(|| tool.get("parameters"));
+            definitions.push((name, schema));
+        }
+    }
+    definitions
+}
+
+// ============================================================================
+// Tool Normalizer
+// ============================================================================
+
+/// Per-request tool name mapping and schema rewriting.
+///
+/// # Example
+///
+/// ```ignore
+/// use llm_proxy_rust::transformer::tool_normalizer::ToolNormalizer;
+///
+/// if let Some(normalizer) = ToolNormalizer::for_request(&payload, Protocol::Gemini, None) {
+///     normalizer.transform_request(&mut request)?;
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ToolNormalizer {
+    /// Client name → provider name, for renamed tools only
+    names: HashMap<String, String>,
+    /// Provider name → client name, for renamed tools only
+    reverse: HashMap<String, String>,
+    /// Schema rewrite rules of the provider
+    dialect: SchemaDialect,
+}
+
+impl ToolNormalizer {
+    /// Build the name mapping for `tool_names` under `rules`.
+    ///
+    /// Valid names are kept; invalid ones are sanitized, and a sanitized name
+    /// clashing with another tool gets a hash suffix of its original name.
+    pub fn new<'a>(
+        tool_names: impl IntoIterator<Item = &'a str>,
+        rules: ToolNameRules,
+        dialect: SchemaDialect,
+    ) -> Self {
+        let mut seen = HashSet::new();
+        let tool_names: Vec<&str> = tool_names
+            .into_iter()
+            .filter(|name| seen.insert(*name))
+            .collect();
+
+        let mut taken: HashSet<String> = tool_names
+            .iter()
+            .filter(|name| rules.is_valid(name))
+            .map(|name| name.to_string())
+            .collect();
+        let mut names = HashMap::new();
+        let mut reverse = HashMap::new();
+        for name in tool_names.into_iter().filter(|name| !rules.is_valid(name)) {
+            let mut provider_name = rules.sanitize(name);
+            if taken.contains(&provider_name) {
+                provider_name = rules.with_hash_suffix(&provider_name, name);
+            }
+            taken.insert(provider_name.clone());
+            reverse.insert(provider_name.clone(), name.to_string());
+            names.insert(name.to_string(), provider_name);
+        }
+
+        Self {
+            names,
+            reverse,
+            dialect,
+        }
+    }
+
+    /// Normalizer for a raw client request sent to `provider_protocol`.
+    ///
+    /// `overrides` is the provider's `tool_schema` param. Returns `None` when
+    /// the request defines no tools or its tools need no rewriting, so
+    /// same-protocol requests can still pass through untouched.
+    pub fn for_request(
+        raw: &Value,
+        provider_protocol: Protocol,
+        overrides: Option<&Value>,
+    ) -> Option<Self> {
+        let definitions = raw_tool_definitions(raw);
+        if definitions.is_empty() {
+            return None;
+        }
+        let mut dialect = SchemaDialect::for_protocol(provider_protocol);
+        if let Some(overrides) = overrides {
+            dialect = dialect.with_overrides(overrides);
+        }
+        let rewrites_schemas = !dialect.is_empty()
+            && definitions
+                .iter()
+                .any(|(_, schema)| schema.is_some_and(|s| dialect.rewrites(s)));
+
+        let normalizer = Self::new(
+            definitions.iter().map(|(name, _)| *name),
+            ToolNameRules::for_protocol(provider_protocol),
+            dialect,
+        );
+        if normalizer.names.is_empty() && !rewrites_schemas {
+            return None;
+        }
+        Some(normalizer)
+    }
+
+    /// Whether any tool is renamed for the provider.
+    pub fn has_renames(&self) -> bool {
+        !self.names.is_empty()
+    }
+
+    /// Name the provider knows the client's tool `name` by.
+    pub fn provider_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.names.get(name).map(String::as_str).unwrap_or(name)
+    }
+
+    /// Name the client defined for the provider's tool `name`.
+    pub fn client_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.reverse.get(name).map(String::as_str).unwrap_or(name)
+    }
+
+    fn to_provider(&self, name: &mut String) {
+        if let Some(renamed) = self.names.get(name.as_str()) {
+            *name = renamed.clone();
+        }
+    }
+
+    fn to_client(&self, name: &mut String) {
+        if let Some(original) = self.reverse.get(name.as_str()) {
+            *name = original.clone();
+        }
+    }
+
+    /// Rename a streamed tool call back to the client's tool name.
+    pub fn restore_chunk(&self, chunk: &mut UnifiedStreamChunk) {
+        for block in [chunk.content_block.as_mut(), chunk.delta.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            if let UnifiedContent::ToolUse { name, .. } = block {
+                self.to_client(name);
+            }
+        }
+    }
+
+    fn restore_calls(&self, content: &mut [UnifiedContent], tool_calls: &mut [UnifiedToolCall]) {
+        for block in content {
+            if let UnifiedContent::ToolUse { name, .. } = block {
+                self.to_client(name);
+            }
+        }
+        for call in tool_calls {
+            self.to_client(&mut call.name);
+        }
+    }
+}
+
+impl FeatureTransformer for ToolNormalizer {
+    fn transform_request(&self, request: &mut UnifiedRequest) -> Result<()> {
+        for tool in &mut request.tools {
+            if !self.dialect.is_empty() {
+                tool.input_schema = self
+                    .dialect
+                    .apply(&tool.input_schema)
+                    .map_err(|e| AppError::BadRequest(format!("Tool '{}': {}", tool.name, e)))?;
+            }
+            self.to_provider(&mut tool.name);
+        }
+
+        if let Some(name) = request
+            .tool_choice
+            .as_mut()
+            .and_then(|choice| choice.get_mut("name"))
+        {
+            if let Some(renamed) = name.as_str().and_then(|n| self.names.get(n)) {
+                *name = Value::String(renamed.clone());
+            }
+        }
+
+        // Earlier calls in the conversation must match the renamed definitions
+        for message in &mut request.messages {
+            for call in &mut message.tool_calls {
+                self.to_provider(&mut call.name);
+            }
+            for block in &mut message.content {
+                if let UnifiedContent::ToolUse { name, .. } = block {
+                    self.to_provider(name);
+                }
+            }
+            if message.role == Role::Tool {
+                if let Some(name) = message.name.as_mut() {
+                    self.to_provider(name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn transform_response(&self, response: &mut UnifiedResponse) -> Result<()> {
+        self.restore_calls(&mut response.content, &mut response.tool_calls);
+        for choice in &mut response.additional_choices {
+            self.restore_calls(&mut choice.content, &mut choice.tool_calls);
+        }
+        Ok(())
+    }
+
+    fn transform_stream_chunk(&self, chunk: &mut UnifiedStreamChunk) -> Result<()> {
+        self.restore_chunk(chunk);
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "tool_normalizer"
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::unified::{UnifiedMessage, UnifiedParameters, UnifiedTool};
+    use serde_json::json;
+
+    fn request_with_tools(tools: Vec<UnifiedTool>) -> UnifiedRequest {
+        UnifiedRequest {
+            model: "m".to_string(),
+            messages: vec![UnifiedMessage::new(Role::User, "hi")],
+            system: None,
+            parameters: UnifiedParameters::default(),
+            tools,
+            tool_choice: None,
+            request_id: String::new(),
+            client_protocol: Protocol::OpenAI,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_sanitize_escapes_and_shortens() {
+        let openai = ToolNameRules::for_protocol(Protocol::OpenAI);
+        assert_eq!(openai.sanitize("files.read"), "files_read");
+        assert_eq!(openai.sanitize("get weather!"), "get_weather_");
+
+        let long = "a".repeat(100);
+        let shortened = openai.sanitize(&long);
+        assert_eq!(shortened.len(), 64);
+        assert!(openai.is_valid(&shortened));
+        // Deterministic across calls
+        assert_eq!(shortened, openai.sanitize(&long));
+
+        let gemini = ToolNameRules::for_protocol(Protocol::Gemini);
+        assert!(gemini.is_valid("files.read"));
+        assert_eq!(gemini.sanitize("1password"), "_1password");
+    }
+
+    #[test]
+    fn test_colliding_names_get_distinct_provider_names() {
+        let normalizer = ToolNormalizer::new(
+            ["a.b", "a_b", "a-b!"],
+            ToolNameRules::for_protocol(Protocol::OpenAI),
+            SchemaDialect::default(),
+        );
+        assert_eq!(normalizer.provider_name("a_b"), "a_b");
+        let renamed = normalizer.provider_name("a.b");
+        assert_ne!(renamed, "a_b");
+        assert!(renamed.starts_with("a_b_"));
+        assert_eq!(normalizer.client_name(renamed), "a.b");
+        assert_eq!(normalizer.provider_name("a-b!"), "a-b_");
+    }
+
+    #[test]
+    fn test_inline_refs_with_cycle_guard() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "address": {"$ref": "#/$defs/Address", "description": "Home"},
+                "node": {"$ref": "#/definitions/Node"}
+            },
+            "$defs": {"Address": {"type": "object", "properties": {"city": {"type": "string"}}}},
+            "definitions": {
+                "Node": {"type": "object", "properties": {"next": {"$ref": "#/definitions/Node"}}}
+            }
+        });
+        let dialect = SchemaDialect::for_protocol(Protocol::Gemini);
+        assert!(dialect.rewrites(&schema));
+
+        let inlined = dialect.apply(&schema).unwrap();
+        assert!(inlined.get("$defs").is_none());
+        assert!(inlined.get("definitions").is_none());
+        assert_eq!(
+            inlined["properties"]["address"],
+            json!({
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "description": "Home"
+            })
+        );
+        assert_eq!(
+            inlined["properties"]["node"]["properties"]["next"],
+            json!({"type": "object"})
+        );
+        assert!(!dialect.rewrites(&inlined));
+    }
+
+    #[test]
+    fn test_inline_refs_expansion_is_bounded() {
+        // Each definition references the next one twice: 2^40 nodes when inlined
+        let mut defs = Map::new();
+        for level in 0..40 {
+            let next = format!("#/$defs/L{}", level + 1);
+            defs.insert(
+                format!("L{}", level),
+                json!({"type": "object", "properties": {"a": {"$ref": next}, "b": {"$ref": next}}}),
+            );
+        }
+        defs.insert("L40".to_string(), json!({"type": "string"}));
+        let schema = json!({"$ref": "#/$defs/L0", "$defs": defs});
+        let dialect = SchemaDialect::for_protocol(Protocol::Gemini);
+        assert!(dialect.apply(&schema).is_err());
+
+        let normalizer = ToolNormalizer::new(
+            ["deep"],
+            ToolNameRules::for_protocol(Protocol::Gemini),
+            dialect,
+        );
+        let mut request =
+            request_with_tools(vec![UnifiedTool::function("deep", None, schema.clone())]);
+        let err = normalizer.transform_request(&mut request).unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+    }
+
+    #[test]
+    fn test_one_of_and_stripped_keywords() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "oneOf": {"type": "string", "format": "uri"},
+                "value": {"oneOf": [{"type": "string"}, {"type": "integer"}]}
+            }
+        });
+        let dialect = SchemaDialect::for_protocol(Protocol::OpenAI)
+            .with_overrides(&json!({"one_of_to_any_of": true, "strip_keywords": ["format"]}));
+
+        let rewritten = dialect.apply(&schema).unwrap();
+        // Property names are not keywords
+        assert_eq!(rewritten["properties"]["oneOf"], json!({"type": "string"}));
+        assert_eq!(
+            rewritten["properties"]["value"],
+            json!({"anyOf": [{"type": "string"}, {"type": "integer"}]})
+        );
+        assert!(SchemaDialect::for_protocol(Protocol::OpenAI).is_empty());
+    }
+
+    #[test]
+    fn test_for_request_skips_valid_tools() {
+        let raw = json!({
+            "tools": [{"type": "function", "function": {"name": "get_weather", "parameters": {"type": "object"}}}]
+        });
+        assert!(ToolNormalizer::for_request(&raw, Protocol::OpenAI, None).is_none());
+        assert!(ToolNormalizer::for_request(&raw, Protocol::Gemini, None).is_none());
+
+        let raw = json!({"tools": [{"name": "fs.read", "input_schema": {"type": "object"}}]});
+        let normalizer = ToolNormalizer::for_request(&raw, Protocol::OpenAI, None).unwrap();
+        assert!(normalizer.has_renames());
+        assert!(ToolNormalizer::for_request(&raw, Protocol::Gemini, None).is_none());
+    }
+
+    #[test]
+    fn test_request_and_response_round_trip() {
+        let normalizer = ToolNormalizer::new(
+            ["fs.read"],
+            ToolNameRules::for_protocol(Protocol::Anthropic),
+            SchemaDialect::default(),
+        );
+        let mut request = request_with_tools(vec![UnifiedTool::function(
+            "fs.read",
+            None,
+            json!({"type": "object"}),
+        )]);
+        request.tool_choice = Some(json!({"type": "tool", "name": "fs.read"}));
+        let mut assistant = UnifiedMessage::new(Role::Assistant, "");
+        assistant.content = vec![UnifiedContent::tool_use("call_1", "fs.read", json!({}))];
+        request.messages.push(assistant);
+
+        normalizer.transform_request(&mut request).unwrap();
+        assert_eq!(request.tools[0].name, "fs_read");
+        assert_eq!(request.tool_choice.unwrap()["name"], "fs_read");
+        assert!(matches!(
+            &request.messages[1].content[0],
+            UnifiedContent::ToolUse { name, .. } if name == "fs_read"
+        ));
+
+        let mut response = UnifiedResponse::new("r", "m", vec![], None, Default::default());
+        response.tool_calls.push(UnifiedToolCall {
+            id: "call_2".to_string(),
+            name: "fs_read".to_string(),
+            arguments: json!({}),
+        });
+        normalizer.transform_response(&mut response).unwrap();
+        assert_eq!(response.tool_calls[0].name, "fs.read");
+
+        let mut chunk = UnifiedStreamChunk::content_block_start(
+            0,
+            UnifiedContent::tool_use("call_3", "fs_read", json!({})),
+        );
+        normalizer.transform_stream_chunk(&mut chunk).unwrap();
+        assert!(matches!(
+            chunk.content_block,
+            Some(UnifiedContent::ToolUse { ref name, .. }) if name == "fs.read"
+        ));
+    }
+}
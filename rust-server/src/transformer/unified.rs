@@ -173,6 +173,15 @@ pub enum UnifiedContent {
         filename: Option<String>,
     },
 
+    /// Document content such as a PDF (base64, URL, plain text or file reference)
+    Document {
+        source_type: String, // "base64" | "url" | "text" | "file"
+        media_type: String,  // MIME type
+        data: String,        // base64 data, URL, plain text or file id
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+
     /// Audio content
     Audio { data: String, format: String },
 
@@ -231,6 +240,36 @@ impl UnifiedContent {
         }
     }
 
+    /// Create a document content block from base64 data.
+    pub fn document_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        UnifiedContent::Document {
+            source_type: "base64".to_string(),
+            media_type: media_type.into(),
+            data: data.into(),
+            title: None,
+        }
+    }
+
+    /// Create a document content block from URL.
+    pub fn document_url(url: impl Into<String>) -> Self {
+        UnifiedContent::Document {
+            source_type: "url".to_string(),
+            media_type: String::new(),
+            data: url.into(),
+            title: None,
+        }
+    }
+
+    /// Create a plain-text document content block.
+    pub fn document_text(text: impl Into<String>) -> Self {
+        UnifiedContent::Document {
+            source_type: "text".to_string(),
+            media_type: "text/plain".to_string(),
+            data: text.into(),
+            title: None,
+        }
+    }
+
     /// Create a thinking content block.
     pub fn thinking(text: impl Into<String>, signature: Option<String>) -> Self {
         UnifiedContent::Thinking {
@@ -256,6 +295,7 @@ impl UnifiedContent {
             UnifiedContent::ToolResult { .. } => "tool_result",
             UnifiedContent::Thinking { .. } => "thinking",
             UnifiedContent::File { .. } => "file",
+            UnifiedContent::Document { .. } => "document",
             UnifiedContent::Audio { .. } => "audio",
             UnifiedContent::Refusal { .. } => "refusal",
             UnifiedContent::ToolInputDelta { .. } => "tool_input_delta",
@@ -416,11 +456,156 @@ pub struct UnifiedParameters {
     pub stop_sequences: Option<Vec<String>>,
     #[serde(default)]
     pub stream: bool,
+    /// Requested output format (plain text, JSON object or JSON schema)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Number of choices to generate (OpenAI `n`, Gemini `candidateCount`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// Reasoning/extended thinking controls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<ReasoningConfig>,
     /// Extended parameters (protocol-specific, passed through)
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub extra: HashMap<String, Value>,
 }
 
+/// Unified structured output format.
+///
+/// Serializes to the Response API `text.format` shape; the Chat Completions
+/// `response_format` nests the schema fields under `json_schema` instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        schema: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+}
+
+impl ResponseFormat {
+    /// Whether the model is expected to return JSON.
+    pub fn is_json(&self) -> bool {
+        !matches!(self, ResponseFormat::Text)
+    }
+
+    /// JSON schema for `json_schema` formats.
+    pub fn schema(&self) -> Option<&Value> {
+        match self {
+            ResponseFormat::JsonSchema { schema, .. } => Some(schema),
+            _ => None,
+        }
+    }
+}
+
+/// Unified reasoning (extended thinking) configuration.
+///
+/// Protocols express reasoning either as an effort level or as a token budget;
+/// both are kept so a request can be re-emitted in whichever form the target
+/// protocol understands:
+///
+/// | Protocol  | Effort                         | Budget                          | Include thoughts                 |
+/// |-----------|--------------------------------|---------------------------------|----------------------------------|
+/// | OpenAI    | `reasoning_effort`             | -                               | -                                |
+/// | Responses | `reasoning.effort`             | -                               | `reasoning.summary`              |
+/// | Anthropic | -                              | `thinking.budget_tokens`        | implied by `enabled`             |
+/// | Gemini    | `thinkingConfig.thinkingLevel` | `thinkingConfig.thinkingBudget` | `thinkingConfig.includeThoughts` |
+///
+/// Missing values are derived with [`ReasoningEffort::budget_tokens`] and
+/// [`ReasoningEffort::from_budget`]. Effort `none` and a zero budget disable reasoning.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReasoningConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<ReasoningEffort>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_tokens: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_thoughts: Option<bool>,
+}
+
+impl ReasoningConfig {
+    /// Whether the config turns reasoning off.
+    pub fn is_disabled(&self) -> bool {
+        self.effort == Some(ReasoningEffort::None) || self.budget_tokens == Some(0)
+    }
+
+    /// Effort level, derived from the budget when only a budget was given.
+    pub fn resolved_effort(&self) -> Option<ReasoningEffort> {
+        self.effort
+            .or_else(|| self.budget_tokens.map(ReasoningEffort::from_budget))
+    }
+
+    /// Token budget, derived from the effort when only an effort was given.
+    pub fn resolved_budget(&self) -> Option<i32> {
+        self.budget_tokens
+            .or_else(|| self.effort.map(|e| e.budget_tokens()))
+    }
+}
+
+/// Reasoning effort level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    None,
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    /// Parse an effort string (case-insensitive); `disable` is an alias of `none`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "none" | "disable" => Some(ReasoningEffort::None),
+            "minimal" => Some(ReasoningEffort::Minimal),
+            "low" => Some(ReasoningEffort::Low),
+            "medium" => Some(ReasoningEffort::Medium),
+            "high" => Some(ReasoningEffort::High),
+            _ => None,
+        }
+    }
+
+    /// Wire name of the effort level.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReasoningEffort::None => "none",
+            ReasoningEffort::Minimal => "minimal",
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
+    }
+
+    /// Thinking token budget used for this effort on budget-based protocols.
+    pub fn budget_tokens(&self) -> i32 {
+        match self {
+            ReasoningEffort::None => 0,
+            ReasoningEffort::Minimal => 1024,
+            ReasoningEffort::Low => 2048,
+            ReasoningEffort::Medium => 8192,
+            ReasoningEffort::High => 24576,
+        }
+    }
+
+    /// Effort level closest to a thinking token budget.
+    pub fn from_budget(budget_tokens: i32) -> Self {
+        match budget_tokens {
+            i32::MIN..=0 => ReasoningEffort::None,
+            1..=1024 => ReasoningEffort::Minimal,
+            1025..=4096 => ReasoningEffort::Low,
+            4097..=16384 => ReasoningEffort::Medium,
+            _ => ReasoningEffort::High,
+        }
+    }
+}
+
 // ============================================================================
 // Request Types
 // ============================================================================
@@ -541,6 +726,9 @@ pub struct UnifiedUsage {
     pub cache_read_tokens: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_write_tokens: Option<i32>,
+    /// Reasoning tokens, already included in `output_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<i32>,
 }
 
 impl UnifiedUsage {
@@ -551,6 +739,7 @@ impl UnifiedUsage {
             output_tokens,
             cache_read_tokens: None,
             cache_write_tokens: None,
+            reasoning_tokens: None,
         }
     }
 
@@ -573,6 +762,19 @@ pub struct UnifiedResponse {
     /// Tool calls (if any)
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tool_calls: Vec<UnifiedToolCall>,
+    /// Choices after the first one when more than one was generated (`n > 1`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_choices: Vec<UnifiedChoice>,
+}
+
+/// An additional generated choice of a multi-choice response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnifiedChoice {
+    pub content: Vec<UnifiedContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<StopReason>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<UnifiedToolCall>,
 }
 
 impl UnifiedResponse {
@@ -591,6 +793,7 @@ impl UnifiedResponse {
             stop_reason,
             usage,
             tool_calls: vec![],
+            additional_choices: vec![],
         }
     }
 
@@ -643,6 +846,9 @@ pub struct UnifiedStreamChunk {
     pub chunk_type: ChunkType,
     #[serde(default)]
     pub index: usize,
+    /// Choice this chunk belongs to when several choices are streamed (`n > 1`)
+    #[serde(default)]
+    pub choice_index: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub delta: Option<UnifiedContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -663,6 +869,7 @@ impl UnifiedStreamChunk {
         UnifiedStreamChunk {
             chunk_type: ChunkType::MessageStart,
             index: 0,
+            choice_index: 0,
             delta: None,
             usage: Some(message.usage.clone()),
             stop_reason: None,
@@ -676,6 +883,7 @@ impl UnifiedStreamChunk {
         UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockStart,
             index,
+            choice_index: 0,
             delta: None,
             usage: None,
             stop_reason: None,
@@ -689,6 +897,7 @@ impl UnifiedStreamChunk {
         UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockDelta,
             index,
+            choice_index: 0,
             delta: Some(delta),
             usage: None,
             stop_reason: None,
@@ -702,6 +911,7 @@ impl UnifiedStreamChunk {
         UnifiedStreamChunk {
             chunk_type: ChunkType::ContentBlockStop,
             index,
+            choice_index: 0,
             delta: None,
             usage: None,
             stop_reason: None,
@@ -715,6 +925,7 @@ impl UnifiedStreamChunk {
         UnifiedStreamChunk {
             chunk_type: ChunkType::MessageDelta,
             index: 0,
+            choice_index: 0,
             delta: None,
             usage: Some(usage),
             stop_reason: Some(stop_reason),
@@ -728,6 +939,7 @@ impl UnifiedStreamChunk {
         UnifiedStreamChunk {
             chunk_type: ChunkType::MessageStop,
             index: 0,
+            choice_index: 0,
             delta: None,
             usage: None,
             stop_reason: None,
@@ -741,6 +953,7 @@ impl UnifiedStreamChunk {
         UnifiedStreamChunk {
             chunk_type: ChunkType::Ping,
             index: 0,
+            choice_index: 0,
             delta: None,
             usage: None,
             stop_reason: None,
@@ -758,6 +971,36 @@ impl UnifiedStreamChunk {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_reasoning_effort_budget_mapping() {
+        for effort in [
+            ReasoningEffort::None,
+            ReasoningEffort::Minimal,
+            ReasoningEffort::Low,
+            ReasoningEffort::Medium,
+            ReasoningEffort::High,
+        ] {
+            assert_eq!(ReasoningEffort::from_budget(effort.budget_tokens()), effort);
+            assert_eq!(ReasoningEffort::parse(effort.as_str()), Some(effort));
+        }
+        assert_eq!(
+            ReasoningEffort::from_budget(10_000),
+            ReasoningEffort::Medium
+        );
+        assert_eq!(
+            ReasoningEffort::parse("DISABLE"),
+            Some(ReasoningEffort::None)
+        );
+
+        let budget_only = ReasoningConfig {
+            budget_tokens: Some(3000),
+            ..Default::default()
+        };
+        assert_eq!(budget_only.resolved_effort(), Some(ReasoningEffort::Low));
+        assert_eq!(budget_only.resolved_budget(), Some(3000));
+        assert!(!budget_only.is_disabled());
+    }
+
     #[test]
     fn test_protocol_display() {
         assert_eq!(Protocol::OpenAI.to_string(), "openai");
@@ -865,6 +1108,27 @@ mod tests {
         assert_eq!(content.content_type(), "tool_use");
     }
 
+    #[test]
+    fn test_response_format_serialization() {
+        let format = ResponseFormat::JsonSchema {
+            name: "person".to_string(),
+            description: None,
+            schema: serde_json::json!({"type": "object"}),
+            strict: Some(true),
+        };
+        let json = serde_json::to_value(&format).unwrap();
+        assert_eq!(json["type"], "json_schema");
+        assert_eq!(json["name"], "person");
+        assert_eq!(json["strict"], true);
+        assert!(format.is_json());
+        assert!(!ResponseFormat::Text.is_json());
+
+        let parsed: ResponseFormat =
+            serde_json::from_value(serde_json::json!({"type": "json_object"})).unwrap();
+        assert_eq!(parsed, ResponseFormat::JsonObject);
+        assert!(parsed.schema().is_none());
+    }
+
     #[test]
     fn test_provider_type_to_protocol_openai() {
         assert_eq!(provider_type_to_protocol("openai"), Protocol::OpenAI);
@@ -118,10 +118,13 @@ fn create_test_config_no_auth() -> AppConfig {
         verify_ssl: false,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials: vec![],
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     }
 }
 
@@ -139,6 +142,12 @@ fn create_test_config_with_auth() -> AppConfig {
         rate_limit: None,
         enabled: true,
         allowed_models: vec![],
+        context_trimming: None,
+        expires_at: None,
+        previous_key: None,
+        allowed_cidrs: vec![],
+        pii_redaction: None,
+        guardrails: vec![],
     }];
     config
 }
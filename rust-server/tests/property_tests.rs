@@ -44,10 +44,13 @@ fn app_config_strategy() -> impl Strategy<Value = AppConfig> {
         verify_ssl: true,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials: vec![],
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     })
 }
 
@@ -120,10 +123,13 @@ proptest! {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 100,
             max_tokens_limit: 4096,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -210,10 +216,13 @@ proptest! {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 100,
             max_tokens_limit: 4096,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -272,10 +281,13 @@ proptest! {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 100,
             max_tokens_limit: 4096,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -376,10 +388,13 @@ mod quickcheck_tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 100,
             max_tokens_limit: 4096,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -413,10 +428,13 @@ mod quickcheck_tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 100,
             max_tokens_limit: 4096,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -501,10 +519,13 @@ mod complex_multi_provider_tests {
             verify_ssl: true,
             request_timeout_secs: 300,
             ttft_timeout_secs: None,
+            stream_idle_timeout_secs: None,
+            sse_keepalive_secs: None,
             credentials: vec![],
             provider_suffix: None,
             min_tokens_limit: 100,
             max_tokens_limit: 4096,
+            guardrail_rules: vec![],
         };
 
         let service = ProviderService::new(config);
@@ -0,0 +1,226 @@
+//! Integration tests for the OpenAI-compatible audio endpoints.
+//!
+//! These tests verify:
+//! - Multipart transcriptions forwarded as multipart with model mapping
+//! - Speech responses streamed back as binary audio
+//! - Non-OpenAI providers rejected before any upstream call
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+    Router,
+};
+use llm_proxy_rust::{
+    api::{audio_speech, audio_transcriptions, audio_translations, AppState, ProxyState},
+    core::{init_metrics, AppConfig, MetricsMiddleware},
+    services::ProviderService,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{body_partial_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+// ============================================================================
+// Test Helpers
+// ============================================================================
+
+async fn create_audio_test_app(
+    mock_server: &MockServer,
+    provider_type: &str,
+    mapped_model: &str,
+) -> Router {
+    use llm_proxy_rust::core::config::{ModelMappingValue, ProviderConfig, ServerConfig};
+    use llm_proxy_rust::core::RateLimiter;
+
+    init_metrics();
+
+    let mut model_mapping: HashMap<String, ModelMappingValue> = HashMap::new();
+    model_mapping.insert("audio-model".to_string(), mapped_model.into());
+
+    let config = AppConfig {
+        providers: vec![ProviderConfig {
+            name: "MockProvider".to_string(),
+            api_base: mock_server.uri(),
+            api_key: "test_key".to_string(),
+            weight: 1,
+            model_mapping,
+            provider_type: provider_type.to_string(),
+            provider_params: HashMap::new(),
+        }],
+        server: ServerConfig {
+            host: "0.0.0.0".to_string(),
+            port: 18000,
+        },
+        verify_ssl: false,
+        request_timeout_secs: 30,
+        ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
+        credentials: vec![],
+        provider_suffix: None,
+        min_tokens_limit: 100,
+        max_tokens_limit: 4096,
+        guardrail_rules: vec![],
+    };
+
+    let provider_service = ProviderService::new(config.clone());
+    let app_state = Arc::new(AppState::new(
+        config,
+        provider_service,
+        Arc::new(RateLimiter::new()),
+        reqwest::Client::new(),
+        None,
+    ));
+
+    Router::new()
+        .route("/v1/audio/transcriptions", post(audio_transcriptions))
+        .route("/v1/audio/translations", post(audio_translations))
+        .route("/v1/audio/speech", post(audio_speech))
+        .layer(axum::middleware::from_fn(MetricsMiddleware::track_metrics))
+        .with_state(Arc::new(ProxyState::new(app_state)))
+}
+
+fn multipart_request(uri: &str) -> Request<Body> {
+    let boundary = "XBOUNDARYX";
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\naudio-model\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"response_format\"\r\n\r\nverbose_json\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"speech.mp3\"\r\n\
+         Content-Type: audio/mpeg\r\n\r\nAUDIODATA\r\n--{b}--\r\n",
+        b = boundary
+    );
+    Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(Body::from(body))
+        .unwrap()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_transcription_multipart_passthrough() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/audio/transcriptions"))
+        .and(header("authorization", "Bearer test_key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "text": "hello world",
+            "duration": 1.5
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let app = create_audio_test_app(&mock_server, "openai", "whisper-1").await;
+    let response = app
+        .oneshot(multipart_request("/v1/audio/transcriptions"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["text"], "hello world");
+
+    let received = mock_server.received_requests().await.unwrap();
+    assert_eq!(received.len(), 1);
+    let upstream_body = String::from_utf8_lossy(&received[0].body);
+    assert!(upstream_body.contains("whisper-1"));
+    assert!(!upstream_body.contains("audio-model"));
+    assert!(upstream_body.contains("AUDIODATA"));
+}
+
+#[tokio::test]
+async fn test_transcription_requires_file() {
+    let mock_server = MockServer::start().await;
+
+    let app = create_audio_test_app(&mock_server, "openai", "whisper-1").await;
+    let boundary = "XBOUNDARYX";
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\naudio-model\r\n--{b}--\r\n",
+        b = boundary
+    );
+    let request = Request::builder()
+        .uri("/v1/audio/translations")
+        .method("POST")
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_speech_streams_binary_audio() {
+    let mock_server = MockServer::start().await;
+
+    let audio: Vec<u8> = vec![0xFF, 0xFB, 0x90, 0x64, 1, 2, 3, 4];
+    Mock::given(method("POST"))
+        .and(path("/audio/speech"))
+        .and(body_partial_json(json!({"model": "tts-1", "input": "hi"})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "audio/mpeg")
+                .set_body_bytes(audio.clone()),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let app = create_audio_test_app(&mock_server, "openai", "tts-1").await;
+    let request = Request::builder()
+        .uri("/v1/audio/speech")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "audio-model", "input": "hi", "voice": "alloy"}).to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "audio/mpeg"
+    );
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.to_vec(), audio);
+}
+
+#[tokio::test]
+async fn test_speech_rejects_non_openai_provider() {
+    let mock_server = MockServer::start().await;
+
+    let app = create_audio_test_app(&mock_server, "anthropic", "claude-sonnet-4").await;
+    let request = Request::builder()
+        .uri("/v1/audio/speech")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "audio-model", "input": "hi", "voice": "alloy"}).to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+}
@@ -65,10 +65,13 @@ async fn create_vertex_test_app(mock_server: &MockServer) -> Router {
         verify_ssl: false,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials: vec![],
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     };
 
     let provider_service = ProviderService::new(config.clone());
@@ -0,0 +1,278 @@
+//! Integration tests for the OpenAI-compatible image endpoints.
+//!
+//! These tests verify:
+//! - JSON generations passthrough to OpenAI-type providers with model mapping
+//! - Multipart edits forwarded as multipart
+//! - OpenAI images → Gemini generateContent translation for image models
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+    Router,
+};
+use llm_proxy_rust::{
+    api::{image_edits, image_generations, AppState, ProxyState},
+    core::{init_metrics, AppConfig, MetricsMiddleware},
+    services::ProviderService,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{body_partial_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+// ============================================================================
+// Test Helpers
+// ============================================================================
+
+async fn create_images_test_app(
+    mock_server: &MockServer,
+    provider_type: &str,
+    mapped_model: &str,
+) -> Router {
+    use llm_proxy_rust::core::config::{ModelMappingValue, ProviderConfig, ServerConfig};
+    use llm_proxy_rust::core::RateLimiter;
+
+    init_metrics();
+
+    let mut model_mapping: HashMap<String, ModelMappingValue> = HashMap::new();
+    model_mapping.insert("image-model".to_string(), mapped_model.into());
+
+    let mut provider_params = HashMap::new();
+    provider_params.insert("gcp_project".to_string(), json!("test-project"));
+    provider_params.insert("gcp_location".to_string(), json!("us-central1"));
+    provider_params.insert("gcp_publisher".to_string(), json!("google"));
+
+    let config = AppConfig {
+        providers: vec![ProviderConfig {
+            name: "MockProvider".to_string(),
+            api_base: mock_server.uri(),
+            api_key: "test_key".to_string(),
+            weight: 1,
+            model_mapping,
+            provider_type: provider_type.to_string(),
+            provider_params,
+        }],
+        server: ServerConfig {
+            host: "0.0.0.0".to_string(),
+            port: 18000,
+        },
+        verify_ssl: false,
+        request_timeout_secs: 30,
+        ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
+        credentials: vec![],
+        provider_suffix: None,
+        min_tokens_limit: 100,
+        max_tokens_limit: 4096,
+        guardrail_rules: vec![],
+    };
+
+    let provider_service = ProviderService::new(config.clone());
+    let app_state = Arc::new(AppState::new(
+        config,
+        provider_service,
+        Arc::new(RateLimiter::new()),
+        reqwest::Client::new(),
+        None,
+    ));
+
+    Router::new()
+        .route("/v1/images/generations", post(image_generations))
+        .route("/v1/images/edits", post(image_edits))
+        .layer(axum::middleware::from_fn(MetricsMiddleware::track_metrics))
+        .with_state(Arc::new(ProxyState::new(app_state)))
+}
+
+async fn body_json(response: axum::response::Response) -> serde_json::Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_image_generation_openai_passthrough() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/images/generations"))
+        .and(header("authorization", "Bearer test_key"))
+        .and(body_partial_json(
+            json!({"model": "gpt-image-1", "prompt": "a cat"}),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "created": 1,
+            "data": [{"b64_json": "QUJD"}],
+            "usage": {"input_tokens": 3, "output_tokens": 100, "total_tokens": 103}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let app = create_images_test_app(&mock_server, "openai", "gpt-image-1").await;
+    let request = Request::builder()
+        .uri("/v1/images/generations")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "image-model", "prompt": "a cat"}).to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_json(response).await;
+    assert_eq!(json["data"][0]["b64_json"], "QUJD");
+}
+
+#[tokio::test]
+async fn test_image_edit_openai_multipart_passthrough() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/images/edits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "created": 1,
+            "data": [{"b64_json": "RURJVA=="}]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let app = create_images_test_app(&mock_server, "openai", "gpt-image-1").await;
+    let boundary = "XBOUNDARYX";
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nimage-model\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"prompt\"\r\n\r\nadd a hat\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"cat.png\"\r\n\
+         Content-Type: image/png\r\n\r\nPNGDATA\r\n--{b}--\r\n",
+        b = boundary
+    );
+    let request = Request::builder()
+        .uri("/v1/images/edits")
+        .method("POST")
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let received = mock_server.received_requests().await.unwrap();
+    assert_eq!(received.len(), 1);
+    let content_type = received[0].headers.get("content-type").unwrap();
+    assert!(content_type
+        .to_str()
+        .unwrap()
+        .starts_with("multipart/form-data"));
+    let upstream_body = String::from_utf8_lossy(&received[0].body);
+    assert!(upstream_body.contains("gpt-image-1"));
+    assert!(upstream_body.contains("PNGDATA"));
+}
+
+#[tokio::test]
+async fn test_image_generation_gemini_translation() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1/projects/test-project/locations/us-central1/publishers/google/models/gemini-2.5-flash-image:generateContent",
+        ))
+        .and(body_partial_json(json!({
+            "contents": [{"role": "user", "parts": [{"text": "a cat"}]}],
+            "generationConfig": {"responseModalities": ["TEXT", "IMAGE"]}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"inlineData": {"mimeType": "image/png", "data": "QUJD"}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 4, "candidatesTokenCount": 1290}
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let app = create_images_test_app(&mock_server, "gemini", "gemini-2.5-flash-image").await;
+    let request = Request::builder()
+        .uri("/v1/images/generations")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "image-model", "prompt": "a cat", "response_format": "url"})
+                .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_json(response).await;
+    assert_eq!(json["data"][0]["url"], "data:image/png;base64,QUJD");
+    assert_eq!(json["usage"]["input_tokens"], 4);
+}
+
+#[tokio::test]
+async fn test_image_generation_vertex_uses_configured_action() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1/projects/test-project/locations/us-central1/publishers/google/models/gemini-2.5-flash-image:rawPredict",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"inlineData": {"mimeType": "image/png", "data": "QUJD"}}
+                ]},
+                "finishReason": "STOP"
+            }]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let app = create_images_test_app(&mock_server, "gcp-vertex", "gemini-2.5-flash-image").await;
+    let request = Request::builder()
+        .uri("/v1/images/generations")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "image-model", "prompt": "a cat"}).to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = body_json(response).await;
+    assert_eq!(json["data"][0]["b64_json"], "QUJD");
+}
+
+#[tokio::test]
+async fn test_image_generation_rejects_non_image_gemini_model() {
+    let mock_server = MockServer::start().await;
+
+    let app = create_images_test_app(&mock_server, "gemini", "gemini-2.5-pro").await;
+    let request = Request::builder()
+        .uri("/v1/images/generations")
+        .method("POST")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"model": "image-model", "prompt": "a cat"}).to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(mock_server.received_requests().await.unwrap().is_empty());
+}
@@ -6,6 +6,16 @@
 //! - Response API → OpenAI cross-protocol conversion
 //! - Streaming responses with protocol conversion
 //! - Error handling across protocols
+//! - Structured output validation with a repair retry
+//! - `n > 1` emulated with parallel calls for single-choice providers
+//! - Reasoning effort translated to Anthropic extended thinking
+//! - Context-window guard rejecting or trimming oversized prompts
+//! - Upstream token counting with caching and local fallback
+//! - Prompt-based tool calling for models without function calling
+//! - Tool name and schema normalization with names restored in responses
+//! - Repair of malformed tool call arguments
+//! - Streaming and blocking upstream modes converted to the client's mode
+//! - Image URLs fetched and inlined for providers that need base64 data
 
 use axum::{
     body::Body,
@@ -14,7 +24,10 @@ use axum::{
     Router,
 };
 use llm_proxy_rust::{
-    api::{chat_completions_v2, messages_v2, responses_v2, AppState, ProxyState},
+    api::{
+        chat_completions_v2, chat_count_tokens_v2, count_tokens_v2, messages_v2, responses_v2,
+        AppState, ProxyState,
+    },
     core::{init_metrics, AppConfig, MetricsMiddleware, ERROR_TYPE_AUTHENTICATION},
     services::ProviderService,
 };
@@ -22,7 +35,7 @@ use serde_json::json;
 use std::sync::Arc;
 use tower::ServiceExt;
 use wiremock::{
-    matchers::{header, method, path},
+    matchers::{body_string_contains, header, method, path},
     Mock, MockServer, ResponseTemplate,
 };
 
@@ -37,7 +50,25 @@ async fn create_v2_test_app(mock_server: &MockServer) -> Router {
 
 /// Create a test app with v2 proxy routes and custom timeout
 async fn create_v2_test_app_with_timeout(mock_server: &MockServer, timeout_secs: u64) -> Router {
-    use llm_proxy_rust::core::config::{ModelMappingValue, ProviderConfig, ServerConfig};
+    build_v2_test_app(
+        mock_server,
+        timeout_secs,
+        "openai",
+        std::collections::HashMap::new(),
+    )
+    .await
+}
+
+/// Create a test app with v2 proxy routes for a specific provider type and params
+async fn build_v2_test_app(
+    mock_server: &MockServer,
+    timeout_secs: u64,
+    provider_type: &str,
+    provider_params: std::collections::HashMap<String, serde_json::Value>,
+) -> Router {
+    use llm_proxy_rust::core::config::{
+        ContextTrimming, ModelMappingEntry, ModelMappingValue, ProviderConfig, ServerConfig,
+    };
     use llm_proxy_rust::core::RateLimiter;
     use std::collections::HashMap;
 
@@ -46,6 +77,31 @@ async fn create_v2_test_app_with_timeout(mock_server: &MockServer, timeout_secs:
     let mut model_mapping: HashMap<String, ModelMappingValue> = HashMap::new();
     model_mapping.insert("gpt-4".to_string(), "test-gpt-4".into());
     model_mapping.insert("claude-3-opus".to_string(), "test-claude-3".into());
+    model_mapping.insert(
+        "claude-small".to_string(),
+        ModelMappingValue::Extended(ModelMappingEntry {
+            mapped_model: "test-claude-small".to_string(),
+            max_input_tokens: Some(100),
+            ..Default::default()
+        }),
+    );
+    model_mapping.insert(
+        "claude-small-trim".to_string(),
+        ModelMappingValue::Extended(ModelMappingEntry {
+            mapped_model: "test-claude-small".to_string(),
+            max_input_tokens: Some(100),
+            context_trimming: Some(ContextTrimming::DropOldest),
+            ..Default::default()
+        }),
+    );
+    model_mapping.insert(
+        "local-llm".to_string(),
+        ModelMappingValue::Extended(ModelMappingEntry {
+            mapped_model: "test-local-llm".to_string(),
+            supports_function_calling: Some(false),
+            ..Default::default()
+        }),
+    );
 
     let config = AppConfig {
         providers: vec![ProviderConfig {
@@ -54,8 +110,8 @@ async fn create_v2_test_app_with_timeout(mock_server: &MockServer, timeout_secs:
             api_key: "test_key".to_string(),
             weight: 1,
             model_mapping,
-            provider_type: "openai".to_string(),
-            provider_params: HashMap::new(),
+            provider_type: provider_type.to_string(),
+            provider_params,
         }],
         server: ServerConfig {
             host: "0.0.0.0".to_string(),
@@ -64,10 +120,13 @@ async fn create_v2_test_app_with_timeout(mock_server: &MockServer, timeout_secs:
         verify_ssl: false,
         request_timeout_secs: timeout_secs,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials: vec![],
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     };
 
     let provider_service = ProviderService::new(config.clone());
@@ -95,6 +154,11 @@ async fn create_v2_test_app_with_timeout(mock_server: &MockServer, timeout_secs:
         .route("/v2/chat/completions", post(chat_completions_v2))
         .route("/v2/messages", post(messages_v2))
         .route("/v2/responses", post(responses_v2))
+        .route("/v2/messages/count_tokens", post(count_tokens_v2))
+        .route(
+            "/v2/chat/completions/count_tokens",
+            post(chat_count_tokens_v2),
+        )
         .layer(axum::middleware::from_fn(MetricsMiddleware::track_metrics))
         .with_state(proxy_state)
 }
@@ -204,6 +268,986 @@ async fn test_v2_openai_with_system_message() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+// ============================================================================
+// Structured Output Tests
+// ============================================================================
+
+/// Anthropic response answering through the structured output tool
+fn anthropic_tool_response(input: serde_json::Value) -> serde_json::Value {
+    json!({
+        "id": "msg_123",
+        "type": "message",
+        "role": "assistant",
+        "model": "test-claude-3",
+        "content": [{
+            "type": "tool_use",
+            "id": "toolu_1",
+            "name": "__llm_proxy_json_response",
+            "input": input
+        }],
+        "stop_reason": "tool_use",
+        "usage": {"input_tokens": 10, "output_tokens": 5}
+    })
+}
+
+#[tokio::test]
+async fn test_v2_structured_output_repaired_on_anthropic() {
+    let mock_server = MockServer::start().await;
+
+    // Repair request carries the validation error back to the model
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_string_contains(
+            "did not match the required JSON schema",
+        ))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(anthropic_tool_response(json!({"name": "Ada", "age": 36}))),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // First answer is missing a required field
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_string_contains("\"__llm_proxy_json_response\""))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(anthropic_tool_response(json!({"name": "Ada"}))),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let mut params = std::collections::HashMap::new();
+    params.insert("structured_output_validation".to_string(), json!(true));
+    let app = build_v2_test_app(&mock_server, 300, "anthropic", params).await;
+
+    let request = Request::builder()
+        .uri("/v2/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-opus",
+                "messages": [{"role": "user", "content": "Who wrote the first program?"}],
+                "response_format": {
+                    "type": "json_schema",
+                    "json_schema": {
+                        "name": "person",
+                        "schema": {
+                            "type": "object",
+                            "properties": {
+                                "name": {"type": "string"},
+                                "age": {"type": "integer"}
+                            },
+                            "required": ["name", "age"]
+                        }
+                    }
+                }
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let message = &json["choices"][0]["message"];
+    assert!(message.get("tool_calls").is_none() || message["tool_calls"].is_null());
+    assert_eq!(json["choices"][0]["finish_reason"], "stop");
+    let content: serde_json::Value =
+        serde_json::from_str(message["content"].as_str().unwrap()).unwrap();
+    assert_eq!(content, json!({"name": "Ada", "age": 36}));
+}
+
+// ============================================================================
+// Multi-Choice Emulation Tests
+// ============================================================================
+
+fn anthropic_text_response(text: &str) -> serde_json::Value {
+    json!({
+        "id": "msg_123",
+        "type": "message",
+        "role": "assistant",
+        "model": "test-claude-3",
+        "content": [{"type": "text", "text": text}],
+        "stop_reason": "end_turn",
+        "usage": {"input_tokens": 10, "output_tokens": 4}
+    })
+}
+
+#[tokio::test]
+async fn test_v2_n_choices_emulated_on_anthropic() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(anthropic_text_response("Hi!")))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "anthropic",
+        std::collections::HashMap::new(),
+    )
+    .await;
+
+    let request = Request::builder()
+        .uri("/v2/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-opus",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "n": 3
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    let choices = json["choices"].as_array().unwrap();
+    assert_eq!(choices.len(), 3);
+    for (i, choice) in choices.iter().enumerate() {
+        assert_eq!(choice["index"], i);
+        assert_eq!(choice["message"]["content"], "Hi!");
+    }
+    assert_eq!(json["usage"]["prompt_tokens"], 30);
+    assert_eq!(json["usage"]["completion_tokens"], 12);
+}
+
+#[tokio::test]
+async fn test_v2_reasoning_effort_maps_to_anthropic_thinking() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_string_contains("\"budget_tokens\":8192"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(anthropic_text_response("Hi!")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "anthropic",
+        std::collections::HashMap::new(),
+    )
+    .await;
+
+    let request = Request::builder()
+        .uri("/v2/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-opus",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "reasoning_effort": "medium"
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_v2_context_length_exceeded_rejected_before_upstream() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(anthropic_text_response("Hi!")))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "anthropic",
+        std::collections::HashMap::new(),
+    )
+    .await;
+
+    let request = Request::builder()
+        .uri("/v2/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "claude-small",
+                "messages": [{"role": "user", "content": "hello ".repeat(300)}]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], "context_length_exceeded");
+}
+
+#[tokio::test]
+async fn test_v2_context_trimming_drops_oldest_turns() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(body_string_contains("latest question"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(anthropic_text_response("Hi!")))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "anthropic",
+        std::collections::HashMap::new(),
+    )
+    .await;
+
+    let request = Request::builder()
+        .uri("/v2/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "claude-small-trim",
+                "messages": [
+                    {"role": "user", "content": "hello ".repeat(300)},
+                    {"role": "assistant", "content": "ok"},
+                    {"role": "user", "content": "latest question"}
+                ]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let trimmed: usize = response
+        .headers()
+        .get("x-context-trimmed-tokens")
+        .expect("trimmed tokens header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(trimmed >= 300);
+}
+
+fn upstream_count_params() -> std::collections::HashMap<String, serde_json::Value> {
+    let mut params = std::collections::HashMap::new();
+    params.insert("upstream_count_tokens".to_string(), json!(true));
+    params.insert("gcp_project".to_string(), json!("proj"));
+    params.insert("gcp_location".to_string(), json!("us-east5"));
+    params.insert("gcp_publisher".to_string(), json!("google"));
+    params
+}
+
+async fn post_json(app: &Router, uri: &str, body: serde_json::Value) -> serde_json::Value {
+    let request = Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_v2_count_tokens_uses_upstream_and_caches() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .and(body_string_contains("\"model\":\"test-claude-3\""))
+        .and(body_string_contains("count me upstream"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"input_tokens": 1234})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(&mock_server, 300, "anthropic", upstream_count_params()).await;
+    let request = json!({
+        "model": "claude-3-opus",
+        "messages": [{"role": "user", "content": "count me upstream"}]
+    });
+
+    for _ in 0..2 {
+        let body = post_json(&app, "/v2/messages/count_tokens", request.clone()).await;
+        assert_eq!(body["input_tokens"], 1234);
+    }
+}
+
+#[tokio::test]
+async fn test_v2_chat_count_tokens_falls_back_to_local_estimate() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages/count_tokens"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(&mock_server, 300, "anthropic", upstream_count_params()).await;
+    let body = post_json(
+        &app,
+        "/v2/chat/completions/count_tokens",
+        json!({
+            "model": "claude-3-opus",
+            "messages": [{"role": "user", "content": "fallback please"}]
+        }),
+    )
+    .await;
+
+    assert_eq!(body["object"], "token_count");
+    let tokens = body["input_tokens"].as_i64().unwrap();
+    assert!(tokens > 0 && tokens < 100);
+}
+
+#[tokio::test]
+async fn test_v2_chat_count_tokens_translates_to_gemini() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1/projects/proj/locations/us-east5/publishers/google/models/test-gpt-4:countTokens",
+        ))
+        .and(body_string_contains("\"contents\""))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"totalTokens": 77})))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(&mock_server, 300, "gemini", upstream_count_params()).await;
+    let body = post_json(
+        &app,
+        "/v2/chat/completions/count_tokens",
+        json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "count me on gemini"}]
+        }),
+    )
+    .await;
+
+    assert_eq!(body["input_tokens"], 77);
+}
+
+#[tokio::test]
+async fn test_v2_n_choices_streaming_interleaved() {
+    let mock_server = MockServer::start().await;
+
+    let sse_body = concat!(
+        "event: message_start\n",
+        "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"type\":\"message\",\"role\":\"assistant\",\"model\":\"test-claude-3\",\"content\":[],\"stop_reason\":null,\"usage\":{\"input_tokens\":10,\"output_tokens\":0}}}\n\n",
+        "event: content_block_start\n",
+        "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n\n",
+        "event: content_block_delta\n",
+        "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n",
+        "event: content_block_stop\n",
+        "data: {\"type\":\"content_block_stop\",\"index\":0}\n\n",
+        "event: message_delta\n",
+        "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"end_turn\"},\"usage\":{\"input_tokens\":10,\"output_tokens\":2}}\n\n",
+        "event: message_stop\n",
+        "data: {\"type\":\"message_stop\"}\n\n",
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse_body),
+        )
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "anthropic",
+        std::collections::HashMap::new(),
+    )
+    .await;
+
+    let request = Request::builder()
+        .uri("/v2/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "claude-3-opus",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "n": 2,
+                "stream": true
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    let events: Vec<serde_json::Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+
+    let mut text = [String::new(), String::new()];
+    let mut finished = [false, false];
+    for event in &events {
+        for choice in event["choices"].as_array().unwrap() {
+            let index = choice["index"].as_u64().unwrap() as usize;
+            if let Some(content) = choice["delta"]["content"].as_str() {
+                text[index].push_str(content);
+            }
+            if choice["finish_reason"] == "stop" {
+                finished[index] = true;
+            }
+        }
+    }
+    assert_eq!(text, ["Hi".to_string(), "Hi".to_string()]);
+    assert_eq!(finished, [true, true]);
+
+    let usage = &events.last().unwrap()["usage"];
+    assert_eq!(usage["prompt_tokens"], 20);
+    assert_eq!(usage["completion_tokens"], 4);
+    assert_eq!(body.matches("data: [DONE]").count(), 1);
+}
+
+fn weather_tools() -> serde_json::Value {
+    json!([{
+        "type": "function",
+        "function": {
+            "name": "get_weather",
+            "description": "Get the weather",
+            "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+        }
+    }])
+}
+
+#[tokio::test]
+async fn test_v2_tool_emulation_non_streaming() {
+    let mock_server = MockServer::start().await;
+
+    let mut upstream = openai_response();
+    upstream["choices"][0]["message"]["content"] = json!(
+        "<tool_call>\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Paris\"}}\n</tool_call>"
+    );
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("# Tools"))
+        .and(body_string_contains(
+            "<tool_response name=\\\"get_weather\\\">\\nSunny",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(upstream))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = create_v2_test_app(&mock_server).await;
+    let body = post_json(
+        &app,
+        "/v2/chat/completions",
+        json!({
+            "model": "local-llm",
+            "messages": [
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": null, "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"city\":\"Lyon\"}"}
+                }]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "Sunny"},
+                {"role": "user", "content": "And Paris?"}
+            ],
+            "tools": weather_tools()
+        }),
+    )
+    .await;
+
+    let choice = &body["choices"][0];
+    assert_eq!(choice["finish_reason"], "tool_calls");
+    let call = &choice["message"]["tool_calls"][0];
+    assert_eq!(call["function"]["name"], "get_weather");
+    let arguments: serde_json::Value =
+        serde_json::from_str(call["function"]["arguments"].as_str().unwrap()).unwrap();
+    assert_eq!(arguments, json!({"city": "Paris"}));
+    assert!(choice["message"]["content"].is_null());
+}
+
+#[tokio::test]
+async fn test_v2_tool_emulation_streaming_to_anthropic() {
+    let mock_server = MockServer::start().await;
+
+    let sse_body = concat!(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-local-llm\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Checking.\\n<tool\"},\"finish_reason\":null}]}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-local-llm\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"_call>{\\\"name\\\": \\\"get_weather\\\", \"},\"finish_reason\":null}]}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-local-llm\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"\\\"arguments\\\": {\\\"city\\\": \\\"Paris\\\"}}</tool_call>\"},\"finish_reason\":null}]}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-local-llm\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("# Tools"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse_body),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = create_v2_test_app(&mock_server).await;
+    let request = Request::builder()
+        .uri("/v2/messages")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "local-llm",
+                "max_tokens": 100,
+                "stream": true,
+                "messages": [{"role": "user", "content": "Weather in Paris?"}],
+                "tools": [{
+                    "name": "get_weather",
+                    "description": "Get the weather",
+                    "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    let events: Vec<serde_json::Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+
+    let text: String = events
+        .iter()
+        .filter_map(|e| e["delta"]["text"].as_str())
+        .collect();
+    assert_eq!(text, "Checking.\n");
+
+    let tool_start = events
+        .iter()
+        .find(|e| e["type"] == "content_block_start" && e["content_block"]["type"] == "tool_use")
+        .expect("tool_use block");
+    assert_eq!(tool_start["content_block"]["name"], "get_weather");
+    let arguments: String = events
+        .iter()
+        .filter_map(|e| e["delta"]["partial_json"].as_str())
+        .collect();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&arguments).unwrap(),
+        json!({"city": "Paris"})
+    );
+    let message_delta = events
+        .iter()
+        .find(|e| e["type"] == "message_delta")
+        .unwrap();
+    assert_eq!(message_delta["delta"]["stop_reason"], "tool_use");
+}
+
+#[tokio::test]
+async fn test_v2_tool_names_and_schemas_normalized() {
+    let mock_server = MockServer::start().await;
+
+    let mut upstream = openai_response();
+    upstream["choices"][0]["finish_reason"] = json!("tool_calls");
+    upstream["choices"][0]["message"] = json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{
+            "id": "call_1",
+            "type": "function",
+            "function": {"name": "files_read", "arguments": "{\"path\":\"a.txt\"}"}
+        }]
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(upstream))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let params = std::collections::HashMap::from([(
+        "tool_schema".to_string(),
+        json!({"inline_refs": true}),
+    )]);
+    let app = build_v2_test_app(&mock_server, 300, "openai", params).await;
+    let body = post_json(
+        &app,
+        "/v2/chat/completions",
+        json!({
+            "model": "gpt-4",
+            "messages": [{"role": "user", "content": "Read a.txt"}],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "files.read",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {"path": {"$ref": "#/$defs/Path"}},
+                        "$defs": {"Path": {"type": "string"}}
+                    }
+                }
+            }],
+            "tool_choice": {"type": "function", "function": {"name": "files.read"}}
+        }),
+    )
+    .await;
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let function = &sent["tools"][0]["function"];
+    assert_eq!(function["name"], "files_read");
+    assert_eq!(
+        function["parameters"],
+        json!({"type": "object", "properties": {"path": {"type": "string"}}})
+    );
+    assert_eq!(sent["tool_choice"]["function"]["name"], "files_read");
+
+    let call = &body["choices"][0]["message"]["tool_calls"][0];
+    assert_eq!(call["function"]["name"], "files.read");
+}
+
+#[tokio::test]
+async fn test_v2_truncated_tool_arguments_repaired() {
+    let mock_server = MockServer::start().await;
+
+    let mut upstream = openai_response();
+    upstream["choices"][0]["finish_reason"] = json!("tool_calls");
+    upstream["choices"][0]["message"] = json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{
+            "id": "call_1",
+            "type": "function",
+            "function": {"name": "get_weather", "arguments": "{\"city\": \"Paris\","}
+        }]
+    });
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(upstream))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = create_v2_test_app(&mock_server).await;
+    let body = post_json(
+        &app,
+        "/v2/messages",
+        json!({
+            "model": "gpt-4",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Weather in Paris?"}],
+            "tools": [{
+                "name": "get_weather",
+                "input_schema": {"type": "object", "properties": {"city": {"type": "string"}}}
+            }]
+        }),
+    )
+    .await;
+
+    let tool_use = body["content"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|block| block["type"] == "tool_use")
+        .expect("tool_use block");
+    assert_eq!(tool_use["input"], json!({"city": "Paris"}));
+}
+
+fn upstream_mode_params(mode: &str) -> std::collections::HashMap<String, serde_json::Value> {
+    std::collections::HashMap::from([("upstream_mode".to_string(), json!(mode))])
+}
+
+#[tokio::test]
+async fn test_v2_stream_only_upstream_serves_blocking_client() {
+    let mock_server = MockServer::start().await;
+
+    let sse_body = concat!(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hello\"},\"finish_reason\":null}]}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" world\"},\"finish_reason\":null}]}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-gpt-4\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1,\"model\":\"test-gpt-4\",\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2,\"total_tokens\":11}}\n\n",
+        "data: [DONE]\n\n",
+    );
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("\"include_usage\":true"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(sse_body),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(&mock_server, 300, "openai", upstream_mode_params("stream")).await;
+    let body = post_json(
+        &app,
+        "/v2/messages",
+        json!({
+            "model": "gpt-4",
+            "max_tokens": 100,
+            "messages": [{"role": "user", "content": "Hi"}]
+        }),
+    )
+    .await;
+
+    assert_eq!(body["content"][0]["text"], "Hello world");
+    assert_eq!(body["stop_reason"], "end_turn");
+    assert_eq!(body["usage"]["input_tokens"], 9);
+    assert_eq!(body["usage"]["output_tokens"], 2);
+}
+
+#[tokio::test]
+async fn test_v2_blocking_upstream_serves_streaming_client() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(openai_response()))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "openai",
+        upstream_mode_params("blocking"),
+    )
+    .await;
+    let request = Request::builder()
+        .uri("/v2/messages")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            json!({
+                "model": "gpt-4",
+                "max_tokens": 100,
+                "stream": true,
+                "messages": [{"role": "user", "content": "Hi"}]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    let upstream_request: serde_json::Value =
+        serde_json::from_slice(&mock_server.received_requests().await.unwrap()[0].body).unwrap();
+    assert!(upstream_request.get("stream").is_none());
+
+    let events: Vec<serde_json::Value> = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .filter(|data| *data != "[DONE]")
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+    let text: String = events
+        .iter()
+        .filter_map(|e| e["delta"]["text"].as_str())
+        .collect();
+    assert_eq!(text, "Hello! How can I help you today?");
+
+    let message_delta = events
+        .iter()
+        .find(|e| e["type"] == "message_delta")
+        .expect("message_delta event");
+    assert_eq!(message_delta["delta"]["stop_reason"], "end_turn");
+    assert_eq!(message_delta["usage"]["output_tokens"], 12);
+    assert!(events.iter().any(|e| e["type"] == "message_stop"));
+}
+
+/// 1x1 PNG
+const PNG_BASE64: &str =
+    "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNkYPhfDwAChwGA60e6kgAAAABJRU5ErkJggg==";
+
+fn image_inlining_params(
+    image_inlining: serde_json::Value,
+) -> std::collections::HashMap<String, serde_json::Value> {
+    let mut params = upstream_count_params();
+    params.remove("upstream_count_tokens");
+    params.insert("image_inlining".to_string(), image_inlining);
+    params
+}
+
+fn image_url_request(url: &str) -> serde_json::Value {
+    json!({
+        "model": "gpt-4",
+        "messages": [{
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "What is this?"},
+                {"type": "image_url", "image_url": {"url": url}}
+            ]
+        }]
+    })
+}
+
+#[tokio::test]
+async fn test_v2_image_urls_inlined_for_gemini() {
+    use base64::Engine as _;
+    let mock_server = MockServer::start().await;
+
+    let png = base64::engine::general_purpose::STANDARD
+        .decode(PNG_BASE64)
+        .unwrap();
+    Mock::given(method("GET"))
+        .and(path("/images/cat"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(png))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(
+            "/v1/projects/proj/locations/us-east5/publishers/google/models/test-gpt-4:generateContent",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "A single pixel"}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 260, "candidatesTokenCount": 3}
+        })))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "gemini",
+        image_inlining_params(json!({"allow_private_networks": true})),
+    )
+    .await;
+    let url = format!("{}/images/cat", mock_server.uri());
+    // The second request is served from the image cache
+    for _ in 0..2 {
+        let body = post_json(&app, "/v2/chat/completions", image_url_request(&url)).await;
+        assert_eq!(body["choices"][0]["message"]["content"], "A single pixel");
+    }
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let upstream_request: serde_json::Value = requests
+        .iter()
+        .find(|r| r.method.as_str() == "POST")
+        .map(|r| serde_json::from_slice(&r.body).unwrap())
+        .unwrap();
+    let parts = upstream_request["contents"][0]["parts"].as_array().unwrap();
+    let inline = parts
+        .iter()
+        .find_map(|p| p.get("inlineData"))
+        .expect("inlineData part");
+    assert_eq!(inline["mimeType"], "image/png");
+    assert_eq!(inline["data"], PNG_BASE64);
+    assert!(parts.iter().all(|p| p.get("fileData").is_none()));
+}
+
+#[tokio::test]
+async fn test_v2_image_url_to_private_address_rejected() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let app = build_v2_test_app(
+        &mock_server,
+        300,
+        "gemini",
+        image_inlining_params(json!(true)),
+    )
+    .await;
+    let request = Request::builder()
+        .uri("/v2/chat/completions")
+        .method("POST")
+        .header("content-type", "application/json")
+        .header("authorization", "Bearer test_client_key")
+        .body(Body::from(
+            image_url_request(&format!("{}/images/cat", mock_server.uri())).to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 // ============================================================================
 // Anthropic Format Tests (Cross-Protocol Conversion)
 // ============================================================================
@@ -97,6 +97,12 @@ fn test_app_state_with_rate_limiter() {
         }),
         enabled: true,
         allowed_models: vec![],
+        context_trimming: None,
+        expires_at: None,
+        previous_key: None,
+        allowed_cidrs: vec![],
+        pii_redaction: None,
+        guardrails: vec![],
     }];
 
     let config = AppConfig {
@@ -113,10 +119,13 @@ fn test_app_state_with_rate_limiter() {
         verify_ssl: true,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials,
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     };
 
     let provider_service = ProviderService::new(config.clone());
@@ -171,6 +180,8 @@ fn test_disabled_key_not_rate_limited() {
         verify_ssl: true,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials: vec![CredentialConfig {
             credential_key: "disabled-key".to_string(),
             name: "Disabled Key".to_string(),
@@ -181,10 +192,17 @@ fn test_disabled_key_not_rate_limited() {
             }),
             enabled: false,
             allowed_models: vec![],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         }],
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     };
 
     let rate_limiter = RateLimiter::new();
@@ -220,6 +238,8 @@ fn test_key_without_rate_limit_config() {
         verify_ssl: true,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials: vec![CredentialConfig {
             credential_key: "unlimited-key".to_string(),
             name: "Unlimited Key".to_string(),
@@ -227,10 +247,17 @@ fn test_key_without_rate_limit_config() {
             rate_limit: None,
             enabled: true,
             allowed_models: vec![],
+            context_trimming: None,
+            expires_at: None,
+            previous_key: None,
+            allowed_cidrs: vec![],
+            pii_redaction: None,
+            guardrails: vec![],
         }],
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     };
 
     let rate_limiter = RateLimiter::new();
@@ -267,6 +294,8 @@ fn test_mixed_rate_limits() {
         verify_ssl: true,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         provider_suffix: None,
         credentials: vec![
             CredentialConfig {
@@ -279,6 +308,12 @@ fn test_mixed_rate_limits() {
                 }),
                 enabled: true,
                 allowed_models: vec![],
+                context_trimming: None,
+                expires_at: None,
+                previous_key: None,
+                allowed_cidrs: vec![],
+                pii_redaction: None,
+                guardrails: vec![],
             },
             CredentialConfig {
                 credential_key: "unlimited-key-1".to_string(),
@@ -287,6 +322,12 @@ fn test_mixed_rate_limits() {
                 rate_limit: None,
                 enabled: true,
                 allowed_models: vec![],
+                context_trimming: None,
+                expires_at: None,
+                previous_key: None,
+                allowed_cidrs: vec![],
+                pii_redaction: None,
+                guardrails: vec![],
             },
             CredentialConfig {
                 credential_key: "limited-key-2".to_string(),
@@ -298,6 +339,12 @@ fn test_mixed_rate_limits() {
                 }),
                 enabled: true,
                 allowed_models: vec![],
+                context_trimming: None,
+                expires_at: None,
+                previous_key: None,
+                allowed_cidrs: vec![],
+                pii_redaction: None,
+                guardrails: vec![],
             },
             CredentialConfig {
                 credential_key: "unlimited-key-2".to_string(),
@@ -306,10 +353,17 @@ fn test_mixed_rate_limits() {
                 rate_limit: None,
                 enabled: true,
                 allowed_models: vec![],
+                context_trimming: None,
+                expires_at: None,
+                previous_key: None,
+                allowed_cidrs: vec![],
+                pii_redaction: None,
+                guardrails: vec![],
             },
         ],
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     };
 
     let rate_limiter = RateLimiter::new();
@@ -362,6 +416,12 @@ fn make_credential(key: &str, rps: Option<u32>, enabled: bool) -> CredentialConf
         }),
         enabled,
         allowed_models: vec![],
+        context_trimming: None,
+        expires_at: None,
+        previous_key: None,
+        allowed_cidrs: vec![],
+        pii_redaction: None,
+        guardrails: vec![],
     }
 }
 
@@ -557,5 +617,7 @@ fn test_rate_limit_exempt_paths_constant() {
 
     assert!(RATE_LIMIT_EXEMPT_PATHS.contains(&"/v1/messages/count_tokens"));
     assert!(RATE_LIMIT_EXEMPT_PATHS.contains(&"/v2/messages/count_tokens"));
-    assert_eq!(RATE_LIMIT_EXEMPT_PATHS.len(), 2);
+    assert!(RATE_LIMIT_EXEMPT_PATHS.contains(&"/v1/chat/completions/count_tokens"));
+    assert!(RATE_LIMIT_EXEMPT_PATHS.contains(&"/v2/chat/completions/count_tokens"));
+    assert_eq!(RATE_LIMIT_EXEMPT_PATHS.len(), 4);
 }
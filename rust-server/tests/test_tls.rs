@@ -0,0 +1,177 @@
+//! End-to-end tests of native TLS termination and client certificates.
+
+use axum::extract::ConnectInfo;
+use axum::routing::get;
+use axum::{Extension, Router};
+use llm_proxy_rust::core::tls::{serve_tls, ClientAuth, ClientCertificate, TlsConfig, TlsServer};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls");
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(FIXTURES).join(name)
+}
+
+fn tls_config(client_auth: Option<ClientAuth>) -> TlsConfig {
+    TlsConfig {
+        cert_file: fixture("server.pem"),
+        key_file: fixture("server.key"),
+        client_ca_file: client_auth.map(|_| fixture("ca.pem")),
+        client_auth: client_auth.unwrap_or(ClientAuth::Required),
+        reload_interval: Duration::from_secs(30),
+        profile_rules: Vec::new(),
+    }
+}
+
+async fn whoami(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    certificate: Option<Extension<ClientCertificate>>,
+) -> String {
+    let subject = certificate
+        .map(|Extension(c)| c.subject)
+        .unwrap_or_else(|| "none".to_string());
+    format!("{} {}", peer.ip(), subject)
+}
+
+/// Serve a test router over TLS; dropping the sender shuts it down.
+async fn start(tls: Arc<TlsServer>) -> (SocketAddr, oneshot::Sender<()>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = oneshot::channel::<()>();
+    let app = Router::new().route("/whoami", get(whoami));
+    tokio::spawn(serve_tls(listener, app, tls, async {
+        let _ = rx.await;
+    }));
+    (addr, tx)
+}
+
+fn client(addr: SocketAddr, ca: &str, identity: bool) -> reqwest::Client {
+    let ca = std::fs::read(fixture(ca)).unwrap();
+    let mut builder = reqwest::Client::builder()
+        .use_native_tls()
+        .resolve("localhost", addr)
+        .add_root_certificate(reqwest::Certificate::from_pem(&ca).unwrap());
+    if identity {
+        let cert = std::fs::read(fixture("client.pem")).unwrap();
+        let key = std::fs::read(fixture("client.key")).unwrap();
+        builder = builder.identity(reqwest::Identity::from_pkcs8_pem(&cert, &key).unwrap());
+    }
+    builder.build().unwrap()
+}
+
+async fn get_whoami(client: &reqwest::Client, addr: SocketAddr) -> reqwest::Result<String> {
+    client
+        .get(format!("https://localhost:{}/whoami", addr.port()))
+        .send()
+        .await?
+        .text()
+        .await
+}
+
+#[tokio::test]
+async fn test_serves_https_with_peer_address() {
+    let tls = Arc::new(TlsServer::new(tls_config(None)).unwrap());
+    let (addr, _shutdown) = start(tls).await;
+
+    let body = get_whoami(&client(addr, "ca.pem", false), addr)
+        .await
+        .unwrap();
+    assert_eq!(body, "127.0.0.1 none");
+
+    // Certificates from another CA are not trusted
+    assert!(get_whoami(&client(addr, "ca2.pem", false), addr)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_required_client_certificate() {
+    let tls = Arc::new(TlsServer::new(tls_config(Some(ClientAuth::Required))).unwrap());
+    let (addr, _shutdown) = start(tls).await;
+
+    let body = get_whoami(&client(addr, "ca.pem", true), addr)
+        .await
+        .unwrap();
+    assert_eq!(body, "127.0.0.1 O=Acme,OU=Platform,CN=billing-svc");
+
+    assert!(get_whoami(&client(addr, "ca.pem", false), addr)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_optional_client_certificate() {
+    let tls = Arc::new(TlsServer::new(tls_config(Some(ClientAuth::Optional))).unwrap());
+    let (addr, _shutdown) = start(tls).await;
+
+    let body = get_whoami(&client(addr, "ca.pem", false), addr)
+        .await
+        .unwrap();
+    assert_eq!(body, "127.0.0.1 none");
+    let body = get_whoami(&client(addr, "ca.pem", true), addr)
+        .await
+        .unwrap();
+    assert!(body.ends_with("CN=billing-svc"));
+}
+
+#[tokio::test]
+async fn test_reloaded_certificate_serves_new_connections() {
+    let dir = tempfile::tempdir().unwrap();
+    let cert_file = dir.path().join("tls.pem");
+    let key_file = dir.path().join("tls.key");
+    std::fs::copy(fixture("server.pem"), &cert_file).unwrap();
+    std::fs::copy(fixture("server.key"), &key_file).unwrap();
+    let tls = Arc::new(
+        TlsServer::new(TlsConfig {
+            cert_file: cert_file.clone(),
+            key_file: key_file.clone(),
+            ..tls_config(None)
+        })
+        .unwrap(),
+    );
+    let (addr, _shutdown) = start(tls.clone()).await;
+
+    assert!(get_whoami(&client(addr, "ca2.pem", false), addr)
+        .await
+        .is_err());
+
+    std::fs::copy(fixture("server2.pem"), &cert_file).unwrap();
+    std::fs::copy(fixture("server2.key"), &key_file).unwrap();
+    assert_eq!(tls.reload_if_changed(), Ok(true));
+
+    let body = get_whoami(&client(addr, "ca2.pem", false), addr)
+        .await
+        .unwrap();
+    assert_eq!(body, "127.0.0.1 none");
+}
+
+#[tokio::test]
+async fn test_provider_client_with_private_ca_and_client_certificate() {
+    use llm_proxy_rust::core::provider_network::{NetworkDefaults, NetworkSettings};
+
+    let tls = Arc::new(TlsServer::new(tls_config(Some(ClientAuth::Required))).unwrap());
+    let (addr, _shutdown) = start(tls).await;
+    let url = format!("https://127.0.0.1:{}/whoami", addr.port());
+    let defaults = NetworkDefaults {
+        verify_ssl: true,
+        request_timeout_secs: 30,
+    };
+
+    // The default client neither trusts the private CA nor has a certificate
+    let default = defaults.client_builder().build().unwrap();
+    assert!(default.get(&url).send().await.is_err());
+
+    let settings = NetworkSettings {
+        ca_bundle: Some(fixture("ca.pem").display().to_string()),
+        client_cert: Some(fixture("client.pem").display().to_string()),
+        client_key: Some(fixture("client.key").display().to_string()),
+        ..Default::default()
+    };
+    let client = settings.build_client(&defaults).unwrap();
+    let body = client.get(&url).send().await.unwrap().text().await.unwrap();
+    assert!(body.ends_with("CN=billing-svc"));
+}
@@ -62,10 +62,13 @@ fn create_test_config_no_auth(mock_server_uri: &str) -> llm_proxy_rust::core::Ap
         verify_ssl: false,
         request_timeout_secs: 300,
         ttft_timeout_secs: None,
+        stream_idle_timeout_secs: None,
+        sse_keepalive_secs: None,
         credentials: vec![],
         provider_suffix: None,
         min_tokens_limit: 100,
         max_tokens_limit: 4096,
+        guardrail_rules: vec![],
     }
 }
 
@@ -80,6 +83,12 @@ fn create_test_config_with_auth(mock_server_uri: &str) -> llm_proxy_rust::core::
         rate_limit: None,
         enabled: true,
         allowed_models: vec![],
+        context_trimming: None,
+        expires_at: None,
+        previous_key: None,
+        allowed_cidrs: vec![],
+        pii_redaction: None,
+        guardrails: vec![],
     }];
     config
 }
@@ -301,6 +310,36 @@ async fn test_v2_count_tokens_requires_auth() {
     );
 }
 
+/// Test that /v2/messages/count_tokens enforces the credential's allowed models
+#[tokio::test]
+async fn test_v2_count_tokens_checks_allowed_models() {
+    let mock_server = MockServer::start().await;
+    let mut config = create_test_config_with_auth(&mock_server.uri());
+    config.credentials[0].allowed_models = vec!["claude-3-opus".to_string()];
+    let app = create_v2_test_app_with_config(config);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v2/messages/count_tokens")
+        .header("Content-Type", "application/json")
+        .header("x-api-key", "test_master_key")
+        .body(Body::from(
+            json!({
+                "model": "gpt-4",
+                "messages": [{"role": "user", "content": "hi"}]
+            })
+            .to_string(),
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::FORBIDDEN,
+        "Expected 403 Forbidden for a model the credential may not use"
+    );
+}
+
 // ============================================================================
 // Invalid Authentication Tests
 // ============================================================================
@@ -63,6 +63,11 @@ export interface ProviderCreateResponse {
 }
 
 // Credential Types
+export type ContextTrimming =
+  | { strategy: 'drop_oldest' }
+  | { strategy: 'keep_last'; messages: number }
+  | { strategy: 'middle_out' };
+
 export interface Credential {
   id: number;
   name: string;
@@ -70,6 +75,7 @@ export interface Credential {
   allowed_models: string[];
   rate_limit: number | null;
   is_enabled: boolean;
+  context_trimming?: ContextTrimming | null;
 }
 
 export interface CredentialCreate {
@@ -77,6 +83,7 @@ export interface CredentialCreate {
   name: string;
   allowed_models?: string[];
   rate_limit?: number | null;
+  context_trimming?: ContextTrimming | null;
 }
 
 export interface CredentialUpdate {
@@ -84,6 +91,7 @@ export interface CredentialUpdate {
   name?: string;
   allowed_models?: string[];
   rate_limit?: number | null;
+  context_trimming?: ContextTrimming | null;
   is_enabled?: boolean;
 }
 
@@ -346,6 +354,8 @@ export interface RequestLog {
   total_tokens: number;
   total_duration_ms: number | null;
   ttft_ms: number | null;
+  audio_duration_ms?: number | null;
+  pii_redactions?: Record<string, number> | null;
   error_category: string | null;
   error_message: string | null;
   client: string | null;